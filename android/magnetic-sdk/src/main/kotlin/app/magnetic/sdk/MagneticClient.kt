@@ -4,6 +4,7 @@ import kotlinx.coroutines.*
 import kotlinx.coroutines.flow.*
 import kotlinx.serialization.json.jsonObject
 import okhttp3.*
+import okhttp3.HttpUrl.Companion.toHttpUrlOrNull
 import okhttp3.MediaType.Companion.toMediaType
 import okhttp3.RequestBody.Companion.toRequestBody
 import okhttp3.sse.EventSource
@@ -191,8 +192,10 @@ class MagneticClient(
 
                 val encoded = action.replace(" ", "%20")
                 val body = jsonBody.toRequestBody("application/json".toMediaType())
+                val url = "$baseUrl/actions/$encoded"
                 val request = Request.Builder()
-                    .url("$baseUrl/actions/$encoded")
+                    .url(url)
+                    .header("X-Magnetic-CSRF", csrfToken(url))
                     .post(body)
                     .build()
 
@@ -220,8 +223,10 @@ class MagneticClient(
             try {
                 val body = "{\"path\":\"${path.replace("\"", "\\\"")}\"}"}
                     .toRequestBody("application/json".toMediaType())
+                val url = "$baseUrl/actions/navigate"
                 val request = Request.Builder()
-                    .url("$baseUrl/actions/navigate")
+                    .url(url)
+                    .header("X-Magnetic-CSRF", csrfToken(url))
                     .post(body)
                     .build()
 
@@ -249,6 +254,12 @@ class MagneticClient(
         } catch (_: Exception) { /* SSE will deliver the next snapshot */ }
     }
 
+    /** The magnetic_csrf cookie set with the session; action POSTs echo it as X-Magnetic-CSRF. */
+    private fun csrfToken(url: String): String {
+        val httpUrl = url.toHttpUrlOrNull() ?: return ""
+        return cookieJar.loadForRequest(httpUrl).firstOrNull { it.name == "magnetic_csrf" }?.value ?: ""
+    }
+
     /** Clean up resources. Call when the hosting Activity/Fragment is destroyed. */
     fun destroy() {
         disconnect()
@@ -2,6 +2,7 @@
 
 use core::cell::UnsafeCell;
 use core::fmt::{self, Write};
+use magnetic_wasm_abi::Framed;
 
 #[panic_handler]
 fn panic(_: &core::panic::PanicInfo) -> ! {
@@ -17,19 +18,20 @@ const INPUT_CAP: usize = 1024;
 const MAX_ITEMS: usize = 2000;
 const OVERSCAN: usize = 5;
 const DEFAULT_COUNT: usize = 1500;
+const STATE_LEN: usize = 24; // state_export() blob: "ISS1" u32 count, f64 ×2
 
 // ═══════════════════════════════════════════════════════════════════
 // Output buffer (64 KB — fits ~15 visible cards at ~1 KB each)
 // ═══════════════════════════════════════════════════════════════════
 
 struct Buf {
-    data: [u8; BUF_CAP],
+    out: Framed<BUF_CAP>,
     len: usize,
 }
 
 impl Buf {
     const fn new() -> Self {
-        Self { data: [0; BUF_CAP], len: 0 }
+        Self { out: Framed::new(), len: 0 }
     }
     fn clear(&mut self) {
         self.len = 0;
@@ -38,16 +40,21 @@ impl Buf {
         let b = s.as_bytes();
         let avail = BUF_CAP - self.len;
         let n = if b.len() < avail { b.len() } else { avail };
-        let dst = &mut self.data[self.len..self.len + n];
+        let dst = &mut self.out.data_mut()[self.len..self.len + n];
         dst.copy_from_slice(&b[..n]);
         self.len += n;
     }
     fn push_byte(&mut self, b: u8) {
         if self.len < BUF_CAP {
-            self.data[self.len] = b;
+            self.out.data_mut()[self.len] = b;
             self.len += 1;
         }
     }
+    /// Pointer to the output as a frame: [len u32][snapshot]
+    fn framed(&mut self) -> *const u8 {
+        self.out.set_len(self.len);
+        self.out.as_ptr()
+    }
 }
 
 impl Write for Buf {
@@ -173,6 +180,34 @@ impl State {
 
         (start, end)
     }
+
+    /// Saved state: item data is regenerated from the count, so only the
+    /// count and scroll position are written (little-endian).
+    fn export(&self, out: &mut [u8; STATE_LEN]) {
+        out[..4].copy_from_slice(b"ISS1");
+        out[4..8].copy_from_slice(&(self.count as u32).to_le_bytes());
+        out[8..16].copy_from_slice(&self.scroll_top.to_le_bytes());
+        out[16..24].copy_from_slice(&self.viewport_height.to_le_bytes());
+    }
+
+    /// Restore an export(). False (state untouched) if the bytes are invalid.
+    fn import(&mut self, b: &[u8]) -> bool {
+        if b.len() != STATE_LEN || &b[..4] != b"ISS1" {
+            return false;
+        }
+        let count = u32::from_le_bytes([b[4], b[5], b[6], b[7]]) as usize;
+        let f64_at = |i: usize| f64::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3], b[i + 4], b[i + 5], b[i + 6], b[i + 7]]);
+        let (scroll_top, viewport_height) = (f64_at(8), f64_at(16));
+        if count > MAX_ITEMS || !scroll_top.is_finite() || !viewport_height.is_finite() {
+            return false;
+        }
+        if count != self.count {
+            self.generate(count);
+        }
+        self.scroll_top = scroll_top;
+        self.viewport_height = viewport_height;
+        true
+    }
 }
 
 fn min_u32(a: u32, b: u32) -> u32 { if a < b { a } else { b } }
@@ -411,6 +446,7 @@ struct Globals {
     state: UnsafeCell<State>,
     buf: UnsafeCell<Buf>,
     input: UnsafeCell<[u8; INPUT_CAP]>,
+    saved: UnsafeCell<Framed<STATE_LEN>>,
 }
 unsafe impl Sync for Globals {}
 
@@ -418,6 +454,7 @@ static G: Globals = Globals {
     state: UnsafeCell::new(State::new()),
     buf: UnsafeCell::new(Buf::new()),
     input: UnsafeCell::new([0u8; INPUT_CAP]),
+    saved: UnsafeCell::new(Framed::new()),
 };
 
 #[no_mangle]
@@ -432,7 +469,7 @@ pub extern "C" fn init() -> *const u8 {
         let buf = &mut *G.buf.get();
         state.generate(DEFAULT_COUNT);
         render(state, buf);
-        buf.data.as_ptr()
+        buf.out.data_ptr()
     }
 }
 
@@ -443,7 +480,7 @@ pub extern "C" fn reduce(len: u32) -> *const u8 {
         let state = &mut *G.state.get();
         let buf = &mut *G.buf.get();
         process(state, input, buf);
-        buf.data.as_ptr()
+        buf.out.data_ptr()
     }
 }
 
@@ -451,3 +488,56 @@ pub extern "C" fn reduce(len: u32) -> *const u8 {
 pub extern "C" fn snapshot_len() -> u32 {
     unsafe { (*G.buf.get()).len as u32 }
 }
+
+// Framed variants (magnetic-wasm-abi): one pointer to [len u32][data],
+// no snapshot_len() / state_len() call needed.
+
+#[no_mangle]
+pub extern "C" fn init_framed() -> *const u8 {
+    init();
+    unsafe { (*G.buf.get()).framed() }
+}
+
+#[no_mangle]
+pub extern "C" fn reduce_framed(len: u32) -> *const u8 {
+    reduce(len);
+    unsafe { (*G.buf.get()).framed() }
+}
+
+#[no_mangle]
+pub extern "C" fn state_export_framed() -> *const u8 {
+    state_export();
+    unsafe { (*G.saved.get()).as_ptr() }
+}
+
+// State save/load: hosts persist state_export() bytes (state_len() long)
+// and hand them back via the input buffer to state_import(len), which
+// re-renders and returns 1 (0 = invalid, state unchanged).
+
+#[no_mangle]
+pub extern "C" fn state_export() -> *const u8 {
+    unsafe {
+        let saved = &mut *G.saved.get();
+        (*G.state.get()).export(saved.data_mut());
+        saved.set_len(STATE_LEN);
+        saved.data_ptr()
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn state_len() -> u32 {
+    STATE_LEN as u32
+}
+
+#[no_mangle]
+pub extern "C" fn state_import(len: u32) -> u32 {
+    unsafe {
+        let input = &(&*G.input.get())[..(len as usize).min(INPUT_CAP)];
+        let state = &mut *G.state.get();
+        if !state.import(input) {
+            return 0;
+        }
+        render(state, &mut *G.buf.get());
+        1
+    }
+}
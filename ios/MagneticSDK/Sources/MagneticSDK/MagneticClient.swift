@@ -167,6 +167,7 @@ public final class MagneticClient: ObservableObject {
         var request = URLRequest(url: url)
         request.httpMethod = "POST"
         request.setValue("application/json", forHTTPHeaderField: "Content-Type")
+        request.setValue(csrfToken(for: url), forHTTPHeaderField: "X-Magnetic-CSRF")
         request.httpBody = try? JSONEncoder().encode(payload)
 
         Task {
@@ -199,6 +200,7 @@ public final class MagneticClient: ObservableObject {
         var request = URLRequest(url: url)
         request.httpMethod = "POST"
         request.setValue("application/json", forHTTPHeaderField: "Content-Type")
+        request.setValue(csrfToken(for: url), forHTTPHeaderField: "X-Magnetic-CSRF")
         request.httpBody = try? JSONEncoder().encode(["path": path])
 
         Task {
@@ -219,6 +221,11 @@ public final class MagneticClient: ObservableObject {
         }
     }
 
+    /// The magnetic_csrf cookie set with the session; action POSTs echo it as X-Magnetic-CSRF.
+    private func csrfToken(for url: URL) -> String {
+        cookieStorage.cookies(for: url)?.first { $0.name == "magnetic_csrf" }?.value ?: ""
+    }
+
     /// Parse action/navigate response body and apply as DOM snapshot for instant feedback.
     private func applyResponseSnapshot(_ data: Data) {
         Task { @MainActor in
@@ -91,6 +91,7 @@ function usage() {
     --server <url>            Platform server URL for push
     --name <name>             App name for push (default: from magnetic.json)
     --key <api_key>           API key for push (or set MAGNETIC_API_KEY)
+    --token <token>           Deploy token for a direct push (or set MAGNETIC_DEPLOY_TOKEN)
     --minify                  Minify the output bundle
 
   Developer workflow:
@@ -449,6 +450,22 @@ async function main() {
         const bundleContent = readFileSync(deploy.bundlePath, 'utf-8');
         deployPayload = { name: appName, bundle: bundleContent, assets: deploy.assets, config: serverConfig } as any;
 
+        // Files read by "type": "file" data sources, from the app's data/ dir
+        const fileSources = appConfig.data.filter((d) => d.type === 'file');
+        if (fileSources.length > 0) {
+          const files: Record<string, string> = {};
+          for (const src of fileSources) {
+            const filePath = join(appDir, 'data', src.url);
+            if (!existsSync(filePath)) {
+              log('error', `Data source '${src.key}': ${filePath} not found`);
+              process.exit(1);
+            }
+            files[src.url] = readFileSync(filePath, 'utf-8');
+          }
+          deployPayload.files = files;
+          log('info', `Data files: ${Object.keys(files).length}`);
+        }
+
         // Hybrid pre-render: if magnetic.json has prerender routes, pre-render them
         const prerenderPatterns = appConfig.prerender;
         if (prerenderPatterns && prerenderPatterns.length > 0) {
@@ -526,12 +543,14 @@ async function main() {
         const directBody = Buffer.from(JSON.stringify(
           isStaticPush
             ? { static: true, assets: deployPayload.assets }
-            : { bundle: deployPayload.bundle, assets: deployPayload.assets, config: deployPayload.config, prerendered: deployPayload.prerendered }
+            : { bundle: deployPayload.bundle, assets: deployPayload.assets, config: deployPayload.config, prerendered: deployPayload.prerendered, files: deployPayload.files }
         ));
+        const deployToken = getArg('--token') || process.env.MAGNETIC_DEPLOY_TOKEN;
         const resp = await fetch(`${serverUrl}/api/apps/${appName}/deploy`, {
           method: 'POST',
           headers: {
             'Content-Type': 'application/json',
+            ...(deployToken ? { 'Authorization': `Bearer ${deployToken}` } : {}),
           },
           body: directBody,
         });
@@ -312,11 +312,12 @@ export function generateBridge(scan: AppScan, config?: MagneticAppConfig, design
   lines.push('  ]};');
   lines.push('}');
   lines.push('');
-  lines.push('export function render(path, sid) {');
+  lines.push('// locale: the session\'s negotiated locale (--locales), or null');
+  lines.push('export function render(path, sid, locale) {');
   lines.push('  try {');
   lines.push('    var st = __getState(sid);');
   lines.push('    const merged = Object.assign({}, __magneticData, st);');
-  lines.push('    const vm = toViewModel(merged, path);')
+  lines.push('    const vm = toViewModel(merged, path, locale);')
   lines.push('    const result = router.resolve(path, vm);');
   if (catchAllPage) {
     lines.push(`    if (!result) return ${catchAllPage.importName}({ params: {} });`);
@@ -339,7 +340,7 @@ export function generateBridge(scan: AppScan, config?: MagneticAppConfig, design
   lines.push('}');
   lines.push('');
   lines.push('export function reduce(ap) {');
-  lines.push('  const { action, payload = {}, path = \'/\', session } = ap;');
+  lines.push('  const { action, payload = {}, path = \'/\', session, locale } = ap;');
   lines.push('  var sid = session || "__default";');
   lines.push('  try {');
   lines.push('    var st = __getState(sid);');
@@ -347,7 +348,7 @@ export function generateBridge(scan: AppScan, config?: MagneticAppConfig, design
   lines.push('  } catch(e) {');
   lines.push('    return __errorBoundary("reduce(" + action + "): " + (e && e.message || e), path);');
   lines.push('  }');
-  lines.push('  return render(path, sid);');
+  lines.push('  return render(path, sid, locale);');
   lines.push('}');
   lines.push('');
   lines.push('export function cleanupSessions(maxAgeMs) {');
@@ -363,6 +364,10 @@ export function generateBridge(scan: AppScan, config?: MagneticAppConfig, design
   lines.push('export function dropSession(sid) {');
   lines.push('  __sessions.delete(sid); __sessionTS.delete(sid);');
   lines.push('}');
+  lines.push('');
+  // Page paths for the server's sitemap.xml (patterns are skipped there)
+  const pagePaths = scan.pages.filter(p => !p.isCatchAll).map(p => p.routePath);
+  lines.push(`export function routes() { return ${JSON.stringify(pagePaths)}; }`);
 
   // CSS framework: renderWithCSS() — new export for SSR paths only
   // render() and reduce() are untouched — SSE/action flows are unaffected
@@ -378,8 +383,8 @@ export function generateBridge(scan: AppScan, config?: MagneticAppConfig, design
       // Mode "all": generate every utility class once at init
       lines.push('var __cssCache = generateAllCSS(__designConfig);');
       lines.push('');
-      lines.push('export function renderWithCSS(path, sid) {');
-      lines.push('  var dom = render(path, sid);');
+      lines.push('export function renderWithCSS(path, sid, locale) {');
+      lines.push('  var dom = render(path, sid, locale);');
       lines.push('  return { root: dom, css: __cssCache };');
       lines.push('}');
 
@@ -410,8 +415,8 @@ export function generateBridge(scan: AppScan, config?: MagneticAppConfig, design
       lines.push('  __designConfig');
       lines.push(');');
       lines.push('');
-      lines.push('export function renderWithCSS(path, sid) {');
-      lines.push('  var dom = render(path, sid);');
+      lines.push('export function renderWithCSS(path, sid, locale) {');
+      lines.push('  var dom = render(path, sid, locale);');
       lines.push('  return { root: dom, css: __cssCache };');
       lines.push('}');
 
@@ -419,8 +424,8 @@ export function generateBridge(scan: AppScan, config?: MagneticAppConfig, design
       // Mode "used": per-request extraction — smallest output, but SSE may miss classes
       lines.push('var __cssExtractor = createExtractor(__designConfig);');
       lines.push('');
-      lines.push('export function renderWithCSS(path, sid) {');
-      lines.push('  var dom = render(path, sid);');
+      lines.push('export function renderWithCSS(path, sid, locale) {');
+      lines.push('  var dom = render(path, sid, locale);');
       lines.push('  return { root: dom, css: __cssExtractor(dom) };');
       lines.push('}');
     }
@@ -4,46 +4,172 @@
   var M = self.Magnetic = {};
   var root = null;      // mount element
   var es = null;        // EventSource
+  var ws = null;        // WebSocket (when connected to a /ws url)
   var wasm = null;      // WASM exports (null = not loaded, 0 = loading)
+  var wcap = 16384;     // largest snapshot the WASM input buffer takes
   var status = "disconnected";
   var queue = [];        // queued actions while offline
   var keys = {};         // keyed element cache
   var deb = {};          // debounce timers
   var lastHash = "";     // hash of last applied snapshot (dedup fallback)
+  var lastRaw = null;    // last full snapshot received, base for server deltas
+  var resync = null;     // reopen the EventSource for a fresh snapshot
   var enc = new TextEncoder();
+  var dec = new TextDecoder();
   M.status = function() { return status; };
 
-  // --- Connect to SSE + mount ---
-  M.connect = function(url, mount) {
+  // WASM transport counters (debug overlay / cache tuning); null without WASM
+  var STATS = ["cacheHits", "cacheMisses", "predictionsConfirmed", "predictionsWrong",
+    "dedupSkips", "stores", "bytesStored", "cacheBytes", "cacheRejects", "diffOverflows", "actionsRejected",
+    "predictionsExpired"];
+  M.stats = function() {
+    if (!wasm || !wasm.stats_ptr) return null;
+    var v = new Uint32Array(wasm.memory.buffer, wasm.stats_ptr(), wasm.stats_len());
+    var out = {};
+    for (var i = 0; i < STATS.length && i < v.length; i++) out[STATS[i]] = v[i];
+    return out;
+  };
+
+  // --- Connect to SSE (or WebSocket, for a url ending in /ws) + mount ---
+  // opts.coalesce: apply only the latest SSE snapshot per animation frame, so
+  // rapid feeds don't store/patch every intermediate state
+  M.connect = function(url, mount, opts) {
     root = typeof mount == "string" ? d.querySelector(mount) : mount;
-    es = new EventSource(url);
-    es.onmessage = function(ev) {
-      try {
-        var raw = ev.data;
-        // WASM dedup: skip re-render if snapshot is identical
-        if (wasm && wasm.store) {
-          var bytes = enc.encode(raw);
-          if (bytes.length <= 16384) {
-            new Uint8Array(wasm.memory.buffer).set(bytes, wasm.input_ptr());
-            if (wasm.store(bytes.length) === 0) return;
-          }
-        } else {
-          var h = fnv(raw);
-          if (h === lastHash) return;
-          lastHash = h;
-        }
-        apply(JSON.parse(raw));
-      } catch(e) { console.error("[magnetic] SSE error:", e); }
-    };
-    es.onerror = function() {
-      if (wasm) status = "offline";
-    };
+    var coalesce = opts && opts.coalesce, latest = null;
+    function onData(raw) {
+      if (!coalesce) return receive(raw);
+      if (latest === null) requestAnimationFrame(function() {
+        var raw = latest;
+        latest = null;
+        if (raw !== null) receive(raw);
+      });
+      latest = raw;
+    }
     status = "connected";
     bind();
+    if (/\/ws$/.test(url.split("?")[0])) return openWs(wsUrl(url), onData);
+    function open() {
+      es = new EventSource(url);
+      es.onmessage = function(ev) { onData(ev.data); };
+      // Server deltas apply in order, after any snapshot still waiting
+      // for its frame
+      es.addEventListener("delta", function(ev) {
+        if (latest !== null) { receive(latest); latest = null; }
+        receiveDelta(ev.data);
+      });
+      // Server shutting down: reconnect (to its replacement) after a random
+      // delay, so its clients don't all come back at once
+      es.addEventListener("reconnect", function() {
+        var old = es;
+        old.close();
+        if (wasm) status = "offline";
+        setTimeout(function() { if (es === old) open(); }, 250 + Math.random() * 1000);
+      });
+      // Dev server (--watch) picked up a new bundle or static file
+      es.addEventListener("reload", function() { location.reload(); });
+      es.onerror = function() {
+        if (wasm) status = "offline";
+      };
+      es.onopen = function() {
+        var was = status;
+        status = "connected";
+        if (was == "offline") replay();
+      };
+    }
+    resync = function() { if (es) { es.close(); open(); } };
+    open();
   };
 
+  // WebSocket transport: the same snapshots as SSE, wrapped as
+  // {"type":"snapshot","data":<snapshot>}; actions go over the socket too.
+  // Reconnects a second after the socket drops.
+  var WS_SNAP = '{"type":"snapshot","data":';
+  var WS_RELOAD = '{"type":"reload","data":null}';
+  function openWs(url, onData) {
+    var sock = ws = new WebSocket(url);
+    sock.onmessage = function(ev) {
+      var raw = ev.data;
+      if (typeof raw != "string") return;
+      if (raw.indexOf(WS_SNAP) === 0) onData(raw.slice(WS_SNAP.length, -1));
+      else if (raw == WS_RELOAD) location.reload();
+    };
+    sock.onopen = function() {
+      var was = status;
+      status = "connected";
+      if (was == "offline") replay();
+    };
+    sock.onclose = function() {
+      if (ws !== sock) return; // disconnect() or already replaced
+      if (wasm) status = "offline";
+      setTimeout(function() { if (ws === sock) openWs(url, onData); }, 1000);
+    };
+  }
+
+  // ws:/wss: url for a relative or http(s) one
+  function wsUrl(url) {
+    var u = new URL(url, location.href);
+    if (u.protocol == "http:") u.protocol = "ws:";
+    else if (u.protocol == "https:") u.protocol = "wss:";
+    return u.href;
+  }
+
+  // Handle one SSE snapshot
+  function receive(raw) {
+    lastRaw = raw;
+    try {
+      // WASM dedup: skip re-render if snapshot is identical
+      if (wasm && wasm.store) {
+        var bytes = enc.encode(raw);
+        if (put(bytes)) {
+          var patches = diff(bytes.length);
+          if (wasm.store(bytes.length) === 0) return;
+          if (patches && applyPatches(patches)) return;
+        }
+      } else {
+        var h = fnv(raw);
+        if (h === lastHash) return;
+        lastHash = h;
+      }
+      apply(JSON.parse(raw));
+    } catch(e) { console.error("[magnetic] SSE error:", e); }
+  }
+
+  // Handle one server delta (--sse-delta): keyed patches against the last
+  // full snapshot. A key that isn't mounted means the view is out of step,
+  // so reconnect for a fresh snapshot.
+  function receiveDelta(raw) {
+    try {
+      var list = JSON.parse(raw), snap = lastRaw && JSON.parse(lastRaw);
+      for (var i = 0; snap && i < list.length; i++) if (!replaceKeyed(snap, list[i])) snap = null;
+      if (!snap || !applyPatches(list)) { if (resync) resync(); return; }
+      // Keep WASM's current snapshot (or the dedup hash) in step with the DOM
+      lastRaw = JSON.stringify(snap);
+      if (wasm && wasm.store) {
+        var bytes = enc.encode(lastRaw);
+        if (put(bytes)) wasm.store(bytes.length);
+      } else {
+        lastHash = fnv(lastRaw);
+      }
+    } catch(e) { console.error("[magnetic] SSE error:", e); }
+  }
+
+  // Replace the node keyed p.key in snapshot s; false if it has none
+  function replaceKeyed(s, p) {
+    if (s.root.key === p.key) { s.root = p.node; return true; }
+    return (function walk(n) {
+      var c = n.children || [];
+      for (var i = 0; i < c.length; i++) {
+        if (c[i].key === p.key) { c[i] = p.node; return true; }
+        if (walk(c[i])) return true;
+      }
+      return false;
+    })(s.root);
+  }
+
   M.disconnect = function() {
+    resync = null;
     if (es) { es.close(); es = null; }
+    if (ws) { var w = ws; ws = null; w.close(); }
     status = "disconnected";
   };
 
@@ -75,6 +201,50 @@
   }
   M._apply = apply;
 
+  // Copy a snapshot into WASM input; larger ones go to memory reserve()
+  // grows (taking memory.buffer only after it, as growing detaches it)
+  function put(bytes) {
+    var p = bytes.length <= wcap ? wasm.input_ptr() : (wasm.reserve ? wasm.reserve(bytes.length) : 0);
+    if (!p) return false;
+    new Uint8Array(wasm.memory.buffer).set(bytes, p);
+    return true;
+  }
+
+  // Partition WASM predictions per route: the same action name can mean
+  // something else on another page
+  function setNs(path) {
+    if (!wasm || !wasm.set_namespace) return;
+    var b = enc.encode(path);
+    if (b.length > wcap) return;
+    new Uint8Array(wasm.memory.buffer).set(b, wasm.input_ptr());
+    wasm.set_namespace(b.length);
+  }
+
+  // Bytes of a framed WASM result: [len u32 LE][data] at p (ABI 3+)
+  function framed(p) {
+    var n = new DataView(wasm.memory.buffer).getUint32(p, true);
+    return new Uint8Array(wasm.memory.buffer, p + 4, n);
+  }
+
+  // Structural diff in WASM (snapshot already in input buffer, before store):
+  // [{key, node}] keyed replacements, or null when a full apply is needed
+  function diff(len) {
+    var b;
+    if (wasm.diff_framed) b = framed(wasm.diff_framed(len));
+    else if (wasm.diff_current) b = new Uint8Array(wasm.memory.buffer, wasm.diff_current(len), wasm.diff_len());
+    else return null;
+    if (!b.length) return null;
+    return JSON.parse(dec.decode(b));
+  }
+
+  // Patch only the changed keyed subtrees; false if any key isn't mounted
+  function applyPatches(list) {
+    var i;
+    for (i = 0; i < list.length; i++) if (!keys[list[i].key]) return false;
+    for (i = 0; i < list.length; i++) patch(keys[list[i].key], list[i].node);
+    return true;
+  }
+
   // Create a brand-new DOM tree from descriptor (first render / new keys)
   function create(n) {
     var el = d.createElement(n.tag);
@@ -89,6 +259,12 @@
 
   // Patch an existing DOM element in-place (never detaches it from parent)
   function patch(el, n) {
+    // Drop attributes the node no longer has (e.g. a cleared "disabled");
+    // data-* holds keys and event bindings, synced separately
+    for (var ai = el.attributes.length - 1; ai >= 0; ai--) {
+      var an = el.attributes[ai].name;
+      if (an.indexOf("data-") !== 0 && !(n.attrs && an in n.attrs)) el.removeAttribute(an);
+    }
     setAttrs(el, n);
     // Sync event bindings: add new, remove stale
     if (n.events) {
@@ -194,9 +370,13 @@
       var t = el.closest("[data-a_submit]");
       if (t) {
         e.preventDefault();
-        var p = {}, f = new FormData(t);
-        f.forEach(function(v, k) { p[k] = v; });
-        send(t.dataset.a_submit, p);
+        var p = {}, f = new FormData(t), files = false;
+        f.forEach(function(v, k) {
+          if (typeof v == "string") p[k] = v;
+          else if (v.name) files = true;
+        });
+        if (files) sendForm(t.dataset.a_submit, f);
+        else send(t.dataset.a_submit, p);
         t.querySelectorAll("input").forEach(function(i) { i.value = ""; });
       }
     });
@@ -283,40 +463,104 @@
 
   // --- Action dispatch: POST → apply response (single round-trip) ---
   M.send = send;
-  function send(action, payload) {
+  function send(action, payload, key) {
     // Client-side navigation: intercept navigate: prefix
     if (action.indexOf("navigate:") === 0) {
       var path = action.slice(9);
       history.pushState({}, "", path);
+      setNs(path.split("?")[0]);
       action = "navigate";
       payload = { path: path };
     }
 
-    var body = JSON.stringify({ action: action, payload: payload });
+    // One Idempotency-Key per send; a replay from the offline queue reuses
+    // it, so the server runs the action at most once
+    key = key || newKey();
+    var body = JSON.stringify({ action: action, payload: payload, key: key });
+
+    // Over a WebSocket the resulting snapshot arrives as a message
+    if (ws && ws.readyState === 1) {
+      ws.send(JSON.stringify({ type: "action", action: action, payload: payload }));
+      return;
+    }
 
     // POST to server, apply response snapshot directly
     fetch("/actions/" + encodeURIComponent(action), {
       method: "POST",
-      headers: { "Content-Type": "application/json" },
+      headers: { "Content-Type": "application/json", "X-Magnetic-CSRF": csrfToken(), "Idempotency-Key": key },
       body: body
     }).then(function(r) { return r.text(); })
-      .then(function(raw) {
-        if (!raw || raw[0] !== "{") return;
-        // Store in WASM for SSE dedup (so broadcast for same action is skipped)
-        if (wasm && wasm.store) {
-          var bytes = enc.encode(raw);
-          if (bytes.length <= 16384) {
-            new Uint8Array(wasm.memory.buffer).set(bytes, wasm.input_ptr());
-            wasm.store(bytes.length);
-          }
-        } else {
-          lastHash = fnv(raw);
-        }
-        try { apply(JSON.parse(raw)); } catch(e) {}
-      }).catch(function() {});
+      .then(applyReply).catch(function() {});
+
+    // If offline, queue for replay on reconnect (in WASM when it has room)
+    if (status != "connected") {
+      var qb = enc.encode(body);
+      if (wasm && wasm.queue_action && qb.length <= wcap) {
+        new Uint8Array(wasm.memory.buffer).set(qb, wasm.input_ptr());
+        if (wasm.queue_action(qb.length)) return;
+      }
+      queue.push(body);
+    }
+  }
+
+  // A form with files goes as multipart/form-data. The server stores the
+  // files and passes their metadata in the payload. Never over the
+  // WebSocket or the offline queue, which carry JSON only.
+  function sendForm(action, form) {
+    fetch("/actions/" + encodeURIComponent(action), {
+      method: "POST",
+      headers: { "X-Magnetic-CSRF": csrfToken(), "Idempotency-Key": newKey() },
+      body: form
+    }).then(function(r) { return r.text(); })
+      .then(applyReply).catch(function() {});
+  }
+
+  // Apply the snapshot an action POST answered with
+  function applyReply(raw) {
+    if (!raw || raw[0] !== "{") return;
+    lastRaw = raw;
+    // Store in WASM for SSE dedup (so broadcast for same action is skipped)
+    if (wasm && wasm.store) {
+      var bytes = enc.encode(raw);
+      if (put(bytes)) {
+        var patches = diff(bytes.length);
+        wasm.store(bytes.length);
+        if (patches && applyPatches(patches)) return;
+      }
+    } else {
+      lastHash = fnv(raw);
+    }
+    try { apply(JSON.parse(raw)); } catch(e) {}
+  }
 
-    // If offline, queue for later
-    if (status != "connected") queue.push(body);
+  // CSRF token for action POSTs: the SSR page's meta tag, else the cookie
+  // the server set with the session (pages not rendered by the server)
+  function csrfToken() {
+    var m = document.querySelector('meta[name="csrf-token"]');
+    if (m) return m.getAttribute("content");
+    var c = document.cookie.match(/(?:^|;\s*)magnetic_csrf=([^;]+)/);
+    return c ? c[1] : "";
+  }
+
+  function newKey() {
+    if (window.crypto && crypto.randomUUID) return crypto.randomUUID();
+    return Date.now().toString(36) + Math.random().toString(36).slice(2);
+  }
+
+  // Replay actions queued while offline, oldest first
+  function replay() {
+    var list = [], i;
+    if (wasm && wasm.pending_count) {
+      for (i = 0; i < wasm.pending_count(); i++) {
+        list.push(dec.decode(new Uint8Array(wasm.memory.buffer, wasm.pending_ptr(i), wasm.pending_len(i))));
+      }
+      wasm.clear_pending();
+    }
+    list = list.concat(queue.splice(0));
+    for (i = 0; i < list.length; i++) {
+      var q = JSON.parse(list[i]);
+      send(q.action, q.payload, q.key);
+    }
   }
 
   // --- Prefetching: warm server cache on link hover ---
@@ -335,11 +579,46 @@
 
   // --- Client-side routing: back/forward ---
   self.addEventListener("popstate", function() {
+    setNs(location.pathname);
     send("navigate", { path: location.pathname + location.search });
   });
 
+  // --- Persist WASM state (last snapshot + learned predictions) across loads ---
+  // Keyed by path: instant paint must never show another route's snapshot
+  function stateKey() { return "magnetic:transport:" + location.pathname; }
+  function saveState() {
+    if (!wasm || !wasm.export_state) return;
+    try {
+      var b = wasm.export_framed ? framed(wasm.export_framed())
+        : new Uint8Array(wasm.memory.buffer, wasm.export_state(), wasm.export_len());
+      var n = b.length, s = "";
+      if (!n) return;
+      for (var i = 0; i < n; i += 8192) s += String.fromCharCode.apply(null, b.subarray(i, i + 8192));
+      localStorage.setItem(stateKey(), btoa(s));
+    } catch(e) {}
+  }
+  function restoreState() {
+    if (!wasm.import_state) return;
+    try {
+      var s = localStorage.getItem(stateKey());
+      if (!s) return;
+      s = atob(s);
+      var b = new Uint8Array(s.length);
+      for (var i = 0; i < s.length; i++) b[i] = s.charCodeAt(i);
+      if (!put(b)) return;
+      var n = wasm.import_state(b.length);
+      // Instant paint: nothing rendered (or received) yet → last session's snapshot
+      if (n && root && !root.firstChild) {
+        apply(JSON.parse(dec.decode(new Uint8Array(wasm.memory.buffer, wasm.init(), n))));
+      }
+    } catch(e) {}
+  }
+  self.addEventListener("pagehide", saveState);
+
   // --- WASM loader (generic transport — snapshot cache + dedup) ---
-  M.loadWasm = function(url) {
+  // opts.ttl: drop predictions learned more than this many server snapshot
+  // changes ago (apps whose data changes underneath, e.g. polled sources)
+  M.loadWasm = function(url, opts) {
     if (wasm !== null) return;
     wasm = 0; // loading sentinel
     fetch(url)
@@ -347,11 +626,20 @@
       .then(function(b) { return WebAssembly.instantiate(b, {}); })
       .then(function(result) {
         wasm = result.instance.exports;
-        // Drain queued actions (replay via POST)
-        while (queue.length) {
-          var q = JSON.parse(queue.shift());
-          send(q.action, q.payload);
+        // Binaries without abi_version() (e.g. a stale cached transport.wasm)
+        // predate the extended ABI: keep only the base exports, so every
+        // feature check below falls back instead of misreading pointers
+        if (!wasm.abi_version || wasm.abi_version() < 1) {
+          var x = wasm;
+          wasm = { memory: x.memory, input_ptr: x.input_ptr, init: x.init,
+            reduce: x.reduce, snapshot_len: x.snapshot_len, store: x.store };
         }
+        if (wasm.input_cap) wcap = wasm.input_cap();
+        if (wasm.set_ttl && opts && opts.ttl) wasm.set_ttl(opts.ttl);
+        setNs(location.pathname);
+        restoreState();
+        // Drain queued actions (replay via POST)
+        replay();
       })
       .catch(function() { wasm = null; });
   };
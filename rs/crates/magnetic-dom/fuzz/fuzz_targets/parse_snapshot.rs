@@ -0,0 +1,15 @@
+//! Snapshots arrive over SSE from the server: parse anything, then render
+//! and split whatever parses (deeply nested trees must not overflow).
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use magnetic_dom::{component::split_components, parse_snapshot};
+use magnetic_render_html::render_to_html;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(json) = std::str::from_utf8(data) else { return };
+    let Ok(snapshot) = parse_snapshot(json) else { return };
+    render_to_html(&snapshot.root);
+    split_components(&snapshot.root);
+});
@@ -0,0 +1,117 @@
+//! Component splitting for multi-file codegen.
+//!
+//! Nodes marked `data-component="Name"` become their own generated function
+//! (and file). The tree they came from keeps a `magnetic:component`
+//! placeholder node in their place, with the function name in its `name`
+//! attr, which the codegen renderers turn into a call.
+
+use crate::DomNode;
+use std::collections::HashMap;
+
+/// Placeholder tag left where a component subtree was split out
+pub const COMPONENT_TAG: &str = "magnetic:component";
+
+/// A subtree split out of a larger tree, named by its `data-component` attr.
+#[derive(Debug, Clone)]
+pub struct Component {
+    pub name: String,
+    pub root: DomNode,
+}
+
+/// Split a tree at `data-component` nodes.
+///
+/// Returns the index tree plus every component, innermost first. Components
+/// are split recursively, so a component's tree holds placeholders for the
+/// components nested inside it. Repeats of an identical subtree share one
+/// component; differing subtrees with the same name get a numeric suffix
+/// (`TaskItem`, `TaskItem2`, ...).
+pub fn split_components(root: &DomNode) -> (DomNode, Vec<Component>) {
+    let mut components = Vec::new();
+    let index = split_node(root, &mut components);
+    (index, components)
+}
+
+/// Function name of a component placeholder node
+pub fn placeholder_name(node: &DomNode) -> Option<&str> {
+    if node.tag != COMPONENT_TAG {
+        return None;
+    }
+    node.attrs.as_ref()?.get("name").map(|s| s.as_str())
+}
+
+fn split_node(node: &DomNode, components: &mut Vec<Component>) -> DomNode {
+    let split = split_children(node, components);
+    let Some(base) = node.component() else { return split };
+
+    let mut n = 1;
+    let name = loop {
+        let candidate = if n == 1 { base.to_string() } else { format!("{}{}", base, n) };
+        match components.iter().find(|c| c.name == candidate) {
+            Some(c) if c.root == split => break candidate,
+            Some(_) => n += 1,
+            None => {
+                components.push(Component { name: candidate.clone(), root: split });
+                break candidate;
+            }
+        }
+    };
+
+    DomNode {
+        tag: COMPONENT_TAG.to_string(),
+        key: node.key.clone(),
+        attrs: Some(HashMap::from([("name".to_string(), name)])),
+        events: None,
+        text: None,
+        html: None,
+        children: None,
+    }
+}
+
+fn split_children(node: &DomNode, components: &mut Vec<Component>) -> DomNode {
+    DomNode {
+        tag: node.tag.clone(),
+        key: node.key.clone(),
+        attrs: node.attrs.clone(),
+        events: node.events.clone(),
+        text: node.text.clone(),
+        html: node.html.clone(),
+        children: node.children.as_ref()
+            .map(|children| children.iter().map(|c| split_node(c, components)).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_node;
+
+    #[test]
+    fn test_split_components() {
+        let root = parse_node(r#"{
+            "tag": "div",
+            "children": [
+                { "tag": "header", "attrs": { "data-component": "Header" }, "children": [
+                    { "tag": "h1", "text": "Tasks" }
+                ] },
+                { "tag": "ul", "children": [
+                    { "tag": "li", "attrs": { "data-component": "Item" }, "text": "a" },
+                    { "tag": "li", "attrs": { "data-component": "Item" }, "text": "a" },
+                    { "tag": "li", "attrs": { "data-component": "Item" }, "text": "b" }
+                ] }
+            ]
+        }"#).unwrap();
+
+        let (index, components) = split_components(&root);
+        let names: Vec<&str> = components.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Header", "Item", "Item2"]);
+        assert_eq!(components[0].root.children_iter()[0].text.as_deref(), Some("Tasks"));
+
+        let header = &index.children_iter()[0];
+        assert_eq!(header.tag, COMPONENT_TAG);
+        assert_eq!(placeholder_name(header), Some("Header"));
+        let items: Vec<_> = index.children_iter()[1].children_iter().iter()
+            .map(|n| placeholder_name(n).unwrap())
+            .collect();
+        assert_eq!(items, ["Item", "Item", "Item2"]);
+    }
+}
@@ -0,0 +1,120 @@
+//! Keyed structural diff between two trees.
+//!
+//! The result is a list of keyed subtree replacements, the same patch list
+//! the transport WASM's `diff_current` produces and `magnetic.js` applies:
+//! `[{"key":"k","node":{...}},...]`. Anything that can't be expressed that
+//! way (a changed root, or children added, removed or reordered under an
+//! unkeyed parent) means the whole tree has to be replaced.
+
+use crate::DomNode;
+use serde::Serialize;
+
+/// Replace the node keyed `key` with `node`, in place
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Patch<'a> {
+    pub key: &'a str,
+    pub node: &'a DomNode,
+}
+
+/// Patches that turn `old` into `new` (empty if they're equal), or None if
+/// `new` has to replace `old` as a whole.
+pub fn diff<'a>(old: &DomNode, new: &'a DomNode) -> Option<Vec<Patch<'a>>> {
+    let mut out = Vec::new();
+    if diff_node(old, new, &mut out) {
+        return Some(out);
+    }
+    match (&old.key, &new.key) {
+        (Some(ok), Some(nk)) if ok == nk => Some(vec![Patch { key: nk, node: new }]),
+        _ => None,
+    }
+}
+
+/// Returns false if `new` has to be replaced as a whole; patches for
+/// changed keyed descendants are appended to `out` otherwise.
+fn diff_node<'a>(old: &DomNode, new: &'a DomNode, out: &mut Vec<Patch<'a>>) -> bool {
+    if old == new {
+        return true;
+    }
+    // Everything but the children must be identical
+    if old.tag != new.tag
+        || old.key != new.key
+        || old.attrs != new.attrs
+        || old.events != new.events
+        || old.text != new.text
+        || old.html != new.html
+    {
+        return false;
+    }
+    let (Some(oc), Some(nc)) = (&old.children, &new.children) else {
+        return false;
+    };
+    if oc.len() != nc.len() {
+        return false;
+    }
+    let mark = out.len();
+    for (o, n) in oc.iter().zip(nc) {
+        if diff_node(o, n, out) {
+            continue;
+        }
+        match (&o.key, &n.key) {
+            (Some(ok), Some(nk)) if ok == nk => out.push(Patch { key: nk, node: n }),
+            _ => {
+                // Parent gets replaced: drop its descendants' patches
+                out.truncate(mark);
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_node;
+
+    fn list(items: &[(&str, &str)]) -> DomNode {
+        let children: Vec<String> = items.iter()
+            .map(|(k, t)| format!(r#"{{ "tag": "li", "key": "{}", "text": "{}" }}"#, k, t))
+            .collect();
+        parse_node(&format!(
+            r#"{{ "tag": "div", "key": "app", "children": [
+                {{ "tag": "h1", "text": "Tasks" }},
+                {{ "tag": "ul", "children": [{}] }}
+            ] }}"#,
+            children.join(",")
+        )).unwrap()
+    }
+
+    #[test]
+    fn test_diff_keyed_replacements() {
+        let old = list(&[("a", "one"), ("b", "two"), ("c", "three")]);
+        let new = list(&[("a", "one"), ("b", "TWO"), ("c", "three")]);
+
+        assert_eq!(diff(&old, &old), Some(vec![]));
+
+        let patches = diff(&old, &new).unwrap();
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].key, "b");
+        assert_eq!(patches[0].node.text.as_deref(), Some("TWO"));
+        assert_eq!(
+            serde_json::to_string(&patches).unwrap(),
+            r#"[{"key":"b","node":{"tag":"li","key":"b","text":"TWO"}}]"#
+        );
+    }
+
+    #[test]
+    fn test_diff_falls_back_to_keyed_ancestor() {
+        // Added child under the unkeyed <ul>: only the keyed root can be patched
+        let old = list(&[("a", "one")]);
+        let new = list(&[("a", "one"), ("b", "two")]);
+        let patches = diff(&old, &new).unwrap();
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].key, "app");
+
+        // Unkeyed root: no usable diff
+        let old = parse_node(r#"{ "tag": "div", "children": [{ "tag": "p", "text": "a" }] }"#).unwrap();
+        let new = parse_node(r#"{ "tag": "div", "children": [{ "tag": "p", "text": "b" }] }"#).unwrap();
+        assert_eq!(diff(&old, &new), None);
+    }
+}
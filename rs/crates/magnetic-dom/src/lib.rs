@@ -3,13 +3,17 @@
 //! This crate defines the canonical Rust representation of the Magnetic JSON DOM
 //! snapshot format. All renderers (HTML, Kotlin, SwiftUI) consume this type.
 
+pub mod component;
+pub mod diff;
+pub mod mapping;
+
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// A single node in the Magnetic DOM tree.
 ///
 /// Mirrors the JSON schema at contracts/schemas/dom/snapshot.schema.json.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DomNode {
     /// HTML tag name (e.g. "div", "button", "input")
     pub tag: String,
@@ -77,6 +81,12 @@ impl DomNode {
         }
     }
 
+    /// Component name from `data-component`, marking a subtree that the
+    /// codegen renderers can split into its own file
+    pub fn component(&self) -> Option<&str> {
+        self.attrs.as_ref()?.get("data-component").map(|s| s.as_str())
+    }
+
     /// Get an event action by event name
     pub fn event(&self, name: &str) -> Option<&str> {
         self.events.as_ref()?.get(name).map(|s| s.as_str())
@@ -0,0 +1,152 @@
+//! Custom tag/class mappings for the codegen renderers.
+//!
+//! A mapping file (`--render-map mappings.json`) overrides how specific tags
+//! or classes are translated, per target language:
+//!
+//! ```json
+//! {
+//!   "kotlin": {
+//!     "imports": ["androidx.media3.ui.PlayerView"],
+//!     "classes": { "chip": "AssistChip(onClick = { onAction(\"{event:click}\") }, label = { Text(\"{text}\") })" },
+//!     "tags": { "video": "VideoPlayer(url = \"{attr:src}\")" }
+//!   },
+//!   "swift": {
+//!     "tags": { "video": "VideoPlayer(player: AVPlayer(url: URL(string: \"{attr:src}\")!))" }
+//!   }
+//! }
+//! ```
+//!
+//! Template placeholders: `{text}`, `{key}`, `{attr:NAME}`, `{event:NAME}`
+//! (all escaped for the target language) and `{children}`, which must sit
+//! alone on its line and is replaced by the rendered child nodes.
+
+use crate::DomNode;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Mapping overrides for every codegen target.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RenderMaps {
+    #[serde(default)]
+    pub kotlin: TagMapping,
+    #[serde(default)]
+    pub swift: TagMapping,
+}
+
+/// Mapping overrides for one target language.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TagMapping {
+    /// Extra imports added to the generated file header
+    #[serde(default)]
+    pub imports: Vec<String>,
+    /// Class name (with or without leading '.') → template
+    #[serde(default)]
+    pub classes: HashMap<String, String>,
+    /// Tag name → template
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+impl RenderMaps {
+    /// Parse a JSON mapping file
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+impl TagMapping {
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty() && self.tags.is_empty()
+    }
+
+    /// Template for a node: the first class with a mapping wins, then the tag
+    pub fn lookup(&self, node: &DomNode) -> Option<&str> {
+        if let Some(class) = node.class() {
+            for c in class.split_whitespace() {
+                let hit = self.classes.get(c).or_else(|| self.classes.get(&format!(".{}", c)));
+                if let Some(t) = hit {
+                    return Some(t);
+                }
+            }
+        }
+        self.tags.get(&node.tag).map(|s| s.as_str())
+    }
+}
+
+/// Substitute `{text}`, `{key}`, `{attr:NAME}` and `{event:NAME}` in one
+/// template line. Unknown placeholders (including `{children}`) are kept.
+pub fn fill_template(line: &str, node: &DomNode, escape: fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let Some(close) = after.find('}') else {
+            out.push_str(&rest[open..]);
+            return out;
+        };
+        let name = &after[..close];
+        let value = match name {
+            "text" => Some(text_of(node)),
+            "key" => Some(node.key.clone().unwrap_or_default()),
+            _ => {
+                if let Some(attr) = name.strip_prefix("attr:") {
+                    Some(node.attrs.as_ref().and_then(|a| a.get(attr)).cloned().unwrap_or_default())
+                } else {
+                    name.strip_prefix("event:")
+                        .map(|ev| node.event(ev).unwrap_or("").to_string())
+                }
+            }
+        };
+        match value {
+            Some(v) => {
+                out.push_str(&escape(&v));
+                rest = &after[close + 1..];
+            }
+            // Not a placeholder (e.g. a Kotlin lambda brace): keep the '{'
+            // and keep scanning inside it
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn text_of(node: &DomNode) -> String {
+    let mut text = node.text.clone().unwrap_or_default();
+    for child in node.children_iter() {
+        text.push_str(&text_of(child));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_and_fill() {
+        let maps = RenderMaps::from_json(r#"{
+            "kotlin": {
+                "classes": { ".chip": "AssistChip(label = { Text(\"{text}\") }, onClick = { onAction(\"{event:click}\") })" },
+                "tags": { "video": "VideoPlayer(\"{attr:src}\")" }
+            }
+        }"#).unwrap();
+        let chip = DomNode {
+            attrs: Some(HashMap::from([("class".into(), "tag chip".into())])),
+            events: Some(HashMap::from([("click".into(), "pick".into())])),
+            ..DomNode::text("span", "Say \"hi\"")
+        };
+        let template = maps.kotlin.lookup(&chip).unwrap();
+        let escape: fn(&str) -> String = |s| s.replace('"', "\\\"");
+        assert_eq!(
+            fill_template(template, &chip, escape),
+            r#"AssistChip(label = { Text("Say \"hi\"") }, onClick = { onAction("pick") })"#
+        );
+        assert!(maps.swift.is_empty());
+        assert!(maps.kotlin.lookup(&DomNode::text("p", "x")).is_none());
+    }
+}
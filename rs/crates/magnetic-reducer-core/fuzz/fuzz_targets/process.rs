@@ -0,0 +1,26 @@
+//! Action bytes come straight from the network: run any sequence through
+//! process() on the fixed-buffer (no_std) state, then check the state
+//! survives an export/import round trip and import rejects garbage safely.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use magnetic_reducer_core::{export_state, import_state, process, tick, AppState, Buf};
+
+fuzz_target!(|actions: Vec<&[u8]>| {
+    let mut state = Box::new(AppState::new());
+    let mut buf = Box::new(Buf::new());
+    for (i, action) in actions.iter().enumerate() {
+        process(&mut state, action, &mut buf);
+        tick(&mut state, i as u64 * 1000, &mut buf);
+        import_state(&mut state, action);
+    }
+
+    if export_state(&state, &mut buf) {
+        let saved = buf.as_bytes().to_vec();
+        let mut restored = Box::new(AppState::new());
+        assert!(import_state(&mut restored, &saved));
+        assert_eq!(restored.count, state.count);
+        assert_eq!(restored.msg_count(), state.msg_count());
+    }
+});
@@ -1,19 +1,79 @@
-/// Fixed-size write buffer — no alloc needed.
+// ---------------------------------------------------------------------------
+// std builds: growable write buffer (server / production)
+// ---------------------------------------------------------------------------
+#[cfg(feature = "std")]
+extern crate alloc;
+
+/// Write buffer — grows as needed, never truncates.
+#[cfg(feature = "std")]
+pub struct Buf {
+    pub data: alloc::vec::Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl Buf {
+    pub const fn new() -> Self {
+        Self { data: alloc::vec::Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    pub fn push(&mut self, b: u8) {
+        self.data.push(b);
+    }
+
+    pub fn extend(&mut self, s: &[u8]) {
+        self.data.extend_from_slice(s);
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Drop everything past the first `len` bytes.
+    pub fn truncate(&mut self, len: usize) {
+        self.data.truncate(len);
+    }
+
+    pub fn len(&self) -> usize { self.data.len() }
+
+    pub fn is_empty(&self) -> bool { self.data.is_empty() }
+
+    /// Always false: a growable buffer never drops output.
+    pub fn overflowed(&self) -> bool { false }
+
+    /// Bytes written since the last clear (the same as len()).
+    pub fn required_len(&self) -> usize { self.data.len() }
+}
+
+// ---------------------------------------------------------------------------
+// no_std builds: fixed 4 KB buffer (WASM offline fallback)
+// ---------------------------------------------------------------------------
+
+/// Fixed-size write buffer — no alloc needed. Output past capacity is
+/// dropped but counted: check overflowed() before using the bytes.
+#[cfg(not(feature = "std"))]
 pub struct Buf {
     pub data: [u8; 4096],
     pub len: usize,
+    required: usize,
 }
 
+#[cfg(not(feature = "std"))]
 impl Buf {
     pub const fn new() -> Self {
-        Self { data: [0u8; 4096], len: 0 }
+        Self { data: [0u8; 4096], len: 0, required: 0 }
     }
 
     pub fn clear(&mut self) {
         self.len = 0;
+        self.required = 0;
     }
 
     pub fn push(&mut self, b: u8) {
+        self.required += 1;
         if self.len < self.data.len() {
             self.data[self.len] = b;
             self.len += 1;
@@ -29,4 +89,26 @@ impl Buf {
     pub fn as_bytes(&self) -> &[u8] {
         &self.data[..self.len]
     }
+
+    /// Drop everything past the first `len` bytes (dropped overflow included).
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.required { self.required = len; }
+        if len < self.len { self.len = len; }
+    }
+
+    pub fn len(&self) -> usize { self.len }
+
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// True if output was dropped since the last clear (as_bytes() is then
+    /// truncated, e.g. invalid JSON).
+    pub fn overflowed(&self) -> bool { self.required > self.len }
+
+    /// Bytes written since the last clear, including dropped ones: the
+    /// capacity a host would have needed.
+    pub fn required_len(&self) -> usize { self.required }
+}
+
+impl Default for Buf {
+    fn default() -> Self { Self::new() }
 }
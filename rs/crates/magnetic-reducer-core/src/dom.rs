@@ -1,38 +1,11 @@
 use crate::buf::Buf;
+use crate::node::{write_escaped, write_i64};
 use crate::state::AppState;
 
-/// Write i32 as decimal into buf.
-fn write_i32(buf: &mut Buf, mut n: i32) {
-    if n == 0 {
-        buf.push(b'0');
-        return;
-    }
-    if n < 0 {
-        buf.push(b'-');
-        n = -n;
-    }
-    let start = buf.len;
-    while n > 0 {
-        buf.push(b'0' + (n % 10) as u8);
-        n /= 10;
-    }
-    // Reverse the digits we just wrote
-    let end = buf.len;
-    let slice = &mut buf.data[start..end];
-    slice.reverse();
-}
-
 /// Write a JSON-escaped string (bytes) into buf, surrounded by quotes.
 fn write_str(buf: &mut Buf, s: &[u8]) {
     buf.push(b'"');
-    for &b in s {
-        match b {
-            b'"' => { buf.push(b'\\'); buf.push(b'"'); }
-            b'\\' => { buf.push(b'\\'); buf.push(b'\\'); }
-            b'\n' => { buf.push(b'\\'); buf.push(b'n'); }
-            c => buf.push(c),
-        }
-    }
+    write_escaped(buf, s);
     buf.push(b'"');
 }
 
@@ -50,7 +23,7 @@ pub fn render_snapshot(state: &AppState, buf: &mut Buf) {
     //-- Child 0: h1 with count
     open_tag(buf, b"h1", Some(b"title"));
     buf.extend(b",\"text\":\"Count: ");
-    write_i32(buf, state.count);
+    write_i64(buf, state.count as i64);
     buf.extend(b"\"}");
 
     buf.push(b',');
@@ -68,6 +41,11 @@ pub fn render_snapshot(state: &AppState, buf: &mut Buf) {
     open_tag(buf, b"button", None);
     write_events_1(buf, b"click", b"increment");
     buf.extend(b",\"text\":\"+\"}");
+    buf.push(b',');
+    // Undo / redo buttons, disabled when there is no step to take
+    history_button(buf, b"undo", b"Undo", state.can_undo());
+    buf.push(b',');
+    history_button(buf, b"redo", b"Redo", state.can_redo());
     buf.extend(b"]}"); // close children + controls div
 
     buf.push(b',');
@@ -116,15 +94,36 @@ pub fn render_snapshot(state: &AppState, buf: &mut Buf) {
     buf.push(b'}'); // close snapshot wrapper
 }
 
-fn write_escaped(buf: &mut Buf, s: &[u8]) {
-    for &b in s {
-        match b {
-            b'"' => { buf.push(b'\\'); buf.push(b'"'); }
-            b'\\' => { buf.push(b'\\'); buf.push(b'\\'); }
-            b'\n' => { buf.push(b'\\'); buf.push(b'n'); }
-            c => buf.push(c),
-        }
+/// Button dispatching `action` on click; `"disabled"` attr unless enabled.
+fn history_button(buf: &mut Buf, action: &[u8], label: &[u8], enabled: bool) {
+    open_tag(buf, b"button", Some(action));
+    if !enabled {
+        write_attrs_1(buf, b"disabled", b"");
     }
+    write_events_1(buf, b"click", action);
+    buf.extend(b",\"text\":");
+    write_str(buf, label);
+    buf.push(b'}');
+}
+
+/// Snapshot for a route no page matches, linking back to `/`.
+pub fn render_not_found(state: &AppState, buf: &mut Buf) {
+    buf.clear();
+    buf.extend(b"{\"root\":");
+    open_tag(buf, b"div", None);
+    write_attrs_1(buf, b"class", b"app");
+    buf.extend(b",\"children\":[");
+    open_tag(buf, b"h1", Some(b"title"));
+    buf.extend(b",\"text\":\"Not found: ");
+    write_escaped(buf, state.route.as_bytes());
+    buf.extend(b"\"}");
+    buf.push(b',');
+    open_tag(buf, b"a", None);
+    write_attrs_1(buf, b"href", b"/");
+    write_events_1(buf, b"click", b"navigate:/");
+    buf.extend(b",\"text\":\"Home\"}");
+    buf.extend(b"]}"); // close children + root div
+    buf.push(b'}'); // close snapshot wrapper
 }
 
 /// Write opening of a node object: {"tag":"...", optionally "key":"..."
@@ -183,13 +182,23 @@ pub fn render_html(state: &AppState, buf: &mut Buf) {
 
     // h1: count
     buf.extend(b"<h1 data-key=\"title\">Count: ");
-    write_i32(buf, state.count);
+    write_i64(buf, state.count as i64);
     buf.extend(b"</h1>");
 
     // Controls
     buf.extend(b"<div class=\"controls\">");
     buf.extend(b"<button data-a_click=\"decrement\">-</button>");
     buf.extend(b"<button data-a_click=\"increment\">+</button>");
+    buf.extend(if state.can_undo() {
+        b"<button data-key=\"undo\" data-a_click=\"undo\">Undo</button>".as_slice()
+    } else {
+        b"<button data-key=\"undo\" data-a_click=\"undo\" disabled>Undo</button>"
+    });
+    buf.extend(if state.can_redo() {
+        b"<button data-key=\"redo\" data-a_click=\"redo\">Redo</button>".as_slice()
+    } else {
+        b"<button data-key=\"redo\" data-a_click=\"redo\" disabled>Redo</button>"
+    });
     buf.extend(b"</div>");
 
     // Messages
@@ -214,3 +223,14 @@ pub fn render_html(state: &AppState, buf: &mut Buf) {
 
     buf.extend(b"</div>");
 }
+
+/// HTML for a route no page matches.
+pub fn render_not_found_html(state: &AppState, buf: &mut Buf) {
+    buf.clear();
+    buf.extend(b"<div class=\"app\">");
+    buf.extend(b"<h1 data-key=\"title\">Not found: ");
+    write_html_escaped(buf, state.route.as_bytes());
+    buf.extend(b"</h1>");
+    buf.extend(b"<a href=\"/\" data-a_click=\"navigate:/\">Home</a>");
+    buf.extend(b"</div>");
+}
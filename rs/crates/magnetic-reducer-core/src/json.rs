@@ -0,0 +1,170 @@
+//! Minimal no_std JSON field extractor. No alloc.
+//!
+//! Works on the raw bytes of one object: `field` finds a top-level member and
+//! returns its value as a byte span, the typed helpers decode that span
+//! (`elements` walks the values of an array the same way).
+//! Nested objects/arrays and escaped quotes are skipped properly, so a key
+//! that only appears inside a nested value or a string is never matched.
+
+fn skip_ws(s: &[u8], mut i: usize) -> usize {
+    while i < s.len() && (s[i] == b' ' || s[i] == b'\t' || s[i] == b'\n' || s[i] == b'\r') { i += 1; }
+    i
+}
+
+/// End index (exclusive) of the string starting at the quote at `i`.
+fn skip_string(s: &[u8], mut i: usize) -> Option<usize> {
+    i += 1;
+    while i < s.len() {
+        match s[i] {
+            b'"' => return Some(i + 1),
+            b'\\' => i += 2,
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// End index (exclusive) of the value starting at `i`.
+fn skip_value(s: &[u8], i: usize) -> Option<usize> {
+    match *s.get(i)? {
+        b'"' => skip_string(s, i),
+        b'{' | b'[' => {
+            let mut depth = 0usize;
+            let mut j = i;
+            while j < s.len() {
+                match s[j] {
+                    b'"' => { j = skip_string(s, j)?; continue; }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 { return Some(j + 1); }
+                    }
+                    _ => {}
+                }
+                j += 1;
+            }
+            None
+        }
+        _ => {
+            let mut j = i;
+            while j < s.len() && !matches!(s[j], b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r') { j += 1; }
+            if j == i { None } else { Some(j) }
+        }
+    }
+}
+
+/// Raw value of the top-level member `key` (unquoted) of `obj`.
+pub fn field<'a>(obj: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    let mut i = skip_ws(obj, 0);
+    if obj.get(i) != Some(&b'{') { return None; }
+    i = skip_ws(obj, i + 1);
+    while obj.get(i) == Some(&b'"') {
+        let key_end = skip_string(obj, i)?;
+        let name = &obj[i + 1..key_end - 1];
+        i = skip_ws(obj, key_end);
+        if obj.get(i) != Some(&b':') { return None; }
+        i = skip_ws(obj, i + 1);
+        let end = skip_value(obj, i)?;
+        if name == key { return Some(&obj[i..end]); }
+        i = skip_ws(obj, end);
+        if obj.get(i) != Some(&b',') { return None; }
+        i = skip_ws(obj, i + 1);
+    }
+    None
+}
+
+/// True if the value is a JSON array.
+pub fn is_array(value: &[u8]) -> bool {
+    value.get(skip_ws(value, 0)) == Some(&b'[')
+}
+
+/// Elements of a JSON array, as raw value spans. Yields nothing if `arr`
+/// is not a complete array (e.g. truncated).
+pub fn elements(arr: &[u8]) -> Elements<'_> {
+    let start = skip_ws(arr, 0);
+    match skip_value(arr, start) {
+        Some(end) if is_array(arr) => Elements { s: &arr[..end], i: skip_ws(arr, start + 1) },
+        _ => Elements { s: arr, i: arr.len() },
+    }
+}
+
+pub struct Elements<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let start = self.i;
+        let Some(end) = skip_value(self.s, start).filter(|_| self.s.get(start) != Some(&b']')) else {
+            self.i = self.s.len();
+            return None;
+        };
+        self.i = skip_ws(self.s, end);
+        match self.s.get(self.i) {
+            Some(b',') => self.i = skip_ws(self.s, self.i + 1),
+            _ => self.i = self.s.len(), // `]` or malformed: this is the last one
+        }
+        Some(&self.s[start..end])
+    }
+}
+
+/// String member, unescaped into `out` (truncated to its length).
+pub fn str_field<'b>(obj: &[u8], key: &[u8], out: &'b mut [u8]) -> Option<&'b [u8]> {
+    let v = field(obj, key)?;
+    if v.len() < 2 || v[0] != b'"' { return None; }
+    let v = &v[1..v.len() - 1];
+    let mut n = 0;
+    let mut i = 0;
+    while i < v.len() && n < out.len() {
+        let c = if v[i] == b'\\' && i + 1 < v.len() {
+            i += 1;
+            match v[i] {
+                b'n' => b'\n',
+                b't' => b'\t',
+                b'r' => b'\r',
+                b'"' | b'\\' | b'/' => v[i],
+                c => {
+                    // Other escapes (\uXXXX, ...) are kept as written
+                    out[n] = b'\\';
+                    n += 1;
+                    if n == out.len() { break; }
+                    c
+                }
+            }
+        } else {
+            v[i]
+        };
+        out[n] = c;
+        n += 1;
+        i += 1;
+    }
+    Some(&out[..n])
+}
+
+/// Integer member (a JSON number without fraction or exponent).
+pub fn int_field(obj: &[u8], key: &[u8]) -> Option<i64> {
+    let v = field(obj, key)?;
+    let (neg, digits) = match v.first()? {
+        b'-' => (true, &v[1..]),
+        _ => (false, v),
+    };
+    if digits.is_empty() { return None; }
+    let mut n: i64 = 0;
+    for &d in digits {
+        if !d.is_ascii_digit() { return None; }
+        n = n.checked_mul(10)?.checked_add((d - b'0') as i64)?;
+    }
+    Some(if neg { -n } else { n })
+}
+
+/// Boolean member.
+pub fn bool_field(obj: &[u8], key: &[u8]) -> Option<bool> {
+    match field(obj, key)? {
+        b"true" => Some(true),
+        b"false" => Some(false),
+        _ => None,
+    }
+}
@@ -2,50 +2,194 @@
 
 pub mod buf;
 mod dom;
-mod parse;
+pub mod json;
+pub mod node;
+mod persist;
+pub mod registry;
+pub mod router;
 mod state;
+pub mod timer;
 
 pub use buf::Buf;
-pub use state::{AppState, Message};
+pub use node::{RenderNode, TextValue};
+pub use registry::{Handler, Payload, Registry};
+pub use router::{Page, Router, View};
+pub use state::{AppState, Message, Route};
+pub use timer::Timers;
 
 /// Supported actions.
+// Passed by value once per dispatch; there is no alloc to box the text into
+#[allow(clippy::large_enum_variant)]
 pub enum Action {
     Increment,
     Decrement,
     SendMessage { text_buf: [u8; 256], text_len: usize },
+    Undo,
+    Redo,
+    Navigate { path_buf: [u8; 128], path_len: usize },
     Unknown,
 }
 
 /// Pure reducer: mutate state based on action.
 pub fn reduce(state: &mut AppState, action: Action) {
     match action {
-        Action::Increment => state.count += 1,
+        Action::Increment => {
+            state.checkpoint();
+            state.count += 1;
+        }
         Action::Decrement => {
             if state.count > 0 {
+                state.checkpoint();
                 state.count -= 1;
             }
         }
         Action::SendMessage { text_buf, text_len } => {
+            state.checkpoint();
             state.push_message(b"user", &text_buf[..text_len]);
         }
+        Action::Undo => {
+            state.undo();
+        }
+        Action::Redo => {
+            state.redo();
+        }
+        Action::Navigate { path_buf, path_len } => {
+            state.route.set(&path_buf[..path_len]);
+        }
         Action::Unknown => {}
     }
 }
 
-/// Render the current state to a JSON DOM snapshot into the provided buffer.
+/// Built-in pages, rendered by `render` / `render_html`.
+pub const PAGES: Router = Router::new(Page { snapshot: dom::render_not_found, html: dom::render_not_found_html })
+    .page("/", Page { snapshot: dom::render_snapshot, html: dom::render_html });
+
+/// Render the current route to a JSON DOM snapshot into the provided buffer.
 pub fn render(state: &AppState, buf: &mut Buf) {
-    dom::render_snapshot(state, buf);
+    PAGES.render(state, buf);
 }
 
-/// Render the current state as an HTML string for SSR first-paint.
+/// Render the current route as an HTML string for SSR first-paint.
 pub fn render_html(state: &AppState, buf: &mut Buf) {
-    dom::render_html(state, buf);
+    PAGES.render_html(state, buf);
+}
+
+/// Serialize the state (without undo history) so a host can persist it
+/// across reloads or snapshot a session. False if it doesn't fit in `buf`.
+pub fn export_state(state: &AppState, buf: &mut Buf) -> bool {
+    persist::export_state(state, buf)
+}
+
+/// Restore a state written by `export_state`. False (state untouched) if the
+/// bytes are not a valid export.
+pub fn import_state(state: &mut AppState, bytes: &[u8]) -> bool {
+    persist::import_state(state, bytes)
+}
+
+/// Built-in actions, dispatched by `process`.
+pub const ACTIONS: Registry = Registry::new()
+    .with("increment", |state, _| reduce(state, Action::Increment))
+    .with("decrement", |state, _| reduce(state, Action::Decrement))
+    .with("send_message", send_message)
+    .with("undo", |state, _| reduce(state, Action::Undo))
+    .with("redo", |state, _| reduce(state, Action::Redo))
+    .with("navigate", navigate);
+
+fn send_message(state: &mut AppState, payload: &Payload) {
+    let mut text_buf = [0u8; 256];
+    let text_len = payload.str("text", &mut text_buf).map_or(0, |t| t.len());
+    reduce(state, Action::SendMessage { text_buf, text_len });
+}
+
+fn navigate(state: &mut AppState, payload: &Payload) {
+    let mut path_buf = [0u8; 128];
+    let path_len = payload.str("path", &mut path_buf).map_or(0, |p| p.len());
+    reduce(state, Action::Navigate { path_buf, path_len });
 }
 
 /// Parse action bytes and dispatch reduce + render.
-/// Input format: `{"action":"name","payload":{...}}`
+/// Input format: `{"action":"name","payload":{...}}`, or an array of those
+/// (e.g. events coalesced per frame), applied in order before one render.
+/// If a timer is pending, the snapshot ends with `"wake":<ms>` (see `tick`).
 pub fn process(state: &mut AppState, input: &[u8], buf: &mut Buf) {
-    let action = parse::parse_action(input);
-    reduce(state, action);
-    render(state, buf);
+    process_with(&ACTIONS, &PAGES, state, input, buf);
+}
+
+/// `process` with an app's own actions and pages.
+/// Unknown actions leave the state unchanged (the snapshot is still rendered);
+/// so does a batch with one in it.
+pub fn process_with(actions: &Registry, pages: &Router, state: &mut AppState, input: &[u8], buf: &mut Buf) {
+    actions.dispatch_batch(state, input);
+    pages.render(state, buf);
+    write_wake(state, buf);
+}
+
+/// Advance the clock to `now_ms`, dispatch every scheduled action that is
+/// due, and render. Hosts call this once the `wake` delay of the last
+/// output has passed; set `state.timers.set_now` before `process` too if
+/// handlers schedule from user actions, so their delays count from now.
+pub fn tick(state: &mut AppState, now_ms: u64, buf: &mut Buf) {
+    tick_with(&ACTIONS, &PAGES, state, now_ms, buf);
+}
+
+/// `tick` with an app's own actions and pages. At most MAX_TIMERS actions
+/// fire per call, so a timer that reschedules itself with no delay can't
+/// spin; the rest are reported as `"wake":0`.
+pub fn tick_with(actions: &Registry, pages: &Router, state: &mut AppState, now_ms: u64, buf: &mut Buf) {
+    state.timers.set_now(now_ms);
+    let mut action = [0u8; timer::ACTION_CAP];
+    for _ in 0..timer::MAX_TIMERS {
+        let Some(len) = state.timers.pop_due(&mut action) else { break };
+        actions.dispatch_batch(state, &action[..len]);
+    }
+    pages.render(state, buf);
+    write_wake(state, buf);
+}
+
+/// Add `"wake":<ms until the next timer>` to the rendered snapshot.
+fn write_wake(state: &AppState, buf: &mut Buf) {
+    let Some(ms) = state.timers.wake_in() else { return };
+    if buf.overflowed() || buf.as_bytes().last() != Some(&b'}') {
+        return;
+    }
+    buf.truncate(buf.len() - 1);
+    buf.extend(b",\"wake\":");
+    node::write_u64(buf, ms);
+    buf.push(b'}');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick_fires_due_actions() {
+        let mut state = AppState::new();
+        let mut buf = Buf::new();
+        state.timers.set_now(1000);
+        assert!(state.schedule(br#"{"action":"increment"}"#, 100));
+
+        tick(&mut state, 1040, &mut buf);
+        assert_eq!(state.count, 0);
+        assert!(buf.as_bytes().ends_with(b",\"wake\":60}"));
+
+        tick(&mut state, 1100, &mut buf);
+        assert_eq!(state.count, 1);
+        assert!(!buf.as_bytes().windows(7).any(|w| w == b"\"wake\":"));
+        assert!(state.timers.is_empty());
+    }
+
+    #[test]
+    fn test_tick_stops_a_timer_that_reschedules_itself() {
+        const SPIN: Registry = Registry::new().with("spin", |state, _| {
+            state.count += 1;
+            state.schedule(br#"{"action":"spin"}"#, 0);
+        });
+        let mut state = AppState::new();
+        let mut buf = Buf::new();
+        state.schedule(br#"{"action":"spin"}"#, 0);
+        tick_with(&SPIN, &PAGES, &mut state, 0, &mut buf);
+        assert_eq!(state.count, timer::MAX_TIMERS as i32);
+        assert!(buf.as_bytes().ends_with(b",\"wake\":0}"));
+    }
 }
@@ -0,0 +1,102 @@
+//! Building blocks for writing JSON DOM nodes into a `Buf`.
+//!
+//! Shared by the built-in views and by code generated with
+//! `#[derive(MagneticRender)]` (magnetic-reducer-derive), which implements
+//! `RenderNode` for a state struct instead of balancing braces by hand.
+
+use crate::buf::Buf;
+
+#[cfg(feature = "std")]
+extern crate alloc;
+
+/// A value that renders as one DOM node.
+pub trait RenderNode {
+    /// Write this node's JSON object (no snapshot wrapper) into `buf`.
+    fn render_node(&self, buf: &mut Buf);
+}
+
+/// Render `node` as a full snapshot: `{"root":<node>}`.
+pub fn render_root<N: RenderNode + ?Sized>(node: &N, buf: &mut Buf) {
+    buf.clear();
+    buf.extend(b"{\"root\":");
+    node.render_node(buf);
+    buf.push(b'}');
+}
+
+/// A value that renders as node text (or a dynamic key).
+pub trait TextValue {
+    /// Write the value JSON-escaped, without surrounding quotes.
+    fn write_text(&self, buf: &mut Buf);
+}
+
+impl<T: TextValue + ?Sized> TextValue for &T {
+    fn write_text(&self, buf: &mut Buf) { (**self).write_text(buf) }
+}
+
+impl TextValue for str {
+    fn write_text(&self, buf: &mut Buf) { write_escaped(buf, self.as_bytes()) }
+}
+
+impl TextValue for [u8] {
+    fn write_text(&self, buf: &mut Buf) { write_escaped(buf, self) }
+}
+
+impl TextValue for bool {
+    fn write_text(&self, buf: &mut Buf) { buf.extend(if *self { b"true" } else { b"false" }) }
+}
+
+#[cfg(feature = "std")]
+impl TextValue for alloc::string::String {
+    fn write_text(&self, buf: &mut Buf) { write_escaped(buf, self.as_bytes()) }
+}
+
+#[cfg(feature = "std")]
+impl TextValue for alloc::vec::Vec<u8> {
+    fn write_text(&self, buf: &mut Buf) { write_escaped(buf, self) }
+}
+
+macro_rules! text_value_int {
+    ($($t:ty => $w:ident),*) => {
+        $(impl TextValue for $t {
+            fn write_text(&self, buf: &mut Buf) { $w(buf, *self as _) }
+        })*
+    };
+}
+
+text_value_int!(
+    i8 => write_i64, i16 => write_i64, i32 => write_i64, i64 => write_i64, isize => write_i64,
+    u8 => write_u64, u16 => write_u64, u32 => write_u64, u64 => write_u64, usize => write_u64
+);
+
+/// Write an unsigned integer as decimal.
+pub fn write_u64(buf: &mut Buf, mut n: u64) {
+    let mut digits = [0u8; 20];
+    let mut i = digits.len();
+    loop {
+        i -= 1;
+        digits[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 { break; }
+    }
+    buf.extend(&digits[i..]);
+}
+
+/// Write a signed integer as decimal.
+pub fn write_i64(buf: &mut Buf, n: i64) {
+    if n < 0 {
+        buf.push(b'-');
+    }
+    write_u64(buf, n.unsigned_abs());
+}
+
+/// Write bytes JSON-escaped (no surrounding quotes).
+pub fn write_escaped(buf: &mut Buf, s: &[u8]) {
+    for &b in s {
+        match b {
+            b'"' => { buf.push(b'\\'); buf.push(b'"'); }
+            b'\\' => { buf.push(b'\\'); buf.push(b'\\'); }
+            b'\n' => { buf.push(b'\\'); buf.push(b'n'); }
+            c => buf.push(c),
+        }
+    }
+}
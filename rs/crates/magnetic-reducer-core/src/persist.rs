@@ -0,0 +1,166 @@
+use crate::buf::Buf;
+use crate::state::AppState;
+
+/// Format tag + version. Bump when the layout below changes, so a blob saved
+/// by an older build is rejected instead of misread.
+const MAGIC: &[u8; 4] = b"MRS1";
+
+// Layout (little-endian):
+//   "MRS1"  i32 count  u32 messages
+//   messages × (u8 author_len, author, u16 text_len, text)
+//
+// Undo history is not saved: it belongs to the session that made the edits.
+// Neither is the route, which follows the URL, nor pending timers, which
+// were scheduled against the state being replaced.
+
+/// Serialize the state into `buf`. False (buf cleared) if it doesn't fit.
+pub fn export_state(state: &AppState, buf: &mut Buf) -> bool {
+    buf.clear();
+    buf.extend(MAGIC);
+    buf.extend(&state.count.to_le_bytes());
+    buf.extend(&(state.msg_count() as u32).to_le_bytes());
+    let mut i = 0;
+    while i < state.msg_count() {
+        let m = state.msg_at(i);
+        let author = &m.author_bytes()[..m.author_bytes().len().min(255)];
+        let text = &m.text_bytes()[..m.text_bytes().len().min(65535)];
+        buf.push(author.len() as u8);
+        buf.extend(author);
+        buf.extend(&(text.len() as u16).to_le_bytes());
+        buf.extend(text);
+        i += 1;
+    }
+    if buf.overflowed() {
+        buf.clear();
+        return false;
+    }
+    true
+}
+
+/// Replace the state with one written by `export_state`. The input is fully
+/// validated first; on failure the state is left untouched and false returned.
+pub fn import_state(state: &mut AppState, bytes: &[u8]) -> bool {
+    let mut r = Reader { b: bytes, i: 0 };
+    if r.take(MAGIC.len()) != Some(MAGIC.as_slice()) {
+        return false;
+    }
+    let (Some(count), Some(n)) = (r.take(4), r.take(4)) else { return false };
+    let count = i32::from_le_bytes([count[0], count[1], count[2], count[3]]);
+    let n = u32::from_le_bytes([n[0], n[1], n[2], n[3]]) as usize;
+    let messages = r.i;
+
+    let mut i = 0;
+    while i < n {
+        if r.message().is_none() {
+            return false;
+        }
+        i += 1;
+    }
+    if r.i != bytes.len() {
+        return false;
+    }
+
+    // The route comes from the URL being loaded, not from the saved state
+    let route = state.route;
+    let now = state.timers.now();
+    *state = AppState::new();
+    state.route = route;
+    state.timers.set_now(now);
+    state.count = count;
+    r.i = messages;
+    while let Some((author, text)) = r.message() {
+        state.push_message(author, text);
+    }
+    true
+}
+
+struct Reader<'a> {
+    b: &'a [u8],
+    i: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.b.get(self.i..self.i + n)?;
+        self.i += n;
+        Some(bytes)
+    }
+
+    fn message(&mut self) -> Option<(&'a [u8], &'a [u8])> {
+        let alen = self.take(1)?[0] as usize;
+        let author = self.take(alen)?;
+        let tlen = self.take(2)?;
+        let text = self.take(u16::from_le_bytes([tlen[0], tlen[1]]) as usize)?;
+        Some((author, text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> AppState {
+        let mut state = AppState::new();
+        state.count = -7;
+        state.push_message(b"ann", b"hello");
+        state.push_message(b"", b"line\nbreak");
+        state
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut buf = Buf::new();
+        assert!(export_state(&sample(), &mut buf));
+
+        let mut state = AppState::new();
+        state.route.set(b"/about");
+        state.checkpoint();
+        state.count = 3;
+        assert!(import_state(&mut state, buf.as_bytes()));
+        assert_eq!(state.count, -7);
+        assert_eq!(state.msg_count(), 2);
+        assert_eq!(state.msg_at(0).author_bytes(), b"ann");
+        assert_eq!(state.msg_at(1).text_bytes(), b"line\nbreak");
+        // The route is kept and the history is not carried over
+        assert_eq!(state.route.as_bytes(), b"/about");
+        assert!(!state.can_undo());
+
+        let mut again = Buf::new();
+        assert!(export_state(&state, &mut again));
+        assert_eq!(again.as_bytes(), buf.as_bytes());
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        let mut buf = Buf::new();
+        assert!(export_state(&sample(), &mut buf));
+        let good = buf.as_bytes();
+
+        let mut wrong_magic = [0u8; 64];
+        wrong_magic[..good.len()].copy_from_slice(good);
+        wrong_magic[3] = b'2';
+        let mut trailing = [0u8; 64];
+        trailing[..good.len()].copy_from_slice(good);
+        let mut too_many = [0u8; 64];
+        too_many[..good.len()].copy_from_slice(good);
+        too_many[8] = 3;
+
+        let bad: [&[u8]; 7] = [
+            b"",
+            b"MRS",
+            b"{\"count\":1}",
+            &wrong_magic[..good.len()],
+            &good[..good.len() - 1],
+            &trailing[..good.len() + 1],
+            &too_many[..good.len()],
+        ];
+        for bytes in bad {
+            let mut state = AppState::new();
+            state.count = 5;
+            state.push_message(b"me", b"kept");
+            assert!(!import_state(&mut state, bytes));
+            assert_eq!(state.count, 5);
+            assert_eq!(state.msg_count(), 1);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Action registry — name → handler, with typed access to the payload.
+//!
+//! Adding an action is one `register` (or `with`) call; handlers read their
+//! payload through `Payload` instead of scanning the input bytes by hand.
+
+use crate::json;
+use crate::state::AppState;
+
+/// Most actions a registry holds.
+pub const MAX_ACTIONS: usize = 32;
+
+/// Action handler: mutate state from the action's payload.
+pub type Handler = fn(&mut AppState, &Payload);
+
+/// The `payload` object of an action (empty if the action had none).
+pub struct Payload<'a> {
+    raw: &'a [u8],
+}
+
+impl<'a> Payload<'a> {
+    pub fn new(raw: &'a [u8]) -> Self {
+        Self { raw }
+    }
+
+    /// Raw JSON of the whole payload.
+    pub fn raw(&self) -> &'a [u8] { self.raw }
+
+    /// Raw JSON value of a payload field.
+    pub fn field(&self, key: &str) -> Option<&'a [u8]> {
+        json::field(self.raw, key.as_bytes())
+    }
+
+    /// String field, unescaped into `out` (truncated to its length).
+    pub fn str<'b>(&self, key: &str, out: &'b mut [u8]) -> Option<&'b [u8]> {
+        json::str_field(self.raw, key.as_bytes(), out)
+    }
+
+    /// Integer field.
+    pub fn int(&self, key: &str) -> Option<i64> {
+        json::int_field(self.raw, key.as_bytes())
+    }
+
+    /// Boolean field.
+    pub fn bool(&self, key: &str) -> Option<bool> {
+        json::bool_field(self.raw, key.as_bytes())
+    }
+}
+
+/// Fixed-capacity action table — no alloc, can be built in a `const`.
+pub struct Registry {
+    names: [&'static str; MAX_ACTIONS],
+    handlers: [Option<Handler>; MAX_ACTIONS],
+    len: usize,
+}
+
+impl Registry {
+    pub const fn new() -> Self {
+        Self { names: [""; MAX_ACTIONS], handlers: [None; MAX_ACTIONS], len: 0 }
+    }
+
+    /// Builder form of `register`, usable in const context.
+    /// Panics if the registry is full.
+    pub const fn with(mut self, name: &'static str, handler: Handler) -> Self {
+        assert!(self.len < MAX_ACTIONS, "action registry full");
+        self.names[self.len] = name;
+        self.handlers[self.len] = Some(handler);
+        self.len += 1;
+        self
+    }
+
+    /// Register (or replace) the handler for `name`.
+    /// Returns false if the registry is full.
+    pub fn register(&mut self, name: &'static str, handler: Handler) -> bool {
+        if let Some(i) = self.find(name.as_bytes()) {
+            self.handlers[i] = Some(handler);
+            return true;
+        }
+        if self.len == MAX_ACTIONS {
+            return false;
+        }
+        self.names[self.len] = name;
+        self.handlers[self.len] = Some(handler);
+        self.len += 1;
+        true
+    }
+
+    /// Handler registered for `name`, if any.
+    pub fn get(&self, name: &[u8]) -> Option<Handler> {
+        self.handlers[self.find(name)?]
+    }
+
+    /// Parse `{"action":"name","payload":{...}}` and run its handler.
+    /// Returns false if the input has no action or the action is unknown.
+    pub fn dispatch(&self, state: &mut AppState, input: &[u8]) -> bool {
+        let Some(handler) = self.handler_for(input) else {
+            return false;
+        };
+        let payload = Payload::new(json::field(input, b"payload").unwrap_or(b"{}"));
+        handler(state, &payload);
+        true
+    }
+
+    /// Dispatch a single action or, for a JSON array of actions, each in
+    /// order as one batch: the state records a single undo step for it.
+    /// A batch is applied whole or not at all: if any action in it is
+    /// unknown, none run. Returns the number of actions handled.
+    pub fn dispatch_batch(&self, state: &mut AppState, input: &[u8]) -> usize {
+        if !json::is_array(input) {
+            return self.dispatch(state, input) as usize;
+        }
+        if !json::elements(input).all(|a| self.handler_for(a).is_some()) {
+            return 0;
+        }
+        state.begin_batch();
+        let handled = json::elements(input).filter(|a| self.dispatch(state, a)).count();
+        state.end_batch();
+        handled
+    }
+
+    /// Handler for the `action` named in `{"action":"name",...}`.
+    fn handler_for(&self, input: &[u8]) -> Option<Handler> {
+        let mut name = [0u8; 64];
+        self.get(json::str_field(input, b"action", &mut name)?)
+    }
+
+    fn find(&self, name: &[u8]) -> Option<usize> {
+        let mut i = 0;
+        while i < self.len {
+            if self.names[i].as_bytes() == name { return Some(i); }
+            i += 1;
+        }
+        None
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self { Self::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ACTIONS;
+
+    #[test]
+    fn test_batch_with_unknown_action_is_not_applied() {
+        let mut state = AppState::new();
+        let batch = br#"[{"action":"increment"},{"action":"nope"},{"action":"increment"}]"#;
+        assert_eq!(ACTIONS.dispatch_batch(&mut state, batch), 0);
+        assert_eq!(state.count, 0);
+        assert!(!state.can_undo());
+
+        let batch = br#"[{"action":"increment"},{"payload":{}}]"#;
+        assert_eq!(ACTIONS.dispatch_batch(&mut state, batch), 0);
+        assert_eq!(state.count, 0);
+    }
+
+    #[test]
+    fn test_batch_is_one_undo_step() {
+        let mut state = AppState::new();
+        ACTIONS.dispatch(&mut state, br#"{"action":"increment"}"#);
+        let batch = br#"[{"action":"increment"},{"action":"send_message","payload":{"text":"hi"}},{"action":"increment"}]"#;
+        assert_eq!(ACTIONS.dispatch_batch(&mut state, batch), 3);
+        assert_eq!((state.count, state.msg_count()), (3, 1));
+
+        assert!(state.undo());
+        assert_eq!((state.count, state.msg_count()), (1, 0));
+        assert!(state.redo());
+        assert_eq!((state.count, state.msg_count()), (3, 1));
+        assert!(state.undo());
+        assert!(state.undo());
+        assert_eq!(state.count, 0);
+        assert!(!state.can_undo());
+    }
+}
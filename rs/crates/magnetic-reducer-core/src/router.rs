@@ -0,0 +1,82 @@
+//! Route table — page renderers per path, for multi-page apps.
+//!
+//! The `navigate` action sets `AppState::route`; rendering then picks the
+//! page registered for it. Patterns are exact paths (`/about`) or prefixes
+//! ending in `/*` (`/docs/*` matches `/docs` and everything below it).
+
+use crate::buf::Buf;
+use crate::state::AppState;
+
+/// Most pages a router holds.
+pub const MAX_PAGES: usize = 16;
+
+/// Renders a page into the buffer (clearing it first).
+pub type View = fn(&AppState, &mut Buf);
+
+/// A page's two renderers: JSON DOM snapshot and SSR HTML.
+#[derive(Clone, Copy)]
+pub struct Page {
+    pub snapshot: View,
+    pub html: View,
+}
+
+/// Fixed-capacity path → page table — no alloc, can be built in a `const`.
+pub struct Router {
+    paths: [&'static str; MAX_PAGES],
+    pages: [Option<Page>; MAX_PAGES],
+    len: usize,
+    not_found: Page,
+}
+
+impl Router {
+    /// Empty router rendering `not_found` for every path.
+    pub const fn new(not_found: Page) -> Self {
+        Self { paths: [""; MAX_PAGES], pages: [None; MAX_PAGES], len: 0, not_found }
+    }
+
+    /// Add a page. Earlier pages win when patterns overlap.
+    /// Panics if the router is full.
+    pub const fn page(mut self, path: &'static str, page: Page) -> Self {
+        assert!(self.len < MAX_PAGES, "router full");
+        self.paths[self.len] = path;
+        self.pages[self.len] = Some(page);
+        self.len += 1;
+        self
+    }
+
+    /// Page for `path` (the not-found page if nothing matches).
+    pub fn find(&self, path: &[u8]) -> Page {
+        let mut i = 0;
+        while i < self.len {
+            if let Some(page) = self.pages[i] {
+                if matches(self.paths[i].as_bytes(), path) { return page; }
+            }
+            i += 1;
+        }
+        self.not_found
+    }
+
+    /// Render the current route's snapshot.
+    pub fn render(&self, state: &AppState, buf: &mut Buf) {
+        (self.find(state.route.as_bytes()).snapshot)(state, buf);
+    }
+
+    /// Render the current route's SSR HTML.
+    pub fn render_html(&self, state: &AppState, buf: &mut Buf) {
+        (self.find(state.route.as_bytes()).html)(state, buf);
+    }
+}
+
+/// Pattern match, ignoring a trailing slash on the path.
+fn matches(pattern: &[u8], path: &[u8]) -> bool {
+    let path = match path {
+        [rest @ .., b'/'] if !rest.is_empty() => rest,
+        _ => path,
+    };
+    match pattern {
+        [prefix @ .., b'/', b'*'] => {
+            path == prefix || (path.starts_with(prefix) && path.get(prefix.len()) == Some(&b'/'))
+        }
+        _ => pattern == path,
+    }
+}
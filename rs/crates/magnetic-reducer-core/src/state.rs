@@ -1,3 +1,63 @@
+use crate::timer::Timers;
+
+// ---------------------------------------------------------------------------
+// Current route (both builds)
+// ---------------------------------------------------------------------------
+const ROUTE_CAP: usize = 128;
+
+/// Path of the current page, without query string or fragment.
+#[derive(Clone, Copy)]
+pub struct Route {
+    data: [u8; ROUTE_CAP],
+    len: usize,
+}
+
+impl Route {
+    /// The `/` route.
+    pub const fn root() -> Self {
+        let mut data = [0u8; ROUTE_CAP];
+        data[0] = b'/';
+        Self { data, len: 1 }
+    }
+
+    /// Set from a navigate path (`/a/b?x=1#top` → `/a/b`), truncated to
+    /// ROUTE_CAP bytes. An empty path is `/`.
+    pub fn set(&mut self, path: &[u8]) {
+        let mut end = 0;
+        while end < path.len() && path[end] != b'?' && path[end] != b'#' { end += 1; }
+        if end == 0 {
+            *self = Self::root();
+            return;
+        }
+        let len = if end > ROUTE_CAP { ROUTE_CAP } else { end };
+        self.data[..len].copy_from_slice(&path[..len]);
+        self.len = len;
+    }
+
+    pub fn as_bytes(&self) -> &[u8] { &self.data[..self.len] }
+}
+
+// ---------------------------------------------------------------------------
+// Scheduled actions (both builds)
+// ---------------------------------------------------------------------------
+impl AppState {
+    /// Dispatch `action` (`{"action":...}` JSON) `delay_ms` from now, on a
+    /// later `tick`. Returns false if it could not be queued.
+    pub fn schedule(&mut self, action: &[u8], delay_ms: u64) -> bool {
+        self.timers.schedule(action, delay_ms)
+    }
+}
+
+/// Undo grouping while a batch of actions is dispatched: one step per batch.
+#[derive(Clone, Copy, PartialEq)]
+enum Batch {
+    Off,
+    /// In a batch, no undo step recorded yet
+    Open,
+    /// In a batch, its undo step already recorded
+    Recorded,
+}
+
 // ---------------------------------------------------------------------------
 // std builds: dynamic Vec-backed state (server / production)
 // ---------------------------------------------------------------------------
@@ -5,6 +65,7 @@
 extern crate alloc;
 
 #[cfg(feature = "std")]
+#[derive(Clone)]
 pub struct Message {
     pub author: alloc::vec::Vec<u8>,
     pub text: alloc::vec::Vec<u8>,
@@ -19,18 +80,91 @@ impl Message {
     pub fn text_bytes(&self) -> &[u8] { &self.text }
 }
 
+#[cfg(feature = "std")]
+const HISTORY_CAP: usize = 50;
+
+/// Undoable part of the state.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+struct Saved {
+    count: i32,
+    messages: alloc::vec::Vec<Message>,
+}
+
 #[cfg(feature = "std")]
 pub struct AppState {
     pub count: i32,
     pub messages: alloc::vec::Vec<Message>,
+    pub route: Route,
+    pub timers: Timers,
+    batch: Batch,
+    undo: alloc::collections::VecDeque<Saved>,
+    redo: alloc::vec::Vec<Saved>,
 }
 
 #[cfg(feature = "std")]
 impl AppState {
     pub fn new() -> Self {
-        Self { count: 0, messages: alloc::vec::Vec::new() }
+        Self {
+            count: 0,
+            messages: alloc::vec::Vec::new(),
+            route: Route::root(),
+            timers: Timers::new(),
+            batch: Batch::Off,
+            undo: alloc::collections::VecDeque::new(),
+            redo: alloc::vec::Vec::new(),
+        }
+    }
+
+    fn save(&self) -> Saved {
+        Saved { count: self.count, messages: self.messages.clone() }
+    }
+
+    fn restore(&mut self, saved: Saved) {
+        self.count = saved.count;
+        self.messages = saved.messages;
+    }
+
+    /// Record the current state as an undo step. Call before an undoable
+    /// change; clears the redo steps. Keeps the last HISTORY_CAP steps.
+    /// Within a batch only the first call records a step.
+    pub fn checkpoint(&mut self) {
+        match self.batch {
+            Batch::Recorded => return,
+            Batch::Open => self.batch = Batch::Recorded,
+            Batch::Off => {}
+        }
+        if self.undo.len() == HISTORY_CAP {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(self.save());
+        self.redo.clear();
+    }
+
+    /// Step back. Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(prev) = self.undo.pop_back() else { return false };
+        self.redo.push(self.save());
+        self.restore(prev);
+        true
     }
 
+    /// Step forward again. Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(next) = self.redo.pop() else { return false };
+        self.undo.push_back(self.save());
+        self.restore(next);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool { !self.undo.is_empty() }
+
+    pub fn can_redo(&self) -> bool { !self.redo.is_empty() }
+
+    pub(crate) fn begin_batch(&mut self) { self.batch = Batch::Open; }
+
+    pub(crate) fn end_batch(&mut self) { self.batch = Batch::Off; }
+
     pub fn msg_count(&self) -> usize { self.messages.len() }
 
     pub fn msg_at(&self, i: usize) -> &Message { &self.messages[i] }
@@ -53,6 +187,7 @@ impl Default for AppState {
 // no_std builds: fixed-buffer state (WASM offline fallback)
 // ---------------------------------------------------------------------------
 #[cfg(not(feature = "std"))]
+#[derive(Clone, Copy)]
 pub struct Message {
     pub author: [u8; 32],
     pub author_len: usize,
@@ -82,11 +217,45 @@ impl Message {
 #[cfg(not(feature = "std"))]
 const MAX_MESSAGES: usize = 20;
 
+// Each step copies the whole state (~6 KB), so keep few in WASM
+#[cfg(not(feature = "std"))]
+const HISTORY_CAP: usize = 4;
+
+/// Undoable part of the state.
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy)]
+struct Saved {
+    count: i32,
+    messages: [Message; MAX_MESSAGES],
+    msg_len: usize,
+}
+
+#[cfg(not(feature = "std"))]
+impl Saved {
+    const fn empty() -> Self {
+        Self {
+            count: 0,
+            messages: { const E: Message = Message::empty(); [E; MAX_MESSAGES] },
+            msg_len: 0,
+        }
+    }
+}
+
 #[cfg(not(feature = "std"))]
 pub struct AppState {
     pub count: i32,
     messages: [Message; MAX_MESSAGES],
     msg_len: usize,
+    pub route: Route,
+    pub timers: Timers,
+    batch: Batch,
+    // Undo steps: ring of undo_len entries from undo_start (oldest first)
+    undo: [Saved; HISTORY_CAP],
+    undo_start: usize,
+    undo_len: usize,
+    // Redo steps: stack, most recent undo last
+    redo: [Saved; HISTORY_CAP],
+    redo_len: usize,
 }
 
 #[cfg(not(feature = "std"))]
@@ -96,9 +265,84 @@ impl AppState {
             count: 0,
             messages: { const E: Message = Message::empty(); [E; MAX_MESSAGES] },
             msg_len: 0,
+            route: Route::root(),
+            timers: Timers::new(),
+            batch: Batch::Off,
+            undo: { const E: Saved = Saved::empty(); [E; HISTORY_CAP] },
+            undo_start: 0,
+            undo_len: 0,
+            redo: { const E: Saved = Saved::empty(); [E; HISTORY_CAP] },
+            redo_len: 0,
+        }
+    }
+
+    fn save(&self) -> Saved {
+        Saved { count: self.count, messages: self.messages, msg_len: self.msg_len }
+    }
+
+    fn restore(&mut self, saved: &Saved) {
+        self.count = saved.count;
+        self.messages = saved.messages;
+        self.msg_len = saved.msg_len;
+    }
+
+    /// Push an undo step, dropping the oldest when full.
+    fn push_undo(&mut self) {
+        let i = (self.undo_start + self.undo_len) % HISTORY_CAP;
+        self.undo[i] = self.save();
+        if self.undo_len == HISTORY_CAP {
+            self.undo_start = (self.undo_start + 1) % HISTORY_CAP;
+        } else {
+            self.undo_len += 1;
+        }
+    }
+
+    /// Record the current state as an undo step. Call before an undoable
+    /// change; clears the redo steps. Keeps the last HISTORY_CAP steps.
+    /// Within a batch only the first call records a step.
+    pub fn checkpoint(&mut self) {
+        match self.batch {
+            Batch::Recorded => return,
+            Batch::Open => self.batch = Batch::Recorded,
+            Batch::Off => {}
+        }
+        self.push_undo();
+        self.redo_len = 0;
+    }
+
+    /// Step back. Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        if self.undo_len == 0 {
+            return false;
+        }
+        self.undo_len -= 1;
+        self.redo[self.redo_len] = self.save();
+        self.redo_len += 1;
+        let prev = self.undo[(self.undo_start + self.undo_len) % HISTORY_CAP];
+        self.restore(&prev);
+        true
+    }
+
+    /// Step forward again. Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        if self.redo_len == 0 {
+            return false;
         }
+        self.redo_len -= 1;
+        self.push_undo();
+        let next = self.redo[self.redo_len];
+        self.restore(&next);
+        true
     }
 
+    pub fn can_undo(&self) -> bool { self.undo_len > 0 }
+
+    pub fn can_redo(&self) -> bool { self.redo_len > 0 }
+
+    pub(crate) fn begin_batch(&mut self) { self.batch = Batch::Open; }
+
+    pub(crate) fn end_batch(&mut self) { self.batch = Batch::Off; }
+
     pub fn msg_count(&self) -> usize { self.msg_len }
 
     pub fn msg_at(&self, i: usize) -> &Message { &self.messages[i] }
@@ -119,3 +363,76 @@ impl AppState {
         self.msg_len += 1;
     }
 }
+
+#[cfg(not(feature = "std"))]
+impl Default for AppState {
+    fn default() -> Self { Self::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(state: &mut AppState) {
+        state.checkpoint();
+        state.count += 1;
+    }
+
+    #[test]
+    fn test_history_keeps_the_last_steps() {
+        let mut state = AppState::new();
+        for _ in 0..HISTORY_CAP + 3 {
+            step(&mut state);
+        }
+        let mut undone = 0;
+        while state.undo() {
+            undone += 1;
+        }
+        assert_eq!(undone, HISTORY_CAP);
+        assert_eq!(state.count, 3);
+
+        let mut redone = 0;
+        while state.redo() {
+            redone += 1;
+        }
+        assert_eq!(redone, HISTORY_CAP);
+        assert_eq!(state.count, HISTORY_CAP as i32 + 3);
+    }
+
+    #[test]
+    fn test_new_step_clears_redo() {
+        let mut state = AppState::new();
+        assert!(!state.undo());
+        step(&mut state);
+        step(&mut state);
+        assert!(state.undo());
+        assert!(state.can_redo());
+        step(&mut state);
+        assert!(!state.can_redo());
+        assert!(!state.redo());
+        assert_eq!(state.count, 2);
+    }
+
+    #[test]
+    fn test_undo_restores_messages() {
+        let mut state = AppState::new();
+        state.checkpoint();
+        state.push_message(b"a", b"one");
+        state.checkpoint();
+        state.push_message(b"b", b"two");
+        assert!(state.undo());
+        assert_eq!(state.msg_count(), 1);
+        assert_eq!(state.msg_at(0).text_bytes(), b"one");
+        assert!(state.redo());
+        assert_eq!(state.msg_at(1).author_bytes(), b"b");
+    }
+
+    #[test]
+    fn test_route_drops_query() {
+        let mut route = Route::root();
+        route.set(b"/a/b?x=1#top");
+        assert_eq!(route.as_bytes(), b"/a/b");
+        route.set(b"?x=1");
+        assert_eq!(route.as_bytes(), b"/");
+    }
+}
@@ -0,0 +1,152 @@
+//! Scheduled actions — wake-ups requested by reducers, driven by the host.
+//!
+//! A handler calls `AppState::schedule` with an action and a delay; the
+//! rendered output then carries a `"wake"` member (ms until the next timer
+//! is due) and the host calls `tick(now_ms)` once that time has passed,
+//! which dispatches every due action. No threads or app-specific timers.
+
+/// Most timers pending at once.
+pub const MAX_TIMERS: usize = 8;
+
+/// Longest scheduled action, in bytes of JSON.
+pub const ACTION_CAP: usize = 128;
+
+#[derive(Clone, Copy)]
+struct Timer {
+    due: u64,
+    action: [u8; ACTION_CAP],
+    len: usize,
+}
+
+impl Timer {
+    const fn empty() -> Self {
+        Self { due: 0, action: [0u8; ACTION_CAP], len: 0 }
+    }
+}
+
+/// Fixed-capacity timer list with the host's clock, in milliseconds.
+pub struct Timers {
+    now: u64,
+    list: [Timer; MAX_TIMERS],
+    len: usize,
+}
+
+impl Timers {
+    pub const fn new() -> Self {
+        Self { now: 0, list: [Timer::empty(); MAX_TIMERS], len: 0 }
+    }
+
+    /// The host clock as of the last `set_now` (or `tick`).
+    pub fn now(&self) -> u64 { self.now }
+
+    /// Advance the clock. Delays passed to `schedule` count from here.
+    pub fn set_now(&mut self, now_ms: u64) {
+        self.now = now_ms;
+    }
+
+    /// Queue `action` (`{"action":...}` JSON, or an array of them) to be
+    /// dispatched `delay_ms` after the current clock. Returns false if
+    /// the list is full or the action is longer than ACTION_CAP.
+    pub fn schedule(&mut self, action: &[u8], delay_ms: u64) -> bool {
+        if self.len == MAX_TIMERS || action.len() > ACTION_CAP {
+            return false;
+        }
+        let t = &mut self.list[self.len];
+        t.due = self.now.saturating_add(delay_ms);
+        t.action[..action.len()].copy_from_slice(action);
+        t.len = action.len();
+        self.len += 1;
+        true
+    }
+
+    /// Drop every pending timer for exactly `action`. Returns how many.
+    pub fn cancel(&mut self, action: &[u8]) -> usize {
+        let before = self.len;
+        let mut i = 0;
+        while i < self.len {
+            if &self.list[i].action[..self.list[i].len] == action {
+                self.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        before - self.len
+    }
+
+    /// Ms from the clock until the next timer is due (0 if overdue).
+    pub fn wake_in(&self) -> Option<u64> {
+        let due = self.list[..self.len].iter().map(|t| t.due).min()?;
+        Some(due.saturating_sub(self.now))
+    }
+
+    /// Remove the earliest due timer (by due time, then schedule order)
+    /// and copy its action into `out`. Returns the action's length.
+    pub fn pop_due(&mut self, out: &mut [u8; ACTION_CAP]) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        for (i, t) in self.list[..self.len].iter().enumerate() {
+            if t.due <= self.now && best.is_none_or(|b| t.due < self.list[b].due) {
+                best = Some(i);
+            }
+        }
+        let i = best?;
+        let len = self.list[i].len;
+        out[..len].copy_from_slice(&self.list[i].action[..len]);
+        self.remove(i);
+        Some(len)
+    }
+
+    pub fn len(&self) -> usize { self.len }
+
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Remove keeping order, so equal due times fire first-scheduled first.
+    fn remove(&mut self, i: usize) {
+        self.list.copy_within(i + 1..self.len, i);
+        self.len -= 1;
+    }
+}
+
+impl Default for Timers {
+    fn default() -> Self { Self::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_due_in_order() {
+        let mut timers = Timers::new();
+        assert_eq!(timers.wake_in(), None);
+        assert!(timers.schedule(b"b", 20));
+        assert!(timers.schedule(b"a", 10));
+        assert!(timers.schedule(b"c", 20));
+        assert_eq!(timers.wake_in(), Some(10));
+
+        let mut out = [0u8; ACTION_CAP];
+        assert_eq!(timers.pop_due(&mut out), None);
+        timers.set_now(25);
+        assert_eq!(timers.wake_in(), Some(0));
+        let mut fired = [0u8; 3];
+        for slot in fired.iter_mut() {
+            let len = timers.pop_due(&mut out).unwrap();
+            assert_eq!(len, 1);
+            *slot = out[0];
+        }
+        assert_eq!(&fired, b"abc");
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_and_capacity() {
+        let mut timers = Timers::new();
+        for _ in 0..MAX_TIMERS - 1 {
+            assert!(timers.schedule(b"x", 5));
+        }
+        assert!(timers.schedule(b"y", 5));
+        assert!(!timers.schedule(b"z", 5));
+        assert!(!Timers::new().schedule(&[b' '; ACTION_CAP + 1], 5));
+        assert_eq!(timers.cancel(b"x"), MAX_TIMERS - 1);
+        assert_eq!(timers.len(), 1);
+    }
+}
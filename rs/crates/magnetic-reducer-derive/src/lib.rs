@@ -0,0 +1,254 @@
+//! magnetic-reducer-derive — `#[derive(MagneticRender)]` for reducer state
+//!
+//! Generates `RenderNode::render_node` (the struct as one JSON DOM node) and
+//! an inherent `render(&self, &mut Buf)` writing a full `{"root":...}`
+//! snapshot, for the magnetic-reducer-core `Buf`. All brace balancing is
+//! done here, once; static JSON is precomputed into byte literals.
+//!
+//! ```ignore
+//! #[derive(MagneticRender)]
+//! #[magnetic(tag = "div", class = "app")]
+//! struct Board {
+//!     #[magnetic(tag = "h1", key = "title", prefix = "Count: ")]
+//!     count: i32,                  // {"tag":"h1","key":"title","text":"Count: 3"}
+//!     #[magnetic(list, tag = "ul", key = "tasks")]
+//!     tasks: Vec<Task>,            // children: each Task's render_node
+//!     #[magnetic(skip)]
+//!     next_id: u32,
+//! }
+//!
+//! #[derive(MagneticRender)]
+//! #[magnetic(tag = "li", key_field = "id", click = "toggle")]
+//! struct Task { #[magnetic(skip)] id: u32, title: String }
+//! ```
+//!
+//! Struct attributes: `tag` (default `div`), `class`, `key` or `key_field`
+//! (a field rendered as the key), and events `click`/`submit`/`input`/`change`.
+//!
+//! Field attributes: text leaves by default (`TextValue` fields, `tag`
+//! default `span`, optional `prefix`/`suffix`); `list` renders each item's
+//! `RenderNode` as children (anything with `.iter()`); `node` renders a
+//! nested `RenderNode` in place; `skip` leaves the field out. Text and list
+//! fields take the same `tag`/`class`/`key`/event attributes as structs.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, Ident, LitByteStr, LitStr};
+
+#[proc_macro_derive(MagneticRender, attributes(magnetic))]
+pub fn derive_magnetic_render(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+const EVENTS: &[&str] = &["click", "submit", "input", "change"];
+
+#[derive(Default)]
+struct NodeAttrs {
+    tag: Option<String>,
+    class: Option<String>,
+    key: Option<String>,
+    key_field: Option<Ident>,
+    events: Vec<(String, String)>,
+    prefix: String,
+    suffix: String,
+    skip: bool,
+    list: bool,
+    node: bool,
+}
+
+fn parse_attrs(attrs: &[Attribute]) -> syn::Result<NodeAttrs> {
+    let mut out = NodeAttrs::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("magnetic")) {
+        attr.parse_nested_meta(|meta| {
+            let ident = meta.path.get_ident().map(|i| i.to_string()).unwrap_or_default();
+            match ident.as_str() {
+                "skip" => out.skip = true,
+                "list" => out.list = true,
+                "node" => out.node = true,
+                "tag" => out.tag = Some(meta.value()?.parse::<LitStr>()?.value()),
+                "class" => out.class = Some(meta.value()?.parse::<LitStr>()?.value()),
+                "key" => out.key = Some(meta.value()?.parse::<LitStr>()?.value()),
+                "key_field" => {
+                    let name = meta.value()?.parse::<LitStr>()?;
+                    out.key_field = Some(Ident::new(&name.value(), name.span()));
+                }
+                "prefix" => out.prefix = meta.value()?.parse::<LitStr>()?.value(),
+                "suffix" => out.suffix = meta.value()?.parse::<LitStr>()?.value(),
+                ev if EVENTS.contains(&ev) => {
+                    out.events.push((ev.to_string(), meta.value()?.parse::<LitStr>()?.value()));
+                }
+                _ => return Err(meta.error("unknown magnetic attribute")),
+            }
+            Ok(())
+        })?;
+    }
+    Ok(out)
+}
+
+/// Quoted, JSON-escaped string literal.
+fn json_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    out.push_str(&json_escape(s));
+    out.push('"');
+    out
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Generated statements: runs of static JSON merged into one `extend`.
+struct Writer {
+    stmts: Vec<TokenStream2>,
+    pending: String,
+}
+
+impl Writer {
+    fn new() -> Self {
+        Self { stmts: Vec::new(), pending: String::new() }
+    }
+
+    fn lit(&mut self, s: &str) {
+        self.pending.push_str(s);
+    }
+
+    fn stmt(&mut self, tokens: TokenStream2) {
+        self.flush();
+        self.stmts.push(tokens);
+    }
+
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let bytes = LitByteStr::new(self.pending.as_bytes(), Span::call_site());
+        self.stmts.push(quote! { buf.extend(#bytes); });
+        self.pending.clear();
+    }
+
+    fn finish(mut self) -> Vec<TokenStream2> {
+        self.flush();
+        self.stmts
+    }
+}
+
+/// `{"tag":..,"key":..,"attrs":{..},"events":{..}` — the node left open.
+fn open_node(w: &mut Writer, attrs: &NodeAttrs, default_tag: &str) {
+    w.lit("{\"tag\":");
+    w.lit(&json_str(attrs.tag.as_deref().unwrap_or(default_tag)));
+    if let Some(field) = &attrs.key_field {
+        w.lit(",\"key\":\"");
+        w.stmt(quote! { ::magnetic_reducer_core::node::TextValue::write_text(&self.#field, buf); });
+        w.lit("\"");
+    } else if let Some(key) = &attrs.key {
+        w.lit(",\"key\":");
+        w.lit(&json_str(key));
+    }
+    if let Some(class) = &attrs.class {
+        w.lit(",\"attrs\":{\"class\":");
+        w.lit(&json_str(class));
+        w.lit("}");
+    }
+    if !attrs.events.is_empty() {
+        w.lit(",\"events\":{");
+        for (i, (ev, action)) in attrs.events.iter().enumerate() {
+            if i > 0 {
+                w.lit(",");
+            }
+            w.lit(&json_str(ev));
+            w.lit(":");
+            w.lit(&json_str(action));
+        }
+        w.lit("}");
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(input, "MagneticRender only supports structs"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(input, "MagneticRender needs named fields"));
+    };
+
+    let container = parse_attrs(&input.attrs)?;
+    if container.skip || container.list || container.node || !container.prefix.is_empty() || !container.suffix.is_empty() {
+        return Err(syn::Error::new_spanned(input, "skip/list/node/prefix/suffix are field attributes"));
+    }
+
+    let mut w = Writer::new();
+    open_node(&mut w, &container, "div");
+    w.lit(",\"children\":[");
+    let mut first = true;
+    for field in &fields.named {
+        let attrs = parse_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        if attrs.key_field.is_some() {
+            return Err(syn::Error::new_spanned(field, "key_field is a struct attribute"));
+        }
+        if !first {
+            w.lit(",");
+        }
+        first = false;
+
+        let name = field.ident.as_ref().expect("named field");
+        if attrs.node {
+            w.stmt(quote! { ::magnetic_reducer_core::node::RenderNode::render_node(&self.#name, buf); });
+        } else if attrs.list {
+            open_node(&mut w, &attrs, "div");
+            w.lit(",\"children\":[");
+            w.stmt(quote! {
+                for (i, item) in self.#name.iter().enumerate() {
+                    if i > 0 {
+                        buf.push(b',');
+                    }
+                    ::magnetic_reducer_core::node::RenderNode::render_node(item, buf);
+                }
+            });
+            w.lit("]}");
+        } else {
+            open_node(&mut w, &attrs, "span");
+            w.lit(",\"text\":\"");
+            w.lit(&json_escape(&attrs.prefix));
+            w.stmt(quote! { ::magnetic_reducer_core::node::TextValue::write_text(&self.#name, buf); });
+            w.lit(&json_escape(&attrs.suffix));
+            w.lit("\"}");
+        }
+    }
+    w.lit("]}");
+    let stmts = w.finish();
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::magnetic_reducer_core::node::RenderNode for #ident #ty_generics #where_clause {
+            fn render_node(&self, buf: &mut ::magnetic_reducer_core::Buf) {
+                #(#stmts)*
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Render as a full JSON DOM snapshot (`{"root":...}`) into `buf`.
+            pub fn render(&self, buf: &mut ::magnetic_reducer_core::Buf) {
+                ::magnetic_reducer_core::node::render_root(self, buf);
+            }
+        }
+    })
+}
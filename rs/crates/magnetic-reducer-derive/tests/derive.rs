@@ -0,0 +1,71 @@
+use magnetic_reducer_core::node::RenderNode;
+use magnetic_reducer_core::Buf;
+use magnetic_reducer_derive::MagneticRender;
+
+#[derive(MagneticRender)]
+#[magnetic(tag = "li", key_field = "id", class = "task", click = "toggle")]
+struct Task {
+    #[magnetic(skip)]
+    id: u32,
+    title: String,
+    #[magnetic(tag = "input", class = "done")]
+    done: bool,
+}
+
+#[derive(MagneticRender)]
+#[magnetic(class = "header")]
+struct Header {
+    #[magnetic(tag = "h1", key = "title", prefix = "Tasks (", suffix = ")")]
+    count: usize,
+}
+
+#[derive(MagneticRender)]
+#[magnetic(tag = "main", key = "board", class = "app")]
+struct Board {
+    #[magnetic(node)]
+    header: Header,
+    #[magnetic(list, tag = "ul", key = "tasks")]
+    tasks: Vec<Task>,
+    #[magnetic(skip)]
+    next_id: u32,
+}
+
+fn text(buf: &Buf) -> &str {
+    std::str::from_utf8(buf.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_render_board() {
+    let board = Board {
+        header: Header { count: 2 },
+        tasks: vec![
+            Task { id: 1, title: "Write \"docs\"".into(), done: true },
+            Task { id: 7, title: "Ship".into(), done: false },
+        ],
+        next_id: 8,
+    };
+    assert_eq!(board.next_id, 8);
+
+    let mut buf = Buf::new();
+    board.render(&mut buf);
+    assert_eq!(
+        text(&buf),
+        concat!(
+            r#"{"root":{"tag":"main","key":"board","attrs":{"class":"app"},"children":["#,
+            r#"{"tag":"div","attrs":{"class":"header"},"children":[{"tag":"h1","key":"title","text":"Tasks (2)"}]},"#,
+            r#"{"tag":"ul","key":"tasks","children":["#,
+            r#"{"tag":"li","key":"1","attrs":{"class":"task"},"events":{"click":"toggle"},"children":["#,
+            r#"{"tag":"span","text":"Write \"docs\""},{"tag":"input","attrs":{"class":"done"},"text":"true"}]},"#,
+            r#"{"tag":"li","key":"7","attrs":{"class":"task"},"events":{"click":"toggle"},"children":["#,
+            r#"{"tag":"span","text":"Ship"},{"tag":"input","attrs":{"class":"done"},"text":"false"}]}"#,
+            r#"]}]}}"#,
+        )
+    );
+}
+
+#[test]
+fn test_render_node_without_wrapper() {
+    let mut buf = Buf::new();
+    Header { count: 0 }.render_node(&mut buf);
+    assert_eq!(text(&buf), r#"{"tag":"div","attrs":{"class":"header"},"children":[{"tag":"h1","key":"title","text":"Tasks (0)"}]}"#);
+}
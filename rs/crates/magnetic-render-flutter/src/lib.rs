@@ -0,0 +1,366 @@
+//! magnetic-render-flutter — Render Magnetic DomNode trees to Flutter widgets
+//!
+//! Translates the JSON DOM tree into a Dart widget tree.
+//! The output is a complete StatelessWidget class that can be dropped into a
+//! Flutter project; every event is routed through an `onAction(String)` callback.
+//!
+//! Mapping strategy:
+//!   div          → Column (vertical) / Row (if row-like class)
+//!   span         → Text (inline)
+//!   h1..h6       → Text('...', style: Theme.of(context).textTheme.headlineX)
+//!   p            → Text('...')
+//!   button       → ElevatedButton(onPressed: () => onAction('action'), child: Text('label'))
+//!   input        → TextField(decoration: InputDecoration(hintText: '...'))
+//!   form         → Column; its submit button and Enter in its inputs → onAction('submit action')
+//!   a / Link     → TextButton(onPressed: () => onAction('navigate:href'), child: Text('label'))
+//!   img          → Image.network(src, semanticLabel: alt)
+//!   nav          → Row (navigation bar)
+//!   ul/ol        → Column of list items
+//!   li           → Column of its children
+
+use magnetic_dom::DomNode;
+
+/// Render a DomNode tree to a Flutter StatelessWidget class.
+pub fn render_to_flutter(node: &DomNode, class_name: &str) -> String {
+    let mut buf = String::with_capacity(4096);
+
+    // File header
+    buf.push_str("import 'package:flutter/material.dart';\n\n");
+
+    buf.push_str(&format!("class {} extends StatelessWidget {{\n", class_name));
+    buf.push_str("  final void Function(String) onAction;\n\n");
+    buf.push_str(&format!(
+        "  const {}({{super.key, required this.onAction}});\n\n",
+        class_name
+    ));
+    buf.push_str("  @override\n");
+    buf.push_str("  Widget build(BuildContext context) {\n");
+    buf.push_str("    return SingleChildScrollView(\n");
+    buf.push_str("      padding: const EdgeInsets.all(16),\n");
+    buf.push_str("      child: ");
+    write_dart_node(node, &mut buf, 3, None);
+    buf.push_str(",\n");
+    buf.push_str("    );\n");
+    buf.push_str("  }\n");
+    buf.push_str("}\n");
+
+    buf
+}
+
+fn indent(buf: &mut String, depth: usize) {
+    for _ in 0..depth {
+        buf.push_str("  ");
+    }
+}
+
+/// Write a single widget expression. The caller positions the cursor; nested
+/// lines are indented one level deeper than `depth` and the closing paren sits
+/// at `depth`. No trailing comma is written. `submit` is the action of the
+/// enclosing form, if any.
+fn write_dart_node(node: &DomNode, buf: &mut String, depth: usize, submit: Option<&str>) {
+    match node.tag.as_str() {
+        // magnetic:head nodes are not relevant for native
+        "magnetic:head" => buf.push_str("const SizedBox.shrink()"),
+
+        // Headings → Text with text theme style
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let style = match node.tag.as_str() {
+                "h1" => "headlineLarge",
+                "h2" => "headlineMedium",
+                "h3" => "headlineSmall",
+                "h4" => "titleLarge",
+                "h5" => "titleMedium",
+                _ => "titleSmall",
+            };
+            let text = collect_text(node).unwrap_or_default();
+            buf.push_str(&format!(
+                "Text('{}', style: Theme.of(context).textTheme.{})",
+                escape_dart(&text), style
+            ));
+        }
+
+        // Paragraph / span / label → Text
+        "p" | "span" | "label" => match collect_text(node) {
+            Some(text) => buf.push_str(&format!("Text('{}')", escape_dart(&text))),
+            None => buf.push_str("const SizedBox.shrink()"),
+        },
+
+        // Button → ElevatedButton
+        "button" => {
+            let is_submit = node.attrs.as_ref()
+                .and_then(|a| a.get("type"))
+                .is_some_and(|t| t == "submit");
+            // Inside a form, a submit button (or one without its own click
+            // action) submits the form
+            let action = match (node.event("click"), submit) {
+                (Some(_), Some(submit)) if is_submit => submit,
+                (None, Some(submit)) => submit,
+                (action, _) => action.unwrap_or("noop"),
+            };
+            let label = collect_text(node).unwrap_or_default();
+            buf.push_str("ElevatedButton(\n");
+            indent(buf, depth + 1);
+            buf.push_str(&format!("onPressed: () => onAction('{}'),\n", escape_dart(action)));
+            indent(buf, depth + 1);
+            buf.push_str(&format!("child: Text('{}'),\n", escape_dart(&label)));
+            indent(buf, depth);
+            buf.push(')');
+        }
+
+        // Input → TextField
+        "input" => {
+            let placeholder = node.attrs.as_ref()
+                .and_then(|a| a.get("placeholder"))
+                .map(|s| s.as_str())
+                .unwrap_or("");
+            let action = node.event("input").unwrap_or("");
+            buf.push_str("TextField(\n");
+            indent(buf, depth + 1);
+            buf.push_str(&format!(
+                "decoration: InputDecoration(hintText: '{}', border: const OutlineInputBorder()),\n",
+                escape_dart(placeholder)
+            ));
+            if !action.is_empty() {
+                indent(buf, depth + 1);
+                buf.push_str(&format!("onChanged: (_) => onAction('{}'),\n", escape_dart(action)));
+            }
+            // Enter submits the enclosing form, like a browser
+            if let Some(submit) = submit {
+                indent(buf, depth + 1);
+                buf.push_str(&format!("onSubmitted: (_) => onAction('{}'),\n", escape_dart(submit)));
+            }
+            indent(buf, depth);
+            buf.push(')');
+        }
+
+        // Anchor / Link → TextButton with navigate action
+        "a" => {
+            let action = node.event("click")
+                .or_else(|| node.attrs.as_ref()?.get("href").map(|h| h.as_str()))
+                .unwrap_or("");
+            let label = collect_text(node).unwrap_or_default();
+            buf.push_str("TextButton(\n");
+            indent(buf, depth + 1);
+            buf.push_str(&format!("onPressed: () => onAction('{}'),\n", escape_dart(action)));
+            indent(buf, depth + 1);
+            buf.push_str(&format!("child: Text('{}'),\n", escape_dart(&label)));
+            indent(buf, depth);
+            buf.push(')');
+        }
+
+        // Image → Image.network
+        "img" => {
+            let src = node.attrs.as_ref()
+                .and_then(|a| a.get("src"))
+                .map(|s| s.as_str())
+                .unwrap_or("");
+            let alt = node.attrs.as_ref()
+                .and_then(|a| a.get("alt"))
+                .map(|s| s.as_str())
+                .unwrap_or("");
+            buf.push_str(&format!(
+                "Image.network('{}', semanticLabel: '{}')",
+                escape_dart(src), escape_dart(alt)
+            ));
+        }
+
+        // Form → Column; its buttons and inputs submit it
+        "form" => {
+            let action = node.event("submit").unwrap_or("submit");
+            buf.push_str("Column(\n");
+            indent(buf, depth + 1);
+            buf.push_str("crossAxisAlignment: CrossAxisAlignment.stretch,\n");
+            indent(buf, depth + 1);
+            buf.push_str("children: [\n");
+            write_children(node, buf, depth + 2, Some(action));
+            indent(buf, depth + 1);
+            buf.push_str("],\n");
+            indent(buf, depth);
+            buf.push(')');
+        }
+
+        // Nav → Row
+        "nav" => {
+            buf.push_str("Row(\n");
+            indent(buf, depth + 1);
+            buf.push_str("spacing: 8,\n");
+            indent(buf, depth + 1);
+            buf.push_str("children: [\n");
+            write_children(node, buf, depth + 2, submit);
+            indent(buf, depth + 1);
+            buf.push_str("],\n");
+            indent(buf, depth);
+            buf.push(')');
+        }
+
+        // Lists → Column of keyed items
+        "ul" | "ol" => {
+            buf.push_str("Column(\n");
+            indent(buf, depth + 1);
+            buf.push_str("crossAxisAlignment: CrossAxisAlignment.start,\n");
+            indent(buf, depth + 1);
+            buf.push_str("spacing: 8,\n");
+            indent(buf, depth + 1);
+            buf.push_str("children: [\n");
+            for (i, child) in node.children_iter().iter().enumerate() {
+                if child.is_head() { continue; }
+                let key = child.key.clone().unwrap_or_else(|| i.to_string());
+                indent(buf, depth + 2);
+                buf.push_str("KeyedSubtree(\n");
+                indent(buf, depth + 3);
+                buf.push_str(&format!("key: const ValueKey('{}'),\n", escape_dart(&key)));
+                indent(buf, depth + 3);
+                buf.push_str("child: ");
+                write_dart_node(child, buf, depth + 3, submit);
+                buf.push_str(",\n");
+                indent(buf, depth + 2);
+                buf.push_str("),\n");
+            }
+            indent(buf, depth + 1);
+            buf.push_str("],\n");
+            indent(buf, depth);
+            buf.push(')');
+        }
+
+        // Default: div, li and everything else → Column or Row
+        _ => {
+            let is_row = is_row_layout(node);
+            buf.push_str(if is_row { "Row(\n" } else { "Column(\n" });
+            indent(buf, depth + 1);
+            if is_row {
+                buf.push_str("crossAxisAlignment: CrossAxisAlignment.center,\n");
+            } else {
+                buf.push_str("crossAxisAlignment: CrossAxisAlignment.start,\n");
+            }
+            indent(buf, depth + 1);
+            buf.push_str("spacing: 8,\n");
+            indent(buf, depth + 1);
+            buf.push_str("children: [\n");
+
+            // Text content
+            if let Some(text) = &node.text {
+                indent(buf, depth + 2);
+                buf.push_str(&format!("Text('{}'),\n", escape_dart(text)));
+            }
+
+            // Children
+            write_children(node, buf, depth + 2, submit);
+
+            indent(buf, depth + 1);
+            buf.push_str("],\n");
+            indent(buf, depth);
+            buf.push(')');
+        }
+    }
+}
+
+/// Write each child as an element of a `children: [...]` list.
+fn write_children(node: &DomNode, buf: &mut String, depth: usize, submit: Option<&str>) {
+    for child in node.children_iter() {
+        if child.is_head() { continue; }
+        indent(buf, depth);
+        write_dart_node(child, buf, depth, submit);
+        buf.push_str(",\n");
+    }
+}
+
+/// Check if a node should be rendered as a Row (horizontal) vs Column (vertical)
+fn is_row_layout(node: &DomNode) -> bool {
+    if let Some(class) = node.class() {
+        return class.contains("row")
+            || class.contains("flex-row")
+            || class.contains("topnav")
+            || class.contains("add-form")
+            || class.contains("filters")
+            || class.contains("task-card");
+    }
+    matches!(node.tag.as_str(), "nav" | "header")
+}
+
+/// Collect all text content from a node and its children
+fn collect_text(node: &DomNode) -> Option<String> {
+    let mut text = String::new();
+    collect_text_inner(node, &mut text);
+    if text.is_empty() { None } else { Some(text) }
+}
+
+fn collect_text_inner(node: &DomNode, buf: &mut String) {
+    if let Some(t) = &node.text {
+        buf.push_str(t);
+    }
+    for child in node.children_iter() {
+        collect_text_inner(child, buf);
+    }
+}
+
+fn escape_dart(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\'', "\\'")
+        .replace('\n', "\\n")
+        .replace('$', "\\$")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use magnetic_dom::DomNode;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_button_render() {
+        let node = DomNode {
+            tag: "button".into(),
+            key: Some("btn".into()),
+            attrs: None,
+            events: Some(HashMap::from([("click".into(), "increment".into())])),
+            text: Some("+".into()),
+            html: None,
+            children: None,
+        };
+        let dart = render_to_flutter(&node, "TestScreen");
+        assert!(dart.contains("class TestScreen extends StatelessWidget"));
+        assert!(dart.contains("onPressed: () => onAction('increment')"));
+        assert!(dart.contains("child: Text('+')"));
+    }
+
+    #[test]
+    fn test_row_children_and_escaping() {
+        let node = DomNode {
+            tag: "div".into(),
+            key: None,
+            attrs: Some(HashMap::from([("class".into(), "row".into())])),
+            events: None,
+            text: None,
+            html: None,
+            children: Some(vec![
+                DomNode::text("h1", "It's $5"),
+                DomNode::text("p", "body"),
+            ]),
+        };
+        let dart = render_to_flutter(&node, "RowView");
+        assert!(dart.contains("Row("));
+        assert!(dart.contains("Text('It\\'s \\$5', style: Theme.of(context).textTheme.headlineLarge)"));
+        assert!(dart.contains("Text('body'),"));
+    }
+
+    #[test]
+    fn test_form_submits_through_on_action() {
+        let submit = DomNode {
+            attrs: Some(HashMap::from([("type".into(), "submit".into())])),
+            ..DomNode::text("button", "Add")
+        };
+        let node = DomNode {
+            tag: "form".into(),
+            key: None,
+            attrs: None,
+            events: Some(HashMap::from([("submit".into(), "add_task".into())])),
+            text: None,
+            html: None,
+            children: Some(vec![DomNode::text("input", ""), submit]),
+        };
+        let dart = render_to_flutter(&node, "FormView");
+        assert!(dart.contains("onSubmitted: (_) => onAction('add_task'),"));
+        assert!(dart.contains("onPressed: () => onAction('add_task'),"));
+        assert!(!dart.contains("//"));
+    }
+}
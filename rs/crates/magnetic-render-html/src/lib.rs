@@ -30,7 +30,8 @@ pub fn render_page(opts: &PageOptions) -> String {
     let extracted_title = extract_title(&opts.root);
 
     let mut html = String::with_capacity(body_html.len() + 2048);
-    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+    let lang = opts.lang.as_deref().unwrap_or("en");
+    html.push_str(&format!("<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n", escape_attr(lang)));
     html.push_str("<meta charset=\"utf-8\" />\n");
     html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\" />\n");
 
@@ -43,6 +44,9 @@ pub fn render_page(opts: &PageOptions) -> String {
     if let Some(desc) = &opts.description {
         html.push_str(&format!("<meta name=\"description\" content=\"{}\" />\n", escape_attr(desc)));
     }
+    if let Some(token) = &opts.csrf_token {
+        html.push_str(&format!("<meta name=\"csrf-token\" content=\"{}\" />\n", escape_attr(token)));
+    }
 
     // Injected head elements from <Head> (excluding <title> since we handled it above)
     let head_no_title = remove_title_from_head_html(&head_extra);
@@ -105,6 +109,11 @@ pub struct PageOptions {
     /// Inline script blocks injected after external scripts but before SSE bootstrap.
     /// Used for registering client-side renderers for delta mode.
     pub inline_scripts: Vec<String>,
+    /// Emitted as `<meta name="csrf-token">`; magnetic.js sends it back
+    /// with every action POST.
+    pub csrf_token: Option<String>,
+    /// `<html lang>`; "en" when None.
+    pub lang: Option<String>,
 }
 
 fn write_node(node: &DomNode, buf: &mut String) {
@@ -310,4 +319,43 @@ mod tests {
         assert!(html.contains("<input"));
         assert!(!html.contains("</input>"));
     }
+
+    #[test]
+    fn test_csrf_meta() {
+        let page = render_page(&PageOptions {
+            root: DomNode::text("p", "hi"),
+            scripts: vec![],
+            styles: vec![],
+            inline_css: None,
+            sse_url: None,
+            mount_selector: None,
+            wasm_url: None,
+            title: None,
+            description: None,
+            inline_scripts: vec![],
+            csrf_token: Some("a\"b".into()),
+            lang: None,
+        });
+        assert!(page.contains("<meta name=\"csrf-token\" content=\"a&quot;b\" />"));
+        assert!(page.contains("<html lang=\"en\">"));
+    }
+
+    #[test]
+    fn test_lang() {
+        let page = render_page(&PageOptions {
+            root: DomNode::text("p", "salut"),
+            scripts: vec![],
+            styles: vec![],
+            inline_css: None,
+            sse_url: None,
+            mount_selector: None,
+            wasm_url: None,
+            title: None,
+            description: None,
+            inline_scripts: vec![],
+            csrf_token: None,
+            lang: Some("fr-CA".into()),
+        });
+        assert!(page.contains("<html lang=\"fr-CA\">"));
+    }
 }
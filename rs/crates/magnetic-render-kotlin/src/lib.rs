@@ -10,48 +10,184 @@
 //!   h1..h6       → Text(..., style = MaterialTheme.typography.headlineX)
 //!   p            → Text(...)
 //!   button       → Button(onClick = { onAction("action") }) { Text("label") }
-//!   input        → OutlinedTextField(value = "", onValueChange = {}, ...)
-//!   form         → Column (wraps children, submit → onAction)
+//!   input        → OutlinedTextField(value = form["name"], onValueChange = { form["name"] = it })
+//!   form         → Column + FormState; submit → onActionPayload(action, form.payload())
 //!   a / Link     → TextButton(onClick = { onAction("navigate:href") }) { Text("label") }
 //!   img          → AsyncImage(model = src, contentDescription = alt)
 //!   nav          → Row (navigation bar)
 //!   ul/ol        → LazyColumn
 //!   li           → item { Text/Row }
+//!   table        → Column of weighted Rows (th / thead cells in bold)
+//!   data-component="Name" → Name(onAction, onActionPayload), in its own file
+//!                  with `render_to_kotlin_files`
+//!
+//! Accessibility: aria-label → contentDescription, role → Role.X, h1..h6 and
+//! role="heading" → heading(), all via Modifier.semantics { }.
 
+use magnetic_dom::component::{placeholder_name, split_components, COMPONENT_TAG};
+use magnetic_dom::mapping::{fill_template, TagMapping};
 use magnetic_dom::DomNode;
 
 /// Render a DomNode tree to a Jetpack Compose @Composable function.
 pub fn render_to_kotlin(node: &DomNode, fn_name: &str) -> String {
+    render_to_kotlin_with_map(node, fn_name, &TagMapping::default())
+}
+
+/// Like `render_to_kotlin`, with custom tag/class mappings taking precedence
+/// over the built-in ones (see `magnetic_dom::mapping`).
+pub fn render_to_kotlin_with_map(node: &DomNode, fn_name: &str, map: &TagMapping) -> String {
     let mut buf = String::with_capacity(4096);
 
     // File header
     buf.push_str("package com.magnetic.app\n\n");
     buf.push_str("import androidx.compose.foundation.layout.*\n");
+    buf.push_str("import androidx.compose.foundation.text.KeyboardActions\n");
+    buf.push_str("import androidx.compose.foundation.text.KeyboardOptions\n");
     buf.push_str("import androidx.compose.foundation.lazy.LazyColumn\n");
     buf.push_str("import androidx.compose.foundation.lazy.items\n");
     buf.push_str("import androidx.compose.material3.*\n");
     buf.push_str("import androidx.compose.runtime.*\n");
     buf.push_str("import androidx.compose.ui.Alignment\n");
     buf.push_str("import androidx.compose.ui.Modifier\n");
-    buf.push_str("import androidx.compose.ui.unit.dp\n\n");
+    buf.push_str("import androidx.compose.ui.semantics.*\n");
+    buf.push_str("import androidx.compose.ui.text.font.FontWeight\n");
+    buf.push_str("import androidx.compose.ui.text.input.ImeAction\n");
+    buf.push_str("import androidx.compose.ui.unit.dp\n");
+    if contains_tag(node, "img") {
+        buf.push_str("import coil.compose.AsyncImage\n");
+    }
+    for import in &map.imports {
+        buf.push_str(&format!("import {}\n", import));
+    }
+    buf.push('\n');
 
-    buf.push_str(&format!("@Composable\nfun {}(onAction: (String) -> Unit) {{\n", fn_name));
-    write_kotlin_node(node, &mut buf, 1);
+    // onActionPayload carries form / input values; by default it drops the
+    // payload so existing `onAction`-only callers keep working.
+    buf.push_str(&format!("@Composable\nfun {}(\n", fn_name));
+    buf.push_str("    onAction: (String) -> Unit,\n");
+    buf.push_str("    onActionPayload: (String, Map<String, String>) -> Unit = { action, _ -> onAction(action) },\n");
+    buf.push_str(") {\n");
+
+    // Input state holders: one per form, plus `fields` for inputs outside forms
+    let mut forms = Vec::new();
+    collect_forms(node, &mut forms);
+    if has_loose_inputs(node) {
+        buf.push_str("    val fields = remember { FormState() }\n");
+    }
+    for i in 0..forms.len() {
+        buf.push_str(&format!("    val form{} = remember {{ FormState() }}\n", i));
+    }
+
+    write_kotlin_node(node, &mut buf, 1, Ctx { forms: &forms, form: None, map });
     buf.push_str("}\n");
 
+    if !forms.is_empty() || has_loose_inputs(node) {
+        buf.push_str(FORM_STATE);
+    }
+
     buf
 }
 
+/// Render a tree as one file per `data-component` subtree plus an index file
+/// (`{fn_name}.kt`) holding the root composable, which calls into the
+/// components. Returns (file name, source) pairs, index first.
+pub fn render_to_kotlin_files(node: &DomNode, fn_name: &str, map: &TagMapping) -> Vec<(String, String)> {
+    let (index, components) = split_components(node);
+    let mut files = vec![(format!("{}.kt", fn_name), render_to_kotlin_with_map(&index, fn_name, map))];
+    for c in &components {
+        files.push((format!("{}.kt", c.name), render_to_kotlin_with_map(&c.root, &c.name, map)));
+    }
+    files
+}
+
+/// Collects input values so a form submit can send them as the action payload,
+/// the same map magnetic.js builds from FormData on the web.
+const FORM_STATE: &str = "
+private class FormState {
+    private val values = mutableStateMapOf<String, String>()
+
+    operator fun get(name: String): String = values[name] ?: \"\"
+
+    operator fun set(name: String, value: String) {
+        values[name] = value
+    }
+
+    fun payload(): Map<String, String> = values.toMap()
+
+    fun clear() = values.clear()
+}
+";
+
+/// Render context: every form in the tree (document order) and the index of
+/// the form enclosing the current node, plus any custom tag mappings.
+#[derive(Clone, Copy)]
+struct Ctx<'a> {
+    forms: &'a [&'a DomNode],
+    form: Option<usize>,
+    map: &'a TagMapping,
+}
+
+impl Ctx<'_> {
+    /// Kotlin variable holding input state for the current scope
+    fn state_var(&self) -> String {
+        match self.form {
+            Some(i) => format!("form{}", i),
+            None => "fields".to_string(),
+        }
+    }
+
+    /// Kotlin statement submitting the enclosing form, if any
+    fn submit_call(&self) -> Option<String> {
+        let i = self.form?;
+        let action = self.forms[i].event("submit").unwrap_or("submit");
+        Some(format!(
+            "onActionPayload(\"{}\", form{i}.payload()); form{i}.clear()",
+            escape_kotlin(action)
+        ))
+    }
+}
+
+fn collect_forms<'a>(node: &'a DomNode, forms: &mut Vec<&'a DomNode>) {
+    if node.tag == "form" {
+        forms.push(node);
+    }
+    for child in node.children_iter() {
+        collect_forms(child, forms);
+    }
+}
+
+/// Whether the tree has inputs that are not inside a form
+fn has_loose_inputs(node: &DomNode) -> bool {
+    match node.tag.as_str() {
+        "form" => false,
+        "input" => true,
+        _ => node.children_iter().iter().any(has_loose_inputs),
+    }
+}
+
 fn indent(buf: &mut String, depth: usize) {
     for _ in 0..depth {
         buf.push_str("    ");
     }
 }
 
-fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
+fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize, ctx: Ctx) {
+    if let Some(template) = ctx.map.lookup(node) {
+        write_kotlin_template(template, node, buf, depth, ctx);
+        return;
+    }
+
     match node.tag.as_str() {
         // Skip magnetic:head nodes (not relevant for native)
-        "magnetic:head" => return,
+        "magnetic:head" => {}
+
+        // Component split out by render_to_kotlin_files → call it
+        COMPONENT_TAG => {
+            if let Some(name) = placeholder_name(node) {
+                indent(buf, depth);
+                buf.push_str(&format!("{}(onAction = onAction, onActionPayload = onActionPayload)\n", name));
+            }
+        }
 
         // Headings → Text with typography style
         "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
@@ -66,8 +202,8 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
             if let Some(text) = collect_text(node) {
                 indent(buf, depth);
                 buf.push_str(&format!(
-                    "Text(\"{}\", style = MaterialTheme.typography.{})\n",
-                    escape_kotlin(&text), style
+                    "Text(\"{}\", style = MaterialTheme.typography.{}, modifier = Modifier{})\n",
+                    escape_kotlin(&text), style, semantics(node)
                 ));
             }
         }
@@ -76,19 +212,25 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
         "p" | "span" | "label" => {
             if let Some(text) = collect_text(node) {
                 indent(buf, depth);
-                buf.push_str(&format!("Text(\"{}\")\n", escape_kotlin(&text)));
+                buf.push_str(&format!("Text(\"{}\"{})\n", escape_kotlin(&text), modifier_arg(node)));
             }
         }
 
         // Button → Button composable
         "button" => {
-            let action = node.event("click").unwrap_or("noop");
             let label = collect_text(node).unwrap_or_default();
+            let is_submit = node.attrs.as_ref()
+                .and_then(|a| a.get("type"))
+                .is_some_and(|t| t == "submit");
+            // Inside a form, a submit button (or one without its own click
+            // action) submits the form values
+            let on_click = match (node.event("click"), ctx.submit_call()) {
+                (Some(_), Some(submit)) if is_submit => submit,
+                (None, Some(submit)) => submit,
+                (action, _) => format!("onAction(\"{}\")", escape_kotlin(action.unwrap_or("noop"))),
+            };
             indent(buf, depth);
-            buf.push_str(&format!(
-                "Button(onClick = {{ onAction(\"{}\") }}) {{\n",
-                escape_kotlin(action)
-            ));
+            buf.push_str(&format!("Button(onClick = {{ {} }}{}) {{\n", on_click, modifier_arg(node)));
             indent(buf, depth + 1);
             buf.push_str(&format!("Text(\"{}\")\n", escape_kotlin(&label)));
             indent(buf, depth);
@@ -97,40 +239,44 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
 
         // Input → OutlinedTextField
         "input" => {
-            let input_type = node.attrs.as_ref()
-                .and_then(|a| a.get("type"))
-                .map(|s| s.as_str())
-                .unwrap_or("text");
             let placeholder = node.attrs.as_ref()
                 .and_then(|a| a.get("placeholder"))
                 .map(|s| s.as_str())
                 .unwrap_or("");
             let name = node.attrs.as_ref()
                 .and_then(|a| a.get("name"))
+                .or(node.key.as_ref())
                 .map(|s| s.as_str())
                 .unwrap_or("input");
             let action = node.event("input").unwrap_or("");
 
+            let state = ctx.state_var();
+            let field = format!("{}[\"{}\"]", state, escape_kotlin(name));
+
             indent(buf, depth);
-            buf.push_str(&format!(
-                "var {name}Value by remember {{ mutableStateOf(\"\") }}\n"
-            ));
-            indent(buf, depth);
-            buf.push_str(&format!(
-                "OutlinedTextField(\n"
-            ));
+            buf.push_str("OutlinedTextField(\n");
             indent(buf, depth + 1);
-            buf.push_str(&format!("value = {}Value,\n", name));
+            buf.push_str(&format!("value = {},\n", field));
             indent(buf, depth + 1);
-            buf.push_str(&format!("onValueChange = {{ {}Value = it", name));
+            buf.push_str(&format!("onValueChange = {{ {} = it", field));
             if !action.is_empty() {
-                buf.push_str(&format!("; onAction(\"{}\")", escape_kotlin(action)));
+                buf.push_str(&format!(
+                    "; onActionPayload(\"{}\", mapOf(\"value\" to it))",
+                    escape_kotlin(action)
+                ));
             }
             buf.push_str(" },\n");
             indent(buf, depth + 1);
             buf.push_str(&format!("placeholder = {{ Text(\"{}\") }},\n", escape_kotlin(placeholder)));
+            // Enter submits the enclosing form, like a browser
+            if let Some(submit) = ctx.submit_call() {
+                indent(buf, depth + 1);
+                buf.push_str("keyboardOptions = KeyboardOptions(imeAction = ImeAction.Done),\n");
+                indent(buf, depth + 1);
+                buf.push_str(&format!("keyboardActions = KeyboardActions(onDone = {{ {} }}),\n", submit));
+            }
             indent(buf, depth + 1);
-            buf.push_str("modifier = Modifier.fillMaxWidth()\n");
+            buf.push_str(&format!("modifier = Modifier.fillMaxWidth(){}\n", semantics(node)));
             indent(buf, depth);
             buf.push_str(")\n");
         }
@@ -143,8 +289,8 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
             let label = collect_text(node).unwrap_or_default();
             indent(buf, depth);
             buf.push_str(&format!(
-                "TextButton(onClick = {{ onAction(\"{}\") }}) {{\n",
-                escape_kotlin(action)
+                "TextButton(onClick = {{ onAction(\"{}\") }}{}) {{\n",
+                escape_kotlin(action), modifier_arg(node)
             ));
             indent(buf, depth + 1);
             buf.push_str(&format!("Text(\"{}\")\n", escape_kotlin(&label)));
@@ -152,24 +298,35 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
             buf.push_str("}\n");
         }
 
+        // Image → AsyncImage (coil); empty alt marks the image decorative
+        "img" => {
+            let src = node.attrs.as_ref()
+                .and_then(|a| a.get("src"))
+                .map(|s| s.as_str())
+                .unwrap_or("");
+            let description = match node.attrs.as_ref().and_then(|a| a.get("aria-label").or(a.get("alt"))) {
+                Some(alt) if !alt.is_empty() => format!("\"{}\"", escape_kotlin(alt)),
+                _ => "null".to_string(),
+            };
+            indent(buf, depth);
+            buf.push_str(&format!(
+                "AsyncImage(model = \"{}\", contentDescription = {})\n",
+                escape_kotlin(src), description
+            ));
+        }
+
         // Form → Column with submit handler
         "form" => {
-            let action = node.event("submit").unwrap_or("submit");
+            let ctx = Ctx { form: ctx.forms.iter().position(|f| std::ptr::eq(*f, node)), ..ctx };
             indent(buf, depth);
             buf.push_str("Column(\n");
             indent(buf, depth + 1);
-            buf.push_str("modifier = Modifier.fillMaxWidth()\n");
+            buf.push_str(&format!("modifier = Modifier.fillMaxWidth(){}\n", semantics(node)));
             indent(buf, depth);
             buf.push_str(") {\n");
             for child in node.children_iter() {
-                write_kotlin_node(child, buf, depth + 1);
+                write_kotlin_node(child, buf, depth + 1, ctx);
             }
-            // Submit button if form has action
-            indent(buf, depth + 1);
-            buf.push_str(&format!(
-                "// Form submit: onAction(\"{}\")\n",
-                escape_kotlin(action)
-            ));
             indent(buf, depth);
             buf.push_str("}\n");
         }
@@ -181,11 +338,11 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
             indent(buf, depth + 1);
             buf.push_str("horizontalArrangement = Arrangement.spacedBy(8.dp),\n");
             indent(buf, depth + 1);
-            buf.push_str("modifier = Modifier.fillMaxWidth()\n");
+            buf.push_str(&format!("modifier = Modifier.fillMaxWidth(){}\n", semantics(node)));
             indent(buf, depth);
             buf.push_str(") {\n");
             for child in node.children_iter() {
-                write_kotlin_node(child, buf, depth + 1);
+                write_kotlin_node(child, buf, depth + 1, ctx);
             }
             indent(buf, depth);
             buf.push_str("}\n");
@@ -198,7 +355,7 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
             for (i, child) in node.children_iter().iter().enumerate() {
                 indent(buf, depth + 1);
                 buf.push_str(&format!("item(key = \"{}\") {{\n", child.key.as_deref().unwrap_or(&i.to_string())));
-                write_kotlin_node(child, buf, depth + 2);
+                write_kotlin_node(child, buf, depth + 2, ctx);
                 indent(buf, depth + 1);
                 buf.push_str("}\n");
             }
@@ -209,7 +366,7 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
         "li" => {
             // Render children directly
             for child in node.children_iter() {
-                write_kotlin_node(child, buf, depth);
+                write_kotlin_node(child, buf, depth, ctx);
             }
             if let Some(text) = &node.text {
                 indent(buf, depth);
@@ -217,6 +374,33 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
             }
         }
 
+        // Table → Column of Rows, cells share the width by weight
+        "table" => {
+            indent(buf, depth);
+            buf.push_str(&format!("Column(modifier = Modifier.fillMaxWidth(){}) {{\n", semantics(node)));
+            for (i, (row, header)) in table_rows(node).into_iter().enumerate() {
+                if i > 0 {
+                    indent(buf, depth + 1);
+                    buf.push_str("HorizontalDivider()\n");
+                }
+                indent(buf, depth + 1);
+                buf.push_str("Row(\n");
+                indent(buf, depth + 2);
+                buf.push_str("horizontalArrangement = Arrangement.spacedBy(8.dp),\n");
+                indent(buf, depth + 2);
+                buf.push_str("modifier = Modifier.fillMaxWidth().padding(vertical = 4.dp)\n");
+                indent(buf, depth + 1);
+                buf.push_str(") {\n");
+                for cell in row.children_iter() {
+                    write_kotlin_cell(cell, header, buf, depth + 2, ctx);
+                }
+                indent(buf, depth + 1);
+                buf.push_str("}\n");
+            }
+            indent(buf, depth);
+            buf.push_str("}\n");
+        }
+
         // Default: div and everything else → Column or Row
         _ => {
             let is_row = is_row_layout(node);
@@ -235,7 +419,7 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
                 buf.push_str("verticalArrangement = Arrangement.spacedBy(8.dp),\n");
             }
             indent(buf, depth + 1);
-            buf.push_str("modifier = Modifier.fillMaxWidth()\n");
+            buf.push_str(&format!("modifier = Modifier.fillMaxWidth(){}\n", semantics(node)));
             indent(buf, depth);
             buf.push_str(") {\n");
 
@@ -247,7 +431,7 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
 
             // Children
             for child in node.children_iter() {
-                write_kotlin_node(child, buf, depth + 1);
+                write_kotlin_node(child, buf, depth + 1, ctx);
             }
 
             indent(buf, depth);
@@ -256,6 +440,119 @@ fn write_kotlin_node(node: &DomNode, buf: &mut String, depth: usize) {
     }
 }
 
+/// Write a custom mapping template; a `{children}` line renders the child
+/// nodes at that line's indentation
+fn write_kotlin_template(template: &str, node: &DomNode, buf: &mut String, depth: usize, ctx: Ctx) {
+    for line in template.lines() {
+        let extra = (line.len() - line.trim_start().len()) / 4;
+        if line.trim() == "{children}" {
+            for child in node.children_iter() {
+                write_kotlin_node(child, buf, depth + extra, ctx);
+            }
+            continue;
+        }
+        indent(buf, depth);
+        buf.push_str(&fill_template(line, node, escape_kotlin));
+        buf.push('\n');
+    }
+}
+
+/// Write a table cell weighted by its colspan; text-only cells become a Text
+fn write_kotlin_cell(cell: &DomNode, header: bool, buf: &mut String, depth: usize, ctx: Ctx) {
+    let span = colspan(cell);
+    let bold = header || cell.tag == "th";
+    let text_only = cell.children_iter().iter().all(|c| c.children.is_none() && c.events.is_none()
+        && matches!(c.tag.as_str(), "span" | "strong" | "b" | "em" | "i" | "small" | "code"));
+
+    indent(buf, depth);
+    if text_only {
+        let text = collect_text(cell).unwrap_or_default();
+        buf.push_str(&format!("Text(\"{}\", modifier = Modifier.weight({}f)", escape_kotlin(&text), span));
+        if bold {
+            buf.push_str(", fontWeight = FontWeight.Bold");
+        }
+        buf.push_str(")\n");
+    } else {
+        buf.push_str(&format!("Box(modifier = Modifier.weight({}f)) {{\n", span));
+        for child in cell.children_iter() {
+            write_kotlin_node(child, buf, depth + 1, ctx);
+        }
+        indent(buf, depth);
+        buf.push_str("}\n");
+    }
+}
+
+/// Rows of a table with a header flag (rows inside thead), looking through
+/// thead/tbody/tfoot sections
+fn table_rows(table: &DomNode) -> Vec<(&DomNode, bool)> {
+    let mut rows = Vec::new();
+    for child in table.children_iter() {
+        match child.tag.as_str() {
+            "tr" => rows.push((child, false)),
+            "thead" | "tbody" | "tfoot" => {
+                let header = child.tag == "thead";
+                for row in child.children_iter().iter().filter(|r| r.tag == "tr") {
+                    rows.push((row, header));
+                }
+            }
+            _ => {}
+        }
+    }
+    rows
+}
+
+fn colspan(cell: &DomNode) -> u32 {
+    cell.attrs.as_ref()
+        .and_then(|a| a.get("colspan"))
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(1)
+}
+
+/// Compose semantics for aria-label, role and headings as a
+/// `.semantics { ... }` modifier suffix ("" when there is nothing to add)
+fn semantics(node: &DomNode) -> String {
+    let attr = |k: &str| node.attrs.as_ref().and_then(|a| a.get(k)).map(|s| s.as_str());
+    let mut props = Vec::new();
+
+    if let Some(label) = attr("aria-label") {
+        props.push(format!("contentDescription = \"{}\"", escape_kotlin(label)));
+    }
+    let role = attr("role");
+    if matches!(node.tag.as_str(), "h1" | "h2" | "h3" | "h4" | "h5" | "h6") || role == Some("heading") {
+        props.push("heading()".to_string());
+    }
+    let compose_role = match role {
+        Some("button") => Some("Button"),
+        Some("checkbox") => Some("Checkbox"),
+        Some("switch") => Some("Switch"),
+        Some("radio") => Some("RadioButton"),
+        Some("tab") => Some("Tab"),
+        Some("img") | Some("image") => Some("Image"),
+        _ => None,
+    };
+    if let Some(r) = compose_role {
+        props.push(format!("role = Role.{}", r));
+    }
+
+    if props.is_empty() {
+        String::new()
+    } else {
+        format!(".semantics {{ {} }}", props.join("; "))
+    }
+}
+
+/// `, modifier = Modifier.semantics { ... }` for composables that otherwise
+/// take no modifier ("" when there are no semantics)
+fn modifier_arg(node: &DomNode) -> String {
+    let sem = semantics(node);
+    if sem.is_empty() { sem } else { format!(", modifier = Modifier{}", sem) }
+}
+
+fn contains_tag(node: &DomNode, tag: &str) -> bool {
+    node.tag == tag || node.children_iter().iter().any(|c| contains_tag(c, tag))
+}
+
 /// Check if a node should be rendered as a Row (horizontal) vs Column (vertical)
 fn is_row_layout(node: &DomNode) -> bool {
     if let Some(class) = node.class() {
@@ -315,4 +612,139 @@ mod tests {
         assert!(kt.contains("onAction(\"increment\")"));
         assert!(kt.contains("Text(\"+\")"));
     }
+
+    #[test]
+    fn test_form_submits_payload() {
+        let input = DomNode {
+            attrs: Some(HashMap::from([("name".into(), "title".into())])),
+            ..DomNode::text("input", "")
+        };
+        let form = DomNode {
+            tag: "form".into(),
+            key: None,
+            attrs: None,
+            events: Some(HashMap::from([("submit".into(), "add_task".into())])),
+            text: None,
+            html: None,
+            children: Some(vec![input, DomNode::text("button", "Add")]),
+        };
+        let kt = render_to_kotlin(&form, "FormScreen");
+        assert!(kt.contains("val form0 = remember { FormState() }"));
+        assert!(kt.contains("value = form0[\"title\"],"));
+        assert!(kt.contains("Button(onClick = { onActionPayload(\"add_task\", form0.payload()); form0.clear() })"));
+        assert!(kt.contains("private class FormState"));
+    }
+
+    #[test]
+    fn test_accessibility_semantics() {
+        let img = DomNode {
+            tag: "img".into(),
+            key: None,
+            attrs: Some(HashMap::from([("src".into(), "/logo.png".into()), ("alt".into(), "Logo".into())])),
+            events: None,
+            text: None,
+            html: None,
+            children: None,
+        };
+        let close = DomNode {
+            attrs: Some(HashMap::from([("aria-label".into(), "Close dialog".into())])),
+            ..DomNode::text("button", "×")
+        };
+        let node = DomNode {
+            tag: "div".into(),
+            key: None,
+            attrs: Some(HashMap::from([("role".into(), "tab".into())])),
+            events: None,
+            text: None,
+            html: None,
+            children: Some(vec![DomNode::text("h2", "Settings"), img, close]),
+        };
+        let kt = render_to_kotlin(&node, "A11yScreen");
+        assert!(kt.contains("import coil.compose.AsyncImage"));
+        assert!(kt.contains("style = MaterialTheme.typography.headlineMedium, modifier = Modifier.semantics { heading() })"));
+        assert!(kt.contains("AsyncImage(model = \"/logo.png\", contentDescription = \"Logo\")"));
+        assert!(kt.contains("modifier = Modifier.semantics { contentDescription = \"Close dialog\" }) {"));
+        assert!(kt.contains("modifier = Modifier.fillMaxWidth().semantics { role = Role.Tab }"));
+    }
+
+    #[test]
+    fn test_component_files() {
+        let node = magnetic_dom::parse_node(r#"{
+            "tag": "div",
+            "children": [
+                { "tag": "header", "attrs": { "data-component": "Header" }, "children": [
+                    { "tag": "h1", "text": "Tasks" }
+                ] },
+                { "tag": "form", "attrs": { "data-component": "AddTask" }, "events": { "submit": "add_task" }, "children": [
+                    { "tag": "input", "attrs": { "name": "title" } }
+                ] }
+            ]
+        }"#).unwrap();
+        let files = render_to_kotlin_files(&node, "MagneticApp", &TagMapping::default());
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["MagneticApp.kt", "Header.kt", "AddTask.kt"]);
+
+        let (_, index) = &files[0];
+        assert!(index.contains("        Header(onAction = onAction, onActionPayload = onActionPayload)\n"));
+        assert!(index.contains("        AddTask(onAction = onAction, onActionPayload = onActionPayload)\n"));
+        assert!(!index.contains("FormState"));
+
+        let (_, add_task) = &files[2];
+        assert!(add_task.starts_with("package com.magnetic.app\n"));
+        assert!(add_task.contains("fun AddTask(\n"));
+        assert!(add_task.contains("val form0 = remember { FormState() }"));
+        assert!(add_task.contains("private class FormState"));
+    }
+
+    #[test]
+    fn test_custom_mapping() {
+        let maps = magnetic_dom::mapping::RenderMaps::from_json(r#"{ "kotlin": {
+            "imports": ["com.example.ui.Chip"],
+            "classes": { "chip": "AssistChip(\n    onClick = { onAction(\"{event:click}\") },\n    label = { Text(\"{text}\") }\n)" },
+            "tags": { "section": "Card {\n    {children}\n}" }
+        } }"#).unwrap();
+        let chip = DomNode {
+            attrs: Some(HashMap::from([("class".into(), "chip".into())])),
+            events: Some(HashMap::from([("click".into(), "filter_all".into())])),
+            ..DomNode::text("span", "All")
+        };
+        let node = DomNode {
+            tag: "section".into(),
+            key: None,
+            attrs: None,
+            events: None,
+            text: None,
+            html: None,
+            children: Some(vec![chip]),
+        };
+        let kt = render_to_kotlin_with_map(&node, "Mapped", &maps.kotlin);
+        assert!(kt.contains("import com.example.ui.Chip\n"));
+        assert!(kt.contains("    Card {\n        AssistChip(\n            onClick = { onAction(\"filter_all\") },\n            label = { Text(\"All\") }\n        )\n    }\n"));
+    }
+
+    #[test]
+    fn test_table_render() {
+        let row = |tag: &str, cells: &[&str]| DomNode {
+            tag: "tr".into(),
+            key: None,
+            attrs: None,
+            events: None,
+            text: None,
+            html: None,
+            children: Some(cells.iter().map(|c| DomNode::text(tag, c)).collect()),
+        };
+        let node = DomNode {
+            tag: "table".into(),
+            key: None,
+            attrs: None,
+            events: None,
+            text: None,
+            html: None,
+            children: Some(vec![row("th", &["Name", "Qty"]), row("td", &["Apples", "3"])]),
+        };
+        let kt = render_to_kotlin(&node, "TableScreen");
+        assert!(kt.contains("Text(\"Name\", modifier = Modifier.weight(1f), fontWeight = FontWeight.Bold)"));
+        assert!(kt.contains("Text(\"Apples\", modifier = Modifier.weight(1f))"));
+        assert!(kt.contains("HorizontalDivider()"));
+    }
 }
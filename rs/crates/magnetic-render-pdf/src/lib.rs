@@ -0,0 +1,391 @@
+//! magnetic-render-pdf — Render Magnetic DomNode trees to paginated PDF
+//!
+//! Lays out the JSON DOM tree as a flowing A4 document using printpdf's
+//! builtin Helvetica fonts, so server-rendered reports can be downloaded as
+//! PDF without a headless browser. Layout is a single top-to-bottom flow:
+//! text is word-wrapped with an average glyph width estimate and a new page
+//! starts whenever the cursor reaches the bottom margin.
+//!
+//! Builtin PDF fonts only cover Windows-1252; other characters are dropped.
+//!
+//! Mapping strategy:
+//!   div          → block (children flow vertically)
+//!   div.card     → bordered box around its children
+//!   h1..h6       → bold text, 22pt → 11pt
+//!   p / span / label / li text → wrapped 11pt text
+//!   table        → grid of equal-width columns, th row in bold, rules between rows
+//!   ul/ol        → bulleted / numbered items
+//!   a / Link     → label text
+//!   img          → [image: alt] placeholder
+//!   hr           → horizontal rule
+//!   button / input / form controls, magnetic:head → skipped
+
+use magnetic_dom::DomNode;
+use printpdf::{
+    path::PaintMode, BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference,
+    PdfLayerReference, Point, Rect,
+};
+
+const PAGE_W: f32 = 210.0;
+const PAGE_H: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const BODY_SIZE: f32 = 11.0;
+/// Points → millimetres
+const PT: f32 = 0.3528;
+/// Average Helvetica glyph width as a fraction of the font size
+const GLYPH_W: f32 = 0.5;
+const CARD_PAD: f32 = 4.0;
+
+/// Render a DomNode tree to PDF bytes.
+pub fn render_to_pdf(node: &DomNode, title: &str) -> Result<Vec<u8>, String> {
+    let doc = lay_out(node, title)?.doc;
+    doc.save_to_bytes().map_err(|e| format!("save pdf: {}", e))
+}
+
+fn lay_out(node: &DomNode, title: &str) -> Result<Layout, String> {
+    let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_W), Mm(PAGE_H), "Layer 1");
+    let layer = doc.get_page(page).get_layer(layer);
+    let font = |f| doc.add_builtin_font(f).map_err(|e| format!("font: {}", e));
+    let fonts = Fonts {
+        regular: font(BuiltinFont::Helvetica)?,
+        bold: font(BuiltinFont::HelveticaBold)?,
+        italic: font(BuiltinFont::HelveticaOblique)?,
+    };
+
+    let mut pdf = Layout { doc, pages: vec![layer], fonts, y: MARGIN, x: 0.0 };
+    pdf.node(node);
+    Ok(pdf)
+}
+
+struct Fonts {
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    italic: IndirectFontRef,
+}
+
+#[derive(Clone, Copy)]
+enum Style {
+    Regular,
+    Bold,
+    Italic,
+}
+
+/// Flow layout cursor. `y` is the distance from the top of the current page,
+/// `x` the indent from the left margin, both in millimetres.
+struct Layout {
+    doc: PdfDocumentReference,
+    pages: Vec<PdfLayerReference>,
+    fonts: Fonts,
+    y: f32,
+    x: f32,
+}
+
+impl Layout {
+    fn layer(&self) -> &PdfLayerReference {
+        self.pages.last().expect("at least one page")
+    }
+
+    /// Start a new page if `h` mm do not fit above the bottom margin
+    fn ensure(&mut self, h: f32) {
+        if self.y + h > PAGE_H - MARGIN && self.y > MARGIN {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_W), Mm(PAGE_H), "Layer 1");
+            self.pages.push(self.doc.get_page(page).get_layer(layer));
+            self.y = MARGIN;
+        }
+    }
+
+    fn gap(&mut self, h: f32) {
+        self.y += h;
+    }
+
+    fn font(&self, style: Style) -> &IndirectFontRef {
+        match style {
+            Style::Regular => &self.fonts.regular,
+            Style::Bold => &self.fonts.bold,
+            Style::Italic => &self.fonts.italic,
+        }
+    }
+
+    /// Write wrapped text starting at indent `x` within `width` mm
+    fn text_at(&mut self, text: &str, size: f32, style: Style, x: f32, width: f32) {
+        let line_h = size * PT * 1.4;
+        for line in wrap(text, size, width) {
+            self.ensure(line_h);
+            let baseline = PAGE_H - self.y - size * PT;
+            self.layer().use_text(line, size, Mm(MARGIN + x), Mm(baseline), self.font(style));
+            self.y += line_h;
+        }
+    }
+
+    fn text(&mut self, text: &str, size: f32, style: Style) {
+        self.text_at(text, size, style, self.x, content_width() - self.x);
+    }
+
+    fn rule(&mut self, x0: f32, x1: f32) {
+        let y = PAGE_H - self.y;
+        self.layer().add_line(Line {
+            points: vec![
+                (Point::new(Mm(MARGIN + x0), Mm(y)), false),
+                (Point::new(Mm(MARGIN + x1), Mm(y)), false),
+            ],
+            is_closed: false,
+        });
+    }
+
+    fn node(&mut self, node: &DomNode) {
+        match node.tag.as_str() {
+            // Interactive controls and head nodes have no place in a printed report
+            "magnetic:head" | "button" | "input" | "textarea" | "select" | "script" | "style" => {}
+
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if let Some(text) = collect_text(node) {
+                    let size = match node.tag.as_str() {
+                        "h1" => 22.0,
+                        "h2" => 18.0,
+                        "h3" => 15.0,
+                        "h4" => 13.0,
+                        "h5" => 12.0,
+                        _ => 11.0,
+                    };
+                    self.gap(size * PT * 0.6);
+                    // Keep a heading together with at least one line of body text
+                    self.ensure(size * PT * 1.4 + BODY_SIZE * PT * 1.4);
+                    self.text(&text, size, Style::Bold);
+                    self.gap(1.5);
+                }
+            }
+
+            "p" | "span" | "label" | "a" | "li" if node.children_iter().iter().all(is_inline) => {
+                if let Some(text) = collect_text(node) {
+                    self.text(&text, BODY_SIZE, Style::Regular);
+                    self.gap(1.5);
+                }
+            }
+
+            "img" => {
+                let alt = node.attrs.as_ref()
+                    .and_then(|a| a.get("alt"))
+                    .map(|s| s.as_str())
+                    .unwrap_or("image");
+                self.text(&format!("[image: {}]", alt), BODY_SIZE, Style::Italic);
+                self.gap(1.5);
+            }
+
+            "hr" => {
+                self.ensure(4.0);
+                self.gap(2.0);
+                self.rule(self.x, content_width());
+                self.gap(2.0);
+            }
+
+            "ul" | "ol" => {
+                let ordered = node.tag == "ol";
+                let mut n = 0;
+                for child in node.children_iter() {
+                    if child.is_head() { continue; }
+                    n += 1;
+                    let marker = if ordered { format!("{}.", n) } else { "-".to_string() };
+                    self.ensure(BODY_SIZE * PT * 1.4);
+                    let y = self.y;
+                    self.text(&marker, BODY_SIZE, Style::Regular);
+                    self.y = y;
+                    self.x += 6.0;
+                    self.node(child);
+                    self.x -= 6.0;
+                }
+                self.gap(1.5);
+            }
+
+            "table" => self.table(node),
+
+            // Default: block containers (div, section, li, form, ...)
+            _ => {
+                let card = node.class().is_some_and(|c| c.split_whitespace().any(|c| c.contains("card")));
+                if card {
+                    self.card(node);
+                    return;
+                }
+                if let Some(text) = &node.text {
+                    self.text(text, BODY_SIZE, Style::Regular);
+                }
+                for child in node.children_iter() {
+                    self.node(child);
+                }
+            }
+        }
+    }
+
+    /// Bordered box around the children; split across pages when needed
+    fn card(&mut self, node: &DomNode) {
+        self.ensure(CARD_PAD * 2.0 + BODY_SIZE * PT * 1.4);
+        let start_page = self.pages.len() - 1;
+        let start_y = self.y;
+
+        self.gap(CARD_PAD);
+        self.x += CARD_PAD;
+        if let Some(text) = &node.text {
+            self.text(text, BODY_SIZE, Style::Regular);
+        }
+        for child in node.children_iter() {
+            self.node(child);
+        }
+        self.x -= CARD_PAD;
+        self.gap(CARD_PAD);
+
+        let x0 = MARGIN + self.x;
+        let x1 = MARGIN + content_width();
+        let end_page = self.pages.len() - 1;
+        for (i, layer) in self.pages.iter().enumerate().take(end_page + 1).skip(start_page) {
+            let top = if i == start_page { start_y } else { MARGIN };
+            let bottom = if i == end_page { self.y } else { PAGE_H - MARGIN };
+            layer.add_rect(
+                Rect::new(Mm(x0), Mm(PAGE_H - bottom), Mm(x1), Mm(PAGE_H - top))
+                    .with_mode(PaintMode::Stroke),
+            );
+        }
+        self.gap(3.0);
+    }
+
+    fn table(&mut self, node: &DomNode) {
+        let mut rows = Vec::new();
+        collect_rows(node, &mut rows);
+        let cols = rows.iter().map(|r| r.children_iter().len()).max().unwrap_or(0);
+        if cols == 0 { return; }
+
+        let width = content_width() - self.x;
+        let col_w = width / cols as f32;
+        let line_h = BODY_SIZE * PT * 1.4;
+
+        self.gap(1.0);
+        for row in rows {
+            let cells: Vec<(String, bool)> = row.children_iter().iter()
+                .map(|c| (collect_text(c).unwrap_or_default(), c.tag == "th"))
+                .collect();
+            let lines = cells.iter()
+                .map(|(t, _)| wrap(t, BODY_SIZE, col_w - 2.0).len().max(1))
+                .max()
+                .unwrap_or(1);
+            let row_h = lines as f32 * line_h + 2.0;
+
+            self.ensure(row_h);
+            let top = self.y;
+            for (i, (text, header)) in cells.iter().enumerate() {
+                self.y = top + 1.0;
+                let style = if *header { Style::Bold } else { Style::Regular };
+                let x = self.x + i as f32 * col_w + 1.0;
+                self.text_at(text, BODY_SIZE, style, x, col_w - 2.0);
+            }
+            self.y = top + row_h;
+            self.rule(self.x, self.x + width);
+        }
+        self.gap(3.0);
+    }
+}
+
+fn content_width() -> f32 {
+    PAGE_W - 2.0 * MARGIN
+}
+
+fn is_inline(node: &DomNode) -> bool {
+    matches!(node.tag.as_str(), "span" | "a" | "strong" | "b" | "em" | "i" | "code" | "small")
+}
+
+/// Collect `tr` rows from a table, looking through thead/tbody/tfoot
+fn collect_rows<'a>(node: &'a DomNode, rows: &mut Vec<&'a DomNode>) {
+    for child in node.children_iter() {
+        match child.tag.as_str() {
+            "tr" => rows.push(child),
+            "thead" | "tbody" | "tfoot" => collect_rows(child, rows),
+            _ => {}
+        }
+    }
+}
+
+/// Greedy word wrap using the average glyph width for `size`
+fn wrap(text: &str, size: f32, width: f32) -> Vec<String> {
+    let max_chars = ((width / (size * PT * GLYPH_W)) as usize).max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word = word;
+        // Hard-break words longer than a full line
+        while word.chars().count() > max_chars {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            let split = word.char_indices().nth(max_chars).map(|(i, _)| i).unwrap_or(word.len());
+            lines.push(word[..split].to_string());
+            word = &word[split..];
+        }
+        let needed = if line.is_empty() { word.chars().count() } else { line.chars().count() + 1 + word.chars().count() };
+        if needed > max_chars && !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Collect all text content from a node and its children
+fn collect_text(node: &DomNode) -> Option<String> {
+    let mut text = String::new();
+    collect_text_inner(node, &mut text);
+    if text.is_empty() { None } else { Some(text) }
+}
+
+fn collect_text_inner(node: &DomNode, buf: &mut String) {
+    if let Some(t) = &node.text {
+        buf.push_str(t);
+    }
+    for child in node.children_iter() {
+        collect_text_inner(child, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use magnetic_dom::DomNode;
+    use std::collections::HashMap;
+
+    fn el(tag: &str, class: Option<&str>, children: Vec<DomNode>) -> DomNode {
+        DomNode {
+            tag: tag.into(),
+            key: None,
+            attrs: class.map(|c| HashMap::from([("class".into(), c.into())])),
+            events: None,
+            text: None,
+            html: None,
+            children: Some(children),
+        }
+    }
+
+    #[test]
+    fn test_wrap() {
+        let lines = wrap("the quick brown fox jumps", 10.0, 10.0 * PT * GLYPH_W * 10.0);
+        assert_eq!(lines, vec!["the quick", "brown fox", "jumps"]);
+    }
+
+    #[test]
+    fn test_report_paginates() {
+        let row = el("tr", None, vec![DomNode::text("td", "cell"), DomNode::text("td", "value")]);
+        let table = el("table", None, vec![
+            el("thead", None, vec![el("tr", None, vec![DomNode::text("th", "Name"), DomNode::text("th", "Value")])]),
+            el("tbody", None, vec![row; 120]),
+        ]);
+        let report = el("div", None, vec![
+            DomNode::text("h1", "Report"),
+            el("div", Some("card"), vec![DomNode::text("p", "Summary")]),
+            table,
+        ]);
+        assert!(lay_out(&report, "Report").unwrap().pages.len() >= 2);
+        let pdf = render_to_pdf(&report, "Report").unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+    }
+}
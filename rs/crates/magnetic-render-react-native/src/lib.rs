@@ -0,0 +1,331 @@
+//! magnetic-render-react-native — Render Magnetic DomNode trees to React Native JSX
+//!
+//! Translates the JSON DOM tree into a React Native functional component.
+//! The output is a complete `.jsx` module exporting a default component that
+//! takes an `onAction(action)` prop, so existing RN shells can consume
+//! Magnetic snapshots without a WebView.
+//!
+//! Mapping strategy:
+//!   div          → View (column) / View style=row (if row-like class)
+//!   span         → Text (inline)
+//!   h1..h6       → <Text style={styles.h1}>...</Text>
+//!   p            → <Text>...</Text>
+//!   button       → <Pressable onPress={() => onAction("action")}><Text>label</Text></Pressable>
+//!   input        → <TextInput placeholder="..." />
+//!   form         → View; its submit button and Enter in its inputs → onAction("submit action")
+//!   a / Link     → <Pressable onPress={() => onAction("navigate:href")}><Text style={styles.link}>
+//!   img          → <Image source={{ uri: src }} accessibilityLabel={alt} />
+//!   nav          → View style=row (navigation bar)
+//!   ul/ol        → View with keyed children
+//!   li           → View
+
+use magnetic_dom::DomNode;
+
+/// Render a DomNode tree to a React Native functional component module.
+pub fn render_to_react_native(node: &DomNode, component_name: &str) -> String {
+    let mut buf = String::with_capacity(4096);
+
+    // Module header
+    buf.push_str("import React from 'react';\n");
+    buf.push_str("import { View, Text, Pressable, TextInput, Image, ScrollView, StyleSheet } from 'react-native';\n\n");
+
+    buf.push_str(&format!("export default function {}({{ onAction }}) {{\n", component_name));
+    buf.push_str("  return (\n");
+    buf.push_str("    <ScrollView contentContainerStyle={styles.container}>\n");
+    write_jsx_node(node, &mut buf, 3, None);
+    buf.push_str("    </ScrollView>\n");
+    buf.push_str("  );\n");
+    buf.push_str("}\n\n");
+
+    buf.push_str(STYLES);
+
+    buf
+}
+
+const STYLES: &str = "\
+const styles = StyleSheet.create({
+  container: { padding: 16, gap: 8 },
+  column: { gap: 8 },
+  row: { flexDirection: 'row', alignItems: 'center', gap: 8 },
+  h1: { fontSize: 32, fontWeight: '700' },
+  h2: { fontSize: 26, fontWeight: '700' },
+  h3: { fontSize: 22, fontWeight: '700' },
+  h4: { fontSize: 20, fontWeight: '600' },
+  h5: { fontSize: 18, fontWeight: '600' },
+  h6: { fontSize: 16, fontWeight: '600' },
+  button: { backgroundColor: '#2563eb', paddingVertical: 8, paddingHorizontal: 16, borderRadius: 8 },
+  buttonText: { color: '#ffffff', fontWeight: '600' },
+  link: { color: '#2563eb' },
+  input: { borderWidth: 1, borderColor: '#d1d5db', borderRadius: 8, padding: 8 },
+  image: { width: '100%', aspectRatio: 16 / 9 },
+});
+";
+
+fn indent(buf: &mut String, depth: usize) {
+    for _ in 0..depth {
+        buf.push_str("  ");
+    }
+}
+
+fn write_jsx_node(node: &DomNode, buf: &mut String, depth: usize, submit: Option<&str>) {
+    write_jsx_node_keyed(node, buf, depth, None, submit);
+}
+
+/// Write a node as JSX. `key` is set when the node is an item of a list;
+/// `submit` is the action of the enclosing form, if any.
+fn write_jsx_node_keyed(node: &DomNode, buf: &mut String, depth: usize, key: Option<&str>, submit: Option<&str>) {
+    let key_attr = key
+        .map(|k| format!(" key={{{}}}", js_string(k)))
+        .unwrap_or_default();
+
+    match node.tag.as_str() {
+        // Skip magnetic:head nodes (not relevant for native)
+        "magnetic:head" => {}
+
+        // Headings → Text with heading style
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            if let Some(text) = collect_text(node) {
+                indent(buf, depth);
+                buf.push_str(&format!(
+                    "<Text{} style={{styles.{}}} accessibilityRole=\"header\">{{{}}}</Text>\n",
+                    key_attr, node.tag, js_string(&text)
+                ));
+            }
+        }
+
+        // Paragraph / span / label → Text
+        "p" | "span" | "label" => {
+            if let Some(text) = collect_text(node) {
+                indent(buf, depth);
+                buf.push_str(&format!("<Text{}>{{{}}}</Text>\n", key_attr, js_string(&text)));
+            }
+        }
+
+        // Button → Pressable
+        "button" => {
+            let is_submit = node.attrs.as_ref()
+                .and_then(|a| a.get("type"))
+                .is_some_and(|t| t == "submit");
+            // Inside a form, a submit button (or one without its own click
+            // action) submits the form
+            let action = match (node.event("click"), submit) {
+                (Some(_), Some(submit)) if is_submit => submit,
+                (None, Some(submit)) => submit,
+                (action, _) => action.unwrap_or("noop"),
+            };
+            let label = collect_text(node).unwrap_or_default();
+            indent(buf, depth);
+            buf.push_str(&format!(
+                "<Pressable{} style={{styles.button}} onPress={{() => onAction({})}}>\n",
+                key_attr, js_string(action)
+            ));
+            indent(buf, depth + 1);
+            buf.push_str(&format!("<Text style={{styles.buttonText}}>{{{}}}</Text>\n", js_string(&label)));
+            indent(buf, depth);
+            buf.push_str("</Pressable>\n");
+        }
+
+        // Input → TextInput
+        "input" => {
+            let placeholder = node.attrs.as_ref()
+                .and_then(|a| a.get("placeholder"))
+                .map(|s| s.as_str())
+                .unwrap_or("");
+            let action = node.event("input").unwrap_or("");
+            indent(buf, depth);
+            buf.push_str(&format!(
+                "<TextInput{} style={{styles.input}} placeholder={{{}}}",
+                key_attr, js_string(placeholder)
+            ));
+            if !action.is_empty() {
+                buf.push_str(&format!(" onChangeText={{() => onAction({})}}", js_string(action)));
+            }
+            // Enter submits the enclosing form, like a browser
+            if let Some(submit) = submit {
+                buf.push_str(&format!(" onSubmitEditing={{() => onAction({})}}", js_string(submit)));
+            }
+            buf.push_str(" />\n");
+        }
+
+        // Anchor / Link → Pressable with link-styled Text
+        "a" => {
+            let action = node.event("click")
+                .or_else(|| node.attrs.as_ref()?.get("href").map(|h| h.as_str()))
+                .unwrap_or("");
+            let label = collect_text(node).unwrap_or_default();
+            indent(buf, depth);
+            buf.push_str(&format!(
+                "<Pressable{} onPress={{() => onAction({})}}>\n",
+                key_attr, js_string(action)
+            ));
+            indent(buf, depth + 1);
+            buf.push_str(&format!("<Text style={{styles.link}}>{{{}}}</Text>\n", js_string(&label)));
+            indent(buf, depth);
+            buf.push_str("</Pressable>\n");
+        }
+
+        // Image → Image with remote source
+        "img" => {
+            let src = node.attrs.as_ref()
+                .and_then(|a| a.get("src"))
+                .map(|s| s.as_str())
+                .unwrap_or("");
+            let alt = node.attrs.as_ref()
+                .and_then(|a| a.get("alt"))
+                .map(|s| s.as_str())
+                .unwrap_or("");
+            indent(buf, depth);
+            buf.push_str(&format!(
+                "<Image{} style={{styles.image}} source={{{{ uri: {} }}}} accessibilityLabel={{{}}} />\n",
+                key_attr, js_string(src), js_string(alt)
+            ));
+        }
+
+        // Form → View; its buttons and inputs submit it
+        "form" => {
+            let action = node.event("submit").unwrap_or("submit");
+            indent(buf, depth);
+            buf.push_str(&format!("<View{} style={{styles.column}}>\n", key_attr));
+            for child in node.children_iter() {
+                write_jsx_node(child, buf, depth + 1, Some(action));
+            }
+            indent(buf, depth);
+            buf.push_str("</View>\n");
+        }
+
+        // Lists → View with keyed children
+        "ul" | "ol" => {
+            indent(buf, depth);
+            buf.push_str(&format!("<View{} style={{styles.column}}>\n", key_attr));
+            for (i, child) in node.children_iter().iter().enumerate() {
+                let k = child.key.clone().unwrap_or_else(|| i.to_string());
+                write_jsx_node_keyed(child, buf, depth + 1, Some(&k), submit);
+            }
+            indent(buf, depth);
+            buf.push_str("</View>\n");
+        }
+
+        // Default: div, nav, li and everything else → View (row or column)
+        _ => {
+            let style = if is_row_layout(node) { "row" } else { "column" };
+            indent(buf, depth);
+            buf.push_str(&format!("<View{} style={{styles.{}}}>\n", key_attr, style));
+
+            // Text content
+            if let Some(text) = &node.text {
+                indent(buf, depth + 1);
+                buf.push_str(&format!("<Text>{{{}}}</Text>\n", js_string(text)));
+            }
+
+            // Children
+            for child in node.children_iter() {
+                write_jsx_node(child, buf, depth + 1, submit);
+            }
+
+            indent(buf, depth);
+            buf.push_str("</View>\n");
+        }
+    }
+}
+
+/// Check if a node should be rendered as a row (horizontal) vs column (vertical)
+fn is_row_layout(node: &DomNode) -> bool {
+    if let Some(class) = node.class() {
+        return class.contains("row")
+            || class.contains("flex-row")
+            || class.contains("topnav")
+            || class.contains("add-form")
+            || class.contains("filters")
+            || class.contains("task-card");
+    }
+    matches!(node.tag.as_str(), "nav" | "header")
+}
+
+/// Collect all text content from a node and its children
+fn collect_text(node: &DomNode) -> Option<String> {
+    let mut text = String::new();
+    collect_text_inner(node, &mut text);
+    if text.is_empty() { None } else { Some(text) }
+}
+
+fn collect_text_inner(node: &DomNode, buf: &mut String) {
+    if let Some(t) = &node.text {
+        buf.push_str(t);
+    }
+    for child in node.children_iter() {
+        collect_text_inner(child, buf);
+    }
+}
+
+/// Quote a string as a double-quoted JS literal (used inside JSX `{...}`).
+fn js_string(s: &str) -> String {
+    let escaped = s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace("</", "<\\/");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use magnetic_dom::DomNode;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_button_render() {
+        let node = DomNode {
+            tag: "button".into(),
+            key: Some("btn".into()),
+            attrs: None,
+            events: Some(HashMap::from([("click".into(), "increment".into())])),
+            text: Some("+".into()),
+            html: None,
+            children: None,
+        };
+        let jsx = render_to_react_native(&node, "TestScreen");
+        assert!(jsx.contains("export default function TestScreen({ onAction })"));
+        assert!(jsx.contains("onPress={() => onAction(\"increment\")}"));
+        assert!(jsx.contains("<Text style={styles.buttonText}>{\"+\"}</Text>"));
+    }
+
+    #[test]
+    fn test_list_items_are_keyed() {
+        let node = DomNode {
+            tag: "ul".into(),
+            key: None,
+            attrs: None,
+            events: None,
+            text: None,
+            html: None,
+            children: Some(vec![DomNode {
+                key: Some("task-1".into()),
+                ..DomNode::text("li", "Write \"docs\"")
+            }]),
+        };
+        let jsx = render_to_react_native(&node, "ListView");
+        assert!(jsx.contains("<View key={\"task-1\"} style={styles.column}>"));
+        assert!(jsx.contains("<Text>{\"Write \\\"docs\\\"\"}</Text>"));
+    }
+
+    #[test]
+    fn test_form_submits_through_on_action() {
+        let submit = DomNode {
+            attrs: Some(HashMap::from([("type".into(), "submit".into())])),
+            ..DomNode::text("button", "Add */ task")
+        };
+        let node = DomNode {
+            tag: "form".into(),
+            key: None,
+            attrs: None,
+            events: Some(HashMap::from([("submit".into(), "add_task".into())])),
+            text: None,
+            html: None,
+            children: Some(vec![DomNode::text("input", ""), submit]),
+        };
+        let jsx = render_to_react_native(&node, "FormView");
+        assert!(jsx.contains("onSubmitEditing={() => onAction(\"add_task\")}"));
+        assert!(jsx.contains("<Pressable style={styles.button} onPress={() => onAction(\"add_task\")}>"));
+        assert!(!jsx.contains("/*"));
+    }
+}
@@ -9,43 +9,184 @@
 //!   h1..h6       → Text("...").font(.largeTitle/.title/.headline/...)
 //!   p            → Text("...")
 //!   button       → Button("label") { onAction("action") }
-//!   input        → TextField("placeholder", text: $binding)
-//!   form         → VStack (wraps children, submit → onAction)
+//!   input        → TextField("placeholder", text: form.binding("name"))
+//!   form         → VStack + FormState; submit → onActionPayload(action, form.payload())
 //!   a / Link     → Button("label") { onAction("navigate:href") }
 //!   img          → AsyncImage(url: URL(string: src))
 //!   nav          → HStack (navigation bar)
 //!   ul/ol        → List / ForEach
 //!   li           → direct children
+//!   table        → Grid of GridRows (th / thead cells in bold)
+//!   data-component="Name" → Name(onAction:onActionPayload:), in its own file
+//!                  with `render_to_swift_files`
+//!
+//! Accessibility: aria-label / alt → .accessibilityLabel, h1..h6 and
+//! role="heading" → .isHeader + .accessibilityHeading(.hN), role → traits.
 
+use magnetic_dom::component::{placeholder_name, split_components, COMPONENT_TAG};
+use magnetic_dom::mapping::{fill_template, TagMapping};
 use magnetic_dom::DomNode;
 
 /// Render a DomNode tree to a SwiftUI View struct.
 pub fn render_to_swift(node: &DomNode, struct_name: &str) -> String {
+    render_to_swift_with_map(node, struct_name, &TagMapping::default())
+}
+
+/// Like `render_to_swift`, with custom tag/class mappings taking precedence
+/// over the built-in ones (see `magnetic_dom::mapping`).
+pub fn render_to_swift_with_map(node: &DomNode, struct_name: &str, map: &TagMapping) -> String {
     let mut buf = String::with_capacity(4096);
 
     // File header
-    buf.push_str("import SwiftUI\n\n");
+    buf.push_str("import SwiftUI\n");
+    for import in &map.imports {
+        buf.push_str(&format!("import {}\n", import));
+    }
+    buf.push('\n');
+
+    let mut forms = Vec::new();
+    collect_forms(node, &mut forms);
+    let loose_inputs = has_loose_inputs(node);
 
     buf.push_str(&format!("struct {}: View {{\n", struct_name));
-    buf.push_str("    var onAction: (String) -> Void\n\n");
+    buf.push_str("    var onAction: (String) -> Void\n");
+    buf.push_str("    var onActionPayload: ((String, [String: String]) -> Void)?\n");
+
+    // Input state holders: one per form, plus `fields` for inputs outside forms
+    if loose_inputs {
+        buf.push_str("    @StateObject private var fields = FormState()\n");
+    }
+    for i in 0..forms.len() {
+        buf.push_str(&format!("    @StateObject private var form{} = FormState()\n", i));
+    }
+    buf.push('\n');
+
+    // onActionPayload carries form / input values; without it the payload is
+    // dropped so existing `onAction`-only callers keep working.
+    buf.push_str("    init(onAction: @escaping (String) -> Void, onActionPayload: ((String, [String: String]) -> Void)? = nil) {\n");
+    buf.push_str("        self.onAction = onAction\n");
+    buf.push_str("        self.onActionPayload = onActionPayload\n");
+    buf.push_str("    }\n\n");
+
     buf.push_str("    var body: some View {\n");
-    write_swift_node(node, &mut buf, 2);
+    write_swift_node(node, &mut buf, 2, Ctx { forms: &forms, form: None, map });
+    buf.push_str("    }\n\n");
+
+    buf.push_str("    private func send(_ action: String, _ payload: [String: String]) {\n");
+    buf.push_str("        if let onActionPayload { onActionPayload(action, payload) } else { onAction(action) }\n");
     buf.push_str("    }\n");
     buf.push_str("}\n");
 
+    if !forms.is_empty() || loose_inputs {
+        buf.push_str(FORM_STATE);
+    }
+
     buf
 }
 
+/// Render a tree as one file per `data-component` subtree plus an index file
+/// (`{struct_name}.swift`) holding the root view, which embeds the
+/// components. Returns (file name, source) pairs, index first.
+pub fn render_to_swift_files(node: &DomNode, struct_name: &str, map: &TagMapping) -> Vec<(String, String)> {
+    let (index, components) = split_components(node);
+    let mut files = vec![(format!("{}.swift", struct_name), render_to_swift_with_map(&index, struct_name, map))];
+    for c in &components {
+        files.push((format!("{}.swift", c.name), render_to_swift_with_map(&c.root, &c.name, map)));
+    }
+    files
+}
+
+/// Collects input values so a form submit can send them as the action payload,
+/// the same map magnetic.js builds from FormData on the web.
+const FORM_STATE: &str = "
+private final class FormState: ObservableObject {
+    @Published private var values: [String: String] = [:]
+
+    func binding(_ name: String, onChange: ((String) -> Void)? = nil) -> Binding<String> {
+        Binding(
+            get: { self.values[name] ?? \"\" },
+            set: { self.values[name] = $0; onChange?($0) }
+        )
+    }
+
+    func payload() -> [String: String] { values }
+
+    func clear() { values.removeAll() }
+}
+";
+
+/// Render context: every form in the tree (document order) and the index of
+/// the form enclosing the current node, plus any custom tag mappings.
+#[derive(Clone, Copy)]
+struct Ctx<'a> {
+    forms: &'a [&'a DomNode],
+    form: Option<usize>,
+    map: &'a TagMapping,
+}
+
+impl Ctx<'_> {
+    /// Swift property holding input state for the current scope
+    fn state_var(&self) -> String {
+        match self.form {
+            Some(i) => format!("form{}", i),
+            None => "fields".to_string(),
+        }
+    }
+
+    /// Swift statements submitting the enclosing form, if any
+    fn submit_call(&self) -> Option<String> {
+        let i = self.form?;
+        let action = self.forms[i].event("submit").unwrap_or("submit");
+        Some(format!(
+            "send(\"{}\", form{i}.payload()); form{i}.clear()",
+            escape_swift(action)
+        ))
+    }
+}
+
+fn collect_forms<'a>(node: &'a DomNode, forms: &mut Vec<&'a DomNode>) {
+    if node.tag == "form" {
+        forms.push(node);
+    }
+    for child in node.children_iter() {
+        collect_forms(child, forms);
+    }
+}
+
+/// Whether the tree has inputs that are not inside a form
+fn has_loose_inputs(node: &DomNode) -> bool {
+    match node.tag.as_str() {
+        "form" => false,
+        "input" => true,
+        _ => node.children_iter().iter().any(has_loose_inputs),
+    }
+}
+
 fn indent(buf: &mut String, depth: usize) {
     for _ in 0..depth {
         buf.push_str("    ");
     }
 }
 
-fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize) {
+fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize, ctx: Ctx) {
+    // Custom mappings are written verbatim, without built-in modifiers
+    if let Some(template) = ctx.map.lookup(node) {
+        write_swift_template(template, node, buf, depth, ctx);
+        return;
+    }
+
+    let start = buf.len();
     match node.tag.as_str() {
         // Skip magnetic:head nodes (not relevant for native)
-        "magnetic:head" => return,
+        "magnetic:head" => {}
+
+        // Component split out by render_to_swift_files → embed it
+        COMPONENT_TAG => {
+            if let Some(name) = placeholder_name(node) {
+                indent(buf, depth);
+                buf.push_str(&format!("{}(onAction: onAction, onActionPayload: onActionPayload)\n", name));
+            }
+        }
 
         // Headings → Text with font modifier
         "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
@@ -80,15 +221,25 @@ fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize) {
 
         // Button → Button view
         "button" => {
-            let action = node.event("click").unwrap_or("noop");
             let label = collect_text(node).unwrap_or_default();
+            let is_submit = node.attrs.as_ref()
+                .and_then(|a| a.get("type"))
+                .is_some_and(|t| t == "submit");
+            // Inside a form, a submit button (or one without its own click
+            // action) submits the form values
+            let on_click = match (node.event("click"), ctx.submit_call()) {
+                (Some(_), Some(submit)) if is_submit => submit,
+                (None, Some(submit)) => submit,
+                (action, _) => format!("onAction(\"{}\")", escape_swift(action.unwrap_or("noop"))),
+            };
             indent(buf, depth);
             buf.push_str(&format!(
                 "Button(\"{}\") {{\n",
                 escape_swift(&label)
             ));
             indent(buf, depth + 1);
-            buf.push_str(&format!("onAction(\"{}\")\n", escape_swift(action)));
+            buf.push_str(&on_click);
+            buf.push('\n');
             indent(buf, depth);
             buf.push_str("}\n");
         }
@@ -101,20 +252,29 @@ fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize) {
                 .unwrap_or("Enter text");
             let name = node.attrs.as_ref()
                 .and_then(|a| a.get("name"))
+                .or(node.key.as_ref())
                 .map(|s| s.as_str())
                 .unwrap_or("input");
+            let mut binding = format!("{}.binding(\"{}\")", ctx.state_var(), escape_swift(name));
+            if let Some(action) = node.event("input") {
+                binding.push_str(&format!(
+                    " {{ send(\"{}\", [\"value\": $0]) }}",
+                    escape_swift(action)
+                ));
+            }
 
             indent(buf, depth);
             buf.push_str(&format!(
-                "@State var {name}Text: String = \"\"\n"
-            ));
-            indent(buf, depth);
-            buf.push_str(&format!(
-                "TextField(\"{}\", text: ${name}Text)\n",
-                escape_swift(placeholder)
+                "TextField(\"{}\", text: {})\n",
+                escape_swift(placeholder), binding
             ));
             indent(buf, depth + 1);
             buf.push_str(".textFieldStyle(.roundedBorder)\n");
+            // Return submits the enclosing form, like a browser
+            if let Some(submit) = ctx.submit_call() {
+                indent(buf, depth + 1);
+                buf.push_str(&format!(".onSubmit {{ {} }}\n", submit));
+            }
         }
 
         // Anchor / Link → Button with navigate action
@@ -138,19 +298,35 @@ fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize) {
             buf.push_str(".foregroundColor(.accentColor)\n");
         }
 
+        // Image → AsyncImage
+        "img" => {
+            let src = node.attrs.as_ref()
+                .and_then(|a| a.get("src"))
+                .map(|s| s.as_str())
+                .unwrap_or("");
+            indent(buf, depth);
+            buf.push_str(&format!(
+                "AsyncImage(url: URL(string: \"{}\")) {{ image in\n",
+                escape_swift(src)
+            ));
+            indent(buf, depth + 1);
+            buf.push_str("image.resizable().scaledToFit()\n");
+            indent(buf, depth);
+            buf.push_str("} placeholder: {\n");
+            indent(buf, depth + 1);
+            buf.push_str("ProgressView()\n");
+            indent(buf, depth);
+            buf.push_str("}\n");
+        }
+
         // Form → VStack with submit
         "form" => {
-            let action = node.event("submit").unwrap_or("submit");
+            let ctx = Ctx { form: ctx.forms.iter().position(|f| std::ptr::eq(*f, node)), ..ctx };
             indent(buf, depth);
             buf.push_str("VStack(spacing: 12) {\n");
             for child in node.children_iter() {
-                write_swift_node(child, buf, depth + 1);
+                write_swift_node(child, buf, depth + 1, ctx);
             }
-            indent(buf, depth + 1);
-            buf.push_str(&format!(
-                "// Form submit: onAction(\"{}\")\n",
-                escape_swift(action)
-            ));
             indent(buf, depth);
             buf.push_str("}\n");
         }
@@ -160,7 +336,7 @@ fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize) {
             indent(buf, depth);
             buf.push_str("HStack(spacing: 12) {\n");
             for child in node.children_iter() {
-                write_swift_node(child, buf, depth + 1);
+                write_swift_node(child, buf, depth + 1, ctx);
             }
             indent(buf, depth);
             buf.push_str("}\n");
@@ -171,7 +347,7 @@ fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize) {
             indent(buf, depth);
             buf.push_str("VStack(alignment: .leading, spacing: 8) {\n");
             for child in node.children_iter() {
-                write_swift_node(child, buf, depth + 1);
+                write_swift_node(child, buf, depth + 1, ctx);
             }
             indent(buf, depth);
             buf.push_str("}\n");
@@ -179,7 +355,7 @@ fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize) {
 
         "li" => {
             for child in node.children_iter() {
-                write_swift_node(child, buf, depth);
+                write_swift_node(child, buf, depth, ctx);
             }
             if let Some(text) = &node.text {
                 indent(buf, depth);
@@ -187,6 +363,27 @@ fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize) {
             }
         }
 
+        // Table → Grid (iOS 16+), one GridRow per tr
+        "table" => {
+            indent(buf, depth);
+            buf.push_str("Grid(alignment: .leading, horizontalSpacing: 12, verticalSpacing: 8) {\n");
+            for (i, (row, header)) in table_rows(node).into_iter().enumerate() {
+                if i > 0 {
+                    indent(buf, depth + 1);
+                    buf.push_str("Divider()\n");
+                }
+                indent(buf, depth + 1);
+                buf.push_str("GridRow {\n");
+                for cell in row.children_iter() {
+                    write_swift_cell(cell, header, buf, depth + 2, ctx);
+                }
+                indent(buf, depth + 1);
+                buf.push_str("}\n");
+            }
+            indent(buf, depth);
+            buf.push_str("}\n");
+        }
+
         // Default: div and everything else → VStack or HStack
         _ => {
             let is_row = is_row_layout(node);
@@ -209,7 +406,7 @@ fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize) {
 
             // Children
             for child in node.children_iter() {
-                write_swift_node(child, buf, depth + 1);
+                write_swift_node(child, buf, depth + 1, ctx);
             }
 
             indent(buf, depth);
@@ -228,6 +425,130 @@ fn write_swift_node(node: &DomNode, buf: &mut String, depth: usize) {
             }
         }
     }
+
+    // Accessibility modifiers apply to whatever view was just written; li
+    // writes its children directly and has no view of its own
+    if buf.len() > start && node.tag != "li" {
+        let leaf = matches!(node.tag.as_str(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "span" | "label" | "input" | "img");
+        write_swift_a11y(node, buf, if leaf { depth + 1 } else { depth });
+    }
+}
+
+/// Write a custom mapping template; a `{children}` line renders the child
+/// nodes at that line's indentation
+fn write_swift_template(template: &str, node: &DomNode, buf: &mut String, depth: usize, ctx: Ctx) {
+    for line in template.lines() {
+        let extra = (line.len() - line.trim_start().len()) / 4;
+        if line.trim() == "{children}" {
+            for child in node.children_iter() {
+                write_swift_node(child, buf, depth + extra, ctx);
+            }
+            continue;
+        }
+        indent(buf, depth);
+        buf.push_str(&fill_template(line, node, escape_swift));
+        buf.push('\n');
+    }
+}
+
+/// Write accessibility modifiers for alt, aria-label, role and heading level
+fn write_swift_a11y(node: &DomNode, buf: &mut String, depth: usize) {
+    let attr = |k: &str| node.attrs.as_ref().and_then(|a| a.get(k)).map(|s| s.as_str());
+    let role = attr("role");
+    let mut mods = Vec::new();
+
+    match (attr("aria-label"), node.tag.as_str(), attr("alt")) {
+        (Some(label), _, _) => mods.push(format!(".accessibilityLabel(\"{}\")", escape_swift(label))),
+        // Empty alt marks a decorative image
+        (None, "img", Some("")) | (None, "img", None) => mods.push(".accessibilityHidden(true)".to_string()),
+        (None, "img", Some(alt)) => mods.push(format!(".accessibilityLabel(\"{}\")", escape_swift(alt))),
+        _ => {}
+    }
+
+    let level = match node.tag.as_str() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Some(&node.tag[1..]),
+        _ if role == Some("heading") => Some(attr("aria-level").unwrap_or("1")),
+        _ => None,
+    };
+    if let Some(level) = level.filter(|l| matches!(*l, "1" | "2" | "3" | "4" | "5" | "6")) {
+        mods.push(".accessibilityAddTraits(.isHeader)".to_string());
+        mods.push(format!(".accessibilityHeading(.h{})", level));
+    }
+
+    let trait_name = match role {
+        Some("button") => Some(".isButton"),
+        Some("link") => Some(".isLink"),
+        Some("img") | Some("image") => Some(".isImage"),
+        _ if node.tag == "a" => Some(".isLink"),
+        _ => None,
+    };
+    if let Some(t) = trait_name {
+        mods.push(format!(".accessibilityAddTraits({})", t));
+    }
+
+    for m in mods {
+        indent(buf, depth);
+        buf.push_str(&m);
+        buf.push('\n');
+    }
+}
+
+/// Write a table cell; text-only cells become a Text, others a VStack
+fn write_swift_cell(cell: &DomNode, header: bool, buf: &mut String, depth: usize, ctx: Ctx) {
+    let bold = header || cell.tag == "th";
+    let text_only = cell.children_iter().iter().all(|c| c.children.is_none() && c.events.is_none()
+        && matches!(c.tag.as_str(), "span" | "strong" | "b" | "em" | "i" | "small" | "code"));
+
+    indent(buf, depth);
+    if text_only {
+        let text = collect_text(cell).unwrap_or_default();
+        buf.push_str(&format!("Text(\"{}\")\n", escape_swift(&text)));
+        if bold {
+            indent(buf, depth + 1);
+            buf.push_str(".fontWeight(.bold)\n");
+        }
+    } else {
+        buf.push_str("VStack(alignment: .leading, spacing: 4) {\n");
+        for child in cell.children_iter() {
+            write_swift_node(child, buf, depth + 1, ctx);
+        }
+        indent(buf, depth);
+        buf.push_str("}\n");
+    }
+
+    let span = colspan(cell);
+    if span > 1 {
+        indent(buf, depth + 1);
+        buf.push_str(&format!(".gridCellColumns({})\n", span));
+    }
+}
+
+/// Rows of a table with a header flag (rows inside thead), looking through
+/// thead/tbody/tfoot sections
+fn table_rows(table: &DomNode) -> Vec<(&DomNode, bool)> {
+    let mut rows = Vec::new();
+    for child in table.children_iter() {
+        match child.tag.as_str() {
+            "tr" => rows.push((child, false)),
+            "thead" | "tbody" | "tfoot" => {
+                let header = child.tag == "thead";
+                for row in child.children_iter().iter().filter(|r| r.tag == "tr") {
+                    rows.push((row, header));
+                }
+            }
+            _ => {}
+        }
+    }
+    rows
+}
+
+fn colspan(cell: &DomNode) -> u32 {
+    cell.attrs.as_ref()
+        .and_then(|a| a.get("colspan"))
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(1)
 }
 
 /// Check if a node should be rendered as an HStack (horizontal)
@@ -294,5 +615,152 @@ mod tests {
         let swift = render_to_swift(&node, "HeadingView");
         assert!(swift.contains("Text(\"Hello World\")"));
         assert!(swift.contains(".font(.largeTitle)"));
+        assert!(swift.contains(".accessibilityHeading(.h1)"));
+    }
+
+    #[test]
+    fn test_accessibility_modifiers() {
+        let img = |alt: &str| DomNode {
+            tag: "img".into(),
+            key: None,
+            attrs: Some(HashMap::from([("src".into(), "/a.png".into()), ("alt".into(), alt.into())])),
+            events: None,
+            text: None,
+            html: None,
+            children: None,
+        };
+        let node = DomNode {
+            tag: "div".into(),
+            key: None,
+            attrs: Some(HashMap::from([("role".into(), "button".into()), ("aria-label".into(), "Open".into())])),
+            events: None,
+            text: None,
+            html: None,
+            children: Some(vec![img("Avatar"), img("")]),
+        };
+        let swift = render_to_swift(&node, "A11yView");
+        assert!(swift.contains("AsyncImage(url: URL(string: \"/a.png\"))"));
+        assert!(swift.contains(".accessibilityLabel(\"Avatar\")"));
+        assert!(swift.contains(".accessibilityHidden(true)"));
+        assert!(swift.contains("        }\n        .accessibilityLabel(\"Open\")\n        .accessibilityAddTraits(.isButton)\n"));
+    }
+
+    #[test]
+    fn test_form_submits_payload() {
+        let input = DomNode {
+            attrs: Some(HashMap::from([("name".into(), "title".into())])),
+            ..DomNode::text("input", "")
+        };
+        let form = DomNode {
+            tag: "form".into(),
+            key: None,
+            attrs: None,
+            events: Some(HashMap::from([("submit".into(), "add_task".into())])),
+            text: None,
+            html: None,
+            children: Some(vec![input, DomNode::text("button", "Add")]),
+        };
+        let swift = render_to_swift(&form, "FormView");
+        assert!(swift.contains("@StateObject private var form0 = FormState()"));
+        assert!(swift.contains("TextField(\"Enter text\", text: form0.binding(\"title\"))"));
+        assert!(swift.contains(".onSubmit { send(\"add_task\", form0.payload()); form0.clear() }"));
+        assert!(swift.contains("private final class FormState: ObservableObject"));
+    }
+
+    #[test]
+    fn test_component_files() {
+        let node = magnetic_dom::parse_node(r#"{
+            "tag": "div",
+            "children": [
+                { "tag": "h1", "text": "Tasks" },
+                { "tag": "ul", "children": [
+                    { "tag": "li", "attrs": { "data-component": "TaskRow" }, "children": [
+                        { "tag": "span", "text": "Write docs" },
+                        { "tag": "button", "events": { "click": "toggle_1" }, "text": "Done" }
+                    ] }
+                ] }
+            ]
+        }"#).unwrap();
+        let files = render_to_swift_files(&node, "MagneticAppView", &TagMapping::default());
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["MagneticAppView.swift", "TaskRow.swift"]);
+
+        let (_, index) = &files[0];
+        assert!(index.contains("TaskRow(onAction: onAction, onActionPayload: onActionPayload)\n"));
+        assert!(!index.contains("Write docs"));
+
+        let (_, row) = &files[1];
+        assert!(row.starts_with("import SwiftUI\n"));
+        assert!(row.contains("struct TaskRow: View {"));
+        assert!(row.contains("onAction(\"toggle_1\")"));
+    }
+
+    #[test]
+    fn test_custom_mapping() {
+        let maps = magnetic_dom::mapping::RenderMaps::from_json(r#"{ "swift": {
+            "imports": ["AVKit"],
+            "tags": {
+                "video": "VideoPlayer(player: AVPlayer(url: URL(string: \"{attr:src}\")!))",
+                "section": "GroupBox {\n    {children}\n}"
+            }
+        } }"#).unwrap();
+        let node = DomNode {
+            tag: "section".into(),
+            key: None,
+            attrs: None,
+            events: None,
+            text: None,
+            html: None,
+            children: Some(vec![DomNode {
+                tag: "video".into(),
+                key: None,
+                attrs: Some(HashMap::from([("src".into(), "https://example.com/a.mp4".into())])),
+                events: None,
+                text: None,
+                html: None,
+                children: None,
+            }]),
+        };
+        let swift = render_to_swift_with_map(&node, "Mapped", &maps.swift);
+        assert!(swift.starts_with("import SwiftUI\nimport AVKit\n\n"));
+        assert!(swift.contains("        GroupBox {\n            VideoPlayer(player: AVPlayer(url: URL(string: \"https://example.com/a.mp4\")!))\n        }\n"));
+    }
+
+    #[test]
+    fn test_table_render() {
+        let section = |tag: &str, cell: &str, text: &str| DomNode {
+            tag: tag.into(),
+            key: None,
+            attrs: None,
+            events: None,
+            text: None,
+            html: None,
+            children: Some(vec![DomNode {
+                tag: "tr".into(),
+                key: None,
+                attrs: None,
+                events: None,
+                text: None,
+                html: None,
+                children: Some(vec![DomNode {
+                    attrs: Some(HashMap::from([("colspan".into(), "2".into())])),
+                    ..DomNode::text(cell, text)
+                }]),
+            }]),
+        };
+        let node = DomNode {
+            tag: "table".into(),
+            key: None,
+            attrs: None,
+            events: None,
+            text: None,
+            html: None,
+            children: Some(vec![section("thead", "td", "Total"), section("tbody", "td", "42")]),
+        };
+        let swift = render_to_swift(&node, "TableView");
+        assert!(swift.contains("Grid(alignment: .leading"));
+        assert!(swift.contains("Text(\"Total\")\n                    .fontWeight(.bold)"));
+        assert!(swift.contains(".gridCellColumns(2)"));
+        assert!(!swift.contains("Text(\"42\")\n                    .fontWeight"));
     }
 }
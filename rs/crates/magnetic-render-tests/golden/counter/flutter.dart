@@ -0,0 +1,36 @@
+import 'package:flutter/material.dart';
+
+class MagneticApp extends StatelessWidget {
+  final void Function(String) onAction;
+
+  const MagneticApp({super.key, required this.onAction});
+
+  @override
+  Widget build(BuildContext context) {
+    return SingleChildScrollView(
+      padding: const EdgeInsets.all(16),
+      child: Column(
+        crossAxisAlignment: CrossAxisAlignment.start,
+        spacing: 8,
+        children: [
+          Text('Count: 0', style: Theme.of(context).textTheme.headlineLarge),
+          Row(
+            crossAxisAlignment: CrossAxisAlignment.center,
+            spacing: 8,
+            children: [
+              ElevatedButton(
+                onPressed: () => onAction('decrement'),
+                child: Text('-'),
+              ),
+              ElevatedButton(
+                onPressed: () => onAction('increment'),
+                child: Text('+'),
+              ),
+            ],
+          ),
+          Text('Say "hi" & press \$5'),
+        ],
+      ),
+    );
+  }
+}
@@ -0,0 +1,41 @@
+package com.magnetic.app
+
+import androidx.compose.foundation.layout.*
+import androidx.compose.foundation.text.KeyboardActions
+import androidx.compose.foundation.text.KeyboardOptions
+import androidx.compose.foundation.lazy.LazyColumn
+import androidx.compose.foundation.lazy.items
+import androidx.compose.material3.*
+import androidx.compose.runtime.*
+import androidx.compose.ui.Alignment
+import androidx.compose.ui.Modifier
+import androidx.compose.ui.semantics.*
+import androidx.compose.ui.text.font.FontWeight
+import androidx.compose.ui.text.input.ImeAction
+import androidx.compose.ui.unit.dp
+
+@Composable
+fun MagneticApp(
+    onAction: (String) -> Unit,
+    onActionPayload: (String, Map<String, String>) -> Unit = { action, _ -> onAction(action) },
+) {
+    Column(
+        verticalArrangement = Arrangement.spacedBy(8.dp),
+        modifier = Modifier.fillMaxWidth()
+    ) {
+        Text("Count: 0", style = MaterialTheme.typography.headlineLarge, modifier = Modifier.semantics { heading() })
+        Row(
+            horizontalArrangement = Arrangement.spacedBy(8.dp),
+            verticalAlignment = Alignment.CenterVertically,
+            modifier = Modifier.fillMaxWidth()
+        ) {
+            Button(onClick = { onAction("decrement") }) {
+                Text("-")
+            }
+            Button(onClick = { onAction("increment") }) {
+                Text("+")
+            }
+        }
+        Text("Say \"hi\" & press \$5")
+    }
+}
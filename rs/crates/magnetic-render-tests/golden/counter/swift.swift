@@ -0,0 +1,34 @@
+import SwiftUI
+
+struct MagneticAppView: View {
+    var onAction: (String) -> Void
+    var onActionPayload: ((String, [String: String]) -> Void)?
+
+    init(onAction: @escaping (String) -> Void, onActionPayload: ((String, [String: String]) -> Void)? = nil) {
+        self.onAction = onAction
+        self.onActionPayload = onActionPayload
+    }
+
+    var body: some View {
+        VStack(alignment: .leading, spacing: 8) {
+            Text("Count: 0")
+                .font(.largeTitle)
+                .fontWeight(.bold)
+                .accessibilityAddTraits(.isHeader)
+                .accessibilityHeading(.h1)
+            HStack(spacing: 8) {
+                Button("-") {
+                    onAction("decrement")
+                }
+                Button("+") {
+                    onAction("increment")
+                }
+            }
+            Text("Say \"hi\" & press $5")
+        }
+    }
+
+    private func send(_ action: String, _ payload: [String: String]) {
+        if let onActionPayload { onActionPayload(action, payload) } else { onAction(action) }
+    }
+}
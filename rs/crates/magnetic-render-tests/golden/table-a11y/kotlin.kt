@@ -0,0 +1,63 @@
+package com.magnetic.app
+
+import androidx.compose.foundation.layout.*
+import androidx.compose.foundation.text.KeyboardActions
+import androidx.compose.foundation.text.KeyboardOptions
+import androidx.compose.foundation.lazy.LazyColumn
+import androidx.compose.foundation.lazy.items
+import androidx.compose.material3.*
+import androidx.compose.runtime.*
+import androidx.compose.ui.Alignment
+import androidx.compose.ui.Modifier
+import androidx.compose.ui.semantics.*
+import androidx.compose.ui.text.font.FontWeight
+import androidx.compose.ui.text.input.ImeAction
+import androidx.compose.ui.unit.dp
+import coil.compose.AsyncImage
+
+@Composable
+fun MagneticApp(
+    onAction: (String) -> Unit,
+    onActionPayload: (String, Map<String, String>) -> Unit = { action, _ -> onAction(action) },
+) {
+    Column(
+        verticalArrangement = Arrangement.spacedBy(8.dp),
+        modifier = Modifier.fillMaxWidth()
+    ) {
+        Column(
+            verticalArrangement = Arrangement.spacedBy(8.dp),
+            modifier = Modifier.fillMaxWidth().semantics { heading() }
+        ) {
+            Text("Usage")
+        }
+        AsyncImage(model = "https://example.com/chart.png", contentDescription = "Usage chart")
+        AsyncImage(model = "https://example.com/divider.png", contentDescription = null)
+        Column(modifier = Modifier.fillMaxWidth()) {
+            Row(
+                horizontalArrangement = Arrangement.spacedBy(8.dp),
+                modifier = Modifier.fillMaxWidth().padding(vertical = 4.dp)
+            ) {
+                Text("Region", modifier = Modifier.weight(1f), fontWeight = FontWeight.Bold)
+                Text("Requests", modifier = Modifier.weight(1f), fontWeight = FontWeight.Bold)
+            }
+            HorizontalDivider()
+            Row(
+                horizontalArrangement = Arrangement.spacedBy(8.dp),
+                modifier = Modifier.fillMaxWidth().padding(vertical = 4.dp)
+            ) {
+                Text("eu-west", modifier = Modifier.weight(1f))
+                Text("1,204", modifier = Modifier.weight(1f))
+            }
+            HorizontalDivider()
+            Row(
+                horizontalArrangement = Arrangement.spacedBy(8.dp),
+                modifier = Modifier.fillMaxWidth().padding(vertical = 4.dp)
+            ) {
+                Text("No other regions", modifier = Modifier.weight(2f))
+            }
+        }
+        Button(onClick = { onAction("refresh") }, modifier = Modifier.semantics { contentDescription = "Refresh usage" }) {
+            Text("↻")
+        }
+    }
+}
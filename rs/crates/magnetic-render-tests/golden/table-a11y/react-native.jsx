@@ -0,0 +1,63 @@
+import React from 'react';
+import { View, Text, Pressable, TextInput, Image, ScrollView, StyleSheet } from 'react-native';
+
+export default function MagneticApp({ onAction }) {
+  return (
+    <ScrollView contentContainerStyle={styles.container}>
+      <View style={styles.column}>
+        <View style={styles.column}>
+          <Text>{"Usage"}</Text>
+        </View>
+        <Image style={styles.image} source={{ uri: "https://example.com/chart.png" }} accessibilityLabel={"Usage chart"} />
+        <Image style={styles.image} source={{ uri: "https://example.com/divider.png" }} accessibilityLabel={""} />
+        <View style={styles.column}>
+          <View style={styles.column}>
+            <View style={styles.column}>
+              <View style={styles.column}>
+                <Text>{"Region"}</Text>
+              </View>
+              <View style={styles.column}>
+                <Text>{"Requests"}</Text>
+              </View>
+            </View>
+          </View>
+          <View style={styles.column}>
+            <View style={styles.column}>
+              <View style={styles.column}>
+                <Text>{"eu-west"}</Text>
+              </View>
+              <View style={styles.column}>
+                <Text>{"1,204"}</Text>
+              </View>
+            </View>
+            <View style={styles.column}>
+              <View style={styles.column}>
+                <Text>{"No other regions"}</Text>
+              </View>
+            </View>
+          </View>
+        </View>
+        <Pressable style={styles.button} onPress={() => onAction("refresh")}>
+          <Text style={styles.buttonText}>{"↻"}</Text>
+        </Pressable>
+      </View>
+    </ScrollView>
+  );
+}
+
+const styles = StyleSheet.create({
+  container: { padding: 16, gap: 8 },
+  column: { gap: 8 },
+  row: { flexDirection: 'row', alignItems: 'center', gap: 8 },
+  h1: { fontSize: 32, fontWeight: '700' },
+  h2: { fontSize: 26, fontWeight: '700' },
+  h3: { fontSize: 22, fontWeight: '700' },
+  h4: { fontSize: 20, fontWeight: '600' },
+  h5: { fontSize: 18, fontWeight: '600' },
+  h6: { fontSize: 16, fontWeight: '600' },
+  button: { backgroundColor: '#2563eb', paddingVertical: 8, paddingHorizontal: 16, borderRadius: 8 },
+  buttonText: { color: '#ffffff', fontWeight: '600' },
+  link: { color: '#2563eb' },
+  input: { borderWidth: 1, borderColor: '#d1d5db', borderRadius: 8, padding: 8 },
+  image: { width: '100%', aspectRatio: 16 / 9 },
+});
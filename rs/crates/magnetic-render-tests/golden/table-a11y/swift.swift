@@ -0,0 +1,59 @@
+import SwiftUI
+
+struct MagneticAppView: View {
+    var onAction: (String) -> Void
+    var onActionPayload: ((String, [String: String]) -> Void)?
+
+    init(onAction: @escaping (String) -> Void, onActionPayload: ((String, [String: String]) -> Void)? = nil) {
+        self.onAction = onAction
+        self.onActionPayload = onActionPayload
+    }
+
+    var body: some View {
+        VStack(alignment: .leading, spacing: 8) {
+            VStack(alignment: .leading, spacing: 8) {
+                Text("Usage")
+            }
+            .accessibilityAddTraits(.isHeader)
+            .accessibilityHeading(.h2)
+            AsyncImage(url: URL(string: "https://example.com/chart.png")) { image in
+                image.resizable().scaledToFit()
+            } placeholder: {
+                ProgressView()
+            }
+                .accessibilityLabel("Usage chart")
+            AsyncImage(url: URL(string: "https://example.com/divider.png")) { image in
+                image.resizable().scaledToFit()
+            } placeholder: {
+                ProgressView()
+            }
+                .accessibilityHidden(true)
+            Grid(alignment: .leading, horizontalSpacing: 12, verticalSpacing: 8) {
+                GridRow {
+                    Text("Region")
+                        .fontWeight(.bold)
+                    Text("Requests")
+                        .fontWeight(.bold)
+                }
+                Divider()
+                GridRow {
+                    Text("eu-west")
+                    Text("1,204")
+                }
+                Divider()
+                GridRow {
+                    Text("No other regions")
+                        .gridCellColumns(2)
+                }
+            }
+            Button("↻") {
+                onAction("refresh")
+            }
+            .accessibilityLabel("Refresh usage")
+        }
+    }
+
+    private func send(_ action: String, _ payload: [String: String]) {
+        if let onActionPayload { onActionPayload(action, payload) } else { onAction(action) }
+    }
+}
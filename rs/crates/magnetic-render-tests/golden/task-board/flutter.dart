@@ -0,0 +1,77 @@
+import 'package:flutter/material.dart';
+
+class MagneticApp extends StatelessWidget {
+  final void Function(String) onAction;
+
+  const MagneticApp({super.key, required this.onAction});
+
+  @override
+  Widget build(BuildContext context) {
+    return SingleChildScrollView(
+      padding: const EdgeInsets.all(16),
+      child: Column(
+        crossAxisAlignment: CrossAxisAlignment.start,
+        spacing: 8,
+        children: [
+          Row(
+            spacing: 8,
+            children: [
+              TextButton(
+                onPressed: () => onAction('navigate:/'),
+                child: Text('Tasks'),
+              ),
+              TextButton(
+                onPressed: () => onAction('navigate:/about'),
+                child: Text('About'),
+              ),
+            ],
+          ),
+          Column(
+            crossAxisAlignment: CrossAxisAlignment.stretch,
+            children: [
+              TextField(
+                decoration: InputDecoration(hintText: 'What needs doing?', border: const OutlineInputBorder()),
+                onChanged: (_) => onAction('set_title'),
+                onSubmitted: (_) => onAction('add_task'),
+              ),
+              ElevatedButton(
+                onPressed: () => onAction('add_task'),
+                child: Text('Add'),
+              ),
+            ],
+          ),
+          Column(
+            crossAxisAlignment: CrossAxisAlignment.start,
+            spacing: 8,
+            children: [
+              KeyedSubtree(
+                key: const ValueKey('t1'),
+                child: Column(
+                  crossAxisAlignment: CrossAxisAlignment.start,
+                  spacing: 8,
+                  children: [
+                    Text('Write docs'),
+                    ElevatedButton(
+                      onPressed: () => onAction('toggle_t1'),
+                      child: Text('Done'),
+                    ),
+                  ],
+                ),
+              ),
+              KeyedSubtree(
+                key: const ValueKey('t2'),
+                child: Column(
+                  crossAxisAlignment: CrossAxisAlignment.start,
+                  spacing: 8,
+                  children: [
+                    Text('Ship it'),
+                  ],
+                ),
+              ),
+            ],
+          ),
+        ],
+      ),
+    );
+  }
+}
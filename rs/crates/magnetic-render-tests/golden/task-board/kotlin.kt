@@ -0,0 +1,79 @@
+package com.magnetic.app
+
+import androidx.compose.foundation.layout.*
+import androidx.compose.foundation.text.KeyboardActions
+import androidx.compose.foundation.text.KeyboardOptions
+import androidx.compose.foundation.lazy.LazyColumn
+import androidx.compose.foundation.lazy.items
+import androidx.compose.material3.*
+import androidx.compose.runtime.*
+import androidx.compose.ui.Alignment
+import androidx.compose.ui.Modifier
+import androidx.compose.ui.semantics.*
+import androidx.compose.ui.text.font.FontWeight
+import androidx.compose.ui.text.input.ImeAction
+import androidx.compose.ui.unit.dp
+
+@Composable
+fun MagneticApp(
+    onAction: (String) -> Unit,
+    onActionPayload: (String, Map<String, String>) -> Unit = { action, _ -> onAction(action) },
+) {
+    val form0 = remember { FormState() }
+    Column(
+        verticalArrangement = Arrangement.spacedBy(8.dp),
+        modifier = Modifier.fillMaxWidth()
+    ) {
+        Row(
+            horizontalArrangement = Arrangement.spacedBy(8.dp),
+            modifier = Modifier.fillMaxWidth()
+        ) {
+            TextButton(onClick = { onAction("navigate:/") }) {
+                Text("Tasks")
+            }
+            TextButton(onClick = { onAction("navigate:/about") }) {
+                Text("About")
+            }
+        }
+        Column(
+            modifier = Modifier.fillMaxWidth()
+        ) {
+            OutlinedTextField(
+                value = form0["title"],
+                onValueChange = { form0["title"] = it; onActionPayload("set_title", mapOf("value" to it)) },
+                placeholder = { Text("What needs doing?") },
+                keyboardOptions = KeyboardOptions(imeAction = ImeAction.Done),
+                keyboardActions = KeyboardActions(onDone = { onActionPayload("add_task", form0.payload()); form0.clear() }),
+                modifier = Modifier.fillMaxWidth()
+            )
+            Button(onClick = { onActionPayload("add_task", form0.payload()); form0.clear() }) {
+                Text("Add")
+            }
+        }
+        LazyColumn {
+            item(key = "t1") {
+                Text("Write docs")
+                Button(onClick = { onAction("toggle_t1") }) {
+                    Text("Done")
+                }
+            }
+            item(key = "t2") {
+                Text("Ship it")
+            }
+        }
+    }
+}
+
+private class FormState {
+    private val values = mutableStateMapOf<String, String>()
+
+    operator fun get(name: String): String = values[name] ?: ""
+
+    operator fun set(name: String, value: String) {
+        values[name] = value
+    }
+
+    fun payload(): Map<String, String> = values.toMap()
+
+    fun clear() = values.clear()
+}
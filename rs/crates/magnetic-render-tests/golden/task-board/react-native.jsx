@@ -0,0 +1,53 @@
+import React from 'react';
+import { View, Text, Pressable, TextInput, Image, ScrollView, StyleSheet } from 'react-native';
+
+export default function MagneticApp({ onAction }) {
+  return (
+    <ScrollView contentContainerStyle={styles.container}>
+      <View style={styles.column}>
+        <View style={styles.row}>
+          <Pressable onPress={() => onAction("navigate:/")}>
+            <Text style={styles.link}>{"Tasks"}</Text>
+          </Pressable>
+          <Pressable onPress={() => onAction("navigate:/about")}>
+            <Text style={styles.link}>{"About"}</Text>
+          </Pressable>
+        </View>
+        <View style={styles.column}>
+          <TextInput style={styles.input} placeholder={"What needs doing?"} onChangeText={() => onAction("set_title")} onSubmitEditing={() => onAction("add_task")} />
+          <Pressable style={styles.button} onPress={() => onAction("add_task")}>
+            <Text style={styles.buttonText}>{"Add"}</Text>
+          </Pressable>
+        </View>
+        <View style={styles.column}>
+          <View key={"t1"} style={styles.column}>
+            <Text>{"Write docs"}</Text>
+            <Pressable style={styles.button} onPress={() => onAction("toggle_t1")}>
+              <Text style={styles.buttonText}>{"Done"}</Text>
+            </Pressable>
+          </View>
+          <View key={"t2"} style={styles.column}>
+            <Text>{"Ship it"}</Text>
+          </View>
+        </View>
+      </View>
+    </ScrollView>
+  );
+}
+
+const styles = StyleSheet.create({
+  container: { padding: 16, gap: 8 },
+  column: { gap: 8 },
+  row: { flexDirection: 'row', alignItems: 'center', gap: 8 },
+  h1: { fontSize: 32, fontWeight: '700' },
+  h2: { fontSize: 26, fontWeight: '700' },
+  h3: { fontSize: 22, fontWeight: '700' },
+  h4: { fontSize: 20, fontWeight: '600' },
+  h5: { fontSize: 18, fontWeight: '600' },
+  h6: { fontSize: 16, fontWeight: '600' },
+  button: { backgroundColor: '#2563eb', paddingVertical: 8, paddingHorizontal: 16, borderRadius: 8 },
+  buttonText: { color: '#ffffff', fontWeight: '600' },
+  link: { color: '#2563eb' },
+  input: { borderWidth: 1, borderColor: '#d1d5db', borderRadius: 8, padding: 8 },
+  image: { width: '100%', aspectRatio: 16 / 9 },
+});
@@ -0,0 +1,68 @@
+import SwiftUI
+
+struct MagneticAppView: View {
+    var onAction: (String) -> Void
+    var onActionPayload: ((String, [String: String]) -> Void)?
+    @StateObject private var form0 = FormState()
+
+    init(onAction: @escaping (String) -> Void, onActionPayload: ((String, [String: String]) -> Void)? = nil) {
+        self.onAction = onAction
+        self.onActionPayload = onActionPayload
+    }
+
+    var body: some View {
+        VStack(alignment: .leading, spacing: 8) {
+            HStack(spacing: 12) {
+                Button("Tasks") {
+                    onAction("navigate:/")
+                }
+                .buttonStyle(.plain)
+                .foregroundColor(.accentColor)
+                .accessibilityAddTraits(.isLink)
+                Button("About") {
+                    onAction("navigate:/about")
+                }
+                .buttonStyle(.plain)
+                .foregroundColor(.accentColor)
+                .accessibilityAddTraits(.isLink)
+            }
+            VStack(spacing: 12) {
+                TextField("What needs doing?", text: form0.binding("title") { send("set_title", ["value": $0]) })
+                    .textFieldStyle(.roundedBorder)
+                    .onSubmit { send("add_task", form0.payload()); form0.clear() }
+                Button("Add") {
+                    send("add_task", form0.payload()); form0.clear()
+                }
+            }
+            VStack(alignment: .leading, spacing: 8) {
+                Text("Write docs")
+                Button("Done") {
+                    onAction("toggle_t1")
+                }
+                Text("Ship it")
+            }
+        }
+        .padding()
+        .background(Color(.systemBackground))
+        .cornerRadius(16)
+    }
+
+    private func send(_ action: String, _ payload: [String: String]) {
+        if let onActionPayload { onActionPayload(action, payload) } else { onAction(action) }
+    }
+}
+
+private final class FormState: ObservableObject {
+    @Published private var values: [String: String] = [:]
+
+    func binding(_ name: String, onChange: ((String) -> Void)? = nil) -> Binding<String> {
+        Binding(
+            get: { self.values[name] ?? "" },
+            set: { self.values[name] = $0; onChange?($0) }
+        )
+    }
+
+    func payload() -> [String: String] { values }
+
+    func clear() { values.removeAll() }
+}
@@ -0,0 +1,166 @@
+//! magnetic-render-tests — Golden-file tests for every Magnetic renderer
+//!
+//! Loads DomNode fixtures from a directory, runs each registered renderer over
+//! them and compares the output byte-for-byte with committed golden files:
+//!
+//!   fixtures/counter.json         → DomNode (or `{ "root": DomNode }` snapshot)
+//!   golden/counter/kotlin.kt      → expected render_to_kotlin output
+//!   golden/counter/swift.swift    → expected render_to_swift output
+//!   ...
+//!
+//! `cargo test` runs this crate's own fixtures. After an intended renderer
+//! change, rewrite the goldens with `cargo run -- --bless` and review the diff.
+//! PDF output is left out: it embeds creation dates and document ids.
+
+use magnetic_dom::{parse_node, parse_snapshot, DomNode};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A renderer under test: golden files are named `<name>.<ext>`.
+pub struct Renderer {
+    pub name: &'static str,
+    pub ext: &'static str,
+    pub render: fn(&DomNode) -> String,
+}
+
+/// Every renderer with text output, in golden-file order.
+pub const RENDERERS: &[Renderer] = &[
+    Renderer { name: "html", ext: "html", render: magnetic_render_html::render_to_html },
+    Renderer { name: "kotlin", ext: "kt", render: |n| magnetic_render_kotlin::render_to_kotlin(n, "MagneticApp") },
+    Renderer { name: "swift", ext: "swift", render: |n| magnetic_render_swift::render_to_swift(n, "MagneticAppView") },
+    Renderer { name: "flutter", ext: "dart", render: |n| magnetic_render_flutter::render_to_flutter(n, "MagneticApp") },
+    Renderer { name: "react-native", ext: "jsx", render: |n| magnetic_render_react_native::render_to_react_native(n, "MagneticApp") },
+    Renderer { name: "text", ext: "txt", render: magnetic_render_text::render_to_text },
+];
+
+/// Result of one fixture × renderer case.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Pass,
+    /// Golden file written (bless mode)
+    Blessed,
+    /// No golden file yet
+    Missing,
+    /// Output differs; holds a short diff
+    Mismatch(String),
+}
+
+#[derive(Debug)]
+pub struct Case {
+    pub fixture: String,
+    pub renderer: &'static str,
+    pub golden: PathBuf,
+    pub outcome: Outcome,
+}
+
+impl Case {
+    pub fn failed(&self) -> bool {
+        matches!(self.outcome, Outcome::Missing | Outcome::Mismatch(_))
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            Outcome::Pass => write!(f, "ok       {} [{}]", self.fixture, self.renderer),
+            Outcome::Blessed => write!(f, "blessed  {} [{}] → {}", self.fixture, self.renderer, self.golden.display()),
+            Outcome::Missing => write!(f, "MISSING  {} [{}]: no {}", self.fixture, self.renderer, self.golden.display()),
+            Outcome::Mismatch(diff) => write!(f, "FAILED   {} [{}]\n{}", self.fixture, self.renderer, diff),
+        }
+    }
+}
+
+/// Run every renderer over every `*.json` fixture in `fixtures`, comparing
+/// against (or, with `bless`, rewriting) the goldens under `golden`.
+pub fn run(fixtures: &Path, golden: &Path, bless: bool) -> Result<Vec<Case>, String> {
+    let mut cases = Vec::new();
+    for (name, node) in load_fixtures(fixtures)? {
+        let dir = golden.join(&name);
+        for renderer in RENDERERS {
+            let path = dir.join(format!("{}.{}", renderer.name, renderer.ext));
+            let actual = (renderer.render)(&node);
+            let outcome = match std::fs::read_to_string(&path) {
+                Ok(expected) if expected == actual => Outcome::Pass,
+                _ if bless => {
+                    std::fs::create_dir_all(&dir)
+                        .map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+                    std::fs::write(&path, &actual)
+                        .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+                    Outcome::Blessed
+                }
+                Ok(expected) => Outcome::Mismatch(diff(&expected, &actual)),
+                Err(_) => Outcome::Missing,
+            };
+            cases.push(Case { fixture: name.clone(), renderer: renderer.name, golden: path, outcome });
+        }
+    }
+    Ok(cases)
+}
+
+/// Fixtures sorted by name (file stem)
+fn load_fixtures(dir: &Path) -> Result<Vec<(String, DomNode)>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Cannot read fixtures {}: {}", dir.display(), e))?;
+    let mut fixtures = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let json = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let node = parse_snapshot(&json).map(|s| s.root)
+            .or_else(|_| parse_node(&json))
+            .map_err(|e| format!("Invalid fixture {}: {}", path.display(), e))?;
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        fixtures.push((name, node));
+    }
+    fixtures.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(fixtures)
+}
+
+/// Line diff around the first difference (up to a few lines of context)
+fn diff(expected: &str, actual: &str) -> String {
+    let exp: Vec<&str> = expected.lines().collect();
+    let act: Vec<&str> = actual.lines().collect();
+    let first = exp.iter().zip(&act).position(|(e, a)| e != a)
+        .unwrap_or(exp.len().min(act.len()));
+
+    let mut out = format!("    first difference at line {}:\n", first + 1);
+    for line in exp.iter().skip(first).take(5) {
+        out.push_str(&format!("    - {}\n", line));
+    }
+    for line in act.iter().skip(first).take(5) {
+        out.push_str(&format!("    + {}\n", line));
+    }
+    if exp.len() != act.len() {
+        out.push_str(&format!("    ({} lines expected, {} actual)\n", exp.len(), act.len()));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_files() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let cases = run(&root.join("fixtures"), &root.join("golden"), false).unwrap();
+        assert!(!cases.is_empty());
+        let failures: Vec<String> = cases.iter().filter(|c| c.failed()).map(|c| c.to_string()).collect();
+        assert!(
+            failures.is_empty(),
+            "{} golden mismatches (if intended, run `cargo run -- --bless`):\n{}",
+            failures.len(),
+            failures.join("\n")
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let d = diff("a\nb\nc\n", "a\nx\nc\nd\n");
+        assert!(d.starts_with("    first difference at line 2:\n    - b\n    - c\n    + x\n"));
+        assert!(d.ends_with("(3 lines expected, 4 actual)\n"));
+    }
+}
@@ -0,0 +1,42 @@
+//! magnetic-render-tests — Compare renderer output against golden files
+//!
+//! Usage:
+//!   magnetic-render-tests
+//!   magnetic-render-tests --bless
+//!   magnetic-render-tests --fixtures my/fixtures --golden my/golden
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures").to_string();
+    let mut golden = concat!(env!("CARGO_MANIFEST_DIR"), "/golden").to_string();
+    let mut bless = false;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--fixtures" => { i += 1; fixtures = args[i].clone(); }
+            "--golden" => { i += 1; golden = args[i].clone(); }
+            "--bless" => bless = true,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let cases = match magnetic_render_tests::run(fixtures.as_ref(), golden.as_ref(), bless) {
+        Ok(cases) => cases,
+        Err(e) => {
+            eprintln!("[magnetic-render-tests] {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    for case in &cases {
+        println!("{}", case);
+    }
+    let failed = cases.iter().filter(|c| c.failed()).count();
+    println!("\n{} cases, {} failed", cases.len(), failed);
+    if failed > 0 {
+        eprintln!("[magnetic-render-tests] if the changes are intended, rerun with --bless");
+        std::process::exit(1);
+    }
+}
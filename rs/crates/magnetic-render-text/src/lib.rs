@@ -0,0 +1,226 @@
+//! magnetic-render-text — Render Magnetic DomNode trees to a plain-text outline
+//!
+//! Produces an indented, human-readable outline of the tree: one line per
+//! element, labelled by role, with text content, button labels and input
+//! placeholders inline. Useful as a golden-test snapshot format and as a quick
+//! screen-reader-style check of what a view actually says.
+//!
+//! Mapping strategy:
+//!   div/section  → tag.class (container, children indented)
+//!   h1..h6       → heading N "text"
+//!   p / span     → text "text"
+//!   label        → label "text"
+//!   button       → button "label" (click → action)
+//!   input        → textbox "placeholder" (input → action)
+//!   form         → form (submit → action)
+//!   a / Link     → link "label" → href
+//!   img          → image "alt"
+//!   ul/ol        → list / ordered list, li → item
+//!   magnetic:head → skipped
+
+use magnetic_dom::DomNode;
+
+/// Render a DomNode tree to an indented plain-text outline.
+pub fn render_to_text(node: &DomNode) -> String {
+    let mut buf = String::with_capacity(1024);
+    write_text_node(node, &mut buf, 0);
+    buf
+}
+
+fn indent(buf: &mut String, depth: usize) {
+    for _ in 0..depth {
+        buf.push_str("  ");
+    }
+}
+
+fn write_text_node(node: &DomNode, buf: &mut String, depth: usize) {
+    match node.tag.as_str() {
+        // Skip magnetic:head nodes (no visible content)
+        "magnetic:head" => {}
+
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let text = collect_text(node).unwrap_or_default();
+            indent(buf, depth);
+            buf.push_str(&format!("heading {} {}\n", &node.tag[1..], quote(&text)));
+        }
+
+        "p" | "span" | "label" => {
+            if let Some(text) = collect_text(node) {
+                let role = if node.tag == "label" { "label" } else { "text" };
+                indent(buf, depth);
+                buf.push_str(&format!("{} {}\n", role, quote(&text)));
+            }
+        }
+
+        "button" => {
+            let label = collect_text(node).unwrap_or_default();
+            indent(buf, depth);
+            buf.push_str(&format!("button {}", quote(&label)));
+            write_events(node, buf);
+            buf.push('\n');
+        }
+
+        "input" | "textarea" => {
+            let attrs = node.attrs.as_ref();
+            let placeholder = attrs.and_then(|a| a.get("placeholder")).map(|s| s.as_str()).unwrap_or("");
+            let role = match attrs.and_then(|a| a.get("type")).map(|s| s.as_str()) {
+                Some("checkbox") => "checkbox",
+                Some("radio") => "radio",
+                Some("password") => "password",
+                _ => "textbox",
+            };
+            indent(buf, depth);
+            buf.push_str(&format!("{} {}", role, quote(placeholder)));
+            if let Some(value) = attrs.and_then(|a| a.get("value")).filter(|v| !v.is_empty()) {
+                buf.push_str(&format!(" = {}", quote(value)));
+            }
+            write_events(node, buf);
+            buf.push('\n');
+        }
+
+        "a" => {
+            let label = collect_text(node).unwrap_or_default();
+            indent(buf, depth);
+            buf.push_str(&format!("link {}", quote(&label)));
+            if let Some(href) = node.attrs.as_ref().and_then(|a| a.get("href")) {
+                buf.push_str(&format!(" → {}", href));
+            }
+            write_events(node, buf);
+            buf.push('\n');
+        }
+
+        "img" => {
+            let alt = node.attrs.as_ref()
+                .and_then(|a| a.get("alt"))
+                .map(|s| s.as_str())
+                .unwrap_or("");
+            indent(buf, depth);
+            buf.push_str(&format!("image {}\n", quote(alt)));
+        }
+
+        "ul" | "ol" => {
+            indent(buf, depth);
+            buf.push_str(if node.tag == "ol" { "ordered list" } else { "list" });
+            buf.push('\n');
+            write_children(node, buf, depth + 1);
+        }
+
+        "li" => {
+            indent(buf, depth);
+            buf.push_str("item");
+            if let Some(text) = &node.text {
+                buf.push_str(&format!(" {}", quote(text)));
+            }
+            buf.push('\n');
+            write_children(node, buf, depth + 1);
+        }
+
+        // Default: containers (div, form, nav, section, ...)
+        _ => {
+            indent(buf, depth);
+            buf.push_str(&node.tag);
+            if let Some(class) = node.class() {
+                for c in class.split_whitespace() {
+                    buf.push('.');
+                    buf.push_str(c);
+                }
+            }
+            write_events(node, buf);
+            buf.push('\n');
+            if let Some(text) = &node.text {
+                indent(buf, depth + 1);
+                buf.push_str(&format!("text {}\n", quote(text)));
+            }
+            write_children(node, buf, depth + 1);
+        }
+    }
+}
+
+fn write_children(node: &DomNode, buf: &mut String, depth: usize) {
+    for child in node.children_iter() {
+        write_text_node(child, buf, depth);
+    }
+}
+
+/// Append ` (event → action, ...)` in a stable (sorted) order
+fn write_events(node: &DomNode, buf: &mut String) {
+    let Some(events) = &node.events else { return };
+    if events.is_empty() { return; }
+    let mut pairs: Vec<_> = events.iter().collect();
+    pairs.sort();
+    let list: Vec<String> = pairs.iter().map(|(e, a)| format!("{} → {}", e, a)).collect();
+    buf.push_str(&format!(" ({})", list.join(", ")));
+}
+
+/// Collect all text content from a node and its children
+fn collect_text(node: &DomNode) -> Option<String> {
+    let mut text = String::new();
+    collect_text_inner(node, &mut text);
+    if text.is_empty() { None } else { Some(text) }
+}
+
+fn collect_text_inner(node: &DomNode, buf: &mut String) {
+    if let Some(t) = &node.text {
+        buf.push_str(t);
+    }
+    for child in node.children_iter() {
+        collect_text_inner(child, buf);
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use magnetic_dom::DomNode;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_outline() {
+        let node = DomNode {
+            tag: "div".into(),
+            key: None,
+            attrs: Some(HashMap::from([("class".into(), "app main".into())])),
+            events: None,
+            text: None,
+            html: None,
+            children: Some(vec![
+                DomNode::text("h1", "Counter"),
+                DomNode {
+                    events: Some(HashMap::from([("click".into(), "increment".into())])),
+                    ..DomNode::text("button", "+")
+                },
+                DomNode {
+                    tag: "input".into(),
+                    key: None,
+                    attrs: Some(HashMap::from([("placeholder".into(), "Add task".into())])),
+                    events: Some(HashMap::from([("input".into(), "set_title".into())])),
+                    text: None,
+                    html: None,
+                    children: None,
+                },
+                DomNode {
+                    tag: "ul".into(),
+                    key: None,
+                    attrs: None,
+                    events: None,
+                    text: None,
+                    html: None,
+                    children: Some(vec![DomNode::text("li", "Say \"hi\"")]),
+                },
+            ]),
+        };
+        let expected = "\
+div.app.main
+  heading 1 \"Counter\"
+  button \"+\" (click → increment)
+  textbox \"Add task\" (input → set_title)
+  list
+    item \"Say \\\"hi\\\"\"
+";
+        assert_eq!(render_to_text(&node), expected);
+    }
+}
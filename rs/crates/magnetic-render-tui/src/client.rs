@@ -0,0 +1,227 @@
+//! Action client and terminal event loop.
+//!
+//! Talks to a Magnetic server over its regular HTTP endpoints: every action is
+//! a `POST /actions/<name>` that returns the next snapshot (`{"root":...}`),
+//! exactly like the browser runtime. The `magnetic_sid` cookie is captured
+//! from the first response so the terminal keeps its own session, and the
+//! `magnetic_csrf` cookie is echoed in `X-Magnetic-CSRF` as the server
+//! requires for session-bound actions.
+//!
+//! Keys:
+//!   Tab / ↓      next focus target      Shift-Tab / ↑   previous
+//!   Enter        activate (click / submit form)
+//!   PgUp / PgDn  scroll                 Ctrl-R          reload
+//!   q / Esc      quit (Ctrl-C while editing an input)
+
+use crate::{build_view, draw, ItemKind, TuiState, TuiView};
+use magnetic_dom::{parse_snapshot, DomNode};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::DefaultTerminal;
+use std::time::Duration;
+
+/// HTTP client for one Magnetic app session.
+pub struct Client {
+    base: String,
+    agent: ureq::Agent,
+    session: Option<String>,
+    csrf: Option<String>,
+    path: String,
+}
+
+impl Client {
+    pub fn new(base: &str) -> Self {
+        Client {
+            base: base.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .build(),
+            session: None,
+            csrf: None,
+            path: "/".to_string(),
+        }
+    }
+
+    /// Current route (last navigate target)
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Render a route by sending the `navigate` action
+    pub fn navigate(&mut self, path: &str) -> Result<DomNode, String> {
+        self.path = path.to_string();
+        self.send("navigate", serde_json::json!({ "path": path }))
+    }
+
+    /// POST an action and return the resulting DOM tree.
+    /// `navigate:<path>` actions are translated like the browser runtime does.
+    pub fn send(&mut self, action: &str, payload: serde_json::Value) -> Result<DomNode, String> {
+        if let Some(path) = action.strip_prefix("navigate:") {
+            return self.navigate(path);
+        }
+
+        let url = format!("{}/actions/{}", self.base, urlencode(action));
+        let body = serde_json::json!({ "action": action, "payload": payload }).to_string();
+        let mut req = self.agent.post(&url).set("Content-Type", "application/json");
+        let mut cookies = Vec::new();
+        if let Some(sid) = &self.session {
+            cookies.push(format!("magnetic_sid={}", sid));
+        }
+        if let Some(token) = &self.csrf {
+            cookies.push(format!("magnetic_csrf={}", token));
+            req = req.set("X-Magnetic-CSRF", token);
+        }
+        if !cookies.is_empty() {
+            req = req.set("Cookie", &cookies.join("; "));
+        }
+        let resp = req.send_string(&body).map_err(|e| format!("POST {}: {}", url, e))?;
+
+        for cookie in resp.all("set-cookie") {
+            let Some((name, rest)) = cookie.split_once('=') else { continue };
+            let value = rest.split(';').next().unwrap_or("").trim();
+            if value.is_empty() {
+                continue;
+            }
+            match name {
+                "magnetic_sid" => self.session = Some(value.to_string()),
+                "magnetic_csrf" => self.csrf = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        let text = resp.into_string().map_err(|e| format!("read response: {}", e))?;
+        parse_snapshot(&text)
+            .map(|s| s.root)
+            .map_err(|e| format!("invalid snapshot: {}", e))
+    }
+}
+
+/// Run the interactive terminal UI against `base` (e.g. `http://localhost:3003`),
+/// starting at route `path`. Blocks until the user quits.
+pub fn run(base: &str, path: &str) -> Result<(), String> {
+    let mut client = Client::new(base);
+    let dom = client.navigate(path)?;
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut client, dom, base);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    client: &mut Client,
+    dom: DomNode,
+    base: &str,
+) -> Result<(), String> {
+    let mut view = build_view(&dom);
+    let mut state = TuiState::default();
+
+    loop {
+        terminal.draw(|frame| {
+            let [body, status] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)])
+                .areas(frame.area());
+            state.scroll_to_focus(&view, body.height.saturating_sub(2));
+            let title = format!(" {}{} ", base, client.path());
+            draw(frame, body, &view, &state, &title);
+            let hint = if state.status.is_empty() {
+                "Tab: next  Enter: activate  Ctrl-R: reload  q: quit".to_string()
+            } else {
+                state.status.clone()
+            };
+            frame.render_widget(Line::styled(hint, Style::default().fg(Color::DarkGray)), status);
+        }).map_err(|e| format!("draw: {}", e))?;
+
+        if !event::poll(Duration::from_millis(250)).map_err(|e| e.to_string())? {
+            continue;
+        }
+        let Event::Key(key) = event::read().map_err(|e| e.to_string())? else { continue };
+        if key.kind != KeyEventKind::Press { continue; }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let focused = state.focused_item(&view).map(|i| view.items[i].clone());
+        let editing = matches!(focused.as_ref().map(|it| &it.kind), Some(ItemKind::Input { .. }));
+
+        let next: Option<Result<DomNode, String>> = match key.code {
+            KeyCode::Char('c') if ctrl => return Ok(()),
+            KeyCode::Char('r') if ctrl => {
+                let path = client.path().to_string();
+                Some(client.navigate(&path))
+            }
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char('q') if !editing => return Ok(()),
+            KeyCode::Tab | KeyCode::Down => { state.focus_next(&view); None }
+            KeyCode::BackTab | KeyCode::Up => { state.focus_prev(&view); None }
+            KeyCode::PageDown => { state.page(&view, true); None }
+            KeyCode::PageUp => { state.page(&view, false); None }
+            KeyCode::Enter => focused.and_then(|it| activate(client, &view, &mut state, &it.kind)),
+            KeyCode::Backspace | KeyCode::Char(_) if editing => {
+                let Some(ItemKind::Input { name, action, .. }) = focused.map(|it| it.kind) else { continue };
+                let value = state.values.entry(name).or_default();
+                match key.code {
+                    KeyCode::Char(c) => value.push(c),
+                    _ => { value.pop(); }
+                }
+                let value = value.clone();
+                action.map(|a| client.send(&a, serde_json::json!({ "value": value })))
+            }
+            _ => None,
+        };
+
+        match next {
+            Some(Ok(dom)) => {
+                view = build_view(&dom);
+                state.clamp(&view);
+                state.status.clear();
+            }
+            Some(Err(e)) => state.status = e,
+            None => {}
+        }
+    }
+}
+
+/// Fire the action behind a focus target
+fn activate(
+    client: &mut Client,
+    view: &TuiView,
+    state: &mut TuiState,
+    kind: &ItemKind,
+) -> Option<Result<DomNode, String>> {
+    match kind {
+        ItemKind::Button { action: Some(action), .. } | ItemKind::Link { action } => {
+            Some(client.send(action, serde_json::json!({})))
+        }
+        // Buttons without a click action and Enter in an input submit the form
+        ItemKind::Button { action: None, form: Some(form) }
+        | ItemKind::Input { form: Some(form), .. } => Some(submit(client, view, state, *form)),
+        _ => None,
+    }
+}
+
+/// Submit a form: `{ name: value }` for each of its inputs, then clear them
+fn submit(client: &mut Client, view: &TuiView, state: &mut TuiState, form: usize) -> Result<DomNode, String> {
+    let mut payload = serde_json::Map::new();
+    for item in &view.items {
+        if let ItemKind::Input { name, form: Some(f), .. } = &item.kind {
+            if *f == form {
+                let value = state.values.remove(name).unwrap_or_default();
+                payload.insert(name.clone(), serde_json::Value::String(value));
+            }
+        }
+    }
+    client.send(&view.forms[form], serde_json::Value::Object(payload))
+}
+
+/// Percent-encode an action name for the URL path
+fn urlencode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
@@ -0,0 +1,484 @@
+//! magnetic-render-tui — Render Magnetic DomNode trees to terminal UIs
+//!
+//! Flattens the JSON DOM tree into a list of line-positioned items and draws
+//! them with ratatui. Interactive nodes (buttons, links, inputs) become focus
+//! targets; the `client` module drives them against a running Magnetic server
+//! so dashboards built on Magnetic are usable over SSH.
+//!
+//! Mapping strategy:
+//!   div          → block (new line per child) / single line (if row-like class)
+//!   span         → inline text
+//!   h1..h6       → bold text (h1/h2 highlighted)
+//!   p / label    → text line
+//!   button       → [ label ] focus target, Enter → click action
+//!   input        → editable field, typing → input action, Enter → form submit
+//!   form         → groups inputs; submit sends { name: value } payload
+//!   a / Link     → underlined focus target, Enter → click action or navigate:href
+//!   img          → [image: alt]
+//!   nav          → single line (navigation bar)
+//!   ul/ol        → bulleted / numbered lines
+//!   li           → one line per item
+
+pub mod client;
+
+use magnetic_dom::DomNode;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::Frame;
+use std::collections::HashMap;
+
+/// A flattened, drawable view of a DomNode tree.
+#[derive(Debug, Default)]
+pub struct TuiView {
+    pub items: Vec<Item>,
+    /// Submit action per form, indexed by the `form` field of inputs and buttons
+    pub forms: Vec<String>,
+}
+
+/// A single drawable item positioned on a terminal line.
+#[derive(Debug, Clone)]
+pub struct Item {
+    /// Logical line index (items in a row share a line)
+    pub line: usize,
+    /// Indent level (nested lists)
+    pub indent: usize,
+    pub kind: ItemKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemKind {
+    Heading(u8),
+    Text,
+    Image,
+    /// `action` is the click action; a button without one submits its `form`
+    Button { action: Option<String>, form: Option<usize> },
+    Link { action: String },
+    Input { name: String, action: Option<String>, form: Option<usize> },
+}
+
+impl Item {
+    /// Whether Tab / arrow navigation can land on this item
+    pub fn is_focusable(&self) -> bool {
+        matches!(self.kind, ItemKind::Button { .. } | ItemKind::Link { .. } | ItemKind::Input { .. })
+    }
+}
+
+impl TuiView {
+    /// Indexes into `items` of every focus target, in document order
+    pub fn focusables(&self) -> Vec<usize> {
+        self.items.iter().enumerate()
+            .filter(|(_, it)| it.is_focusable())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Number of logical lines in the view
+    pub fn line_count(&self) -> usize {
+        self.items.last().map(|it| it.line + 1).unwrap_or(0)
+    }
+}
+
+/// Interactive state kept across re-renders (focus, typed values, scroll).
+#[derive(Debug, Default)]
+pub struct TuiState {
+    /// Position within `TuiView::focusables()`
+    pub focus: usize,
+    /// Input values keyed by input name
+    pub values: HashMap<String, String>,
+    pub scroll: u16,
+    pub status: String,
+    /// Viewport height at the last draw
+    height: u16,
+    /// Scrolled by paging: the view stays put until focus moves
+    pinned: bool,
+}
+
+impl TuiState {
+    /// Index into `view.items` of the focused item, if any
+    pub fn focused_item(&self, view: &TuiView) -> Option<usize> {
+        view.focusables().get(self.focus).copied()
+    }
+
+    pub fn focus_next(&mut self, view: &TuiView) {
+        let n = view.focusables().len();
+        if n > 0 { self.focus = (self.focus + 1) % n; }
+        self.pinned = false;
+    }
+
+    pub fn focus_prev(&mut self, view: &TuiView) {
+        let n = view.focusables().len();
+        if n > 0 { self.focus = (self.focus + n - 1) % n; }
+        self.pinned = false;
+    }
+
+    /// Clamp focus after a re-render changed the number of targets
+    pub fn clamp(&mut self, view: &TuiView) {
+        let n = view.focusables().len();
+        if self.focus >= n { self.focus = n.saturating_sub(1); }
+        self.pinned = false;
+    }
+
+    /// Scroll a viewport's height down (or up), taking focus along to the
+    /// first (or last) target on the new page when the focused one leaves it
+    pub fn page(&mut self, view: &TuiView, down: bool) {
+        let height = if self.height > 0 { self.height } else { 10 };
+        let max = (view.line_count() as u16).saturating_sub(height);
+        self.scroll = if down {
+            self.scroll.saturating_add(height).min(max)
+        } else {
+            self.scroll.saturating_sub(height)
+        };
+        self.pinned = true;
+
+        let visible = |i: usize| (self.scroll..self.scroll.saturating_add(height)).contains(&(view.items[i].line as u16));
+        let targets = view.focusables();
+        if self.focused_item(view).is_some_and(visible) {
+            return;
+        }
+        let on_page = targets.iter().enumerate().filter(|&(_, &i)| visible(i)).map(|(pos, _)| pos);
+        if let Some(pos) = if down { on_page.min() } else { on_page.max() } {
+            self.focus = pos;
+        }
+    }
+
+    /// Adjust scroll so the focused line is inside a viewport of `height`
+    /// lines, unless the view was paged since focus last moved
+    pub fn scroll_to_focus(&mut self, view: &TuiView, height: u16) {
+        self.height = height;
+        if self.pinned { return; }
+        let Some(idx) = self.focused_item(view) else { return };
+        let line = view.items[idx].line as u16;
+        if line < self.scroll {
+            self.scroll = line;
+        } else if height > 0 && line >= self.scroll + height {
+            self.scroll = line + 1 - height;
+        }
+    }
+}
+
+/// Flatten a DomNode tree into a drawable view.
+pub fn build_view(node: &DomNode) -> TuiView {
+    let mut b = Builder::default();
+    b.walk(node, 0, false);
+    TuiView { items: b.items, forms: b.forms }
+}
+
+#[derive(Default)]
+struct Builder {
+    items: Vec<Item>,
+    forms: Vec<String>,
+    form: Option<usize>,
+    line: usize,
+    line_used: bool,
+}
+
+impl Builder {
+    fn newline(&mut self) {
+        if self.line_used {
+            self.line += 1;
+            self.line_used = false;
+        }
+    }
+
+    /// Push an item; block items get a line of their own
+    fn push(&mut self, indent: usize, kind: ItemKind, text: String, inline: bool) {
+        if !inline { self.newline(); }
+        self.items.push(Item { line: self.line, indent, kind, text });
+        self.line_used = true;
+        if !inline { self.newline(); }
+    }
+
+    fn walk(&mut self, node: &DomNode, depth: usize, inline: bool) {
+        match node.tag.as_str() {
+            // Skip magnetic:head nodes (not relevant for terminals)
+            "magnetic:head" => {}
+
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if let Some(text) = collect_text(node) {
+                    let level = node.tag[1..].parse().unwrap_or(6);
+                    self.push(depth, ItemKind::Heading(level), text, inline);
+                }
+            }
+
+            "p" | "span" | "label" => {
+                if let Some(text) = collect_text(node) {
+                    let inline = inline || node.tag == "span";
+                    self.push(depth, ItemKind::Text, text, inline);
+                }
+            }
+
+            "button" => {
+                let action = node.event("click").map(String::from);
+                let label = collect_text(node).unwrap_or_default();
+                self.push(depth, ItemKind::Button { action, form: self.form }, label, inline);
+            }
+
+            "a" => {
+                let action = match node.event("click") {
+                    Some(a) => a.to_string(),
+                    None => {
+                        let href = node.attrs.as_ref()
+                            .and_then(|a| a.get("href"))
+                            .map(|s| s.as_str())
+                            .unwrap_or("/");
+                        format!("navigate:{}", href)
+                    }
+                };
+                let label = collect_text(node).unwrap_or_default();
+                self.push(depth, ItemKind::Link { action }, label, inline);
+            }
+
+            "input" | "textarea" => {
+                let attrs = node.attrs.as_ref();
+                let name = attrs.and_then(|a| a.get("name")).cloned()
+                    .or_else(|| node.key.clone())
+                    .unwrap_or_else(|| format!("input{}", self.items.len()));
+                let placeholder = attrs.and_then(|a| a.get("placeholder")).cloned().unwrap_or_default();
+                let action = node.event("input").map(String::from);
+                self.push(depth, ItemKind::Input { name, action, form: self.form }, placeholder, inline);
+            }
+
+            "img" => {
+                let alt = node.attrs.as_ref()
+                    .and_then(|a| a.get("alt"))
+                    .map(|s| s.as_str())
+                    .unwrap_or("image");
+                self.push(depth, ItemKind::Image, alt.to_string(), inline);
+            }
+
+            "form" => {
+                let outer = self.form;
+                if let Some(action) = node.event("submit") {
+                    self.forms.push(action.to_string());
+                    self.form = Some(self.forms.len() - 1);
+                }
+                self.walk_children(node, depth, inline || is_row_layout(node));
+                self.form = outer;
+                if !inline { self.newline(); }
+            }
+
+            "ul" | "ol" => {
+                let ordered = node.tag == "ol";
+                let mut n = 0;
+                for child in node.children_iter() {
+                    if child.is_head() { continue; }
+                    n += 1;
+                    let marker = if ordered { format!("{}.", n) } else { "•".to_string() };
+                    self.newline();
+                    self.push(depth, ItemKind::Text, marker, true);
+                    // List item content stays on the marker's line
+                    if child.tag == "li" {
+                        if let Some(text) = &child.text {
+                            self.push(depth, ItemKind::Text, text.clone(), true);
+                        }
+                        self.walk_children(child, depth + 1, true);
+                    } else {
+                        self.walk(child, depth + 1, true);
+                    }
+                    self.newline();
+                }
+            }
+
+            // Default: div, nav, li and everything else → block or row
+            _ => {
+                let row = inline || is_row_layout(node);
+                if !inline { self.newline(); }
+                if let Some(text) = &node.text {
+                    self.push(depth, ItemKind::Text, text.clone(), row);
+                }
+                self.walk_children(node, depth, row);
+                if !inline { self.newline(); }
+            }
+        }
+    }
+
+    fn walk_children(&mut self, node: &DomNode, depth: usize, inline: bool) {
+        for child in node.children_iter() {
+            self.walk(child, depth, inline);
+        }
+    }
+}
+
+/// Draw a view into `area`, highlighting the focused item.
+pub fn draw(frame: &mut Frame, area: Rect, view: &TuiView, state: &TuiState, title: &str) {
+    let focused = state.focused_item(view);
+    let mut lines: Vec<Line> = Vec::with_capacity(view.line_count());
+
+    for (i, item) in view.items.iter().enumerate() {
+        while lines.len() <= item.line {
+            lines.push(Line::default());
+        }
+        let line = &mut lines[item.line];
+        if line.spans.is_empty() {
+            line.spans.push(Span::raw("  ".repeat(item.indent)));
+        } else {
+            line.spans.push(Span::raw(" "));
+        }
+        let mut span = item_span(item, state);
+        if focused == Some(i) {
+            span = span.patch_style(Style::default().add_modifier(Modifier::REVERSED));
+        }
+        line.spans.push(span);
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title.to_string()))
+        .scroll((state.scroll, 0));
+    frame.render_widget(paragraph, area);
+}
+
+fn item_span<'a>(item: &'a Item, state: &TuiState) -> Span<'a> {
+    match &item.kind {
+        ItemKind::Heading(level) => {
+            let style = Style::default().add_modifier(Modifier::BOLD);
+            let style = if *level <= 2 { style.fg(Color::Cyan) } else { style };
+            Span::styled(item.text.as_str(), style)
+        }
+        ItemKind::Text => Span::raw(item.text.as_str()),
+        ItemKind::Image => Span::styled(
+            format!("[image: {}]", item.text),
+            Style::default().fg(Color::DarkGray),
+        ),
+        ItemKind::Button { .. } => Span::styled(
+            format!("[ {} ]", item.text),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        ItemKind::Link { .. } => Span::styled(
+            item.text.as_str(),
+            Style::default().fg(Color::Blue).add_modifier(Modifier::UNDERLINED),
+        ),
+        ItemKind::Input { name, .. } => {
+            match state.values.get(name).filter(|v| !v.is_empty()) {
+                Some(v) => Span::raw(format!("[{:<20}]", v)),
+                None => Span::styled(
+                    format!("[{:<20}]", item.text),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            }
+        }
+    }
+}
+
+/// Check if a node should be laid out on a single line
+fn is_row_layout(node: &DomNode) -> bool {
+    if let Some(class) = node.class() {
+        return class.contains("row")
+            || class.contains("flex-row")
+            || class.contains("topnav")
+            || class.contains("add-form")
+            || class.contains("filters")
+            || class.contains("task-card");
+    }
+    matches!(node.tag.as_str(), "nav" | "header")
+}
+
+/// Collect all text content from a node and its children
+fn collect_text(node: &DomNode) -> Option<String> {
+    let mut text = String::new();
+    collect_text_inner(node, &mut text);
+    if text.is_empty() { None } else { Some(text) }
+}
+
+fn collect_text_inner(node: &DomNode, buf: &mut String) {
+    if let Some(t) = &node.text {
+        buf.push_str(t);
+    }
+    for child in node.children_iter() {
+        collect_text_inner(child, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use magnetic_dom::DomNode;
+    use std::collections::HashMap;
+
+    fn node(tag: &str, class: Option<&str>, children: Vec<DomNode>) -> DomNode {
+        DomNode {
+            tag: tag.into(),
+            key: None,
+            attrs: class.map(|c| HashMap::from([("class".into(), c.into())])),
+            events: None,
+            text: None,
+            html: None,
+            children: Some(children),
+        }
+    }
+
+    #[test]
+    fn test_button_is_focus_target() {
+        let btn = DomNode {
+            events: Some(HashMap::from([("click".into(), "increment".into())])),
+            ..DomNode::text("button", "+")
+        };
+        let view = build_view(&node("div", None, vec![DomNode::text("h1", "Counter"), btn]));
+        assert_eq!(view.items.len(), 2);
+        assert_eq!(view.items[0].kind, ItemKind::Heading(1));
+        assert_eq!(view.items[1].kind, ItemKind::Button { action: Some("increment".into()), form: None });
+        assert_eq!(view.items[1].line, 1);
+        assert_eq!(view.focusables(), vec![1]);
+    }
+
+    #[test]
+    fn test_row_layout_shares_line_and_form_inputs() {
+        let input = DomNode {
+            attrs: Some(HashMap::from([("name".into(), "title".into())])),
+            ..DomNode::text("input", "")
+        };
+        let form = DomNode {
+            events: Some(HashMap::from([("submit".into(), "add_task".into())])),
+            ..node("form", Some("add-form"), vec![input, DomNode::text("button", "Add")])
+        };
+        let view = build_view(&node("div", None, vec![form, DomNode::text("p", "after")]));
+        assert_eq!(view.forms, vec!["add_task".to_string()]);
+        assert_eq!(view.items[0].line, view.items[1].line);
+        assert!(matches!(view.items[0].kind, ItemKind::Input { form: Some(0), .. }));
+        assert_eq!(view.items[1].kind, ItemKind::Button { action: None, form: Some(0) });
+        assert_eq!(view.items[2].line, view.items[0].line + 1);
+    }
+
+    #[test]
+    fn test_paging_survives_the_next_draw() {
+        // 30 lines, a button on every third
+        let children = (0..30)
+            .map(|i| if i % 3 == 0 { DomNode::text("button", &format!("b{}", i)) } else { DomNode::text("p", &format!("p{}", i)) })
+            .collect();
+        let view = build_view(&node("div", None, children));
+        let mut state = TuiState::default();
+        let draw = |state: &mut TuiState| {
+            let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(40, 12)).unwrap();
+            terminal.draw(|frame| {
+                let area = frame.area();
+                state.scroll_to_focus(&view, area.height - 2);
+                draw(frame, area, &view, state, "test");
+            }).unwrap();
+        };
+        draw(&mut state);
+        assert_eq!(state.scroll, 0);
+
+        state.page(&view, true);
+        draw(&mut state);
+        assert_eq!(state.scroll, 10);
+        // Focus came along to the first button on the new page
+        assert_eq!(view.items[state.focused_item(&view).unwrap()].text, "b12");
+
+        state.page(&view, true);
+        draw(&mut state);
+        assert_eq!(state.scroll, 20);
+        state.page(&view, false);
+        draw(&mut state);
+        assert_eq!(state.scroll, 10);
+        assert_eq!(view.items[state.focused_item(&view).unwrap()].text, "b18");
+
+        // Moving focus scrolls to it again
+        state.focus = 0;
+        state.focus_prev(&view);
+        draw(&mut state);
+        assert_eq!(view.items[state.focused_item(&view).unwrap()].text, "b27");
+        assert_eq!(state.scroll, 18);
+    }
+}
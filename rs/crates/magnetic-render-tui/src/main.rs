@@ -0,0 +1,26 @@
+//! magnetic-tui — Drive a Magnetic app from the terminal
+//!
+//! Usage:
+//!   magnetic-tui --server http://localhost:3003
+//!   magnetic-tui --server http://ops.internal:3003 --path /dashboard
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut server = "http://localhost:3003".to_string();
+    let mut path = "/".to_string();
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--server" => { i += 1; server = args[i].clone(); }
+            "--path" => { i += 1; path = args[i].clone(); }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    if let Err(e) = magnetic_render_tui::client::run(&server, &path) {
+        eprintln!("[magnetic-tui] {}", e);
+        std::process::exit(1);
+    }
+}
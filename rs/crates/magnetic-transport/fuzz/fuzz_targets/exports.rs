@@ -0,0 +1,45 @@
+//! Drive the transport exports the way magnetic.js does, with arbitrary
+//! bytes where snapshots, actions, namespaces and persisted state go. The
+//! JSON scanners (diff), LZ decoder (cached predictions) and state import
+//! all see input that came over the network or out of localStorage.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use magnetic_transport::*;
+
+/// Write bytes where JS would (the input buffer, or a reserve()d region)
+fn put(bytes: &[u8]) -> u32 {
+    let p = reserve(bytes.len() as u32);
+    if p.is_null() {
+        return 0;
+    }
+    unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), p, bytes.len()) };
+    bytes.len() as u32
+}
+
+fuzz_target!(|ops: Vec<(u8, &[u8])>| {
+    for (op, bytes) in ops {
+        let len = put(bytes);
+        let out = match op % 6 {
+            0 => {
+                store(len);
+                continue;
+            }
+            1 => diff_framed(len),
+            2 => reduce_framed(len),
+            3 => {
+                import_state(len);
+                continue;
+            }
+            4 => {
+                set_namespace(len);
+                continue;
+            }
+            _ => export_framed(),
+        };
+        // Every framed result must be readable in full
+        let data = unsafe { magnetic_wasm_abi::read(out) };
+        std::hint::black_box(data.iter().fold(0u8, |a, b| a ^ b));
+    }
+});
@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(target_arch = "wasm32", no_std)]
 
 //! magnetic-transport — generic snapshot transport WASM
 //!
@@ -6,14 +6,59 @@
 //! Role: cache snapshots, predict action results, dedup SSE updates.
 //!
 //! Exports (same ABI shape as magnetic-reducer for backward compat):
+//!   abi_version()      → u32         ABI_VERSION (see compatibility below)
 //!   input_ptr()        → *mut u8     JS writes action/snapshot bytes here
 //!   init()             → *const u8   returns current snapshot ptr (initially empty)
 //!   reduce(len)        → *const u8   predict: lookup (state_hash, action_hash) in cache
 //!   snapshot_len()     → u32         length of last reduce() result (0 = cache miss)
 //!   store(len)         → u32         store authoritative snapshot; 0=no change, 1=changed
+//!   diff_current(len)  → *const u8   keyed patch list: incoming snapshot vs current
+//!   diff_len()         → u32         length of last diff_current() result (0 = full apply)
+//!   input_cap()        → u32         size of the static input buffer
+//!   reserve(len)       → *mut u8     input buffer for a snapshot of len bytes (grows memory)
+//!   stats_ptr()        → *const u32  counters (see Stats), stats_len() of them
+//!   stats_reset()                    zero all counters
+//!   queue_action(len)  → u32         buffer an action while offline; 1=queued, 0=full
+//!   pending_count()    → u32         number of queued actions
+//!   pending_ptr(idx)   → *const u8   queued action bytes, in send order
+//!   pending_len(idx)   → u32         length of a queued action
+//!   clear_pending()                  drop the queue after replay
+//!   set_namespace(len)               route path in input buffer; partitions the cache
+//!   export_state()     → *const u8   snapshot + cache entries as bytes, export_len() long
+//!   import_state(len)  → u32         restore an export_state() blob; restored snapshot len
+//!   set_ttl(n)                       expire predictions older than n snapshot changes
+//!   reduce_framed(len) → *const u8   reduce() as a frame: [len u32][snapshot] (len 0 = miss)
+//!   diff_framed(len)   → *const u8   diff_current() as a frame (len 0 = full apply)
+//!   export_framed()    → *const u8   export_state() as a frame (len 0 = nothing exported)
+//!
+//! Frames (magnetic-wasm-abi) carry their length in a 4-byte little-endian
+//! header, so a result never needs a separate *_len() call.
+//!
+//! Cache slots hold LZ-compressed snapshots, so a 16 KB slot fits the 64 KB
+//! snapshots of list-heavy apps (JSON compresses well: repeated tags/keys).
+//! Larger snapshots (up to MAX_SNAPSHOT) go through reserve(): the input and
+//! the current snapshot then live in pages obtained with memory.grow.
+//!
+//! ABI compatibility — magnetic.js may be paired with an older binary served
+//! from a cache, so it checks abi_version() before using anything beyond the
+//! base exports:
+//!
+//!   version   exports / behavior                          magnetic.js uses
+//!   (none)    input_ptr init reduce snapshot_len store    store() dedup only
+//!   1         everything above; 64-bit hashes, LZ slots   all features
+//!   2         + set_ttl, predictions_expired counter       prediction TTL
+//!   3         + reduce_framed diff_framed export_framed    framed results
+//!
+//! Versions are additive: an existing export never changes meaning. Bump
+//! ABI_VERSION when adding exports or changing a buffer/blob format.
+//!
+//! Off wasm32 the crate builds against std (memory.grow is stood in for by
+//! leaked heap pages), so `cargo test` runs the suite at the bottom natively.
 
 use core::cell::UnsafeCell;
+use magnetic_wasm_abi::{Framed, HEADER_LEN};
 
+#[cfg(target_arch = "wasm32")]
 #[panic_handler]
 fn panic(_: &core::panic::PanicInfo) -> ! {
     core::arch::wasm32::unreachable()
@@ -23,49 +68,305 @@ fn panic(_: &core::panic::PanicInfo) -> ! {
 // Tuning constants
 // ═══════════════════════════════════════════════════════════════════
 
-const INPUT_CAP: usize = 16384; // 16 KB shared input buffer
-const SLOT_CAP: usize = 16384;  // 16 KB per snapshot slot
+const INPUT_CAP: usize = 65536; // 64 KB shared input buffer (largest snapshot)
+const SLOT_CAP: usize = 16384;  // 16 KB per compressed cache slot
 const CACHE_N: usize = 4;       // 4 prediction cache entries
+const DIFF_CAP: usize = 65536;  // 64 KB patch list output
+const MAX_SNAPSHOT: usize = 4 << 20; // 4 MB — largest snapshot reserve() accepts
+const PAGE: usize = 65536;      // WASM page size
+const QUEUE_N: usize = 32;      // actions buffered while offline
+const ACTION_CAP: usize = 1024; // 1 KB per queued action
+const ABI_VERSION: u32 = 3;     // reported by abi_version()
 
 // ═══════════════════════════════════════════════════════════════════
-// FNV-1a hash — same algorithm as magnetic.js client-side
+// XXH64 hash — snapshot identity for dedup and cache keys
 // ═══════════════════════════════════════════════════════════════════
+//
+// 64 bits (and the length folded in) so a legitimate update is never
+// dropped as a "duplicate" by a 32-bit collision over large snapshots.
 
-fn fnv(data: &[u8]) -> u32 {
-    let mut h: u32 = 0x811c9dc5;
+const P1: u64 = 0x9e3779b185ebca87;
+const P2: u64 = 0xc2b2ae3d27d4eb4f;
+const P3: u64 = 0x165667b19e3779f9;
+const P4: u64 = 0x85ebca77c2b2ae63;
+const P5: u64 = 0x27d4eb2f165667c5;
+
+fn read64(b: &[u8], i: usize) -> u64 {
+    u64::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3], b[i + 4], b[i + 5], b[i + 6], b[i + 7]])
+}
+
+fn xxh_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(P2)).rotate_left(31).wrapping_mul(P1)
+}
+
+fn xxh_merge(acc: u64, v: u64) -> u64 {
+    (acc ^ xxh_round(0, v)).wrapping_mul(P1).wrapping_add(P4)
+}
+
+fn hash64(data: &[u8]) -> u64 {
+    let len = data.len();
     let mut i = 0;
-    while i < data.len() {
-        h ^= data[i] as u32;
-        h = h.wrapping_mul(0x01000193);
+    let mut h;
+    if len >= 32 {
+        let mut v1 = P1.wrapping_add(P2);
+        let mut v2 = P2;
+        let mut v3 = 0u64;
+        let mut v4 = 0u64.wrapping_sub(P1);
+        while i + 32 <= len {
+            v1 = xxh_round(v1, read64(data, i));
+            v2 = xxh_round(v2, read64(data, i + 8));
+            v3 = xxh_round(v3, read64(data, i + 16));
+            v4 = xxh_round(v4, read64(data, i + 24));
+            i += 32;
+        }
+        h = v1.rotate_left(1)
+            .wrapping_add(v2.rotate_left(7))
+            .wrapping_add(v3.rotate_left(12))
+            .wrapping_add(v4.rotate_left(18));
+        h = xxh_merge(h, v1);
+        h = xxh_merge(h, v2);
+        h = xxh_merge(h, v3);
+        h = xxh_merge(h, v4);
+    } else {
+        h = P5;
+    }
+    h = h.wrapping_add(len as u64);
+
+    while i + 8 <= len {
+        h ^= xxh_round(0, read64(data, i));
+        h = h.rotate_left(27).wrapping_mul(P1).wrapping_add(P4);
+        i += 8;
+    }
+    if i + 4 <= len {
+        let k = u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as u64;
+        h ^= k.wrapping_mul(P1);
+        h = h.rotate_left(23).wrapping_mul(P2).wrapping_add(P3);
+        i += 4;
+    }
+    while i < len {
+        h ^= (data[i] as u64).wrapping_mul(P5);
+        h = h.rotate_left(11).wrapping_mul(P1);
         i += 1;
     }
+
+    h ^= h >> 33;
+    h = h.wrapping_mul(P2);
+    h ^= h >> 29;
+    h = h.wrapping_mul(P3);
+    h ^= h >> 32;
     h
 }
 
 // ═══════════════════════════════════════════════════════════════════
-// Snapshot slot — fixed buffer holding one snapshot
+// LZ compression for cache slots
+// ═══════════════════════════════════════════════════════════════════
+//
+// Byte-oriented LZ77. Each token starts with a control byte:
+//   0xxxxxxx            → literal run of x+1 bytes (1..128) follows
+//   1xxxxxxx lo hi      → copy x+LZ_MIN bytes (4..131) from `offset` back
+
+const LZ_MIN: usize = 4;
+const LZ_MAX: usize = LZ_MIN + 0x7f;
+const LZ_HASH_BITS: u32 = 12;
+const LZ_HASH_N: usize = 1 << LZ_HASH_BITS;
+const LZ_EMPTY: u32 = u32::MAX;
+
+fn lz_hash(b: &[u8], i: usize) -> usize {
+    let v = u32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]);
+    (v.wrapping_mul(0x9e3779b1) >> (32 - LZ_HASH_BITS)) as usize
+}
+
+/// Compress `src` into `dst`. Returns compressed length, None if it doesn't fit.
+fn lz_compress(src: &[u8], dst: &mut [u8], table: &mut [u32; LZ_HASH_N]) -> Option<usize> {
+    table.fill(LZ_EMPTY);
+    let mut o = 0;
+    let mut i = 0;
+    let mut lit = 0; // start of pending literals
+    while i + LZ_MIN <= src.len() {
+        let h = lz_hash(src, i);
+        let cand = table[h];
+        table[h] = i as u32;
+        if cand != LZ_EMPTY && i - cand as usize <= 0xffff && src[cand as usize..cand as usize + LZ_MIN] == src[i..i + LZ_MIN] {
+            let cand = cand as usize;
+            let mut len = LZ_MIN;
+            while len < LZ_MAX && i + len < src.len() && src[cand + len] == src[i + len] {
+                len += 1;
+            }
+            o = lz_literals(&src[lit..i], dst, o)?;
+            if o + 3 > dst.len() {
+                return None;
+            }
+            dst[o] = 0x80 | (len - LZ_MIN) as u8;
+            dst[o + 1..o + 3].copy_from_slice(&((i - cand) as u16).to_le_bytes());
+            o += 3;
+            i += len;
+            lit = i;
+        } else {
+            i += 1;
+        }
+    }
+    lz_literals(&src[lit..], dst, o)
+}
+
+fn lz_literals(lits: &[u8], dst: &mut [u8], mut o: usize) -> Option<usize> {
+    for run in lits.chunks(128) {
+        if o + 1 + run.len() > dst.len() {
+            return None;
+        }
+        dst[o] = (run.len() - 1) as u8;
+        dst[o + 1..o + 1 + run.len()].copy_from_slice(run);
+        o += 1 + run.len();
+    }
+    Some(o)
+}
+
+/// Decompress `src` (produced by lz_compress) into `dst`. Returns length, or
+/// None if `src` is malformed or doesn't fit (e.g. a corrupted imported entry).
+fn lz_decompress(src: &[u8], dst: &mut [u8]) -> Option<usize> {
+    let mut i = 0;
+    let mut o = 0;
+    while i < src.len() {
+        let c = src[i] as usize;
+        if c & 0x80 == 0 {
+            let n = c + 1;
+            let lit = src.get(i + 1..i + 1 + n)?;
+            dst.get_mut(o..o + n)?.copy_from_slice(lit);
+            i += 1 + n;
+            o += n;
+        } else {
+            let len = (c & 0x7f) + LZ_MIN;
+            let off = u16::from_le_bytes([*src.get(i + 1)?, *src.get(i + 2)?]) as usize;
+            if off == 0 || off > o || o + len > dst.len() {
+                return None;
+            }
+            // Byte by byte: a match may overlap the bytes it produces
+            let mut k = 0;
+            while k < len {
+                dst[o + k] = dst[o + k - off];
+                k += 1;
+            }
+            i += 3;
+            o += len;
+        }
+    }
+    Some(o)
+}
+
+// ═══════════════════════════════════════════════════════════════════
+// Grown region — buffer beyond the static ones, from memory.grow
+// ═══════════════════════════════════════════════════════════════════
+//
+// Nothing else allocates, so a region is simply fresh pages at the end of
+// memory. Growing abandons the old pages (memory can't shrink anyway);
+// capacity doubles, so at most half the grown memory is ever wasted.
+
+struct Region {
+    ptr: *mut u8,
+    cap: usize,
+}
+
+impl Region {
+    const fn new() -> Self {
+        Self { ptr: core::ptr::null_mut(), cap: 0 }
+    }
+
+    /// Make room for `len` bytes. False if len is over MAX_SNAPSHOT or
+    /// memory can't grow.
+    fn ensure(&mut self, len: usize) -> bool {
+        if len <= self.cap {
+            return true;
+        }
+        if len > MAX_SNAPSHOT {
+            return false;
+        }
+        let pages = len.next_power_of_two().div_ceil(PAGE);
+        let prev = grow(pages);
+        if prev == usize::MAX {
+            return false;
+        }
+        self.ptr = (prev * PAGE) as *mut u8;
+        self.cap = pages * PAGE;
+        true
+    }
+
+    fn bytes(&self, len: usize) -> &[u8] {
+        if len == 0 || len > self.cap {
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts(self.ptr, len) }
+    }
+
+    fn bytes_mut(&mut self, len: usize) -> &mut [u8] {
+        if len == 0 || len > self.cap {
+            return &mut [];
+        }
+        unsafe { core::slice::from_raw_parts_mut(self.ptr, len) }
+    }
+}
+
+/// Grow memory by `pages`; returns the previous size in pages (= index of
+/// the first new page), or usize::MAX on failure.
+#[cfg(target_arch = "wasm32")]
+fn grow(pages: usize) -> usize {
+    core::arch::wasm32::memory_grow(0, pages)
+}
+
+/// Host builds (tests): leaked, page-aligned heap memory stands in for
+/// memory.grow.
+#[cfg(not(target_arch = "wasm32"))]
+fn grow(pages: usize) -> usize {
+    let pages = vec![0u8; (pages + 1) * PAGE].leak();
+    (pages.as_mut_ptr() as usize).div_ceil(PAGE)
+}
+
+/// Snapshot bytes JS wrote: the static input buffer, or the region handed
+/// out by reserve() for larger snapshots
+fn input_bytes<'a>(input: &'a [u8; INPUT_CAP], big: &'a Region, len: u32) -> Option<&'a [u8]> {
+    let len = len as usize;
+    if len == 0 {
+        None
+    } else if len <= INPUT_CAP {
+        Some(&input[..len])
+    } else if len <= big.cap {
+        Some(big.bytes(len))
+    } else {
+        None
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════
+// Snapshot slot — buffer holding the current snapshot
 // ═══════════════════════════════════════════════════════════════════
 
 struct Slot {
-    data: [u8; SLOT_CAP],
+    data: [u8; INPUT_CAP],
+    big: Region, // used instead of `data` for snapshots over INPUT_CAP
     len: u32,
-    hash: u32,
+    hash: u64,
 }
 
 impl Slot {
     const fn new() -> Self {
-        Self { data: [0; SLOT_CAP], len: 0, hash: 0 }
+        Self { data: [0; INPUT_CAP], big: Region::new(), len: 0, hash: 0 }
     }
 
     fn write(&mut self, src: &[u8]) {
-        let n = if src.len() < SLOT_CAP { src.len() } else { SLOT_CAP };
-        let mut i = 0;
-        while i < n {
-            self.data[i] = src[i];
-            i += 1;
+        self.hash = hash64(src);
+        if src.len() <= INPUT_CAP {
+            self.data[..src.len()].copy_from_slice(src);
+        } else if self.big.ensure(src.len()) {
+            self.big.bytes_mut(src.len()).copy_from_slice(src);
+        } else {
+            // Too large to keep: no dedup or diff against it
+            self.len = 0;
+            return;
         }
-        self.len = n as u32;
-        self.hash = fnv(&self.data[..n]);
+        self.len = src.len() as u32;
+    }
+
+    fn bytes(&self) -> &[u8] {
+        let len = self.len as usize;
+        if len <= INPUT_CAP { &self.data[..len] } else { self.big.bytes(len) }
     }
 
     fn is_empty(&self) -> bool {
@@ -78,19 +379,262 @@ impl Slot {
 // ═══════════════════════════════════════════════════════════════════
 
 struct CacheEntry {
-    key: u32, // fnv(state_hash ^ action_hash * golden_ratio)
-    slot: Slot,
+    key: u64, // make_key(namespace, state_hash, action_hash)
+    packed: [u8; SLOT_CAP], // lz_compress'd snapshot
+    packed_len: u32,
+    hash: u64, // hash64 of the uncompressed snapshot
+    generation: u32, // Transport::generation when learned or last confirmed
     valid: bool,
 }
 
 impl CacheEntry {
     const fn new() -> Self {
-        Self { key: 0, slot: Slot::new(), valid: false }
+        Self { key: 0, packed: [0; SLOT_CAP], packed_len: 0, hash: 0, generation: 0, valid: false }
+    }
+}
+
+/// Cache key: the same action on the same snapshot predicts differently per
+/// route namespace (set_namespace)
+fn make_key(namespace: u64, state_hash: u64, action_hash: u64) -> u64 {
+    state_hash ^ action_hash.rotate_left(32).wrapping_mul(P1) ^ namespace.wrapping_mul(P2)
+}
+
+// ═══════════════════════════════════════════════════════════════════
+// JSON scanning — just enough to walk snapshot structure without parsing
+// ═══════════════════════════════════════════════════════════════════
+//
+// Values are handled as byte spans of the original JSON. Snapshots come
+// from the same serializer, so equal nodes are byte-identical.
+
+fn skip_ws(b: &[u8], mut i: usize) -> usize {
+    while i < b.len() && matches!(b[i], b' ' | b'\t' | b'\n' | b'\r') {
+        i += 1;
+    }
+    i
+}
+
+/// End (exclusive) of the JSON value starting at `i`, or None if malformed
+fn skip_value(b: &[u8], i: usize) -> Option<usize> {
+    match *b.get(i)? {
+        b'"' => {
+            let mut j = i + 1;
+            while j < b.len() {
+                match b[j] {
+                    b'\\' => j += 2,
+                    b'"' => return Some(j + 1),
+                    _ => j += 1,
+                }
+            }
+            None
+        }
+        b'{' | b'[' => {
+            let mut depth = 0usize;
+            let mut j = i;
+            while j < b.len() {
+                match b[j] {
+                    b'"' => {
+                        j = skip_value(b, j)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(j + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                j += 1;
+            }
+            None
+        }
+        _ => {
+            let mut j = i;
+            while j < b.len() && !matches!(b[j], b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r') {
+                j += 1;
+            }
+            if j > i { Some(j) } else { None }
+        }
+    }
+}
+
+/// Iterates the members of a JSON object (name span with quotes, value span)
+/// or, with `array`, the elements of a JSON array (empty name span).
+struct Members<'a> {
+    b: &'a [u8],
+    i: usize,
+    array: bool,
+}
+
+impl<'a> Members<'a> {
+    fn object(b: &'a [u8]) -> Self {
+        Self { b, i: 1, array: false }
+    }
+
+    fn array(b: &'a [u8]) -> Self {
+        Self { b, i: 1, array: true }
+    }
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let b = self.b;
+        let mut i = skip_ws(b, self.i);
+        if i < b.len() && b[i] == b',' {
+            i = skip_ws(b, i + 1);
+        }
+        if i >= b.len() || b[i] == b'}' || b[i] == b']' {
+            return None;
+        }
+        let mut name: &[u8] = &[];
+        if !self.array {
+            let end = skip_value(b, i)?;
+            name = &b[i..end];
+            i = skip_ws(b, end);
+            if b.get(i) != Some(&b':') {
+                return None;
+            }
+            i = skip_ws(b, i + 1);
+        }
+        let end = skip_value(b, i)?;
+        self.i = end;
+        Some((name, &b[i..end]))
+    }
+}
+
+/// Value span of an object member
+fn member<'a>(obj: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    if obj.first() != Some(&b'{') {
+        return None;
+    }
+    Members::object(obj).find(|(n, _)| n.len() == name.len() + 2 && &n[1..n.len() - 1] == name).map(|(_, v)| v)
+}
+
+// ═══════════════════════════════════════════════════════════════════
+// Structural diff — keyed subtree replacements
+// ═══════════════════════════════════════════════════════════════════
+//
+// Output: [{"key":"k","node":{...}},...] — each entry replaces the keyed node
+// in place. Anything that cannot be expressed that way (changed root, added /
+// removed / reordered children under an unkeyed parent) means a full apply.
+
+struct PatchOut<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    overflow: bool,
+}
+
+impl PatchOut<'_> {
+    fn put(&mut self, bytes: &[u8]) {
+        if self.len + bytes.len() > self.buf.len() {
+            self.overflow = true;
+            return;
+        }
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    fn replace(&mut self, key: &[u8], node: &[u8]) {
+        self.put(if self.buf[self.len - 1] == b'[' { b"{\"key\":" } else { b",{\"key\":" });
+        self.put(key);
+        self.put(b",\"node\":");
+        self.put(node);
+        self.put(b"}");
+    }
+}
+
+/// Diff two nodes. Returns false if `new` has to be replaced as a whole;
+/// patches for changed keyed descendants are appended to `out` otherwise.
+fn diff_node(new: &[u8], old: &[u8], out: &mut PatchOut) -> bool {
+    if new == old {
+        return true;
+    }
+    if new.first() != Some(&b'{') || old.first() != Some(&b'{') {
+        return false;
+    }
+
+    // Every member other than children must be identical, in the same order
+    let mut nm = Members::object(new).filter(|(n, _)| *n != b"\"children\"");
+    let mut om = Members::object(old).filter(|(n, _)| *n != b"\"children\"");
+    loop {
+        match (nm.next(), om.next()) {
+            (None, None) => break,
+            (Some(a), Some(b)) if a == b => {}
+            _ => return false,
+        }
+    }
+
+    let (Some(nc), Some(oc)) = (member(new, b"children"), member(old, b"children")) else {
+        return false;
+    };
+    let mark = out.len;
+    let mut na = Members::array(nc);
+    let mut oa = Members::array(oc);
+    loop {
+        match (na.next(), oa.next()) {
+            (None, None) => return true,
+            (Some((_, n)), Some((_, o))) => {
+                if diff_node(n, o, out) {
+                    continue;
+                }
+                match (member(n, b"key"), member(o, b"key")) {
+                    (Some(nk), Some(ok)) if nk == ok => out.replace(nk, n),
+                    _ => {
+                        // Parent gets replaced: drop its descendants' patches
+                        out.len = mark;
+                        return false;
+                    }
+                }
+            }
+            _ => {
+                out.len = mark;
+                return false;
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════
+// Stats — counters for tuning CACHE_N and a client debug overlay
+// ═══════════════════════════════════════════════════════════════════
+
+/// Exported as consecutive u32s via stats_ptr(); field order is the ABI.
+/// Counters wrap on overflow.
+#[repr(C)]
+struct Stats {
+    cache_hits: u32,            // reduce() returned a prediction
+    cache_misses: u32,          // reduce() had nothing cached
+    predictions_confirmed: u32, // store() matched the prediction (no re-render)
+    predictions_wrong: u32,     // store() differed from the prediction
+    dedup_skips: u32,           // store() identical to current (duplicate SSE)
+    stores: u32,                // store() calls that replaced current
+    bytes_stored: u32,          // snapshot bytes passed to store()
+    cache_bytes: u32,           // compressed bytes written to cache slots
+    cache_rejects: u32,         // snapshots too large for a cache slot
+    diff_overflows: u32,        // diff_current() patch lists over DIFF_CAP
+    actions_rejected: u32,      // queue_action() calls rejected (queue full / too big)
+    predictions_expired: u32,   // reduce() dropped an entry older than the TTL
+}
+
+const STATS_N: u32 = (core::mem::size_of::<Stats>() / 4) as u32;
+
+impl Stats {
+    const fn new() -> Self {
+        Self {
+            cache_hits: 0, cache_misses: 0,
+            predictions_confirmed: 0, predictions_wrong: 0,
+            dedup_skips: 0, stores: 0, bytes_stored: 0,
+            cache_bytes: 0, cache_rejects: 0, diff_overflows: 0,
+            actions_rejected: 0, predictions_expired: 0,
+        }
     }
 }
 
-fn make_key(state_hash: u32, action_hash: u32) -> u32 {
-    state_hash ^ action_hash.wrapping_mul(0x9e3779b9)
+fn bump(counter: &mut u32, by: usize) {
+    *counter = counter.wrapping_add(by as u32);
 }
 
 // ═══════════════════════════════════════════════════════════════════
@@ -99,6 +643,7 @@ fn make_key(state_hash: u32, action_hash: u32) -> u32 {
 
 struct Transport {
     input: [u8; INPUT_CAP],
+    big_input: Region, // reserve()d input for snapshots over INPUT_CAP
 
     // Current authoritative snapshot
     current: Slot,
@@ -107,34 +652,240 @@ struct Transport {
     cache: [CacheEntry; CACHE_N],
     cache_cursor: usize,
 
-    // Last reduce() result
+    // Last reduce() result (cache hits are decompressed into `output`)
+    output: Framed<INPUT_CAP>,
     result_ptr: *const u8,
     result_len: u32,
+    lz_table: [u32; LZ_HASH_N],
+
+    // Last diff_current() result
+    diff: Framed<DIFF_CAP>,
+
+    // Route namespace: hash64 of the path passed to set_namespace() (0 = none)
+    namespace: u64,
+
+    // Authoritative updates so far (store() calls that changed the
+    // snapshot), and how many a cache entry may lag behind (0 = no expiry)
+    generation: u32,
+    ttl: u32,
 
     // Pending prediction metadata (for cache learning on store())
-    predicted_hash: u32,
-    pending_action_hash: u32,
-    pending_pre_hash: u32,
+    predicted_hash: u64,
+    pending_key: u64,
     has_pending: bool,
+
+    // Offline action queue (replayed by JS on reconnect)
+    queue: [[u8; ACTION_CAP]; QUEUE_N],
+    queue_lens: [u32; QUEUE_N],
+    queue_count: usize,
+
+    // Last export_state() result, framed (HEADER_LEN bytes, then the blob)
+    export: Region,
+    export_len: u32,
+
+    stats: Stats,
 }
 
 impl Transport {
     const fn new() -> Self {
         Self {
             input: [0; INPUT_CAP],
+            big_input: Region::new(),
             current: Slot::new(),
             cache: [
                 CacheEntry::new(), CacheEntry::new(),
                 CacheEntry::new(), CacheEntry::new(),
             ],
             cache_cursor: 0,
+            output: Framed::new(),
             result_ptr: core::ptr::null(),
             result_len: 0,
+            lz_table: [LZ_EMPTY; LZ_HASH_N],
+            diff: Framed::new(),
             predicted_hash: 0,
-            pending_action_hash: 0,
-            pending_pre_hash: 0,
+            namespace: 0,
+            generation: 0,
+            ttl: 0,
+            pending_key: 0,
             has_pending: false,
+            queue: [[0; ACTION_CAP]; QUEUE_N],
+            queue_lens: [0; QUEUE_N],
+            queue_count: 0,
+            export: Region::new(),
+            export_len: 0,
+            stats: Stats::new(),
+        }
+    }
+
+    /// Keyed patch list from the current snapshot to the one in the input
+    /// buffer, into `diff` (length 0 = no usable diff).
+    fn diff_snapshot(&mut self, snap_len: u32) {
+        self.diff.set_len(0);
+        if self.current.is_empty() {
+            return;
+        }
+        let Some(new) = input_bytes(&self.input, &self.big_input, snap_len) else {
+            return;
+        };
+        let old = self.current.bytes();
+        let (Some(new_root), Some(old_root)) = (member(new, b"root"), member(old, b"root")) else {
+            return;
+        };
+
+        let mut out = PatchOut { buf: self.diff.data_mut(), len: 0, overflow: false };
+        out.put(b"[");
+        if !diff_node(new_root, old_root, &mut out) {
+            match (member(new_root, b"key"), member(old_root, b"key")) {
+                (Some(nk), Some(ok)) if nk == ok => out.replace(nk, new_root),
+                _ => return,
+            }
+        }
+        out.put(b"]");
+
+        if out.overflow {
+            bump(&mut self.stats.diff_overflows, 1);
+        } else {
+            let len = out.len;
+            self.diff.set_len(len);
+        }
+    }
+
+    /// Serialize the current snapshot and valid cache entries into `export`.
+    /// Returns the length (0 if memory can't grow).
+    fn export_state(&mut self) -> u32 {
+        let current = self.current.bytes();
+        let mut need = STATE_MAGIC.len() + 4 + current.len() + 4;
+        for e in self.cache.iter().filter(|e| e.valid) {
+            need += 8 + 8 + 4 + e.packed_len as usize;
+        }
+        if !self.export.ensure(HEADER_LEN + need) {
+            return 0;
+        }
+
+        let frame = self.export.bytes_mut(HEADER_LEN + need);
+        let mut out = PatchOut { buf: &mut frame[HEADER_LEN..], len: 0, overflow: false };
+        out.put(STATE_MAGIC);
+        out.put(&(current.len() as u32).to_le_bytes());
+        out.put(current);
+        out.put(&(self.cache.iter().filter(|e| e.valid).count() as u32).to_le_bytes());
+        // Oldest first, so import's round-robin order matches
+        let mut i = 0;
+        while i < CACHE_N {
+            let e = &self.cache[(self.cache_cursor + i) % CACHE_N];
+            if e.valid {
+                out.put(&e.key.to_le_bytes());
+                out.put(&e.hash.to_le_bytes());
+                out.put(&e.packed_len.to_le_bytes());
+                out.put(&e.packed[..e.packed_len as usize]);
+            }
+            i += 1;
+        }
+        let len = if out.overflow { 0 } else { out.len };
+        magnetic_wasm_abi::write_header(frame, len);
+        len as u32
+    }
+
+    /// Restore state written by export_state() from the input buffer. The
+    /// whole blob is validated before anything is replaced; the snapshot is
+    /// only restored if there is no current one yet. Returns the restored
+    /// snapshot's length (0 = none).
+    fn import_state(&mut self, len: u32) -> u32 {
+        let Some(src) = input_bytes(&self.input, &self.big_input, len) else {
+            return 0;
+        };
+        let mut r = StateReader { b: src, i: 0 };
+        if r.take(STATE_MAGIC.len()) != Some(STATE_MAGIC.as_slice()) {
+            return 0;
+        }
+        let Some(current) = r.u32().and_then(|n| r.take(n as usize)) else {
+            return 0;
+        };
+        let Some(count) = r.u32().map(|n| n as usize).filter(|&n| n <= CACHE_N) else {
+            return 0;
+        };
+        let entries = r.i;
+
+        // Validate: every entry must decompress to its recorded hash
+        let mut k = 0;
+        while k < count {
+            let (Some(_), Some(hash), Some(packed)) = (r.u64(), r.u64(), r.u32().and_then(|n| r.take(n as usize))) else {
+                return 0;
+            };
+            if packed.len() > SLOT_CAP {
+                return 0;
+            }
+            let out = self.output.data_mut();
+            match lz_decompress(packed, out) {
+                Some(n) if hash64(&out[..n]) == hash => {}
+                _ => return 0,
+            }
+            k += 1;
+        }
+        if r.i != src.len() {
+            return 0;
+        }
+
+        r.i = entries;
+        let mut k = 0;
+        while k < CACHE_N {
+            let e = &mut self.cache[k];
+            e.valid = k < count;
+            if e.valid {
+                let (Some(key), Some(hash), Some(packed)) = (r.u64(), r.u64(), r.u32().and_then(|n| r.take(n as usize))) else {
+                    return 0;
+                };
+                e.key = key;
+                e.hash = hash;
+                e.generation = self.generation;
+                e.packed[..packed.len()].copy_from_slice(packed);
+                e.packed_len = packed.len() as u32;
+            }
+            k += 1;
+        }
+        self.cache_cursor = count % CACHE_N;
+
+        if !self.current.is_empty() || current.is_empty() {
+            return 0;
         }
+        self.current.write(current);
+        self.current.len
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════
+// Persisted state — export_state() / import_state() byte format
+// ═══════════════════════════════════════════════════════════════════
+//
+// Little-endian, versioned by the magic so a blob from an older build is
+// rejected rather than misread:
+//
+//   "MGT1"  u32 snapshot_len  snapshot bytes
+//   u32 count  count × (u64 key, u64 hash, u32 packed_len, packed bytes)
+//
+// The namespace isn't stored: cache keys already include it, and JS sets the
+// current route's namespace on load.
+
+const STATE_MAGIC: &[u8; 4] = b"MGT1";
+
+struct StateReader<'a> {
+    b: &'a [u8],
+    i: usize,
+}
+
+impl<'a> StateReader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.b.get(self.i..self.i.checked_add(n)?)?;
+        self.i += n;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let b = self.take(8)?;
+        Some(u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
     }
 }
 
@@ -155,19 +906,42 @@ static G: Globals = Globals {
 // WASM exports
 // ═══════════════════════════════════════════════════════════════════
 
+/// ABI version of this binary (see the compatibility table at the top).
+#[no_mangle]
+pub extern "C" fn abi_version() -> u32 {
+    ABI_VERSION
+}
+
 /// Pointer to shared input buffer. JS writes action or snapshot bytes here.
 #[no_mangle]
 pub extern "C" fn input_ptr() -> *mut u8 {
     unsafe { (*G.t.get()).input.as_mut_ptr() }
 }
 
+/// Input buffer for a snapshot of `len` bytes: the static input buffer when
+/// it fits, else a region grown with memory.grow (re-read memory.buffer
+/// after calling). Null if len is over MAX_SNAPSHOT or memory can't grow.
+#[no_mangle]
+pub extern "C" fn reserve(len: u32) -> *mut u8 {
+    unsafe {
+        let t = &mut *G.t.get();
+        if len as usize <= INPUT_CAP {
+            return t.input.as_mut_ptr();
+        }
+        if !t.big_input.ensure(len as usize) {
+            return core::ptr::null_mut();
+        }
+        t.big_input.ptr
+    }
+}
+
 /// Initialize. Returns pointer to current snapshot data (empty on first call).
 #[no_mangle]
 pub extern "C" fn init() -> *const u8 {
-    unsafe { (*G.t.get()).current.data.as_ptr() }
+    unsafe { (*G.t.get()).current.bytes().as_ptr() }
 }
 
-/// Predict: look up (current.hash, action_hash) in cache.
+/// Predict: look up (namespace, current.hash, action_hash) in cache.
 /// Returns pointer to snapshot data.
 /// Call snapshot_len() to check result: 0 = cache miss, >0 = hit.
 #[no_mangle]
@@ -175,28 +949,44 @@ pub extern "C" fn reduce(action_len: u32) -> *const u8 {
     unsafe {
         let t = &mut *G.t.get();
         let action = &t.input[..action_len as usize];
-        let action_hash = fnv(action);
-        let key = make_key(t.current.hash, action_hash);
+        let action_hash = hash64(action);
+        let key = make_key(t.namespace, t.current.hash, action_hash);
 
-        // Record pending info so store() can learn
-        t.pending_action_hash = action_hash;
-        t.pending_pre_hash = t.current.hash;
+        // Record pending info so store() can learn (under the namespace the
+        // action was sent from, even if navigation changes it meanwhile)
+        t.pending_key = key;
         t.has_pending = true;
 
         // Search cache
         let mut i = 0;
         while i < CACHE_N {
             if t.cache[i].valid && t.cache[i].key == key {
+                let e = &mut t.cache[i];
+                // Learned too many authoritative updates ago: server data
+                // may have moved on, so don't flash it
+                if t.ttl != 0 && t.generation.wrapping_sub(e.generation) > t.ttl {
+                    e.valid = false;
+                    bump(&mut t.stats.predictions_expired, 1);
+                    break;
+                }
                 // Cache hit — return predicted snapshot
-                t.result_ptr = t.cache[i].slot.data.as_ptr();
-                t.result_len = t.cache[i].slot.len;
-                t.predicted_hash = t.cache[i].slot.hash;
+                let Some(n) = lz_decompress(&e.packed[..e.packed_len as usize], t.output.data_mut()) else {
+                    e.valid = false;
+                    break;
+                };
+                t.output.set_len(n);
+                t.result_ptr = t.output.data_ptr();
+                t.result_len = n as u32;
+                t.predicted_hash = e.hash;
+                bump(&mut t.stats.cache_hits, 1);
                 return t.result_ptr;
             }
             i += 1;
         }
 
         // Cache miss
+        bump(&mut t.stats.cache_misses, 1);
+        t.output.set_len(0);
         t.result_ptr = t.current.data.as_ptr();
         t.result_len = 0;
         t.predicted_hash = 0;
@@ -210,6 +1000,51 @@ pub extern "C" fn snapshot_len() -> u32 {
     unsafe { (*G.t.get()).result_len }
 }
 
+/// reduce() returning a frame: [len u32][predicted snapshot], len 0 =
+/// cache miss.
+#[no_mangle]
+pub extern "C" fn reduce_framed(action_len: u32) -> *const u8 {
+    reduce(action_len);
+    unsafe { (*G.t.get()).output.as_ptr() }
+}
+
+/// Diff the snapshot in the input buffer against the current one, without
+/// storing it. Returns pointer to a JSON patch list of keyed node
+/// replacements; call diff_len() for its length: 0 = no usable diff (first
+/// snapshot, structural change, or patch list too large) → apply in full.
+/// Call before store(), which replaces the current snapshot.
+#[no_mangle]
+pub extern "C" fn diff_current(snap_len: u32) -> *const u8 {
+    unsafe {
+        let t = &mut *G.t.get();
+        t.diff_snapshot(snap_len);
+        t.diff.data_ptr()
+    }
+}
+
+/// Length of the last diff_current() result. 0 = apply the snapshot in full.
+#[no_mangle]
+pub extern "C" fn diff_len() -> u32 {
+    unsafe { (*G.t.get()).diff.len() as u32 }
+}
+
+/// diff_current() returning a frame: [len u32][patch list], len 0 = apply
+/// the snapshot in full.
+#[no_mangle]
+pub extern "C" fn diff_framed(snap_len: u32) -> *const u8 {
+    unsafe {
+        let t = &mut *G.t.get();
+        t.diff_snapshot(snap_len);
+        t.diff.as_ptr()
+    }
+}
+
+/// Size of the static input buffer; larger snapshots go through reserve().
+#[no_mangle]
+pub extern "C" fn input_cap() -> u32 {
+    INPUT_CAP as u32
+}
+
 /// Store authoritative snapshot from input buffer.
 /// Learns cache entry if a prediction was pending.
 /// Returns:
@@ -220,24 +1055,40 @@ pub extern "C" fn store(snap_len: u32) -> u32 {
     unsafe {
         let t = &mut *G.t.get();
 
-        if snap_len == 0 || snap_len as usize > INPUT_CAP {
+        let Some(snap) = input_bytes(&t.input, &t.big_input, snap_len) else {
             return 0;
+        };
+        let snap_hash = hash64(snap);
+        bump(&mut t.stats.bytes_stored, snap.len());
+        if t.current.is_empty() || snap_hash != t.current.hash {
+            t.generation = t.generation.wrapping_add(1);
         }
 
-        let snap = &t.input[..snap_len as usize];
-        let snap_hash = fnv(snap);
-
         // Learn: cache (prev_state, action) → this result
         if t.has_pending {
-            let key = make_key(t.pending_pre_hash, t.pending_action_hash);
-
-            // Only cache if snapshot fits in a slot
-            if (snap_len as usize) <= SLOT_CAP {
-                let idx = t.cache_cursor % CACHE_N;
-                t.cache[idx].key = key;
-                t.cache[idx].slot.write(snap);
-                t.cache[idx].valid = true;
-                t.cache_cursor = t.cache_cursor.wrapping_add(1);
+            let key = t.pending_key;
+
+            // Relearn into the entry already holding this key (a corrected or
+            // confirmed prediction), else the next round-robin slot. Only
+            // cache if the compressed snapshot fits in a slot (and its
+            // decompressed form in the output buffer)
+            let existing = t.cache.iter().position(|e| e.valid && e.key == key);
+            let idx = existing.unwrap_or(t.cache_cursor % CACHE_N);
+            let e = &mut t.cache[idx];
+            e.valid = false;
+            let packed = if snap.len() <= INPUT_CAP { lz_compress(snap, &mut e.packed, &mut t.lz_table) } else { None };
+            if let Some(n) = packed {
+                e.key = key;
+                e.packed_len = n as u32;
+                e.hash = snap_hash;
+                e.generation = t.generation;
+                e.valid = true;
+                if existing.is_none() {
+                    t.cache_cursor = t.cache_cursor.wrapping_add(1);
+                }
+                bump(&mut t.stats.cache_bytes, n);
+            } else {
+                bump(&mut t.stats.cache_rejects, 1);
             }
 
             t.has_pending = false;
@@ -246,22 +1097,416 @@ pub extern "C" fn store(snap_len: u32) -> u32 {
         // Check: does authoritative match our prediction?
         if t.predicted_hash != 0 && snap_hash == t.predicted_hash {
             // Prediction was correct — update current, no re-render
+            bump(&mut t.stats.predictions_confirmed, 1);
             t.current.write(snap);
             t.predicted_hash = 0;
             t.result_len = 0;
             return 0;
         }
+        if t.predicted_hash != 0 {
+            bump(&mut t.stats.predictions_wrong, 1);
+        }
         t.predicted_hash = 0;
 
         // Check: is it identical to current? (duplicate SSE)
         if !t.current.is_empty() && snap_hash == t.current.hash {
+            bump(&mut t.stats.dedup_skips, 1);
             return 0;
         }
 
         // New snapshot — update current, signal re-render
+        bump(&mut t.stats.stores, 1);
         t.current.write(snap);
-        t.result_ptr = t.current.data.as_ptr();
+        t.result_ptr = t.current.bytes().as_ptr();
         t.result_len = t.current.len;
         1
     }
 }
+
+/// Pointer to the Stats counters (stats_len() consecutive u32s).
+#[no_mangle]
+pub extern "C" fn stats_ptr() -> *const u32 {
+    unsafe { &(*G.t.get()).stats as *const Stats as *const u32 }
+}
+
+/// Number of u32 counters at stats_ptr().
+#[no_mangle]
+pub extern "C" fn stats_len() -> u32 {
+    STATS_N
+}
+
+/// Zero all counters.
+#[no_mangle]
+pub extern "C" fn stats_reset() {
+    unsafe { (*G.t.get()).stats = Stats::new() }
+}
+
+/// Queue the action in the input buffer (the JSON POST body) while the
+/// connection is down. Returns 1 if queued, 0 if the queue is full or the
+/// action is larger than ACTION_CAP (JS keeps it queued itself then).
+#[no_mangle]
+pub extern "C" fn queue_action(len: u32) -> u32 {
+    unsafe {
+        let t = &mut *G.t.get();
+        let len = len as usize;
+        if len == 0 || len > ACTION_CAP || t.queue_count == QUEUE_N {
+            bump(&mut t.stats.actions_rejected, 1);
+            return 0;
+        }
+        let i = t.queue_count;
+        t.queue[i][..len].copy_from_slice(&t.input[..len]);
+        t.queue_lens[i] = len as u32;
+        t.queue_count += 1;
+        1
+    }
+}
+
+/// Number of queued actions.
+#[no_mangle]
+pub extern "C" fn pending_count() -> u32 {
+    unsafe { (*G.t.get()).queue_count as u32 }
+}
+
+/// Pointer to queued action `idx` (0 = oldest). Null if out of range.
+#[no_mangle]
+pub extern "C" fn pending_ptr(idx: u32) -> *const u8 {
+    unsafe {
+        let t = &*G.t.get();
+        if idx as usize >= t.queue_count {
+            return core::ptr::null();
+        }
+        t.queue[idx as usize].as_ptr()
+    }
+}
+
+/// Length of queued action `idx`. 0 if out of range.
+#[no_mangle]
+pub extern "C" fn pending_len(idx: u32) -> u32 {
+    unsafe {
+        let t = &*G.t.get();
+        if idx as usize >= t.queue_count {
+            return 0;
+        }
+        t.queue_lens[idx as usize]
+    }
+}
+
+/// Drop all queued actions (after JS has replayed them).
+#[no_mangle]
+pub extern "C" fn clear_pending() {
+    unsafe { (*G.t.get()).queue_count = 0 }
+}
+
+/// Serialize the current snapshot and learned cache entries for JS to
+/// persist (e.g. in localStorage). Returns pointer; call export_len() for
+/// the length (0 = nothing exported).
+#[no_mangle]
+pub extern "C" fn export_state() -> *const u8 {
+    unsafe {
+        let t = &mut *G.t.get();
+        t.export_len = t.export_state();
+        if t.export.ptr.is_null() { t.export.ptr } else { t.export.ptr.add(HEADER_LEN) }
+    }
+}
+
+/// Length of the last export_state() result.
+#[no_mangle]
+pub extern "C" fn export_len() -> u32 {
+    unsafe { (*G.t.get()).export_len }
+}
+
+/// export_state() returning a frame: [len u32][blob], len 0 = nothing
+/// exported.
+#[no_mangle]
+pub extern "C" fn export_framed() -> *const u8 {
+    unsafe {
+        let t = &mut *G.t.get();
+        t.export_len = t.export_state();
+        if t.export_len == 0 { magnetic_wasm_abi::EMPTY.as_ptr() } else { t.export.ptr }
+    }
+}
+
+/// Restore an export_state() blob from the input buffer (reserve() it when
+/// over input_cap()). Replaces the cache entries; the snapshot is restored
+/// only before the first store(). Returns the restored snapshot's length —
+/// read it at init() — or 0 (nothing restored, or the blob was rejected).
+#[no_mangle]
+pub extern "C" fn import_state(len: u32) -> u32 {
+    unsafe { (*G.t.get()).import_state(len) }
+}
+
+/// Drop cached predictions learned more than `updates` authoritative
+/// snapshot changes ago (0 = never expire, the default).
+#[no_mangle]
+pub extern "C" fn set_ttl(updates: u32) {
+    unsafe { (*G.t.get()).ttl = updates }
+}
+
+/// Set the cache namespace to the route path in the input buffer, so
+/// predictions learned on one route are never returned on another.
+/// len 0 = back to the shared default namespace.
+#[no_mangle]
+pub extern "C" fn set_namespace(len: u32) {
+    unsafe {
+        let t = &mut *G.t.get();
+        let len = len as usize;
+        t.namespace = if len == 0 || len > INPUT_CAP { 0 } else { hash64(&t.input[..len]) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, MutexGuard};
+
+    // The exports share one global transport: tests take turns on a fresh one
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    fn fresh() -> MutexGuard<'static, ()> {
+        let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        unsafe { *G.t.get() = Transport::new() };
+        guard
+    }
+
+    /// Write bytes where JS would (the input buffer, or a reserve()d region)
+    fn put(bytes: &[u8]) -> u32 {
+        let p = reserve(bytes.len() as u32);
+        assert!(!p.is_null());
+        unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), p, bytes.len()) };
+        bytes.len() as u32
+    }
+
+    fn stats() -> &'static Stats {
+        unsafe { &(*G.t.get()).stats }
+    }
+
+    fn predict(action: &[u8]) -> Option<&'static [u8]> {
+        let p = reduce(put(action));
+        let n = snapshot_len() as usize;
+        if n == 0 { None } else { Some(unsafe { core::slice::from_raw_parts(p, n) }) }
+    }
+
+    const S1: &[u8] = br#"{"root":{"tag":"ul","key":"list","children":[{"tag":"li","key":"a","text":"A"},{"tag":"li","key":"b","text":"B"}]}}"#;
+    const S2: &[u8] = br#"{"root":{"tag":"ul","key":"list","children":[{"tag":"li","key":"a","text":"A"},{"tag":"li","key":"b","text":"B2"}]}}"#;
+
+    #[test]
+    fn test_hash64_reference() {
+        assert_eq!(hash64(b""), 0xef46db3751d8e999);
+        assert_eq!(hash64(b"a"), 0xd24ec4f1a98c6e5b);
+        assert_eq!(hash64(b"abc"), 0x44bc2cf5ad770999);
+        assert_eq!(hash64(b"Nobody inspects the spammish repetition"), 0xfbcea83c8a378bf1);
+    }
+
+    #[test]
+    fn test_store_dedup() {
+        let _g = fresh();
+        assert_eq!(store(put(S1)), 1);
+        assert_eq!(store(put(S1)), 0);
+        assert_eq!(store(put(S2)), 1);
+        assert_eq!(stats().stores, 2);
+        assert_eq!(stats().dedup_skips, 1);
+        let current = unsafe { core::slice::from_raw_parts(init(), S2.len()) };
+        assert_eq!(current, S2);
+    }
+
+    #[test]
+    fn test_predict_learn_confirm() {
+        let _g = fresh();
+        let action = br#"{"action":"edit","payload":{}}"#;
+        store(put(S1));
+        assert_eq!(predict(action), None);
+        assert_eq!(store(put(S2)), 1); // learned (S1, edit) → S2
+
+        store(put(S1));
+        assert_eq!(predict(action), Some(S2));
+        assert_eq!(store(put(S2)), 0); // confirmed: no re-render
+        assert_eq!(stats().cache_hits, 1);
+        assert_eq!(stats().predictions_confirmed, 1);
+
+        // Same action from another state: no prediction
+        assert_eq!(predict(action), None);
+    }
+
+    #[test]
+    fn test_prediction_wrong() {
+        let _g = fresh();
+        let action = b"toggle";
+        store(put(S1));
+        predict(action);
+        store(put(S2));
+
+        store(put(S1));
+        assert_eq!(predict(action), Some(S2));
+        assert_eq!(store(put(S1)), 0); // server says nothing changed: dedup
+        assert_eq!(stats().predictions_wrong, 1);
+        assert_eq!(stats().dedup_skips, 1);
+
+        // The correction replaced the stale entry instead of sitting behind it
+        assert_eq!(predict(action), Some(S1));
+    }
+
+    #[test]
+    fn test_prediction_ttl() {
+        let _g = fresh();
+        let s3 = br#"{"root":{"tag":"p","text":"other"}}"#;
+        let action = b"edit";
+        set_ttl(2);
+        store(put(S1));
+        predict(action);
+        store(put(S2)); // learned
+
+        store(put(S1));
+        assert_eq!(predict(action), Some(S2));
+        store(put(S2)); // confirmed: fresh again
+
+        for snap in [s3, S1, s3, S1] {
+            store(put(snap));
+        }
+        assert_eq!(predict(action), None);
+        assert_eq!(stats().predictions_expired, 1);
+    }
+
+    #[test]
+    fn test_namespace_partition() {
+        let _g = fresh();
+        let action = b"open";
+        set_namespace(put(b"/a"));
+        store(put(S1));
+        predict(action);
+        store(put(S2));
+        store(put(S1));
+
+        set_namespace(put(b"/b"));
+        assert_eq!(predict(action), None);
+        set_namespace(put(b"/a"));
+        assert_eq!(predict(action), Some(S2));
+    }
+
+    #[test]
+    fn test_diff_current() {
+        let _g = fresh();
+        diff_current(put(S1));
+        assert_eq!(diff_len(), 0); // nothing to diff against yet
+        store(put(S1));
+
+        let p = diff_current(put(S2));
+        let patches = unsafe { core::slice::from_raw_parts(p, diff_len() as usize) };
+        assert_eq!(patches, br#"[{"key":"b","node":{"tag":"li","key":"b","text":"B2"}}]"#);
+
+        // A removed child under the list changes its structure: replace the
+        // list itself
+        let s3 = br#"{"root":{"tag":"ul","key":"list","children":[{"tag":"li","key":"a","text":"A"}]}}"#;
+        let p = diff_current(put(s3));
+        let patches = unsafe { core::slice::from_raw_parts(p, diff_len() as usize) };
+        assert_eq!(patches, [&b"[{\"key\":\"list\",\"node\":"[..], &s3[8..s3.len() - 1], b"}]"].concat());
+
+        // Unkeyed root changed: full apply
+        diff_current(put(br#"{"root":{"tag":"div"}}"#));
+        assert_eq!(diff_len(), 0);
+    }
+
+    #[test]
+    fn test_large_snapshot() {
+        let _g = fresh();
+        let mut big = br#"{"root":{"tag":"ul","children":["#.to_vec();
+        for i in 0..4000 {
+            big.extend_from_slice(format!(r#"{{"tag":"li","key":"k{}","text":"item {}"}},"#, i, i).as_bytes());
+        }
+        big.pop();
+        big.extend_from_slice(b"]}}");
+        assert!(big.len() > INPUT_CAP);
+
+        assert_eq!(store(put(&big)), 1);
+        assert_eq!(store(put(&big)), 0);
+        let current = unsafe { core::slice::from_raw_parts(init(), big.len()) };
+        assert_eq!(current, &big[..]);
+        assert!(reserve(MAX_SNAPSHOT as u32 + 1).is_null());
+    }
+
+    #[test]
+    fn test_lz_roundtrip() {
+        let mut table = [LZ_EMPTY; LZ_HASH_N];
+        let mut packed = [0u8; SLOT_CAP];
+        let mut out = [0u8; INPUT_CAP];
+        let src = S1.repeat(20);
+        let n = lz_compress(&src, &mut packed, &mut table).unwrap();
+        assert!(n < src.len() / 4);
+        assert_eq!(lz_decompress(&packed[..n], &mut out), Some(src.len()));
+        assert_eq!(&out[..src.len()], &src[..]);
+
+        // Malformed input is rejected, never read or written out of bounds
+        assert_ne!(lz_decompress(&packed[..n - 1], &mut out), Some(src.len()));
+        assert_eq!(lz_decompress(&[0x80, 0x05, 0x00], &mut out), None);
+        assert_eq!(lz_decompress(&[0x10, b'x'], &mut out), None);
+        assert_eq!(lz_decompress(&packed[..n], &mut out[..10]), None);
+    }
+
+    #[test]
+    fn test_offline_queue() {
+        let _g = fresh();
+        assert_eq!(queue_action(put(b"first")), 1);
+        assert_eq!(queue_action(put(b"second")), 1);
+        assert_eq!(queue_action(put(&[b'x'; ACTION_CAP + 1])), 0);
+        assert_eq!(pending_count(), 2);
+        let second = unsafe { core::slice::from_raw_parts(pending_ptr(1), pending_len(1) as usize) };
+        assert_eq!(second, b"second");
+        assert!(pending_ptr(2).is_null());
+
+        for _ in 2..QUEUE_N {
+            assert_eq!(queue_action(put(b"more")), 1);
+        }
+        assert_eq!(queue_action(put(b"full")), 0);
+        assert_eq!(stats().actions_rejected, 2);
+        clear_pending();
+        assert_eq!(pending_count(), 0);
+    }
+
+    #[test]
+    fn test_export_import() {
+        let _g = fresh();
+        let action = b"edit";
+        store(put(S1));
+        predict(action);
+        store(put(S2));
+        let p = export_state();
+        let blob = unsafe { core::slice::from_raw_parts(p, export_len() as usize) }.to_vec();
+        assert!(blob.starts_with(STATE_MAGIC));
+
+        // Next page load
+        unsafe { *G.t.get() = Transport::new() };
+        let mut corrupt = blob.clone();
+        let last = corrupt.len() - 2;
+        corrupt[last] ^= 0x55;
+        assert_eq!(import_state(put(&corrupt)), 0);
+        assert_eq!(import_state(put(&blob[..blob.len() - 1])), 0);
+        assert_eq!(predict(action), None); // nothing was imported
+
+        assert_eq!(import_state(put(&blob)) as usize, S2.len());
+        let current = unsafe { core::slice::from_raw_parts(init(), S2.len()) };
+        assert_eq!(current, S2);
+        store(put(S1));
+        assert_eq!(predict(action), Some(S2));
+
+        // Never replaces a snapshot already received
+        assert_eq!(import_state(put(&blob)), 0);
+    }
+
+    #[test]
+    fn test_framed_results() {
+        let _g = fresh();
+        let read = |p| unsafe { magnetic_wasm_abi::read(p) };
+        assert_eq!(read(diff_framed(put(S1))), b""); // nothing to diff against
+
+        let action = b"edit";
+        store(put(S1));
+        assert_eq!(read(reduce_framed(put(action))), b"");
+        store(put(S2));
+        store(put(S1));
+        assert_eq!(read(reduce_framed(put(action))), S2);
+
+        assert_eq!(read(diff_framed(put(S2))), br#"[{"key":"b","node":{"tag":"li","key":"b","text":"B2"}}]"#);
+        let blob = read(export_framed()).to_vec();
+        let p = export_state();
+        assert_eq!(unsafe { core::slice::from_raw_parts(p, export_len() as usize) }, blob.as_slice());
+    }
+}
@@ -0,0 +1,369 @@
+//! archive.rs — Deploys uploaded as a gzipped tar
+//!
+//! `POST /api/apps/<name>/deploy` with `Content-Type: application/gzip`
+//! takes a `.tar.gz` of the app instead of the JSON payload:
+//!
+//! ```text
+//! bundle.js           → "bundle"
+//! config.json         → "config"
+//! public/<path>       → "assets"   (nested paths kept)
+//! data/<path>         → "files"    (file data sources)
+//! static/<path>       → "assets", with "static": true (SSG; no bundle.js)
+//! ```
+//!
+//! Other entries are ignored. The archive becomes that JSON payload, so it
+//! deploys, is kept in the version history and rolls back like any other.
+//! Files that aren't UTF-8 (fonts, images) become `{"base64": "..."}`.
+//!
+//! Nothing is extracted as is: every entry's path is checked (relative, no
+//! `..`, no empty or `.` parts) and only regular files are read. Links,
+//! devices and other entry types fail the deploy. The unpacked size is
+//! capped at MAX_UNPACKED_MB and the entry count at MAX_ENTRIES, so a
+//! small upload can't expand without bound; the upload itself is bounded
+//! by `--max-body-mb`.
+
+use std::io::Read;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+
+/// Most an archive may hold once unpacked
+pub const MAX_UNPACKED_MB: usize = 256;
+/// Most entries an archive may hold
+pub const MAX_ENTRIES: usize = 10_000;
+
+const BLOCK: usize = 512;
+
+/// Whether a deploy's Content-Type is a gzipped archive
+pub fn is_archive(content_type: Option<&str>) -> bool {
+    let mime = content_type.and_then(|ct| ct.split(';').next()).map(str::trim);
+    mime.is_some_and(|m| m.eq_ignore_ascii_case("application/gzip") || m.eq_ignore_ascii_case("application/x-gzip"))
+}
+
+/// The deploy payload packed in a `.tar.gz`
+pub fn to_payload(gz: &[u8]) -> Result<Value, String> {
+    let limit = MAX_UNPACKED_MB * 1024 * 1024;
+    let mut tar = Vec::new();
+    flate2::read::GzDecoder::new(gz)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut tar)
+        .map_err(|e| format!("Not a gzip archive: {}", e))?;
+    if tar.len() > limit {
+        return Err(format!("Archive is over {} MB unpacked", MAX_UNPACKED_MB));
+    }
+
+    let mut payload = Map::new();
+    let mut assets = Map::new();
+    let mut static_assets = Map::new();
+    let mut files = Map::new();
+    for (path, data) in entries(&tar)? {
+        match path.split_once('/') {
+            None if path == "bundle.js" => {
+                let bundle = String::from_utf8(data).map_err(|_| "bundle.js is not UTF-8".to_string())?;
+                payload.insert("bundle".into(), bundle.into());
+            }
+            None if path == "config.json" => {
+                let config = String::from_utf8(data).map_err(|_| "config.json is not UTF-8".to_string())?;
+                payload.insert("config".into(), config.into());
+            }
+            Some(("public", rest)) => {
+                assets.insert(rest.to_string(), contents(data));
+            }
+            Some(("static", rest)) => {
+                static_assets.insert(rest.to_string(), contents(data));
+            }
+            Some(("data", rest)) => {
+                files.insert(rest.to_string(), contents(data));
+            }
+            _ => {}
+        }
+    }
+
+    match (payload.contains_key("bundle"), static_assets.is_empty()) {
+        (true, false) => return Err("The archive has both bundle.js and static/; deploy one or the other".into()),
+        (false, true) => return Err("The archive has neither bundle.js nor static/".into()),
+        (false, false) => {
+            payload.insert("static".into(), true.into());
+            payload.insert("assets".into(), static_assets.into());
+        }
+        (true, true) => {
+            payload.insert("assets".into(), assets.into());
+            payload.insert("files".into(), files.into());
+        }
+    }
+    Ok(payload.into())
+}
+
+/// A deploy file's bytes: a string as is, or `{"base64": ...}` decoded
+pub fn file_bytes(content: &Value) -> Option<Vec<u8>> {
+    match content {
+        Value::String(text) => Some(text.clone().into_bytes()),
+        Value::Object(obj) => STANDARD.decode(obj.get("base64")?.as_str()?).ok(),
+        _ => None,
+    }
+}
+
+/// A file for the payload: text as a string, anything else as base64
+fn contents(data: Vec<u8>) -> Value {
+    match String::from_utf8(data) {
+        Ok(text) => text.into(),
+        Err(e) => json!({ "base64": STANDARD.encode(e.into_bytes()) }),
+    }
+}
+
+/// The regular files of a tar (ustar, with pax and GNU long names), as
+/// checked relative paths and their contents
+fn entries(tar: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut out = Vec::new();
+    let mut at = 0;
+    let mut count = 0;
+    // A long name from a pax or GNU header, for the entry after it
+    let mut long_name: Option<String> = None;
+    while at + BLOCK <= tar.len() {
+        let header = &tar[at..at + BLOCK];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        count += 1;
+        if count > MAX_ENTRIES {
+            return Err(format!("Archive has over {} entries", MAX_ENTRIES));
+        }
+        if Some(checksum(header)) != octal(&header[148..156]) {
+            return Err(format!("Bad tar header at byte {}", at));
+        }
+        let size = octal(&header[124..136]).ok_or_else(|| format!("Bad entry size at byte {}", at))? as usize;
+        let start = at + BLOCK;
+        let end = start.checked_add(size).filter(|&end| end <= tar.len())
+            .ok_or_else(|| "Archive is truncated".to_string())?;
+        let data = &tar[start..end];
+        at = start + size.div_ceil(BLOCK) * BLOCK;
+
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => header_name(header),
+        };
+        match header[156] {
+            b'0' | 0 | b'7' => {
+                let path = safe_path(&name)?;
+                out.push((path, data.to_vec()));
+            }
+            // The archive's root (`./`) is fine; other directories are
+            // only checked, since files bring their own
+            b'5' if matches!(name.trim_start_matches("./").trim_end_matches('/'), "" | ".") => {}
+            b'5' => {
+                safe_path(&name)?;
+            }
+            // pax extended header: only `path` matters here
+            b'x' => long_name = pax_path(data),
+            // pax global header
+            b'g' => {}
+            // GNU long name
+            b'L' => long_name = Some(String::from_utf8_lossy(data).trim_end_matches('\0').to_string()),
+            kind => return Err(format!("'{}' is not a regular file or directory (type '{}')", name, kind as char)),
+        }
+    }
+    Ok(out)
+}
+
+/// `prefix/name` of a ustar header
+fn header_name(header: &[u8]) -> String {
+    let field = |bytes: &[u8]| {
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).to_string()
+    };
+    let name = field(&header[0..100]);
+    // POSIX ustar only: old GNU headers keep other fields there
+    let prefix = if &header[257..263] == b"ustar\0" { field(&header[345..500]) } else { String::new() };
+    if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) }
+}
+
+/// The `path` record of a pax header: `"<len> path=<value>\n"`
+fn pax_path(data: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(data).ok()?;
+    text.lines()
+        .find_map(|record| record.split_once(' ')?.1.strip_prefix("path="))
+        .map(String::from)
+}
+
+/// An entry's path without `./`, or an error if it could leave the app dir
+fn safe_path(name: &str) -> Result<String, String> {
+    let trimmed = name.trim_start_matches("./").trim_end_matches('/');
+    let parts: Vec<&str> = trimmed.split('/').collect();
+    let unsafe_path = name.starts_with('/')
+        || name.contains('\\')
+        || trimmed.is_empty()
+        || parts.iter().any(|p| p.is_empty() || *p == "." || *p == "..");
+    if unsafe_path {
+        return Err(format!("Unsafe path in archive: '{}'", name));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// A NUL- or space-terminated octal number
+fn octal(field: &[u8]) -> Option<u64> {
+    let text = std::str::from_utf8(field).ok()?;
+    let digits = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    u64::from_str_radix(digits, 8).ok()
+}
+
+/// The header's checksum, with its own field counted as spaces
+fn checksum(header: &[u8]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| if (148..156).contains(&i) { b' ' as u64 } else { b as u64 })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// A ustar header for `name`, with a valid checksum
+    fn header(name: &str, kind: u8, size: usize) -> Vec<u8> {
+        let mut header = vec![0u8; BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        let sum = checksum(&header);
+        header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+        header
+    }
+
+    /// A header and its data, padded to whole blocks
+    fn entry(name: &str, kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut out = header(name, kind, data.len());
+        out.extend_from_slice(data);
+        out.resize(out.len().div_ceil(BLOCK) * BLOCK, 0);
+        out
+    }
+
+    fn tar(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = entries.concat();
+        out.extend_from_slice(&[0u8; BLOCK * 2]);
+        out
+    }
+
+    fn gz(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn app_layout_becomes_the_payload() {
+        let archive = tar(&[
+            entry("./", b'5', b""),
+            entry("./bundle.js", b'0', b"export default 1"),
+            entry("config.json", b'0', b"{}"),
+            entry("public/img/logo.png", b'0', &[0x89, 0xff]),
+            entry("data/posts.json", b'0', b"[]"),
+            entry("README.md", b'0', b"ignored"),
+        ]);
+        let payload = to_payload(&gz(&archive)).unwrap();
+        assert_eq!(payload["bundle"], "export default 1");
+        assert_eq!(payload["config"], "{}");
+        assert_eq!(payload["assets"]["img/logo.png"]["base64"], STANDARD.encode([0x89, 0xff]));
+        assert_eq!(payload["files"]["posts.json"], "[]");
+        assert!(payload.get("README.md").is_none());
+    }
+
+    #[test]
+    fn safe_path_rejects_leaving_the_app_dir() {
+        for name in ["../etc/passwd", "public/../../x", "/etc/passwd", "public//x", "public/./x", "a\\b", ".", "./"] {
+            assert!(safe_path(name).is_err(), "{}", name);
+        }
+        assert_eq!(safe_path("./public/app.css").unwrap(), "public/app.css");
+        assert_eq!(safe_path("public/img/").unwrap(), "public/img");
+    }
+
+    #[test]
+    fn unsafe_entries_fail_the_deploy() {
+        let err = entries(&tar(&[entry("../bundle.js", b'0', b"x")])).unwrap_err();
+        assert!(err.contains("Unsafe path"), "{}", err);
+        let err = entries(&tar(&[entry("public/../../x/", b'5', b"")])).unwrap_err();
+        assert!(err.contains("Unsafe path"), "{}", err);
+    }
+
+    #[test]
+    fn links_are_refused() {
+        for kind in [b'1', b'2'] {
+            let err = entries(&tar(&[entry("public/link", kind, b"")])).unwrap_err();
+            assert!(err.contains("is not a regular file or directory"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn pax_path_names_the_next_entry() {
+        let long = format!("public/{}/app.css", "d".repeat(120));
+        let record = format!("path={}\n", long);
+        let record = format!("{} {}", record.len() + 4, record);
+        let files = entries(&tar(&[
+            entry("PaxHeader/app.css", b'x', record.as_bytes()),
+            entry("public/short.css", b'0', b"body{}"),
+            entry("public/after.css", b'0', b""),
+        ]))
+        .unwrap();
+        assert_eq!(files[0], (long, b"body{}".to_vec()));
+        assert_eq!(files[1].0, "public/after.css");
+    }
+
+    #[test]
+    fn gnu_long_link_names_the_next_entry() {
+        let long = format!("data/{}.json", "n".repeat(150));
+        let files = entries(&tar(&[
+            entry("././@LongLink", b'L', format!("{}\0", long).as_bytes()),
+            entry("data/short.json", b'0', b"{}"),
+        ]))
+        .unwrap();
+        assert_eq!(files, vec![(long, b"{}".to_vec())]);
+    }
+
+    #[test]
+    fn long_names_are_checked_too() {
+        let err = entries(&tar(&[
+            entry("././@LongLink", b'L', b"../../escape\0"),
+            entry("ok", b'0', b""),
+        ]))
+        .unwrap_err();
+        assert!(err.contains("Unsafe path"), "{}", err);
+    }
+
+    #[test]
+    fn bad_checksum_is_refused() {
+        let mut archive = tar(&[entry("bundle.js", b'0', b"x")]);
+        archive[0] = b'B';
+        assert_eq!(entries(&archive).unwrap_err(), "Bad tar header at byte 0");
+    }
+
+    #[test]
+    fn truncated_archive_is_refused() {
+        let archive = entry("bundle.js", b'0', &[b'x'; 1000]);
+        assert_eq!(entries(&archive[..BLOCK + 600]).unwrap_err(), "Archive is truncated");
+    }
+
+    #[test]
+    fn entry_count_is_capped() {
+        let dir = entry("public/", b'5', b"");
+        assert!(entries(&tar(&vec![dir.clone(); MAX_ENTRIES])).is_ok());
+        let err = entries(&tar(&vec![dir; MAX_ENTRIES + 1])).unwrap_err();
+        assert_eq!(err, format!("Archive has over {} entries", MAX_ENTRIES));
+    }
+
+    #[test]
+    fn unpacked_size_is_capped() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        let chunk = vec![0u8; 1024 * 1024];
+        for _ in 0..=MAX_UNPACKED_MB {
+            encoder.write_all(&chunk).unwrap();
+        }
+        let bomb = encoder.finish().unwrap();
+        assert!(bomb.len() < 2 * 1024 * 1024);
+        assert_eq!(to_payload(&bomb).unwrap_err(), format!("Archive is over {} MB unpacked", MAX_UNPACKED_MB));
+    }
+}
@@ -0,0 +1,46 @@
+//! api_keys.rs — API keys for machine callers
+//!
+//! `auth.api_keys` lists keys by name, each with the hex SHA-256 of the key
+//! (never the key itself) and its scopes:
+//! - `"api"`: any method on /apps/<name>/api/*;
+//! - `"api:read"`: GET and HEAD there only;
+//! - `"deploy"`: POST /api/apps/<name>/deploy, to redeploy the app.
+//!
+//! A caller sends the key as `Authorization: Bearer <key>` or
+//! `X-Api-Key: <key>`. The presented key is hashed and compared in constant
+//! time with every configured hash.
+
+use std::collections::HashMap;
+
+use crate::data::ApiKey;
+
+/// The key a request presents, if any
+pub fn presented(headers: &HashMap<String, String>) -> Option<&str> {
+    headers
+        .get("x-api-key")
+        .map(String::as_str)
+        .or_else(|| {
+            let value = headers.get("authorization")?;
+            let (scheme, key) = value.split_once(' ')?;
+            scheme.eq_ignore_ascii_case("bearer").then_some(key)
+        })
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+/// The configured key `key` hashes to
+pub fn find<'a>(keys: &'a [ApiKey], key: &str) -> Option<&'a ApiKey> {
+    let hash = super::store::key(key);
+    keys.iter().find(|k| {
+        let want = k.hash.trim();
+        let want = want.strip_prefix("sha256:").unwrap_or(want).to_ascii_lowercase();
+        crate::csrf::constant_time_eq(want.as_bytes(), hash.as_bytes())
+    })
+}
+
+/// Whether `key` covers `scope`, for a request with `method`
+pub fn allows(key: &ApiKey, scope: &str, method: &str) -> bool {
+    key.scopes.iter().any(|s| {
+        s == scope || (scope == "api" && s == "api:read" && matches!(method, "GET" | "HEAD"))
+    })
+}
@@ -0,0 +1,243 @@
+//! jwks.rs — OIDC id_token verification
+//!
+//! The id_token from a code exchange is checked against the issuer's JSON
+//! Web Key Set (`jwks_uri` in its discovery document):
+//! - signature: RS256/384/512, PS256/384/512, ES256 or ES384 (never `none`
+//!   or HMAC), with the key named by `kid`;
+//! - `iss` is the discovered issuer, `aud` holds the client_id (and `azp`,
+//!   when present, is it);
+//! - `exp` hasn't passed, allowing CLOCK_SKEW;
+//! - `nonce` is the login's.
+//!
+//! The user's roles come from the claim named by the auth config's
+//! `roles_claim` (default `roles`; a dotted path such as
+//! `realm_access.roles` reaches into objects), as an array of strings or a
+//! space-separated string.
+//!
+//! The discovery document and keys are fetched on first use and cached.
+//! Keys are fetched again after KEYS_MAX_AGE, or when a token names a key
+//! the set doesn't have (the issuer rotated), at most once per
+//! REFETCH_AFTER. Fetches happen outside the cache's lock, so a slow
+//! issuer doesn't hold up logins that the cached keys can verify.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Deserialize;
+
+use super::session::Claims;
+
+const KEYS_MAX_AGE: Duration = Duration::from_secs(3600);
+const REFETCH_AFTER: Duration = Duration::from_secs(60);
+/// Leeway for `exp`, in seconds
+const CLOCK_SKEW: u64 = 60;
+
+#[derive(Default)]
+pub struct Verifier {
+    cache: Mutex<Cache>,
+}
+
+#[derive(Default)]
+struct Cache {
+    /// (issuer, jwks_uri) from discovery
+    meta: Option<(String, String)>,
+    keys: Vec<Jwk>,
+    fetched: Option<Instant>,
+}
+
+#[derive(Clone, Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    #[serde(rename = "use")]
+    usage: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    exp: u64,
+    azp: Option<String>,
+    nonce: Option<String>,
+    email: Option<String>,
+    name: Option<String>,
+    #[serde(flatten)]
+    rest: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::One(aud) => aud == client_id,
+            Audience::Many(auds) => auds.iter().any(|a| a == client_id),
+        }
+    }
+}
+
+impl Verifier {
+    /// Verify `id_token` from `issuer` for `client_id`, and return its
+    /// claims, with the roles found under `roles_claim`.
+    pub fn verify(
+        &self,
+        id_token: &str,
+        issuer: &str,
+        client_id: &str,
+        nonce: Option<&str>,
+        roles_claim: &str,
+    ) -> Result<Claims, String> {
+        let mut parts = id_token.split('.');
+        let (Some(header), Some(payload), Some(sig), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err("id_token is not a JWT".into());
+        };
+        let decode = |part: &str| URL_SAFE_NO_PAD.decode(part.trim_end_matches('='));
+        let header: Header = decode(header)
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+            .ok_or("id_token header unreadable")?;
+        let sig = decode(sig).map_err(|_| "id_token signature unreadable")?;
+        let signed = &id_token[..id_token.rfind('.').unwrap_or(0)];
+
+        let (expected_iss, key) = self.key(issuer, header.kid.as_deref(), &header.alg)?;
+        check_signature(&header.alg, &key, signed.as_bytes(), &sig)?;
+
+        let claims: Payload = decode(payload)
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+            .ok_or("id_token claims unreadable")?;
+        if claims.iss != expected_iss {
+            return Err(format!("id_token issuer {} is not {}", claims.iss, expected_iss));
+        }
+        if !claims.aud.contains(client_id) || claims.azp.as_deref().is_some_and(|azp| azp != client_id) {
+            return Err("id_token is for another client".into());
+        }
+        if unix_now() > claims.exp.saturating_add(CLOCK_SKEW) {
+            return Err("id_token expired".into());
+        }
+        if nonce.is_some() && claims.nonce.as_deref() != nonce {
+            return Err("id_token nonce mismatch".into());
+        }
+        let roles = super::session::roles(&claims.rest, roles_claim);
+        Ok(Claims { sub: claims.sub, email: claims.email, name: claims.name, roles })
+    }
+
+    /// The discovered issuer, and the key for `kid` (or the only key that
+    /// fits `alg`), fetching the set again if it doesn't have one
+    fn key(&self, issuer: &str, kid: Option<&str>, alg: &str) -> Result<(String, Jwk), String> {
+        let cached = self.cache.lock().unwrap().meta.clone();
+        let (iss, jwks_uri) = match cached {
+            Some(meta) => meta,
+            None => {
+                let doc = super::oauth2::discovery(issuer)?;
+                let field = |name: &str| doc.get(name).and_then(|v| v.as_str()).map(String::from);
+                let jwks_uri = field("jwks_uri").ok_or("No jwks_uri in OIDC discovery")?;
+                let meta = (field("issuer").unwrap_or_else(|| issuer.to_string()), jwks_uri);
+                self.cache.lock().unwrap().meta.get_or_insert(meta).clone()
+            }
+        };
+        {
+            let cache = self.cache.lock().unwrap();
+            let stale = cache.fetched.is_none_or(|at| at.elapsed() > KEYS_MAX_AGE);
+            let key = find(&cache.keys, kid, alg);
+            let retry = key.is_none() && cache.fetched.is_some_and(|at| at.elapsed() > REFETCH_AFTER);
+            if !stale && !retry {
+                return key.map(|key| (iss, key)).ok_or_else(|| "No JWKS key for the id_token".into());
+            }
+        }
+        let keys = fetch_keys(&jwks_uri)?;
+        let key = find(&keys, kid, alg);
+        let mut cache = self.cache.lock().unwrap();
+        cache.keys = keys;
+        cache.fetched = Some(Instant::now());
+        key.map(|key| (iss, key)).ok_or_else(|| "No JWKS key for the id_token".into())
+    }
+}
+
+fn fetch_keys(uri: &str) -> Result<Vec<Jwk>, String> {
+    #[derive(Deserialize)]
+    struct Set {
+        keys: Vec<Jwk>,
+    }
+    let text = ureq::get(uri)
+        .call()
+        .map_err(|e| format!("JWKS fetch failed: {}", e))?
+        .into_string()
+        .map_err(|e| format!("JWKS read: {}", e))?;
+    let set: Set = serde_json::from_str(&text).map_err(|e| format!("JWKS parse: {}", e))?;
+    Ok(set.keys)
+}
+
+fn find(keys: &[Jwk], kid: Option<&str>, alg: &str) -> Option<Jwk> {
+    let kty = if alg.starts_with("ES") { "EC" } else { "RSA" };
+    let mut fits = keys
+        .iter()
+        .filter(|k| k.kty == kty && k.usage.as_deref().is_none_or(|u| u == "sig"));
+    match kid {
+        Some(kid) => fits.find(|k| k.kid.as_deref() == Some(kid)).cloned(),
+        None => {
+            let first = fits.next()?;
+            fits.next().is_none().then(|| first.clone())
+        }
+    }
+}
+
+fn check_signature(alg: &str, key: &Jwk, message: &[u8], sig: &[u8]) -> Result<(), String> {
+    let b64 = |v: &Option<String>| v.as_deref().and_then(|s| URL_SAFE_NO_PAD.decode(s).ok()).ok_or("JWKS key incomplete");
+    let ok = match alg {
+        "RS256" | "RS384" | "RS512" | "PS256" | "PS384" | "PS512" => {
+            let params: &signature::RsaParameters = match alg {
+                "RS256" => &signature::RSA_PKCS1_2048_8192_SHA256,
+                "RS384" => &signature::RSA_PKCS1_2048_8192_SHA384,
+                "RS512" => &signature::RSA_PKCS1_2048_8192_SHA512,
+                "PS256" => &signature::RSA_PSS_2048_8192_SHA256,
+                "PS384" => &signature::RSA_PSS_2048_8192_SHA384,
+                _ => &signature::RSA_PSS_2048_8192_SHA512,
+            };
+            let key = RsaPublicKeyComponents { n: b64(&key.n)?, e: b64(&key.e)? };
+            key.verify(params, message, sig).is_ok()
+        }
+        "ES256" | "ES384" => {
+            let (params, crv): (&signature::EcdsaVerificationAlgorithm, _) = match alg {
+                "ES256" => (&signature::ECDSA_P256_SHA256_FIXED, "P-256"),
+                _ => (&signature::ECDSA_P384_SHA384_FIXED, "P-384"),
+            };
+            if key.crv.as_deref() != Some(crv) {
+                return Err("JWKS key is on another curve".into());
+            }
+            // Uncompressed point: 0x04 || x || y
+            let mut point = vec![0x04];
+            point.extend(b64(&key.x)?);
+            point.extend(b64(&key.y)?);
+            UnparsedPublicKey::new(params, point).verify(message, sig).is_ok()
+        }
+        other => return Err(format!("id_token algorithm {} not accepted", other)),
+    };
+    if ok { Ok(()) } else { Err("id_token signature invalid".into()) }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
@@ -5,19 +5,97 @@
 //! No username/password — only modern passwordless flows.
 //! Handles login flow, token exchange, session management, and token injection
 //! into data source requests.
+//!
+//! An OAuth2/OIDC login starts at /auth/login with a random CSRF state,
+//! recorded for 10 minutes with a PKCE code_verifier (unless `"pkce":
+//! false`) and, with the `openid` scope, a nonce (session.rs LoginStore). The state also
+//! goes into a signed `<cookie>_login` cookie, tying the login to the
+//! browser that began it. The authorize URL carries the state, the
+//! verifier's S256 code_challenge and the nonce. The callback must return
+//! a state that matches the cookie and is still recorded, once; the
+//! verifier goes with the code exchange. With the `oidc` provider and the
+//! `openid` scope, the id_token that comes back is verified against the
+//! issuer's JWKS (jwks.rs), nonce included, and its claims (sub, email,
+//! name) are kept on the session.
+//!
+//! After an OAuth2/OIDC login the userinfo profile (`userinfo_url`, or the
+//! discovered endpoint for oidc) fills in what the id_token left out, or
+//! gives the claims when there is no id_token. `user()` hands them to
+//! renders as `data.user`.
+//!
+//! With `smtp` configured, magic links are built in (smtp.rs): /auth/send
+//! emails a sealed one-time token, and the callback redeems it for a
+//! session whose claims are the email address. No provider token comes
+//! with it, so none is injected into data sources.
+//!
+//! `protect` rules guard paths: a pattern that is `required` or lists
+//! `roles` needs a session (else the request is sent to /auth/login), and
+//! with roles, one of them among the session's claims (else 403). Roles
+//! come from the verified id_token, or the userinfo profile without one.
+//!
+//! `api_keys` lets machine callers use /api routes and redeploy the app
+//! with a key instead of a session (api_keys.rs).
+//!
+//! Sessions live in memory unless the `session` config names a `store`:
+//! `"sqlite:<path>"` or a `redis://` URL (store.rs).
+//!
+//! A refresh token the provider rotated out goes on the store's revocation
+//! list. If it is presented again, after a concurrent refresh has settled,
+//! it was replayed, and every session of its subject is revoked. POST
+//! /auth/logout-all does the same on purpose: sessions of the subject
+//! created until then, stateless ones included, are refused by `load`.
 
 pub mod session;
 pub mod oauth2;
-
-use crate::data::AuthConfig;
-use session::{SessionStore, SessionData};
+pub mod jwks;
+pub mod store;
+pub mod smtp;
+pub mod throttle;
+pub mod api_keys;
+
+use crate::data::{AuthConfig, ProtectRule};
+use session::{Claims, Login, LoginStore, SessionStore, SessionData};
 use std::collections::HashMap;
+use std::time::Duration;
 
 // ── Auth middleware context ──────────────────────────────────────────
 
 pub struct AuthMiddleware {
     pub config: AuthConfig,
     pub sessions: SessionStore,
+    /// OAuth2/OIDC logins awaiting their callback
+    logins: LoginStore,
+    /// Issuer keys for id_tokens (OIDC)
+    jwks: jwks::Verifier,
+    /// Redeemed built-in magic links
+    magic_links: smtp::MagicLinks,
+    /// OTP failures by method_id and client
+    otp_throttle: throttle::Throttle,
+}
+
+/// Tokens from a login, and the verified claims of its id_token
+pub struct Tokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_in: u64,
+    pub claims: Option<Claims>,
+}
+
+/// What the `protect` rules let a request do. Ordered from least to most
+/// restricted, so combining paths takes the max.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    Allowed,
+    /// Logged in, without a needed role
+    Forbidden,
+    /// Not logged in (or, for API routes, no valid key)
+    Login,
+}
+
+impl From<(String, Option<String>, u64)> for Tokens {
+    fn from((access_token, refresh_token, expires_in): (String, Option<String>, u64)) -> Self {
+        Tokens { access_token, refresh_token, expires_in, claims: None }
+    }
 }
 
 impl AuthMiddleware {
@@ -27,9 +105,29 @@ impl AuthMiddleware {
             .map(|t| parse_ttl(t))
             .unwrap_or(86400); // 24h default
 
+        let store = config.session.as_ref()
+            .and_then(|s| s.store.as_deref())
+            .map(resolve_env);
+        let sessions = match store::open(store.as_deref()) {
+            Ok(backend) => SessionStore::with_backend(ttl_secs, backend),
+            Err(e) => {
+                eprintln!("[auth] session store: {}; keeping sessions in memory", e);
+                SessionStore::new(ttl_secs)
+            }
+        };
+
+        let otp_throttle = throttle::Throttle::new(
+            config.otp_max_attempts.unwrap_or(10),
+            Duration::from_secs(config.otp_lockout.as_deref().map(parse_ttl).unwrap_or(900)),
+        );
+
         Self {
             config,
-            sessions: SessionStore::new(ttl_secs),
+            sessions,
+            logins: LoginStore::default(),
+            jwks: jwks::Verifier::default(),
+            magic_links: smtp::MagicLinks::default(),
+            otp_throttle,
         }
     }
 
@@ -60,6 +158,13 @@ impl AuthMiddleware {
             .unwrap_or("magnetic_session")
     }
 
+    /// Whether the session is sealed into the cookie instead of the store.
+    fn is_stateless(&self) -> bool {
+        self.config.session.as_ref()
+            .and_then(|s| s.stateless)
+            .unwrap_or(false)
+    }
+
     /// Configured token field name in verify responses.
     fn token_field(&self) -> &str {
         self.config.token_field.as_deref().unwrap_or("session_token")
@@ -70,37 +175,95 @@ impl AuthMiddleware {
         self.config.token_expires_in.unwrap_or(3600)
     }
 
-    /// Extract session ID from request cookies.
+    /// Cookie holding a login in progress (OAuth2/OIDC).
+    fn login_cookie_name(&self) -> String {
+        format!("{}_login", self.cookie_name())
+    }
+
+    /// Whether logins get an id_token to verify: OIDC with the openid scope.
+    fn verifies_id_token(&self) -> bool {
+        self.provider() == "oidc" && self.config.scopes.iter().any(|s| s == "openid")
+    }
+
+    /// Claim holding the user's roles (default: "roles").
+    fn roles_claim(&self) -> &str {
+        self.config.roles_claim.as_deref().unwrap_or("roles")
+    }
+
+    /// Whether the code flow sends a PKCE challenge (default: true).
+    fn uses_pkce(&self) -> bool {
+        self.config.pkce.unwrap_or(true)
+    }
+
+    /// Extract session ID from request cookies: the signed ID, or for
+    /// stateless sessions the sealed cookie value itself.
     pub fn session_from_cookies(&self, headers: &HashMap<String, String>) -> Option<String> {
-        let cookie_header = headers.get("cookie")?;
-        let name = self.cookie_name();
-        for part in cookie_header.split(';') {
-            let part = part.trim();
-            if let Some(val) = part.strip_prefix(&format!("{}=", name)) {
-                return Some(val.to_string());
-            }
+        let val = cookie_value(headers, self.cookie_name())?;
+        if self.is_stateless() {
+            return Some(val.to_string());
         }
-        None
+        crate::cookie::verify(val)
     }
 
     /// Get session data for a request (if valid session exists).
     pub fn get_session(&self, headers: &HashMap<String, String>) -> Option<SessionData> {
         let session_id = self.session_from_cookies(headers)?;
-        self.sessions.get(&session_id)
+        self.load(&session_id)
+    }
+
+    /// A stored session, or for stateless sessions the sealed cookie's.
+    /// Tokens refreshed since the cookie was sealed are in the store. None
+    /// once its subject logged out everywhere.
+    fn load(&self, session_id: &str) -> Option<SessionData> {
+        let session = self.sessions.get(session_id).or_else(|| {
+            self.is_stateless()
+                .then(|| session::open(session_id, self.sessions.ttl_secs))
+                .flatten()
+        })?;
+        if self.sessions.is_revoked(&session) {
+            self.sessions.remove(session_id);
+            return None;
+        }
+        Some(session)
     }
 
     /// Get the access token for a valid session, refreshing if needed.
     pub fn get_access_token(&self, headers: &HashMap<String, String>) -> Option<String> {
         let session_id = self.session_from_cookies(headers)?;
-        let session = self.sessions.get(&session_id)?;
+        let session = self.load(&session_id)?;
 
         // Check if token is expired
         if session.is_expired() {
             // Only OAuth2/OIDC supports refresh tokens
             if self.is_oauth() {
                 if let Some(ref refresh_token) = session.refresh_token {
+                    if self.sessions.is_refresh_token_revoked(refresh_token) {
+                        // A concurrent request may have just rotated it
+                        if let Some(current) = self.load(&session_id) {
+                            if current.refresh_token.as_ref() != Some(refresh_token) && !current.is_expired() {
+                                return Some(current.access_token);
+                            }
+                        }
+                        // Replayed: whoever holds it may hold the others too
+                        eprintln!("[auth] rotated-out refresh token replayed; revoking the subject's sessions");
+                        if let Some(claims) = &session.claims {
+                            self.sessions.revoke_subject(&claims.sub);
+                        }
+                        self.sessions.remove(&session_id);
+                        return None;
+                    }
                     match self.refresh_token(refresh_token) {
                         Ok((new_access, new_refresh, expires_in)) => {
+                            // Rotated: the old token must not work again
+                            if new_refresh.as_ref().is_some_and(|new| new != refresh_token) {
+                                self.sessions.revoke_refresh_token(refresh_token);
+                            }
+                            // The cookie can't be resealed from here, so a
+                            // stateless session keeps its refreshed tokens
+                            // in this replica's store
+                            if self.is_stateless() {
+                                self.sessions.insert(&session_id, session.clone());
+                            }
                             self.sessions.update_tokens(
                                 &session_id,
                                 &new_access,
@@ -120,18 +283,59 @@ impl AuthMiddleware {
             return None;
         }
 
-        Some(session.access_token.clone())
+        // Built-in magic links have no provider token
+        Some(session.access_token.clone()).filter(|token| !token.is_empty())
     }
 
     // ── Login URL generation ─────────────────────────────────────────
 
+    /// Start a login (GET /auth/login): the URL to redirect to, and for
+    /// OAuth2/OIDC the Set-Cookie value that ties the login's state to
+    /// this browser until the callback.
+    pub fn begin_login(&self) -> (String, Option<String>) {
+        if !self.is_oauth() {
+            return (self.login_url(&crate::cookie::random_id()), None);
+        }
+        // 64 hex chars, within RFC 7636's 43–128
+        let verifier = self.uses_pkce()
+            .then(|| format!("{}{}", crate::cookie::random_id(), crate::cookie::random_id()));
+        let nonce = self.verifies_id_token().then(crate::cookie::random_id);
+        let challenge = verifier.as_deref().map(oauth2::pkce_challenge);
+        let state = self.logins.begin(verifier, nonce.clone());
+        let url = self.oauth_login_url(&state, challenge.as_deref(), nonce.as_deref());
+        let cookie = format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+            self.login_cookie_name(),
+            crate::cookie::sign(&state),
+            session::LOGIN_TTL.as_secs(),
+        );
+        (url, Some(cookie))
+    }
+
+    /// Check an OAuth2/OIDC callback's `state`: it must match the login
+    /// cookie and a login still waiting for it, which it then ends.
+    pub fn finish_login(&self, headers: &HashMap<String, String>, state: &str) -> Result<Login, String> {
+        let cookie_state = cookie_value(headers, &self.login_cookie_name())
+            .and_then(crate::cookie::verify)
+            .ok_or("No login in progress (login cookie missing or expired)")?;
+        if state.is_empty() || cookie_state != state {
+            return Err("Login state mismatch".into());
+        }
+        self.logins.take(state).ok_or_else(|| "Login expired or already used".into())
+    }
+
+    /// Set-Cookie value that clears the login cookie.
+    pub fn clear_login_cookie(&self) -> String {
+        format!("{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0", self.login_cookie_name())
+    }
+
     /// Build the login URL. Behavior depends on provider type:
     /// - oauth2/oidc: redirect to authorization endpoint
     /// - magic-link: redirect to a custom login page (app provides UI)
     /// - otp: redirect to a custom login page (app provides UI)
-    pub fn login_url(&self, state: &str) -> String {
+    fn login_url(&self, state: &str) -> String {
         match self.provider() {
-            "oidc" | "oauth2" => self.oauth_login_url(state),
+            "oidc" | "oauth2" => self.oauth_login_url(state, None, None),
             // For magic-link and OTP, the developer provides a login page
             // that collects the email and POSTs to /auth/send
             _ => {
@@ -142,7 +346,7 @@ impl AuthMiddleware {
         }
     }
 
-    fn oauth_login_url(&self, state: &str) -> String {
+    fn oauth_login_url(&self, state: &str, code_challenge: Option<&str>, nonce: Option<&str>) -> String {
         let issuer = self.config.issuer.as_deref().unwrap_or("");
         let client_id = resolve_env(self.config.client_id.as_deref().unwrap_or(""));
         let redirect_uri = self.config.redirect_uri.as_deref().unwrap_or("/auth/callback");
@@ -156,27 +360,183 @@ impl AuthMiddleware {
             format!("{}/authorize", issuer)
         };
 
-        format!(
+        let mut url = format!(
             "{}?response_type=code&client_id={}&redirect_uri={}&scope={}&state={}",
             auth_endpoint,
             urlencoding(&client_id),
             urlencoding(redirect_uri),
             urlencoding(&scopes),
             urlencoding(state),
-        )
+        );
+        if let Some(challenge) = code_challenge {
+            url.push_str(&format!("&code_challenge={}&code_challenge_method=S256", challenge));
+        }
+        if let Some(nonce) = nonce {
+            url.push_str(&format!("&nonce={}", urlencoding(nonce)));
+        }
+        url
+    }
+
+    /// The signed-in user for render data: the session's claims (`{}` when
+    /// the provider gave none), or None when not signed in.
+    pub fn user(&self, headers: &HashMap<String, String>) -> Option<serde_json::Value> {
+        let session = self.get_session(headers)?;
+        Some(match session.claims {
+            Some(claims) => serde_json::to_value(claims).unwrap_or_default(),
+            None => serde_json::json!({}),
+        })
+    }
+
+    // ── Route rules ──────────────────────────────────────────────────
+
+    /// The `protect` rule for `path`: the longest matching pattern's. The
+    /// path is matched as the router sees it, so `/admin/` and `//admin`
+    /// fall under `/admin`.
+    fn protect_rule(&self, path: &str) -> Option<&ProtectRule> {
+        let path = route_path(path);
+        self.config.protect.iter()
+            .filter(|(pattern, _)| crate::redirects::matches(pattern, &path))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, rule)| rule)
+    }
+
+    /// Whether the request may reach `path`.
+    pub fn access(&self, headers: &HashMap<String, String>, path: &str) -> Access {
+        let Some(rule) = self.protect_rule(path) else { return Access::Allowed };
+        if !rule.required && rule.roles.is_empty() {
+            return Access::Allowed;
+        }
+        let Some(session) = self.get_session(headers) else { return Access::Login };
+        let roles = session.claims.as_ref().map(|c| c.roles.as_slice()).unwrap_or_default();
+        if rule.roles.is_empty() || rule.roles.iter().any(|role| roles.contains(role)) {
+            Access::Allowed
+        } else {
+            Access::Forbidden
+        }
+    }
+
+    /// Whether the request may call the app's /api routes with `method`.
+    /// Without `api_keys` they stay open; with them a caller needs a key
+    /// with the `api` scope (`api:read` for GET and HEAD), or a session.
+    pub fn api_access(&self, headers: &HashMap<String, String>, method: &str) -> Access {
+        if self.config.api_keys.is_empty() {
+            return Access::Allowed;
+        }
+        match api_keys::presented(headers) {
+            Some(key) => self.key_access(key, "api", method),
+            None if self.get_session(headers).is_some() => Access::Allowed,
+            None => Access::Login,
+        }
+    }
+
+    /// Whether the request may redeploy the app: without `api_keys`
+    /// anyone, as before; with them, a key with the `deploy` scope.
+    pub fn deploy_access(&self, headers: &HashMap<String, String>) -> Access {
+        if self.config.api_keys.is_empty() {
+            return Access::Allowed;
+        }
+        match api_keys::presented(headers) {
+            Some(key) => self.key_access(key, "deploy", "POST"),
+            None => Access::Login,
+        }
+    }
+
+    fn key_access(&self, key: &str, scope: &str, method: &str) -> Access {
+        match api_keys::find(&self.config.api_keys, key) {
+            Some(found) if api_keys::allows(found, scope, method) => Access::Allowed,
+            Some(found) => {
+                eprintln!("[auth] api key '{}' lacks the {} scope for {}", found.name, scope, method);
+                Access::Forbidden
+            }
+            None => {
+                eprintln!("[auth] unknown api key refused");
+                Access::Login
+            }
+        }
     }
 
     // ── Token exchange (multi-provider) ──────────────────────────────
 
+    /// Complete `claims` from the userinfo profile: `userinfo_url`, or for
+    /// oidc the discovered endpoint. The id_token's claims win, and a
+    /// profile for another `sub` is ignored. A failed fetch is logged and
+    /// leaves the claims as they are.
+    fn with_userinfo(&self, claims: Option<Claims>, access_token: &str) -> Option<Claims> {
+        if claims.as_ref().is_some_and(|c| c.email.is_some() && c.name.is_some()) {
+            return claims;
+        }
+        let url = match (&self.config.userinfo_url, self.provider()) {
+            (Some(url), _) => resolve_env(url),
+            (None, "oidc") => {
+                let issuer = self.config.issuer.as_deref().unwrap_or("");
+                match oauth2::discover_userinfo_endpoint(issuer) {
+                    Ok(url) => url,
+                    Err(e) => {
+                        eprintln!("[auth] userinfo: {}", e);
+                        return claims;
+                    }
+                }
+            }
+            _ => return claims,
+        };
+        let profile = match oauth2::userinfo(&url, access_token) {
+            Ok(profile) => profile,
+            Err(e) => {
+                eprintln!("[auth] {}", e);
+                return claims;
+            }
+        };
+        let found = Claims::from_profile(&profile, self.roles_claim());
+        match (claims, found) {
+            (None, found) => found,
+            (Some(mut claims), Some(found)) if found.sub == claims.sub => {
+                claims.email = claims.email.or(found.email);
+                claims.name = claims.name.or(found.name);
+                Some(claims)
+            }
+            (claims, _) => {
+                eprintln!("[auth] userinfo profile has another or no sub; ignored");
+                claims
+            }
+        }
+    }
+
     /// Exchange code/token for session. Branches by provider type.
-    pub fn exchange_code(&self, code: &str) -> Result<(String, Option<String>, u64), String> {
+    /// `login` is the OAuth2/OIDC login the code came back to.
+    pub fn exchange_code(&self, code: &str, login: Option<&Login>) -> Result<Tokens, String> {
         match self.provider() {
             "oidc" | "oauth2" => {
                 let issuer = self.config.issuer.as_deref().unwrap_or("");
                 let client_id = resolve_env(self.config.client_id.as_deref().unwrap_or(""));
                 let client_secret = resolve_env(self.config.client_secret.as_deref().unwrap_or(""));
                 let redirect_uri = self.config.redirect_uri.as_deref().unwrap_or("/auth/callback");
-                oauth2::exchange_code(issuer, &self.config.provider, &client_id, &client_secret, redirect_uri, code)
+                let tokens = oauth2::exchange_code(
+                    issuer, &self.config.provider, &client_id, &client_secret, redirect_uri, code,
+                    login.and_then(|l| l.verifier.as_deref()),
+                )?;
+                let claims = if self.verifies_id_token() {
+                    let id_token = tokens.id_token.as_deref().ok_or("No id_token in token response")?;
+                    let nonce = login.and_then(|l| l.nonce.as_deref());
+                    Some(self.jwks.verify(id_token, issuer, &client_id, nonce, self.roles_claim())?)
+                } else {
+                    None
+                };
+                let claims = self.with_userinfo(claims, &tokens.access_token);
+                Ok(Tokens {
+                    access_token: tokens.access_token,
+                    refresh_token: tokens.refresh_token,
+                    expires_in: tokens.expires_in,
+                    claims,
+                })
+            }
+            "magic-link" if self.config.smtp.is_some() => {
+                let email = self.magic_links.redeem(code)?;
+                Ok(Tokens {
+                    access_token: String::new(),
+                    refresh_token: None,
+                    expires_in: self.sessions.ttl_secs,
+                    claims: Some(Claims { sub: email.clone(), email: Some(email), name: None, roles: Vec::new() }),
+                })
             }
             "magic-link" => {
                 // For magic-link, "code" is actually the token from the callback URL
@@ -186,7 +546,7 @@ impl AuthMiddleware {
                 }
                 oauth2::verify_magic_link_token(
                     &verify_url, code, self.token_field(), self.default_expires(),
-                )
+                ).map(Tokens::from)
             }
             "otp" => {
                 Err("OTP provider uses /auth/verify endpoint, not /auth/callback".into())
@@ -200,6 +560,16 @@ impl AuthMiddleware {
     /// Send a magic-link or OTP to the given email address.
     /// Called from POST /auth/send with JSON body { "email": "..." }
     pub fn send_auth_email(&self, email: &str) -> Result<serde_json::Value, String> {
+        if let (Some(smtp), true) = (&self.config.smtp, self.is_magic_link()) {
+            let redirect_uri = self.config.redirect_uri.as_deref().unwrap_or("");
+            if !redirect_uri.starts_with("https://") && !redirect_uri.starts_with("http://") {
+                return Err("built-in magic links need an absolute redirect_uri".into());
+            }
+            let sep = if redirect_uri.contains('?') { '&' } else { '?' };
+            let link = format!("{}{}token={}", redirect_uri, sep, self.magic_links.issue(email));
+            smtp::send(smtp, &resolve_env(&smtp.url), email, &link)?;
+            return Ok(serde_json::json!({ "sent": true }));
+        }
         let login_url = resolve_env(self.config.login_url.as_deref().unwrap_or(""));
         if login_url.is_empty() {
             return Err(format!("{} provider requires login_url", self.provider()));
@@ -210,11 +580,37 @@ impl AuthMiddleware {
         oauth2::send_otp(&login_url, email, &client_id, &client_secret)
     }
 
+    /// Start a guess by `client` at `method_id` (throttle.rs), or how
+    /// long it must wait first. Every started guess goes on to
+    /// `verify_otp_code`.
+    pub fn otp_begin(&self, method_id: &str, client: &str) -> Result<(), Duration> {
+        self.otp_throttle.begin(method_id, client)
+    }
+
     /// Verify an OTP code. Called from POST /auth/verify with JSON body { "code": "...", "method_id": "..." }
-    pub fn verify_otp_code(&self, code: &str, method_id: &str) -> Result<(String, Option<String>, u64), String> {
+    /// from `client`, after `otp_begin`. Only a code the provider turned
+    /// down counts as a failure.
+    pub fn verify_otp_code(&self, code: &str, method_id: &str, client: &str) -> Result<(String, Option<String>, u64), String> {
+        match self.check_otp_code(code, method_id) {
+            Ok(tokens) => {
+                self.otp_throttle.succeeded(method_id, client);
+                Ok(tokens)
+            }
+            Err(oauth2::OtpError::Rejected(e)) => {
+                self.otp_throttle.failed(method_id, client);
+                Err(e)
+            }
+            Err(e) => {
+                self.otp_throttle.cancelled(method_id, client);
+                Err(e.message())
+            }
+        }
+    }
+
+    fn check_otp_code(&self, code: &str, method_id: &str) -> Result<(String, Option<String>, u64), oauth2::OtpError> {
         let verify_url = resolve_env(self.config.verify_url.as_deref().unwrap_or(""));
         if verify_url.is_empty() {
-            return Err("OTP provider requires verify_url".into());
+            return Err(oauth2::OtpError::Unavailable("OTP provider requires verify_url".into()));
         }
         let client_id = resolve_env(self.config.client_id.as_deref().unwrap_or(""));
         let client_secret = resolve_env(self.config.client_secret.as_deref().unwrap_or(""));
@@ -238,23 +634,38 @@ impl AuthMiddleware {
     // ── Session management ───────────────────────────────────────────
 
     /// Create a new session after successful login.
-    pub fn create_session(
-        &self,
-        access_token: &str,
-        refresh_token: Option<&str>,
-        expires_in: u64,
-    ) -> (String, String) {
-        let session_id = self.sessions.create(access_token, refresh_token, expires_in);
+    pub fn create_session(&self, tokens: &Tokens) -> (String, String) {
+        let (access_token, refresh_token) = (&tokens.access_token, tokens.refresh_token.as_deref());
+        let (session_id, value) = if self.is_stateless() {
+            let sealed = session::seal(access_token, refresh_token, tokens.expires_in, tokens.claims.clone());
+            (sealed.clone(), sealed)
+        } else {
+            let id = self.sessions.create(access_token, refresh_token, tokens.expires_in, tokens.claims.clone());
+            let signed = crate::cookie::sign(&id);
+            (id, signed)
+        };
         let cookie = format!(
             "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
             self.cookie_name(),
-            session_id,
+            value,
             self.sessions.ttl_secs,
         );
         (session_id, cookie)
     }
 
-    /// Remove session (logout).
+    /// Revoke every session of the request's subject (POST
+    /// /auth/logout-all), stateless ones included, and return the cookie
+    /// clearing this one. Err without a session, or one without claims.
+    pub fn logout_all(&self, headers: &HashMap<String, String>) -> Result<String, String> {
+        let session = self.get_session(headers).ok_or("Not logged in")?;
+        let claims = session.claims.ok_or("This session has no subject to log out")?;
+        self.sessions.revoke_subject(&claims.sub);
+        eprintln!("[auth] logout-all: sessions of sub={} revoked", claims.sub);
+        Ok(self.logout(headers))
+    }
+
+    /// Remove session (logout). A stateless session can't be revoked; its
+    /// cookie is only cleared on this client.
     pub fn logout(&self, headers: &HashMap<String, String>) -> String {
         if let Some(session_id) = self.session_from_cookies(headers) {
             self.sessions.remove(&session_id);
@@ -268,6 +679,19 @@ impl AuthMiddleware {
 
 // ── Helpers ─────────────────────────────────────────────────────────
 
+/// `path` with repeated `/` collapsed and no trailing `/` (but `/` itself).
+fn route_path(path: &str) -> String {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    format!("/{}", segments.join("/"))
+}
+
+/// A cookie's value from the request's Cookie header.
+fn cookie_value<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers.get("cookie")?
+        .split(';')
+        .find_map(|part| part.trim().strip_prefix(name)?.strip_prefix('='))
+}
+
 fn parse_ttl(s: &str) -> u64 {
     let s = s.trim();
     if s.ends_with('h') {
@@ -304,3 +728,85 @@ fn urlencoding(s: &str) -> String {
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn middleware() -> AuthMiddleware {
+        let config: AuthConfig = serde_json::from_value(serde_json::json!({
+            "provider": "otp",
+            "protect": {
+                "/admin": { "roles": ["admin"] },
+                "/admin/*": { "roles": ["admin"] },
+                "/account/*": { "required": true },
+                "/account/public": {},
+            },
+        }))
+        .unwrap();
+        AuthMiddleware::new(config)
+    }
+
+    /// Request headers carrying a session with `roles`
+    fn signed_in(auth: &AuthMiddleware, roles: &[&str]) -> HashMap<String, String> {
+        crate::cookie::install(&["--session-secret".to_string(), "test".to_string()]);
+        let mut tokens = Tokens::from(("token".to_string(), None, 3600));
+        tokens.claims = Some(Claims {
+            sub: "user-1".to_string(),
+            email: None,
+            name: None,
+            roles: roles.iter().map(|r| r.to_string()).collect(),
+        });
+        let (_, cookie) = auth.create_session(&tokens);
+        let value = cookie.split(';').next().unwrap().to_string();
+        HashMap::from([("cookie".to_string(), value)])
+    }
+
+    #[test]
+    fn unprotected_paths_are_open() {
+        let auth = middleware();
+        let none = HashMap::new();
+        assert_eq!(auth.access(&none, "/"), Access::Allowed);
+        assert_eq!(auth.access(&none, "/about"), Access::Allowed);
+        assert_eq!(auth.access(&none, "/administrator"), Access::Allowed);
+    }
+
+    #[test]
+    fn required_needs_a_session() {
+        let auth = middleware();
+        assert_eq!(auth.access(&HashMap::new(), "/account/settings"), Access::Login);
+        assert_eq!(auth.access(&signed_in(&auth, &[]), "/account/settings"), Access::Allowed);
+    }
+
+    #[test]
+    fn longest_pattern_wins() {
+        let auth = middleware();
+        assert_eq!(auth.access(&HashMap::new(), "/account/public"), Access::Allowed);
+    }
+
+    #[test]
+    fn roles_need_one_of_them() {
+        let auth = middleware();
+        assert_eq!(auth.access(&HashMap::new(), "/admin"), Access::Login);
+        assert_eq!(auth.access(&signed_in(&auth, &["editor"]), "/admin/users"), Access::Forbidden);
+        assert_eq!(auth.access(&signed_in(&auth, &["editor", "admin"]), "/admin/users"), Access::Allowed);
+    }
+
+    #[test]
+    fn slashes_do_not_get_around_a_rule() {
+        let auth = middleware();
+        let none = HashMap::new();
+        for path in ["/admin/", "//admin", "/admin//", "//admin//users", "/admin//users/"] {
+            assert_eq!(auth.access(&none, path), Access::Login, "{}", path);
+        }
+        assert_eq!(auth.access(&none, "/account//public/"), Access::Allowed);
+    }
+
+    #[test]
+    fn route_path_is_the_routers() {
+        assert_eq!(route_path("/"), "/");
+        assert_eq!(route_path(""), "/");
+        assert_eq!(route_path("//"), "/");
+        assert_eq!(route_path("/a//b/"), "/a/b");
+    }
+}
@@ -2,20 +2,30 @@
 //!
 //! Handles:
 //! - OIDC discovery (.well-known/openid-configuration)
-//! - Authorization code → token exchange
+//! - Authorization code → token exchange, with a PKCE code_verifier
+//!   (RFC 7636, S256) when the login made one; the id_token is verified
+//!   in jwks.rs
 //! - Token refresh
+//! - The userinfo profile, fetched once after login
+//!
+//! An empty client_secret is left out of token requests, for public
+//! clients that rely on PKCE alone.
 
-/// Discover the authorization endpoint from OIDC .well-known configuration.
-pub fn discover_auth_endpoint(issuer: &str) -> Result<String, String> {
+/// Fetch the issuer's OIDC .well-known configuration.
+pub fn discovery(issuer: &str) -> Result<serde_json::Value, String> {
     let url = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
     let resp = ureq::get(&url)
         .call()
         .map_err(|e| format!("OIDC discovery failed: {}", e))?;
     let text = resp.into_string()
         .map_err(|e| format!("OIDC discovery read: {}", e))?;
-    let body: serde_json::Value = serde_json::from_str(&text)
-        .map_err(|e| format!("OIDC discovery parse: {}", e))?;
-    body.get("authorization_endpoint")
+    serde_json::from_str(&text)
+        .map_err(|e| format!("OIDC discovery parse: {}", e))
+}
+
+/// Discover the authorization endpoint from OIDC .well-known configuration.
+pub fn discover_auth_endpoint(issuer: &str) -> Result<String, String> {
+    discovery(issuer)?.get("authorization_endpoint")
         .and_then(|v: &serde_json::Value| v.as_str())
         .map(|s: &str| s.to_string())
         .ok_or_else(|| "No authorization_endpoint in OIDC discovery".into())
@@ -23,22 +33,53 @@ pub fn discover_auth_endpoint(issuer: &str) -> Result<String, String> {
 
 /// Discover the token endpoint from OIDC .well-known configuration.
 fn discover_token_endpoint(issuer: &str) -> Result<String, String> {
-    let url = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
-    let resp = ureq::get(&url)
-        .call()
-        .map_err(|e| format!("OIDC discovery failed: {}", e))?;
-    let text = resp.into_string()
-        .map_err(|e| format!("OIDC discovery read: {}", e))?;
-    let body: serde_json::Value = serde_json::from_str(&text)
-        .map_err(|e| format!("OIDC discovery parse: {}", e))?;
-    body.get("token_endpoint")
+    discovery(issuer)?.get("token_endpoint")
         .and_then(|v: &serde_json::Value| v.as_str())
         .map(|s: &str| s.to_string())
         .ok_or_else(|| "No token_endpoint in OIDC discovery".into())
 }
 
-/// Exchange an authorization code for access + refresh tokens.
-/// Returns (access_token, refresh_token, expires_in_secs).
+/// Discover the userinfo endpoint from OIDC .well-known configuration.
+pub fn discover_userinfo_endpoint(issuer: &str) -> Result<String, String> {
+    discovery(issuer)?.get("userinfo_endpoint")
+        .and_then(|v: &serde_json::Value| v.as_str())
+        .map(|s: &str| s.to_string())
+        .ok_or_else(|| "No userinfo_endpoint in OIDC discovery".into())
+}
+
+/// Fetch the user's profile from a userinfo endpoint with their access
+/// token.
+pub fn userinfo(url: &str, access_token: &str) -> Result<serde_json::Value, String> {
+    let resp = ureq::get(url)
+        .set("Authorization", &format!("Bearer {}", access_token))
+        .set("Accept", "application/json")
+        .call()
+        .map_err(|e| format!("userinfo failed: {}", e))?;
+    let text = resp.into_string()
+        .map_err(|e| format!("userinfo read: {}", e))?;
+    serde_json::from_str(&text)
+        .map_err(|e| format!("userinfo parse: {}", e))
+}
+
+/// A token endpoint's answer to a code exchange
+pub struct CodeTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_in: u64,
+    /// OIDC id_token, unverified
+    pub id_token: Option<String>,
+}
+
+/// The S256 code_challenge for a PKCE code_verifier:
+/// BASE64URL(SHA256(verifier)), unpadded.
+pub fn pkce_challenge(verifier: &str) -> String {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    URL_SAFE_NO_PAD.encode(ring::digest::digest(&ring::digest::SHA256, verifier.as_bytes()))
+}
+
+/// Exchange an authorization code for access + refresh tokens (and an
+/// id_token, for the openid scope).
 pub fn exchange_code(
     issuer: &str,
     provider: &str,
@@ -46,20 +87,26 @@ pub fn exchange_code(
     client_secret: &str,
     redirect_uri: &str,
     code: &str,
-) -> Result<(String, Option<String>, u64), String> {
+    code_verifier: Option<&str>,
+) -> Result<CodeTokens, String> {
     let token_url = if provider == "oidc" {
         discover_token_endpoint(issuer)?
     } else {
         format!("{}/token", issuer.trim_end_matches('/'))
     };
 
-    let body = format!(
-        "grant_type=authorization_code&code={}&redirect_uri={}&client_id={}&client_secret={}",
+    let mut body = format!(
+        "grant_type=authorization_code&code={}&redirect_uri={}&client_id={}",
         urlencoding(code),
         urlencoding(redirect_uri),
         urlencoding(client_id),
-        urlencoding(client_secret),
     );
+    if !client_secret.is_empty() {
+        body.push_str(&format!("&client_secret={}", urlencoding(client_secret)));
+    }
+    if let Some(verifier) = code_verifier {
+        body.push_str(&format!("&code_verifier={}", urlencoding(verifier)));
+    }
 
     eprintln!("[auth] exchanging code at {}", token_url);
 
@@ -86,8 +133,12 @@ pub fn exchange_code(
         .and_then(|v: &serde_json::Value| v.as_u64())
         .unwrap_or(3600);
 
+    let id_token = json.get("id_token")
+        .and_then(|v: &serde_json::Value| v.as_str())
+        .map(|s: &str| s.to_string());
+
     eprintln!("[auth] token exchange successful (expires_in={}s)", expires_in);
-    Ok((access_token, refresh_token, expires_in))
+    Ok(CodeTokens { access_token, refresh_token, expires_in, id_token })
 }
 
 /// Refresh an access token using a refresh token.
@@ -105,12 +156,14 @@ pub fn refresh_token(
         format!("{}/token", issuer.trim_end_matches('/'))
     };
 
-    let body = format!(
-        "grant_type=refresh_token&refresh_token={}&client_id={}&client_secret={}",
+    let mut body = format!(
+        "grant_type=refresh_token&refresh_token={}&client_id={}",
         urlencoding(refresh_token),
         urlencoding(client_id),
-        urlencoding(client_secret),
     );
+    if !client_secret.is_empty() {
+        body.push_str(&format!("&client_secret={}", urlencoding(client_secret)));
+    }
 
     eprintln!("[auth] refreshing token at {}", token_url);
 
@@ -214,6 +267,23 @@ pub fn send_otp(
 
 /// Verify an OTP code via the provider's verify API.
 /// Returns (session_token, None, expires_in).
+/// Why an OTP wasn't verified
+pub enum OtpError {
+    /// The provider turned the code down
+    Rejected(String),
+    /// No verdict on the code: the provider couldn't be reached, was
+    /// overloaded or answered with something unusable
+    Unavailable(String),
+}
+
+impl OtpError {
+    pub fn message(self) -> String {
+        match self {
+            OtpError::Rejected(e) | OtpError::Unavailable(e) => e,
+        }
+    }
+}
+
 pub fn verify_otp(
     verify_url: &str,
     code: &str,
@@ -222,7 +292,7 @@ pub fn verify_otp(
     client_secret: &str,
     token_field: &str,
     default_expires: u64,
-) -> Result<(String, Option<String>, u64), String> {
+) -> Result<(String, Option<String>, u64), OtpError> {
     eprintln!("[auth] verifying OTP at {}", verify_url);
 
     let body = serde_json::json!({
@@ -234,19 +304,25 @@ pub fn verify_otp(
         .set("Content-Type", "application/json")
         .set("Authorization", &format!("Basic {}", base64_encode(&format!("{}:{}", client_id, client_secret))))
         .send_string(&body.to_string())
-        .map_err(|e| format!("OTP verify failed: {}", e))?;
+        .map_err(|e| match e {
+            // A 4xx is the provider's answer on the code, except "slow down"
+            ureq::Error::Status(status, _) if (400..500).contains(&status) && status != 429 => {
+                OtpError::Rejected(format!("OTP verify failed: {}", e))
+            }
+            e => OtpError::Unavailable(format!("OTP verify failed: {}", e)),
+        })?;
 
     let text = resp.into_string()
-        .map_err(|e| format!("OTP verify read: {}", e))?;
+        .map_err(|e| OtpError::Unavailable(format!("OTP verify read: {}", e)))?;
     let json: serde_json::Value = serde_json::from_str(&text)
-        .map_err(|e| format!("OTP verify parse: {}", e))?;
+        .map_err(|e| OtpError::Unavailable(format!("OTP verify parse: {}", e)))?;
 
     let session_token = json.get(token_field)
         .or_else(|| json.get("session_token"))
         .or_else(|| json.get("access_token"))
         .or_else(|| json.get("token"))
         .and_then(|v: &serde_json::Value| v.as_str())
-        .ok_or_else(|| format!("No '{}' in OTP verify response", token_field))?
+        .ok_or_else(|| OtpError::Unavailable(format!("No '{}' in OTP verify response", token_field)))?
         .to_string();
 
     let expires_in = json.get("expires_in")
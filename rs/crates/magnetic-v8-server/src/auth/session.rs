@@ -1,16 +1,31 @@
 //! session.rs — Server-side session store
 //!
-//! Sessions are stored in-memory (HashMap). Each session holds:
+//! Sessions are kept by a backend (store.rs): in memory by default, or in
+//! SQLite or Redis so they outlive a restart and are shared between
+//! processes. Each session holds:
 //! - Access token (for injecting into data source requests)
 //! - Refresh token (for automatic token refresh)
 //! - Expiry time
+//! - The user's verified id_token claims (OIDC, jwks.rs)
 //!
 //! Sessions are identified by an opaque random ID (never contains tokens).
+//!
+//! Stateless sessions (`session.stateless = true`) skip the store: the
+//! session itself is sealed into the cookie (cookie.rs), so any replica
+//! holding the secret can read it.
+//!
+//! An OAuth2/OIDC login in progress is kept in memory under its CSRF state
+//! (LoginStore), with its PKCE code_verifier and OIDC nonce, from
+//! /auth/login until the callback.
 
 use std::collections::HashMap;
 use std::sync::Mutex;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use serde::{Deserialize, Serialize};
+
+use super::store::{self, SessionBackend};
+
 // ── Session data ────────────────────────────────────────────────────
 
 #[derive(Clone, Debug)]
@@ -19,9 +34,65 @@ pub struct SessionData {
     pub refresh_token: Option<String>,
     pub token_expires_at: Instant,
     pub created_at: Instant,
+    /// Who logged in, from a verified id_token (None without one)
+    pub claims: Option<Claims>,
+}
+
+/// Verified id_token claims
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Claims {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+}
+
+impl Claims {
+    /// Claims from a userinfo profile: `sub` (or a provider's `id`), `email`,
+    /// `name` (or `preferred_username`, `login`) and the roles under
+    /// `roles_claim`. None without a subject.
+    pub fn from_profile(profile: &serde_json::Value, roles_claim: &str) -> Option<Claims> {
+        let field = |name: &str| profile.get(name).and_then(|v| v.as_str()).map(String::from);
+        let sub = match profile.get("sub").or_else(|| profile.get("id"))? {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Number(n) => n.to_string(),
+            _ => return None,
+        };
+        Some(Claims {
+            sub,
+            email: field("email"),
+            name: field("name").or_else(|| field("preferred_username")).or_else(|| field("login")),
+            roles: profile.as_object().map(|p| roles(p, roles_claim)).unwrap_or_default(),
+        })
+    }
+}
+
+/// The roles under a dotted claim path: an array of strings, or a
+/// space-separated string
+pub fn roles(claims: &serde_json::Map<String, serde_json::Value>, path: &str) -> Vec<String> {
+    let mut parts = path.split('.');
+    let first = parts.next().and_then(|name| claims.get(name));
+    match parts.try_fold(first, |value, name| Some(value?.get(name))).flatten() {
+        Some(serde_json::Value::Array(roles)) => roles.iter().filter_map(|r| r.as_str().map(String::from)).collect(),
+        Some(serde_json::Value::String(roles)) => roles.split_whitespace().map(String::from).collect(),
+        _ => Vec::new(),
+    }
 }
 
 impl SessionData {
+    pub fn new(access_token: &str, refresh_token: Option<&str>, expires_in_secs: u64, claims: Option<Claims>) -> Self {
+        SessionData {
+            access_token: access_token.to_string(),
+            refresh_token: refresh_token.map(|s| s.to_string()),
+            token_expires_at: Instant::now() + Duration::from_secs(expires_in_secs),
+            created_at: Instant::now(),
+            claims,
+        }
+    }
+
     pub fn is_expired(&self) -> bool {
         Instant::now() >= self.token_expires_at
     }
@@ -29,48 +100,67 @@ impl SessionData {
 
 // ── Session store ───────────────────────────────────────────────────
 
+/// Prune the backend at most this often, on session creation
+const PRUNE_EVERY: Duration = Duration::from_secs(600);
+
 pub struct SessionStore {
-    sessions: Mutex<HashMap<String, SessionData>>,
+    backend: Box<dyn SessionBackend>,
     pub ttl_secs: u64,
+    last_prune: Mutex<Instant>,
 }
 
 impl SessionStore {
+    /// An in-memory store.
     pub fn new(ttl_secs: u64) -> Self {
+        Self::with_backend(ttl_secs, Box::new(store::Memory::default()))
+    }
+
+    pub fn with_backend(ttl_secs: u64, backend: Box<dyn SessionBackend>) -> Self {
         Self {
-            sessions: Mutex::new(HashMap::new()),
+            backend,
             ttl_secs,
+            last_prune: Mutex::new(Instant::now()),
         }
     }
 
+    fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_secs)
+    }
+
     /// Create a new session, return the session ID.
     pub fn create(
         &self,
         access_token: &str,
         refresh_token: Option<&str>,
         expires_in_secs: u64,
+        claims: Option<Claims>,
     ) -> String {
-        let session_id = generate_session_id();
-        let data = SessionData {
-            access_token: access_token.to_string(),
-            refresh_token: refresh_token.map(|s| s.to_string()),
-            token_expires_at: Instant::now() + Duration::from_secs(expires_in_secs),
-            created_at: Instant::now(),
-        };
-        self.sessions.lock().unwrap().insert(session_id.clone(), data);
+        let session_id = crate::cookie::random_id();
+        let data = SessionData::new(access_token, refresh_token, expires_in_secs, claims);
+        self.insert(&session_id, data);
+        let mut last_prune = self.last_prune.lock().unwrap();
+        if last_prune.elapsed() > PRUNE_EVERY {
+            *last_prune = Instant::now();
+            drop(last_prune);
+            self.prune();
+        }
         session_id
     }
 
     /// Get session data by ID. Returns None if not found or session TTL expired.
     pub fn get(&self, session_id: &str) -> Option<SessionData> {
-        let sessions = self.sessions.lock().unwrap();
-        let data = sessions.get(session_id)?;
+        let data = logged(self.backend.get(session_id))?;
         // Check session-level TTL (not token expiry — that's handled by refresh)
-        if data.created_at.elapsed() > Duration::from_secs(self.ttl_secs) {
-            drop(sessions);
+        if data.created_at.elapsed() > self.ttl() {
             self.remove(session_id);
             return None;
         }
-        Some(data.clone())
+        Some(data)
+    }
+
+    /// Store a session under an existing ID.
+    pub fn insert(&self, session_id: &str, data: SessionData) {
+        logged(self.backend.put(session_id, &data, self.ttl()));
     }
 
     /// Update tokens for an existing session (after refresh).
@@ -81,66 +171,174 @@ impl SessionStore {
         refresh_token: Option<&str>,
         expires_in_secs: u64,
     ) {
-        let mut sessions = self.sessions.lock().unwrap();
-        if let Some(data) = sessions.get_mut(session_id) {
+        if let Some(mut data) = logged(self.backend.get(session_id)) {
             data.access_token = access_token.to_string();
             if let Some(rt) = refresh_token {
                 data.refresh_token = Some(rt.to_string());
             }
             data.token_expires_at = Instant::now() + Duration::from_secs(expires_in_secs);
+            self.insert(session_id, data);
         }
     }
 
     /// Remove a session (logout or expired).
     pub fn remove(&self, session_id: &str) {
-        self.sessions.lock().unwrap().remove(session_id);
+        logged(self.backend.remove(session_id));
     }
 
     /// Count active sessions.
     pub fn count(&self) -> usize {
-        self.sessions.lock().unwrap().len()
+        logged(self.backend.count())
     }
 
-    /// Prune expired sessions (call periodically from reaper).
+    /// Prune expired sessions.
     pub fn prune(&self) {
-        let ttl = Duration::from_secs(self.ttl_secs);
-        let mut sessions = self.sessions.lock().unwrap();
-        sessions.retain(|_, data| data.created_at.elapsed() < ttl);
+        logged(self.backend.prune(self.ttl()));
+    }
+
+    /// Put a refresh token that was rotated out on the revocation list.
+    pub fn revoke_refresh_token(&self, refresh_token: &str) {
+        let key = format!("rt:{}", store::key(refresh_token));
+        logged(self.backend.revoke(&key, unix_now(), self.ttl()));
     }
-}
 
-/// Generate a cryptographically-ish random session ID.
-/// Uses system time + process-level counter for uniqueness.
-fn generate_session_id() -> String {
-    use std::sync::atomic::{AtomicU64, Ordering};
-    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    /// Whether a refresh token was rotated out.
+    pub fn is_refresh_token_revoked(&self, refresh_token: &str) -> bool {
+        let key = format!("rt:{}", store::key(refresh_token));
+        logged(self.backend.revoked(&key)).is_some()
+    }
 
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    /// Revoke every session of `sub` created until now.
+    pub fn revoke_subject(&self, sub: &str) {
+        let key = format!("sub:{}", store::key(sub));
+        logged(self.backend.revoke(&key, unix_now(), self.ttl()));
+    }
 
-    // FNV-1a hash of time + counter for a 128-bit session ID
-    let mut h1: u64 = 0xcbf29ce484222325;
-    for b in now.to_le_bytes() {
-        h1 ^= b as u64;
-        h1 = h1.wrapping_mul(0x100000001b3);
+    /// Whether `session` was created before its subject's sessions were
+    /// revoked.
+    pub fn is_revoked(&self, session: &SessionData) -> bool {
+        let Some(claims) = &session.claims else { return false };
+        let key = format!("sub:{}", store::key(&claims.sub));
+        logged(self.backend.revoked(&key)).is_some_and(|at| at >= unix_time(session.created_at))
     }
-    for b in count.to_le_bytes() {
-        h1 ^= b as u64;
-        h1 = h1.wrapping_mul(0x100000001b3);
+}
+
+/// A backend result, or its default after logging the error: a store
+/// that's down reads as logged out.
+fn logged<T: Default>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("[auth] session store: {}", e);
+        T::default()
+    })
+}
+
+// ── Stateless sessions ──────────────────────────────────────────────
+
+/// A session as sealed into a cookie or kept by a persistent backend
+/// (store.rs). Times are unix seconds.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    a: String,
+    r: Option<String>,
+    e: u64,
+    c: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    u: Option<Claims>,
+}
+
+/// A session as JSON, to store.
+pub fn to_json(data: &SessionData) -> String {
+    let record = Record {
+        a: data.access_token.clone(),
+        r: data.refresh_token.clone(),
+        e: unix_time(data.token_expires_at),
+        c: unix_time(data.created_at),
+        u: data.claims.clone(),
+    };
+    serde_json::to_string(&record).unwrap()
+}
+
+/// A session from `to_json`.
+pub fn from_json(json: &str) -> Option<SessionData> {
+    let record: Record = serde_json::from_str(json).ok()?;
+    Some(SessionData {
+        access_token: record.a,
+        refresh_token: record.r,
+        token_expires_at: instant(record.e),
+        created_at: instant(record.c),
+        claims: record.u,
+    })
+}
+
+/// Seal a new session into a cookie value.
+pub fn seal(access_token: &str, refresh_token: Option<&str>, expires_in_secs: u64, claims: Option<Claims>) -> String {
+    let data = SessionData::new(access_token, refresh_token, expires_in_secs, claims);
+    crate::cookie::seal(to_json(&data).as_bytes())
+}
+
+/// Open a sealed cookie value. Returns None if it was tampered with, or
+/// the session TTL has passed.
+pub fn open(cookie: &str, ttl_secs: u64) -> Option<SessionData> {
+    let data = from_json(std::str::from_utf8(&crate::cookie::open(cookie)?).ok()?)?;
+    (data.created_at.elapsed() <= Duration::from_secs(ttl_secs)).then_some(data)
+}
+
+// ── Logins in progress ──────────────────────────────────────────────
+
+/// How long a login may take, from /auth/login to the callback
+pub const LOGIN_TTL: Duration = Duration::from_secs(600);
+
+/// An OAuth2/OIDC login between /auth/login and its callback
+pub struct Login {
+    /// PKCE code_verifier, None with PKCE off
+    pub verifier: Option<String>,
+    /// OIDC nonce the id_token must carry
+    pub nonce: Option<String>,
+    started: Instant,
+}
+
+/// Logins in progress by CSRF state. Each state is good for one callback
+/// within LOGIN_TTL.
+#[derive(Default)]
+pub struct LoginStore {
+    logins: Mutex<HashMap<String, Login>>,
+}
+
+impl LoginStore {
+    /// Record a login; returns its new random state.
+    pub fn begin(&self, verifier: Option<String>, nonce: Option<String>) -> String {
+        let state = crate::cookie::random_id();
+        let mut logins = self.logins.lock().unwrap();
+        logins.retain(|_, login| login.started.elapsed() < LOGIN_TTL);
+        logins.insert(state.clone(), Login { verifier, nonce, started: Instant::now() });
+        state
     }
 
-    let mut h2: u64 = 0x84222325cbf29ce4;
-    for b in count.to_le_bytes().iter().rev() {
-        h2 ^= *b as u64;
-        h2 = h2.wrapping_mul(0x1b300000001);
+    /// Take the login for `state`, if it's recorded and not expired.
+    pub fn take(&self, state: &str) -> Option<Login> {
+        let login = self.logins.lock().unwrap().remove(state)?;
+        (login.started.elapsed() < LOGIN_TTL).then_some(login)
     }
-    for b in now.to_le_bytes().iter().rev() {
-        h2 ^= *b as u64;
-        h2 = h2.wrapping_mul(0x1b300000001);
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+/// An Instant as unix seconds
+pub fn unix_time(at: Instant) -> u64 {
+    let now = Instant::now();
+    match at.checked_duration_since(now) {
+        Some(ahead) => unix_now() + ahead.as_secs(),
+        None => unix_now().saturating_sub(now.duration_since(at).as_secs()),
     }
+}
 
-    format!("{:016x}{:016x}", h1, h2)
+/// Unix seconds as an Instant
+fn instant(t: u64) -> Instant {
+    let now = unix_now();
+    match t.checked_sub(now) {
+        Some(ahead) => Instant::now() + Duration::from_secs(ahead),
+        None => Instant::now().checked_sub(Duration::from_secs(now - t)).unwrap_or_else(Instant::now),
+    }
 }
@@ -0,0 +1,104 @@
+//! smtp.rs — Built-in magic links
+//!
+//! With `smtp` in the auth config, the `magic-link` provider needs no
+//! external service: POST /auth/send emails a link itself, and
+//! /auth/callback checks the token it carries.
+//!
+//! The token is sealed with the session secret (cookie.rs), holding the
+//! email address and an expiry LINK_TTL ahead, so any process or replica
+//! with the secret can check it. Each token works once: redeemed tokens are
+//! remembered until they expire, per process.
+//!
+//! The link is `redirect_uri` with `?token=...`, and `redirect_uri` must be
+//! absolute: the request's Host header is the sender's to choose, and a
+//! link built from it could carry a valid token to another site.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use lettre::message::Mailbox;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+
+use crate::data::SmtpConfig;
+
+/// How long a link works after it's sent
+pub const LINK_TTL: Duration = Duration::from_secs(900);
+const DEFAULT_SUBJECT: &str = "Your sign-in link";
+/// Marks a sealed value as a magic-link token
+const PURPOSE: &str = "magic-link";
+
+#[derive(Serialize, Deserialize)]
+struct Token {
+    p: String,
+    /// Email address
+    m: String,
+    /// Expiry, unix seconds
+    x: u64,
+    /// Tells tokens for one address apart, for single use
+    n: String,
+}
+
+/// Tokens redeemed in this process, until they expire
+#[derive(Default)]
+pub struct MagicLinks {
+    used: Mutex<HashMap<String, Instant>>,
+}
+
+impl MagicLinks {
+    /// A new token for `email`.
+    pub fn issue(&self, email: &str) -> String {
+        let token = Token {
+            p: PURPOSE.into(),
+            m: email.to_string(),
+            x: unix_now() + LINK_TTL.as_secs(),
+            n: crate::cookie::random_id(),
+        };
+        crate::cookie::seal(&serde_json::to_vec(&token).unwrap())
+    }
+
+    /// The email address `token` was issued for, if it's intact, unexpired
+    /// and not used yet.
+    pub fn redeem(&self, token: &str) -> Result<String, String> {
+        let token: Token = crate::cookie::open(token)
+            .and_then(|plain| serde_json::from_slice(&plain).ok())
+            .filter(|t: &Token| t.p == PURPOSE)
+            .ok_or("Invalid magic link")?;
+        if unix_now() > token.x {
+            return Err("Magic link expired".into());
+        }
+        let mut used = self.used.lock().unwrap();
+        used.retain(|_, at| at.elapsed() < LINK_TTL);
+        if used.insert(token.n, Instant::now()).is_some() {
+            return Err("Magic link already used".into());
+        }
+        Ok(token.m)
+    }
+}
+
+/// Email `link` to `to`.
+pub fn send(config: &SmtpConfig, url: &str, to: &str, link: &str) -> Result<(), String> {
+    let from: Mailbox = config.from.parse().map_err(|e| format!("smtp from {}: {}", config.from, e))?;
+    let to: Mailbox = to.parse().map_err(|_| "Invalid email address".to_string())?;
+    let body = format!(
+        "Sign in by opening this link:\n\n{}\n\nIt works once, within {} minutes. \
+        If you didn't ask to sign in, you can ignore this email.\n",
+        link,
+        LINK_TTL.as_secs() / 60
+    );
+    let message = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(config.subject.as_deref().unwrap_or(DEFAULT_SUBJECT))
+        .body(body)
+        .map_err(|e| format!("smtp message: {}", e))?;
+    let transport = SmtpTransport::from_url(url)
+        .map_err(|e| format!("smtp url: {}", e))?
+        .build();
+    transport.send(&message).map(drop).map_err(|e| format!("smtp send: {}", e))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
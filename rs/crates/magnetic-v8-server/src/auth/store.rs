@@ -0,0 +1,298 @@
+//! store.rs — Session backends
+//!
+//! Where SessionStore (session.rs) keeps auth sessions, chosen by the
+//! `store` key of the auth `session` config:
+//! - `"memory"` (default): a HashMap in this process, gone on restart;
+//! - `"sqlite:<path>"`: a SQLite file (WAL mode), which survives restarts
+//!   and is shared by processes on one machine;
+//! - `"redis://[:password@]host[:port][/db]"`: a Redis server, shared by
+//!   every process and replica that points at it. Keys expire with the
+//!   session TTL.
+//!
+//! Persistent backends store a session as JSON (session.rs `to_json`)
+//! under the SHA-256 of its id, so a leaked table or keyspace doesn't hand
+//! out live session cookies.
+//!
+//! Each backend also keeps the revocation list: keys (hashed refresh
+//! tokens, subjects) with the unix time they were revoked at, dropped after
+//! a TTL.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use ring::digest;
+
+use super::session::{self, SessionData};
+
+/// Redis keys are KEY_PREFIX + hashed id
+const KEY_PREFIX: &str = "magnetic:session:";
+/// Redis keys for revocations are REVOKED_PREFIX + key
+const REVOKED_PREFIX: &str = "magnetic:revoked:";
+const REDIS_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long SQLite waits for another process's write lock
+const SQLITE_BUSY: Duration = Duration::from_secs(5);
+
+pub trait SessionBackend: Send + Sync {
+    fn get(&self, id: &str) -> Result<Option<SessionData>, String>;
+    /// Store `data` under `id`; it may be dropped once `ttl` has passed
+    /// since the session was created.
+    fn put(&self, id: &str, data: &SessionData, ttl: Duration) -> Result<(), String>;
+    fn remove(&self, id: &str) -> Result<(), String>;
+    /// Drop sessions created more than `ttl` ago.
+    fn prune(&self, ttl: Duration) -> Result<(), String>;
+    fn count(&self) -> Result<usize, String>;
+    /// Record `key` as revoked at unix time `at`, for `ttl`.
+    fn revoke(&self, key: &str, at: u64, ttl: Duration) -> Result<(), String>;
+    /// When `key` was revoked, if it's on the list.
+    fn revoked(&self, key: &str) -> Result<Option<u64>, String>;
+}
+
+/// The backend for a `store` config value (None: memory).
+pub fn open(store: Option<&str>) -> Result<Box<dyn SessionBackend>, String> {
+    match store.map(str::trim) {
+        None | Some("") | Some("memory") => Ok(Box::new(Memory::default())),
+        Some(spec) if spec.starts_with("sqlite:") => Ok(Box::new(Sqlite::open(&spec["sqlite:".len()..])?)),
+        Some(spec) if spec.starts_with("redis://") || spec.starts_with("rediss://") => Ok(Box::new(Redis::open(spec)?)),
+        Some(spec) => Err(format!("unknown session store \"{}\" (memory, sqlite:<path> or redis://...)", spec)),
+    }
+}
+
+/// Hex SHA-256 of a session id or token
+pub fn key(id: &str) -> String {
+    digest::digest(&digest::SHA256, id.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// ── Memory ──────────────────────────────────────────────────────────
+
+#[derive(Default)]
+pub struct Memory {
+    sessions: Mutex<HashMap<String, SessionData>>,
+    /// key → (revoked at, dropped at)
+    revoked: Mutex<HashMap<String, (u64, Instant)>>,
+}
+
+impl SessionBackend for Memory {
+    fn get(&self, id: &str) -> Result<Option<SessionData>, String> {
+        Ok(self.sessions.lock().unwrap().get(id).cloned())
+    }
+
+    fn put(&self, id: &str, data: &SessionData, _ttl: Duration) -> Result<(), String> {
+        self.sessions.lock().unwrap().insert(id.to_string(), data.clone());
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), String> {
+        self.sessions.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn prune(&self, ttl: Duration) -> Result<(), String> {
+        self.sessions.lock().unwrap().retain(|_, data| data.created_at.elapsed() < ttl);
+        self.revoked.lock().unwrap().retain(|_, (_, until)| *until > Instant::now());
+        Ok(())
+    }
+
+    fn count(&self) -> Result<usize, String> {
+        Ok(self.sessions.lock().unwrap().len())
+    }
+
+    fn revoke(&self, key: &str, at: u64, ttl: Duration) -> Result<(), String> {
+        self.revoked.lock().unwrap().insert(key.to_string(), (at, Instant::now() + ttl));
+        Ok(())
+    }
+
+    fn revoked(&self, key: &str) -> Result<Option<u64>, String> {
+        let revoked = self.revoked.lock().unwrap();
+        Ok(revoked.get(key).filter(|(_, until)| *until > Instant::now()).map(|(at, _)| *at))
+    }
+}
+
+// ── SQLite ──────────────────────────────────────────────────────────
+
+pub struct Sqlite {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+impl Sqlite {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let err = |e: rusqlite::Error| format!("sqlite {}: {}", path, e);
+        let conn = rusqlite::Connection::open(path).map_err(err)?;
+        conn.busy_timeout(SQLITE_BUSY).map_err(err)?;
+        conn.pragma_update(None, "journal_mode", "WAL").map_err(err)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                created INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS sessions_created ON sessions (created);
+            CREATE TABLE IF NOT EXISTS revoked (
+                key TEXT PRIMARY KEY,
+                at INTEGER NOT NULL,
+                until INTEGER NOT NULL
+            );",
+        )
+        .map_err(err)?;
+        Ok(Sqlite { conn: Mutex::new(conn) })
+    }
+}
+
+fn sqlite_err(e: rusqlite::Error) -> String {
+    format!("sqlite: {}", e)
+}
+
+impl SessionBackend for Sqlite {
+    fn get(&self, id: &str) -> Result<Option<SessionData>, String> {
+        use rusqlite::OptionalExtension;
+        let json: Option<String> = self.conn.lock().unwrap()
+            .query_row("SELECT data FROM sessions WHERE id = ?1", [key(id)], |row| row.get(0))
+            .optional()
+            .map_err(sqlite_err)?;
+        Ok(json.and_then(|json| session::from_json(&json)))
+    }
+
+    fn put(&self, id: &str, data: &SessionData, _ttl: Duration) -> Result<(), String> {
+        self.conn.lock().unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO sessions (id, data, created) VALUES (?1, ?2, ?3)",
+                rusqlite::params![key(id), session::to_json(data), session::unix_time(data.created_at)],
+            )
+            .map(drop)
+            .map_err(sqlite_err)
+    }
+
+    fn remove(&self, id: &str) -> Result<(), String> {
+        self.conn.lock().unwrap()
+            .execute("DELETE FROM sessions WHERE id = ?1", [key(id)])
+            .map(drop)
+            .map_err(sqlite_err)
+    }
+
+    fn prune(&self, ttl: Duration) -> Result<(), String> {
+        let now = session::unix_time(Instant::now());
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM sessions WHERE created < ?1", [now.saturating_sub(ttl.as_secs())])
+            .map_err(sqlite_err)?;
+        conn.execute("DELETE FROM revoked WHERE until < ?1", [now])
+            .map(drop)
+            .map_err(sqlite_err)
+    }
+
+    fn count(&self) -> Result<usize, String> {
+        self.conn.lock().unwrap()
+            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+            .map_err(sqlite_err)
+    }
+
+    fn revoke(&self, key: &str, at: u64, ttl: Duration) -> Result<(), String> {
+        let until = session::unix_time(Instant::now()) + ttl.as_secs();
+        self.conn.lock().unwrap()
+            .execute("INSERT OR REPLACE INTO revoked (key, at, until) VALUES (?1, ?2, ?3)", rusqlite::params![key, at, until])
+            .map(drop)
+            .map_err(sqlite_err)
+    }
+
+    fn revoked(&self, key: &str) -> Result<Option<u64>, String> {
+        use rusqlite::OptionalExtension;
+        let now = session::unix_time(Instant::now());
+        self.conn.lock().unwrap()
+            .query_row("SELECT at FROM revoked WHERE key = ?1 AND until >= ?2", rusqlite::params![key, now], |row| row.get(0))
+            .optional()
+            .map_err(sqlite_err)
+    }
+}
+
+// ── Redis ───────────────────────────────────────────────────────────
+
+/// One connection, made again after an error
+pub struct Redis {
+    client: redis::Client,
+    conn: Mutex<Option<redis::Connection>>,
+}
+
+impl Redis {
+    pub fn open(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| format!("redis: {}", e))?;
+        let redis = Redis { client, conn: Mutex::new(None) };
+        // Fail at startup on a bad address or password
+        redis.with(|conn| redis::cmd("PING").query::<String>(conn))?;
+        Ok(redis)
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>) -> Result<T, String> {
+        let mut slot = self.conn.lock().unwrap();
+        if slot.is_none() {
+            let conn = self.client
+                .get_connection_with_timeout(REDIS_TIMEOUT)
+                .map_err(|e| format!("redis: {}", e))?;
+            let _ = conn.set_read_timeout(Some(REDIS_TIMEOUT));
+            let _ = conn.set_write_timeout(Some(REDIS_TIMEOUT));
+            *slot = Some(conn);
+        }
+        let result = f(slot.as_mut().unwrap());
+        if result.is_err() {
+            *slot = None;
+        }
+        result.map_err(|e| format!("redis: {}", e))
+    }
+}
+
+impl SessionBackend for Redis {
+    fn get(&self, id: &str) -> Result<Option<SessionData>, String> {
+        let json: Option<String> = self.with(|conn| redis::cmd("GET").arg(KEY_PREFIX.to_string() + &key(id)).query(conn))?;
+        Ok(json.and_then(|json| session::from_json(&json)))
+    }
+
+    fn put(&self, id: &str, data: &SessionData, ttl: Duration) -> Result<(), String> {
+        let left = ttl.saturating_sub(data.created_at.elapsed()).as_secs().max(1);
+        self.with(|conn| {
+            redis::cmd("SET")
+                .arg(KEY_PREFIX.to_string() + &key(id))
+                .arg(session::to_json(data))
+                .arg("EX")
+                .arg(left)
+                .query(conn)
+        })
+    }
+
+    fn remove(&self, id: &str) -> Result<(), String> {
+        self.with(|conn| redis::cmd("DEL").arg(KEY_PREFIX.to_string() + &key(id)).query(conn))
+    }
+
+    /// Keys expire on their own
+    fn prune(&self, _ttl: Duration) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn count(&self) -> Result<usize, String> {
+        self.with(|conn| {
+            let keys: redis::Iter<String> = redis::cmd("SCAN")
+                .cursor_arg(0)
+                .arg("MATCH")
+                .arg(format!("{}*", KEY_PREFIX))
+                .clone()
+                .iter(conn)?;
+            Ok(keys.count())
+        })
+    }
+
+    fn revoke(&self, key: &str, at: u64, ttl: Duration) -> Result<(), String> {
+        self.with(|conn| {
+            redis::cmd("SET")
+                .arg(REVOKED_PREFIX.to_string() + key)
+                .arg(at)
+                .arg("EX")
+                .arg(ttl.as_secs().max(1))
+                .query(conn)
+        })
+    }
+
+    fn revoked(&self, key: &str) -> Result<Option<u64>, String> {
+        self.with(|conn| redis::cmd("GET").arg(REVOKED_PREFIX.to_string() + key).query(conn))
+    }
+}
@@ -0,0 +1,195 @@
+//! throttle.rs — OTP guess throttling
+//!
+//! POST /auth/verify failures are counted per `method_id` and per client
+//! address (rate_limit.rs `client`). The first FREE_FAILURES allow for
+//! typos; after that each key waits 1s, 2s, 4s, ... before its next
+//! attempt, and after `otp_max_attempts` failures (default 10) it is locked
+//! out for `otp_lockout` (default 15m). Either key being held back refuses
+//! the attempt with a 429 and Retry-After, before the provider is asked.
+//!
+//! An attempt is counted as a failure when it starts (`begin`), under the
+//! same lock as the check, so guesses sent in parallel can't all get past
+//! the check before one of them is counted. A success clears its keys; an
+//! attempt the provider never judged (it was down, or answered oddly)
+//! gives its count back. Failures are forgotten FORGET_AFTER the last one
+//! (or when a lockout ends). Counts are per process.
+//!
+//! Every failure, lockout, refusal and success is logged as an `[auth] otp`
+//! line with the method_id and client address, for the audit trail.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Failures before the backoff starts
+const FREE_FAILURES: u32 = 3;
+const FORGET_AFTER: Duration = Duration::from_secs(3600);
+/// Entries are swept once there are this many
+const SWEEP_AT: usize = 10_000;
+
+pub struct Throttle {
+    max_attempts: u32,
+    lockout: Duration,
+    /// "m:<method_id>" or "ip:<client>" → failures
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+struct Entry {
+    failures: u32,
+    last: Instant,
+}
+
+impl Throttle {
+    pub fn new(max_attempts: u32, lockout: Duration) -> Self {
+        Throttle { max_attempts: max_attempts.max(1), lockout, entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// How long until `failures` failures allow another attempt
+    fn delay(&self, failures: u32) -> Duration {
+        if failures >= self.max_attempts {
+            self.lockout
+        } else if failures < FREE_FAILURES {
+            Duration::ZERO
+        } else {
+            Duration::from_secs(1 << (failures - FREE_FAILURES).min(16)).min(self.lockout)
+        }
+    }
+
+    /// Start an attempt by `method_id` from `client`, counting it as a
+    /// failure until it's known to be otherwise. Err with how long to wait
+    /// if either key is held back; then nothing is counted.
+    pub fn begin(&self, method_id: &str, client: &str) -> Result<(), Duration> {
+        let mut entries = self.entries.lock().unwrap();
+        let wait = keys(method_id, client)
+            .iter()
+            .filter_map(|key| entries.get(key))
+            .map(|e| self.delay(e.failures).saturating_sub(e.last.elapsed()))
+            .max()
+            .filter(|wait| !wait.is_zero());
+        if let Some(wait) = wait {
+            eprintln!("[auth] otp refused: method_id={} ip={} retry_after={}s", method_id, client, wait.as_secs_f64().ceil());
+            return Err(wait);
+        }
+        if entries.len() >= SWEEP_AT {
+            let keep = FORGET_AFTER.max(self.lockout);
+            entries.retain(|_, e| e.last.elapsed() < keep);
+        }
+        for key in keys(method_id, client) {
+            let entry = entries.entry(key).or_insert(Entry { failures: 0, last: Instant::now() });
+            // A lockout that has run out starts the count again
+            let forget = if entry.failures >= self.max_attempts { self.lockout } else { FORGET_AFTER };
+            if entry.last.elapsed() >= forget {
+                entry.failures = 0;
+            }
+            entry.failures += 1;
+            entry.last = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// The attempt's code was wrong: it stays counted.
+    pub fn failed(&self, method_id: &str, client: &str) {
+        let entries = self.entries.lock().unwrap();
+        let failures = keys(method_id, client)
+            .iter()
+            .filter_map(|key| entries.get(key))
+            .map(|e| e.failures)
+            .max()
+            .unwrap_or(0);
+        eprintln!("[auth] otp failure {}: method_id={} ip={}", failures, method_id, client);
+        if failures == self.max_attempts {
+            eprintln!("[auth] otp lockout for {}s: method_id={} ip={}", self.lockout.as_secs(), method_id, client);
+        }
+    }
+
+    /// The attempt got no verdict on its code: give its count back.
+    pub fn cancelled(&self, method_id: &str, client: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        for key in keys(method_id, client) {
+            if let Some(entry) = entries.get_mut(&key) {
+                entry.failures = entry.failures.saturating_sub(1);
+            }
+        }
+    }
+
+    /// A successful attempt clears the counts.
+    pub fn succeeded(&self, method_id: &str, client: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        for key in keys(method_id, client) {
+            entries.remove(&key);
+        }
+        eprintln!("[auth] otp verified: method_id={} ip={}", method_id, client);
+    }
+}
+
+/// The keys an attempt counts against. Without a method_id only the
+/// client's, so one caller can't lock out every other.
+fn keys(method_id: &str, client: &str) -> Vec<String> {
+    let mut keys = vec![format!("ip:{}", client)];
+    if !method_id.is_empty() {
+        keys.push(format!("m:{}", method_id));
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    #[test]
+    fn parallel_guesses_are_counted_before_the_check() {
+        let throttle = Arc::new(Throttle::new(10, Duration::from_secs(900)));
+        let threads = 32;
+        let barrier = Arc::new(Barrier::new(threads));
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let throttle = Arc::clone(&throttle);
+                let barrier = Arc::clone(&barrier);
+                // Different clients, one method_id
+                thread::spawn(move || {
+                    barrier.wait();
+                    throttle.begin("method-1", &format!("10.0.0.{}", i)).is_ok()
+                })
+            })
+            .collect();
+        let started = handles.into_iter().map(|h| h.join().unwrap()).filter(|&ok| ok).count();
+        assert_eq!(started, FREE_FAILURES as usize);
+    }
+
+    #[test]
+    fn backoff_then_lockout() {
+        let throttle = Throttle::new(5, Duration::from_secs(900));
+        for _ in 0..FREE_FAILURES {
+            throttle.begin("m", "ip").unwrap();
+            throttle.failed("m", "ip");
+        }
+        let wait = throttle.begin("m", "ip").unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(1));
+        assert_eq!(throttle.delay(4), Duration::from_secs(2));
+        assert_eq!(throttle.delay(5), Duration::from_secs(900));
+    }
+
+    #[test]
+    fn unjudged_attempts_are_given_back() {
+        let throttle = Throttle::new(10, Duration::from_secs(900));
+        for _ in 0..10 {
+            assert!(throttle.begin("m", "ip").is_ok());
+            throttle.cancelled("m", "ip");
+        }
+        assert!(throttle.begin("m", "ip").is_ok());
+    }
+
+    #[test]
+    fn success_clears_the_count() {
+        let throttle = Throttle::new(10, Duration::from_secs(900));
+        for _ in 0..FREE_FAILURES {
+            throttle.begin("m", "ip").unwrap();
+            throttle.failed("m", "ip");
+        }
+        assert!(throttle.begin("m", "ip").is_err());
+        throttle.succeeded("m", "ip");
+        assert!(throttle.begin("m", "ip").is_ok());
+    }
+}
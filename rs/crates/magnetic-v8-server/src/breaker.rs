@@ -0,0 +1,90 @@
+//! breaker.rs — Circuit breakers for data sources
+//!
+//! A source with `breaker` config counts its consecutive failed fetches
+//! (after retries). At `failures` the circuit opens: fetches fail at once,
+//! without calling the upstream, and the last good value stays in place.
+//! After `cooldown` it is half-open: one fetch goes through as a probe while
+//! the others keep failing fast. A probe that succeeds closes the circuit; one
+//! that fails opens it for another cooldown.
+//!
+//! State is per app and source, shared by every session, and reported in
+//! /api/apps/<name>/status.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+pub struct Breakers {
+    states: Mutex<HashMap<String, State>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Consecutive failures
+    failures: u32,
+    /// Open until then
+    open_until: Option<Instant>,
+    /// When the half-open probe went out, while it's out
+    probe: Option<Instant>,
+}
+
+impl Breakers {
+    /// Whether `key` may fetch now. Past an open circuit's cooldown, the
+    /// first caller gets to probe (and another one if a probe hangs for
+    /// longer than `cooldown`).
+    pub fn allow(&self, key: &str, cooldown: Duration) -> Result<(), String> {
+        let mut states = self.states.lock().unwrap();
+        let Some(state) = states.get_mut(key) else { return Ok(()) };
+        let Some(until) = state.open_until else { return Ok(()) };
+        let now = Instant::now();
+        if now < until {
+            return Err(format!("circuit open for '{}' (retry in {}s)", key, (until - now).as_secs_f64().ceil()));
+        }
+        if state.probe.is_some_and(|at| now.duration_since(at) < cooldown) {
+            return Err(format!("circuit half-open for '{}' (probe in flight)", key));
+        }
+        eprintln!("[data] circuit half-open for '{}', probing", key);
+        state.probe = Some(now);
+        Ok(())
+    }
+
+    /// Count a fetch of `key`: a success closes the circuit, and the
+    /// `threshold`th failure in a row (or a failed probe) opens it.
+    pub fn record(&self, key: &str, ok: bool, threshold: u32, cooldown: Duration) {
+        let mut states = self.states.lock().unwrap();
+        if ok {
+            if states.remove(key).is_some_and(|s| s.open_until.is_some()) {
+                eprintln!("[data] circuit closed for '{}'", key);
+            }
+            return;
+        }
+        let state = states.entry(key.to_string()).or_default();
+        state.failures += 1;
+        let probing = state.probe.take().is_some();
+        if probing || (state.open_until.is_none() && state.failures >= threshold.max(1)) {
+            eprintln!("[data] circuit open for '{}' after {} failures (cooldown {:?})", key, state.failures, cooldown);
+            state.open_until = Some(Instant::now() + cooldown);
+        }
+    }
+
+    /// `{key: {"state", "failures", "retry_in_ms"}}` for sources that have
+    /// failed since their last success
+    pub fn to_json(&self) -> serde_json::Value {
+        let now = Instant::now();
+        let states = self.states.lock().unwrap();
+        let map = states
+            .iter()
+            .map(|(key, s)| {
+                let state = match s.open_until {
+                    None => "closed",
+                    Some(until) if now < until => "open",
+                    Some(_) => "half_open",
+                };
+                let retry_in = s.open_until.map(|until| until.saturating_duration_since(now).as_millis() as u64);
+                (key.clone(), serde_json::json!({ "state": state, "failures": s.failures, "retry_in_ms": retry_in }))
+            })
+            .collect();
+        serde_json::Value::Object(map)
+    }
+}
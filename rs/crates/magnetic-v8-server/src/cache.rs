@@ -0,0 +1,161 @@
+//! cache.rs — ETag / Last-Modified validators and 304 responses
+//!
+//! Static files, embedded assets, pre-rendered and SSR pages carry an
+//! ETag: the content hash already in a hashed asset's name, or a hash of
+//! the body otherwise. Files read from disk also carry Last-Modified. A
+//! request whose If-None-Match (or, without one, If-Modified-Since) still
+//! matches gets a 304 with no body.
+//!
+//! ETags are weak (`W/"…"`): they name the content before compression, so
+//! the gzip and brotli encodings of a page share one.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::conn::Conn;
+use crate::{compress, content_hash, AssetManifest};
+
+/// Validators for one response body
+pub struct Validators {
+    etag: String,
+    last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    /// ETag from a hash of `data`
+    pub fn for_body(data: &[u8]) -> Self {
+        Validators { etag: format!("W/\"{}\"", &content_hash(data)[..16]), last_modified: None }
+    }
+
+    /// ETag from a hash of `data`, plus the mtime of the file at `path`
+    pub fn for_file(data: &[u8], path: &Path) -> Self {
+        let mut v = Validators::for_body(data);
+        v.last_modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        v
+    }
+
+    /// Validators for a file from the asset pipeline: a hashed asset reuses
+    /// the hash in its name, anything else is treated as by `for_file`.
+    pub fn for_asset(manifest: &AssetManifest, filename: &str, data: &[u8], path: &Path) -> Self {
+        match manifest_hash(manifest, filename) {
+            Some(hash) => Validators { etag: format!("W/\"{}\"", hash), last_modified: None },
+            None => Validators::for_file(data, path),
+        }
+    }
+
+    /// True if the client's cached copy is still current
+    pub fn not_modified(&self, req_headers: &HashMap<String, String>) -> bool {
+        // If-None-Match takes precedence over If-Modified-Since (RFC 9110 §13.2.2)
+        if let Some(inm) = req_headers.get("if-none-match") {
+            let ours = strip_weak(&self.etag);
+            return inm.split(',').map(str::trim).any(|t| t == "*" || strip_weak(t) == ours);
+        }
+        let (Some(ims), Some(modified)) = (req_headers.get("if-modified-since"), self.last_modified) else {
+            return false;
+        };
+        match parse_http_date(ims) {
+            Some(since) => unix_secs(modified) <= since,
+            None => false,
+        }
+    }
+
+    /// `ETag` (and `Last-Modified`) header lines, each ending in CRLF
+    pub fn headers(&self) -> String {
+        let mut s = format!("ETag: {}\r\n", self.etag);
+        if let Some(t) = self.last_modified {
+            s.push_str(&format!("Last-Modified: {}\r\n", http_date(t)));
+        }
+        s
+    }
+}
+
+/// Send a 304 for a `content_type` response. `headers` are the other
+/// header lines the 200 would have carried (Cache-Control, cookies, ...).
+pub fn write_not_modified(
+    stream: &mut Conn,
+    v: &Validators,
+    content_type: &str,
+    headers: &str,
+) -> std::io::Result<()> {
+    let vary = if compress::is_compressible(content_type) { "Vary: Accept-Encoding\r\n" } else { "" };
+    let resp = format!("HTTP/1.1 304 Not Modified\r\n{}{}{}\r\n", v.headers(), vary, headers);
+    stream.write_all(resp.as_bytes())
+}
+
+/// The 8 hex digits `build_assets` put in a hashed file's name
+fn manifest_hash<'a>(manifest: &AssetManifest, filename: &'a str) -> Option<&'a str> {
+    let original = manifest.reverse.get(filename)?;
+    if original == filename {
+        return None;
+    }
+    let stem = Path::new(filename).file_stem()?.to_str()?;
+    let (_, hash) = stem.rsplit_once('.')?;
+    (hash.len() == 8).then_some(hash)
+}
+
+fn strip_weak(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+fn unix_secs(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs()
+}
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+pub fn http_date(t: SystemTime) -> String {
+    let secs = unix_secs(t);
+    let days = secs / 86400;
+    let (y, m, d) = civil_from_days(days as i64);
+    let rem = secs % 86400;
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize], d, MONTHS[m as usize - 1], y,
+        rem / 3600, rem / 60 % 60, rem % 60
+    )
+}
+
+/// Seconds since the epoch from an IMF-fixdate (the form browsers echo
+/// back from Last-Modified). Other date formats give None.
+pub fn parse_http_date(s: &str) -> Option<u64> {
+    let parts: Vec<&str> = s.split_whitespace().collect();
+    let [_, d, mon, y, hms, "GMT"] = parts.as_slice() else { return None };
+    let d: u32 = d.parse().ok()?;
+    let m = MONTHS.iter().position(|x| x == mon)? as u32 + 1;
+    let y: i64 = y.parse().ok()?;
+    let mut t = hms.split(':').map(|p| p.parse::<u64>().ok());
+    let (h, mi, sec) = (t.next()??, t.next()??, t.next()??);
+    let days = days_from_civil(y, m, d);
+    if days < 0 { return None; }
+    Some(days as u64 * 86400 + h * 3600 + mi * 60 + sec)
+}
+
+// Proleptic Gregorian calendar <-> days since 1970-01-01
+// (Howard Hinnant's civil_from_days / days_from_civil)
+
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (m as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
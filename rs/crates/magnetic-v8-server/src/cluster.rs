@@ -0,0 +1,266 @@
+//! cluster.rs — Listening sockets, and multi-process mode (`--processes N`)
+//!
+//! `--processes 4` turns the server into a supervisor that runs four
+//! copies of itself, each with its own V8 isolates, worker pool and
+//! sessions. Every copy binds `--port` with SO_REUSEPORT, so the kernel
+//! spreads new connections across them. Each also listens on a port of its
+//! own, `--process-port` + i (default `--port` + 1 + i).
+//!
+//! A session's state lives in whichever process handles it, and the kernel
+//! spreads connections by address, not by session. Behind a proxy, send
+//! each session to one process by hashing its `magnetic_sid` cookie over
+//! the processes' own ports (see "Multi-process mode" in
+//! docs/architecture.md for the Caddy config). In `--platform` mode each
+//! process also has its own app registry, so deploys go to every process's
+//! own port.
+//!
+//! The supervisor:
+//! - starts each child with MAGNETIC_PROCESS=<i>, in its own process group,
+//!   so a terminal's Ctrl-C reaches only the supervisor;
+//! - restarts a child that exits, after a delay that grows while it keeps
+//!   dying young;
+//! - forwards SIGTERM/SIGINT to the children as SIGTERM and exits once they
+//!   have drained (shutdown.rs); a second signal kills them;
+//! - makes up a session secret for all the children when none is
+//!   configured, so every process accepts the others' cookies.
+//!
+//! Without `--processes` the server binds `--port` alone, as before.
+
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use socket2::{Domain, SockRef, Socket, Type};
+
+/// Set in each child to its index
+const PROCESS_ENV: &str = "MAGNETIC_PROCESS";
+const SECRET_ENV: &str = "MAGNETIC_SESSION_SECRET";
+
+/// First delay before restarting a child, doubled up to MAX_RESTART_DELAY
+/// while children keep exiting within YOUNG of starting
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+const YOUNG: Duration = Duration::from_secs(10);
+
+/// Processes from `--processes`, 1 when not given
+pub fn processes_arg(args: &[String]) -> usize {
+    crate::find_arg(args, "--processes")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// This process's index when a supervisor started it
+pub fn member() -> Option<usize> {
+    std::env::var(PROCESS_ENV).ok()?.parse().ok()
+}
+
+/// Become the supervisor if `--processes` asks for more than one process
+/// and this isn't one of them already. Doesn't return in that case.
+pub fn supervise_arg(args: &[String]) {
+    let n = processes_arg(args);
+    if n > 1 && member().is_none() && crate::find_arg(args, "--render").is_none() {
+        supervise(args, n);
+    }
+}
+
+// ── Listening ───────────────────────────────────────────────────────
+
+/// The sockets a server accepts on: `--port`, plus this process's own port
+/// under a supervisor. Each has an accept thread; `incoming` yields what
+/// any of them accepts.
+pub struct Listeners {
+    rx: Receiver<io::Result<TcpStream>>,
+    sockets: Vec<TcpListener>,
+}
+
+impl Listeners {
+    pub fn bind(args: &[String], port: &str) -> io::Result<Listeners> {
+        let port: u16 = port.parse().map_err(|_| io::Error::other(format!("bad port {}", port)))?;
+        let mut sockets = Vec::new();
+        match member() {
+            Some(i) => {
+                sockets.push(bind(port, true)?);
+                let own = own_port(args, port, i);
+                sockets.push(bind(own, false)?);
+                eprintln!("[magnetic] process {} (pid {}): own port {}", i, std::process::id(), own);
+            }
+            None => sockets.push(bind(port, false)?),
+        }
+        // Rendezvous, so a busy worker pool leaves connections in the
+        // listen backlog as before
+        let (tx, rx) = mpsc::sync_channel(0);
+        for socket in &sockets {
+            let socket = socket.try_clone()?;
+            let tx = tx.clone();
+            thread::Builder::new().name("accept".into()).spawn(move || {
+                for stream in socket.incoming() {
+                    if crate::shutdown::is_stopping() || tx.send(stream).is_err() {
+                        return;
+                    }
+                }
+            })?;
+        }
+        Ok(Listeners { rx, sockets })
+    }
+
+    /// Accepted connections, until every socket is shut
+    pub fn incoming(&self) -> mpsc::Iter<'_, io::Result<TcpStream>> {
+        self.rx.iter()
+    }
+
+    /// Something for shutdown.rs to call to end `incoming`: it shuts each
+    /// socket, which refuses new connections and (on Linux) wakes its
+    /// accept thread, then connects to it in case that didn't.
+    pub fn waker(&self) -> io::Result<impl FnOnce() + Send + 'static> {
+        let sockets = self.sockets.iter().map(TcpListener::try_clone).collect::<io::Result<Vec<_>>>()?;
+        Ok(move || {
+            for socket in &sockets {
+                let _ = SockRef::from(socket).shutdown(Shutdown::Read);
+                if let Ok(addr) = socket.local_addr() {
+                    let _ = TcpStream::connect_timeout(
+                        &SocketAddr::from(([127, 0, 0, 1], addr.port())),
+                        Duration::from_millis(200),
+                    );
+                }
+            }
+        })
+    }
+}
+
+fn own_port(args: &[String], port: u16, i: usize) -> u16 {
+    let base = crate::find_arg(args, "--process-port")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(port + 1);
+    base + i as u16
+}
+
+fn bind(port: u16, reuse_port: bool) -> io::Result<TcpListener> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let socket = Socket::new(Domain::IPV4, Type::STREAM, None)?;
+    socket.set_reuse_address(true)?;
+    if reuse_port {
+        socket.set_reuse_port(true)?;
+    }
+    socket.bind(&addr.into())?;
+    // The backlog std's TcpListener::bind uses
+    socket.listen(128)?;
+    Ok(socket.into())
+}
+
+// ── Supervisor ──────────────────────────────────────────────────────
+
+struct Slot {
+    child: Option<Child>,
+    started: Instant,
+    restart_at: Option<Instant>,
+    delay: Duration,
+}
+
+fn supervise(args: &[String], n: usize) -> ! {
+    let exe = std::env::current_exe().expect("current executable");
+    // The command line as given: each child reads the config file itself
+    let child_args: Vec<String> = std::env::args().skip(1).collect();
+    let secret = match crate::find_arg(args, "--session-secret").or_else(|| std::env::var(SECRET_ENV).ok()) {
+        Some(_) => None,
+        None => {
+            eprintln!("[magnetic] no --session-secret: the processes share a random one (cookies reset on restart)");
+            Some(format!("{}{}", crate::cookie::random_id(), crate::cookie::random_id()))
+        }
+    };
+    let spawn = |i: usize| -> io::Result<Child> {
+        let mut cmd = Command::new(&exe);
+        cmd.args(&child_args).env(PROCESS_ENV, i.to_string()).process_group(0);
+        if let Some(secret) = &secret {
+            cmd.env(SECRET_ENV, secret);
+        }
+        cmd.spawn()
+    };
+
+    let pids = Arc::new(Mutex::new(vec![None::<u32>; n]));
+    let stopping = Arc::new(AtomicBool::new(false));
+    forward_signals(Arc::clone(&pids), Arc::clone(&stopping));
+
+    let mut slots: Vec<Slot> = (0..n)
+        .map(|_| Slot { child: None, started: Instant::now(), restart_at: Some(Instant::now()), delay: RESTART_DELAY })
+        .collect();
+    eprintln!("[magnetic] supervising {} processes (pid {})", n, std::process::id());
+    loop {
+        let stop = stopping.load(Ordering::Acquire);
+        for (i, slot) in slots.iter_mut().enumerate() {
+            if let Some(child) = &mut slot.child {
+                let Ok(Some(status)) = child.try_wait() else { continue };
+                slot.child = None;
+                pids.lock().unwrap()[i] = None;
+                if stop {
+                    continue;
+                }
+                slot.delay = if slot.started.elapsed() < YOUNG {
+                    (slot.delay * 2).min(MAX_RESTART_DELAY)
+                } else {
+                    RESTART_DELAY
+                };
+                eprintln!("[magnetic] process {} exited ({}), restarting in {:?}", i, status, slot.delay);
+                slot.restart_at = Some(Instant::now() + slot.delay);
+            } else if slot.restart_at.is_some_and(|at| !stop && Instant::now() >= at) {
+                match spawn(i) {
+                    Ok(child) => {
+                        pids.lock().unwrap()[i] = Some(child.id());
+                        slot.child = Some(child);
+                        slot.started = Instant::now();
+                        slot.restart_at = None;
+                    }
+                    Err(e) => {
+                        eprintln!("[magnetic] cannot start process {}: {}", i, e);
+                        slot.restart_at = Some(Instant::now() + MAX_RESTART_DELAY);
+                    }
+                }
+            }
+        }
+        if stop && slots.iter().all(|s| s.child.is_none()) {
+            eprintln!("[magnetic] all processes exited");
+            std::process::exit(0);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// First SIGTERM/SIGINT: pass SIGTERM on so each child drains. Second:
+/// SIGKILL them and exit.
+fn forward_signals(pids: Arc<Mutex<Vec<Option<u32>>>>, stopping: Arc<AtomicBool>) {
+    let mut signals = Signals::new([SIGTERM, SIGINT]).expect("signal handlers");
+    thread::Builder::new()
+        .name("supervisor-signals".into())
+        .spawn(move || {
+            let mut signals = signals.forever();
+            if signals.next().is_none() {
+                return;
+            }
+            eprintln!("[magnetic] stopping processes");
+            stopping.store(true, Ordering::Release);
+            signal_all(&pids, libc::SIGTERM);
+            if signals.next().is_some() {
+                eprintln!("[magnetic] second signal, killing processes");
+                signal_all(&pids, libc::SIGKILL);
+                std::process::exit(1);
+            }
+        })
+        .expect("Failed to spawn signal thread");
+}
+
+fn signal_all(pids: &Mutex<Vec<Option<u32>>>, signal: libc::c_int) {
+    for pid in pids.lock().unwrap().iter().flatten() {
+        // SAFETY: kill(2) has no memory effects; the pid is our child's
+        unsafe {
+            libc::kill(*pid as libc::pid_t, signal);
+        }
+    }
+}
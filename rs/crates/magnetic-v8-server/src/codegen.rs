@@ -0,0 +1,190 @@
+//! codegen.rs — Incremental output writing for --render mode
+//!
+//! Every generated file is content-hashed. Files whose hash matches the last
+//! run are left untouched (so IDE indexers and incremental native builds don't
+//! see spurious changes), and a summary of what changed is printed.
+//!
+//! With --out-dir the hashes live in `<dir>/.magnetic-codegen.json`, which also
+//! records which files the previous run produced: files that are no longer
+//! generated (e.g. a removed data-component) are deleted. A single --out file
+//! is compared against its current contents on disk.
+//!
+//! `--routes /,/about` renders each listed page, and `--routes all` every
+//! page the bundle's `routes()` export lists (patterns like `/blog/:slug`
+//! are skipped). Without it only `/` is rendered. More than one page needs
+//! --out-dir: kotlin and swift write each page's files into
+//! `<dir>/<page>/`, the other modes write `<dir>/<page>.<ext>`. Every page
+//! gets a type name of its own (`MagneticApp`, `MagneticAppAbout`, ...).
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::content_hash;
+
+const MANIFEST_FILE: &str = ".magnetic-codegen.json";
+
+/// Which pages a --render run generates
+pub enum Routes {
+    /// Only `/` (no --routes)
+    Root,
+    /// `--routes all`: whatever the bundle's routes() lists
+    Bundle,
+    List(Vec<String>),
+}
+
+/// Pages from `--routes`
+pub fn routes_arg(args: &[String]) -> Routes {
+    match crate::find_arg(args, "--routes") {
+        None => Routes::Root,
+        Some(s) if s == "all" => Routes::Bundle,
+        Some(s) => Routes::List(s.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect()),
+    }
+}
+
+/// File stem for a page: `index` for `/`, `blog_first_post` for
+/// `/blog/first-post`
+pub fn page_slug(route: &str) -> String {
+    let slug: String = route
+        .trim_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    if slug.is_empty() { "index".into() } else { slug }
+}
+
+/// Type name for a page's root: `base` for `/`, else the route in
+/// PascalCase after it (before a trailing `View`): `MagneticAppAbout`,
+/// `MagneticAppBlogPostView`
+pub fn page_type_name(base: &str, route: &str) -> String {
+    let page: String = route
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            chars.next().map(|c| c.to_ascii_uppercase()).into_iter().chain(chars).collect::<String>()
+        })
+        .collect();
+    match base.strip_suffix("View") {
+        Some(stem) => format!("{}{}View", stem, page),
+        None => format!("{}{}", base, page),
+    }
+}
+
+/// File extension for a --render mode's output
+pub fn extension(mode: &str) -> &'static str {
+    match mode {
+        "kotlin" => "kt",
+        "swift" => "swift",
+        "flutter" => "dart",
+        "react-native" | "rn" => "jsx",
+        "text" => "txt",
+        "pdf" => "pdf",
+        _ => "html",
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CodegenManifest {
+    /// Generated file name → content hash
+    files: BTreeMap<String, String>,
+}
+
+/// What a codegen run did to its output files.
+#[derive(Debug, Default)]
+pub struct WriteSummary {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl WriteSummary {
+    /// Print the summary (changed files listed individually) to stderr
+    pub fn print(&self, target: &str) {
+        eprintln!(
+            "[magnetic-v8] codegen {}: {} added, {} changed, {} unchanged, {} removed",
+            target, self.added.len(), self.changed.len(), self.unchanged.len(), self.removed.len()
+        );
+        for name in &self.added {
+            eprintln!("  + {}", name);
+        }
+        for name in &self.changed {
+            eprintln!("  ~ {}", name);
+        }
+        for name in &self.removed {
+            eprintln!("  - {}", name);
+        }
+    }
+}
+
+/// Write a set of generated files into `dir`, skipping unchanged ones and
+/// removing files the previous run generated but this one did not.
+pub fn write_dir(dir: &Path, files: &[(String, Vec<u8>)]) -> Result<WriteSummary, String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let previous: CodegenManifest = std::fs::read_to_string(&manifest_path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    let mut summary = WriteSummary::default();
+    let mut manifest = CodegenManifest::default();
+
+    for (name, content) in files {
+        let hash = content_hash(content);
+        let path = dir.join(name);
+        let old = previous.files.get(name);
+        if old == Some(&hash) && path.exists() {
+            summary.unchanged.push(name.clone());
+        } else {
+            std::fs::write(&path, content)
+                .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+            if old.is_some() {
+                summary.changed.push(name.clone());
+            } else {
+                summary.added.push(name.clone());
+            }
+        }
+        manifest.files.insert(name.clone(), hash);
+    }
+
+    for name in previous.files.keys() {
+        if manifest.files.contains_key(name) {
+            continue;
+        }
+        // Only ever delete plain file names we generated into this directory
+        if Path::new(name).file_name().map(|f| f == name.as_str()) != Some(true) {
+            continue;
+        }
+        match std::fs::remove_file(dir.join(name)) {
+            Ok(()) => summary.removed.push(name.clone()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Cannot remove {}: {}", dir.join(name).display(), e)),
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    std::fs::write(&manifest_path, json)
+        .map_err(|e| format!("Cannot write {}: {}", manifest_path.display(), e))?;
+
+    Ok(summary)
+}
+
+/// Write a single generated file unless its contents are already identical.
+pub fn write_file(path: &Path, content: &[u8]) -> Result<WriteSummary, String> {
+    let name = path.display().to_string();
+    let mut summary = WriteSummary::default();
+    match std::fs::read(path) {
+        Ok(existing) if existing == content => {
+            summary.unchanged.push(name);
+            return Ok(summary);
+        }
+        Ok(_) => summary.changed.push(name),
+        Err(_) => summary.added.push(name),
+    }
+    std::fs::write(path, content).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(summary)
+}
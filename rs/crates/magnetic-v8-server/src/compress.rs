@@ -0,0 +1,293 @@
+//! compress.rs — Accept-Encoding negotiation and response compression
+//!
+//! Text responses (SSR HTML, JSON snapshots, JS/CSS/SVG/WASM assets) are
+//! sent brotli- or gzip-encoded when the client accepts it. An SSE stream
+//! is gzip-encoded as a whole: one deflate stream per client, flushed after
+//! every event, so each snapshot is compressed against the ones before it.
+//! SSE never uses brotli — its window would cost megabytes per client.
+//!
+//! Handlers write uncompressed bodies; `encode_response` compresses them
+//! after the middleware's response hooks have run (response.rs). Embedded
+//! assets are compressed once and cached (`Precompressed`). Hashed static
+//! assets get `.br` and `.gz` files next to them at startup
+//! (`write_variants`), which are sent as they are (`read_variant`).
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::net::Shutdown;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::conn::Conn;
+use crate::response::Response;
+
+/// Bodies shorter than this are sent as-is
+const MIN_SIZE: usize = 1024;
+
+/// Brotli settings for bodies compressed per request (quality 0–11)
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_LGWIN: u32 = 22;
+
+/// Brotli quality for bodies compressed once and cached
+const BROTLI_QUALITY_MAX: u32 = 11;
+
+const VARY: &str = "Vary: Accept-Encoding\r\n";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    Identity,
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    /// Best encoding the request's Accept-Encoding allows. Brotli wins ties
+    /// with gzip unless `allow_br` is false.
+    pub fn negotiate(req_headers: &HashMap<String, String>, allow_br: bool) -> Encoding {
+        let Some(accept) = req_headers.get("accept-encoding") else {
+            return Encoding::Identity;
+        };
+        let (mut br, mut gzip, mut any) = (None, None, None);
+        for item in accept.split(',') {
+            let mut parts = item.split(';');
+            let name = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+            let q = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            match name.as_str() {
+                "br" => br = Some(q),
+                "gzip" | "x-gzip" => gzip = Some(q),
+                "*" => any = Some(q),
+                _ => {}
+            }
+        }
+        let br = br.or(any).unwrap_or(0.0);
+        let gzip = gzip.or(any).unwrap_or(0.0);
+        if allow_br && br > 0.0 && br >= gzip {
+            Encoding::Brotli
+        } else if gzip > 0.0 {
+            Encoding::Gzip
+        } else {
+            Encoding::Identity
+        }
+    }
+
+    /// Response header lines for this encoding (empty for identity)
+    pub fn headers(self) -> &'static str {
+        match self {
+            Encoding::Identity => "",
+            Encoding::Gzip => "Content-Encoding: gzip\r\nVary: Accept-Encoding\r\n",
+            Encoding::Brotli => "Content-Encoding: br\r\nVary: Accept-Encoding\r\n",
+        }
+    }
+}
+
+/// Text-like types worth compressing (images and fonts already are)
+pub fn is_compressible(content_type: &str) -> bool {
+    let ct = content_type.split(';').next().unwrap_or("").trim();
+    ct.starts_with("text/")
+        || matches!(
+            ct,
+            "application/json" | "application/javascript" | "application/wasm" | "image/svg+xml"
+        )
+}
+
+/// Compress a response body for the request if its type and size make it
+/// worthwhile (the last step of the response phase, response.rs). Bodies
+/// that already have a `Content-Encoding` are left alone.
+pub fn encode_response(resp: &mut Response, req_headers: &HashMap<String, String>) {
+    if resp.header("content-encoding").is_some() {
+        return;
+    }
+    if !resp.header("content-type").is_some_and(is_compressible) {
+        return;
+    }
+    match resp.header("vary").map(str::to_string) {
+        None => resp.headers.push(("Vary".into(), "Accept-Encoding".into())),
+        Some(v) if !v.to_ascii_lowercase().contains("accept-encoding") => {
+            resp.set_header("Vary", &format!("{}, Accept-Encoding", v));
+        }
+        Some(_) => {}
+    }
+    if resp.body.len() < MIN_SIZE {
+        return;
+    }
+    let (encoded, name) = match Encoding::negotiate(req_headers, true) {
+        Encoding::Identity => return,
+        Encoding::Gzip => (gzip(&resp.body), "gzip"),
+        Encoding::Brotli => (brotli(&resp.body, BROTLI_QUALITY), "br"),
+    };
+    match encoded {
+        Ok(data) => {
+            resp.body = data;
+            resp.headers.push(("Content-Encoding".into(), name.into()));
+        }
+        Err(e) => eprintln!("[magnetic-v8] compression failed: {}", e),
+    }
+}
+
+/// Write `<path>.br` and `<path>.gz` for an asset worth compressing, at
+/// maximum quality. Skipped when they already exist: the callers' paths
+/// carry a content hash.
+pub fn write_variants(path: &Path, content: &[u8]) {
+    let name = path.to_string_lossy();
+    if !is_compressible(crate::guess_content_type(&name)) || content.len() < MIN_SIZE {
+        return;
+    }
+    for suffix in [".br", ".gz"] {
+        let variant = with_suffix(path, suffix);
+        if variant.exists() {
+            continue;
+        }
+        let encoded = match suffix {
+            ".br" => brotli(content, BROTLI_QUALITY_MAX),
+            _ => gzip_best(content),
+        };
+        if let Err(e) = encoded.and_then(|data| std::fs::write(&variant, data)) {
+            eprintln!("[magnetic-v8] cannot write {}: {}", variant.display(), e);
+        }
+    }
+}
+
+/// The `write_variants` file for `path` that the request accepts, with its
+/// header lines: brotli if accepted, else gzip
+pub fn read_variant(path: &Path, req_headers: &HashMap<String, String>) -> Option<(Vec<u8>, &'static str)> {
+    for (allow_br, enc, suffix) in [(true, Encoding::Brotli, ".br"), (false, Encoding::Gzip, ".gz")] {
+        if Encoding::negotiate(req_headers, allow_br) == enc {
+            if let Ok(data) = std::fs::read(with_suffix(path, suffix)) {
+                return Some((data, enc.headers()));
+            }
+        }
+    }
+    None
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut p = path.as_os_str().to_owned();
+    p.push(suffix);
+    PathBuf::from(p)
+}
+
+fn gzip_best(body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut enc = GzEncoder::new(Vec::with_capacity(body.len() / 4), Compression::best());
+    enc.write_all(body)?;
+    enc.finish()
+}
+
+fn gzip(body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut enc = GzEncoder::new(Vec::with_capacity(body.len() / 4), Compression::default());
+    enc.write_all(body)?;
+    enc.finish()
+}
+
+fn brotli(body: &[u8], quality: u32) -> io::Result<Vec<u8>> {
+    let mut w = brotli::CompressorWriter::new(
+        Vec::with_capacity(body.len() / 4), 4096, quality, BROTLI_LGWIN,
+    );
+    w.write_all(body)?;
+    w.flush()?;
+    Ok(w.into_inner())
+}
+
+/// Compressed copies of a body that never changes (embedded assets),
+/// made at maximum quality on first use.
+pub struct Precompressed {
+    gzip: OnceLock<Vec<u8>>,
+    brotli: OnceLock<Vec<u8>>,
+}
+
+impl Precompressed {
+    pub const fn new() -> Self {
+        Precompressed { gzip: OnceLock::new(), brotli: OnceLock::new() }
+    }
+
+    /// Like `encode_body`, for `body` — always the same bytes for one cache.
+    pub fn encode<'a>(
+        &'a self,
+        body: &'a [u8],
+        content_type: &str,
+        req_headers: &HashMap<String, String>,
+    ) -> (&'a [u8], &'static str) {
+        if !is_compressible(content_type) {
+            return (body, "");
+        }
+        if body.len() < MIN_SIZE {
+            return (body, VARY);
+        }
+        let enc = Encoding::negotiate(req_headers, true);
+        let cached = match enc {
+            Encoding::Identity => return (body, VARY),
+            Encoding::Gzip => self.gzip.get_or_init(|| gzip(body).unwrap_or_default()),
+            Encoding::Brotli => {
+                self.brotli.get_or_init(|| brotli(body, BROTLI_QUALITY_MAX).unwrap_or_default())
+            }
+        };
+        if cached.is_empty() {
+            (body, VARY)
+        } else {
+            (cached, enc.headers())
+        }
+    }
+}
+
+/// A registered SSE client socket, gzip-encoded if the client accepted it.
+/// `flush` ends each event: for gzip it emits everything written so far
+/// (a sync flush) without ending the stream.
+pub enum SseStream {
+    Plain(Conn),
+    Gzip(GzEncoder<Conn>),
+}
+
+impl SseStream {
+    /// Encoding for an SSE response to this request
+    pub fn negotiate(req_headers: &HashMap<String, String>) -> Encoding {
+        Encoding::negotiate(req_headers, false)
+    }
+
+    /// Wrap `stream` after the response head has been written with
+    /// `enc.headers()`.
+    pub fn new(stream: Conn, enc: Encoding) -> Self {
+        match enc {
+            Encoding::Gzip => SseStream::Gzip(GzEncoder::new(stream, Compression::default())),
+            _ => SseStream::Plain(stream),
+        }
+    }
+
+    pub fn get_ref(&self) -> &Conn {
+        match self {
+            SseStream::Plain(s) => s,
+            SseStream::Gzip(g) => g.get_ref(),
+        }
+    }
+
+    /// After a failed write, close the socket so the gzip trailer written
+    /// on drop fails at once instead of waiting out the write timeout.
+    fn closed_on_err<T>(&self, r: io::Result<T>) -> io::Result<T> {
+        if r.is_err() {
+            let _ = self.get_ref().shutdown(Shutdown::Both);
+        }
+        r
+    }
+}
+
+impl Write for SseStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let r = match self {
+            SseStream::Plain(s) => s.write(buf),
+            SseStream::Gzip(g) => g.write(buf),
+        };
+        self.closed_on_err(r)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let r = match self {
+            SseStream::Plain(s) => s.flush(),
+            SseStream::Gzip(g) => g.flush(),
+        };
+        self.closed_on_err(r)
+    }
+}
@@ -0,0 +1,334 @@
+//! config.rs — Server config file (magnetic.toml or m.json)
+//!
+//! Every setting in the file corresponds to a command-line flag. The file
+//! is read at startup and turned into those flags, which go after the real
+//! command line, so a flag given on the command line wins over the file.
+//! The file is `--config <path>` if given, else `magnetic.toml` or `m.json`
+//! in the working directory if one exists. A path ending in `.json` is read
+//! as JSON; any other as TOML. Unknown keys are an error, so a typo doesn't
+//! silently fall back to a default.
+//!
+//! ```toml
+//! port = 3003
+//! bundle = "dist/app.js"
+//! build = "magnetic build"                # run before serving; on changes with --watch
+//! watch_src = ["pages", "components"]
+//! static = "public"
+//! static_index = "index.html"             # "" to turn off directory index
+//! passthrough = ["/docs"]                 # always static, never rendered
+//! trailing_slash = "remove"               # or "add"
+//! locales = ["en", "fr"]                  # Accept-Language picks; first is default
+//! uploads = "uploads"
+//! cors = "https://example.com"
+//! rate_limit = 100                        # requests per minute per client
+//! middleware = ["logger", "cors", "rate-limit"]
+//! workers = 64
+//! processes = 4                           # SO_REUSEPORT cluster (cluster.rs)
+//! process_port = 3010                     # first process's own port
+//! sse_delta = true
+//! shutdown_timeout = 10
+//! max_body_mb = 16
+//! header_timeout = 10                     # seconds
+//! conn_timeout = 120                      # seconds
+//!
+//! [v8]
+//! workers = 2
+//! heap_mb = 256
+//!
+//! [fallback]                              # client-side routes → page
+//! "/app" = "app/index.html"
+//!
+//! [seo]                                   # robots.txt and sitemap.xml
+//! site_url = "https://example.com"
+//! routes = ["/", "/about"]                # else the bundle's routes()
+//! disallow = ["/admin"]
+//!
+//! [rate_limit_routes]                     # own bucket per client, per minute
+//! "/actions" = 30
+//!
+//! [proxy]                                 # prefix → private backend
+//! "/api/billing" = "https://internal:9000"
+//! "/api/me" = { url = "https://internal:9001", credentials = true }
+//!
+//! [[redirects]]
+//! from = "/blog/:slug"
+//! to = "/posts/:slug"
+//! status = 301                            # 301, 302, 307 or 308
+//!
+//! [[rewrites]]
+//! from = "/go/*"
+//! to = "/landing/:splat"
+//!
+//! [session]
+//! secret = "…"                            # or MAGNETIC_SESSION_SECRET
+//! previous_secrets = ["…"]                # still accepted while rotating
+//!
+//! [tls]
+//! cert = "/etc/magnetic/chain.pem"
+//! key = "/etc/magnetic/key.pem"
+//!
+//! [otlp]                                  # trace export (trace.rs)
+//! endpoint = "http://collector:4318"
+//! service = "magnetic"
+//!
+//! [platform]                              # same as --platform
+//! data_dir = "data/apps"
+//! park_idle = 300
+//! secrets_key = "…"                       # or MAGNETIC_SECRETS_KEY (secrets.rs)
+//! deploy_token = "…"                      # or MAGNETIC_DEPLOY_TOKEN (deploy_tokens.rs)
+//! keep_versions = 10                      # deploys kept per app for rollback
+//!
+//! [platform.quotas]                       # per app (quotas.rs); 0 = no limit
+//! render_ms = 5000                        # per render or action
+//! sse_clients = 1000
+//! bundle_mb = 8
+//!
+//! [platform.quotas.apps.shop]             # one app's own limits
+//! heap_mb = 512
+//! render_ms = 10000
+//! ```
+//!
+//! Auth is configured per app, in the app's magnetic.json (see data.rs).
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::redirects::Rule;
+
+/// Looked for in the working directory when --config isn't given
+const DEFAULT_FILES: [&str; 2] = ["magnetic.toml", "m.json"];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    port: Option<u16>,
+    bundle: Option<String>,
+    build: Option<String>,
+    watch_src: Option<Vec<String>>,
+    #[serde(rename = "static")]
+    static_dir: Option<String>,
+    static_index: Option<String>,
+    passthrough: Option<Vec<String>>,
+    fallback: Option<BTreeMap<String, String>>,
+    redirects: Option<Vec<Rule>>,
+    rewrites: Option<Vec<Rule>>,
+    trailing_slash: Option<String>,
+    proxy: Option<BTreeMap<String, crate::proxy::Route>>,
+    locales: Option<Vec<String>>,
+    uploads: Option<String>,
+    cors: Option<String>,
+    rate_limit: Option<u32>,
+    rate_limit_routes: Option<BTreeMap<String, u32>>,
+    middleware: Option<Vec<String>>,
+    workers: Option<usize>,
+    processes: Option<usize>,
+    process_port: Option<u16>,
+    #[serde(default)]
+    sse_delta: bool,
+    shutdown_timeout: Option<u64>,
+    max_body_mb: Option<usize>,
+    header_timeout: Option<u64>,
+    conn_timeout: Option<u64>,
+    v8: Option<V8Config>,
+    session: Option<SessionConfig>,
+    tls: Option<TlsConfig>,
+    seo: Option<SeoConfig>,
+    otlp: Option<OtlpConfig>,
+    platform: Option<PlatformConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct V8Config {
+    workers: Option<usize>,
+    heap_mb: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SessionConfig {
+    secret: Option<String>,
+    previous_secrets: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TlsConfig {
+    cert: String,
+    key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SeoConfig {
+    site_url: Option<String>,
+    routes: Option<Vec<String>>,
+    disallow: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OtlpConfig {
+    endpoint: Option<String>,
+    service: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlatformConfig {
+    data_dir: Option<String>,
+    park_idle: Option<u64>,
+    secrets_key: Option<String>,
+    deploy_token: Option<String>,
+    keep_versions: Option<usize>,
+    quotas: Option<QuotasConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QuotasConfig {
+    render_ms: Option<u64>,
+    sse_clients: Option<usize>,
+    bundle_mb: Option<usize>,
+    apps: Option<BTreeMap<String, AppQuotaConfig>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AppQuotaConfig {
+    heap_mb: Option<usize>,
+    render_ms: Option<u64>,
+    sse_clients: Option<usize>,
+    bundle_mb: Option<usize>,
+}
+
+/// The command line followed by the config file's settings as flags.
+pub fn with_file(args: Vec<String>) -> Result<Vec<String>, String> {
+    let path = match crate::find_arg(&args, "--config") {
+        Some(p) => p,
+        None => match DEFAULT_FILES.iter().find(|f| std::path::Path::new(f).exists()) {
+            Some(f) => f.to_string(),
+            None => return Ok(args),
+        },
+    };
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Cannot read config {}: {}", path, e))?;
+    let config: Config = if path.ends_with(".json") {
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?
+    } else {
+        toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?
+    };
+    eprintln!("[magnetic] config: {}", path);
+    let mut args = args;
+    args.extend(config.into_flags());
+    Ok(args)
+}
+
+impl Config {
+    fn into_flags(self) -> Vec<String> {
+        let mut flags = Vec::new();
+        let mut set = |flag: &str, value: Option<String>| {
+            if let Some(v) = value {
+                flags.push(flag.to_string());
+                flags.push(v);
+            }
+        };
+        set("--port", self.port.map(|v| v.to_string()));
+        set("--bundle", self.bundle);
+        set("--build", self.build);
+        set("--watch-src", self.watch_src.map(|s| s.join(",")));
+        set("--static", self.static_dir);
+        set("--static-index", self.static_index);
+        set("--passthrough", self.passthrough.map(|p| p.join(",")));
+        set("--fallback", self.fallback.map(|f| {
+            f.iter().map(|(prefix, file)| format!("{}={}", prefix, file)).collect::<Vec<_>>().join(",")
+        }));
+        let rules = |rules: Vec<Rule>| {
+            rules.iter().map(|r| format!("{}={}={}", r.from, r.to, r.status)).collect::<Vec<_>>().join(",")
+        };
+        set("--redirect", self.redirects.map(rules));
+        set("--rewrite", self.rewrites.map(rules));
+        set("--trailing-slash", self.trailing_slash);
+        set("--proxy", self.proxy.map(|p| {
+            p.iter()
+                .map(|(prefix, route)| {
+                    let credentials = if route.credentials() { "=credentials" } else { "" };
+                    format!("{}={}{}", prefix, route.url(), credentials)
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        }));
+        set("--locales", self.locales.map(|l| l.join(",")));
+        set("--uploads", self.uploads);
+        set("--cors", self.cors);
+        set("--rate-limit", self.rate_limit.map(|v| v.to_string()));
+        set("--rate-limit-route", self.rate_limit_routes.map(|r| {
+            r.iter().map(|(prefix, n)| format!("{}={}", prefix, n)).collect::<Vec<_>>().join(",")
+        }));
+        set("--middleware", self.middleware.map(|m| m.join(",")));
+        set("--workers", self.workers.map(|v| v.to_string()));
+        set("--processes", self.processes.map(|v| v.to_string()));
+        set("--process-port", self.process_port.map(|v| v.to_string()));
+        set("--shutdown-timeout", self.shutdown_timeout.map(|v| v.to_string()));
+        set("--max-body-mb", self.max_body_mb.map(|v| v.to_string()));
+        set("--header-timeout", self.header_timeout.map(|v| v.to_string()));
+        set("--conn-timeout", self.conn_timeout.map(|v| v.to_string()));
+        if let Some(v8) = self.v8 {
+            set("--v8-workers", v8.workers.map(|v| v.to_string()));
+            set("--v8-heap-mb", v8.heap_mb.map(|v| v.to_string()));
+        }
+        if let Some(session) = self.session {
+            set("--session-secret", session.secret);
+            set("--session-secret-old", session.previous_secrets.map(|s| s.join(",")));
+        }
+        if let Some(tls) = self.tls {
+            set("--tls-cert", Some(tls.cert));
+            set("--tls-key", Some(tls.key));
+        }
+        if let Some(seo) = self.seo {
+            set("--site-url", seo.site_url);
+            set("--sitemap-routes", seo.routes.map(|r| r.join(",")));
+            set("--robots-disallow", seo.disallow.map(|d| d.join(",")));
+        }
+        if let Some(otlp) = self.otlp {
+            set("--otlp-endpoint", otlp.endpoint);
+            set("--otlp-service", otlp.service);
+        }
+        let platform = self.platform.is_some();
+        if let Some(p) = self.platform {
+            set("--data-dir", p.data_dir);
+            set("--park-idle", p.park_idle.map(|v| v.to_string()));
+            set("--secrets-key", p.secrets_key);
+            set("--deploy-token", p.deploy_token);
+            set("--keep-versions", p.keep_versions.map(|v| v.to_string()));
+            if let Some(q) = p.quotas {
+                set("--render-timeout-ms", q.render_ms.map(|v| v.to_string()));
+                set("--max-sse-clients", q.sse_clients.map(|v| v.to_string()));
+                set("--max-bundle-mb", q.bundle_mb.map(|v| v.to_string()));
+                set("--app-quota", q.apps.map(|apps| {
+                    let mut limits = Vec::new();
+                    for (app, a) in apps {
+                        let mut add = |limit: &str, value: Option<u64>| {
+                            if let Some(v) = value {
+                                limits.push(format!("{}.{}={}", app, limit, v));
+                            }
+                        };
+                        add("heap_mb", a.heap_mb.map(|v| v as u64));
+                        add("render_ms", a.render_ms);
+                        add("sse_clients", a.sse_clients.map(|v| v as u64));
+                        add("bundle_mb", a.bundle_mb.map(|v| v as u64));
+                    }
+                    limits.join(",")
+                }));
+            }
+        }
+        if platform {
+            flags.push("--platform".into());
+        }
+        if self.sse_delta {
+            flags.push("--sse-delta".into());
+        }
+        flags
+    }
+}
@@ -0,0 +1,283 @@
+//! conn.rs — Client connections: plain TCP, or TLS terminated with rustls
+//!
+//! `--tls-cert <chain.pem> --tls-key <key.pem>` makes the server speak
+//! HTTPS itself, so a single-binary deployment needs no reverse proxy.
+//! Handlers see a `Conn` either way. Clones of a TLS connection (the
+//! request reader, an SSE registry entry) share one rustls session behind
+//! a mutex. It's held to hand bytes to and from the session, never while
+//! waiting on the socket for a read, so a WebSocket's reader thread
+//! blocked on its client doesn't hold up the writes to it.
+//!
+//! A read deadline (`set_deadline`) bounds the total time of many reads,
+//! which a per-read timeout can't do for a client that trickles bytes.
+//!
+//! While capturing (`capture`), writes through a handle are buffered
+//! instead of sent, for the response phase of the middleware (response.rs).
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Ciphertext read from the socket at a time. A TLS record is at most
+/// this plus its overhead, so a read never adds more plaintext than
+/// rustls takes in one go.
+const TLS_READ_BUF: usize = 16 * 1024;
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, ServerConnection};
+
+pub struct Conn {
+    tcp: TcpStream,
+    tls: Option<Arc<Mutex<ServerConnection>>>,
+    /// Reads fail with TimedOut after this instant, and each read waits at
+    /// most the given time
+    deadline: Option<(Instant, Duration)>,
+    /// Writes go here instead of to the socket
+    capture: Option<Vec<u8>>,
+}
+
+impl Conn {
+    /// Wrap an accepted socket, starting a TLS session if `tls` is set.
+    /// The handshake runs on the first read.
+    pub fn new(tcp: TcpStream, tls: Option<&Arc<ServerConfig>>) -> io::Result<Conn> {
+        let tls = match tls {
+            Some(config) => {
+                let session = ServerConnection::new(Arc::clone(config))
+                    .map_err(io::Error::other)?;
+                Some(Arc::new(Mutex::new(session)))
+            }
+            None => None,
+        };
+        Ok(Conn { tcp, tls, deadline: None, capture: None })
+    }
+
+    /// Another handle to the same connection (and TLS session), without
+    /// this handle's deadline or capture
+    pub fn try_clone(&self) -> io::Result<Conn> {
+        Ok(Conn { tcp: self.tcp.try_clone()?, tls: self.tls.clone(), deadline: None, capture: None })
+    }
+
+    /// Buffer writes through this handle until `take_capture`
+    pub fn capture(&mut self) {
+        self.capture = Some(Vec::new());
+    }
+
+    /// Stop capturing and return what was written meanwhile
+    pub fn take_capture(&mut self) -> Option<Vec<u8>> {
+        self.capture.take()
+    }
+
+    /// Fail reads through this handle once `deadline` passes, and let each
+    /// read wait at most `idle`. Replaces the read timeout of the socket,
+    /// which all clones share.
+    pub fn set_deadline(&mut self, deadline: Instant, idle: Duration) {
+        self.deadline = Some((deadline, idle));
+    }
+
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.tcp.set_read_timeout(dur)
+    }
+
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.tcp.set_write_timeout(dur)
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.tcp.shutdown(how)
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp.peer_addr()
+    }
+
+    /// Whether the connection is over TLS
+    pub fn is_tls(&self) -> bool {
+        self.tls.is_some()
+    }
+}
+
+impl Read for Conn {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some((deadline, idle)) = self.deadline {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            self.tcp.set_read_timeout(Some(left.min(idle)))?;
+        }
+        let Some(tls) = &self.tls else { return self.tcp.read(buf) };
+        let mut raw = Vec::new();
+        loop {
+            {
+                let mut session = tls.lock().unwrap();
+                match session.reader().read(buf) {
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                    done => return done,
+                }
+                // Handshake messages to send before the peer says more
+                send_tls(&mut session, &mut self.tcp)?;
+            }
+            // Wait for the peer without the session, which writers share
+            raw.resize(TLS_READ_BUF, 0);
+            let n = self.tcp.read(&mut raw)?;
+            if n == 0 {
+                return Ok(0);
+            }
+            let mut session = tls.lock().unwrap();
+            let mut ciphertext = &raw[..n];
+            while !ciphertext.is_empty() {
+                session.read_tls(&mut ciphertext)?;
+                if let Err(e) = session.process_new_packets() {
+                    // Send the alert, if we can, before failing
+                    let _ = session.write_tls(&mut self.tcp);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+                }
+            }
+        }
+    }
+}
+
+/// Send what the TLS session has queued
+fn send_tls(session: &mut ServerConnection, tcp: &mut TcpStream) -> io::Result<()> {
+    while session.wants_write() {
+        session.write_tls(tcp)?;
+    }
+    Ok(())
+}
+
+impl Write for Conn {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(captured) = &mut self.capture {
+            captured.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+        match &self.tls {
+            None => self.tcp.write(buf),
+            Some(tls) => {
+                let mut session = tls.lock().unwrap();
+                let n = session.writer().write(buf)?;
+                send_tls(&mut session, &mut self.tcp)?;
+                Ok(n)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.capture.is_some() {
+            return Ok(());
+        }
+        match &self.tls {
+            None => self.tcp.flush(),
+            Some(tls) => {
+                let mut session = tls.lock().unwrap();
+                session.writer().flush()?;
+                send_tls(&mut session, &mut self.tcp)?;
+                self.tcp.flush()
+            }
+        }
+    }
+}
+
+/// TLS config from `--tls-cert` / `--tls-key`, or None to serve plain HTTP.
+/// Panics if only one is given or the files can't be used.
+pub fn tls_arg(args: &[String]) -> Option<Arc<ServerConfig>> {
+    match (crate::find_arg(args, "--tls-cert"), crate::find_arg(args, "--tls-key")) {
+        (None, None) => None,
+        (Some(cert), Some(key)) => Some(load_tls_config(&cert, &key).unwrap_or_else(|e| panic!("{}", e))),
+        _ => panic!("--tls-cert and --tls-key must be given together"),
+    }
+}
+
+/// Server config for a PEM certificate chain (leaf first) and private key.
+pub fn load_tls_config(cert_path: &str, key_path: &str) -> Result<Arc<ServerConfig>, String> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Cannot read TLS certificate {}: {}", cert_path, e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates in {}", cert_path));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| format!("Cannot read TLS key {}: {}", key_path, e))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("TLS setup failed: {}", e))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("Invalid TLS certificate/key: {}", e))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Test certificates (fixtures/tls): a CA, and a leaf for localhost
+    /// signed by it. Not secret.
+    fn fixture(name: &str) -> String {
+        format!("{}/fixtures/tls/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn client_config() -> Arc<rustls::ClientConfig> {
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(fixture("ca.crt")).unwrap() {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Arc::new(config)
+    }
+
+    #[test]
+    fn tls_write_while_reader_is_blocked() {
+        let config = load_tls_config(&fixture("localhost.crt"), &fixture("localhost.key")).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = thread::spawn(move || {
+            let name = "localhost".try_into().unwrap();
+            let mut session = rustls::ClientConnection::new(client_config(), name).unwrap();
+            let mut tcp = TcpStream::connect(addr).unwrap();
+            tcp.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let mut tls = rustls::Stream::new(&mut session, &mut tcp);
+            tls.write_all(b"hello").unwrap();
+            // Pushed by the server while its reader waits on us
+            let mut pushed = [0u8; 4];
+            tls.read_exact(&mut pushed).unwrap();
+            tls.write_all(b"bye").unwrap();
+            pushed
+        });
+
+        let (tcp, _) = listener.accept().unwrap();
+        let mut conn = Conn::new(tcp, Some(&config)).unwrap();
+        conn.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        let mut hello = [0u8; 5];
+        conn.read_exact(&mut hello).unwrap();
+        assert_eq!(&hello, b"hello");
+
+        // Split like a WebSocket: one handle reads, the other writes
+        let mut writer = conn.try_clone().unwrap();
+        let reader = thread::spawn(move || {
+            let mut bye = [0u8; 3];
+            conn.read_exact(&mut bye).map(|_| bye)
+        });
+        thread::sleep(Duration::from_millis(100));
+        let started = Instant::now();
+        writer.write_all(b"push").unwrap();
+        writer.flush().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1), "write waited on the blocked reader");
+
+        assert_eq!(&client.join().unwrap(), b"push");
+        assert_eq!(&reader.join().unwrap().unwrap(), b"bye");
+    }
+}
@@ -0,0 +1,121 @@
+//! cookie.rs — Signed and encrypted cookies
+//!
+//! Session ids are 128 random bits. Each session cookie carries its id
+//! with an HMAC-SHA256 signature, `<id>.<sig>`, so a client can't make up
+//! an id or alter one. Values sealed with `seal` are encrypted and
+//! authenticated with AES-256-GCM. Stateless auth sessions (auth/) use this
+//! to keep their tokens in the cookie itself.
+//!
+//! Keys come from a server secret: `--session-secret`, or the
+//! MAGNETIC_SESSION_SECRET environment variable. Secrets in
+//! `--session-secret-old` (comma-separated) are still accepted but never
+//! used for new cookies, so a secret can be rotated without logging anyone
+//! out. Without a secret, a random one is made at startup. Cookies then
+//! stop working across restarts, and each replica behind a load balancer
+//! has its own.
+
+use std::sync::OnceLock;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+
+struct Keys {
+    /// Current key first
+    sign: Vec<hmac::Key>,
+    seal: Vec<LessSafeKey>,
+}
+
+static KEYS: OnceLock<Keys> = OnceLock::new();
+
+/// Derive the cookie keys from the configured secrets. Call once at startup.
+pub fn install(args: &[String]) {
+    let mut secrets: Vec<Vec<u8>> = Vec::new();
+    match crate::find_arg(args, "--session-secret").or_else(|| std::env::var("MAGNETIC_SESSION_SECRET").ok()) {
+        Some(secret) => secrets.push(secret.into_bytes()),
+        None => {
+            eprintln!("[magnetic] no --session-secret: using a random one (cookies reset on restart)");
+            secrets.push(random_bytes::<32>().to_vec());
+        }
+    }
+    if let Some(old) = crate::find_arg(args, "--session-secret-old") {
+        secrets.extend(old.split(',').filter(|s| !s.is_empty()).map(|s| s.as_bytes().to_vec()));
+    }
+    let keys = Keys {
+        sign: secrets.iter().map(|s| hmac::Key::new(hmac::HMAC_SHA256, &derive(s, b"magnetic-cookie-sign"))).collect(),
+        seal: secrets.iter().map(|s| {
+            let key = UnboundKey::new(&AES_256_GCM, &derive(s, b"magnetic-cookie-seal")).expect("32-byte key");
+            LessSafeKey::new(key)
+        }).collect(),
+    };
+    let _ = KEYS.set(keys);
+}
+
+/// A new session id: 128 random bits as hex
+pub fn random_id() -> String {
+    random_bytes::<16>().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `value.signature`, for a cookie
+pub fn sign(value: &str) -> String {
+    let tag = hmac::sign(&keys().sign[0], value.as_bytes());
+    format!("{}.{}", value, URL_SAFE_NO_PAD.encode(tag.as_ref()))
+}
+
+/// The value of a `sign`ed cookie, if any current or old key signed it
+pub fn verify(signed: &str) -> Option<String> {
+    let (value, sig) = signed.rsplit_once('.')?;
+    let sig = URL_SAFE_NO_PAD.decode(sig).ok()?;
+    keys()
+        .sign
+        .iter()
+        .any(|key| hmac::verify(key, value.as_bytes(), &sig).is_ok())
+        .then(|| value.to_string())
+}
+
+/// Encrypt and authenticate `plain` into a cookie-safe string
+pub fn seal(plain: &[u8]) -> String {
+    let nonce = random_bytes::<NONCE_LEN>();
+    let mut buf = plain.to_vec();
+    keys().seal[0]
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut buf)
+        .expect("AES-GCM seal");
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&buf);
+    URL_SAFE_NO_PAD.encode(out)
+}
+
+/// The plaintext of a `seal`ed value, if it's intact and any current or
+/// old key sealed it
+pub fn open(sealed: &str) -> Option<Vec<u8>> {
+    let bytes = URL_SAFE_NO_PAD.decode(sealed).ok()?;
+    if bytes.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, cipher) = bytes.split_at(NONCE_LEN);
+    keys().seal.iter().find_map(|key| {
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut buf = cipher.to_vec();
+        key.open_in_place(nonce, Aad::empty(), &mut buf).ok().map(|plain| plain.to_vec())
+    })
+}
+
+fn keys() -> &'static Keys {
+    KEYS.get().expect("cookie::install not called")
+}
+
+/// A 32-byte key for one purpose, from a secret of any length
+pub fn derive(secret: &[u8], purpose: &[u8]) -> [u8; 32] {
+    let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, secret), purpose);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(tag.as_ref());
+    out
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut out = [0u8; N];
+    SystemRandom::new().fill(&mut out).expect("system RNG");
+    out
+}
@@ -0,0 +1,132 @@
+//! csrf.rs — CSRF tokens for action POSTs
+//!
+//! Double-submit tokens: the server hands out a random token with the
+//! session, as the `magnetic_csrf` cookie (SameSite=Strict). SSR pages also
+//! carry it in `<meta name="csrf-token">`. An action POST that carries a
+//! session cookie (`magnetic_sid`, or the app's auth session cookie) must
+//! repeat the token in an `X-Magnetic-CSRF` header, or it gets a 403.
+//!
+//! Another site can make the browser send the session cookie (same-site
+//! sibling subdomains even get past SameSite=Lax). It can neither read the
+//! token nor set a custom header, because the CORS middleware doesn't allow
+//! that header. The server keeps no token state, so tokens survive restarts.
+//! Requests without a session cookie act on no one's session and need no
+//! token. A WebSocket can't carry the header, so /ws checks `Origin` instead.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::conn::Conn;
+
+/// Cookie holding the token
+pub const COOKIE: &str = "magnetic_csrf";
+
+/// Request header that must repeat the token (lowercase, as parsed)
+pub const HEADER: &str = "x-magnetic-csrf";
+
+/// The request's token, and a `Set-Cookie` header line (CRLF-terminated)
+/// if it had none and a new one was made.
+pub fn token(req_headers: &HashMap<String, String>) -> (String, String) {
+    match cookie(req_headers, COOKIE) {
+        Some(t) => (t, String::new()),
+        None => {
+            let t = new_token();
+            let set = format!("Set-Cookie: {}={}; Path=/; SameSite=Strict\r\n", COOKIE, t);
+            (t, set)
+        }
+    }
+}
+
+/// False if the request carries a session cookie but not a matching token.
+/// `auth_cookie`: the app's auth session cookie, if it has auth.
+pub fn check(req_headers: &HashMap<String, String>, auth_cookie: Option<&str>) -> bool {
+    if !has_session(req_headers, auth_cookie) {
+        return true;
+    }
+    match (cookie(req_headers, COOKIE), req_headers.get(HEADER)) {
+        (Some(expected), Some(sent)) => constant_time_eq(expected.as_bytes(), sent.trim().as_bytes()),
+        _ => false,
+    }
+}
+
+/// False if a browser request from another origin (its `Origin` host isn't
+/// the `Host` it was sent to) carries a session cookie. Browsers send
+/// `Origin` on every WebSocket handshake; native clients send none.
+pub fn check_origin(req_headers: &HashMap<String, String>, auth_cookie: Option<&str>) -> bool {
+    let Some(origin) = req_headers.get("origin") else { return true };
+    if !has_session(req_headers, auth_cookie) {
+        return true;
+    }
+    let origin_host = origin.split_once("://").map(|(_, h)| h).unwrap_or(origin);
+    req_headers.get("host").is_some_and(|host| origin_host.eq_ignore_ascii_case(host))
+}
+
+/// 403 `{"error":...}`
+pub fn write_rejected(stream: &mut Conn, error: &str) -> std::io::Result<()> {
+    let body = serde_json::json!({ "error": error }).to_string();
+    let resp = format!(
+        "HTTP/1.1 403 Forbidden\r\nContent-Type: application/json\r\n\
+        Content-Length: {}\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(body.as_bytes())
+}
+
+/// Whether the request carries `magnetic_sid` or the auth session cookie
+fn has_session(req_headers: &HashMap<String, String>, auth_cookie: Option<&str>) -> bool {
+    crate::extract_session_cookie(req_headers).is_some()
+        || auth_cookie.is_some_and(|name| cookie(req_headers, name).is_some())
+}
+
+fn cookie(headers: &HashMap<String, String>, name: &str) -> Option<String> {
+    headers.get("cookie")?.split(';').find_map(|part| {
+        let (k, v) = part.trim().split_once('=')?;
+        (k == name && !v.trim().is_empty()).then(|| v.trim().to_string())
+    })
+}
+
+/// 128 random bits as hex (also names uploaded files)
+pub fn new_token() -> String {
+    crate::cookie::random_id()
+}
+
+/// Constant-time equality (also for API keys and deploy tokens)
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn no_session_cookie_needs_no_token() {
+        assert!(check(&headers(&[]), Some("magnetic_session")));
+        assert!(check(&headers(&[("cookie", "theme=dark")]), Some("magnetic_session")));
+    }
+
+    #[test]
+    fn auth_session_cookie_alone_needs_the_token() {
+        let cookie = "magnetic_session=abc; magnetic_csrf=tok";
+        assert!(!check(&headers(&[("cookie", cookie)]), Some("magnetic_session")));
+        assert!(!check(&headers(&[("cookie", cookie), (HEADER, "other")]), Some("magnetic_session")));
+        assert!(check(&headers(&[("cookie", cookie), (HEADER, "tok")]), Some("magnetic_session")));
+        // A custom cookie name
+        assert!(!check(&headers(&[("cookie", "sess=abc")]), Some("sess")));
+        // Without auth the cookie names no session
+        assert!(check(&headers(&[("cookie", cookie)]), None));
+    }
+
+    #[test]
+    fn cross_origin_websocket_with_auth_session_is_refused() {
+        let cross = headers(&[("cookie", "magnetic_session=abc"), ("origin", "https://evil.example"), ("host", "app.example")]);
+        assert!(!check_origin(&cross, Some("magnetic_session")));
+        let same = headers(&[("cookie", "magnetic_session=abc"), ("origin", "https://app.example"), ("host", "app.example")]);
+        assert!(check_origin(&same, Some("magnetic_session")));
+    }
+}
@@ -0,0 +1,77 @@
+//! csv.rs — CSV for `"type": "file"` data sources
+//!
+//! The first record names the columns; every other record becomes an object
+//! keyed by them, so a file reads as a JSON array of rows. Fields are
+//! strings, quoted (`"a, b"`, with `""` for a quote) or not, and may span
+//! lines when quoted. A missing trailing field is `""`; extra ones are
+//! dropped. Blank lines are skipped.
+
+use serde_json::{Map, Value};
+
+/// `text` as an array of row objects
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut records = records(text.strip_prefix('\u{feff}').unwrap_or(text))?.into_iter();
+    let header = match records.next() {
+        Some(header) => header,
+        None => return Ok(Value::Array(Vec::new())),
+    };
+    let rows = records
+        .map(|record| {
+            let mut row = Map::new();
+            for (i, name) in header.iter().enumerate() {
+                row.insert(name.clone(), Value::String(record.get(i).cloned().unwrap_or_default()));
+            }
+            Value::Object(row)
+        })
+        .collect();
+    Ok(Value::Array(rows))
+}
+
+fn records(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                line += 1;
+                end_record(&mut records, &mut record, &mut field);
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("unclosed quote at line {}", line));
+    }
+    end_record(&mut records, &mut record, &mut field);
+    Ok(records)
+}
+
+fn end_record(records: &mut Vec<Vec<String>>, record: &mut Vec<String>, field: &mut String) {
+    if record.is_empty() && field.is_empty() {
+        return;
+    }
+    record.push(std::mem::take(field));
+    records.push(std::mem::take(record));
+}
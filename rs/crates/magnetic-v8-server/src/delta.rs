@@ -0,0 +1,89 @@
+//! delta.rs — Keyed-patch SSE events (`--sse-delta`)
+//!
+//! With `--sse-delta`, the server keeps the last snapshot each session's
+//! clients were sent. A broadcast snapshot is diffed against it
+//! (magnetic_dom::diff) and goes to SSE clients as a `delta` event holding
+//! only the keyed patches, `[{"key":"k","node":{...}},...]`. A full
+//! `message` event goes out instead every CHECKPOINT_EVERY events, when
+//! there's no usable diff, and when the patches wouldn't be much smaller.
+//! Resume history (resume.rs) and WebSocket clients always get full
+//! snapshots.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use magnetic_dom::{diff, parse_snapshot, DomNode};
+
+/// A full snapshot at least this often
+const CHECKPOINT_EVERY: u32 = 20;
+
+pub struct Deltas {
+    enabled: bool,
+    last: Mutex<HashMap<String, Last>>,
+}
+
+struct Last {
+    root: DomNode,
+    /// Deltas sent since the last full snapshot
+    deltas: u32,
+}
+
+/// True if `--sse-delta` was given
+pub fn delta_arg(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--sse-delta")
+}
+
+impl Deltas {
+    pub fn new(enabled: bool) -> Self {
+        Deltas { enabled, last: Mutex::new(HashMap::new()) }
+    }
+
+    /// Remember a full snapshot the session's clients were sent outside a
+    /// broadcast (the initial SSE snapshot, a navigate reply).
+    pub fn reset(&self, session_id: &str, snapshot: &str) {
+        if !self.enabled {
+            return;
+        }
+        let mut last = self.last.lock().unwrap();
+        match parse_snapshot(snapshot) {
+            Ok(snap) => {
+                last.insert(session_id.to_string(), Last { root: snap.root, deltas: 0 });
+            }
+            Err(_) => {
+                last.remove(session_id);
+            }
+        }
+    }
+
+    /// Patch list for a snapshot about to be broadcast, or None to send it
+    /// whole. Either way it becomes the session's last snapshot. Sessions
+    /// that haven't been reset (no SSE client yet) always get None.
+    pub fn next(&self, session_id: &str, snapshot: &str) -> Option<String> {
+        if !self.enabled {
+            return None;
+        }
+        let mut last = self.last.lock().unwrap();
+        let prev = last.get_mut(session_id)?;
+        let Ok(snap) = parse_snapshot(snapshot) else {
+            last.remove(session_id);
+            return None;
+        };
+        let patches = if prev.deltas + 1 < CHECKPOINT_EVERY {
+            diff::diff(&prev.root, &snap.root)
+                .and_then(|p| serde_json::to_string(&p).ok())
+                .filter(|p| p.len() < snapshot.len() / 2)
+        } else {
+            None
+        };
+        prev.root = snap.root;
+        prev.deltas = if patches.is_some() { prev.deltas + 1 } else { 0 };
+        patches
+    }
+
+    /// Forget sessions without clients; they get a full snapshot next time.
+    pub fn retain(&self, has_clients: impl Fn(&str) -> bool) {
+        if self.enabled {
+            self.last.lock().unwrap().retain(|session_id, _| has_clients(session_id));
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! deploy_tokens.rs — Tokens for the platform's deploy API
+//!
+//! With `--deploy-token` (or MAGNETIC_DEPLOY_TOKEN), deploying an app and
+//! managing its secrets need `Authorization: Bearer <token>` (or
+//! `X-Api-Key`), with:
+//! - the account token itself, for any app, the token API and `/metrics`;
+//! - a token made through the token API: with an `app`, for that app
+//!   only; without one, for any app, but not for the token API.
+//!
+//! The token API, for the account token only:
+//! - `GET /api/tokens`: `[{"name", "app", "created"}]`;
+//! - `POST /api/tokens` with `{"name", "app"?}`: a new token, returned
+//!   this once as `{"name", "app", "token"}`;
+//! - `DELETE /api/tokens/<name>`: revoke one.
+//!
+//! Made tokens are kept in `<data_dir>/tokens.json` as the hex SHA-256 of
+//! each token, never the token itself. A presented token is hashed and
+//! compared in constant time. An app's own `auth.api_keys` with the
+//! `deploy` scope still work for that app. Without `--deploy-token`, deploys
+//! are open as they were, for local development, with a warning at startup.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::auth::api_keys;
+use crate::auth::store::key as hash;
+use crate::auth::Access;
+use crate::csrf::constant_time_eq;
+
+pub struct DeployTokens {
+    /// Hash of the account token; None: deploys are open
+    account: Option<String>,
+    path: String,
+    tokens: Mutex<Vec<Token>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Token {
+    name: String,
+    /// None: any app
+    app: Option<String>,
+    hash: String,
+    /// Unix seconds
+    created: u64,
+}
+
+impl DeployTokens {
+    /// The account token from `--deploy-token`, and the tokens made so far
+    pub fn from_args(args: &[String], data_dir: &str) -> Self {
+        let account = crate::find_arg(args, "--deploy-token")
+            .or_else(|| std::env::var("MAGNETIC_DEPLOY_TOKEN").ok())
+            .filter(|t| !t.is_empty())
+            .map(|t| hash(&t));
+        if account.is_none() {
+            eprintln!("[platform] no --deploy-token: anyone can deploy apps");
+        }
+        let path = format!("{}/tokens.json", data_dir);
+        let tokens = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                eprintln!("[platform] {}: {}", path, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        DeployTokens { account, path, tokens: Mutex::new(tokens) }
+    }
+
+    /// Whether a request may deploy `app`: None when deploys are open
+    pub fn access(&self, app: &str, headers: &HashMap<String, String>) -> Option<Access> {
+        let account = self.account.as_deref()?;
+        let Some(key) = api_keys::presented(headers) else { return Some(Access::Login) };
+        let key = hash(key);
+        if constant_time_eq(account.as_bytes(), key.as_bytes()) {
+            return Some(Access::Allowed);
+        }
+        let tokens = self.tokens.lock().unwrap();
+        Some(match tokens.iter().find(|t| constant_time_eq(t.hash.as_bytes(), key.as_bytes())) {
+            Some(t) if t.app.as_deref().is_none_or(|a| a == app) => Access::Allowed,
+            Some(t) => {
+                eprintln!("[platform] deploy token '{}' is not for app '{}'", t.name, app);
+                Access::Forbidden
+            }
+            None => Access::Login,
+        })
+    }
+
+    /// Whether a request may use the token API: the account token only
+    pub fn admin_access(&self, headers: &HashMap<String, String>) -> Access {
+        let Some(account) = self.account.as_deref() else { return Access::Forbidden };
+        match api_keys::presented(headers) {
+            Some(key) if constant_time_eq(account.as_bytes(), hash(key).as_bytes()) => Access::Allowed,
+            Some(_) => Access::Forbidden,
+            None => Access::Login,
+        }
+    }
+
+    /// Whether a request may read every app's metrics (metrics.rs): the
+    /// account token, or anyone without one
+    pub fn metrics_access(&self, headers: &HashMap<String, String>) -> Access {
+        if self.account.is_none() {
+            return Access::Allowed;
+        }
+        self.admin_access(headers)
+    }
+
+    /// The tokens made so far, without their hashes
+    pub fn list(&self) -> serde_json::Value {
+        let tokens = self.tokens.lock().unwrap();
+        tokens
+            .iter()
+            .map(|t| serde_json::json!({ "name": t.name, "app": t.app, "created": t.created }))
+            .collect()
+    }
+
+    /// Make a token named `name`, for `app` or any app. Returns the token.
+    pub fn create(&self, name: &str, app: Option<&str>) -> Result<String, String> {
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-') {
+            return Err("token names are letters, digits, _ and -".into());
+        }
+        if app.is_some_and(|a| a.is_empty() || a.contains('/') || a.contains("..")) {
+            return Err("invalid app name".into());
+        }
+        let mut tokens = self.tokens.lock().unwrap();
+        if tokens.iter().any(|t| t.name == name) {
+            return Err(format!("token '{}' already exists", name));
+        }
+        let token = format!("{}{}", crate::cookie::random_id(), crate::cookie::random_id());
+        let created = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        tokens.push(Token { name: name.to_string(), app: app.map(String::from), hash: hash(&token), created });
+        self.save(&tokens)?;
+        Ok(token)
+    }
+
+    /// Revoke the token named `name`. False if there's none.
+    pub fn revoke(&self, name: &str) -> Result<bool, String> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let before = tokens.len();
+        tokens.retain(|t| t.name != name);
+        if tokens.len() == before {
+            return Ok(false);
+        }
+        self.save(&tokens)?;
+        Ok(true)
+    }
+
+    fn save(&self, tokens: &[Token]) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(tokens).map_err(|e| e.to_string())?;
+        let tmp = format!("{}.tmp", self.path);
+        std::fs::write(&tmp, json).map_err(|e| format!("write tokens: {}", e))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| format!("write tokens: {}", e))
+    }
+}
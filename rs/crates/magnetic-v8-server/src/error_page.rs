@@ -0,0 +1,68 @@
+//! error_page.rs — App-defined error pages
+//!
+//! An SSR page that fails to render is sent as a 500, and a request for a
+//! missing file that accepts HTML as a 404, each with an error page. Its
+//! content comes from the first of:
+//!
+//! - `renderError(status, message, locale)` exported by the bundle,
+//!   returning a DomNode like `render` does
+//! - `error.html` in the static dir, as the page body
+//! - the built-in `error_fallback` div
+//!
+//! The content then goes through `render_page` like any SSR page, so it has
+//! the app's CSS and client runtime.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use magnetic_dom::DomNode;
+
+use crate::isolates::V8Pool;
+use crate::{error_fallback, Reply, V8Request, V8Result};
+
+pub const ERROR_FILE: &str = "error.html";
+
+/// Content of the error page for `status`. `pool` is None for apps without
+/// a bundle.
+pub fn dom(pool: Option<&V8Pool>, static_dir: &str, status: u16, message: &str, locale: Option<String>) -> DomNode {
+    if let Some(pool) = pool {
+        let reply = Reply::new();
+        let req = V8Request::RenderError { status, message: message.to_string(), locale, reply: reply.clone() };
+        if pool.send(req).is_ok() {
+            match reply.recv() {
+                V8Result::Ok(json) => match serde_json::from_str::<Option<DomNode>>(&json) {
+                    Ok(Some(dom)) => return dom,
+                    // No renderError export
+                    Ok(None) => {}
+                    Err(e) => eprintln!("[magnetic] renderError parse error: {}", e),
+                },
+                V8Result::Err(e) => eprintln!("[magnetic] renderError error: {}", e),
+            }
+        }
+    }
+    if let Ok(html) = std::fs::read_to_string(Path::new(static_dir).join(ERROR_FILE)) {
+        return DomNode {
+            tag: "div".into(),
+            key: Some("error-page".into()),
+            attrs: Some(HashMap::from([("class".into(), "magnetic-error".into())])),
+            events: None,
+            text: None,
+            html: Some(html),
+            children: None,
+        };
+    }
+    error_fallback(message, None)
+}
+
+/// Send a rendered error page. `headers` are extra header lines. Never
+/// cached, since the next request may succeed.
+pub fn write(stream: &mut impl Write, status: u16, page: &str, headers: &str) -> io::Result<()> {
+    let resp = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/html; charset=utf-8\r\n\
+        Content-Length: {}\r\nCache-Control: no-store\r\n{}\r\n",
+        status, crate::status_text(status), page.len(), headers
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(page.as_bytes())
+}
@@ -0,0 +1,58 @@
+//! health.rs — Liveness and readiness probes
+//!
+//! `GET /healthz` answers 200 while the process is serving. `GET /readyz`
+//! answers 200 only while traffic should be sent here. It answers 503 once
+//! shutdown has begun (shutdown.rs), or when an isolate fails a ping. An
+//! isolate fails if it can't answer within READY_TIMEOUT (it's stuck or
+//! gone), or if its bundle failed to load. Both answer HEAD as well.
+//!
+//! In `--platform` mode, `/readyz` covers the process only. A bad deploy of
+//! one app shouldn't take every app off the load balancer. Each app has its
+//! own `GET /api/apps/<name>/readyz`, which pings the app's isolates. An app
+//! is registered only after its data layer's initial fetch, so that fetch
+//! is done whenever the app exists.
+//!
+//! Probes skip the middleware, so they are neither logged nor rate-limited.
+
+use std::io::Write;
+use std::time::Duration;
+
+use crate::conn::Conn;
+
+/// How long an isolate gets to answer a readiness ping
+pub const READY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 200 `{"ok":true}`
+pub fn write_healthz(stream: &mut Conn) -> std::io::Result<()> {
+    write_json(stream, "200 OK", "{\"ok\":true}")
+}
+
+/// 200 `{"ready":true}`, or 503 with the reason
+pub fn write_readyz(stream: &mut Conn, ready: Result<(), String>) -> std::io::Result<()> {
+    match ready {
+        Ok(()) => write_json(stream, "200 OK", "{\"ready\":true}"),
+        Err(reason) => {
+            let body = serde_json::json!({ "ready": false, "reason": reason }).to_string();
+            write_json(stream, "503 Service Unavailable", &body)
+        }
+    }
+}
+
+/// Err while shutting down
+pub fn accepting() -> Result<(), String> {
+    if crate::shutdown::is_stopping() {
+        Err("shutting down".into())
+    } else {
+        Ok(())
+    }
+}
+
+fn write_json(stream: &mut Conn, status: &str, body: &str) -> std::io::Result<()> {
+    let resp = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nCache-Control: no-store\r\n\
+        Content-Length: {}\r\n\r\n",
+        status, body.len()
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(body.as_bytes())
+}
@@ -0,0 +1,164 @@
+//! idempotency.rs — Idempotency-Key for action POSTs
+//!
+//! An action POST may carry an `Idempotency-Key` header. The server keeps
+//! each session's recent keys with the snapshot the action produced. A
+//! retry with the same key gets that snapshot back, marked
+//! `Idempotent-Replayed: true`, and the action doesn't run again, so a
+//! double-submitted form or payment happens once. Keys last KEY_TTL, up to
+//! KEYS_PER_SESSION per session.
+//!
+//! A retry that arrives while the first request is still running gets 409.
+//! A key reused with a different action or body gets 422. If the action
+//! doesn't finish (e.g. no V8 thread), the key is forgotten, so a retry
+//! runs it.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::conn::Conn;
+
+/// How long a key is remembered
+const KEY_TTL: Duration = Duration::from_secs(600);
+
+/// Keys remembered per session (oldest dropped first)
+const KEYS_PER_SESSION: usize = 64;
+
+/// Longest key accepted
+const MAX_KEY_LEN: usize = 255;
+
+pub struct Keys {
+    sessions: Mutex<HashMap<String, VecDeque<Entry>>>,
+}
+
+struct Entry {
+    key: String,
+    /// Hash of action and body, to catch a key reused for another request
+    fingerprint: String,
+    at: Instant,
+    /// None while the first request is running
+    snapshot: Option<Arc<str>>,
+}
+
+pub enum Outcome<'a> {
+    /// First use of the key: run the action, then `Pending::complete`
+    Run(Pending<'a>),
+    /// A retry: answer with the first request's snapshot
+    Replay(Arc<str>),
+    /// 409: the first request is still running
+    InProgress,
+    /// 422: the key was used for a different request
+    Mismatch,
+}
+
+/// A running action's claim on its key. Dropped without `complete`, it
+/// releases the key.
+pub struct Pending<'a> {
+    keys: &'a Keys,
+    session_id: String,
+    key: String,
+    done: bool,
+}
+
+impl Keys {
+    pub fn new() -> Self {
+        Keys { sessions: Mutex::new(HashMap::new()) }
+    }
+
+    /// None if the request has no usable `Idempotency-Key`
+    pub fn begin(
+        &self,
+        session_id: &str,
+        req_headers: &HashMap<String, String>,
+        action: &str,
+        body: &[u8],
+    ) -> Option<Outcome<'_>> {
+        let key = req_headers.get("idempotency-key")?.trim();
+        if key.is_empty() || key.len() > MAX_KEY_LEN {
+            return None;
+        }
+        let mut input = action.as_bytes().to_vec();
+        input.push(0);
+        input.extend_from_slice(body);
+        let fingerprint = crate::content_hash(&input);
+
+        let mut sessions = self.sessions.lock().unwrap();
+        let entries = sessions.entry(session_id.to_string()).or_default();
+        entries.retain(|e| e.at.elapsed() < KEY_TTL);
+        if let Some(e) = entries.iter().find(|e| e.key == key) {
+            return Some(if e.fingerprint != fingerprint {
+                Outcome::Mismatch
+            } else {
+                match &e.snapshot {
+                    Some(snapshot) => Outcome::Replay(snapshot.clone()),
+                    None => Outcome::InProgress,
+                }
+            });
+        }
+        if entries.len() == KEYS_PER_SESSION {
+            entries.pop_front();
+        }
+        entries.push_back(Entry {
+            key: key.to_string(),
+            fingerprint,
+            at: Instant::now(),
+            snapshot: None,
+        });
+        Some(Outcome::Run(Pending {
+            keys: self,
+            session_id: session_id.to_string(),
+            key: key.to_string(),
+            done: false,
+        }))
+    }
+
+    /// Run every keepalive interval: forget expired keys
+    pub fn sweep(&self) {
+        self.sessions.lock().unwrap().retain(|_, entries| {
+            entries.retain(|e| e.at.elapsed() < KEY_TTL);
+            !entries.is_empty()
+        });
+    }
+}
+
+impl Pending<'_> {
+    /// Record the action's snapshot for retries
+    pub fn complete(mut self, snapshot: &str) {
+        self.done = true;
+        let mut sessions = self.keys.sessions.lock().unwrap();
+        let entry = sessions
+            .get_mut(&self.session_id)
+            .and_then(|entries| entries.iter_mut().find(|e| e.key == self.key));
+        if let Some(e) = entry {
+            e.snapshot = Some(Arc::from(snapshot));
+        }
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        if let Some(entries) = self.keys.sessions.lock().unwrap().get_mut(&self.session_id) {
+            entries.retain(|e| e.key != self.key || e.snapshot.is_some());
+        }
+    }
+}
+
+/// 409 or 422 for an `Outcome` that doesn't run or replay
+pub fn write_refused(stream: &mut Conn, outcome: &Outcome) -> std::io::Result<()> {
+    let (status, error) = match outcome {
+        Outcome::InProgress => ("409 Conflict", "A request with this Idempotency-Key is in progress"),
+        _ => ("422 Unprocessable Entity", "Idempotency-Key was used for a different request"),
+    };
+    let body = serde_json::json!({ "error": error }).to_string();
+    let resp = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\n\
+        Content-Length: {}\r\n\r\n",
+        status, body.len()
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(body.as_bytes())
+}
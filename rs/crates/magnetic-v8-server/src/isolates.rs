@@ -0,0 +1,303 @@
+//! isolates.rs — A pool of V8 isolates per app (`--v8-workers N`)
+//!
+//! Each isolate runs the bundle on its own thread (`v8_thread`), so one slow
+//! render only holds up the sessions routed to the same isolate. Requests
+//! for a session always go to the same isolate, which holds that session's
+//! state. API calls go to the isolates in turn. `SetData` and
+//! `CleanupSessions` go to every isolate; the caller's reply comes from the
+//! first one. A request sent after them reaches its isolate after them,
+//! because each isolate handles its queue in order.
+//!
+//! Isolates share nothing, so an app whose reducers or API routes keep
+//! state across sessions in JS must run with a single isolate (the
+//! default).
+//!
+//! Every isolate has a heap limit (`--v8-heap-mb`, LimitedIsolate). Near
+//! it, the running script is terminated instead of V8 aborting the whole
+//! process, and `v8_thread` replaces the isolate. It may also have a time
+//! limit for renders and actions (IsolateLimits, quotas.rs): a Watchdog
+//! terminates a script that runs past it, and the isolate goes on.
+
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::c_void;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::snapshot::Bundle;
+use crate::stats::V8Stats;
+use crate::{v8_thread, Reply, V8Request, V8Result};
+
+/// Isolates when --v8-workers isn't given
+pub const DEFAULT_V8_WORKERS: usize = 1;
+
+/// Heap limit per isolate when --v8-heap-mb isn't given
+pub const DEFAULT_HEAP_MB: usize = 256;
+
+/// What each isolate may use
+#[derive(Clone, Copy)]
+pub struct IsolateLimits {
+    pub heap_mb: usize,
+    /// Longest a render or action may run; None: no limit
+    pub run_time: Option<Duration>,
+}
+
+/// Senders to every isolate of one app. Clones share the isolates.
+#[derive(Clone)]
+pub struct V8Pool {
+    workers: Arc<[mpsc::Sender<V8Request>]>,
+    /// Next isolate for a request without a session
+    next: Arc<AtomicUsize>,
+    /// Queue depth and render times, for /api/stats (stats.rs)
+    stats: Arc<V8Stats>,
+}
+
+impl V8Pool {
+    /// Start `n` isolates (at least one) running `bundle`, each within
+    /// `limits`. Errors they answer with go to `app`'s log (logs.rs); their
+    /// queue and render times to `stats`, which can outlive the pool.
+    pub fn start(bundle: Arc<Bundle>, n: usize, limits: IsolateLimits, app: Option<&str>, stats: Arc<V8Stats>) -> Self {
+        let app: Option<Arc<str>> = app.map(Arc::from);
+        let workers: Vec<_> = (0..n.max(1))
+            .map(|i| {
+                let (tx, rx) = mpsc::channel();
+                let bundle = Arc::clone(&bundle);
+                let stats = Arc::clone(&stats);
+                let app = app.clone();
+                thread::Builder::new()
+                    .name(format!("v8-{}", i))
+                    .spawn(move || v8_thread(bundle, rx, limits, app, stats))
+                    .expect("Failed to spawn V8 thread");
+                tx
+            })
+            .collect();
+        V8Pool { workers: workers.into(), next: Arc::new(AtomicUsize::new(0)), stats }
+    }
+
+    /// This app's `v8` entry in /api/stats
+    pub fn stats_json(&self) -> serde_json::Value {
+        self.stats.json(self.workers.len())
+    }
+
+    /// Route `req` to its isolate(s). `SetData`, `CleanupSessions` and
+    /// `Reload` go to all of them. Fails like `mpsc::Sender::send` if
+    /// the isolate it needed has stopped.
+    #[allow(clippy::result_large_err)] // same error as mpsc::Sender::send
+    pub fn send(&self, req: V8Request) -> Result<(), mpsc::SendError<V8Request>> {
+        let to_all = matches!(
+            req,
+            V8Request::SetData { .. } | V8Request::CleanupSessions { .. } | V8Request::Reload { .. }
+        );
+        self.stats.queued(if to_all { self.workers.len() } else { 1 });
+        if self.workers.len() == 1 {
+            return self.workers[0].send(req);
+        }
+        match req {
+            V8Request::SetData { json, reply } => {
+                for w in &self.workers[1..] {
+                    let _ = w.send(V8Request::SetData { json: json.clone(), reply: Reply::new() });
+                }
+                self.workers[0].send(V8Request::SetData { json, reply })
+            }
+            V8Request::CleanupSessions { max_age_ms, reply } => {
+                for w in &self.workers[1..] {
+                    let _ = w.send(V8Request::CleanupSessions { max_age_ms, reply: Reply::new() });
+                }
+                self.workers[0].send(V8Request::CleanupSessions { max_age_ms, reply })
+            }
+            V8Request::Reload { bundle } => {
+                for w in &self.workers[1..] {
+                    let _ = w.send(V8Request::Reload { bundle: Arc::clone(&bundle) });
+                }
+                self.workers[0].send(V8Request::Reload { bundle })
+            }
+            V8Request::Ping { .. } => self.workers[0].send(req),
+            V8Request::ApiCall { .. } | V8Request::RenderError { .. } | V8Request::Routes { .. } => {
+                let i = self.next.fetch_add(1, Ordering::Relaxed) % self.workers.len();
+                self.workers[i].send(req)
+            }
+            V8Request::Render { ref session_id, .. }
+            | V8Request::Reduce { ref session_id, .. }
+            | V8Request::RenderWithData { ref session_id, .. }
+            | V8Request::RenderWithCSS { ref session_id, .. }
+            | V8Request::RenderWithDataAndCSS { ref session_id, .. }
+            | V8Request::DropSession { ref session_id } => {
+                let i = self.affinity(session_id);
+                self.workers[i].send(req)
+            }
+        }
+    }
+
+    /// Ping every isolate. Err if one has stopped, doesn't answer within
+    /// `timeout`, or failed to load the bundle.
+    pub fn ping(&self, timeout: Duration) -> Result<(), String> {
+        let deadline = Instant::now() + timeout;
+        let mut replies = Vec::with_capacity(self.workers.len());
+        for w in self.workers.iter() {
+            let reply = Reply::new();
+            w.send(V8Request::Ping { reply: reply.clone() })
+                .map_err(|_| "V8 thread stopped".to_string())?;
+            replies.push(reply);
+        }
+        for reply in replies {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if let V8Result::Err(e) = reply.recv_timeout(left) {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders and actions terminated for running past the time limit
+    pub fn timeouts(&self) -> usize {
+        self.stats.timeouts()
+    }
+
+    /// The isolate that holds a session's state
+    fn affinity(&self, session_id: &str) -> usize {
+        let mut h = DefaultHasher::new();
+        session_id.hash(&mut h);
+        (h.finish() % self.workers.len() as u64) as usize
+    }
+}
+
+/// An isolate with a heap limit. Near the limit, the script it's running
+/// is terminated and `heap_exhausted` turns true; the isolate should then
+/// be dropped.
+pub struct LimitedIsolate {
+    // Declared first so it's dropped before the guard its callback points to
+    pub isolate: v8::OwnedIsolate,
+    guard: Box<HeapGuard>,
+}
+
+struct HeapGuard {
+    handle: v8::IsolateHandle,
+    exhausted: AtomicBool,
+}
+
+impl LimitedIsolate {
+    /// `snapshot` is a startup snapshot (snapshot.rs) to boot from.
+    pub fn new(heap_mb: usize, snapshot: Option<&[u8]>) -> Self {
+        let mut params = v8::CreateParams::default().heap_limits(0, heap_mb << 20);
+        if let Some(blob) = snapshot {
+            params = params.snapshot_blob(blob.to_vec());
+        }
+        let mut isolate = v8::Isolate::new(params);
+        let guard = Box::new(HeapGuard {
+            handle: isolate.thread_safe_handle(),
+            exhausted: AtomicBool::new(false),
+        });
+        let data = &*guard as *const HeapGuard as *mut c_void;
+        isolate.add_near_heap_limit_callback(near_heap_limit, data);
+        LimitedIsolate { isolate, guard }
+    }
+
+    pub fn heap_exhausted(&self) -> bool {
+        self.guard.exhausted.load(Ordering::Acquire)
+    }
+
+    /// A watchdog that can terminate this isolate's script
+    pub fn watchdog(&self) -> Watchdog {
+        Watchdog::new(self.guard.handle.clone())
+    }
+}
+
+/// Terminates an isolate's script once it runs past a deadline, from a
+/// thread of its own. Dropping it stops that thread.
+pub struct Watchdog {
+    shared: Arc<(Mutex<Watch>, Condvar)>,
+    handle: v8::IsolateHandle,
+}
+
+#[derive(Default)]
+struct Watch {
+    deadline: Option<Instant>,
+    fired: bool,
+    stop: bool,
+}
+
+impl Watchdog {
+    fn new(handle: v8::IsolateHandle) -> Self {
+        let shared = Arc::new((Mutex::new(Watch::default()), Condvar::new()));
+        let watched = Arc::clone(&shared);
+        let target = handle.clone();
+        thread::Builder::new()
+            .name("v8-watchdog".into())
+            .spawn(move || {
+                let (lock, wake) = &*watched;
+                let mut watch = lock.lock().unwrap();
+                while !watch.stop {
+                    watch = match watch.deadline {
+                        None => wake.wait(watch).unwrap(),
+                        Some(deadline) if Instant::now() >= deadline => {
+                            target.terminate_execution();
+                            watch.deadline = None;
+                            watch.fired = true;
+                            continue;
+                        }
+                        Some(deadline) => {
+                            let left = deadline.saturating_duration_since(Instant::now());
+                            wake.wait_timeout(watch, left).unwrap().0
+                        }
+                    };
+                }
+            })
+            .expect("Failed to spawn V8 watchdog thread");
+        Watchdog { shared, handle }
+    }
+
+    /// Terminate the script if it's still running after `limit`
+    pub fn arm(&self, limit: Duration) {
+        let (lock, wake) = &*self.shared;
+        let mut watch = lock.lock().unwrap();
+        watch.deadline = Some(Instant::now() + limit);
+        watch.fired = false;
+        wake.notify_one();
+    }
+
+    /// Stop watching. True if the script was terminated, in which case the
+    /// isolate can run scripts again.
+    pub fn disarm(&self) -> bool {
+        let mut watch = self.shared.0.lock().unwrap();
+        watch.deadline = None;
+        let fired = std::mem::take(&mut watch.fired);
+        if fired {
+            self.handle.cancel_terminate_execution();
+        }
+        fired
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        let (lock, wake) = &*self.shared;
+        lock.lock().unwrap().stop = true;
+        wake.notify_one();
+    }
+}
+
+/// Called by V8 on the isolate's thread as the heap nears its limit
+extern "C" fn near_heap_limit(data: *mut c_void, current_heap_limit: usize, _initial: usize) -> usize {
+    // SAFETY: `data` is the LimitedIsolate's boxed guard, which outlives the isolate
+    let guard = unsafe { &*(data as *const HeapGuard) };
+    guard.exhausted.store(true, Ordering::Release);
+    guard.handle.terminate_execution();
+    // Room for the terminated script to unwind; the isolate is replaced after
+    current_heap_limit * 2
+}
+
+/// Heap limit per isolate from `--v8-heap-mb`, or DEFAULT_HEAP_MB.
+pub fn v8_heap_arg(args: &[String]) -> usize {
+    crate::find_arg(args, "--v8-heap-mb")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_HEAP_MB)
+}
+
+/// Isolate count from `--v8-workers`, or DEFAULT_V8_WORKERS.
+pub fn v8_workers_arg(args: &[String]) -> usize {
+    crate::find_arg(args, "--v8-workers")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_V8_WORKERS)
+}
@@ -0,0 +1,88 @@
+//! limits.rs — Request size and time limits
+//!
+//! A client must not be able to hold a worker forever, or make the server
+//! allocate whatever it claims. Three limits apply to every connection:
+//!
+//! - `--max-body-mb` (default 16): a larger `Content-Length` gets 413 and
+//!   is never read. The request line and headers are capped at
+//!   MAX_HEAD_BYTES (431).
+//! - `--header-timeout` secs (default 10): the request line and headers
+//!   must arrive within this time, however slowly the bytes trickle in.
+//! - `--conn-timeout` secs (default 120): a deadline for the whole
+//!   connection. Bodies must be read before it passes, and a kept-alive
+//!   connection closes after the response that crosses it.
+//!
+//! Bodies are framed by `Content-Length` alone. A request with
+//! `Transfer-Encoding` gets 501, and one with a bad or repeated
+//! `Content-Length` 400; both close the connection, so a body the server
+//! didn't read can't be taken for the next request.
+//!
+//! Each read also waits at most KEEP_ALIVE_IDLE. SSE streams and
+//! WebSockets leave the request loop once they are set up, so these limits
+//! don't apply to them.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::conn::Conn;
+
+pub const DEFAULT_MAX_BODY_MB: usize = 16;
+pub const DEFAULT_HEADER_TIMEOUT_SECS: u64 = 10;
+pub const DEFAULT_CONN_TIMEOUT_SECS: u64 = 120;
+
+/// Request line plus headers
+pub const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+#[derive(Clone, Copy)]
+pub struct Limits {
+    pub max_body: usize,
+    pub header_timeout: Duration,
+    pub conn_timeout: Duration,
+}
+
+/// Limits from `--max-body-mb`, `--header-timeout` and `--conn-timeout`
+pub fn limits_arg(args: &[String]) -> Limits {
+    let num = |flag: &str, default: u64| {
+        crate::find_arg(args, flag).and_then(|s| s.parse().ok()).unwrap_or(default)
+    };
+    Limits {
+        max_body: num("--max-body-mb", DEFAULT_MAX_BODY_MB as u64) as usize * 1024 * 1024,
+        header_timeout: Duration::from_secs(num("--header-timeout", DEFAULT_HEADER_TIMEOUT_SECS)),
+        conn_timeout: Duration::from_secs(num("--conn-timeout", DEFAULT_CONN_TIMEOUT_SECS)),
+    }
+}
+
+/// A request over a limit, or one whose body can't be framed safely (400,
+/// 501). `read_request` returns it as an io::Error, and `serve_connection`
+/// answers with the status and closes the connection.
+#[derive(Debug)]
+pub struct Exceeded(pub u16);
+
+impl fmt::Display for Exceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "request over limit ({})", self.0)
+    }
+}
+
+impl std::error::Error for Exceeded {}
+
+pub fn exceeded(status: u16) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, Exceeded(status))
+}
+
+/// Answer an `Exceeded` error. Ok(false) for any other error.
+pub fn write_exceeded(stream: &mut Conn, err: &io::Error) -> io::Result<bool> {
+    let Some(Exceeded(status)) = err.get_ref().and_then(|e| e.downcast_ref::<Exceeded>()) else {
+        return Ok(false);
+    };
+    let reason = match status {
+        400 => "Bad Request",
+        413 => "Payload Too Large",
+        501 => "Not Implemented",
+        _ => "Request Header Fields Too Large",
+    };
+    let resp = format!("HTTP/1.1 {} {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status, reason);
+    stream.write_all(resp.as_bytes())?;
+    Ok(true)
+}
@@ -0,0 +1,102 @@
+//! locale.rs — Accept-Language negotiation
+//!
+//! `--locales en,fr,de` lists the locales the app has, the first being the
+//! default. Page loads, SSE/WebSocket connects and action POSTs pick the one
+//! that best matches the request's `Accept-Language` and remember it for the
+//! session. Renders then pass it to the bundle as a third argument,
+//! `render(path, sid, locale)` (and `renderWithCSS`, `renderError`), reduce
+//! gets it as the action's `locale` field, and SSR pages set
+//! `<html lang>` to it.
+//!
+//! Languages are tried in q-value order. One matches a locale with the same
+//! tag or, failing that, the same primary language (`fr-CA` → `fr`,
+//! `de` → `de-AT`). `*`, no match and no header all get the default.
+//! Without `--locales` there is no negotiation: the locale is `null` and
+//! pages are `lang="en"`.
+//!
+//! In `--platform` mode each app lists its own under `locales` in its
+//! magnetic.json config: `["en", "fr"]`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+/// Locales an app declares, default first
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Locales(Vec<String>);
+
+/// Locales from `--locales en,fr,...`
+pub fn locales_arg(args: &[String]) -> Locales {
+    Locales(
+        crate::find_arg(args, "--locales")
+            .map(|s| s.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect())
+            .unwrap_or_default(),
+    )
+}
+
+impl Locales {
+    /// The locale for a request, None if the app declares none
+    pub fn negotiate(&self, req_headers: &HashMap<String, String>) -> Option<String> {
+        let default = self.0.first()?;
+        let mut ranges: Vec<(&str, f32)> = req_headers
+            .get("accept-language")
+            .map(|h| h.split(',').filter_map(parse_range).collect())
+            .unwrap_or_default();
+        // Stable, so equal weights keep the client's order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let found = ranges.iter().find_map(|&(tag, _)| {
+            if tag == "*" {
+                return Some(default);
+            }
+            self.0.iter().find(|l| l.eq_ignore_ascii_case(tag)).or_else(|| {
+                self.0.iter().find(|l| primary(l).eq_ignore_ascii_case(primary(tag)))
+            })
+        });
+        Some(found.unwrap_or(default).clone())
+    }
+}
+
+/// `fr-CA;q=0.8` → ("fr-CA", 0.8); None for q=0 and empty entries
+fn parse_range(range: &str) -> Option<(&str, f32)> {
+    let mut parts = range.split(';');
+    let tag = parts.next()?.trim();
+    let q = parts
+        .find_map(|p| p.trim().strip_prefix("q="))
+        .map(|q| q.trim().parse::<f32>().unwrap_or(0.0))
+        .unwrap_or(1.0);
+    (!tag.is_empty() && q > 0.0).then_some((tag, q))
+}
+
+fn primary(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or(tag)
+}
+
+/// Each session's negotiated locale: session_id → locale
+#[derive(Default)]
+pub struct Sessions(Mutex<HashMap<String, String>>);
+
+impl Sessions {
+    pub fn set(&self, session_id: &str, locale: Option<String>) {
+        let mut map = self.0.lock().unwrap();
+        match locale {
+            Some(locale) => map.insert(session_id.to_string(), locale),
+            None => map.remove(session_id),
+        };
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<String> {
+        self.0.lock().unwrap().get(session_id).cloned()
+    }
+
+    pub fn remove(&self, session_id: &str) {
+        self.0.lock().unwrap().remove(session_id);
+    }
+
+    /// Take over `other`'s sessions (an app's new version, on deploy)
+    pub fn copy_from(&self, other: &Sessions) {
+        let theirs = other.0.lock().unwrap().clone();
+        self.0.lock().unwrap().extend(theirs);
+    }
+}
@@ -0,0 +1,181 @@
+//! logs.rs — Each app's own log, for its owner
+//!
+//! In `--platform` mode, the server's log lines about an app (deploys,
+//! failed renders, data source and action errors) and every error its
+//! isolates answer with are kept per app: the last RING_LINES in memory,
+//! and in `<data_dir>/<name>/logs.jsonl`, one `{"ts", "level", "msg"}` per
+//! line, so they survive a restart. The file is appended to and rewritten
+//! from memory once it has RING_LINES more, so it stays under twice that.
+//! Secret values are masked (secrets.rs).
+//!
+//! `GET /api/apps/<name>/logs?tail=200` returns the last `tail` lines
+//! (default DEFAULT_TAIL), oldest first. With `&follow=sse` the response is
+//! an event stream instead: those lines, then each new one as it's logged,
+//! as `event: log`. Both take the same token or key as deploys.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::compress::SseStream;
+use crate::outbox::Client;
+use crate::write_sse_named;
+
+/// Lines kept per app
+pub const RING_LINES: usize = 1000;
+/// Lines a request gets without `tail`
+pub const DEFAULT_TAIL: usize = 200;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    Error,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Line {
+    /// Unix ms
+    ts: u64,
+    level: Level,
+    msg: String,
+}
+
+struct Logs {
+    data_dir: String,
+    apps: Mutex<HashMap<String, AppLog>>,
+}
+
+struct AppLog {
+    lines: VecDeque<Line>,
+    /// Lines appended to the file since it was last rewritten
+    appended: usize,
+    /// `follow=sse` streams
+    followers: Vec<Client>,
+}
+
+static LOGS: OnceLock<Logs> = OnceLock::new();
+
+/// Keep app logs under `data_dir`. Without it (not `--platform`), app
+/// lines only go to stderr.
+pub fn install(data_dir: &str) {
+    let _ = LOGS.set(Logs { data_dir: data_dir.to_string(), apps: Mutex::new(HashMap::new()) });
+}
+
+/// `[platform:<app>] msg` to stderr, and into the app's log
+pub fn info(app: &str, msg: &str) {
+    eprintln!("[platform:{}] {}", app, msg);
+    record(app, Level::Info, msg);
+}
+
+/// `info`, as an error
+pub fn error(app: &str, msg: &str) {
+    eprintln!("[platform:{}] {}", app, msg);
+    record(app, Level::Error, msg);
+}
+
+/// Into the app's log only, for a line printed elsewhere
+pub fn record(app: &str, level: Level, msg: &str) {
+    let Some(logs) = LOGS.get() else { return };
+    let line = Line { ts: unix_ms(), level, msg: crate::secrets::mask(msg) };
+    let Ok(json) = serde_json::to_string(&line) else { return };
+    let mut apps = logs.apps.lock().unwrap();
+    let log = logs.open(&mut apps, app);
+    let event: Arc<str> = json.as_str().into();
+    log.followers.retain(|c| c.send_event("log", &event).is_ok());
+    log.lines.push_back(line);
+    if log.lines.len() > RING_LINES {
+        log.lines.pop_front();
+    }
+    let path = logs.path(app);
+    log.appended += 1;
+    let written = if log.appended >= RING_LINES {
+        log.appended = 0;
+        rewrite(&path, &log.lines)
+    } else {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut f| writeln!(f, "{}", json))
+    };
+    match written {
+        // No app dir: nothing deployed under that name
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => eprintln!("[platform:{}] log file: {}", app, e),
+        Ok(()) => {}
+    }
+}
+
+/// The app's last `tail` lines, oldest first
+pub fn tail(app: &str, tail: usize) -> serde_json::Value {
+    let Some(logs) = LOGS.get() else { return serde_json::json!([]) };
+    let mut apps = logs.apps.lock().unwrap();
+    let log = logs.open(&mut apps, app);
+    let skip = log.lines.len().saturating_sub(tail);
+    serde_json::to_value(log.lines.iter().skip(skip).collect::<Vec<_>>()).unwrap_or_default()
+}
+
+/// Write the app's last `tail` lines to an SSE stream whose head is out,
+/// then hand it the new lines as they come
+pub fn follow(app: &str, tail: usize, mut stream: SseStream) -> io::Result<()> {
+    let Some(logs) = LOGS.get() else { return Ok(()) };
+    let mut apps = logs.apps.lock().unwrap();
+    let log = logs.open(&mut apps, app);
+    let skip = log.lines.len().saturating_sub(tail);
+    for line in log.lines.iter().skip(skip) {
+        let json = serde_json::to_string(line).unwrap_or_default();
+        write_sse_named(&mut stream, "log", None, json.as_bytes())?;
+    }
+    log.followers.push(Client::sse(stream)?);
+    Ok(())
+}
+
+/// Keepalive to every follower, dropping those that are gone
+pub fn keepalive() {
+    let Some(logs) = LOGS.get() else { return };
+    for log in logs.apps.lock().unwrap().values_mut() {
+        log.followers.retain(|c| c.keepalive().is_ok());
+    }
+}
+
+impl Logs {
+    fn path(&self, app: &str) -> String {
+        format!("{}/{}/logs.jsonl", self.data_dir, app)
+    }
+
+    /// The app's log, read from its file the first time
+    fn open<'a>(&self, apps: &'a mut HashMap<String, AppLog>, app: &str) -> &'a mut AppLog {
+        apps.entry(app.to_string()).or_insert_with(|| {
+            let mut lines: VecDeque<Line> = std::fs::read_to_string(self.path(app))
+                .map(|text| text.lines().filter_map(|l| serde_json::from_str(l).ok()).collect())
+                .unwrap_or_default();
+            let appended = lines.len().saturating_sub(RING_LINES);
+            while lines.len() > RING_LINES {
+                lines.pop_front();
+            }
+            AppLog { lines, appended, followers: Vec::new() }
+        })
+    }
+}
+
+/// Replace the log file with `lines`
+fn rewrite(path: &str, lines: &VecDeque<Line>) -> io::Result<()> {
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(&serde_json::to_string(line).map_err(io::Error::other)?);
+        text.push('\n');
+    }
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, text)?;
+    std::fs::rename(&tmp, path)
+}
+
+fn unix_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
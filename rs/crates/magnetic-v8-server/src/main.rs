@@ -6,28 +6,130 @@
 //!   - Asset pipeline (content-hashing, immutable cache headers, manifest)
 //!   - Head/meta extraction from DomNode
 //!   - SSR, SSE, POST actions, static files, navigation
+//!   - gzip/brotli response compression (Accept-Encoding)
+//!   - ETag / Last-Modified validators with 304 responses
+//!   - WebSocket transport (/ws) as an alternative to SSE
 //!
 //! Usage:
 //!   magnetic-v8-server --bundle dist/app.js --port 3003 --static public/
 //!   magnetic-v8-server --bundle dist/app.js --render kotlin --out app.kt
+//!   magnetic-v8-server --bundle dist/app.js --render swift --render-map mappings.json
+//!   magnetic-v8-server --bundle dist/app.js --render kotlin --out-dir gen/  (one file per data-component)
+//!   magnetic-v8-server --bundle dist/app.js --render flutter --out app.dart
+//!   magnetic-v8-server --bundle dist/app.js --render react-native --out App.jsx
+//!   magnetic-v8-server --bundle dist/app.js --render text
+//!   magnetic-v8-server --bundle dist/app.js --render pdf --out report.pdf
+//!   magnetic-v8-server --bundle dist/app.js --render html --routes all --out-dir site/  (every page)
 //!   magnetic-v8-server --platform --port 3003 --data-dir data/apps
-
+//!
+//! Connections are served by a bounded worker pool (--workers N, default 64).
+//! --processes N runs N server processes sharing --port via SO_REUSEPORT (cluster.rs).
+//! --tls-cert <chain.pem> --tls-key <key.pem> serves HTTPS directly.
+//! --sse-delta sends SSE updates as keyed patches (delta.rs).
+//! Each SSE/WebSocket client has a bounded send queue; slow ones skip ahead or are dropped (outbox.rs).
+//! --v8-workers N runs N V8 isolates per app, routed by session (isolates.rs).
+//! --v8-heap-mb N caps each isolate's heap (default 256); one that hits it is replaced.
+//! --max-body-mb, --header-timeout and --conn-timeout bound each request (limits.rs).
+//! On the platform, --render-timeout-ms, --max-sse-clients, --max-bundle-mb and
+//! --app-quota bound each app's renders, clients and deploys (quotas.rs).
+//! Each platform app's errors and log lines are kept for GET /api/apps/<name>/logs (logs.rs).
+//! Per-app usage is at GET /api/apps/<name>/metrics, and every app's at GET /metrics for Prometheus (metrics.rs).
+//! Platform deploys can also be a .tar.gz with Content-Type: application/gzip (archive.rs).
+//! Session cookies are signed with --session-secret (cookie.rs).
+//! SIGTERM/SIGINT drain connections for up to --shutdown-timeout secs (shutdown.rs).
+//! GET /healthz and /readyz serve liveness and readiness probes (health.rs).
+//! GET /api/stats reports uptime, requests, clients, V8 queue depth and render times (stats.rs).
+//! Settings can also come from magnetic.toml / m.json or --config <path> (config.rs);
+//! --middleware logger,cors,rate-limit[,security-headers] sets the middleware order;
+//! rate-limit is a token bucket per client, --rate-limit-route /actions=30 per route (rate_limit.rs);
+//! response hooks see each response before it is compressed and sent (response.rs).
+//! Multipart action POSTs store their files under --uploads <dir> (upload.rs).
+//! --static-index, --fallback /app=app/index.html and --passthrough /docs shape
+//! static serving: directory index, client-side routes, never-rendered paths (static_routes.rs).
+//! --redirect, --rewrite and --trailing-slash rewrite URLs before routing (redirects.rs).
+//! --proxy /api/billing=https://internal:9000 forwards a prefix to a backend (proxy.rs).
+//! /robots.txt and /sitemap.xml are generated from routes() unless the static dir has them (seo.rs).
+//! --locales en,fr picks each session's locale from Accept-Language for renders (locale.rs).
+//! --otlp-endpoint exports spans for requests, middleware, V8 calls, data fetches and broadcasts (trace.rs).
+//! Render failures and missing pages use the app's renderError() or error.html (error_page.rs).
+//! --watch reloads the bundle and connected pages when files change (watch.rs);
+//! --build "<cmd>" runs the bundler at startup and, with --watch, on source changes.
+
+mod archive;
+mod breaker;
+mod cache;
+mod cluster;
+mod codegen;
+mod compress;
+mod config;
+mod conn;
+mod cookie;
+mod csrf;
+mod csv;
+mod db;
+mod delta;
+mod deploy_tokens;
+mod error_page;
+mod health;
+mod idempotency;
+mod isolates;
+mod limits;
+mod locale;
+mod logs;
+mod metrics;
+mod optimistic;
+mod outbox;
 mod platform;
+mod pool;
+mod proxy;
+mod quotas;
+mod rate_limit;
+mod redirects;
+mod resume;
+mod response;
+mod secrets;
+mod seo;
+mod shutdown;
+mod snapshot;
+mod static_routes;
+mod stats;
+mod trace;
+mod transform;
+mod upload;
+mod versions;
+mod watch;
+mod webhook;
+mod ws;
 pub mod data;
 pub mod auth;
 
 use magnetic_dom::DomNode;
+use cache::Validators;
+use compress::{Precompressed, SseStream};
+use conn::Conn;
+use idempotency::Outcome;
+use limits::Limits;
+use outbox::Client;
+use pool::WorkerPool;
+use response::Response;
+use static_routes::StaticRoutes;
+use magnetic_dom::mapping::RenderMaps;
 use magnetic_render_html::{render_to_html, render_page, PageOptions};
-use magnetic_render_kotlin::render_to_kotlin;
-use magnetic_render_swift::render_to_swift;
+use magnetic_render_kotlin::{render_to_kotlin_files, render_to_kotlin_with_map};
+use magnetic_render_swift::{render_to_swift_files, render_to_swift_with_map};
+use magnetic_render_flutter::render_to_flutter;
+use magnetic_render_react_native::render_to_react_native;
+use magnetic_render_text::render_to_text;
+use magnetic_render_pdf::render_to_pdf;
 
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Read, Write};
-use std::net::{TcpListener, TcpStream};
+use std::net::IpAddr;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::sync::mpsc;
-use std::sync::{Arc, Condvar, Mutex, Once};
+use std::sync::{Arc, Condvar, Mutex, Once, RwLock};
 use std::thread;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 
 // ═══════════════════════════════════════════════════════════════════
 // 0. EMBEDDED FRAMEWORK ASSETS
@@ -39,25 +141,36 @@ const EMBEDDED_MAGNETIC_JS: &[u8] = include_bytes!("../assets/magnetic.min.js");
 /// WASM transport — embedded at compile time. Never exists as a user-visible file.
 const EMBEDDED_TRANSPORT_WASM: &[u8] = include_bytes!("../assets/transport.wasm");
 
+/// Compressed copies of the embedded assets, made on first request.
+static MAGNETIC_JS_ENCODED: Precompressed = Precompressed::new();
+static TRANSPORT_WASM_ENCODED: Precompressed = Precompressed::new();
+
 /// Serve an embedded asset with proper headers. Returns true if handled.
 pub fn serve_embedded(
-    stream: &mut TcpStream,
+    stream: &mut Conn,
     filename: &str,
     extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
 ) -> Option<std::io::Result<()>> {
     // Strip query string (e.g. "magnetic.js?v=abc" → "magnetic.js")
     let bare = filename.split('?').next().unwrap_or(filename);
-    let (data, content_type): (&[u8], &str) = match bare {
-        "magnetic.js" => (EMBEDDED_MAGNETIC_JS, "application/javascript"),
-        "transport.wasm" => (EMBEDDED_TRANSPORT_WASM, "application/wasm"),
+    let (data, content_type, encoded): (&[u8], &str, &Precompressed) = match bare {
+        "magnetic.js" => (EMBEDDED_MAGNETIC_JS, "application/javascript", &MAGNETIC_JS_ENCODED),
+        "transport.wasm" => (EMBEDDED_TRANSPORT_WASM, "application/wasm", &TRANSPORT_WASM_ENCODED),
         _ => return None,
     };
 
     let eh = format_extra_headers(extra_headers);
+    let v = Validators::for_body(data);
+    if v.not_modified(req_headers) {
+        let headers = format!("Cache-Control: public, max-age=31536000, immutable\r\n{}", eh);
+        return Some(cache::write_not_modified(stream, &v, content_type, &headers));
+    }
+    let (data, ce) = encoded.encode(data, content_type, req_headers);
     let resp = format!(
-        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}{}\
         Cache-Control: public, max-age=31536000, immutable\r\n{}\r\n",
-        content_type, data.len(), eh
+        content_type, data.len(), ce, v.headers(), eh
     );
     Some((|| {
         stream.write_all(resp.as_bytes())?;
@@ -75,6 +188,8 @@ pub struct MagneticContext {
     pub path: String,
     pub query: HashMap<String, String>,
     pub headers: HashMap<String, String>,
+    /// The connection's peer address
+    pub peer: Option<IpAddr>,
     pub action: Option<String>,
     pub payload: Option<String>,
     pub status: u16,
@@ -85,7 +200,7 @@ pub struct MagneticContext {
 }
 
 impl MagneticContext {
-    pub fn from_request(method: &str, url: &str, headers: HashMap<String, String>) -> Self {
+    pub fn from_request(method: &str, url: &str, headers: HashMap<String, String>, peer: Option<IpAddr>) -> Self {
         let (path, qs) = url.split_once('?').unwrap_or((url, ""));
         let mut query = HashMap::new();
         if !qs.is_empty() {
@@ -100,6 +215,7 @@ impl MagneticContext {
             path: path.to_string(),
             query,
             headers,
+            peer,
             action: None,
             payload: None,
             status: 200,
@@ -113,28 +229,100 @@ impl MagneticContext {
 
 pub type MiddlewareFn = Box<dyn Fn(&mut MagneticContext) + Send + Sync>;
 
+/// Response-phase hook: sees the finished response before it is sent
+/// (response.rs)
+pub type ResponseFn = Box<dyn Fn(&MagneticContext, &mut Response) + Send + Sync>;
+
+/// Middleware hooks, each with the name its trace span gets (trace.rs)
 pub struct MiddlewareStack {
-    fns: Vec<MiddlewareFn>,
+    fns: Vec<(&'static str, MiddlewareFn)>,
+    after: Vec<(&'static str, ResponseFn)>,
 }
 
 impl MiddlewareStack {
-    pub fn new() -> Self { Self { fns: Vec::new() } }
+    pub fn new() -> Self { Self { fns: Vec::new(), after: Vec::new() } }
+
+    pub fn add(&mut self, name: &'static str, f: MiddlewareFn) { self.fns.push((name, f)); }
 
-    pub fn add(&mut self, f: MiddlewareFn) { self.fns.push(f); }
+    pub fn add_after(&mut self, name: &'static str, f: ResponseFn) { self.after.push((name, f)); }
 
     pub fn run(&self, ctx: &mut MagneticContext) {
-        for f in &self.fns {
+        for (name, f) in &self.fns {
+            let _span = trace::span(name);
             f(ctx);
             if ctx.body.is_some() { return; } // short-circuit
         }
     }
+
+    /// Response hooks, last added first, so the first middleware sees the
+    /// response last. They run for short-circuited responses too.
+    pub fn run_after(&self, ctx: &MagneticContext, resp: &mut Response) {
+        for (name, f) in self.after.iter().rev() {
+            let _span = trace::span(name);
+            f(ctx, resp);
+        }
+    }
+}
+
+/// Middleware in `--middleware` order (comma-separated names, default
+/// "logger,cors,rate-limit"), configured by `--cors`, `--rate-limit` and
+/// `--rate-limit-route`.
+/// `log_tag` prefixes the logger's lines. Also returns a description for
+/// the startup log.
+pub fn middleware_arg(args: &[String], default_rate_limit: u32, log_tag: &str) -> (MiddlewareStack, String) {
+    let cors_origin = find_arg(args, "--cors").unwrap_or_else(|| "*".to_string());
+    let rate_limit_max: u32 = find_arg(args, "--rate-limit")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(default_rate_limit);
+    let order = find_arg(args, "--middleware").unwrap_or_else(|| "logger,cors,rate-limit".to_string());
+
+    let mut stack = MiddlewareStack::new();
+    let mut names = Vec::new();
+    for name in order.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        match name {
+            "logger" => {
+                stack.add_after("middleware.logger", logger_middleware(log_tag));
+                names.push("logger".to_string());
+            }
+            "security-headers" => {
+                stack.add_after("middleware.security_headers", security_headers_middleware());
+                names.push("security-headers".to_string());
+            }
+            "cors" => {
+                stack.add("middleware.cors", cors_middleware(&cors_origin));
+                names.push(format!("cors({})", cors_origin));
+            }
+            "rate-limit" => {
+                let routes = rate_limit::routes_arg(args);
+                let mut desc = format!("rate-limit({}/min", rate_limit_max);
+                for (prefix, n) in &routes {
+                    desc.push_str(&format!(", {} {}/min", prefix, n));
+                }
+                names.push(format!("{})", desc));
+                stack.add("middleware.rate_limit", rate_limit::Limiter::new(rate_limit_max, routes).middleware());
+            }
+            other => eprintln!("[magnetic] unknown middleware '{}' ignored", other),
+        }
+    }
+    (stack, names.join(", "))
 }
 
 /// Logger middleware — logs method + path + status + timing
-pub fn logger_middleware() -> MiddlewareFn {
-    Box::new(|_ctx: &mut MagneticContext| {
-        // Pre-hook: start_time is set in MagneticContext::from_request.
-        // Actual log line emitted after handler completes in handle_connection.
+pub fn logger_middleware(tag: &str) -> ResponseFn {
+    let tag = tag.to_string();
+    Box::new(move |ctx: &MagneticContext, resp: &mut Response| {
+        let ms = ctx.start_time.elapsed().as_millis();
+        eprintln!("[{}] {} {} → {} ({}ms)", tag, ctx.method, ctx.path, resp.status, ms);
+    })
+}
+
+/// Security headers middleware — adds the usual hardening headers to
+/// responses that don't set their own
+pub fn security_headers_middleware() -> ResponseFn {
+    Box::new(|_ctx: &MagneticContext, resp: &mut Response| {
+        resp.default_header("X-Content-Type-Options", "nosniff");
+        resp.default_header("X-Frame-Options", "SAMEORIGIN");
+        resp.default_header("Referrer-Policy", "strict-origin-when-cross-origin");
     })
 }
 
@@ -146,44 +334,13 @@ pub fn cors_middleware(origins: &str) -> MiddlewareFn {
             "Access-Control-Allow-Origin".into(), origin.clone(),
         );
         ctx.response_headers.insert(
-            "Access-Control-Allow-Headers".into(), "Content-Type".into(),
+            "Access-Control-Allow-Headers".into(), "Content-Type, Idempotency-Key".into(),
         );
         ctx.response_headers.insert(
             "Access-Control-Allow-Methods".into(), "GET, POST, OPTIONS".into(),
         );
-        if ctx.method == "OPTIONS" {
-            ctx.status = 204;
-            ctx.body = Some(String::new());
-        }
-    })
-}
-
-/// Rate-limit middleware — per-IP sliding window
-pub fn rate_limit_middleware(window_ms: u64, max_requests: u32) -> MiddlewareFn {
-    let hits: Arc<Mutex<HashMap<String, (u32, u64)>>> = Arc::new(Mutex::new(HashMap::new()));
-    Box::new(move |ctx: &mut MagneticContext| {
-        let ip = ctx.headers.get("x-forwarded-for")
-            .or_else(|| ctx.headers.get("x-real-ip"))
-            .cloned()
-            .unwrap_or_else(|| "unknown".into());
-
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-
-        let mut map = hits.lock().unwrap();
-        let entry = map.entry(ip).or_insert((0, now + window_ms));
-
-        if now > entry.1 {
-            *entry = (0, now + window_ms);
-        }
-        entry.0 += 1;
-
-        if entry.0 > max_requests {
-            ctx.status = 429;
-            ctx.body = Some("{\"error\":\"Too many requests\"}".into());
-        }
+        // Preflights are answered by routing, with the route's methods
+        // (write_allow)
     })
 }
 
@@ -192,19 +349,46 @@ pub fn rate_limit_middleware(window_ms: u64, max_requests: u32) -> MiddlewareFn
 // ═══════════════════════════════════════════════════════════════════
 
 /// Asset manifest: original filename → hashed filename
+#[derive(serde::Serialize)]
 pub struct AssetManifest {
     pub files: HashMap<String, String>,    // original → hashed
     pub reverse: HashMap<String, String>,  // hashed → original
 }
 
+/// Written by `build_assets` into its output dir, served as `/manifest.json`
+pub const MANIFEST_FILE: &str = "manifest.json";
+
 impl AssetManifest {
     pub fn new() -> Self {
         AssetManifest { files: HashMap::new(), reverse: HashMap::new() }
     }
+
+    /// URL of an asset by its original name: `img/app.js` →
+    /// `/img/app.1a2b3c4d.js`. Names not in the manifest map to themselves.
+    pub fn manifest_url(&self, name: &str) -> String {
+        let name = name.trim_start_matches('/');
+        format!("/{}", self.files.get(name).map(String::as_str).unwrap_or(name))
+    }
+
+    /// JS that gives the bundle the same lookup, as a global
+    /// `manifest_url(name)`, run in each isolate (`snapshot::Bundle::assets`)
+    pub fn script(&self) -> String {
+        let files = serde_json::to_string(&self.files).unwrap_or_else(|_| "{}".to_string());
+        format!(
+            "globalThis.__magnetic_assets = {};\n\
+            globalThis.manifest_url = function(name) {{ \
+            name = String(name).replace(/^\\/+/, ''); \
+            return '/' + (globalThis.__magnetic_assets[name] || name); }};",
+            files
+        )
+    }
 }
 
-/// Build content-hashed asset manifest from a source directory.
-/// Copies files to out_dir with hashed names. Returns manifest.
+/// Build content-hashed asset manifest from a source directory, including
+/// its subdirectories (dot-directories such as `.hashed` are skipped).
+/// Copies files to out_dir with hashed names, at the same relative path:
+/// `img/app.js` → `img/app.1a2b3c4d.js`. Manifest keys are those relative
+/// paths. Returns manifest.
 pub fn build_assets(src_dir: &str, out_dir: &str, passthrough: &[&str]) -> AssetManifest {
     let mut manifest = AssetManifest::new();
     let hash_exts = [".css", ".js", ".wasm"];
@@ -214,16 +398,15 @@ pub fn build_assets(src_dir: &str, out_dir: &str, passthrough: &[&str]) -> Asset
     if !src.exists() { return manifest; }
     if !out.exists() { let _ = std::fs::create_dir_all(out); }
 
-    let entries = match std::fs::read_dir(src) {
-        Ok(e) => e,
-        Err(_) => return manifest,
-    };
+    let mut files = Vec::new();
+    collect_asset_files(src, "", &mut files);
 
-    for entry in entries.flatten() {
-        let name = entry.file_name().to_string_lossy().to_string();
-        if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) { continue; }
-
-        let src_path = entry.path();
+    for (src_path, name) in files {
+        let (dir, file_name) = match name.rsplit_once('/') {
+            Some((dir, file)) => (format!("{}/", dir), file),
+            None => (String::new(), name.as_str()),
+        };
+        let _ = std::fs::create_dir_all(out.join(&dir));
 
         // Passthrough files — copy without hashing
         if passthrough.contains(&name.as_str()) {
@@ -233,7 +416,7 @@ pub fn build_assets(src_dir: &str, out_dir: &str, passthrough: &[&str]) -> Asset
             continue;
         }
 
-        let ext = std::path::Path::new(&name)
+        let ext = std::path::Path::new(file_name)
             .extension()
             .map(|e| format!(".{}", e.to_string_lossy()))
             .unwrap_or_default();
@@ -246,42 +429,64 @@ pub fn build_assets(src_dir: &str, out_dir: &str, passthrough: &[&str]) -> Asset
             continue;
         }
 
-        // Read file, compute MD5 hash (first 8 hex chars)
+        // Read file, compute SHA-256 hash (first 8 hex chars)
         let content = match std::fs::read(&src_path) {
             Ok(c) => c,
             Err(_) => continue,
         };
-        let hash = md5_hex(&content);
-        let stem = std::path::Path::new(&name)
+        let hash = content_hash(&content);
+        let stem = std::path::Path::new(file_name)
             .file_stem()
             .map(|s| s.to_string_lossy().to_string())
             .unwrap_or_default();
-        let hashed_name = format!("{}.{}{}", stem, &hash[..8], ext);
+        let hashed_name = format!("{}{}.{}{}", dir, stem, &hash[..8], ext);
 
         let _ = std::fs::copy(&src_path, out.join(&hashed_name));
+        compress::write_variants(&out.join(&hashed_name), &content);
         manifest.files.insert(name.clone(), hashed_name.clone());
         manifest.reverse.insert(hashed_name, name);
     }
 
+    // The static dir's own manifest.json (a web app manifest) wins
+    if manifest.files.contains_key(MANIFEST_FILE) {
+        eprintln!("[magnetic] {}/{} exists: asset manifest not written", src_dir, MANIFEST_FILE);
+    } else if let Ok(json) = serde_json::to_string_pretty(&manifest) {
+        if let Err(e) = std::fs::write(out.join(MANIFEST_FILE), json) {
+            eprintln!("[magnetic] cannot write asset manifest: {}", e);
+        }
+    }
+
     manifest
 }
 
-/// Simple MD5 implementation (sufficient for content hashing)
-fn md5_hex(data: &[u8]) -> String {
-    // Use a simple hash: FNV-1a 128-bit split into hex
-    // For production parity we want deterministic content hashing.
-    // We'll use a basic approach: sum bytes with mixing.
-    let mut h: u64 = 0xcbf29ce484222325;
-    for &b in data {
-        h ^= b as u64;
-        h = h.wrapping_mul(0x100000001b3);
-    }
-    let mut h2: u64 = 0x84222325cbf29ce4;
-    for &b in data.iter().rev() {
-        h2 ^= b as u64;
-        h2 = h2.wrapping_mul(0x1b3_0000_0001);
+/// Every file under `dir`, with its path relative to the static dir
+/// (`prefix` is the path of `dir`, ending in `/` unless empty)
+fn collect_asset_files(dir: &std::path::Path, prefix: &str, files: &mut Vec<(std::path::PathBuf, String)>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(file_type) = entry.file_type() else { continue };
+        if file_type.is_dir() {
+            if !name.starts_with('.') {
+                collect_asset_files(&entry.path(), &format!("{}{}/", prefix, name), files);
+            }
+        } else if file_type.is_file() {
+            files.push((entry.path(), format!("{}{}", prefix, name)));
+        }
     }
-    format!("{:016x}{:016x}", h, h2)
+}
+
+/// SHA-256 of `data` as hex. Callers keep as many leading digits as they
+/// need: 8 in hashed asset names, 16 in ETags.
+fn content_hash(data: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
 }
 
 // ═══════════════════════════════════════════════════════════════════
@@ -319,28 +524,112 @@ pub fn error_fallback(error_msg: &str, action: Option<&str>) -> DomNode {
 // ═══════════════════════════════════════════════════════════════════
 
 pub enum V8Request {
-    Render { path: String, session_id: String, reply: Arc<Reply> },
-    Reduce { action: String, payload: String, path: String, session_id: String, reply: Arc<Reply> },
+    /// `locale` is the session's negotiated locale (locale.rs), passed to
+    /// the bundle's render and reduce calls
+    Render { path: String, session_id: String, locale: Option<String>, reply: Arc<Reply> },
+    /// `data_json`, when given, is injected after the reducer runs and
+    /// before the result renders
+    Reduce {
+        action: String,
+        payload: String,
+        path: String,
+        session_id: String,
+        locale: Option<String>,
+        data_json: Option<String>,
+        reply: Arc<Reply>,
+    },
     /// Inject data context into V8 (calls MagneticApp.setData(json))
     SetData { json: String, reply: Arc<Reply> },
     /// Inject data then render (combined for atomicity)
-    RenderWithData { path: String, data_json: String, session_id: String, reply: Arc<Reply> },
+    RenderWithData { path: String, data_json: String, session_id: String, locale: Option<String>, reply: Arc<Reply> },
     /// Call an API route handler (server/api/*.ts)
     ApiCall { method: String, path: String, body: String, reply: Arc<Reply> },
-    /// Call renderWithCSS(path, sid) — returns {root: DomNode, css: string}
-    /// Falls back to render(path, sid) if renderWithCSS is not exported
-    RenderWithCSS { path: String, session_id: String, reply: Arc<Reply> },
+    /// Call renderWithCSS(path, sid, locale) — returns {root: DomNode, css: string}
+    /// Falls back to render(path, sid, locale) if renderWithCSS is not exported
+    RenderWithCSS { path: String, session_id: String, locale: Option<String>, reply: Arc<Reply> },
     /// Inject data then call renderWithCSS (combined for SSR with data)
-    RenderWithDataAndCSS { path: String, data_json: String, session_id: String, reply: Arc<Reply> },
+    RenderWithDataAndCSS { path: String, data_json: String, session_id: String, locale: Option<String>, reply: Arc<Reply> },
+    /// Call renderError(status, message, locale) — the app's error page,
+    /// "null" if the bundle doesn't export it (error_page.rs)
+    RenderError { status: u16, message: String, locale: Option<String>, reply: Arc<Reply> },
+    /// Call routes() — the app's page paths for sitemap.xml, "null" if the
+    /// bundle doesn't export it (seo.rs)
+    Routes { reply: Arc<Reply> },
     /// Garbage-collect idle sessions in V8
     CleanupSessions { max_age_ms: u64, reply: Arc<Reply> },
     /// Drop a specific session (on SSE disconnect)
     DropSession { session_id: String },
+    /// Readiness probe: answered Ok once the bundle has loaded
+    Ping { reply: Arc<Reply> },
+    /// Replace the isolate with one running a new bundle (--watch)
+    Reload { bundle: Arc<snapshot::Bundle> },
+}
+
+impl V8Request {
+    /// Where the result goes (DropSession and Reload have no reply)
+    pub fn reply(&self) -> Option<Arc<Reply>> {
+        match self {
+            V8Request::Render { reply, .. }
+            | V8Request::Reduce { reply, .. }
+            | V8Request::SetData { reply, .. }
+            | V8Request::RenderWithData { reply, .. }
+            | V8Request::ApiCall { reply, .. }
+            | V8Request::RenderWithCSS { reply, .. }
+            | V8Request::RenderWithDataAndCSS { reply, .. }
+            | V8Request::RenderError { reply, .. }
+            | V8Request::Routes { reply }
+            | V8Request::CleanupSessions { reply, .. }
+            | V8Request::Ping { reply } => Some(Arc::clone(reply)),
+            V8Request::DropSession { .. } | V8Request::Reload { .. } => None,
+        }
+    }
+
+    /// Give the request a fresh reply to answer into. Returns the caller's
+    /// and the fresh one; the caller's must then be answered by hand.
+    fn hold_reply(&mut self) -> Option<(Arc<Reply>, Arc<Reply>)> {
+        let reply = match self {
+            V8Request::Render { reply, .. }
+            | V8Request::Reduce { reply, .. }
+            | V8Request::SetData { reply, .. }
+            | V8Request::RenderWithData { reply, .. }
+            | V8Request::ApiCall { reply, .. }
+            | V8Request::RenderWithCSS { reply, .. }
+            | V8Request::RenderWithDataAndCSS { reply, .. }
+            | V8Request::RenderError { reply, .. }
+            | V8Request::Routes { reply }
+            | V8Request::CleanupSessions { reply, .. }
+            | V8Request::Ping { reply } => reply,
+            V8Request::DropSession { .. } | V8Request::Reload { .. } => return None,
+        };
+        let held = Reply::new();
+        Some((std::mem::replace(reply, Arc::clone(&held)), held))
+    }
+
+    /// Span name for the bundle call this makes (trace.rs)
+    fn span_name(&self) -> &'static str {
+        match self {
+            V8Request::Render { .. } => "v8.render",
+            V8Request::Reduce { .. } => "v8.reduce",
+            V8Request::SetData { .. } => "v8.set_data",
+            V8Request::RenderWithData { .. } => "v8.render_with_data",
+            V8Request::ApiCall { .. } => "v8.api",
+            V8Request::RenderWithCSS { .. } => "v8.render_with_css",
+            V8Request::RenderWithDataAndCSS { .. } => "v8.render_with_data_and_css",
+            V8Request::RenderError { .. } => "v8.render_error",
+            V8Request::Routes { .. } => "v8.routes",
+            V8Request::CleanupSessions { .. } => "v8.cleanup_sessions",
+            V8Request::DropSession { .. } => "v8.drop_session",
+            V8Request::Ping { .. } => "v8.ping",
+            V8Request::Reload { .. } => "v8.reload",
+        }
+    }
 }
 
 pub struct Reply {
     pub data: Mutex<Option<V8Result>>,
     pub ready: Condvar,
+    /// The span the request was made in, for the V8 thread's span (trace.rs)
+    pub trace: Option<trace::Parent>,
 }
 
 impl Reply {
@@ -348,6 +637,7 @@ impl Reply {
         Arc::new(Reply {
             data: Mutex::new(None),
             ready: Condvar::new(),
+            trace: trace::current(),
         })
     }
 
@@ -396,120 +686,278 @@ pub fn ensure_v8_initialized() {
     });
 }
 
-pub fn v8_thread(js_source: String, rx: mpsc::Receiver<V8Request>) {
+/// Serve V8 requests from `rx` until every sender is gone. An isolate that
+/// nears its heap limit (`limits.heap_mb`) or panics mid-request is
+/// replaced by a fresh one that replays the bundle; the sessions it held
+/// start over. A render or action past `limits.run_time` is terminated
+/// and answered with an error, and the isolate goes on.
+/// `V8Request::Reload` swaps in a new bundle the same way. With `app`, the
+/// errors it answers with also go to that app's log (logs.rs).
+pub fn v8_thread(
+    bundle: Arc<snapshot::Bundle>,
+    rx: mpsc::Receiver<V8Request>,
+    limits: isolates::IsolateLimits,
+    app: Option<Arc<str>>,
+    stats: Arc<stats::V8Stats>,
+) {
     ensure_v8_initialized();
+    let mut bundle = bundle;
+    loop {
+        match run_isolate(&bundle, &rx, limits, app.as_deref(), &stats) {
+            IsolateExit::Closed => return,
+            IsolateExit::Failed(reason) => {
+                eprintln!("[magnetic-v8] ⚠ {} — recreating isolate", reason);
+                if let Some(app) = app.as_deref() {
+                    logs::record(app, logs::Level::Error, &format!("V8 isolate replaced: {}", reason));
+                }
+            }
+            IsolateExit::Reload(next) => {
+                eprintln!("[magnetic-v8] bundle changed — reloading isolate");
+                bundle = next;
+            }
+        }
+    }
+}
 
-    let mut isolate = v8::Isolate::new(v8::CreateParams::default());
+/// Why `run_isolate` returned
+enum IsolateExit {
+    /// Every sender is gone
+    Closed,
+    /// The isolate must be replaced
+    Failed(&'static str),
+    /// Start over with this bundle
+    Reload(Arc<snapshot::Bundle>),
+}
+
+/// Run one isolate until `rx` closes or the isolate has to go.
+fn run_isolate(
+    bundle: &snapshot::Bundle,
+    rx: &mpsc::Receiver<V8Request>,
+    limits: isolates::IsolateLimits,
+    app: Option<&str>,
+    stats: &stats::V8Stats,
+) -> IsolateExit {
+    let mut limited = isolates::LimitedIsolate::new(limits.heap_mb, bundle.snapshot.as_deref());
+    let watchdog = limits.run_time.map(|_| limited.watchdog());
+    let isolate = &mut limited.isolate;
 
     let global_context;
     let mut init_error: Option<String> = None;
     {
-        let handle_scope = &mut v8::HandleScope::new(&mut isolate);
+        let handle_scope = &mut v8::HandleScope::new(isolate);
         let context = v8::Context::new(handle_scope, Default::default());
         global_context = v8::Global::new(handle_scope, context);
         let scope = &mut v8::ContextScope::new(handle_scope, context);
 
-        let code = v8::String::new(scope, &js_source).unwrap();
-        match v8::Script::compile(scope, code, None) {
-            Some(script) => {
-                if script.run(scope).is_none() {
-                    init_error = Some("JS bundle threw during execution".into());
+        // A context from a snapshot already holds the evaluated bundle
+        if bundle.snapshot.is_none() {
+            let code = v8::String::new(scope, &bundle.source).unwrap();
+            match v8::Script::compile(scope, code, None) {
+                Some(script) => {
+                    if script.run(scope).is_none() {
+                        init_error = Some("JS bundle threw during execution".into());
+                    }
+                }
+                None => {
+                    init_error = Some("Failed to compile JS bundle".into());
                 }
             }
-            None => {
-                init_error = Some("Failed to compile JS bundle".into());
+        }
+
+        // The asset manifest, for manifest_url() at render time
+        if init_error.is_none() && !bundle.assets.is_empty() {
+            let code = v8::String::new(scope, &bundle.assets).unwrap();
+            if v8::Script::compile(scope, code, None).and_then(|script| script.run(scope)).is_none() {
+                init_error = Some("Asset manifest script failed".into());
             }
         }
     }
 
     if let Some(ref err) = init_error {
         eprintln!("[magnetic-v8] ⚠ bundle init failed: {}", err);
-        // Stay alive to drain requests with error responses so callers don't hang
+        if let Some(app) = app {
+            logs::record(app, logs::Level::Error, &format!("V8 bundle init failed: {}", err));
+        }
+        // Stay alive to drain requests with error responses so callers don't
+        // hang, until a fixed bundle arrives
         for req in rx {
-            let err_msg = format!("V8 bundle failed to initialize: {}", err);
-            match req {
-                V8Request::Render { reply, .. }
-                | V8Request::SetData { reply, .. }
-                | V8Request::RenderWithData { reply, .. }
-                | V8Request::RenderWithCSS { reply, .. }
-                | V8Request::RenderWithDataAndCSS { reply, .. }
-                | V8Request::ApiCall { reply, .. }
-                | V8Request::CleanupSessions { reply, .. } => {
-                    reply.send(V8Result::Err(err_msg));
-                }
-                V8Request::Reduce { reply, .. } => {
-                    reply.send(V8Result::Err(err_msg));
-                }
-                V8Request::DropSession { .. } => {}
+            stats.started();
+            if let V8Request::Reload { bundle } = req {
+                return IsolateExit::Reload(bundle);
+            }
+            if let Some(reply) = req.reply() {
+                reply.send(V8Result::Err(format!("V8 bundle failed to initialize: {}", err)));
             }
         }
-        return;
+        return IsolateExit::Closed;
     }
 
     eprintln!("[magnetic-v8] V8 runtime initialized");
 
-    for req in rx {
-        match req {
-            V8Request::Render { path, session_id, reply } => {
-                let result = v8_call_render(&mut isolate, &global_context, &path, &session_id);
-                reply.send(result);
+    for mut req in rx {
+        stats.started();
+        if let V8Request::Reload { bundle } = req {
+            return IsolateExit::Reload(bundle);
+        }
+        let reply = req.reply();
+        let parent = reply.as_ref().and_then(|r| r.trace.as_ref());
+        let _span = trace::child_of(parent, req.span_name());
+        if let Some(parent) = parent {
+            trace::attr("magnetic.v8.queued_us", trace::since(parent).as_micros() as u64);
+        }
+        match &req {
+            V8Request::Reduce { action, path, .. } => {
+                trace::attr("magnetic.action", action.as_str());
+                trace::attr("url.path", path.as_str());
             }
-            V8Request::Reduce { action, payload, path, session_id, reply } => {
-                let reduce_result = v8_call_reduce(
-                    &mut isolate, &global_context, &action, &payload, &session_id,
-                );
-                if let V8Result::Err(e) = reduce_result {
-                    eprintln!("[magnetic-v8] reduce error on \"{}\": {}", action, e);
-                }
-                let result = v8_call_render(&mut isolate, &global_context, &path, &session_id);
-                reply.send(result);
+            V8Request::Render { path, .. }
+            | V8Request::RenderWithData { path, .. }
+            | V8Request::RenderWithCSS { path, .. }
+            | V8Request::RenderWithDataAndCSS { path, .. }
+            | V8Request::ApiCall { path, .. } => trace::attr("url.path", path.as_str()),
+            _ => {}
+        }
+        let render = matches!(
+            req,
+            V8Request::Render { .. }
+                | V8Request::RenderWithData { .. }
+                | V8Request::RenderWithCSS { .. }
+                | V8Request::RenderWithDataAndCSS { .. }
+        );
+        let timed = match (&watchdog, limits.run_time) {
+            (Some(watchdog), Some(limit)) if render || matches!(req, V8Request::Reduce { .. }) => Some((watchdog, limit)),
+            _ => None,
+        };
+        // The request answers into a reply of its own when the answer is
+        // needed here first: for the app's log, or to say why a request past
+        // the time limit was stopped
+        let held = if app.is_some() || timed.is_some() { req.hold_reply() } else { None };
+        if let (Some((watchdog, limit)), Some(_)) = (timed, &held) {
+            watchdog.arm(limit);
+        }
+        let start = Instant::now();
+        let handled = catch_unwind(AssertUnwindSafe(|| {
+            handle_v8_request(&mut limited.isolate, &global_context, req)
+        }));
+        if render {
+            stats.render_took(start.elapsed());
+        }
+        let stopped = held.is_some() && timed.is_some_and(|(watchdog, _)| watchdog.disarm());
+        if handled.is_err() {
+            trace::error("V8 isolate crashed");
+            if let Some(app) = app {
+                logs::record(app, logs::Level::Error, "V8 isolate crashed");
             }
-            V8Request::SetData { json, reply } => {
-                let result = v8_call_set_data(&mut isolate, &global_context, &json);
-                reply.send(result);
+            if let Some(reply) = reply {
+                reply.send(V8Result::Err("V8 isolate crashed".into()));
             }
-            V8Request::RenderWithData { path, data_json, session_id, reply } => {
-                let set_result = v8_call_set_data(&mut isolate, &global_context, &data_json);
-                if let V8Result::Err(e) = set_result {
-                    eprintln!("[magnetic-v8] setData error: {}", e);
+            return IsolateExit::Failed("panic while handling a request");
+        }
+        if let Some((caller, held)) = held {
+            let result = match timed {
+                Some((_, limit)) if stopped => {
+                    stats.timed_out();
+                    let message = format!("Request took longer than {}ms and was stopped", limit.as_millis());
+                    trace::error(&message);
+                    eprintln!("[magnetic-v8] ⚠ {}", message);
+                    Some(V8Result::Err(message))
                 }
-                let result = v8_call_render(&mut isolate, &global_context, &path, &session_id);
-                reply.send(result);
-            }
-            V8Request::ApiCall { method, path, body, reply } => {
-                let result = v8_call_api(&mut isolate, &global_context, &method, &path, &body);
-                reply.send(result);
+                _ => held.data.lock().unwrap().take(),
+            };
+            if let Some(result) = result {
+                if let (Some(app), V8Result::Err(e)) = (app, &result) {
+                    logs::record(app, logs::Level::Error, &format!("V8: {}", e));
+                }
+                caller.send(result);
             }
-            V8Request::RenderWithCSS { path, session_id, reply } => {
-                let result = v8_call_render_with_css(&mut isolate, &global_context, &path, &session_id);
-                reply.send(result);
+        }
+        if limited.heap_exhausted() {
+            return IsolateExit::Failed("heap limit reached");
+        }
+    }
+    IsolateExit::Closed
+}
+
+fn handle_v8_request(isolate: &mut v8::OwnedIsolate, global_context: &v8::Global<v8::Context>, req: V8Request) {
+    match req {
+        V8Request::Render { path, session_id, locale, reply } => {
+            let result = v8_call_render(isolate, global_context, &path, &session_id, locale.as_deref());
+            reply.send(result);
+        }
+        V8Request::Reduce { action, payload, path, session_id, locale, data_json, reply } => {
+            let reduce_result = v8_call_reduce(
+                isolate, global_context, &action, &payload, &session_id, locale.as_deref(),
+            );
+            if let V8Result::Err(e) = reduce_result {
+                eprintln!("[magnetic-v8] reduce error on \"{}\": {}", action, e);
             }
-            V8Request::RenderWithDataAndCSS { path, data_json, session_id, reply } => {
-                let set_result = v8_call_set_data(&mut isolate, &global_context, &data_json);
-                if let V8Result::Err(e) = set_result {
+            if let Some(data_json) = data_json {
+                if let V8Result::Err(e) = v8_call_set_data(isolate, global_context, &data_json) {
                     eprintln!("[magnetic-v8] setData error: {}", e);
                 }
-                let result = v8_call_render_with_css(&mut isolate, &global_context, &path, &session_id);
-                reply.send(result);
             }
-            V8Request::CleanupSessions { max_age_ms, reply } => {
-                let result = v8_call_cleanup_sessions(&mut isolate, &global_context, max_age_ms);
-                reply.send(result);
+            let result = v8_call_render(isolate, global_context, &path, &session_id, locale.as_deref());
+            reply.send(result);
+        }
+        V8Request::SetData { json, reply } => {
+            let result = v8_call_set_data(isolate, global_context, &json);
+            reply.send(result);
+        }
+        V8Request::RenderWithData { path, data_json, session_id, locale, reply } => {
+            let set_result = v8_call_set_data(isolate, global_context, &data_json);
+            if let V8Result::Err(e) = set_result {
+                eprintln!("[magnetic-v8] setData error: {}", e);
             }
-            V8Request::DropSession { session_id } => {
-                v8_call_drop_session(&mut isolate, &global_context, &session_id);
+            let result = v8_call_render(isolate, global_context, &path, &session_id, locale.as_deref());
+            reply.send(result);
+        }
+        V8Request::ApiCall { method, path, body, reply } => {
+            let result = v8_call_api(isolate, global_context, &method, &path, &body);
+            reply.send(result);
+        }
+        V8Request::RenderWithCSS { path, session_id, locale, reply } => {
+            let result = v8_call_render_with_css(isolate, global_context, &path, &session_id, locale.as_deref());
+            reply.send(result);
+        }
+        V8Request::RenderWithDataAndCSS { path, data_json, session_id, locale, reply } => {
+            let set_result = v8_call_set_data(isolate, global_context, &data_json);
+            if let V8Result::Err(e) = set_result {
+                eprintln!("[magnetic-v8] setData error: {}", e);
             }
+            let result = v8_call_render_with_css(isolate, global_context, &path, &session_id, locale.as_deref());
+            reply.send(result);
+        }
+        V8Request::RenderError { status, message, locale, reply } => {
+            let result = v8_call_render_error(isolate, global_context, status, &message, locale.as_deref());
+            reply.send(result);
+        }
+        V8Request::Routes { reply } => {
+            let result = v8_call_routes(isolate, global_context);
+            reply.send(result);
+        }
+        V8Request::CleanupSessions { max_age_ms, reply } => {
+            let result = v8_call_cleanup_sessions(isolate, global_context, max_age_ms);
+            reply.send(result);
+        }
+        V8Request::DropSession { session_id } => {
+            v8_call_drop_session(isolate, global_context, &session_id);
+        }
+        V8Request::Ping { reply } => {
+            reply.send(V8Result::Ok(String::new()));
         }
+        // Handled by run_isolate
+        V8Request::Reload { .. } => {}
     }
 }
 
-/// Call renderWithCSS(path, sid) — returns JSON string of {root: DomNode, css: string}
-/// Falls back to render(path, sid) wrapped as {root: DomNode} if renderWithCSS is not available
+/// Call renderWithCSS(path, sid, locale) — returns JSON string of {root: DomNode, css: string}
+/// Falls back to render(path, sid, locale) wrapped as {root: DomNode} if renderWithCSS is not available
 fn v8_call_render_with_css(
     isolate: &mut v8::OwnedIsolate,
     context: &v8::Global<v8::Context>,
     path: &str,
     session_id: &str,
+    locale: Option<&str>,
 ) -> V8Result {
     let handle_scope = &mut v8::HandleScope::new(isolate);
     let context = v8::Local::new(handle_scope, context);
@@ -520,8 +968,8 @@ fn v8_call_render_with_css(
 
     // Try renderWithCSS first, fall back to render if not available
     let call_code = format!(
-        r#"(function() {{ try {{ if (typeof globalThis.MagneticApp.renderWithCSS === 'function') {{ return JSON.stringify(globalThis.MagneticApp.renderWithCSS("{0}", "{1}")); }} else {{ var dom = globalThis.MagneticApp.render("{0}", "{1}"); return JSON.stringify({{root: dom, css: null}}); }} }} catch(e) {{ return JSON.stringify({{__error: e.message || String(e)}}); }} }})()"#,
-        safe_path, safe_sid
+        r#"(function() {{ try {{ if (typeof globalThis.MagneticApp.renderWithCSS === 'function') {{ return JSON.stringify(globalThis.MagneticApp.renderWithCSS("{0}", "{1}", {2})); }} else {{ var dom = globalThis.MagneticApp.render("{0}", "{1}", {2}); return JSON.stringify({{root: dom, css: null}}); }} }} catch(e) {{ return JSON.stringify({{__error: e.message || String(e)}}); }} }})()"#,
+        safe_path, safe_sid, locale_js(locale)
     );
 
     let code = v8::String::new(scope, &call_code).unwrap();
@@ -551,6 +999,7 @@ pub fn v8_call_render(
     context: &v8::Global<v8::Context>,
     path: &str,
     session_id: &str,
+    locale: Option<&str>,
 ) -> V8Result {
     let handle_scope = &mut v8::HandleScope::new(isolate);
     let context = v8::Local::new(handle_scope, context);
@@ -560,8 +1009,8 @@ pub fn v8_call_render(
     let safe_sid = session_id.replace('\\', "\\\\").replace('"', "\\\"");
 
     let call_code = format!(
-        r#"(function() {{ try {{ return JSON.stringify(globalThis.MagneticApp.render("{}", "{}")); }} catch(e) {{ return JSON.stringify({{__error: e.message || String(e)}}); }} }})()"#,
-        safe_path, safe_sid
+        r#"(function() {{ try {{ return JSON.stringify(globalThis.MagneticApp.render("{}", "{}", {})); }} catch(e) {{ return JSON.stringify({{__error: e.message || String(e)}}); }} }})()"#,
+        safe_path, safe_sid, locale_js(locale)
     );
 
     let code = v8::String::new(scope, &call_code).unwrap();
@@ -630,16 +1079,18 @@ fn v8_call_reduce(
     action: &str,
     payload: &str,
     session_id: &str,
+    locale: Option<&str>,
 ) -> V8Result {
     let handle_scope = &mut v8::HandleScope::new(isolate);
     let context = v8::Local::new(handle_scope, context);
     let scope = &mut v8::ContextScope::new(handle_scope, context);
 
     let inner_json = format!(
-        r#"{{"action":"{}","payload":{},"session":"{}"}}"#,
+        r#"{{"action":"{}","payload":{},"session":"{}","locale":{}}}"#,
         action.replace('\\', "\\\\").replace('"', "\\\""),
         payload,
-        session_id.replace('\\', "\\\\").replace('"', "\\\"")
+        session_id.replace('\\', "\\\\").replace('"', "\\\""),
+        locale_js(locale)
     ).replace('\'', "\\'");
 
     let call_code = format!(
@@ -700,6 +1151,81 @@ fn v8_call_api(
     }
 }
 
+/// `locale` as a JS literal: a string, or null without negotiation
+fn locale_js(locale: Option<&str>) -> String {
+    serde_json::to_string(&locale).unwrap_or_else(|_| "null".into())
+}
+
+/// Call renderError(status, message, locale) — returns JSON DomNode, or
+/// "null" if renderError is not exported
+fn v8_call_render_error(
+    isolate: &mut v8::OwnedIsolate,
+    context: &v8::Global<v8::Context>,
+    status: u16,
+    message: &str,
+    locale: Option<&str>,
+) -> V8Result {
+    let handle_scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Local::new(handle_scope, context);
+    let scope = &mut v8::ContextScope::new(handle_scope, context);
+
+    let safe_message = serde_json::to_string(message).unwrap_or_else(|_| "\"\"".into());
+    let call_code = format!(
+        r#"(function() {{ try {{ if (!globalThis.MagneticApp || typeof globalThis.MagneticApp.renderError !== 'function') return "null"; return JSON.stringify(globalThis.MagneticApp.renderError({}, {}, {})); }} catch(e) {{ return JSON.stringify({{__error: e.message || String(e)}}); }} }})()"#,
+        status, safe_message, locale_js(locale)
+    );
+
+    let code = v8::String::new(scope, &call_code).unwrap();
+    let script = match v8::Script::compile(scope, code, None) {
+        Some(s) => s,
+        None => return V8Result::Err("Failed to compile renderError call".into()),
+    };
+    match script.run(scope) {
+        Some(result) => {
+            let json = result.to_rust_string_lossy(scope);
+            if json.contains("\"__error\"") {
+                if let Ok(val) = serde_json::from_str::<serde_json::Value>(&json) {
+                    if let Some(msg) = val.get("__error").and_then(|v| v.as_str()) {
+                        return V8Result::Err(msg.to_string());
+                    }
+                }
+            }
+            V8Result::Ok(json)
+        }
+        None => V8Result::Err("renderError() returned undefined".into()),
+    }
+}
+
+/// Call routes() — returns a JSON array of page paths, or "null" if routes
+/// is not exported
+fn v8_call_routes(isolate: &mut v8::OwnedIsolate, context: &v8::Global<v8::Context>) -> V8Result {
+    let handle_scope = &mut v8::HandleScope::new(isolate);
+    let context = v8::Local::new(handle_scope, context);
+    let scope = &mut v8::ContextScope::new(handle_scope, context);
+
+    let call_code = r#"(function() { try { if (!globalThis.MagneticApp || typeof globalThis.MagneticApp.routes !== 'function') return "null"; return JSON.stringify(globalThis.MagneticApp.routes()); } catch(e) { return JSON.stringify({__error: e.message || String(e)}); } })()"#;
+
+    let code = v8::String::new(scope, call_code).unwrap();
+    let script = match v8::Script::compile(scope, code, None) {
+        Some(s) => s,
+        None => return V8Result::Err("Failed to compile routes call".into()),
+    };
+    match script.run(scope) {
+        Some(result) => {
+            let json = result.to_rust_string_lossy(scope);
+            if json.contains("\"__error\"") {
+                if let Ok(val) = serde_json::from_str::<serde_json::Value>(&json) {
+                    if let Some(msg) = val.get("__error").and_then(|v| v.as_str()) {
+                        return V8Result::Err(msg.to_string());
+                    }
+                }
+            }
+            V8Result::Ok(json)
+        }
+        None => V8Result::Err("routes() returned undefined".into()),
+    }
+}
+
 /// Call cleanupSessions(maxAgeMs) — garbage collect idle sessions
 fn v8_call_cleanup_sessions(
     isolate: &mut v8::OwnedIsolate,
@@ -754,48 +1280,91 @@ fn v8_call_drop_session(
 
 /// Generate a random session ID (hex string)
 pub fn generate_session_id() -> String {
-    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-    let seed = now.as_nanos();
-    // Simple random-ish hex: mix timestamp with thread ID
-    let tid = std::thread::current().id();
-    let h = seed ^ (format!("{:?}", tid).len() as u128 * 0x9e3779b97f4a7c15);
-    format!("{:016x}", h as u64)
+    cookie::random_id()
 }
 
-/// Extract session ID from Cookie header
+/// Extract session ID from Cookie header. A cookie with a bad signature
+/// (cookie.rs) counts as none.
 pub fn extract_session_cookie(headers: &HashMap<String, String>) -> Option<String> {
     let cookie = headers.get("cookie")?;
     for part in cookie.split(';') {
         let part = part.trim();
         if let Some(val) = part.strip_prefix("magnetic_sid=") {
-            let val = val.trim();
-            if !val.is_empty() {
-                return Some(val.to_string());
+            if let Some(id) = cookie::verify(val.trim()) {
+                return Some(id);
             }
         }
     }
     None
 }
 
+/// `Set-Cookie` header line (CRLF-terminated) for the signed session ID
+pub fn session_cookie(session_id: &str) -> String {
+    format!("Set-Cookie: magnetic_sid={}; Path=/; HttpOnly; SameSite=Lax\r\n", cookie::sign(session_id))
+}
+
 struct Server {
-    v8_tx: mpsc::Sender<V8Request>,
-    /// Per-session SSE clients: session_id → list of TcpStream clones
-    sse_clients: Mutex<HashMap<String, Vec<TcpStream>>>,
+    v8_tx: isolates::V8Pool,
+    /// Per-session push clients (SSE and WebSocket): session_id → clients
+    sse_clients: Mutex<HashMap<String, Vec<Client>>>,
+    /// Recent snapshots per session, for Last-Event-ID resume
+    sse_history: resume::History,
+    /// Last snapshot per session, for --sse-delta
+    sse_deltas: delta::Deltas,
+    /// Recent Idempotency-Keys per session, with their snapshots
+    idempotency: idempotency::Keys,
     static_dir: String,
     asset_dir: String,
     /// Per-session current path: session_id → path
     session_paths: Mutex<HashMap<String, String>>,
-    inline_css: Option<String>,
+    /// style.css, re-read on change with --watch
+    inline_css: RwLock<Option<String>>,
     middleware: MiddlewareStack,
     manifest: AssetManifest,
+    limits: Limits,
+    /// Where multipart uploads are stored (--uploads)
+    uploads_dir: String,
+    /// Directory index, fallback and pass-through rules (static_routes.rs)
+    static_routes: StaticRoutes,
+    redirects: redirects::Rules,
+    proxy: proxy::ProxyRoutes,
+    /// robots.txt and sitemap.xml settings (seo.rs)
+    seo: seo::Seo,
+    /// Declared locales (--locales) and each session's pick
+    locales: locale::Locales,
+    session_locales: locale::Sessions,
 }
 
 // ═══════════════════════════════════════════════════════════════════
 // 6. MAIN
 // ═══════════════════════════════════════════════════════════════════
 
+/// One page's --render output in `mode`
+fn render_output(mode: &str, dom: &DomNode, route: &str, maps: &RenderMaps) -> Vec<u8> {
+    match mode {
+        "kotlin" => render_to_kotlin_with_map(dom, &codegen::page_type_name("MagneticApp", route), &maps.kotlin).into_bytes(),
+        "swift" => render_to_swift_with_map(dom, &codegen::page_type_name("MagneticAppView", route), &maps.swift).into_bytes(),
+        "flutter" => render_to_flutter(dom, &codegen::page_type_name("MagneticApp", route)).into_bytes(),
+        "react-native" | "rn" => render_to_react_native(dom, &codegen::page_type_name("MagneticApp", route)).into_bytes(),
+        "text" => render_to_text(dom).into_bytes(),
+        "pdf" => render_to_pdf(dom, "Magnetic")
+            .unwrap_or_else(|e| panic!("PDF render error: {}", e)),
+        "html" => render_to_html(dom).into_bytes(),
+        _ => panic!("Unknown render mode: {}. Use: html, kotlin, swift, flutter, react-native, text, pdf", mode),
+    }
+}
+
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let args = match config::with_file(std::env::args().collect()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("[magnetic] {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    // --processes N: supervise N copies of this command (cluster.rs)
+    cluster::supervise_arg(&args);
 
     // Platform mode: multi-tenant hosting
     if args.iter().any(|a| a == "--platform") {
@@ -808,10 +1377,22 @@ fn main() {
     let static_dir = find_arg(&args, "--static").unwrap_or_else(|| "public".to_string());
     let render_mode = find_arg(&args, "--render");
     let out_path = find_arg(&args, "--out");
-    let cors_origin = find_arg(&args, "--cors").unwrap_or_else(|| "*".to_string());
-    let rate_limit_max: u32 = find_arg(&args, "--rate-limit")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(100);
+    let out_dir = find_arg(&args, "--out-dir");
+    let render_map = find_arg(&args, "--render-map");
+    let workers = pool::workers_arg(&args);
+    let shutdown_timeout = shutdown::timeout_arg(&args);
+    let tls = conn::tls_arg(&args);
+    let sse_delta = delta::delta_arg(&args);
+    let watch = watch::watch_arg(&args);
+    let v8_workers = isolates::v8_workers_arg(&args);
+    // Render time limits are per app, on the platform (quotas.rs)
+    let v8_limits = isolates::IsolateLimits { heap_mb: isolates::v8_heap_arg(&args), run_time: None };
+
+    // --build: bundle the sources first (watch.rs)
+    let build = watch::build_arg(&args);
+    if let Some(build) = &build {
+        build.run();
+    }
 
     let js_source = std::fs::read_to_string(&bundle_path)
         .unwrap_or_else(|e| panic!("Cannot read bundle {}: {}", bundle_path, e));
@@ -819,41 +1400,94 @@ fn main() {
     // Code generation mode (single-shot, no server)
     if let Some(mode) = &render_mode {
         let (tx, rx) = mpsc::channel();
-        let js = js_source.clone();
-        thread::spawn(move || v8_thread(js, rx));
-
-        let reply = Reply::new();
-        tx.send(V8Request::Render { path: "/".into(), session_id: "__default".into(), reply: reply.clone() }).unwrap();
-        let dom_json = match reply.recv() {
-            V8Result::Ok(j) => j,
-            V8Result::Err(e) => panic!("render() error: {}", e),
+        let bundle = snapshot::Bundle::source(js_source.clone(), String::new());
+        thread::spawn(move || v8_thread(bundle, rx, v8_limits, None, Arc::default()));
+
+        let render = |path: &str| -> DomNode {
+            let reply = Reply::new();
+            tx.send(V8Request::Render { path: path.into(), session_id: "__default".into(), locale: None, reply: reply.clone() }).unwrap();
+            let dom_json = match reply.recv() {
+                V8Result::Ok(j) => j,
+                V8Result::Err(e) => panic!("render({}) error: {}", path, e),
+            };
+            serde_json::from_str(&dom_json)
+                .unwrap_or_else(|e| panic!("Failed to parse DomNode for {}: {}", path, e))
         };
 
-        let dom: DomNode = serde_json::from_str(&dom_json)
-            .unwrap_or_else(|e| panic!("Failed to parse DomNode: {}", e));
+        let routes = match codegen::routes_arg(&args) {
+            codegen::Routes::Root => vec!["/".to_string()],
+            codegen::Routes::List(routes) => routes,
+            codegen::Routes::Bundle => {
+                let reply = Reply::new();
+                tx.send(V8Request::Routes { reply: reply.clone() }).unwrap();
+                let routes = match reply.recv() {
+                    V8Result::Ok(json) => serde_json::from_str::<Option<Vec<String>>>(&json)
+                        .unwrap_or_else(|e| panic!("routes() parse error: {}", e))
+                        .unwrap_or_default(),
+                    V8Result::Err(e) => panic!("routes() error: {}", e),
+                };
+                routes.into_iter().filter(|r| seo::is_page(r)).collect()
+            }
+        };
+        if routes.is_empty() {
+            panic!("--routes: no pages to render");
+        }
+        if routes.len() > 1 && out_dir.is_none() {
+            panic!("--routes with more than one page needs --out-dir");
+        }
 
-        let output = match mode.as_str() {
-            "kotlin" => render_to_kotlin(&dom, "MagneticApp"),
-            "swift" => render_to_swift(&dom, "MagneticAppView"),
-            "html" => render_to_html(&dom),
-            _ => panic!("Unknown render mode: {}. Use: html, kotlin, swift", mode),
+        let maps = match &render_map {
+            Some(path) => {
+                let json = std::fs::read_to_string(path)
+                    .unwrap_or_else(|e| panic!("Cannot read render map {}: {}", path, e));
+                RenderMaps::from_json(&json)
+                    .unwrap_or_else(|e| panic!("Invalid render map {}: {}", path, e))
+            }
+            None => RenderMaps::default(),
         };
 
+        if let Some(dir) = &out_dir {
+            let dir = std::path::Path::new(dir);
+            let mut pages: Vec<(String, Vec<u8>)> = Vec::new();
+            for route in &routes {
+                let dom = render(route);
+                // Multi-file output: one file per data-component subtree + index
+                let files = match mode.as_str() {
+                    "kotlin" => render_to_kotlin_files(&dom, &codegen::page_type_name("MagneticApp", route), &maps.kotlin),
+                    "swift" => render_to_swift_files(&dom, &codegen::page_type_name("MagneticAppView", route), &maps.swift),
+                    _ => {
+                        let name = format!("{}.{}", codegen::page_slug(route), codegen::extension(mode));
+                        pages.push((name, render_output(mode, &dom, route, &maps)));
+                        continue;
+                    }
+                };
+                let files: Vec<(String, Vec<u8>)> = files.into_iter()
+                    .map(|(name, source)| (name, source.into_bytes()))
+                    .collect();
+                let target = if routes.len() > 1 { dir.join(codegen::page_slug(route)) } else { dir.to_path_buf() };
+                codegen::write_dir(&target, &files)
+                    .unwrap_or_else(|e| panic!("{}", e))
+                    .print(&target.display().to_string());
+            }
+            if !pages.is_empty() {
+                codegen::write_dir(dir, &pages)
+                    .unwrap_or_else(|e| panic!("{}", e))
+                    .print(&dir.display().to_string());
+            }
+            return;
+        }
+
+        let output = render_output(mode, &render(&routes[0]), &routes[0], &maps);
         if let Some(path) = &out_path {
-            std::fs::write(path, &output)
-                .unwrap_or_else(|e| panic!("Cannot write {}: {}", path, e));
-            eprintln!("[magnetic-v8] Wrote {} ({} bytes)", path, output.len());
+            codegen::write_file(std::path::Path::new(path), &output)
+                .unwrap_or_else(|e| panic!("{}", e))
+                .print(path);
         } else {
-            print!("{}", output);
+            std::io::stdout().write_all(&output).expect("write stdout");
         }
         return;
     }
 
-    // Start V8 thread
-    let (tx, rx) = mpsc::channel();
-    let js = js_source;
-    thread::spawn(move || v8_thread(js, rx));
-
     // Build asset pipeline
     let asset_dir = format!("{}/.hashed", static_dir);
     let manifest = build_assets(
@@ -867,6 +1501,10 @@ fn main() {
         }
     }
 
+    // Start V8 isolates
+    let bundle = snapshot::Bundle::source(js_source, manifest.script());
+    let tx = isolates::V8Pool::start(bundle, v8_workers, v8_limits, None, Arc::default());
+
     // Load inline CSS (use hashed path if available)
     let css_hashed = manifest.files.get("style.css").cloned();
     let css_path = if let Some(ref h) = css_hashed {
@@ -876,42 +1514,103 @@ fn main() {
     };
     let inline_css = std::fs::read_to_string(&css_path).ok();
 
+    cookie::install(&args);
+    trace::init(&trace::otlp_arg(&args));
+    stats::start();
+
     // Build middleware stack
-    let mut middleware = MiddlewareStack::new();
-    middleware.add(logger_middleware());
-    middleware.add(cors_middleware(&cors_origin));
-    middleware.add(rate_limit_middleware(60_000, rate_limit_max));
+    let (middleware, middleware_desc) = middleware_arg(&args, 100, "magnetic");
 
     let server = Arc::new(Server {
         v8_tx: tx,
         sse_clients: Mutex::new(HashMap::new()),
+        sse_history: resume::History::new(),
+        sse_deltas: delta::Deltas::new(sse_delta),
+        idempotency: idempotency::Keys::new(),
         static_dir: static_dir.clone(),
         asset_dir,
         session_paths: Mutex::new(HashMap::new()),
-        inline_css,
+        inline_css: RwLock::new(inline_css),
         middleware,
         manifest,
+        limits: limits::limits_arg(&args),
+        uploads_dir: find_arg(&args, "--uploads").unwrap_or_else(|| "uploads".to_string()),
+        static_routes: static_routes::routes_arg(&args),
+        redirects: redirects::rules_arg(&args),
+        proxy: proxy::proxy_arg(&args),
+        seo: seo::seo_arg(&args),
+        locales: locale::locales_arg(&args),
+        session_locales: locale::Sessions::default(),
     });
 
-    let addr = format!("0.0.0.0:{}", port);
-    let listener = TcpListener::bind(&addr).expect("Failed to bind");
-    eprintln!("[magnetic-v8] http://localhost:{}", port);
+    let listeners = cluster::Listeners::bind(&args, &port).expect("Failed to bind");
+    eprintln!("[magnetic-v8] {}://localhost:{}", if tls.is_some() { "https" } else { "http" }, port);
     eprintln!("[magnetic-v8] Rust HTTP/SSE + V8 TSX rendering");
     eprintln!("[magnetic-v8] Bundle: {}", bundle_path);
-    eprintln!("[magnetic-v8] Middleware: logger, cors({}), rate-limit({}/min)", cors_origin, rate_limit_max);
+    eprintln!("[magnetic-v8] Middleware: {}", middleware_desc);
+    eprintln!("[magnetic-v8] Workers: {}", workers);
+
+    {
+        let server = Arc::clone(&server);
+        thread::spawn(move || sse_keepalive_loop(&server));
+    }
+
+    if watch {
+        eprintln!("[magnetic-v8] Watching {} and {}/ for changes", bundle_path, static_dir);
+        if let Some(build) = build {
+            build.spawn(&bundle_path);
+        }
+        let server = Arc::clone(&server);
+        let css_path = format!("{}/style.css", static_dir);
+        watch::spawn(bundle_path.clone(), static_dir.clone(), server.asset_dir.clone(), move |change| {
+            match change {
+                watch::Change::Bundle(source) => {
+                    let _ = server.v8_tx.send(V8Request::Reload { bundle: snapshot::Bundle::source(source, server.manifest.script()) });
+                }
+                watch::Change::Static => {
+                    *server.inline_css.write().unwrap() = std::fs::read_to_string(&css_path).ok();
+                }
+            }
+            // Sessions left without clients are cleaned up by the keepalive loop
+            for list in server.sse_clients.lock().unwrap().values_mut() {
+                list.retain(|c| c.reload().is_ok());
+            }
+        });
+    }
+
+    shutdown::install(listeners.waker().expect("Failed to clone listener"), shutdown_timeout);
 
-    for stream in listener.incoming() {
+    let pool = WorkerPool::new("http", workers);
+    for stream in listeners.incoming() {
+        if shutdown::is_stopping() { break; }
         let stream = match stream {
             Ok(s) => s,
             Err(e) => { eprintln!("[err] accept: {}", e); continue; }
         };
         let server = Arc::clone(&server);
-        thread::spawn(move || {
+        let tls = tls.clone();
+        pool.execute(move || {
+            let Ok(stream) = Conn::new(stream, tls.as_ref()) else { return };
             if let Err(e) = handle_connection(stream, &server) {
                 let _ = e;
             }
         });
     }
+
+    // Shutting down: refuse new connections, finish what's running
+    drop(listeners);
+    shutdown::wait_idle();
+    let clients: Vec<Client> = server.sse_clients.lock().unwrap().values().flatten().cloned().collect();
+    for client in &clients {
+        client.reconnect();
+    }
+    let deadline = Instant::now() + SSE_WRITE_TIMEOUT;
+    for client in &clients {
+        client.wait(deadline);
+    }
+    let count = clients.len();
+    eprintln!("[magnetic] asked {} client(s) to reconnect, exiting", count);
+    std::process::exit(0);
 }
 
 pub fn find_arg(args: &[String], flag: &str) -> Option<String> {
@@ -922,83 +1621,259 @@ pub fn find_arg(args: &[String], flag: &str) -> Option<String> {
 // 7. HTTP HANDLER
 // ═══════════════════════════════════════════════════════════════════
 
-fn handle_connection(mut stream: TcpStream, server: &Server) -> std::io::Result<()> {
-    let mut reader = BufReader::new(stream.try_clone()?);
+/// How long a kept-alive connection may sit idle before it is closed
+pub const KEEP_ALIVE_IDLE: Duration = Duration::from_secs(5);
+
+/// Interval between SSE keepalive comments (also how soon a closed SSE
+/// client is noticed)
+pub const SSE_KEEPALIVE_SECS: u64 = 30;
+
+/// A client whose write blocks this long is disconnected (outbox.rs)
+pub const SSE_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One HTTP request. The body is read with the head, so the next request
+/// on a kept-alive connection starts at the right byte whatever the route.
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>, // keys lowercased
+    pub body: Vec<u8>,
+    /// HTTP/1.1 without `Connection: close`
+    pub keep_alive: bool,
+    /// The client's address (the proxy's, behind one)
+    pub peer: Option<IpAddr>,
+}
+
+/// Read the next request. Ok(None) when the client closed the connection
+/// (a TLS client may skip close_notify) or sent nothing within KEEP_ALIVE_IDLE.
+/// The head must arrive by `head_deadline` and the body by `body_deadline`
+/// (limits.rs); a request over a size limit is an `Exceeded` error. So is
+/// one whose body length isn't certain (`Transfer-Encoding`, or a missing,
+/// bad or repeated `Content-Length` value), since reading the wrong number
+/// of bytes would take the rest of its body for the next request.
+pub fn read_request(
+    reader: &mut BufReader<Conn>,
+    max_body: usize,
+    head_deadline: Instant,
+    body_deadline: Instant,
+) -> std::io::Result<Option<Request>> {
+    reader.get_mut().set_deadline(head_deadline, KEEP_ALIVE_IDLE);
+    let mut head = reader.by_ref().take(limits::MAX_HEAD_BYTES as u64);
+
     let mut request_line = String::new();
-    reader.read_line(&mut request_line)?;
+    match head.read_line(&mut request_line) {
+        Ok(0) => return Ok(None),
+        Ok(_) => {}
+        Err(e) if matches!(
+            e.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut | std::io::ErrorKind::UnexpectedEof
+        ) => return Ok(None),
+        Err(e) => return Err(e),
+    }
 
-    let parts: Vec<&str> = request_line.trim().split_whitespace().collect();
-    if parts.len() < 2 { return Ok(()); }
-    let method = parts[0];
-    let path = parts[1];
+    let parts: Vec<&str> = request_line.split_whitespace().collect();
+    if parts.len() < 2 { return Ok(None); }
+    let method = parts[0].to_string();
+    let path = parts[1].to_string();
+    let http11 = parts.get(2) == Some(&"HTTP/1.1");
 
     // Read headers
-    let mut raw_headers = HashMap::new();
-    let mut content_length: usize = 0;
+    let mut headers = HashMap::new();
+    let mut content_length: Option<usize> = None;
     loop {
         let mut line = String::new();
-        reader.read_line(&mut line)?;
+        head.read_line(&mut line)?;
+        if !line.ends_with('\n') {
+            // Out of head bytes, or the client closed mid-head
+            if head.limit() == 0 { return Err(limits::exceeded(431)); }
+            return Ok(None);
+        }
         let trimmed = line.trim();
         if trimmed.is_empty() { break; }
         if let Some((k, v)) = trimmed.split_once(':') {
             let key = k.trim().to_lowercase();
             let val = v.trim().to_string();
+            if key == "transfer-encoding" {
+                return Err(limits::exceeded(501));
+            }
             if key == "content-length" {
-                content_length = val.parse().unwrap_or(0);
+                let digits = !val.is_empty() && val.bytes().all(|b| b.is_ascii_digit());
+                match (digits.then(|| val.parse::<usize>().ok()).flatten(), content_length) {
+                    (Some(len), None) => content_length = Some(len),
+                    _ => return Err(limits::exceeded(400)),
+                }
             }
-            raw_headers.insert(key, val);
+            headers.insert(key, val);
         }
     }
 
-    // Run middleware
-    let mut ctx = MagneticContext::from_request(method, path, raw_headers);
+    let content_length = content_length.unwrap_or(0);
+    if content_length > max_body {
+        return Err(limits::exceeded(413));
+    }
+    reader.get_mut().set_deadline(body_deadline, KEEP_ALIVE_IDLE);
+    let mut body = vec![0u8; content_length];
+    if content_length > 0 { reader.read_exact(&mut body)?; }
 
-    server.middleware.run(&mut ctx);
+    let close = headers.get("connection").is_some_and(|v| v.eq_ignore_ascii_case("close"));
+    let peer = reader.get_ref().peer_addr().ok().map(|a| a.ip());
+    Ok(Some(Request { method, path, headers, body, keep_alive: http11 && !close, peer }))
+}
 
-    // Log request
-    let log_method = ctx.method.clone();
-    let log_path = ctx.path.clone();
-    let log_start = ctx.start_time;
-
-    // Check if middleware short-circuited (e.g. OPTIONS, rate limit)
-    if let Some(body) = &ctx.body {
-        let mut resp_headers = String::new();
-        for (k, v) in &ctx.response_headers {
-            resp_headers.push_str(&format!("{}: {}\r\n", k, v));
-        }
-        let resp = format!(
-            "HTTP/1.1 {} {}\r\n{}Content-Length: {}\r\n\r\n",
-            ctx.status, status_text(ctx.status),
-            resp_headers, body.len()
-        );
-        stream.write_all(resp.as_bytes())?;
-        stream.write_all(body.as_bytes())?;
-        let ms = log_start.elapsed().as_millis();
-        eprintln!("[magnetic] {} {} → {} ({}ms)", log_method, log_path, ctx.status, ms);
-        return Ok(());
+/// Serve requests on one connection until the client closes it, asks for
+/// `Connection: close`, goes idle for KEEP_ALIVE_IDLE, or reaches the
+/// connection deadline (limits.rs). Responses all carry a Content-Length,
+/// so any of them can be followed by another request (an SSE stream only
+/// returns once the client is gone).
+pub fn serve_connection<F>(stream: Conn, limits: Limits, mut handle: F) -> std::io::Result<()>
+where
+    F: FnMut(&mut Conn, Request) -> std::io::Result<()>,
+{
+    let conn_deadline = Instant::now() + limits.conn_timeout;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut stream = stream;
+    loop {
+        let head_deadline = (Instant::now() + limits.header_timeout).min(conn_deadline);
+        let req = match read_request(&mut reader, limits.max_body, head_deadline, conn_deadline) {
+            Ok(Some(req)) => req,
+            Ok(None) => break,
+            Err(e) => {
+                if limits::write_exceeded(&mut stream, &e)? { break; }
+                return Err(e);
+            }
+        };
+        // An upgraded connection belongs to its WebSocket from here on
+        let keep_alive = req.keep_alive && !ws::is_upgrade(&req.headers);
+        {
+            let _in_flight = shutdown::track();
+            handle(&mut stream, req)?;
+        }
+        if !keep_alive || shutdown::is_stopping() || Instant::now() >= conn_deadline { break; }
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: Conn, server: &Arc<Server>) -> std::io::Result<()> {
+    serve_connection(stream, server.limits, |stream, req| handle_request(stream, server, req))
+}
+
+fn handle_request(stream: &mut Conn, server: &Arc<Server>, req: Request) -> std::io::Result<()> {
+    let Request { method, path, headers: raw_headers, body, peer, .. } = req;
+    let (method, path) = (method.as_str(), path.as_str());
+
+    // Probes bypass the middleware (health.rs)
+    let head = method == "HEAD";
+    match (method, path) {
+        ("GET" | "HEAD", "/healthz") => return response::bare(stream, head, health::write_healthz),
+        ("GET" | "HEAD", "/readyz") => {
+            let ready = health::accepting().and_then(|_| server.v8_tx.ping(health::READY_TIMEOUT));
+            return response::bare(stream, head, |stream| health::write_readyz(stream, ready));
+        }
+        _ => {}
     }
 
-    // Collect response headers from middleware for subsequent handlers
-    let extra_headers = ctx.response_headers.clone();
+    stats::request();
+    let span = trace::request(method, path, &raw_headers);
+
+    // Run middleware
+    let mut ctx = MagneticContext::from_request(method, path, raw_headers, peer);
+    server.middleware.run(&mut ctx);
+    if let Some(id) = span.request_id() {
+        ctx.response_headers.insert("X-Request-Id".into(), id);
+    }
+
+    // Proxy routes stream the backend's response, past the response phase
+    if let (None, Some(upstream)) = (&ctx.body, server.proxy.upstream(path)) {
+        let tls = stream.is_tls();
+        return proxy::forward(stream, &upstream, &ctx, &body, tls);
+    }
 
-    let result = match (method, path) {
-        ("GET", "/sse") => handle_sse(stream.try_clone()?, server, &extra_headers, &ctx.headers),
-        ("POST", p) if p.starts_with("/actions/") => {
-            let mut body = vec![0u8; content_length];
-            if content_length > 0 { reader.read_exact(&mut body)?; }
-            handle_action(&mut stream, server, p, &body, &extra_headers, &ctx.headers)
+    response::respond(stream, &server.middleware, &ctx, |stream| {
+        // Check if middleware short-circuited (e.g. OPTIONS, rate limit)
+        if let Some(body) = &ctx.body {
+            return write_short_circuit(stream, &ctx, body);
         }
-        ("GET", p) => handle_get(&mut stream, server, p, &extra_headers, &ctx.headers),
-        _ => {
-            stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+        // Headers the middleware set go on every response
+        let extra_headers = &ctx.response_headers;
+        // Redirect and rewrite rules come before routing (redirects.rs)
+        let path = match server.redirects.apply(path) {
+            redirects::Outcome::Redirect(status, location) => {
+                return redirects::write_redirect(stream, status, &location, extra_headers);
+            }
+            redirects::Outcome::Rewrite(to) => to,
+            redirects::Outcome::Pass => path.to_string(),
+        };
+        let allow = allowed_methods(&path);
+        if !allows(allow, method) {
+            return write_allow(stream, method, allow, extra_headers);
         }
-    };
+        match (method, path.as_str()) {
+            ("GET", "/sse") => handle_sse(stream.try_clone()?, server, extra_headers, &ctx.headers),
+            ("GET", "/ws") => handle_ws(stream, server, extra_headers, &ctx.headers),
+            ("POST", p) if p.starts_with("/actions/") => {
+                handle_action(stream, server, p, &body, extra_headers, &ctx.headers)
+            }
+            ("GET" | "HEAD", stats::PATH) => {
+                let mut stats = stats::base();
+                stats.insert("clients".into(), stats::clients_json(&server.sse_clients.lock().unwrap()));
+                stats.insert("v8".into(), server.v8_tx.stats_json());
+                stats::write(stream, stats, extra_headers)
+            }
+            ("GET" | "HEAD", p) if seo::generates(&server.static_dir, p) => {
+                server.seo.write(stream, p, Some(&server.v8_tx), "", &ctx.headers, extra_headers)
+            }
+            // HEAD renders the same response; the response phase drops the body
+            ("GET" | "HEAD", p) => handle_get(stream, server, p, extra_headers, &ctx.headers),
+            _ => {
+                stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+            }
+        }
+    })
+}
 
-    let ms = log_start.elapsed().as_millis();
-    if log_path != "/sse" {
-        eprintln!("[magnetic] {} {} → 200 ({}ms)", log_method, log_path, ms);
+/// Methods a route of the standalone server takes, for OPTIONS and 405s
+fn allowed_methods(path: &str) -> &'static str {
+    match path.split('?').next().unwrap_or(path) {
+        "/sse" | "/ws" => "GET, OPTIONS",
+        p if p.starts_with("/actions/") => "POST, OPTIONS",
+        _ => "GET, HEAD, OPTIONS",
     }
-    result
+}
+
+/// Whether `method` is in an `allowed_methods` list, OPTIONS aside
+pub fn allows(allow: &str, method: &str) -> bool {
+    method != "OPTIONS" && allow.split(", ").any(|m| m == method)
+}
+
+/// 204 with `Allow: <allow>` for OPTIONS, 405 for any other method. The
+/// CORS middleware's Access-Control-Allow-Methods gets the same list.
+pub fn write_allow(
+    stream: &mut Conn,
+    method: &str,
+    allow: &str,
+    extra_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let mut headers = extra_headers.clone();
+    if let Some(methods) = headers.get_mut("Access-Control-Allow-Methods") {
+        *methods = allow.to_string();
+    }
+    let (status, length) = if method == "OPTIONS" { (204, "") } else { (405, "Content-Length: 0\r\n") };
+    let resp = format!(
+        "HTTP/1.1 {} {}\r\nAllow: {}\r\n{}{}\r\n",
+        status, status_text(status), allow, length, format_extra_headers(&headers)
+    );
+    stream.write_all(resp.as_bytes())
+}
+
+/// The response of a middleware that set `ctx.body`
+pub fn write_short_circuit(stream: &mut Conn, ctx: &MagneticContext, body: &str) -> std::io::Result<()> {
+    let resp = format!(
+        "HTTP/1.1 {} {}\r\n{}Content-Length: {}\r\n\r\n",
+        ctx.status, status_text(ctx.status),
+        format_extra_headers(&ctx.response_headers), body.len()
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(body.as_bytes())
 }
 
 pub fn format_extra_headers(headers: &HashMap<String, String>) -> String {
@@ -1010,7 +1885,7 @@ pub fn format_extra_headers(headers: &HashMap<String, String>) -> String {
 }
 
 fn handle_sse(
-    mut stream: TcpStream,
+    mut stream: Conn,
     server: &Server,
     extra_headers: &HashMap<String, String>,
     req_headers: &HashMap<String, String>,
@@ -1020,73 +1895,216 @@ fn handle_sse(
         .unwrap_or_else(generate_session_id);
 
     let eh = format_extra_headers(extra_headers);
+    let (_, csrf_cookie) = csrf::token(req_headers);
+    let enc = SseStream::negotiate(req_headers);
     let header = format!(
         "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
-        Cache-Control: no-cache\r\nConnection: keep-alive\r\n\
-        Set-Cookie: magnetic_sid={}; Path=/; HttpOnly; SameSite=Lax\r\n{}\r\n",
-        session_id, eh
+        Cache-Control: no-cache\r\nConnection: keep-alive\r\n{}{}{}{}\r\n",
+        enc.headers(), session_cookie(&session_id), csrf_cookie, eh
     );
     stream.write_all(header.as_bytes())?;
+    let mut stream = SseStream::new(stream, enc);
 
     let path = server.session_paths.lock().unwrap()
         .get(&session_id).cloned().unwrap_or_else(|| "/".to_string());
+    let locale = server.locales.negotiate(req_headers);
+    server.session_locales.set(&session_id, locale.clone());
+    // A reconnecting EventSource gets the events it missed; anyone else
+    // (or one too far behind) a fresh snapshot
+    if let Some(missed) = server.sse_history.missed(&session_id, req_headers) {
+        for (id, snapshot) in &missed {
+            write_sse_event(&mut stream, Some(*id), snapshot.as_bytes())?;
+        }
+        eprintln!("[magnetic] SSE client resumed (session={}, {} missed)", &session_id[..8], missed.len());
+    } else {
+        let id = server.sse_history.open(&session_id);
+        let reply = Reply::new();
+        server.v8_tx.send(V8Request::Render { path: path.clone(), session_id: session_id.clone(), locale, reply: reply.clone() }).unwrap();
+        let dom_json = v8_result_to_json(reply.recv(), None);
+        let snapshot = format!("{{\"root\":{}}}", dom_json);
+        write_sse_event(&mut stream, Some(id), snapshot.as_bytes())?;
+        server.sse_deltas.reset(&session_id, &snapshot);
+    }
+
+    // Hand the socket to the registry (its writer thread writes to it from
+    // now on). Shutting down reads ends serve_connection's wait for another
+    // request, which frees this worker.
+    stream.get_ref().shutdown(std::net::Shutdown::Read)?;
+    stream.get_ref().set_write_timeout(Some(SSE_WRITE_TIMEOUT))?;
+    let client = Client::sse(stream)?;
+    {
+        let mut clients = server.sse_clients.lock().unwrap();
+        clients.entry(session_id.clone()).or_default().push(client);
+    }
+    eprintln!("[magnetic] SSE client connected (session={}, path={})", &session_id[..8], path);
+    Ok(())
+}
+
+/// WebSocket transport (ws.rs): the session and snapshots of /sse, plus
+/// actions sent over the socket. The socket's reader thread handles those
+/// until the client goes away.
+fn handle_ws(
+    stream: &mut Conn,
+    server: &Arc<Server>,
+    extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    if !csrf::check_origin(req_headers, None) {
+        return csrf::write_rejected(stream, "Cross-origin WebSocket refused");
+    }
+    let session_id = extract_session_cookie(req_headers)
+        .unwrap_or_else(generate_session_id);
+
+    let (_, csrf_cookie) = csrf::token(req_headers);
+    let headers = format!(
+        "{}{}{}",
+        session_cookie(&session_id), csrf_cookie, format_extra_headers(extra_headers)
+    );
+    if !ws::accept(stream, req_headers, &headers)? {
+        return Ok(());
+    }
+    let (sender, mut reader) = ws::split(stream.try_clone()?)?;
+    sender.set_write_timeout(Some(SSE_WRITE_TIMEOUT))?;
+
+    let path = server.session_paths.lock().unwrap()
+        .get(&session_id).cloned().unwrap_or_else(|| "/".to_string());
+    let locale = server.locales.negotiate(req_headers);
+    server.session_locales.set(&session_id, locale.clone());
     let reply = Reply::new();
-    server.v8_tx.send(V8Request::Render { path: path.clone(), session_id: session_id.clone(), reply: reply.clone() }).unwrap();
+    server.v8_tx.send(V8Request::Render { path: path.clone(), session_id: session_id.clone(), locale, reply: reply.clone() }).unwrap();
     let dom_json = v8_result_to_json(reply.recv(), None);
     let snapshot = format!("{{\"root\":{}}}", dom_json);
-    write_sse_event(&mut stream, snapshot.as_bytes())?;
-
-    let client = stream.try_clone()?;
+    sender.send("snapshot", snapshot.as_bytes())?;
+    let client = Client::ws(sender.clone(), stream)?;
     {
         let mut clients = server.sse_clients.lock().unwrap();
-        clients.entry(session_id.clone()).or_insert_with(Vec::new).push(client);
+        clients.entry(session_id.clone()).or_default().push(client.clone());
     }
-    eprintln!("[magnetic] SSE client connected (session={}, path={})", &session_id[..8], path);
+    eprintln!("[magnetic] WebSocket client connected (session={}, path={})", &session_id[..8], path);
+
+    let server = Arc::clone(server);
+    thread::Builder::new().name("ws-reader".into()).spawn(move || {
+        while let Ok(Some((action, payload))) = reader.next_action(&sender) {
+            let _in_flight = shutdown::track();
+            let snapshot = run_action(&server, &session_id, &action, payload);
+            if action == "navigate" {
+                let _ = client.send_snapshot(None, &snapshot.into(), None);
+            } else {
+                broadcast_snapshot(&server.sse_clients, &server.sse_history, &server.sse_deltas, &session_id, &snapshot);
+            }
+        }
+        // The registry drops this client at its next failed send
+        client.close();
+    })?;
+    Ok(())
+}
 
+/// Keepalive (SSE comment or WebSocket ping) to every client, every
+/// SSE_KEEPALIVE_SECS. Clients that are gone are dropped; a session
+/// with none left loses its V8 state and event history.
+fn sse_keepalive_loop(server: &Server) {
     loop {
-        thread::sleep(std::time::Duration::from_secs(30));
-        if stream.write_all(b": keepalive\n\n").is_err() {
-            eprintln!("[magnetic] SSE client disconnected (session={})", &session_id[..8]);
-            // Clean up this client from sse_clients
+        thread::sleep(Duration::from_secs(SSE_KEEPALIVE_SECS));
+        let mut gone = Vec::new();
+        {
             let mut clients = server.sse_clients.lock().unwrap();
-            if let Some(list) = clients.get_mut(&session_id) {
-                list.retain(|mut c| c.write_all(b"").is_ok());
-                if list.is_empty() {
-                    clients.remove(&session_id);
-                    // Drop session state in V8
-                    let _ = server.v8_tx.send(V8Request::DropSession { session_id: session_id.clone() });
-                    server.session_paths.lock().unwrap().remove(&session_id);
-                }
-            }
-            break;
+            clients.retain(|session_id, list| {
+                list.retain(|c| c.keepalive().is_ok());
+                if list.is_empty() { gone.push(session_id.clone()); }
+                !list.is_empty()
+            });
+            server.sse_history.sweep(|session_id| clients.contains_key(session_id));
+            server.sse_deltas.retain(|session_id| clients.contains_key(session_id));
+        }
+        server.idempotency.sweep();
+        for session_id in gone {
+            eprintln!("[magnetic] SSE client disconnected (session={})", &session_id[..8]);
+            server.sse_history.remove(&session_id);
+            let _ = server.v8_tx.send(V8Request::DropSession { session_id: session_id.clone() });
+            server.session_paths.lock().unwrap().remove(&session_id);
+            server.session_locales.remove(&session_id);
         }
     }
-    Ok(())
 }
 
 fn handle_action(
-    stream: &mut TcpStream,
+    stream: &mut Conn,
     server: &Server,
     url_path: &str,
     body: &[u8],
     extra_headers: &HashMap<String, String>,
     req_headers: &HashMap<String, String>,
 ) -> std::io::Result<()> {
+    if !csrf::check(req_headers, None) {
+        return csrf::write_rejected(stream, "Missing or invalid CSRF token");
+    }
     let action = urlencoding_decode(url_path.strip_prefix("/actions/").unwrap_or(""));
-    let body_str = String::from_utf8_lossy(body);
 
     // Session ID from cookie (fall back to __default for cookieless requests)
     let session_id = extract_session_cookie(req_headers)
         .unwrap_or_else(|| "__default".to_string());
+    server.session_locales.set(&session_id, server.locales.negotiate(req_headers));
+
+    // A retry with a known Idempotency-Key gets the first response again
+    // (and stores no uploads)
+    let (snapshot, replayed) = match server.idempotency.begin(&session_id, req_headers, &action, body) {
+        Some(Outcome::Replay(snapshot)) => (snapshot.to_string(), true),
+        Some(refused @ (Outcome::InProgress | Outcome::Mismatch)) => {
+            return idempotency::write_refused(stream, &refused);
+        }
+        outcome => {
+            let payload = match action_payload(body, req_headers, &server.uploads_dir) {
+                Ok(payload) => payload,
+                Err(e) => return upload::write_error(stream, &e),
+            };
+            let snapshot = run_action(server, &session_id, &action, payload);
+            if let Some(Outcome::Run(pending)) = outcome {
+                pending.complete(&snapshot);
+            }
+            (snapshot, false)
+        }
+    };
 
-    let payload = if body_str.is_empty() { "{}".to_string() } else {
+    let eh = format_extra_headers(extra_headers);
+    let replayed_header = if replayed { "Idempotent-Replayed: true\r\n" } else { "" };
+    let resp = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+        Content-Length: {}\r\n{}{}\r\n",
+        snapshot.len(), replayed_header, eh
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(snapshot.as_bytes())?;
+
+    // Broadcast only to this session's clients (not all users). A replay
+    // changed nothing.
+    if action != "navigate" && !replayed {
+        broadcast_snapshot(&server.sse_clients, &server.sse_history, &server.sse_deltas, &session_id, &snapshot);
+    }
+    Ok(())
+}
+
+/// The reduce payload of an action POST: the JSON body's `payload` field
+/// (or the whole body), or a multipart form's fields (upload.rs)
+pub fn action_payload(
+    body: &[u8],
+    req_headers: &HashMap<String, String>,
+    uploads_dir: &str,
+) -> Result<String, upload::Error> {
+    if let Some(boundary) = upload::boundary(req_headers) {
+        return upload::payload(body, &boundary, uploads_dir);
+    }
+    let body_str = String::from_utf8_lossy(body);
+    Ok(if body_str.is_empty() { "{}".to_string() } else {
         if let Ok(val) = serde_json::from_str::<serde_json::Value>(&body_str) {
             if let Some(p) = val.get("payload") { p.to_string() } else { val.to_string() }
         } else { "{}".to_string() }
-    };
-
-    let snapshot: String;
+    })
+}
 
+/// Run an action (or navigate) for a session and return the new
+/// `{"root":...}` snapshot. Shared by POST /actions/* and WebSocket actions.
+fn run_action(server: &Server, session_id: &str, action: &str, payload: String) -> String {
+    let session_id = session_id.to_string();
     if action == "navigate" {
         let nav_path = serde_json::from_str::<serde_json::Value>(&payload)
             .ok()
@@ -1097,10 +2115,15 @@ fn handle_action(
         server.session_paths.lock().unwrap().insert(session_id.clone(), nav_path.clone());
         let v8_start = Instant::now();
         let reply = Reply::new();
-        server.v8_tx.send(V8Request::Render { path: nav_path, session_id: session_id.clone(), reply: reply.clone() }).unwrap();
+        server.v8_tx.send(V8Request::Render {
+            path: nav_path, session_id: session_id.clone(), locale: server.session_locales.get(&session_id), reply: reply.clone(),
+        }).unwrap();
         let dom_json = v8_result_to_json(reply.recv(), None);
         eprintln!("[magnetic] V8 render: {}ms", v8_start.elapsed().as_micros() as f64 / 1000.0);
-        snapshot = format!("{{\"root\":{}}}", dom_json);
+        let snapshot = format!("{{\"root\":{}}}", dom_json);
+        // The reply goes to this client only: later deltas start from it
+        server.sse_deltas.reset(&session_id, &snapshot);
+        snapshot
     } else {
         let path = server.session_paths.lock().unwrap()
             .get(&session_id).cloned().unwrap_or_else(|| "/".to_string());
@@ -1108,71 +2131,63 @@ fn handle_action(
         let v8_start = Instant::now();
         let reply = Reply::new();
         server.v8_tx.send(V8Request::Reduce {
-            action: action.clone(), payload, path, session_id: session_id.clone(), reply: reply.clone(),
+            action: action.to_string(), payload, path, session_id: session_id.clone(),
+            locale: server.session_locales.get(&session_id), data_json: None, reply: reply.clone(),
         }).unwrap();
-        let dom_json = v8_result_to_json(reply.recv(), Some(&action));
+        let dom_json = v8_result_to_json(reply.recv(), Some(action));
         eprintln!("[magnetic] V8 reduce: {}ms", v8_start.elapsed().as_micros() as f64 / 1000.0);
-        snapshot = format!("{{\"root\":{}}}", dom_json);
+        format!("{{\"root\":{}}}", dom_json)
     }
-
-    let eh = format_extra_headers(extra_headers);
-    let resp = format!(
-        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
-        Content-Length: {}\r\n{}\r\n",
-        snapshot.len(), eh
-    );
-    stream.write_all(resp.as_bytes())?;
-    stream.write_all(snapshot.as_bytes())?;
-
-    // Broadcast only to this session's SSE clients (not all users)
-    if action != "navigate" {
-        let mut clients = server.sse_clients.lock().unwrap();
-        if let Some(list) = clients.get_mut(&session_id) {
-            let mut alive = Vec::new();
-            for mut client in list.drain(..) {
-                if write_sse_event(&mut client, snapshot.as_bytes()).is_ok() {
-                    alive.push(client);
-                }
-            }
-            if alive.is_empty() {
-                clients.remove(&session_id);
-            } else {
-                *list = alive;
-            }
-        }
-    }
-    Ok(())
 }
 
 fn handle_get(
-    stream: &mut TcpStream,
+    stream: &mut Conn,
     server: &Server,
     path: &str,
     extra_headers: &HashMap<String, String>,
     req_headers: &HashMap<String, String>,
 ) -> std::io::Result<()> {
+    let route_path = path.split('?').next().unwrap_or("/");
+
     // Static files
-    let has_ext = path.contains('.') && !path.ends_with('/');
-    let ext = path.rsplit('.').next().unwrap_or("");
-    if has_ext && ext != "html" {
-        return serve_static(stream, server, path, extra_headers);
+    let has_ext = route_path.contains('.') && !route_path.ends_with('/');
+    let ext = route_path.rsplit('.').next().unwrap_or("");
+    let rules = &server.static_routes;
+    if (has_ext && ext != "html") || rules.is_passthrough(route_path) {
+        return serve_static(stream, server, route_path, extra_headers, req_headers);
+    }
+    // A directory with an index file, or a client-side route
+    let dir_index = if route_path.ends_with('/') && route_path != "/" {
+        rules.index(&server.static_dir, route_path)
+    } else {
+        None
+    };
+    if let Some(file) = dir_index.or_else(|| rules.fallback(&server.static_dir, route_path)) {
+        return serve_static(stream, server, &format!("/{}", file), extra_headers, req_headers);
     }
 
     // SSR — get or create session, set cookie
-    let route_path = path.split('?').next().unwrap_or("/");
     let (session_id, is_new) = match extract_session_cookie(req_headers) {
         Some(sid) => (sid, false),
         None => (generate_session_id(), true),
     };
     server.session_paths.lock().unwrap().insert(session_id.clone(), route_path.to_string());
+    let locale = server.locales.negotiate(req_headers);
+    server.session_locales.set(&session_id, locale.clone());
+    let (csrf_token, csrf_cookie) = csrf::token(req_headers);
 
     // Use RenderWithCSS to get both DOM and generated CSS from V8
     let reply = Reply::new();
     server.v8_tx.send(V8Request::RenderWithCSS {
-        path: route_path.to_string(), session_id: session_id.clone(), reply: reply.clone(),
+        path: route_path.to_string(), session_id: session_id.clone(), locale: locale.clone(), reply: reply.clone(),
     }).unwrap();
 
-    let (dom, generated_css) = match reply.recv() {
+    // A render that fails is a 500 with the app's error page
+    let render_error = |message: &str| {
+        let dom = error_page::dom(Some(&server.v8_tx), &server.static_dir, 500, message, locale.clone());
+        (dom, None, 500)
+    };
+    let (dom, generated_css, status) = match reply.recv() {
         V8Result::Ok(json) => {
             // Parse {root: DomNode, css: string|null}
             match serde_json::from_str::<serde_json::Value>(&json) {
@@ -1180,27 +2195,63 @@ fn handle_get(
                     let root_val = wrapper.get("root").cloned().unwrap_or(serde_json::Value::Null);
                     let css_val = wrapper.get("css").and_then(|v| v.as_str()).map(String::from);
                     match serde_json::from_value::<DomNode>(root_val) {
-                        Ok(d) => (d, css_val),
+                        Ok(d) => (d, css_val, 200),
                         Err(e) => {
                             eprintln!("[magnetic-v8] render parse error: {}", e);
-                            (error_fallback(&format!("JSON parse error: {}", e), None), None)
+                            render_error(&format!("JSON parse error: {}", e))
                         }
                     }
                 }
                 Err(e) => {
                     eprintln!("[magnetic-v8] render parse error: {}", e);
-                    (error_fallback(&format!("JSON parse error: {}", e), None), None)
+                    render_error(&format!("JSON parse error: {}", e))
                 }
             }
         }
         V8Result::Err(e) => {
             eprintln!("[magnetic-v8] render error: {}", e);
-            (error_fallback(&e, None), None)
+            render_error(&e)
         }
     };
 
+    let page = ssr_page(server, dom, generated_css, csrf_token, locale);
+
+    let eh = format_extra_headers(extra_headers);
+    let mut cookie_header = if is_new {
+        session_cookie(&session_id)
+    } else {
+        String::new()
+    };
+    cookie_header.push_str(&csrf_cookie);
+    if status != 200 {
+        return error_page::write(stream, status, &page, &format!("{}{}", cookie_header, eh));
+    }
+    // no-cache: the browser may keep the page but revalidates it each time
+    let v = Validators::for_body(page.as_bytes());
+    if v.not_modified(req_headers) {
+        let headers = format!("Cache-Control: no-cache\r\n{}{}", cookie_header, eh);
+        return cache::write_not_modified(stream, &v, "text/html", &headers);
+    }
+    let resp = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+        Content-Length: {}\r\n{}Cache-Control: no-cache\r\n{}{}\r\n",
+        page.len(), v.headers(), cookie_header, eh
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(page.as_bytes())
+}
+
+/// The full HTML page around `dom`, with the app's CSS and client runtime
+fn ssr_page(
+    server: &Server,
+    dom: DomNode,
+    generated_css: Option<String>,
+    csrf_token: String,
+    lang: Option<String>,
+) -> String {
     // Merge CSS: generated CSS from design.json + user's style.css (if any)
-    let merged_css = match (&generated_css, &server.inline_css) {
+    let inline_css = server.inline_css.read().unwrap().clone();
+    let merged_css = match (&generated_css, &inline_css) {
         (Some(gen), Some(user)) => Some(format!("{}{}", gen, user)),
         (Some(gen), None) => Some(gen.clone()),
         (None, Some(user)) => Some(user.clone()),
@@ -1211,7 +2262,7 @@ fn handle_get(
     let magnetic_js = "/magnetic.js".to_string();
     let wasm_url = Some("/transport.wasm".to_string());
 
-    let page = render_page(&PageOptions {
+    render_page(&PageOptions {
         root: dom,
         scripts: vec![magnetic_js],
         styles: vec![],
@@ -1222,34 +2273,40 @@ fn handle_get(
         title: Some("Magnetic Task Board".to_string()),
         description: Some("Server-driven UI — Rust + V8".to_string()),
         inline_scripts: vec![],
-    });
+        csrf_token: Some(csrf_token),
+        lang,
+    })
+}
 
-    let eh = format_extra_headers(extra_headers);
-    let cookie_header = if is_new {
-        format!("Set-Cookie: magnetic_sid={}; Path=/; HttpOnly; SameSite=Lax\r\n", session_id)
-    } else {
-        String::new()
-    };
-    let resp = format!(
-        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
-        Content-Length: {}\r\n{}{}\r\n",
-        page.len(), cookie_header, eh
-    );
-    stream.write_all(resp.as_bytes())?;
-    stream.write_all(page.as_bytes())
+/// 404 for a missing file: the app's error page if the client wants HTML
+fn write_not_found(
+    stream: &mut Conn,
+    server: &Server,
+    extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    if !static_routes::accepts_html(req_headers) {
+        return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+    }
+    let (csrf_token, csrf_cookie) = csrf::token(req_headers);
+    let locale = server.locales.negotiate(req_headers);
+    let dom = error_page::dom(Some(&server.v8_tx), &server.static_dir, 404, "Page not found", locale.clone());
+    let page = ssr_page(server, dom, None, csrf_token, locale);
+    error_page::write(stream, 404, &page, &format!("{}{}", csrf_cookie, format_extra_headers(extra_headers)))
 }
 
 /// Serve static files with proper cache headers based on asset manifest
 fn serve_static(
-    stream: &mut TcpStream,
+    stream: &mut Conn,
     server: &Server,
     path: &str,
     extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
 ) -> std::io::Result<()> {
     let filename = path.trim_start_matches('/');
 
     // Embedded framework assets — served from binary, never from disk
-    if let Some(result) = serve_embedded(stream, filename, extra_headers) {
+    if let Some(result) = serve_embedded(stream, filename, extra_headers, req_headers) {
         return result;
     }
 
@@ -1273,7 +2330,20 @@ fn serve_static(
     let data = match std::fs::read(&file_path) {
         Ok(d) => d,
         Err(_) => {
-            return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+            // A directory's index file, or a client-side route's page
+            let rules = &server.static_routes;
+            let route = format!("/{}", filename);
+            let file = rules.index(&server.static_dir, &route).or_else(|| {
+                static_routes::accepts_html(req_headers)
+                    .then(|| rules.fallback(&server.static_dir, &route))
+                    .flatten()
+            });
+            return match file {
+                Some(file) if file != filename => {
+                    serve_static(stream, server, &format!("/{}", file), extra_headers, req_headers)
+                }
+                _ => write_not_found(stream, server, extra_headers, req_headers),
+            };
         }
     };
 
@@ -1290,10 +2360,17 @@ fn serve_static(
     };
 
     let eh = format_extra_headers(extra_headers);
+    let v = Validators::for_asset(&server.manifest, filename, &data, &file_path);
+    if v.not_modified(req_headers) {
+        let headers = format!("Cache-Control: {}\r\n{}", cache, eh);
+        return cache::write_not_modified(stream, &v, ct, &headers);
+    }
+    // A hashed asset's precompressed file, if the client takes one
+    let (data, ce) = compress::read_variant(&file_path, req_headers).unwrap_or((data, ""));
     let resp = format!(
-        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}{}\
         Cache-Control: {}\r\n{}\r\n",
-        ct, data.len(), cache, eh
+        ct, data.len(), ce, v.headers(), cache, eh
     );
     stream.write_all(resp.as_bytes())?;
     stream.write_all(&data)
@@ -1313,15 +2390,16 @@ pub fn v8_result_to_json(result: V8Result, action: Option<&str>) -> String {
     }
 }
 
-pub fn write_sse_event(stream: &mut TcpStream, data: &[u8]) -> std::io::Result<()> {
-    stream.write_all(b"event: message\ndata: ")?;
-    stream.write_all(data)?;
-    stream.write_all(b"\n\n")?;
-    stream.flush()
+/// Write a snapshot SSE event, with its resume id (resume.rs) if it has one.
+pub fn write_sse_event(stream: &mut impl Write, id: Option<u64>, data: &[u8]) -> std::io::Result<()> {
+    write_sse_named(stream, "message", id, data)
 }
 
 /// Write a named SSE event (e.g. "delta") to a browser client stream.
-pub fn write_sse_named(stream: &mut TcpStream, event: &str, data: &[u8]) -> std::io::Result<()> {
+pub fn write_sse_named(stream: &mut impl Write, event: &str, id: Option<u64>, data: &[u8]) -> std::io::Result<()> {
+    if let Some(id) = id {
+        writeln!(stream, "id: {}", id)?;
+    }
     stream.write_all(b"event: ")?;
     stream.write_all(event.as_bytes())?;
     stream.write_all(b"\ndata: ")?;
@@ -1330,6 +2408,33 @@ pub fn write_sse_named(stream: &mut TcpStream, event: &str, data: &[u8]) -> std:
     stream.flush()
 }
 
+/// Record a snapshot in the session's history and queue it for every
+/// client of the session (outbox.rs), dropping the ones that are gone (and
+/// the session's entry once none are left).
+pub fn broadcast_snapshot(
+    clients: &Mutex<HashMap<String, Vec<Client>>>,
+    history: &resume::History,
+    deltas: &delta::Deltas,
+    session_id: &str,
+    snapshot: &str,
+) {
+    let _span = trace::span("sse.broadcast");
+    // Ids and deltas are worked out under the registry lock so clients get
+    // events in the order they were computed
+    let mut clients = clients.lock().unwrap();
+    let id = history.record(session_id, snapshot);
+    let patches = deltas.next(session_id, snapshot);
+    if let Some(list) = clients.get_mut(session_id) {
+        let snapshot: Arc<str> = snapshot.into();
+        let patches: Option<Arc<str>> = patches.map(Into::into);
+        list.retain(|c| c.send_snapshot(id, &snapshot, patches.as_ref()).is_ok());
+        trace::attr("magnetic.clients", list.len());
+        if list.is_empty() {
+            clients.remove(session_id);
+        }
+    }
+}
+
 pub fn guess_content_type(path: &str) -> &str {
     if path.ends_with(".js") { "application/javascript" }
     else if path.ends_with(".css") { "text/css" }
@@ -1351,9 +2456,12 @@ pub fn status_text(code: u16) -> &'static str {
         301 => "Moved Permanently",
         302 => "Found",
         304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
         400 => "Bad Request",
         403 => "Forbidden",
         404 => "Not Found",
+        405 => "Method Not Allowed",
         429 => "Too Many Requests",
         500 => "Internal Server Error",
         _ => "OK",
@@ -1377,3 +2485,85 @@ pub fn urlencoding_decode(s: &str) -> String {
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    /// Send `raw` on a fresh connection served by `serve_connection`, and
+    /// return what the client got back plus the paths the handler saw
+    fn exchange(raw: &'static [u8]) -> (String, Vec<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut tcp = TcpStream::connect(addr).unwrap();
+            tcp.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+            tcp.write_all(raw).unwrap();
+            let mut out = String::new();
+            tcp.read_to_string(&mut out).unwrap();
+            out
+        });
+        let (tcp, _) = listener.accept().unwrap();
+        let limits = Limits {
+            max_body: 1024,
+            header_timeout: Duration::from_secs(5),
+            conn_timeout: Duration::from_secs(5),
+        };
+        let mut seen = Vec::new();
+        serve_connection(Conn::new(tcp, None).unwrap(), limits, |stream, req| {
+            seen.push(req.path);
+            stream.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+        })
+        .unwrap();
+        (client.join().unwrap(), seen)
+    }
+
+    #[test]
+    fn keep_alive_reads_each_body() {
+        let (out, seen) = exchange(
+            b"POST /a HTTP/1.1\r\nContent-Length: 5\r\n\r\nhelloGET /b HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        assert_eq!(seen, ["/a", "/b"]);
+        assert_eq!(out.matches("204").count(), 2);
+    }
+
+    #[test]
+    fn transfer_encoding_is_refused_and_closes() {
+        let (out, seen) = exchange(
+            b"POST /a HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1a\r\nGET /smuggled HTTP/1.1\r\n\r\n\r\n0\r\n\r\n",
+        );
+        assert!(out.starts_with("HTTP/1.1 501 "), "{}", out);
+        assert!(out.contains("Connection: close"));
+        assert!(seen.is_empty());
+    }
+
+    #[test]
+    fn bad_content_length_is_refused_and_closes() {
+        for raw in [
+            &b"POST /a HTTP/1.1\r\nContent-Length: abc\r\n\r\nGET /smuggled HTTP/1.1\r\n\r\n"[..],
+            b"POST /a HTTP/1.1\r\nContent-Length: +5\r\n\r\nhello",
+            b"POST /a HTTP/1.1\r\nContent-Length: 99999999999999999999999\r\n\r\n",
+            b"POST /a HTTP/1.1\r\nContent-Length: 5, 5\r\n\r\nhello",
+        ] {
+            let (out, seen) = exchange(raw);
+            assert!(out.starts_with("HTTP/1.1 400 "), "{}", out);
+            assert!(seen.is_empty());
+        }
+    }
+
+    #[test]
+    fn repeated_content_length_is_refused() {
+        // Conflicting, and equal but duplicated
+        for raw in [
+            &b"POST /a HTTP/1.1\r\nContent-Length: 0\r\nContent-Length: 27\r\n\r\nGET /smuggled HTTP/1.1\r\n\r\n"[..],
+            b"POST /a HTTP/1.1\r\nContent-Length: 5\r\ncontent-length: 5\r\n\r\nhello",
+        ] {
+            let (out, seen) = exchange(raw);
+            assert!(out.starts_with("HTTP/1.1 400 "), "{}", out);
+            assert!(out.contains("Connection: close"));
+            assert!(seen.is_empty());
+        }
+    }
+}
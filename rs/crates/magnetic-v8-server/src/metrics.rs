@@ -0,0 +1,154 @@
+//! metrics.rs — Per-app usage on the platform, for quotas and billing
+//!
+//! In `--platform` mode each app's usage is counted from the server's
+//! start, by app name, so it carries over deploys and reloads:
+//! - `requests`: requests routed to the app (`/apps/<name>/*` or its
+//!   subdomain);
+//! - `actions`: actions run, over HTTP or WebSocket, navigations included;
+//! - `fetch_failures`: failed data source fetches (data.rs);
+//! - `clients`: live SSE and WebSocket clients now, and the most at once;
+//! - render times of the app's isolates (stats.rs): percentiles of the
+//!   last RENDER_SAMPLES, and the count and total of all of them.
+//!
+//! `GET /api/apps/<name>/metrics` returns one app's as JSON, with the same
+//! token or key as deploys:
+//!
+//! ```json
+//! {"requests":1200,"actions":310,"fetch_failures":2,
+//!  "renders":{"count":1480,"p50_ms":1.8,"p95_ms":6.4,"p99_ms":12.1,"samples":1000},
+//!  "render_timeouts":0,"clients":{"current":4,"peak":17}}
+//! ```
+//!
+//! `GET /metrics` returns every app's in the Prometheus text format,
+//! labelled `app="<name>"`, with the server's uptime and request count.
+//! With `--deploy-token` it needs the account token (deploy_tokens.rs),
+//! which Prometheus sends as `authorization: {credentials: ...}`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+
+use crate::stats::{ms, V8Stats};
+
+pub const PROMETHEUS_PATH: &str = "/metrics";
+
+/// Render time percentiles reported
+const QUANTILES: [usize; 3] = [50, 95, 99];
+
+/// One app's usage
+#[derive(Default)]
+pub struct AppMetrics {
+    requests: AtomicU64,
+    actions: AtomicU64,
+    fetch_failures: AtomicU64,
+    clients: AtomicUsize,
+    clients_peak: AtomicUsize,
+    /// Queue and render times of the app's isolates, for every version
+    pub v8: Arc<V8Stats>,
+}
+
+static APPS: Mutex<BTreeMap<String, Arc<AppMetrics>>> = Mutex::new(BTreeMap::new());
+
+/// The app's metrics, made on first use
+pub fn app(name: &str) -> Arc<AppMetrics> {
+    Arc::clone(APPS.lock().unwrap().entry(name.to_string()).or_default())
+}
+
+impl AppMetrics {
+    pub fn request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn action(&self) {
+        self.actions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn fetch_failed(&self) {
+        self.fetch_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The app has `n` live clients
+    pub fn clients(&self, n: usize) {
+        self.clients.store(n, Ordering::Relaxed);
+        self.clients_peak.fetch_max(n, Ordering::Relaxed);
+    }
+
+    /// The `/api/apps/<name>/metrics` body
+    pub fn json(&self) -> Value {
+        let (count, _) = self.v8.render_totals();
+        let mut renders = json!({ "count": count });
+        if let Some((p, samples)) = self.v8.render_percentiles(&QUANTILES) {
+            for (q, d) in QUANTILES.iter().zip(p) {
+                renders[format!("p{}_ms", q)] = ms(d).into();
+            }
+            renders["samples"] = samples.into();
+        }
+        json!({
+            "requests": self.requests.load(Ordering::Relaxed),
+            "actions": self.actions.load(Ordering::Relaxed),
+            "fetch_failures": self.fetch_failures.load(Ordering::Relaxed),
+            "renders": renders,
+            "render_timeouts": self.v8.timeouts(),
+            "clients": {
+                "current": self.clients.load(Ordering::Relaxed),
+                "peak": self.clients_peak.load(Ordering::Relaxed),
+            },
+        })
+    }
+}
+
+/// Every app's metrics in the Prometheus text exposition format
+pub fn prometheus() -> String {
+    let apps: Vec<(String, Arc<AppMetrics>)> =
+        APPS.lock().unwrap().iter().map(|(name, m)| (name.clone(), Arc::clone(m))).collect();
+    let mut out = String::new();
+
+    family(&mut out, "magnetic_uptime_seconds", "gauge", "Seconds since the server started.");
+    let _ = writeln!(out, "magnetic_uptime_seconds {}", crate::stats::uptime_secs());
+    family(&mut out, "magnetic_requests_total", "counter", "Requests served.");
+    let _ = writeln!(out, "magnetic_requests_total {}", crate::stats::requests());
+
+    type Sample = fn(&AppMetrics) -> u64;
+    let per_app: [(&str, &str, &str, Sample); 6] = [
+        ("magnetic_app_requests_total", "counter", "Requests routed to the app.", |m| m.requests.load(Ordering::Relaxed)),
+        ("magnetic_app_actions_total", "counter", "Actions the app ran.", |m| m.actions.load(Ordering::Relaxed)),
+        ("magnetic_app_fetch_failures_total", "counter", "Failed data source fetches.", |m| m.fetch_failures.load(Ordering::Relaxed)),
+        ("magnetic_app_render_timeouts_total", "counter", "Renders and actions stopped at the time limit.", |m| m.v8.timeouts() as u64),
+        ("magnetic_app_clients", "gauge", "Live SSE and WebSocket clients.", |m| m.clients.load(Ordering::Relaxed) as u64),
+        ("magnetic_app_clients_peak", "gauge", "Most live SSE and WebSocket clients at once.", |m| m.clients_peak.load(Ordering::Relaxed) as u64),
+    ];
+    for (name, kind, help, value) in per_app {
+        family(&mut out, name, kind, help);
+        for (app, m) in &apps {
+            let _ = writeln!(out, "{}{{app=\"{}\"}} {}", name, label(app), value(m));
+        }
+    }
+
+    let name = "magnetic_app_render_seconds";
+    family(&mut out, name, "summary", "Render time in V8; quantiles of the recent renders.");
+    for (app, m) in &apps {
+        let app = label(app);
+        if let Some((p, _)) = m.v8.render_percentiles(&QUANTILES) {
+            for (q, d) in QUANTILES.iter().zip(p) {
+                let _ = writeln!(out, "{}{{app=\"{}\",quantile=\"{}\"}} {}", name, app, *q as f64 / 100.0, d.as_secs_f64());
+            }
+        }
+        let (count, total) = m.v8.render_totals();
+        let _ = writeln!(out, "{}_sum{{app=\"{}\"}} {}", name, app, total.as_secs_f64());
+        let _ = writeln!(out, "{}_count{{app=\"{}\"}} {}", name, app, count);
+    }
+    out
+}
+
+/// A metric family's HELP and TYPE lines
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+}
+
+/// A label value, escaped
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,133 @@
+//! optimistic.rs — Optimistic updates for action mappings
+//!
+//! An action mapping's `optimistic` template changes its `target` data key
+//! before the action is forwarded, so the session sees the result at once.
+//! If the forward fails, the target goes back to what it was. The template
+//! is one operation:
+//! - `{"append": item}` / `{"prepend": item}`: add to a list;
+//! - `{"remove": match}`: drop the list items that match;
+//! - `{"update": {"match": match, "set": fields}}`: merge `fields` into the
+//!   list items that match;
+//! - `{"merge": fields}`: merge `fields` into an object;
+//! - `{"replace": value}`: the whole value.
+//!
+//! A `match` is an object whose fields an item must all equal. In the
+//! templates, a string that is exactly `${payload.x}` (or `${payload.a.b}`)
+//! stands for that payload value, with its JSON type; inside a longer
+//! string it's spliced in as text.
+
+use serde_json::{Map, Value};
+
+const OPS: &[&str] = &["append", "prepend", "remove", "update", "merge", "replace"];
+
+/// Check a template's shape (at config load).
+pub fn check(op: &Value) -> Result<(), String> {
+    let (name, arg) = single(op)?;
+    match name {
+        "remove" | "merge" if !arg.is_object() => Err(format!("{} takes an object", name)),
+        "update" if !arg.get("match").is_some_and(Value::is_object) || !arg.get("set").is_some_and(Value::is_object) => {
+            Err("update takes {\"match\": {...}, \"set\": {...}}".into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The target's value after `op`, from `current` (None: not loaded).
+pub fn apply(op: &Value, current: Option<&Value>, payload: &Value) -> Result<Value, String> {
+    let (name, arg) = single(op)?;
+    let arg = render(arg, payload);
+    let list = || match current {
+        Some(Value::Array(items)) => Ok(items.clone()),
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(_) => Err(format!("{} needs the target to be a list", name)),
+    };
+    Ok(match name {
+        "append" => {
+            let mut items = list()?;
+            items.push(arg);
+            Value::Array(items)
+        }
+        "prepend" => {
+            let mut items = list()?;
+            items.insert(0, arg);
+            Value::Array(items)
+        }
+        "remove" => Value::Array(list()?.into_iter().filter(|item| !matches(item, &arg)).collect()),
+        "update" => {
+            let set = arg.get("set").cloned().unwrap_or_default();
+            let items = list()?
+                .into_iter()
+                .map(|item| if matches(&item, &arg["match"]) { merge(item, &set) } else { item })
+                .collect();
+            Value::Array(items)
+        }
+        "merge" => merge(current.cloned().unwrap_or_else(|| Value::Object(Map::new())), &arg),
+        _ => arg,
+    })
+}
+
+/// The template's one operation and its argument
+fn single(op: &Value) -> Result<(&str, &Value), String> {
+    match op.as_object() {
+        Some(obj) if obj.len() == 1 => {
+            let (name, arg) = obj.iter().next().unwrap();
+            if OPS.contains(&name.as_str()) {
+                Ok((name, arg))
+            } else {
+                Err(format!("unknown operation '{}' (one of {})", name, OPS.join(", ")))
+            }
+        }
+        _ => Err(format!("expected one operation, as {{\"<op>\": ...}} (one of {})", OPS.join(", "))),
+    }
+}
+
+/// `template` with `${payload...}` filled in
+fn render(template: &Value, payload: &Value) -> Value {
+    match template {
+        Value::String(s) => {
+            if let Some(path) = s.strip_prefix("${payload.").and_then(|p| p.strip_suffix('}')).filter(|p| !p.contains('}')) {
+                return lookup(payload, path).cloned().unwrap_or(Value::Null);
+            }
+            let mut out = String::new();
+            let mut rest = s.as_str();
+            while let Some(start) = rest.find("${payload.") {
+                let Some(end) = rest[start..].find('}') else { break };
+                out.push_str(&rest[..start]);
+                match lookup(payload, &rest[start + 10..start + end]) {
+                    Some(Value::String(v)) => out.push_str(v),
+                    Some(Value::Null) | None => {}
+                    Some(v) => out.push_str(&v.to_string()),
+                }
+                rest = &rest[start + end + 1..];
+            }
+            out.push_str(rest);
+            Value::String(out)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| render(v, payload)).collect()),
+        Value::Object(obj) => Value::Object(obj.iter().map(|(k, v)| (k.clone(), render(v, payload))).collect()),
+        other => other.clone(),
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |v, key| v.get(key))
+}
+
+/// Whether `item` has every field of `pattern`
+fn matches(item: &Value, pattern: &Value) -> bool {
+    match (item.as_object(), pattern.as_object()) {
+        (Some(item), Some(pattern)) => pattern.iter().all(|(k, v)| item.get(k) == Some(v)),
+        _ => false,
+    }
+}
+
+/// `fields` merged over `target` (an object)
+fn merge(target: Value, fields: &Value) -> Value {
+    match (target, fields.as_object()) {
+        (Value::Object(mut obj), Some(fields)) => {
+            obj.extend(fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+            Value::Object(obj)
+        }
+        (target, _) => target,
+    }
+}
@@ -0,0 +1,283 @@
+//! outbox.rs — Per-client send queues for SSE and WebSocket clients
+//!
+//! Each client in the session registry has a bounded queue and a writer
+//! thread of its own. Broadcasting only queues the message, so a client
+//! that reads slowly holds up neither the other clients of its session nor
+//! the registry lock.
+//!
+//! - A full queue drops its oldest message. Every snapshot carries the
+//!   whole view, so a client that falls behind skips to the newest one.
+//!   After a snapshot is dropped the next one goes out whole, never as a
+//!   delta (delta.rs), since the client missed the base it patches.
+//! - A client that has had QUEUE_LEN messages dropped since its last
+//!   completed write can't keep up, and is disconnected. Its EventSource
+//!   reconnects and resumes (resume.rs), or gets a fresh snapshot.
+//! - A write that doesn't finish within SSE_WRITE_TIMEOUT disconnects too.
+//!
+//! A disconnected client fails its next send, and the registry drops it
+//! then (at the latest, at the next keepalive).
+
+use std::collections::VecDeque;
+use std::io;
+use std::net::Shutdown;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crate::compress::SseStream;
+use crate::conn::Conn;
+use crate::ws::WsSender;
+use crate::{write_sse_event, write_sse_named};
+
+/// Messages a client may have waiting
+const QUEUE_LEN: usize = 32;
+
+/// A browser connection that snapshots are pushed to: an SSE stream or a
+/// WebSocket (ws.rs). Clones share the queue; the writer stops once the
+/// last one is dropped.
+#[derive(Clone)]
+pub struct Client(Arc<Handle>);
+
+struct Handle(Arc<Outbox>);
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+struct Outbox {
+    state: Mutex<State>,
+    /// Signalled when a message is queued, the queue closes or the writer
+    /// exits
+    changed: Condvar,
+    /// Another handle to the socket, to shut it under a blocked writer
+    socket: Conn,
+}
+
+#[derive(Default)]
+struct State {
+    queue: VecDeque<Msg>,
+    /// Messages dropped since the writer last finished a write
+    behind: usize,
+    /// A snapshot was dropped: the next one goes out whole
+    resync: bool,
+    /// No more messages are taken; the writer exits once the queue is empty
+    closed: bool,
+    /// The writer has exited
+    done: bool,
+}
+
+enum Msg {
+    Snapshot { id: Option<u64>, snapshot: Arc<str>, delta: Option<Arc<str>> },
+    Event { event: &'static str, data: Arc<str> },
+    Keepalive,
+    Reload,
+    Reconnect,
+}
+
+enum Sink {
+    Sse(SseStream),
+    Ws(WsSender),
+}
+
+impl Client {
+    /// Register an SSE stream whose response head and first events have
+    /// been written
+    pub fn sse(stream: SseStream) -> io::Result<Client> {
+        let socket = stream.get_ref().try_clone()?;
+        Client::spawn("sse-writer", Sink::Sse(stream), socket)
+    }
+
+    /// Register an upgraded WebSocket; `conn` is its connection
+    pub fn ws(sender: WsSender, conn: &Conn) -> io::Result<Client> {
+        Client::spawn("ws-writer", Sink::Ws(sender), conn.try_clone()?)
+    }
+
+    fn spawn(name: &str, mut sink: Sink, socket: Conn) -> io::Result<Client> {
+        let outbox = Arc::new(Outbox { state: Mutex::default(), changed: Condvar::new(), socket });
+        let writer = Arc::clone(&outbox);
+        thread::Builder::new().name(name.into()).spawn(move || {
+            writer.write_all(&mut sink);
+            sink.close();
+            let _ = writer.socket.shutdown(Shutdown::Both);
+            let mut state = writer.state.lock().unwrap();
+            state.closed = true;
+            state.done = true;
+            state.queue.clear();
+            writer.changed.notify_all();
+        })?;
+        Ok(Client(Arc::new(Handle(outbox))))
+    }
+
+    fn outbox(&self) -> &Outbox {
+        &self.0 .0
+    }
+
+    /// Queue a `{"root":...}` snapshot. SSE clients get its id, and the
+    /// patch list instead of the snapshot when there is one (delta.rs).
+    /// Fails once the client is gone.
+    pub fn send_snapshot(&self, id: Option<u64>, snapshot: &Arc<str>, delta: Option<&Arc<str>>) -> io::Result<()> {
+        self.outbox().push(Msg::Snapshot { id, snapshot: Arc::clone(snapshot), delta: delta.cloned() })
+    }
+
+    /// Queue a named event, such as a log line (logs.rs). Fails once the
+    /// client is gone.
+    pub fn send_event(&self, event: &'static str, data: &Arc<str>) -> io::Result<()> {
+        self.outbox().push(Msg::Event { event, data: Arc::clone(data) })
+    }
+
+    /// SSE comment or WebSocket ping, unless messages are already waiting;
+    /// fails once the client is gone
+    pub fn keepalive(&self) -> io::Result<()> {
+        let outbox = self.outbox();
+        let mut state = outbox.state.lock().unwrap();
+        if state.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        if state.queue.is_empty() {
+            state.queue.push_back(Msg::Keepalive);
+            outbox.changed.notify_all();
+        }
+        Ok(())
+    }
+
+    /// Tell the page to reload itself (--watch)
+    pub fn reload(&self) -> io::Result<()> {
+        self.outbox().push(Msg::Reload)
+    }
+
+    /// Last message before a shutdown: SSE `event: reconnect`, or a
+    /// WebSocket close with 1012 (service restart). Whatever was still
+    /// queued is dropped; `wait` for it to go out.
+    pub fn reconnect(&self) {
+        let outbox = self.outbox();
+        let mut state = outbox.state.lock().unwrap();
+        if !state.closed {
+            state.queue.clear();
+            state.queue.push_back(Msg::Reconnect);
+            state.closed = true;
+            outbox.changed.notify_all();
+        }
+    }
+
+    /// Stop taking messages: the writer sends what is queued, closes the
+    /// connection and exits
+    pub fn close(&self) {
+        self.outbox().close();
+    }
+
+    /// Wait until the writer has exited, or `deadline`
+    pub fn wait(&self, deadline: Instant) {
+        let outbox = self.outbox();
+        let mut state = outbox.state.lock().unwrap();
+        while !state.done {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else { return };
+            state = outbox.changed.wait_timeout(state, left).unwrap().0;
+        }
+    }
+}
+
+impl Outbox {
+    fn push(&self, msg: Msg) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        if state.queue.len() >= QUEUE_LEN {
+            // A reload has to arrive; anything else is superseded by what
+            // follows it
+            let oldest = state.queue.iter().position(|m| !matches!(m, Msg::Reload)).unwrap_or(0);
+            if matches!(state.queue.remove(oldest), Some(Msg::Snapshot { .. })) {
+                state.resync = true;
+            }
+            state.behind += 1;
+            if state.behind >= QUEUE_LEN {
+                drop(state);
+                eprintln!("[magnetic] disconnecting a client that can't keep up");
+                self.disconnect();
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "client can't keep up"));
+            }
+        }
+        state.queue.push_back(msg);
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    /// Stop taking messages; the writer finishes the queue and exits
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.changed.notify_all();
+    }
+
+    /// Drop the queue and shut the socket, which fails a blocked write
+    fn disconnect(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.queue.clear();
+        self.changed.notify_all();
+        drop(state);
+        let _ = self.socket.shutdown(Shutdown::Both);
+    }
+
+    /// The writer thread: send queued messages until the queue is closed
+    /// and empty, or a write fails
+    fn write_all(&self, sink: &mut Sink) {
+        loop {
+            let (msg, whole) = {
+                let mut state = self.state.lock().unwrap();
+                let msg = loop {
+                    if let Some(msg) = state.queue.pop_front() {
+                        break msg;
+                    }
+                    if state.closed {
+                        return;
+                    }
+                    state = self.changed.wait(state).unwrap();
+                };
+                let whole = matches!(msg, Msg::Snapshot { .. }) && std::mem::take(&mut state.resync);
+                (msg, whole)
+            };
+            if sink.write(msg, whole).is_err() {
+                self.disconnect();
+                return;
+            }
+            self.state.lock().unwrap().behind = 0;
+        }
+    }
+}
+
+impl Sink {
+    /// Write one message; `whole` sends a snapshot without its delta
+    fn write(&mut self, msg: Msg, whole: bool) -> io::Result<()> {
+        match (self, msg) {
+            (Sink::Sse(s), Msg::Snapshot { id, snapshot, delta }) => match delta {
+                Some(patches) if !whole => write_sse_named(s, "delta", id, patches.as_bytes()),
+                _ => write_sse_event(s, id, snapshot.as_bytes()),
+            },
+            (Sink::Ws(w), Msg::Snapshot { snapshot, .. }) => w.send("snapshot", snapshot.as_bytes()),
+            (Sink::Sse(s), Msg::Event { event, data }) => write_sse_named(s, event, None, data.as_bytes()),
+            (Sink::Ws(w), Msg::Event { event, data }) => w.send(event, data.as_bytes()),
+            (Sink::Sse(s), Msg::Keepalive) => {
+                io::Write::write_all(s, b": keepalive\n\n").and_then(|_| io::Write::flush(s))
+            }
+            (Sink::Ws(w), Msg::Keepalive) => w.ping(),
+            (Sink::Sse(s), Msg::Reload) => write_sse_named(s, "reload", None, b"{}"),
+            (Sink::Ws(w), Msg::Reload) => w.send("reload", b"null"),
+            (Sink::Sse(s), Msg::Reconnect) => write_sse_named(s, "reconnect", None, b"{}"),
+            (Sink::Ws(w), Msg::Reconnect) => {
+                w.close_with(1012);
+                Ok(())
+            }
+        }
+    }
+
+    /// End the stream: a WebSocket close frame, or an SSE stream's gzip
+    /// trailer (written when it drops)
+    fn close(self) {
+        match self {
+            Sink::Sse(s) => drop(s),
+            Sink::Ws(w) => w.close(),
+        }
+    }
+}
@@ -9,10 +9,8 @@
 //! { "bundle": "<js source>", "assets": { "file.css": "<content>", ... } }
 
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Read, Write};
-use std::net::{TcpListener, TcpStream};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -23,18 +21,47 @@ use magnetic_render_html::{render_page, PageOptions};
 use crate::{
     V8Request, V8Result, Reply, AssetManifest,
     MagneticContext, MiddlewareStack,
-    v8_thread, v8_result_to_json, error_fallback,
-    write_sse_event, write_sse_named, guess_content_type,
-    format_extra_headers, status_text, urlencoding_decode,
-    cors_middleware, rate_limit_middleware, logger_middleware,
-    build_assets, find_arg, serve_embedded,
+    v8_result_to_json,
+    write_sse_event, guess_content_type, broadcast_snapshot,
+    format_extra_headers, status_text, write_short_circuit, urlencoding_decode, serve_connection, Request,
+    middleware_arg,
+    build_assets, find_arg, serve_embedded, allows, write_allow,
+    SSE_KEEPALIVE_SECS, SSE_WRITE_TIMEOUT,
 };
-use crate::data::{DataContext, parse_config, fetch_page_data, fetch_page_data_with_token, fetch_page_data_streaming, forward_action, start_poll_threads, start_sse_threads, start_ws_threads, fetch_data_source};
-use crate::auth::AuthMiddleware;
+use crate::cache::{self, Validators};
+use crate::compress::{self, SseStream};
+use crate::conn::{self, Conn};
+use crate::outbox::Client;
+use crate::csrf;
+use crate::error_page;
+use crate::ws;
+use crate::pool::WorkerPool;
+use crate::delta::{self, Deltas};
+use crate::deploy_tokens::DeployTokens;
+use crate::idempotency::{self, Keys, Outcome};
+use crate::limits::Limits;
+use crate::upload;
+use crate::versions;
+use crate::locale::{self, Locales};
+use crate::proxy::ProxyRoutes;
+use crate::metrics::AppMetrics;
+use crate::quotas::{Quota, Quotas};
+use crate::redirects;
+use crate::seo::{self, Seo};
+use crate::static_routes::{self, StaticRoutes};
+use crate::isolates::V8Pool;
+use crate::resume::History;
+use crate::snapshot::{self, Bundle};
+use crate::data::{DataContext, parse_config, fetch_page_data, fetch_page_data_with_token, fetch_page_data_streaming, fetch_next_page, forward_action, start_poll_threads, start_sse_threads, start_ws_threads, start_file_threads, resolve_files, PAGINATE_ACTION};
+use crate::auth::{Access, AuthMiddleware};
 
 // ── Idle timeout for V8 parking ──────────────────────────────────────
 
 const PARK_IDLE_SECS: u64 = 300; // 5 minutes
+/// Retry-After for a client turned away at the app's client limit
+const CLIENTS_FULL_RETRY_SECS: u64 = 30;
+/// How long a deploy's health render of `/` may take
+const HEALTH_RENDER_TIMEOUT: Duration = Duration::from_secs(10);
 const REAPER_INTERVAL_SECS: u64 = 30;
 
 // ── Per-app handle ──────────────────────────────────────────────────
@@ -43,22 +70,47 @@ struct AppHandle {
     name: String,
     /// True for SSG/static deployments — no V8, serve files from static_dir
     is_static: bool,
-    v8_tx: Mutex<Option<mpsc::Sender<V8Request>>>,
+    v8_tx: Mutex<Option<V8Pool>>,
     parked: AtomicBool,
     last_activity: Mutex<Instant>,
-    /// Per-session SSE clients: session_id → list of TcpStream clones
-    sse_clients: Mutex<HashMap<String, Vec<TcpStream>>>,
+    /// Per-session SSE clients: session_id → list of client streams
+    sse_clients: Mutex<HashMap<String, Vec<Client>>>,
+    /// Recent snapshots per session, for Last-Event-ID resume
+    sse_history: History,
+    /// Last snapshot per session, for --sse-delta
+    sse_deltas: Deltas,
+    /// Recent Idempotency-Keys per session, with their snapshots
+    idempotency: Keys,
     /// Per-session current path: session_id → path
     session_paths: Mutex<HashMap<String, String>>,
+    /// Per-session signed-in user (auth): session_id → `data.user`
+    session_users: Mutex<HashMap<String, serde_json::Value>>,
     static_dir: String,
     asset_dir: String,
     inline_css: Option<String>,
     manifest: AssetManifest,
+    /// Directory index, fallback and pass-through rules (static_routes.rs)
+    routes: StaticRoutes,
+    /// Redirects and rewrites (redirects.rs)
+    redirects: redirects::Rules,
+    /// Prefixes forwarded to backends (proxy.rs)
+    proxy: ProxyRoutes,
+    /// Declared locales and each session's pick (locale.rs)
+    locales: Locales,
+    session_locales: locale::Sessions,
+    /// robots.txt and sitemap.xml settings (seo.rs)
+    seo: Seo,
     data_dir: String,
     /// Declarative data layer context (if magnetic.json has data/actions config)
     data_ctx: Option<Arc<DataContext>>,
     /// Auth middleware (if magnetic.json has auth config)
     auth: Option<Arc<AuthMiddleware>>,
+    /// Resource limits (quotas.rs)
+    quota: Quota,
+    /// SSE and WebSocket clients turned away at `quota.sse_clients`
+    clients_refused: AtomicUsize,
+    /// Usage, kept across deploys (metrics.rs)
+    metrics: Arc<AppMetrics>,
 }
 
 impl AppHandle {
@@ -68,7 +120,7 @@ impl AppHandle {
     }
 
     /// Ensure V8 thread is available. Returns sender or error string.
-    fn ensure_warm(&self) -> Result<mpsc::Sender<V8Request>, String> {
+    fn ensure_warm(&self) -> Result<V8Pool, String> {
         let guard = self.v8_tx.lock().unwrap();
         if let Some(ref tx) = *guard {
             if self.parked.load(Ordering::Acquire) {
@@ -98,9 +150,74 @@ impl AppHandle {
         self.sse_clients.lock().unwrap().values().map(|v| v.len()).sum()
     }
 
+    /// Note the live client count in the app's metrics
+    fn note_clients(&self) {
+        self.metrics.clients(self.sse_client_count());
+    }
+
+    /// Whether a new client would go over `quota.sse_clients`. An SSE
+    /// client replaces its session's (`replacing`), so those aren't counted.
+    fn clients_full(&self, replacing: Option<&str>) -> bool {
+        let clients = self.sse_clients.lock().unwrap();
+        let count: usize = clients
+            .iter()
+            .filter(|(session_id, _)| Some(session_id.as_str()) != replacing)
+            .map(|(_, list)| list.len())
+            .sum();
+        drop(clients);
+        let full = self.quota.clients_full(count);
+        if full {
+            self.clients_refused.fetch_add(1, Ordering::Relaxed);
+        }
+        full
+    }
+
     fn idle_secs(&self) -> u64 {
         self.last_activity.lock().unwrap().elapsed().as_secs()
     }
+
+    /// Take over the sessions of the version this one replaces (deploys)
+    fn take_sessions(&self, old: &AppHandle) {
+        let paths = old.session_paths.lock().unwrap().clone();
+        self.session_paths.lock().unwrap().extend(paths);
+        let users = old.session_users.lock().unwrap().clone();
+        self.session_users.lock().unwrap().extend(users);
+        self.session_locales.copy_from(&old.session_locales);
+    }
+
+    /// Note who is signed in on a session (auth), for its renders,
+    /// including those without a request (data updates, deferred data).
+    fn note_user(&self, session_id: &str, req_headers: &HashMap<String, String>) {
+        if let Some(auth) = &self.auth {
+            let user = auth.user(req_headers).unwrap_or(serde_json::Value::Null);
+            self.session_users.lock().unwrap().insert(session_id.to_string(), user);
+        }
+    }
+
+    /// Forget the per-session data of the request's session, on logout.
+    fn drop_session_data(&self, req_headers: &HashMap<String, String>) {
+        if let (Some(ctx), Some(session_id)) = (&self.data_ctx, crate::extract_session_cookie(req_headers)) {
+            ctx.drop_session(&session_id);
+        }
+    }
+
+    /// Data to inject before rendering a session's page: the data layer's
+    /// values for `path` (per-session sources from this session's own), and
+    /// with auth `user` (null when signed out). The isolate keeps injected
+    /// data between renders, so with auth every render sets it, or one
+    /// session's user or data could show on another's page. None for an
+    /// app with neither.
+    fn render_data(&self, session_id: &str, path: &str) -> Option<String> {
+        if self.data_ctx.is_none() && self.auth.is_none() {
+            return None;
+        }
+        let mut data = self.data_ctx.as_ref().map(|ctx| ctx.data_for_session(session_id, path)).unwrap_or_default();
+        if self.auth.is_some() {
+            let user = self.session_users.lock().unwrap().get(session_id).cloned();
+            data.insert("user".into(), user.unwrap_or(serde_json::Value::Null));
+        }
+        Some(serde_json::Value::Object(data).to_string())
+    }
 }
 
 /// Start background data threads (poll + SSE) for an app.
@@ -111,10 +228,12 @@ fn start_data_threads(app: Arc<AppHandle>) {
         None => return,
     };
 
-    let has_poll = ctx.config.data.iter().any(|d| d.source_type == "poll");
+    let has_poll = ctx.config.data.iter().any(|d| d.source_type == "poll" || (d.source_type == "db" && d.refresh.is_some()));
     let has_sse = ctx.config.data.iter().any(|d| d.source_type == "sse");
     let has_ws = ctx.config.data.iter().any(|d| d.source_type == "ws");
-    if !has_poll && !has_sse && !has_ws {
+    let has_file = ctx.config.data.iter().any(|d| d.source_type == "file");
+    let has_webhook = ctx.config.data.iter().any(|d| d.source_type == "webhook");
+    if !has_poll && !has_sse && !has_ws && !has_file && !has_webhook {
         return;
     }
 
@@ -155,32 +274,20 @@ fn start_data_threads(app: Arc<AppHandle>) {
                     None => return,
                 };
                 for (session_id, path) in &sessions {
-                    let data_json = ctx.data_json_for_page(path);
+                    let data_json = app.render_data(session_id, path).unwrap_or_else(|| ctx.data_json_for_page(path));
                     let reply = Reply::new();
                     if tx.send(V8Request::RenderWithData {
                         path: path.clone(),
                         session_id: session_id.clone(),
                         data_json,
+                        locale: app.session_locales.get(session_id),
                         reply: reply.clone(),
                     }).is_err() {
                         continue;
                     }
                     let dom_json = v8_result_to_json(reply.recv(), None);
                     let snapshot = format!("{{\"root\":{}}}", dom_json);
-                    let mut clients = app.sse_clients.lock().unwrap();
-                    if let Some(list) = clients.get_mut(session_id) {
-                        let mut alive = Vec::new();
-                        for mut client in list.drain(..) {
-                            if write_sse_event(&mut client, snapshot.as_bytes()).is_ok() {
-                                alive.push(client);
-                            }
-                        }
-                        if alive.is_empty() {
-                            clients.remove(session_id);
-                        } else {
-                            *list = alive;
-                        }
-                    }
+                    broadcast_snapshot(&app.sse_clients, &app.sse_history, &app.sse_deltas, session_id, &snapshot);
                 }
             });
         })
@@ -192,6 +299,12 @@ fn start_data_threads(app: Arc<AppHandle>) {
     if has_sse {
         start_sse_threads(Arc::clone(&ctx), Arc::clone(&on_change));
     }
+    if has_webhook {
+        ctx.set_on_change(Arc::clone(&on_change));
+    }
+    if has_file {
+        start_file_threads(Arc::clone(&ctx), Arc::clone(&on_change));
+    }
     if has_ws {
         start_ws_threads(Arc::clone(&ctx), on_change);
     }
@@ -203,6 +316,18 @@ pub struct Platform {
     apps: RwLock<HashMap<String, Arc<AppHandle>>>,
     data_dir: String,
     middleware: MiddlewareStack,
+    /// --sse-delta: apps send SSE updates as keyed patches (delta.rs)
+    sse_delta: bool,
+    /// --v8-workers: V8 isolates per app (isolates.rs)
+    v8_workers: usize,
+    /// Per-app resource limits (quotas.rs)
+    quotas: Quotas,
+    /// Request size and time limits (limits.rs)
+    limits: Limits,
+    /// --deploy-token and the tokens made with it (deploy_tokens.rs)
+    deploy_tokens: DeployTokens,
+    /// --keep-versions: deploys kept per app for rollback (versions.rs)
+    keep_versions: usize,
 }
 
 // ── Platform entry point ────────────────────────────────────────────
@@ -210,10 +335,6 @@ pub struct Platform {
 pub fn run_platform(args: &[String]) {
     let port = find_arg(args, "--port").unwrap_or_else(|| "3003".to_string());
     let data_dir = find_arg(args, "--data-dir").unwrap_or_else(|| "data/apps".to_string());
-    let cors_origin = find_arg(args, "--cors").unwrap_or_else(|| "*".to_string());
-    let rate_limit_max: u32 = find_arg(args, "--rate-limit")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(200);
 
     // Ensure data directory exists
     let _ = std::fs::create_dir_all(&data_dir);
@@ -223,20 +344,32 @@ pub fn run_platform(args: &[String]) {
     // could SEGV if V8 internals aren't fully ready.
     crate::ensure_v8_initialized();
 
+    crate::trace::init(&crate::trace::otlp_arg(args));
+    crate::stats::start();
+
     // Build middleware
-    let mut middleware = MiddlewareStack::new();
-    middleware.add(logger_middleware());
-    middleware.add(cors_middleware(&cors_origin));
-    middleware.add(rate_limit_middleware(60_000, rate_limit_max));
+    let (middleware, middleware_desc) = middleware_arg(args, 200, "platform");
 
     let park_idle = find_arg(args, "--park-idle")
         .and_then(|s| s.parse().ok())
         .unwrap_or(PARK_IDLE_SECS);
+    let workers = crate::pool::workers_arg(args);
+    let shutdown_timeout = crate::shutdown::timeout_arg(args);
+    let tls = conn::tls_arg(args);
+    crate::cookie::install(args);
+    crate::secrets::install(args);
+    crate::logs::install(&data_dir);
 
     let platform = Arc::new(Platform {
         apps: RwLock::new(HashMap::new()),
         data_dir: data_dir.clone(),
         middleware,
+        sse_delta: delta::delta_arg(args),
+        v8_workers: crate::isolates::v8_workers_arg(args),
+        quotas: Quotas::from_args(args),
+        limits: crate::limits::limits_arg(args),
+        deploy_tokens: DeployTokens::from_args(args, &data_dir),
+        keep_versions: versions::keep_arg(args),
     });
 
     // Load existing apps from data directory.
@@ -252,7 +385,7 @@ pub fn run_platform(args: &[String]) {
                 let static_marker = entry.path().join("static.marker");
                 let bundle_path = entry.path().join("bundle.js");
                 if static_marker.exists() {
-                    match load_static_app(&name, &data_dir) {
+                    match load_static_app(&name, &data_dir, &platform) {
                         Ok(handle) => {
                             eprintln!("[platform] Loaded static app: {}", name);
                             let app = Arc::new(handle);
@@ -261,7 +394,7 @@ pub fn run_platform(args: &[String]) {
                         Err(e) => eprintln!("[platform] Failed to load static {}: {}", name, e),
                     }
                 } else if bundle_path.exists() {
-                    match load_app(&name, &data_dir) {
+                    match load_app(&name, &data_dir, &platform) {
                         Ok(handle) => {
                             eprintln!("[platform] Loaded app: {}", name);
                             let app = Arc::new(handle);
@@ -282,13 +415,14 @@ pub fn run_platform(args: &[String]) {
     }
 
     let app_count = platform.apps.read().unwrap().len();
-    let addr = format!("0.0.0.0:{}", port);
-    let listener = TcpListener::bind(&addr).expect("Failed to bind");
-    eprintln!("[platform] http://localhost:{}", port);
+    let listeners = crate::cluster::Listeners::bind(args, &port).expect("Failed to bind");
+    eprintln!("[platform] {}://localhost:{}", if tls.is_some() { "https" } else { "http" }, port);
     eprintln!("[platform] Magnetic Platform Server — multi-tenant V8 hosting");
     eprintln!("[platform] Data dir: {}", data_dir);
     eprintln!("[platform] Apps loaded: {}", app_count);
     eprintln!("[platform] V8 park idle: {}s", park_idle);
+    eprintln!("[platform] Middleware: {}", middleware_desc);
+    eprintln!("[platform] Workers: {}", workers);
     eprintln!("[platform] Deploy: POST /api/apps/<name>/deploy");
     eprintln!("[platform] Access: GET /apps/<name>/");
 
@@ -305,42 +439,96 @@ pub fn run_platform(args: &[String]) {
         });
     }
 
-    for stream in listener.incoming() {
+    {
+        let platform_ref = Arc::clone(&platform);
+        thread::spawn(move || sse_keepalive_loop(platform_ref));
+    }
+
+    crate::shutdown::install(listeners.waker().expect("Failed to clone listener"), shutdown_timeout);
+
+    let pool = WorkerPool::new("platform", workers);
+    for stream in listeners.incoming() {
+        if crate::shutdown::is_stopping() { break; }
         let stream = match stream {
             Ok(s) => s,
             Err(e) => { eprintln!("[err] accept: {}", e); continue; }
         };
         let platform = Arc::clone(&platform);
-        thread::spawn(move || {
+        let tls = tls.clone();
+        pool.execute(move || {
+            let Ok(stream) = Conn::new(stream, tls.as_ref()) else { return };
             if let Err(_) = handle_platform_connection(stream, &platform) {}
         });
     }
+
+    // Shutting down: refuse new connections, finish what's running
+    drop(listeners);
+    crate::shutdown::wait_idle();
+    let mut clients: Vec<Client> = Vec::new();
+    for app in platform.apps.read().unwrap().values() {
+        clients.extend(app.sse_clients.lock().unwrap().values().flatten().cloned());
+    }
+    for client in &clients {
+        client.reconnect();
+    }
+    let deadline = Instant::now() + SSE_WRITE_TIMEOUT;
+    for client in &clients {
+        client.wait(deadline);
+    }
+    let count = clients.len();
+    eprintln!("[platform] asked {} client(s) to reconnect, exiting", count);
+    std::process::exit(0);
 }
 
 // ── Load an app from disk ───────────────────────────────────────────
 
-fn load_app(name: &str, data_dir: &str) -> Result<AppHandle, String> {
+fn load_app(name: &str, data_dir: &str, platform: &Platform) -> Result<AppHandle, String> {
     let app_dir = format!("{}/{}", data_dir, name);
     let bundle_path = format!("{}/bundle.js", app_dir);
+    let snapshot_path = format!("{}/snapshot.bin", app_dir);
     let config_path = format!("{}/config.json", app_dir);
     let public_dir = format!("{}/public", app_dir);
 
     let js_source = std::fs::read_to_string(&bundle_path)
         .map_err(|e| format!("Cannot read bundle: {}", e))?;
 
-    // Start V8 thread for this app
-    let (tx, rx) = mpsc::channel();
-    let js = js_source;
-    thread::spawn(move || v8_thread(js, rx));
+    // Build asset pipeline
+    let asset_dir = format!("{}/.hashed", public_dir);
+    let manifest = build_assets(
+        &public_dir, &asset_dir,
+        &["index.html"],
+    );
+
+    // Start V8 isolates for this app, from the deploy-time snapshot if usable
+    let bundle = Bundle::with_snapshot_file(js_source, &snapshot_path, manifest.script());
+    if bundle.snapshot.is_some() {
+        eprintln!("[platform:{}] booting V8 from snapshot", name);
+    }
+    let quota = platform.quotas.get(name);
+    let metrics = crate::metrics::app(name);
+    let tx = V8Pool::start(bundle, platform.v8_workers, quota.isolate_limits(), Some(name), Arc::clone(&metrics.v8));
 
     // Load data layer config (if present)
     let mut data_ctx: Option<Arc<DataContext>> = None;
     let mut auth_mw: Option<Arc<AuthMiddleware>> = None;
+    let mut routes = StaticRoutes::default();
+    let mut redirects = redirects::Rules::default();
+    let mut proxy = ProxyRoutes::default();
+    let mut locales = Locales::default();
+    let mut seo = Seo::default();
 
     if std::path::Path::new(&config_path).exists() {
         if let Ok(json) = std::fs::read_to_string(&config_path) {
+            let json = crate::secrets::fill(name, &app_dir, &json);
             match parse_config(&json) {
                 Ok(config) => {
+                    if let Some(ref r) = config.routes {
+                        routes = r.clone();
+                    }
+                    redirects = config.redirects.clone();
+                    proxy = config.proxy.clone();
+                    locales = config.locales.clone();
+                    seo = config.seo.clone();
                     // Initialize auth middleware if configured
                     if let Some(ref auth_cfg) = config.auth {
                         eprintln!("[platform:{}] auth: provider={}", name, auth_cfg.provider);
@@ -354,7 +542,9 @@ fn load_app(name: &str, data_dir: &str) -> Result<AppHandle, String> {
                             "[platform:{}] data layer: {} sources, {} actions",
                             name, config.data.len(), config.actions.len()
                         );
-                        let ctx = Arc::new(DataContext::new(config));
+                        let mut config = config;
+                        resolve_files(&mut config, &app_dir);
+                        let ctx = Arc::new(DataContext::new(name, config));
                         // Fetch initial data for all global sources
                         let fetched = fetch_page_data(&ctx, "/");
                         if fetched > 0 {
@@ -372,18 +562,11 @@ fn load_app(name: &str, data_dir: &str) -> Result<AppHandle, String> {
                         data_ctx = Some(ctx);
                     }
                 }
-                Err(e) => eprintln!("[platform:{}] config parse error: {}", name, e),
+                Err(e) => crate::logs::error(name, &format!("config parse error: {}", e)),
             }
         }
     }
 
-    // Build asset pipeline
-    let asset_dir = format!("{}/.hashed", public_dir);
-    let manifest = build_assets(
-        &public_dir, &asset_dir,
-        &["index.html"],
-    );
-
     // Load CSS
     let css_path = manifest.files.get("style.css")
         .map(|h| format!("{}/{}", asset_dir, h))
@@ -397,19 +580,32 @@ fn load_app(name: &str, data_dir: &str) -> Result<AppHandle, String> {
         parked: AtomicBool::new(false),
         last_activity: Mutex::new(Instant::now()),
         sse_clients: Mutex::new(HashMap::new()),
+        sse_history: History::new(),
+        sse_deltas: Deltas::new(platform.sse_delta),
+        idempotency: Keys::new(),
         session_paths: Mutex::new(HashMap::new()),
+        session_users: Mutex::new(HashMap::new()),
         static_dir: public_dir,
         asset_dir,
         inline_css,
         manifest,
+        routes,
+        redirects,
+        proxy,
+        locales,
+        session_locales: locale::Sessions::default(),
+        seo,
         data_dir: data_dir.to_string(),
         data_ctx,
         auth: auth_mw,
+        quota,
+        clients_refused: AtomicUsize::new(0),
+        metrics,
     })
 }
 
 /// Load a static (SSG) app — no V8, just serve files from disk
-fn load_static_app(name: &str, data_dir: &str) -> Result<AppHandle, String> {
+fn load_static_app(name: &str, data_dir: &str, platform: &Platform) -> Result<AppHandle, String> {
     let app_dir = format!("{}/{}", data_dir, name);
     let static_dir = format!("{}/static", app_dir);
 
@@ -419,6 +615,15 @@ fn load_static_app(name: &str, data_dir: &str) -> Result<AppHandle, String> {
 
     eprintln!("[platform:{}] loaded as static site (no V8)", name);
 
+    let config = std::fs::read_to_string(format!("{}/config.json", app_dir))
+        .ok()
+        .and_then(|json| parse_config(&json).ok());
+    let routes = config.as_ref().and_then(|c| c.routes.clone()).unwrap_or_default();
+    let redirects = config.as_ref().map(|c| c.redirects.clone()).unwrap_or_default();
+    let proxy = config.as_ref().map(|c| c.proxy.clone()).unwrap_or_default();
+    let locales = config.as_ref().map(|c| c.locales.clone()).unwrap_or_default();
+    let seo = config.map(|c| c.seo).unwrap_or_default();
+
     Ok(AppHandle {
         name: name.to_string(),
         is_static: true,
@@ -426,19 +631,74 @@ fn load_static_app(name: &str, data_dir: &str) -> Result<AppHandle, String> {
         parked: AtomicBool::new(false),
         last_activity: Mutex::new(Instant::now()),
         sse_clients: Mutex::new(HashMap::new()),
+        sse_history: History::new(),
+        sse_deltas: Deltas::new(false),
+        idempotency: Keys::new(),
         session_paths: Mutex::new(HashMap::new()),
+        session_users: Mutex::new(HashMap::new()),
         static_dir,
         asset_dir: String::new(),
         inline_css: None,
         manifest: AssetManifest { files: HashMap::new(), reverse: HashMap::new() },
+        routes,
+        redirects,
+        proxy,
+        locales,
+        session_locales: locale::Sessions::default(),
+        seo,
         data_dir: data_dir.to_string(),
         data_ctx: None,
         auth: None,
+        quota: platform.quotas.get(name),
+        clients_refused: AtomicUsize::new(0),
+        metrics: crate::metrics::app(name),
     })
 }
 
 // ── Reaper thread: parks idle V8 isolates ───────────────────────────
 
+/// Keepalive (SSE comment or WebSocket ping) to every client of every app,
+/// every SSE_KEEPALIVE_SECS. Clients that are gone are dropped; a
+/// session with none left loses its event history and V8 state (parked
+/// apps have none to drop).
+fn sse_keepalive_loop(platform: Arc<Platform>) {
+    loop {
+        thread::sleep(Duration::from_secs(SSE_KEEPALIVE_SECS));
+        crate::logs::keepalive();
+        let apps: Vec<Arc<AppHandle>> = platform.apps.read().unwrap().values().cloned().collect();
+        for app in apps {
+            let mut gone = Vec::new();
+            {
+                let mut clients = app.sse_clients.lock().unwrap();
+                clients.retain(|session_id, list| {
+                    list.retain(|c| c.keepalive().is_ok());
+                    if list.is_empty() { gone.push(session_id.clone()); }
+                    !list.is_empty()
+                });
+                app.sse_history.sweep(|session_id| clients.contains_key(session_id));
+                app.sse_deltas.retain(|session_id| clients.contains_key(session_id));
+            }
+            app.note_clients();
+            app.idempotency.sweep();
+            for session_id in gone {
+                eprintln!("[platform:{}] SSE disconnected (session={})", app.name, &session_id[..8]);
+                app.sse_history.remove(&session_id);
+                if !app.is_parked() {
+                    if let Ok(tx) = app.ensure_warm() {
+                        let _ = tx.send(V8Request::DropSession { session_id: session_id.clone() });
+                    }
+                }
+                app.session_paths.lock().unwrap().remove(&session_id);
+                app.session_users.lock().unwrap().remove(&session_id);
+                if let Some(ctx) = &app.data_ctx {
+                    ctx.drop_session(&session_id);
+                }
+                app.session_locales.remove(&session_id);
+            }
+        }
+    }
+}
+
 fn reaper_loop(platform: Arc<Platform>, idle_threshold: u64) {
     loop {
         thread::sleep(Duration::from_secs(REAPER_INTERVAL_SECS));
@@ -463,35 +723,32 @@ fn reaper_loop(platform: Arc<Platform>, idle_threshold: u64) {
 // ── Platform HTTP handler ───────────────────────────────────────────
 
 fn handle_platform_connection(
-    mut stream: TcpStream,
+    stream: Conn,
     platform: &Platform,
 ) -> std::io::Result<()> {
-    let mut reader = BufReader::new(stream.try_clone()?);
-    let mut request_line = String::new();
-    reader.read_line(&mut request_line)?;
-
-    let parts: Vec<&str> = request_line.trim().split_whitespace().collect();
-    if parts.len() < 2 { return Ok(()); }
-    let method = parts[0];
-    let path = parts[1];
-
-    // Read headers
-    let mut raw_headers = HashMap::new();
-    let mut content_length: usize = 0;
-    loop {
-        let mut line = String::new();
-        reader.read_line(&mut line)?;
-        let trimmed = line.trim();
-        if trimmed.is_empty() { break; }
-        if let Some((k, v)) = trimmed.split_once(':') {
-            let key = k.trim().to_lowercase();
-            let val = v.trim().to_string();
-            if key == "content-length" {
-                content_length = val.parse().unwrap_or(0);
-            }
-            raw_headers.insert(key, val);
-        }
+    serve_connection(stream, platform.limits, |stream, req| handle_platform_request(stream, platform, req))
+}
+
+/// Readiness of one app: static apps are always ready, SSR apps when their
+/// isolates answer a ping. Doesn't unpark the app.
+fn app_ready(platform: &Platform, name: &str) -> Result<(), String> {
+    let app = platform.apps.read().unwrap().get(name).cloned()
+        .ok_or_else(|| format!("App '{}' not found", name))?;
+    if app.is_static {
+        return Ok(());
     }
+    let pool = app.v8_tx.lock().unwrap().clone()
+        .ok_or_else(|| "V8 thread not available".to_string())?;
+    pool.ping(crate::health::READY_TIMEOUT)
+}
+
+fn handle_platform_request(
+    stream: &mut Conn,
+    platform: &Platform,
+    req: Request,
+) -> std::io::Result<()> {
+    let Request { method, path, headers: raw_headers, body, peer, .. } = req;
+    let (method, path) = (method.as_str(), path.as_str());
 
     // Detect subdomain access.
     // Primary: X-Subdomain header (if Caddy sends it).
@@ -511,58 +768,225 @@ fn handle_platform_connection(
                 })
         });
 
-    // Keep a copy of headers for auth cookie extraction
-    let req_headers = raw_headers.clone();
+    // Probes bypass the middleware (health.rs)
+    if method == "GET" || method == "HEAD" {
+        let head = method == "HEAD";
+        if path == "/healthz" {
+            return crate::response::bare(stream, head, crate::health::write_healthz);
+        }
+        if path == "/readyz" {
+            let ready = crate::health::accepting();
+            return crate::response::bare(stream, head, |stream| crate::health::write_readyz(stream, ready));
+        }
+        if let Some(name) = path.strip_prefix("/api/apps/").and_then(|s| s.strip_suffix("/readyz")) {
+            let ready = crate::health::accepting().and_then(|_| app_ready(platform, name));
+            return crate::response::bare(stream, head, |stream| crate::health::write_readyz(stream, ready));
+        }
+    }
+
+    crate::stats::request();
+    let span = crate::trace::request(method, path, &raw_headers);
 
     // Run middleware
-    let mut ctx = MagneticContext::from_request(method, path, raw_headers);
+    let mut ctx = MagneticContext::from_request(method, path, raw_headers, peer);
     platform.middleware.run(&mut ctx);
-    let log_start = ctx.start_time;
+    if let Some(id) = span.request_id() {
+        ctx.response_headers.insert("X-Request-Id".into(), id);
+    }
+
+    // An app's proxy routes stream the backend's response, past the response phase
+    if let (None, Some(upstream)) = (&ctx.body, proxy_upstream(platform, path)) {
+        let tls = stream.is_tls();
+        return crate::proxy::forward(stream, &upstream, &ctx, &body, tls);
+    }
+
+    crate::response::respond(stream, &platform.middleware, &ctx, |stream| {
+        // Check if middleware short-circuited (e.g. OPTIONS, rate limit)
+        if let Some(body) = &ctx.body {
+            return write_short_circuit(stream, &ctx, body);
+        }
+        route_platform_request(stream, platform, method, path, &body, via_subdomain, &ctx)
+    })
+}
+
+/// Methods a platform route takes, for OPTIONS and 405s. None for app
+/// paths and unknown paths.
+fn platform_allowed_methods(path: &str) -> Option<&'static str> {
+    if path.starts_with("/api/apps/") && path.ends_with("/deploy") {
+        Some("POST, OPTIONS")
+    } else if path.starts_with("/api/apps/") && path.ends_with("/versions") {
+        Some("GET, HEAD, OPTIONS")
+    } else if rollback_route(path).is_some() {
+        Some("POST, OPTIONS")
+    } else if path == "/api/tokens" {
+        Some("GET, HEAD, POST, OPTIONS")
+    } else if path.starts_with("/api/tokens/") {
+        Some("DELETE, OPTIONS")
+    } else if let Some((_, secret)) = secrets_route(path) {
+        Some(if secret.is_some() { "PUT, DELETE, OPTIONS" } else { "GET, HEAD, OPTIONS" })
+    } else if (path.starts_with("/api/apps/") && path.ends_with("/status"))
+        || logs_route(path).is_some()
+        || metrics_route(path).is_some()
+        || matches!(path, "/api/apps" | crate::stats::PATH | crate::metrics::PROMETHEUS_PATH | "/" | "")
+    {
+        Some("GET, HEAD, OPTIONS")
+    } else {
+        None
+    }
+}
+
+/// Methods a path within an app takes, for OPTIONS and 405s
+fn app_allowed_methods(app: &AppHandle, app_path: &str) -> &'static str {
+    let route = app_path.split('?').next().unwrap_or(app_path);
+    match route {
+        _ if app.is_static => "GET, HEAD, OPTIONS",
+        "/sse" | "/ws" => "GET, OPTIONS",
+        "/auth/send" | "/auth/verify" | "/auth/logout" | "/auth/logout-all" if app.auth.is_some() => "POST, OPTIONS",
+        p if p.starts_with("/actions/") || p.starts_with("/hooks/") => "POST, OPTIONS",
+        p if p.starts_with("/api/") => "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS",
+        _ => "GET, HEAD, OPTIONS",
+    }
+}
+
+/// The backend for `/apps/{name}/...`, if the app proxies that path
+fn proxy_upstream(platform: &Platform, path: &str) -> Option<crate::proxy::Upstream> {
+    let rest = path.strip_prefix("/apps/")?;
+    let (app_name, app_path) = rest.split_at(rest.find('/')?);
+    let apps = platform.apps.read().unwrap();
+    apps.get(app_name)?.proxy.upstream(app_path)
+}
+
+fn route_platform_request(
+    stream: &mut Conn,
+    platform: &Platform,
+    method: &str,
+    path: &str,
+    body: &[u8],
+    via_subdomain: Option<String>,
+    ctx: &MagneticContext,
+) -> std::io::Result<()> {
+    let req_headers = &ctx.headers;
+    // Headers the middleware set go on every response
+    let extra_headers = &ctx.response_headers;
+    let get = method == "GET" || method == "HEAD";
+
+    if let Some(allow) = platform_allowed_methods(path) {
+        if !allows(allow, method) {
+            return write_allow(stream, method, allow, extra_headers);
+        }
+    }
+
+    // Route: deploy API
+    if method == "POST" && path.starts_with("/api/apps/") && path.ends_with("/deploy") {
+        let name = path.strip_prefix("/api/apps/").and_then(|s| s.strip_suffix("/deploy")).unwrap_or("");
+        let access = deploy_access(platform, name, req_headers);
+        if access != Access::Allowed {
+            return write_key_denied(stream, access, extra_headers);
+        }
+        return handle_deploy(stream, platform, path, body, req_headers, extra_headers);
+    }
 
-    if let Some(body) = &ctx.body {
-        let mut resp_headers = String::new();
-        for (k, v) in &ctx.response_headers {
-            resp_headers.push_str(&format!("{}: {}\r\n", k, v));
+    // Route: version history and rollback (versions.rs), with the same
+    // access as deploys
+    if get && path.starts_with("/api/apps/") && path.ends_with("/versions") {
+        let name = path.strip_prefix("/api/apps/").and_then(|s| s.strip_suffix("/versions")).unwrap_or("");
+        let access = deploy_access(platform, name, req_headers);
+        if access != Access::Allowed {
+            return write_key_denied(stream, access, extra_headers);
         }
+        let json = versions::to_json(&format!("{}/{}", platform.data_dir, name)).to_string();
+        let eh = format_extra_headers(extra_headers);
         let resp = format!(
-            "HTTP/1.1 {} {}\r\n{}Content-Length: {}\r\n\r\n",
-            ctx.status, status_text(ctx.status), resp_headers, body.len()
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+            Content-Length: {}\r\n{}\r\n",
+            json.len(), eh
         );
         stream.write_all(resp.as_bytes())?;
-        stream.write_all(body.as_bytes())?;
-        let ms = log_start.elapsed().as_millis();
-        eprintln!("[platform] {} {} → {} ({}ms)", method, path, ctx.status, ms);
-        return Ok(());
+        return stream.write_all(json.as_bytes());
+    }
+    if let Some((name, version)) = rollback_route(path) {
+        let access = deploy_access(platform, name, req_headers);
+        if access != Access::Allowed {
+            return write_key_denied(stream, access, extra_headers);
+        }
+        return handle_rollback(stream, platform, name, version, extra_headers);
+    }
+
+    // Route: deploy tokens (deploy_tokens.rs), for the account token
+    if path == "/api/tokens" || path.starts_with("/api/tokens/") {
+        let access = platform.deploy_tokens.admin_access(req_headers);
+        if access != Access::Allowed {
+            return write_key_denied(stream, access, extra_headers);
+        }
+        return handle_tokens(stream, platform, method, path, body, extra_headers);
     }
 
-    let extra_headers = ctx.response_headers.clone();
+    // Route: app secrets (secrets.rs), with the same access as deploys
+    if let Some((name, secret)) = secrets_route(path) {
+        let access = deploy_access(platform, name, req_headers);
+        if access != Access::Allowed {
+            return write_key_denied(stream, access, extra_headers);
+        }
+        return handle_secrets(stream, platform, method, name, secret, body, extra_headers);
+    }
 
-    // Route: deploy API
-    if method == "POST" && path.starts_with("/api/apps/") && path.ends_with("/deploy") {
-        let mut body = vec![0u8; content_length];
-        if content_length > 0 { reader.read_exact(&mut body)?; }
-        let result = handle_deploy(&mut stream, platform, path, &body, &extra_headers);
-        let ms = log_start.elapsed().as_millis();
-        eprintln!("[platform] {} {} → ({}ms)", method, path, ms);
-        return result;
+    // Route: app logs (logs.rs), with the same access as deploys
+    if let Some((name, query)) = logs_route(path) {
+        let access = deploy_access(platform, name, req_headers);
+        if access != Access::Allowed {
+            return write_key_denied(stream, access, extra_headers);
+        }
+        return handle_logs(stream, platform, name, query, req_headers, extra_headers);
+    }
+
+    // Route: app usage (metrics.rs), with the same access as deploys
+    if let Some(name) = metrics_route(path) {
+        let access = deploy_access(platform, name, req_headers);
+        if access != Access::Allowed {
+            return write_key_denied(stream, access, extra_headers);
+        }
+        return handle_metrics(stream, platform, name, extra_headers);
+    }
+
+    // Route: every app's usage for Prometheus (metrics.rs)
+    if get && path == crate::metrics::PROMETHEUS_PATH {
+        let access = platform.deploy_tokens.metrics_access(req_headers);
+        if access != Access::Allowed {
+            return write_key_denied(stream, access, extra_headers);
+        }
+        let body = crate::metrics::prometheus();
+        let resp = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+            Cache-Control: no-store\r\nContent-Length: {}\r\n{}\r\n",
+            body.len(), format_extra_headers(extra_headers)
+        );
+        stream.write_all(resp.as_bytes())?;
+        return stream.write_all(body.as_bytes());
     }
 
     // Route: app status
-    if method == "GET" && path.starts_with("/api/apps/") && path.ends_with("/status") {
+    if get && path.starts_with("/api/apps/") && path.ends_with("/status") {
         let name = path
             .strip_prefix("/api/apps/")
             .and_then(|s| s.strip_suffix("/status"))
             .unwrap_or("");
         let apps = platform.apps.read().unwrap();
         let json = if let Some(app) = apps.get(name) {
+            let breakers = app.data_ctx.as_ref().map_or_else(|| serde_json::json!({}), |ctx| ctx.breakers_json());
+            let render_timeouts = app.v8_tx.lock().unwrap().as_ref().map_or(0, V8Pool::timeouts);
+            let quotas = serde_json::json!({
+                "limits": app.quota,
+                "render_timeouts": render_timeouts,
+                "clients_refused": app.clients_refused.load(Ordering::Relaxed),
+            });
             format!(
-                "{{\"name\":\"{}\",\"warm\":{},\"sse_clients\":{},\"idle_secs\":{}}}",
-                name, !app.is_parked(), app.sse_client_count(), app.idle_secs()
+                "{{\"name\":\"{}\",\"warm\":{},\"sse_clients\":{},\"idle_secs\":{},\"breakers\":{},\"quotas\":{}}}",
+                name, !app.is_parked(), app.sse_client_count(), app.idle_secs(), breakers, quotas
             )
         } else {
             format!("{{\"error\":\"App '{}' not found\"}}", name)
         };
-        let eh = format_extra_headers(&extra_headers);
+        let eh = format_extra_headers(extra_headers);
         let resp = format!(
             "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
             Content-Length: {}\r\n{}\r\n",
@@ -572,12 +996,35 @@ fn handle_platform_connection(
         return stream.write_all(json.as_bytes());
     }
 
+    // Route: server statistics (stats.rs)
+    if get && path == crate::stats::PATH {
+        let apps = platform.apps.read().unwrap();
+        let mut clients = 0;
+        let mut sessions = 0;
+        let mut per_app = serde_json::Map::new();
+        for (name, app) in apps.iter() {
+            let app_clients = crate::stats::clients_json(&app.sse_clients.lock().unwrap());
+            clients += app_clients["clients"].as_u64().unwrap_or(0);
+            sessions += app_clients["sessions"].as_u64().unwrap_or(0);
+            let v8 = app.v8_tx.lock().unwrap().as_ref().map(V8Pool::stats_json);
+            per_app.insert(name.clone(), serde_json::json!({
+                "warm": !app.is_parked(),
+                "clients": app_clients,
+                "v8": v8,
+            }));
+        }
+        let mut stats = crate::stats::base();
+        stats.insert("clients".into(), serde_json::json!({ "clients": clients, "sessions": sessions }));
+        stats.insert("apps".into(), per_app.into());
+        return crate::stats::write(stream, stats, extra_headers);
+    }
+
     // Route: list apps
-    if method == "GET" && path == "/api/apps" {
+    if get && path == "/api/apps" {
         let apps = platform.apps.read().unwrap();
         let names: Vec<&str> = apps.keys().map(|s| s.as_str()).collect();
         let json = serde_json::to_string(&names).unwrap_or_else(|_| "[]".into());
-        let eh = format_extra_headers(&extra_headers);
+        let eh = format_extra_headers(extra_headers);
         let resp = format!(
             "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
             Content-Length: {}\r\n{}\r\n",
@@ -588,7 +1035,7 @@ fn handle_platform_connection(
     }
 
     // Route: platform homepage
-    if method == "GET" && (path == "/" || path == "") {
+    if get && (path == "/" || path == "") {
         let apps = platform.apps.read().unwrap();
         let mut html = String::from("<!DOCTYPE html><html><head><title>Magnetic Platform</title>\
             <style>body{font-family:system-ui;max-width:600px;margin:40px auto;padding:0 20px}\
@@ -607,7 +1054,7 @@ fn handle_platform_connection(
             }
         }
         html.push_str("</body></html>");
-        let eh = format_extra_headers(&extra_headers);
+        let eh = format_extra_headers(extra_headers);
         let resp = format!(
             "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
             Content-Length: {}\r\n{}\r\n",
@@ -629,37 +1076,56 @@ fn handle_platform_connection(
         if let Some(app) = apps.get(app_name) {
             let app = Arc::clone(app);
             drop(apps); // release read lock
+            crate::trace::attr("magnetic.app", app_name);
+            app.metrics.request();
+
+            // The app's redirect and rewrite rules come before its routes
+            let rewritten;
+            let app_path = match app.redirects.apply(app_path) {
+                redirects::Outcome::Redirect(status, location) => {
+                    // An app path, under the prefix the browser sees
+                    let location = if location.starts_with('/') && via_subdomain.is_none() {
+                        format!("/apps/{}{}", app_name, location)
+                    } else {
+                        location
+                    };
+                    return redirects::write_redirect(stream, status, &location, extra_headers);
+                }
+                redirects::Outcome::Rewrite(to) => {
+                    rewritten = to;
+                    rewritten.as_str()
+                }
+                redirects::Outcome::Pass => app_path,
+            };
+
+            let allow = app_allowed_methods(&app, app_path);
+            if !allows(allow, method) {
+                return write_allow(stream, method, allow, extra_headers);
+            }
+
+            // robots.txt and sitemap.xml, unless the app ships its own (seo.rs)
+            if matches!(method, "GET" | "HEAD") && seo::generates(&app.static_dir, app_path) {
+                let prefix = if via_subdomain.is_some() { String::new() } else { format!("/apps/{}", app_name) };
+                let tx = if app.is_static { None } else { app.ensure_warm().ok() };
+                return app.seo.write(stream, app_path, tx.as_ref(), &prefix, req_headers, extra_headers);
+            }
 
             // ── Static apps: serve files directly, no V8 ────────
             if app.is_static {
-                if method == "GET" || method == "HEAD" {
-                    let result = handle_static_get(
-                        &mut stream, &app, app_path, &extra_headers,
-                    );
-                    let ms = log_start.elapsed().as_millis();
-                    eprintln!("[platform:static] {} /apps/{}{} → ({}ms)", method, app_name, app_path, ms);
-                    return result;
-                } else {
-                    let msg = "{\"error\":\"Static apps only support GET requests\"}";
-                    let resp = format!(
-                        "HTTP/1.1 405 Method Not Allowed\r\nContent-Type: application/json\r\n\
-                        Content-Length: {}\r\n\r\n", msg.len()
-                    );
-                    stream.write_all(resp.as_bytes())?;
-                    return stream.write_all(msg.as_bytes());
-                }
+                return handle_static_get(stream, &app, app_path, extra_headers, req_headers);
             }
 
             match (method, app_path) {
                 // ── Auth routes ──────────────────────────────────
-                ("GET", "/auth/login") if app.auth.is_some() => {
+                ("GET" | "HEAD", "/auth/login") if app.auth.is_some() => {
                     let auth = app.auth.as_ref().unwrap();
-                    let state = "magnetic"; // TODO: CSRF state token
-                    let url = auth.login_url(state);
-                    let eh = format_extra_headers(&extra_headers);
+                    // OAuth2/OIDC: a CSRF state, tied to this browser by a cookie
+                    let (url, login_cookie) = auth.begin_login();
+                    let set_cookie = login_cookie.map(|c| format!("Set-Cookie: {}\r\n", c)).unwrap_or_default();
+                    let eh = format_extra_headers(extra_headers);
                     let resp = format!(
-                        "HTTP/1.1 302 Found\r\nLocation: {}\r\n{}\r\n",
-                        url, eh
+                        "HTTP/1.1 302 Found\r\nLocation: {}\r\n{}Content-Length: 0\r\n{}\r\n",
+                        url, set_cookie, eh
                     );
                     return stream.write_all(resp.as_bytes());
                 }
@@ -683,28 +1149,49 @@ fn handle_platform_connection(
                         stream.write_all(resp.as_bytes())?;
                         return stream.write_all(msg.as_bytes());
                     }
-                    match auth.exchange_code(exchange_value) {
-                        Ok((access_token, refresh_token, expires_in)) => {
-                            let (_session_id, cookie) = auth.create_session(
-                                &access_token,
-                                refresh_token.as_deref(),
-                                expires_in,
-                            );
+                    let login = if auth.is_oauth() {
+                        let state = path.split("state=").nth(1)
+                            .and_then(|s| s.split('&').next())
+                            .unwrap_or("");
+                        match auth.finish_login(req_headers, state) {
+                            Ok(login) => Some(login),
+                            Err(e) => {
+                                crate::logs::error(app_name, &format!("auth callback rejected: {}", e));
+                                let msg = format!("{{\"error\":\"{}\"}}", e);
+                                let resp = format!(
+                                    "HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+                                    msg.len()
+                                );
+                                stream.write_all(resp.as_bytes())?;
+                                return stream.write_all(msg.as_bytes());
+                            }
+                        }
+                    } else {
+                        None
+                    };
+                    match auth.exchange_code(exchange_value, login.as_ref()) {
+                        Ok(tokens) => {
+                            let (_session_id, cookie) = auth.create_session(&tokens);
                             let redirect_to = if via_subdomain.is_some() {
                                 "/".to_string()
                             } else {
                                 format!("/apps/{}/", app_name)
                             };
-                            let eh = format_extra_headers(&extra_headers);
+                            let clear = if auth.is_oauth() {
+                                format!("Set-Cookie: {}\r\n", auth.clear_login_cookie())
+                            } else {
+                                String::new()
+                            };
+                            let eh = format_extra_headers(extra_headers);
                             let resp = format!(
-                                "HTTP/1.1 302 Found\r\nLocation: {}\r\nSet-Cookie: {}\r\n{}\r\n",
-                                redirect_to, cookie, eh
+                                "HTTP/1.1 302 Found\r\nLocation: {}\r\nSet-Cookie: {}\r\n{}Content-Length: 0\r\n{}\r\n",
+                                redirect_to, cookie, clear, eh
                             );
                             eprintln!("[platform:{}] auth callback: session created ({})", app_name, auth.provider());
                             return stream.write_all(resp.as_bytes());
                         }
                         Err(e) => {
-                            eprintln!("[platform:{}] auth callback error: {}", app_name, e);
+                            crate::logs::error(app_name, &format!("auth callback error: {}", e));
                             let msg = format!("{{\"error\":\"{}\"}}", e);
                             let resp = format!(
                                 "HTTP/1.1 500 Internal Server Error\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
@@ -727,9 +1214,7 @@ fn handle_platform_connection(
                         stream.write_all(resp.as_bytes())?;
                         return stream.write_all(msg.as_bytes());
                     }
-                    let mut body = vec![0u8; content_length];
-                    if content_length > 0 { reader.read_exact(&mut body)?; }
-                    let body_str = String::from_utf8_lossy(&body);
+                    let body_str = String::from_utf8_lossy(body);
                     let email = serde_json::from_str::<serde_json::Value>(&body_str)
                         .ok()
                         .and_then(|v| v.get("email")?.as_str().map(String::from))
@@ -746,7 +1231,7 @@ fn handle_platform_connection(
                     match auth.send_auth_email(&email) {
                         Ok(result) => {
                             let msg = result.to_string();
-                            let eh = format_extra_headers(&extra_headers);
+                            let eh = format_extra_headers(extra_headers);
                             let resp = format!(
                                 "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n",
                                 msg.len(), eh
@@ -756,7 +1241,7 @@ fn handle_platform_connection(
                             return stream.write_all(msg.as_bytes());
                         }
                         Err(e) => {
-                            eprintln!("[platform:{}] auth send error: {}", app_name, e);
+                            crate::logs::error(app_name, &format!("auth send error: {}", e));
                             let msg = format!("{{\"error\":\"{}\"}}", e);
                             let resp = format!(
                                 "HTTP/1.1 500 Internal Server Error\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
@@ -779,9 +1264,7 @@ fn handle_platform_connection(
                         stream.write_all(resp.as_bytes())?;
                         return stream.write_all(msg.as_bytes());
                     }
-                    let mut body = vec![0u8; content_length];
-                    if content_length > 0 { reader.read_exact(&mut body)?; }
-                    let body_str = String::from_utf8_lossy(&body);
+                    let body_str = String::from_utf8_lossy(body);
                     let json_body = serde_json::from_str::<serde_json::Value>(&body_str)
                         .unwrap_or(serde_json::json!({}));
                     let code = json_body.get("code").and_then(|v| v.as_str()).unwrap_or("");
@@ -795,15 +1278,21 @@ fn handle_platform_connection(
                         stream.write_all(resp.as_bytes())?;
                         return stream.write_all(msg.as_bytes());
                     }
-                    match auth.verify_otp_code(code, method_id) {
-                        Ok((access_token, refresh_token, expires_in)) => {
-                            let (_session_id, cookie) = auth.create_session(
-                                &access_token,
-                                refresh_token.as_deref(),
-                                expires_in,
-                            );
+                    let client = crate::rate_limit::client(ctx);
+                    if let Err(wait) = auth.otp_begin(method_id, &client) {
+                        let msg = "{\"error\":\"Too many attempts\"}";
+                        let resp = format!(
+                            "HTTP/1.1 429 Too Many Requests\r\nContent-Type: application/json\r\nRetry-After: {}\r\nContent-Length: {}\r\n\r\n",
+                            wait.as_secs_f64().ceil(), msg.len()
+                        );
+                        stream.write_all(resp.as_bytes())?;
+                        return stream.write_all(msg.as_bytes());
+                    }
+                    match auth.verify_otp_code(code, method_id, &client) {
+                        Ok(tokens) => {
+                            let (_session_id, cookie) = auth.create_session(&tokens.into());
                             let msg = "{\"ok\":true}";
-                            let eh = format_extra_headers(&extra_headers);
+                            let eh = format_extra_headers(extra_headers);
                             let resp = format!(
                                 "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nSet-Cookie: {}\r\nContent-Length: {}\r\n{}\r\n",
                                 cookie, msg.len(), eh
@@ -813,7 +1302,7 @@ fn handle_platform_connection(
                             return stream.write_all(msg.as_bytes());
                         }
                         Err(e) => {
-                            eprintln!("[platform:{}] auth verify error: {}", app_name, e);
+                            crate::logs::error(app_name, &format!("auth verify error: {}", e));
                             let msg = format!("{{\"error\":\"{}\"}}", e);
                             let resp = format!(
                                 "HTTP/1.1 401 Unauthorized\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
@@ -826,9 +1315,10 @@ fn handle_platform_connection(
                 }
                 ("POST", "/auth/logout") if app.auth.is_some() => {
                     let auth = app.auth.as_ref().unwrap();
-                    let cookie = auth.logout(&req_headers);
+                    let cookie = auth.logout(req_headers);
+                    app.drop_session_data(req_headers);
                     let msg = "{\"ok\":true}";
-                    let eh = format_extra_headers(&extra_headers);
+                    let eh = format_extra_headers(extra_headers);
                     let resp = format!(
                         "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nSet-Cookie: {}\r\nContent-Length: {}\r\n{}\r\n",
                         cookie, msg.len(), eh
@@ -836,31 +1326,49 @@ fn handle_platform_connection(
                     stream.write_all(resp.as_bytes())?;
                     return stream.write_all(msg.as_bytes());
                 }
+                ("POST", "/auth/logout-all") if app.auth.is_some() => {
+                    let auth = app.auth.as_ref().unwrap();
+                    let (status, cookie, msg) = match auth.logout_all(req_headers) {
+                        Ok(cookie) => {
+                            app.drop_session_data(req_headers);
+                            ("200 OK", Some(cookie), "{\"ok\":true}".to_string())
+                        }
+                        Err(e) if auth.get_session(req_headers).is_none() => {
+                            ("401 Unauthorized", None, serde_json::json!({"error": e}).to_string())
+                        }
+                        Err(e) => ("400 Bad Request", None, serde_json::json!({"error": e}).to_string()),
+                    };
+                    let set_cookie = cookie.map(|c| format!("Set-Cookie: {}\r\n", c)).unwrap_or_default();
+                    let eh = format_extra_headers(extra_headers);
+                    let resp = format!(
+                        "HTTP/1.1 {}\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\n{}\r\n",
+                        status, set_cookie, msg.len(), eh
+                    );
+                    stream.write_all(resp.as_bytes())?;
+                    return stream.write_all(msg.as_bytes());
+                }
                 // ── Standard app routes ──────────────────────────
                 ("GET", "/sse") => {
-                    return handle_app_sse(stream, &app, &extra_headers, &req_headers);
+                    return handle_app_sse(stream.try_clone()?, &app, extra_headers, req_headers);
+                }
+                ("GET", "/ws") => {
+                    return handle_app_ws(stream, &app, extra_headers, req_headers);
                 }
                 ("POST", p) if p.starts_with("/actions/") => {
-                    let mut body = vec![0u8; content_length];
-                    if content_length > 0 { reader.read_exact(&mut body)?; }
-                    let result = handle_app_action(
-                        &mut stream, &app, p, &body, &extra_headers, &req_headers,
-                    );
-                    let ms = log_start.elapsed().as_millis();
-                    eprintln!("[platform] {} /apps/{}{} → ({}ms)", method, app_name, p, ms);
-                    return result;
+                    return handle_app_action(stream, &app, p, body, extra_headers, req_headers);
+                }
+                ("POST", p) if p.starts_with("/hooks/") => {
+                    return handle_app_hook(stream, &app, p, body, extra_headers, req_headers);
                 }
                 (m, p) if p.starts_with("/api/") => {
-                    let mut body = vec![0u8; content_length];
-                    if content_length > 0 { reader.read_exact(&mut body)?; }
-                    let result = handle_app_api(
-                        &mut stream, &app, m, p, &body, &extra_headers,
-                    );
-                    let ms = log_start.elapsed().as_millis();
-                    eprintln!("[platform] {} /apps/{}{} → ({}ms)", m, app_name, p, ms);
-                    return result;
+                    let access = app.auth.as_ref().map_or(Access::Allowed, |auth| auth.api_access(req_headers, m));
+                    if access != Access::Allowed {
+                        return write_key_denied(stream, access, extra_headers);
+                    }
+                    return handle_app_api(stream, &app, m, p, body, extra_headers);
                 }
-                ("GET", p) => {
+                // HEAD renders the same response; the response phase drops the body
+                ("GET" | "HEAD", p) => {
                     // ── Hybrid pre-render: serve pre-rendered HTML if available ──
                     let clean = p.split('?').next().unwrap_or("/").trim_start_matches('/');
                     let prerender_dir = format!("{}/{}/prerender", platform.data_dir, app_name);
@@ -877,33 +1385,32 @@ fn handle_platform_connection(
                     if std::path::Path::new(&prerender_file).is_file() {
                         if let Ok(data) = std::fs::read(&prerender_file) {
                             let ct = guess_content_type(&prerender_file);
-                            let eh = format_extra_headers(&extra_headers);
+                            let eh = format_extra_headers(extra_headers);
+                            let v = Validators::for_file(&data, std::path::Path::new(&prerender_file));
+                            if v.not_modified(req_headers) {
+                                let headers = format!("Cache-Control: public, max-age=60, must-revalidate\r\n{}", eh);
+                                return cache::write_not_modified(stream, &v, ct, &headers);
+                            }
                             let resp = format!(
-                                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+                                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}\
                                 Cache-Control: public, max-age=60, must-revalidate\r\n{}\r\n",
-                                ct, data.len(), eh
+                                ct, data.len(), v.headers(), eh
                             );
                             stream.write_all(resp.as_bytes())?;
-                            stream.write_all(&data)?;
-                            let ms = log_start.elapsed().as_millis();
-                            eprintln!("[platform:prerender] {} /apps/{}{} → ({}ms)", method, app_name, p, ms);
-                            return Ok(());
+                            return stream.write_all(&data);
                         }
                     }
                     // Fall through to V8 SSR
-                    let result = handle_app_get(
-                        &mut stream, Arc::clone(&app), app_name, p, &extra_headers,
-                        via_subdomain.is_some(), &req_headers,
+                    return handle_app_get(
+                        stream, Arc::clone(&app), app_name, p, extra_headers,
+                        via_subdomain.is_some(), req_headers,
                     );
-                    let ms = log_start.elapsed().as_millis();
-                    eprintln!("[platform] {} /apps/{}{} → ({}ms)", method, app_name, p, ms);
-                    return result;
                 }
                 _ => {}
             }
         } else {
             let msg = format!("{{\"error\":\"App '{}' not found\"}}", app_name);
-            let eh = format_extra_headers(&extra_headers);
+            let eh = format_extra_headers(extra_headers);
             let resp = format!(
                 "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\n\
                 Content-Length: {}\r\n{}\r\n",
@@ -920,10 +1427,11 @@ fn handle_platform_connection(
 // ── Static file handler (SSG apps) ──────────────────────────────────
 
 fn handle_static_get(
-    stream: &mut TcpStream,
+    stream: &mut Conn,
     app: &AppHandle,
     url_path: &str,
     extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
 ) -> std::io::Result<()> {
     let clean_path = url_path.split('?').next().unwrap_or("/");
     let clean_path = clean_path.trim_start_matches('/');
@@ -937,23 +1445,25 @@ fn handle_static_get(
     //   /            → index.html
     //   /components  → components/index.html
     //   /style.css   → style.css
+    //   /app/settings → a fallback route's file (static_routes.rs)
     let base = &app.static_dir;
-    let file_path = if clean_path.is_empty() {
-        format!("{}/index.html", base)
+    let route = format!("/{}", clean_path);
+    let direct = format!("{}/{}", base, clean_path);
+    let file_path = if !clean_path.is_empty() && std::path::Path::new(&direct).is_file() {
+        Some(direct)
     } else {
-        let direct = format!("{}/{}", base, clean_path);
-        if std::path::Path::new(&direct).is_file() {
-            direct
-        } else {
-            // Try as directory with index.html
-            format!("{}/{}/index.html", base, clean_path)
-        }
+        app.routes.index(base, &route)
+            .or_else(|| {
+                static_routes::accepts_html(req_headers)
+                    .then(|| app.routes.fallback(base, &route))
+                    .flatten()
+            })
+            .map(|file| format!("{}/{}", base, file))
     };
 
-    let data = match std::fs::read(&file_path) {
-        Ok(d) => d,
-        Err(_) => {
-            // Fall back to index.html for SPA-like behavior (optional)
+    let data = match file_path.as_ref().and_then(|p| std::fs::read(p).ok()) {
+        Some(d) => d,
+        None => {
             let msg = "<!DOCTYPE html><html><body><h1>404 — Not Found</h1></body></html>";
             let eh = format_extra_headers(extra_headers);
             let resp = format!(
@@ -966,6 +1476,7 @@ fn handle_static_get(
         }
     };
 
+    let file_path = file_path.unwrap_or_default();
     let ct = guess_content_type(&file_path);
     let cache = if file_path.ends_with(".html") {
         "public, max-age=60, must-revalidate"
@@ -974,10 +1485,15 @@ fn handle_static_get(
     };
 
     let eh = format_extra_headers(extra_headers);
+    let v = Validators::for_file(&data, std::path::Path::new(&file_path));
+    if v.not_modified(req_headers) {
+        let headers = format!("Cache-Control: {}\r\n{}", cache, eh);
+        return cache::write_not_modified(stream, &v, ct, &headers);
+    }
     let resp = format!(
-        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}\
         Cache-Control: {}\r\n{}\r\n",
-        ct, data.len(), cache, eh
+        ct, data.len(), v.headers(), cache, eh
     );
     stream.write_all(resp.as_bytes())?;
     stream.write_all(&data)
@@ -985,11 +1501,22 @@ fn handle_static_get(
 
 // ── Deploy handler ──────────────────────────────────────────────────
 
+/// Write the deploy's data layer config (if present in payload)
+fn save_config(app_dir: &str, name: &str, payload: &serde_json::Value) {
+    if let Some(config_str) = payload.get("config").and_then(|v| v.as_str()) {
+        if !config_str.is_empty() && config_str != "null" {
+            let _ = std::fs::write(format!("{}/config.json", app_dir), config_str);
+            eprintln!("[platform] Saved data layer config for '{}'", name);
+        }
+    }
+}
+
 fn handle_deploy(
-    stream: &mut TcpStream,
+    stream: &mut Conn,
     platform: &Platform,
     url_path: &str,
     body: &[u8],
+    req_headers: &HashMap<String, String>,
     extra_headers: &HashMap<String, String>,
 ) -> std::io::Result<()> {
     // Extract app name from /api/apps/<name>/deploy
@@ -1009,6 +1536,27 @@ fn handle_deploy(
         return stream.write_all(msg.as_bytes());
     }
 
+    // A .tar.gz becomes the same payload (archive.rs), kept as JSON in the
+    // version history so it rolls back like any other deploy
+    if crate::archive::is_archive(req_headers.get("content-type").map(String::as_str)) {
+        return match crate::archive::to_payload(body) {
+            Ok(payload) => {
+                let json = payload.to_string();
+                deploy_payload(stream, platform, &name, &payload, extra_headers, Release::New(json.as_bytes()))
+            }
+            Err(e) => {
+                crate::logs::error(&name, &format!("deploy refused: {}", e));
+                let msg = serde_json::json!({"error": format!("Invalid archive: {}", e)}).to_string();
+                let resp = format!(
+                    "HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\n\
+                    Content-Length: {}\r\n\r\n", msg.len()
+                );
+                stream.write_all(resp.as_bytes())?;
+                stream.write_all(msg.as_bytes())
+            }
+        };
+    }
+
     // Parse deploy payload
     let body_str = String::from_utf8_lossy(body);
     let payload: serde_json::Value = match serde_json::from_str(&body_str) {
@@ -1024,6 +1572,26 @@ fn handle_deploy(
         }
     };
 
+    deploy_payload(stream, platform, &name, &payload, extra_headers, Release::New(body))
+}
+
+/// What a deploy does to the app's version history (versions.rs)
+enum Release<'a> {
+    /// Keep the deploy's payload as a new version
+    New(&'a [u8]),
+    /// Make this kept version live again
+    Rollback(u64),
+}
+
+/// Write a deploy's files into the app's dir and (re)load the app
+fn deploy_payload(
+    stream: &mut Conn,
+    platform: &Platform,
+    name: &str,
+    payload: &serde_json::Value,
+    extra_headers: &HashMap<String, String>,
+    release: Release<'_>,
+) -> std::io::Result<()> {
     let is_static_deploy = payload.get("static").and_then(|v| v.as_bool()).unwrap_or(false);
     let app_dir = format!("{}/{}", platform.data_dir, name);
 
@@ -1035,19 +1603,20 @@ fn handle_deploy(
         let _ = std::fs::create_dir_all(&static_dir);
         // Remove bundle.js if switching from SSR to static
         let _ = std::fs::remove_file(format!("{}/bundle.js", app_dir));
+        let _ = std::fs::remove_file(format!("{}/snapshot.bin", app_dir));
 
         // Write static files (supports subdirectory paths like components/index.html)
         let mut file_count = 0usize;
         if let Some(assets) = payload.get("assets").and_then(|v| v.as_object()) {
             for (filepath, content) in assets {
-                if let Some(text) = content.as_str() {
+                if let Some(bytes) = crate::archive::file_bytes(content) {
                     if filepath.contains("..") { continue; }
                     let full_path = format!("{}/{}", static_dir, filepath);
                     // Create parent directories
                     if let Some(parent) = std::path::Path::new(&full_path).parent() {
                         let _ = std::fs::create_dir_all(parent);
                     }
-                    let _ = std::fs::write(&full_path, text);
+                    let _ = std::fs::write(&full_path, bytes);
                     file_count += 1;
                 }
             }
@@ -1055,16 +1624,17 @@ fn handle_deploy(
 
         // Write static marker (so load detects it as static on restart)
         let _ = std::fs::write(format!("{}/static.marker", app_dir), "ssg");
+        save_config(&app_dir, name, payload);
 
         eprintln!("[platform] Deploying static app: {} ({} files)", name, file_count);
 
-        match load_static_app(&name, &platform.data_dir) {
+        match load_static_app(name, &platform.data_dir, platform) {
             Ok(handle) => {
-                let app = Arc::new(handle);
-                platform.apps.write().unwrap().insert(name.clone(), Arc::clone(&app));
+                swap_app(platform, handle);
+                let version = mark_version(platform, &app_dir, name, payload, release);
                 let msg = format!(
-                    "{{\"ok\":true,\"name\":\"{}\",\"url\":\"/apps/{}/\",\"static\":true,\"files\":{}}}",
-                    name, name, file_count
+                    "{{\"ok\":true,\"name\":\"{}\",\"url\":\"/apps/{}/\",\"static\":true,\"files\":{},\"version\":{}}}",
+                    name, name, file_count, serde_json::json!(version)
                 );
                 let eh = format_extra_headers(extra_headers);
                 let resp = format!(
@@ -1074,7 +1644,7 @@ fn handle_deploy(
                 );
                 stream.write_all(resp.as_bytes())?;
                 stream.write_all(msg.as_bytes())?;
-                eprintln!("[platform] ✓ Static app '{}' deployed at /apps/{}/", name, name);
+                crate::logs::info(name, &format!("✓ static app deployed at /apps/{}/", name));
                 Ok(())
             }
             Err(e) => {
@@ -1099,78 +1669,37 @@ fn handle_deploy(
             stream.write_all(resp.as_bytes())?;
             return stream.write_all(msg.as_bytes());
         }
-
-        let public_dir = format!("{}/public", app_dir);
-        let _ = std::fs::create_dir_all(&public_dir);
-        // Remove static marker if switching from static to SSR
-        let _ = std::fs::remove_file(format!("{}/static.marker", app_dir));
-
-        std::fs::write(format!("{}/bundle.js", app_dir), bundle)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-
-        // Write data layer config (if present in payload)
-        if let Some(config_str) = payload.get("config").and_then(|v| v.as_str()) {
-            if !config_str.is_empty() && config_str != "null" {
-                let _ = std::fs::write(format!("{}/config.json", app_dir), config_str);
-                eprintln!("[platform] Saved data layer config for '{}'", name);
-            }
-        }
-
-        // Write assets
-        if let Some(assets) = payload.get("assets").and_then(|v| v.as_object()) {
-            for (filename, content) in assets {
-                if let Some(text) = content.as_str() {
-                    // Security: prevent path traversal
-                    if filename.contains("..") || filename.contains('/') { continue; }
-                    let _ = std::fs::write(format!("{}/{}", public_dir, filename), text);
-                }
-            }
+        let quota = platform.quotas.get(name);
+        if quota.bundle_too_large(bundle.len()) {
+            crate::logs::error(name, &format!("deploy refused: bundle is {} bytes, over {} MB", bundle.len(), quota.bundle_mb));
+            let msg = serde_json::json!({
+                "error": format!("Bundle is {} bytes, over the app's limit of {} MB", bundle.len(), quota.bundle_mb)
+            }).to_string();
+            let resp = format!(
+                "HTTP/1.1 413 Payload Too Large\r\nContent-Type: application/json\r\n\
+                Content-Length: {}\r\n\r\n", msg.len()
+            );
+            stream.write_all(resp.as_bytes())?;
+            return stream.write_all(msg.as_bytes());
         }
 
-        // Write pre-rendered pages (hybrid SSR + SSG)
-        let prerender_dir = format!("{}/prerender", app_dir);
-        let mut prerender_count = 0usize;
-        if let Some(prerendered) = payload.get("prerendered").and_then(|v| v.as_object()) {
-            // Clean previous pre-rendered files
-            let _ = std::fs::remove_dir_all(&prerender_dir);
-            let _ = std::fs::create_dir_all(&prerender_dir);
-            for (route_path, html) in prerendered {
-                if let Some(text) = html.as_str() {
-                    if route_path.contains("..") { continue; }
-                    let clean = route_path.trim_start_matches('/');
-                    let file_path = if clean.is_empty() {
-                        format!("{}/index.html", prerender_dir)
-                    } else {
-                        format!("{}/{}/index.html", prerender_dir, clean)
-                    };
-                    if let Some(parent) = std::path::Path::new(&file_path).parent() {
-                        let _ = std::fs::create_dir_all(parent);
-                    }
-                    let _ = std::fs::write(&file_path, text);
-                    prerender_count += 1;
-                }
-            }
-            eprintln!("[platform] Pre-rendered {} routes for '{}'", prerender_count, name);
-        } else {
-            // No prerendered field — clean old pre-renders (full SSR deploy)
-            let _ = std::fs::remove_dir_all(&prerender_dir);
-        }
+        write_app_files(&app_dir, name, payload, bundle)?;
 
         eprintln!("[platform] Deploying app: {}", name);
 
-        // Load (or reload) the app
-        match load_app(&name, &platform.data_dir) {
+        // Boot the new version next to the running one, which keeps serving
+        // until the new one has passed a health render
+        let loaded = load_app(name, &platform.data_dir, platform)
+            .and_then(|handle| health_render(&handle).map(|_| handle));
+        match loaded {
             Ok(handle) => {
-                let app = Arc::new(handle);
-                let mut apps = platform.apps.write().unwrap();
-                // Old app handle is dropped, V8 thread will exit when channel closes
-                apps.insert(name.clone(), Arc::clone(&app));
-                drop(apps);
+                let app = swap_app(platform, handle);
+                let version = mark_version(platform, &app_dir, name, payload, release);
 
                 // Send the HTTP response BEFORE starting data threads.
                 let msg = format!(
-                    "{{\"ok\":true,\"name\":\"{}\",\"url\":\"/apps/{}/\"}}",
-                    name, name
+                    "{{\"ok\":true,\"name\":\"{}\",\"url\":\"/apps/{}/\",\"version\":{}}}",
+                    name, name, serde_json::json!(version)
                 );
                 let eh = format_extra_headers(extra_headers);
                 let resp = format!(
@@ -1180,14 +1709,16 @@ fn handle_deploy(
                 );
                 stream.write_all(resp.as_bytes())?;
                 stream.write_all(msg.as_bytes())?;
-                eprintln!("[platform] ✓ App '{}' deployed at /apps/{}/", name, name);
+                crate::logs::info(name, &format!("✓ app deployed at /apps/{}/", name));
 
                 // Start data threads (poll + SSE) after response is sent
                 start_data_threads(app);
                 Ok(())
             }
             Err(e) => {
-                let msg = format!("{{\"error\":\"Deploy failed: {}\"}}", e);
+                crate::logs::error(name, &format!("deploy failed, the running version stays: {}", e));
+                restore_live_files(&app_dir, name);
+                let msg = serde_json::json!({"error": format!("Deploy failed: {}", e)}).to_string();
                 let resp = format!(
                     "HTTP/1.1 500 Internal Server Error\r\nContent-Type: application/json\r\n\
                     Content-Length: {}\r\n\r\n", msg.len()
@@ -1199,26 +1730,463 @@ fn handle_deploy(
     }
 }
 
-// ── Per-app request handlers ────────────────────────────────────────
-
-fn handle_app_sse(
-    mut stream: TcpStream,
-    app: &AppHandle,
-    extra_headers: &HashMap<String, String>,
-    req_headers: &HashMap<String, String>,
-) -> std::io::Result<()> {
-    use crate::{extract_session_cookie, generate_session_id};
+/// Write an SSR deploy's bundle, V8 snapshot, config, assets, data files
+/// and pre-rendered pages into the app's dir
+fn write_app_files(app_dir: &str, name: &str, payload: &serde_json::Value, bundle: &str) -> std::io::Result<()> {
+    let public_dir = format!("{}/public", app_dir);
+    let _ = std::fs::create_dir_all(&public_dir);
+    // Remove static marker if switching from static to SSR
+    let _ = std::fs::remove_file(format!("{}/static.marker", app_dir));
 
-    // Get or create session ID from cookie
-    let session_id = extract_session_cookie(req_headers)
-        .unwrap_or_else(generate_session_id);
+    std::fs::write(format!("{}/bundle.js", app_dir), bundle)?;
+
+    // Snapshot the evaluated bundle so isolates boot without re-running it
+    let snapshot_path = format!("{}/snapshot.bin", app_dir);
+    match snapshot::write(&snapshot_path, bundle) {
+        Ok(size) => eprintln!("[platform] Wrote V8 snapshot for '{}' ({} KB)", name, size / 1024),
+        Err(e) => {
+            eprintln!("[platform] No V8 snapshot for '{}': {}", name, e);
+            let _ = std::fs::remove_file(&snapshot_path);
+        }
+    }
+
+    save_config(app_dir, name, payload);
+
+    // Write assets (subdirectories come from archive deploys' public/ tree)
+    if let Some(assets) = payload.get("assets").and_then(|v| v.as_object()) {
+        for (filepath, content) in assets {
+            if let Some(bytes) = crate::archive::file_bytes(content) {
+                // Security: prevent path traversal, and writes into .hashed
+                if filepath.contains("..") || filepath.starts_with('/') || filepath.starts_with('.') { continue; }
+                let full_path = format!("{}/{}", public_dir, filepath);
+                if let Some(parent) = std::path::Path::new(&full_path).parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                let _ = std::fs::write(&full_path, bytes);
+            }
+        }
+    }
+
+    // Write the files read by file data sources
+    if let Some(files) = payload.get("files").and_then(|v| v.as_object()) {
+        let files_dir = format!("{}/data", app_dir);
+        let _ = std::fs::remove_dir_all(&files_dir);
+        for (filepath, content) in files {
+            if let Some(bytes) = crate::archive::file_bytes(content) {
+                if filepath.contains("..") || filepath.starts_with('/') { continue; }
+                let full_path = format!("{}/{}", files_dir, filepath);
+                if let Some(parent) = std::path::Path::new(&full_path).parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                let _ = std::fs::write(&full_path, bytes);
+            }
+        }
+    }
+
+    // Write pre-rendered pages (hybrid SSR + SSG)
+    let prerender_dir = format!("{}/prerender", app_dir);
+    let mut prerender_count = 0usize;
+    if let Some(prerendered) = payload.get("prerendered").and_then(|v| v.as_object()) {
+        // Clean previous pre-rendered files
+        let _ = std::fs::remove_dir_all(&prerender_dir);
+        let _ = std::fs::create_dir_all(&prerender_dir);
+        for (route_path, html) in prerendered {
+            if let Some(text) = html.as_str() {
+                if route_path.contains("..") { continue; }
+                let clean = route_path.trim_start_matches('/');
+                let file_path = if clean.is_empty() {
+                    format!("{}/index.html", prerender_dir)
+                } else {
+                    format!("{}/{}/index.html", prerender_dir, clean)
+                };
+                if let Some(parent) = std::path::Path::new(&file_path).parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
+                let _ = std::fs::write(&file_path, text);
+                prerender_count += 1;
+            }
+        }
+        eprintln!("[platform] Pre-rendered {} routes for '{}'", prerender_count, name);
+    } else {
+        // No prerendered field — clean old pre-renders (full SSR deploy)
+        let _ = std::fs::remove_dir_all(&prerender_dir);
+    }
+    Ok(())
+}
+
+/// Render `/` on a freshly loaded app before it takes traffic, so a
+/// bundle that throws or hangs fails its deploy
+fn health_render(app: &AppHandle) -> Result<(), String> {
+    let tx = app.ensure_warm()?;
+    let session_id = format!("deploy-check-{}", crate::cookie::random_id());
+    let reply = Reply::new();
+    let path = "/".to_string();
+    let request = match app.render_data(&session_id, &path) {
+        Some(data_json) => V8Request::RenderWithData {
+            path, data_json, session_id: session_id.clone(), locale: None, reply: reply.clone(),
+        },
+        None => V8Request::Render { path, session_id: session_id.clone(), locale: None, reply: reply.clone() },
+    };
+    tx.send(request).map_err(|_| "V8 thread is gone".to_string())?;
+    let result = reply.recv_timeout(HEALTH_RENDER_TIMEOUT);
+    let _ = tx.send(V8Request::DropSession { session_id });
+    match result {
+        V8Result::Err(e) => Err(format!("health render of / failed: {}", e)),
+        V8Result::Ok(_) => Ok(()),
+    }
+}
+
+/// Put a newly loaded version of an app in place of the running one. It
+/// takes over the old one's sessions, and the old one is retired.
+fn swap_app(platform: &Platform, handle: AppHandle) -> Arc<AppHandle> {
+    let app = Arc::new(handle);
+    let mut apps = platform.apps.write().unwrap();
+    if let Some(old) = apps.get(&app.name) {
+        app.take_sessions(old);
+    }
+    let old = apps.insert(app.name.clone(), Arc::clone(&app));
+    drop(apps);
+    if let Some(old) = old {
+        retire_app(old);
+    }
+    app
+}
+
+/// Drain an app a deploy replaced: its data threads stop, its SSE and
+/// WebSocket clients are told to reconnect (they land on the new version),
+/// and once they've had SSE_WRITE_TIMEOUT to get that, its isolates are
+/// let go. They exit when the requests still using them finish.
+fn retire_app(old: Arc<AppHandle>) {
+    if let Some(ctx) = &old.data_ctx {
+        ctx.retire();
+    }
+    let clients: Vec<Client> = old.sse_clients.lock().unwrap().drain().flat_map(|(_, list)| list).collect();
+    thread::spawn(move || {
+        for client in &clients {
+            client.reconnect();
+        }
+        let deadline = Instant::now() + SSE_WRITE_TIMEOUT;
+        for client in &clients {
+            client.wait(deadline);
+        }
+        old.v8_tx.lock().unwrap().take();
+        crate::logs::info(&old.name, &format!("retired the previous version ({} client(s) moved over)", clients.len()));
+    });
+}
+
+/// After a failed deploy, write the live version's files back (versions.rs),
+/// so a restart loads what is running rather than what failed
+fn restore_live_files(app_dir: &str, name: &str) {
+    let live = versions::current(app_dir).and_then(|version| {
+        let body = versions::payload(app_dir, version).ok()?;
+        serde_json::from_slice::<serde_json::Value>(&body).ok()
+    });
+    let bundle = live.as_ref().and_then(|p| p.get("bundle")).and_then(|b| b.as_str()).filter(|b| !b.is_empty());
+    match (&live, bundle) {
+        (Some(payload), Some(bundle)) => match write_app_files(app_dir, name, payload, bundle) {
+            Ok(()) => crate::logs::info(name, "restored the live version's files"),
+            Err(e) => crate::logs::error(name, &format!("could not restore the live version's files: {}", e)),
+        },
+        _ => crate::logs::info(name, "no kept SSR version to restore: the failed deploy's files stay on disk"),
+    }
+}
+
+/// Record or restore a deploy's version once the app loaded. The number of
+/// the live version, if history is kept.
+fn mark_version(platform: &Platform, app_dir: &str, name: &str, payload: &serde_json::Value, release: Release<'_>) -> Option<u64> {
+    let marked = match release {
+        Release::New(body) => versions::record(app_dir, body, payload, platform.keep_versions),
+        Release::Rollback(version) => versions::set_current(app_dir, version).map(|_| Some(version)),
+    };
+    marked.unwrap_or_else(|e| {
+        crate::logs::error(name, &format!("versions: {}", e));
+        None
+    })
+}
+
+/// The app and version of `/api/apps/<name>/rollback/<version>`
+fn rollback_route(path: &str) -> Option<(&str, &str)> {
+    let (name, rest) = path.strip_prefix("/api/apps/")?.split_once('/')?;
+    let version = rest.strip_prefix("rollback/")?;
+    (!version.contains('/')).then_some((name, version))
+}
+
+/// Deploy a kept version of an app again
+fn handle_rollback(
+    stream: &mut Conn,
+    platform: &Platform,
+    name: &str,
+    version: &str,
+    extra_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let app_dir = format!("{}/{}", platform.data_dir, name);
+    let payload = match version.parse::<u64>() {
+        _ if name.is_empty() || name.contains("..") => Err(("400 Bad Request", "Invalid app name".to_string())),
+        Err(_) => Err(("400 Bad Request", format!("Invalid version '{}'", version))),
+        Ok(version) => versions::payload(&app_dir, version)
+            .map_err(|e| ("404 Not Found", e))
+            .and_then(|body| {
+                serde_json::from_slice::<serde_json::Value>(&body)
+                    .map_err(|e| ("500 Internal Server Error", format!("Version {} is unreadable: {}", version, e)))
+            })
+            .map(|payload| (version, payload)),
+    };
+    match payload {
+        Ok((version, payload)) => {
+            crate::logs::info(name, &format!("rolling back to version {}", version));
+            deploy_payload(stream, platform, name, &payload, extra_headers, Release::Rollback(version))
+        }
+        Err((status, e)) => {
+            let msg = serde_json::json!({"error": e}).to_string();
+            let eh = format_extra_headers(extra_headers);
+            let resp = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n",
+                status, msg.len(), eh
+            );
+            stream.write_all(resp.as_bytes())?;
+            stream.write_all(msg.as_bytes())
+        }
+    }
+}
+
+/// The app and query string of `/api/apps/<name>/logs[?...]`
+fn logs_route(path: &str) -> Option<(&str, &str)> {
+    let (route, query) = path.split_once('?').unwrap_or((path, ""));
+    let name = route.strip_prefix("/api/apps/")?.strip_suffix("/logs")?;
+    (!name.is_empty() && !name.contains('/')).then_some((name, query))
+}
+
+/// The app's last log lines as JSON, or with `follow=sse` as an event
+/// stream that goes on with new ones
+fn handle_logs(
+    stream: &mut Conn,
+    platform: &Platform,
+    name: &str,
+    query: &str,
+    req_headers: &HashMap<String, String>,
+    extra_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let param = |key: &str| {
+        query.split('&').find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+    };
+    let tail = param("tail")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(crate::logs::DEFAULT_TAIL)
+        .min(crate::logs::RING_LINES);
+    let eh = format_extra_headers(extra_headers);
+    if !std::path::Path::new(&format!("{}/{}", platform.data_dir, name)).is_dir() {
+        let msg = serde_json::json!({"error": format!("App '{}' not found", name)}).to_string();
+        let resp = format!(
+            "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n",
+            msg.len(), eh
+        );
+        stream.write_all(resp.as_bytes())?;
+        return stream.write_all(msg.as_bytes());
+    }
+    if param("follow") == Some("sse") {
+        // Straight to the socket, past the response phase, like an app's SSE
+        let mut sse = stream.try_clone()?;
+        let enc = SseStream::negotiate(req_headers);
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+            Cache-Control: no-cache\r\nConnection: keep-alive\r\n{}{}\r\n",
+            enc.headers(), eh
+        );
+        sse.write_all(head.as_bytes())?;
+        sse.shutdown(std::net::Shutdown::Read)?;
+        sse.set_write_timeout(Some(SSE_WRITE_TIMEOUT))?;
+        return crate::logs::follow(name, tail, SseStream::new(sse, enc));
+    }
+    let msg = serde_json::json!({ "lines": crate::logs::tail(name, tail) }).to_string();
+    let resp = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n",
+        msg.len(), eh
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(msg.as_bytes())
+}
+
+/// The app of `/api/apps/<name>/metrics`
+fn metrics_route(path: &str) -> Option<&str> {
+    let name = path.strip_prefix("/api/apps/")?.strip_suffix("/metrics")?;
+    (!name.is_empty() && !name.contains('/')).then_some(name)
+}
+
+/// The app's usage as JSON
+fn handle_metrics(
+    stream: &mut Conn,
+    platform: &Platform,
+    name: &str,
+    extra_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let (status, msg) = if name.contains("..") || !std::path::Path::new(&format!("{}/{}", platform.data_dir, name)).is_dir() {
+        ("404 Not Found", serde_json::json!({"error": format!("App '{}' not found", name)}))
+    } else {
+        ("200 OK", crate::metrics::app(name).json())
+    };
+    let msg = msg.to_string();
+    let resp = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nCache-Control: no-store\r\n\
+        Content-Length: {}\r\n{}\r\n",
+        status, msg.len(), format_extra_headers(extra_headers)
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(msg.as_bytes())
+}
+
+/// The app and secret name of `/api/apps/<name>/secrets[/<NAME>]`
+fn secrets_route(path: &str) -> Option<(&str, Option<&str>)> {
+    let (name, rest) = path.strip_prefix("/api/apps/")?.split_once('/')?;
+    match rest.strip_prefix("secrets")? {
+        "" => Some((name, None)),
+        secret => Some((name, Some(secret.strip_prefix('/')?).filter(|s| !s.contains('/')))),
+    }
+}
+
+/// GET lists an app's secret names; PUT sets one (the body is its value)
+/// and DELETE removes it, then the app reloads to pick up the change.
+fn handle_secrets(
+    stream: &mut Conn,
+    platform: &Platform,
+    method: &str,
+    name: &str,
+    secret: Option<&str>,
+    body: &[u8],
+    extra_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let app_dir = format!("{}/{}", platform.data_dir, name);
+    let (status, msg) = match (method, secret) {
+        _ if name.is_empty() || name.contains("..") => ("400 Bad Request", serde_json::json!({"error": "Invalid app name"})),
+        (_, Some(secret)) if !crate::secrets::valid_name(secret) => {
+            ("400 Bad Request", serde_json::json!({"error": "Secret names are letters, digits and _"}))
+        }
+        ("GET" | "HEAD", None) => match crate::secrets::load(&app_dir) {
+            Ok(secrets) => ("200 OK", serde_json::json!({"secrets": secrets.keys().collect::<Vec<_>>()})),
+            Err(e) => ("500 Internal Server Error", serde_json::json!({"error": e})),
+        },
+        (_, Some(secret)) => {
+            let changed = crate::secrets::load(&app_dir).and_then(|mut secrets| {
+                let found = match method {
+                    "PUT" => {
+                        let value = String::from_utf8(body.to_vec()).map_err(|_| "Secret values are UTF-8".to_string())?;
+                        secrets.insert(secret.to_string(), value);
+                        true
+                    }
+                    _ => secrets.remove(secret).is_some(),
+                };
+                if found {
+                    crate::secrets::save(&app_dir, &secrets)?;
+                }
+                Ok(found)
+            });
+            match changed {
+                Ok(true) => {
+                    crate::logs::info(name, &format!("secret '{}' {}", secret, if method == "PUT" { "set" } else { "removed" }));
+                    reload_app(platform, name);
+                    ("200 OK", serde_json::json!({"ok": true}))
+                }
+                Ok(false) => ("404 Not Found", serde_json::json!({"error": format!("No secret '{}'", secret)})),
+                Err(e) => ("500 Internal Server Error", serde_json::json!({"error": e})),
+            }
+        }
+        _ => ("404 Not Found", serde_json::json!({"error": "Not found"})),
+    };
+    let msg = msg.to_string();
+    let eh = format_extra_headers(extra_headers);
+    let resp = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n",
+        status, msg.len(), eh
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(msg.as_bytes())
+}
+
+/// `GET /api/tokens` lists the deploy tokens, `POST` makes one and
+/// `DELETE /api/tokens/<name>` revokes one
+fn handle_tokens(
+    stream: &mut Conn,
+    platform: &Platform,
+    method: &str,
+    path: &str,
+    body: &[u8],
+    extra_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let tokens = &platform.deploy_tokens;
+    let (status, msg) = match (method, path.strip_prefix("/api/tokens/")) {
+        ("GET" | "HEAD", None) => ("200 OK", tokens.list()),
+        ("POST", None) => match serde_json::from_slice::<serde_json::Value>(body) {
+            Ok(req) => {
+                let name = req.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                let app = req.get("app").and_then(|a| a.as_str());
+                match tokens.create(name, app) {
+                    Ok(token) => {
+                        eprintln!("[platform] deploy token '{}' made for {}", name, app.unwrap_or("any app"));
+                        ("201 Created", serde_json::json!({"name": name, "app": app, "token": token}))
+                    }
+                    Err(e) => ("400 Bad Request", serde_json::json!({"error": e})),
+                }
+            }
+            Err(e) => ("400 Bad Request", serde_json::json!({"error": format!("Invalid JSON: {}", e)})),
+        },
+        ("DELETE", Some(name)) => match tokens.revoke(name) {
+            Ok(true) => {
+                eprintln!("[platform] deploy token '{}' revoked", name);
+                ("200 OK", serde_json::json!({"ok": true}))
+            }
+            Ok(false) => ("404 Not Found", serde_json::json!({"error": format!("No token '{}'", name)})),
+            Err(e) => ("500 Internal Server Error", serde_json::json!({"error": e})),
+        },
+        _ => ("404 Not Found", serde_json::json!({"error": "Not found"})),
+    };
+    let msg = msg.to_string();
+    let eh = format_extra_headers(extra_headers);
+    let resp = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n",
+        status, msg.len(), eh
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(msg.as_bytes())
+}
+
+/// Load a running (non-static) app again, so it sees changed secrets
+fn reload_app(platform: &Platform, name: &str) {
+    let running = platform.apps.read().unwrap().get(name).is_some_and(|app| !app.is_static);
+    if !running {
+        return;
+    }
+    match load_app(name, &platform.data_dir, platform).and_then(|handle| health_render(&handle).map(|_| handle)) {
+        Ok(handle) => {
+            start_data_threads(swap_app(platform, handle));
+            crate::logs::info(name, "reloaded");
+        }
+        Err(e) => crate::logs::error(name, &format!("reload failed: {}", e)),
+    }
+}
+
+// ── Per-app request handlers ────────────────────────────────────────
+
+fn handle_app_sse(
+    mut stream: Conn,
+    app: &AppHandle,
+    extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    use crate::{extract_session_cookie, generate_session_id};
+
+    // Get or create session ID from cookie
+    let session_id = extract_session_cookie(req_headers)
+        .unwrap_or_else(generate_session_id);
+    if app.clients_full(Some(&session_id)) {
+        return write_clients_full(&mut stream, app, extra_headers);
+    }
 
     let eh = format_extra_headers(extra_headers);
+    let (_, csrf_cookie) = csrf::token(req_headers);
+    let enc = SseStream::negotiate(req_headers);
     let header = format!(
         "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
-        Cache-Control: no-cache\r\nConnection: keep-alive\r\n\
-        Set-Cookie: magnetic_sid={}; Path=/; HttpOnly; SameSite=Lax\r\n{}\r\n",
-        session_id, eh
+        Cache-Control: no-cache\r\nConnection: keep-alive\r\n{}{}{}{}\r\n",
+        enc.headers(), crate::session_cookie(&session_id), csrf_cookie, eh
     );
     stream.write_all(header.as_bytes())?;
 
@@ -1229,67 +2197,179 @@ fn handle_app_sse(
 
     let path = app.session_paths.lock().unwrap()
         .get(&session_id).cloned().unwrap_or_else(|| "/".to_string());
+    let locale = app.locales.negotiate(req_headers);
+    app.session_locales.set(&session_id, locale.clone());
+
+    // A reconnecting EventSource gets the events it missed (resume.rs)
+    let mut stream = SseStream::new(stream, enc);
+    if let Some(missed) = app.sse_history.missed(&session_id, req_headers) {
+        for (id, snapshot) in &missed {
+            write_sse_event(&mut stream, Some(*id), snapshot.as_bytes())?;
+        }
+        eprintln!("[platform:{}] SSE resumed (session={}, {} missed)", app.name, &session_id[..8], missed.len());
+    } else {
+        // Inject fresh data from DataContext before rendering the initial snapshot.
+        // Delta mode skips on_change() so V8 state may be stale — RenderWithData
+        // ensures new connections always see current data.
+        let id = app.sse_history.open(&session_id);
+        app.note_user(&session_id, req_headers);
+        let data_json = app.render_data(&session_id, &path);
+        let reply = Reply::new();
+        let req = if let Some(dj) = data_json {
+            V8Request::RenderWithData {
+                path: path.clone(),
+                session_id: session_id.clone(),
+                data_json: dj,
+                locale,
+                reply: reply.clone(),
+            }
+        } else {
+            V8Request::Render { path: path.clone(), session_id: session_id.clone(), locale, reply: reply.clone() }
+        };
+        if tx.send(req).is_err() {
+            // The 200 head is out; close so the EventSource retries
+            return stream.get_ref().shutdown(std::net::Shutdown::Both);
+        }
+        let dom_json = v8_result_to_json(reply.recv(), None);
+        let snapshot = format!("{{\"root\":{}}}", dom_json);
+        write_sse_event(&mut stream, Some(id), snapshot.as_bytes())?;
+        app.sse_deltas.reset(&session_id, &snapshot);
+    }
+
+    // Hand the socket to the registry (its writer thread writes to it from
+    // now on). Shutting down reads ends serve_connection's wait for another
+    // request, which frees this worker.
+    stream.get_ref().shutdown(std::net::Shutdown::Read)?;
+    stream.get_ref().set_write_timeout(Some(SSE_WRITE_TIMEOUT))?;
+    let client = Client::sse(stream)?;
+    {
+        let mut clients = app.sse_clients.lock().unwrap();
+        // Replace old streams for this session — prevents duplicate deltas
+        // when the browser refreshes (new EventSource, same session cookie).
+        clients.insert(session_id.clone(), vec![client]);
+    }
+    // Re-insert into session_paths — it may have been cleaned up if a previous
+    // SSE connection for this session disconnected.
+    app.session_paths.lock().unwrap().entry(session_id.clone()).or_insert(path.clone());
+    app.note_clients();
+    eprintln!("[platform:{}] SSE connected (session={}, path={})", app.name, &session_id[..8], path);
+    Ok(())
+}
+
+/// 503 for an SSE or WebSocket client past the app's `sse_clients` quota
+fn write_clients_full(
+    stream: &mut Conn,
+    app: &AppHandle,
+    extra_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    crate::logs::error(&app.name, &format!("refused a client: at the limit of {} clients", app.quota.sse_clients));
+    let msg = serde_json::json!({
+        "error": format!("App '{}' is at its limit of {} live clients", app.name, app.quota.sse_clients)
+    }).to_string();
+    let eh = format_extra_headers(extra_headers);
+    let resp = format!(
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\n\
+        Retry-After: {}\r\nContent-Length: {}\r\n{}\r\n",
+        CLIENTS_FULL_RETRY_SECS, msg.len(), eh
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(msg.as_bytes())
+}
+
+/// WebSocket alternative to handle_app_sse (see ws.rs). Actions arriving
+/// on the socket run with the auth cookie of the upgrade request.
+fn handle_app_ws(
+    stream: &mut Conn,
+    app: &Arc<AppHandle>,
+    extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    use crate::{extract_session_cookie, generate_session_id};
+
+    if !csrf::check_origin(req_headers, app.auth.as_ref().map(|auth| auth.cookie_name())) {
+        return csrf::write_rejected(stream, "Cross-origin WebSocket refused");
+    }
+    let session_id = extract_session_cookie(req_headers)
+        .unwrap_or_else(generate_session_id);
+    if app.clients_full(None) {
+        return write_clients_full(stream, app, extra_headers);
+    }
+
+    let (_, csrf_cookie) = csrf::token(req_headers);
+    let headers = format!(
+        "{}{}{}",
+        crate::session_cookie(&session_id), csrf_cookie, format_extra_headers(extra_headers)
+    );
+    if !ws::accept(stream, req_headers, &headers)? {
+        return Ok(());
+    }
+    let (sender, mut reader) = ws::split(stream.try_clone()?)?;
+    sender.set_write_timeout(Some(SSE_WRITE_TIMEOUT))?;
+
+    app.touch();
+    let tx = app.ensure_warm().map_err(std::io::Error::other)?;
+
+    let path = app.session_paths.lock().unwrap()
+        .get(&session_id).cloned().unwrap_or_else(|| "/".to_string());
+    let locale = app.locales.negotiate(req_headers);
+    app.session_locales.set(&session_id, locale.clone());
 
-    // Inject fresh data from DataContext before rendering the initial snapshot.
-    // Delta mode skips on_change() so V8 state may be stale — RenderWithData
-    // ensures new connections always see current data.
-    let data_json = app.data_ctx.as_ref()
-        .map(|ctx| ctx.data_json_for_page(&path));
+    // Fresh data for the initial snapshot, as for SSE
+    app.note_user(&session_id, req_headers);
+    let data_json = app.render_data(&session_id, &path);
     let reply = Reply::new();
     let req = if let Some(dj) = data_json {
         V8Request::RenderWithData {
             path: path.clone(),
             session_id: session_id.clone(),
             data_json: dj,
+            locale,
             reply: reply.clone(),
         }
     } else {
-        V8Request::Render { path: path.clone(), session_id: session_id.clone(), reply: reply.clone() }
+        V8Request::Render { path: path.clone(), session_id: session_id.clone(), locale, reply: reply.clone() }
     };
     if tx.send(req).is_err() {
-        return stream.write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n");
+        sender.close();
+        return Ok(());
     }
     let dom_json = v8_result_to_json(reply.recv(), None);
     let snapshot = format!("{{\"root\":{}}}", dom_json);
-    write_sse_event(&mut stream, snapshot.as_bytes())?;
-
-    let client = stream.try_clone()?;
-    {
-        let mut clients = app.sse_clients.lock().unwrap();
-        // Replace old streams for this session — prevents duplicate deltas
-        // when the browser refreshes (new EventSource, same session cookie).
-        clients.insert(session_id.clone(), vec![client]);
-    }
-    // Re-insert into session_paths — it may have been cleaned up if a previous
-    // SSE connection for this session disconnected.
+    sender.send("snapshot", snapshot.as_bytes())?;
+    let client = Client::ws(sender.clone(), stream)?;
+    app.sse_clients.lock().unwrap()
+        .entry(session_id.clone()).or_default().push(client.clone());
     app.session_paths.lock().unwrap().entry(session_id.clone()).or_insert(path.clone());
-    eprintln!("[platform:{}] SSE connected (session={}, path={})", app.name, &session_id[..8], path);
-
-    loop {
-        thread::sleep(std::time::Duration::from_secs(30));
-        if stream.write_all(b": keepalive\n\n").is_err() {
-            eprintln!("[platform:{}] SSE disconnected (session={})", app.name, &session_id[..8]);
-            // Clean up this client
-            let mut clients = app.sse_clients.lock().unwrap();
-            if let Some(list) = clients.get_mut(&session_id) {
-                list.retain(|mut c| c.write_all(b"").is_ok());
-                if list.is_empty() {
-                    clients.remove(&session_id);
-                    // Drop session state in V8
-                    if let Ok(tx) = app.ensure_warm() {
-                        let _ = tx.send(V8Request::DropSession { session_id: session_id.clone() });
-                    }
-                    app.session_paths.lock().unwrap().remove(&session_id);
-                }
+    app.note_clients();
+    eprintln!("[platform:{}] WebSocket connected (session={}, path={})", app.name, &session_id[..8], path);
+
+    let app = Arc::clone(app);
+    let req_headers = req_headers.clone();
+    thread::Builder::new().name("ws-reader".into()).spawn(move || {
+        while let Ok(Some((action, payload))) = reader.next_action(&sender) {
+            let _in_flight = crate::shutdown::track();
+            if action_access(&app, &session_id, &action, payload.as_bytes(), &req_headers) != Access::Allowed {
+                eprintln!("[platform:{}] ws action {} refused by auth.protect", app.name, action);
+                continue;
+            }
+            let snapshot = match run_app_action(&app, &session_id, &action, payload, &req_headers) {
+                Ok(Some(snapshot)) => snapshot,
+                _ => break,
+            };
+            if action == "navigate" {
+                let _ = client.send_snapshot(None, &snapshot.into(), None);
+            } else {
+                broadcast_snapshot(&app.sse_clients, &app.sse_history, &app.sse_deltas, &session_id, &snapshot);
             }
-            break;
         }
-    }
+        // The registry drops this client at its next failed send
+        client.close();
+    })?;
     Ok(())
 }
 
 fn handle_app_action(
-    stream: &mut TcpStream,
+    stream: &mut Conn,
     app: &AppHandle,
     url_path: &str,
     body: &[u8],
@@ -1298,22 +2378,152 @@ fn handle_app_action(
 ) -> std::io::Result<()> {
     use crate::{extract_session_cookie};
 
+    if !csrf::check(req_headers, app.auth.as_ref().map(|auth| auth.cookie_name())) {
+        return csrf::write_rejected(stream, "Missing or invalid CSRF token");
+    }
     let action = urlencoding_decode(url_path.strip_prefix("/actions/").unwrap_or(""));
-    let body_str = String::from_utf8_lossy(body);
 
     // Session ID from cookie (fall back to __default for cookieless requests)
     let session_id = extract_session_cookie(req_headers)
         .unwrap_or_else(|| "__default".to_string());
+    let access = action_access(app, &session_id, &action, body, req_headers);
+    if access != Access::Allowed {
+        return write_action_denied(stream, access);
+    }
+    app.session_locales.set(&session_id, app.locales.negotiate(req_headers));
+    app.note_user(&session_id, req_headers);
+
+    // A retry with a known Idempotency-Key gets the first response again
+    // (and stores no uploads). A key whose action doesn't finish is released
+    // when `outcome` drops.
+    let (snapshot, replayed) = match app.idempotency.begin(&session_id, req_headers, &action, body) {
+        Some(Outcome::Replay(snapshot)) => (snapshot.to_string(), true),
+        Some(refused @ (Outcome::InProgress | Outcome::Mismatch)) => {
+            return idempotency::write_refused(stream, &refused);
+        }
+        outcome => {
+            let uploads_dir = format!("{}/{}/uploads", app.data_dir, app.name);
+            let payload_str = match crate::action_payload(body, req_headers, &uploads_dir) {
+                Ok(payload) => payload,
+                Err(e) => return upload::write_error(stream, &e),
+            };
+            let Some(snapshot) = run_app_action(app, &session_id, &action, payload_str, req_headers)? else {
+                let msg = "{\"error\":\"V8 thread unavailable\"}";
+                let resp = format!("HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n", msg.len());
+                stream.write_all(resp.as_bytes())?;
+                return stream.write_all(msg.as_bytes());
+            };
+            if let Some(Outcome::Run(pending)) = outcome {
+                pending.complete(&snapshot);
+            }
+            (snapshot, false)
+        }
+    };
+
+    let eh = format_extra_headers(extra_headers);
+    let replayed_header = if replayed { "Idempotent-Replayed: true\r\n" } else { "" };
+    let resp = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+        Content-Length: {}\r\n{}{}\r\n",
+        snapshot.len(), replayed_header, eh
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(snapshot.as_bytes())?;
+
+    // Broadcast only to this session's clients (not all users). A replay
+    // changed nothing.
+    if action != "navigate" && !replayed {
+        broadcast_snapshot(&app.sse_clients, &app.sse_history, &app.sse_deltas, &session_id, &snapshot);
+    }
+    Ok(())
+}
 
-    let payload_str = if body_str.is_empty() { "{}".to_string() } else {
-        if let Ok(val) = serde_json::from_str::<serde_json::Value>(&body_str) {
-            if let Some(p) = val.get("payload") { p.to_string() } else { val.to_string() }
-        } else { "{}".to_string() }
+/// The `auth.protect` verdict for an action: for its own path
+/// (`/actions/<name>`) and for the page it acts on, a navigate's target or
+/// else the session's page.
+fn action_access(
+    app: &AppHandle,
+    session_id: &str,
+    action: &str,
+    payload: &[u8],
+    req_headers: &HashMap<String, String>,
+) -> Access {
+    let Some(auth) = &app.auth else { return Access::Allowed };
+    let page = if action == "navigate" {
+        serde_json::from_slice::<serde_json::Value>(payload)
+            .ok()
+            .and_then(|v| v.get("path")?.as_str().map(String::from))
+    } else {
+        app.session_paths.lock().unwrap().get(session_id).cloned()
     };
+    let page = page.unwrap_or_else(|| "/".to_string());
+    let page = page.split('?').next().unwrap_or("/");
+    auth.access(req_headers, &format!("/actions/{}", action)).max(auth.access(req_headers, page))
+}
 
-    let snapshot: String;
+/// 401 (not logged in) or 403 (without a needed role) for an action
+fn write_action_denied(stream: &mut Conn, access: Access) -> std::io::Result<()> {
+    let (status, msg) = match access {
+        Access::Login => (401, "{\"error\":\"Login required\"}"),
+        _ => (403, "{\"error\":\"Forbidden\"}"),
+    };
+    let resp = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+        status, crate::status_text(status), msg.len()
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(msg.as_bytes())
+}
+
+/// Whether a request may deploy app `name` or manage its secrets: with a
+/// deploy token (deploy_tokens.rs) when the platform has one, or with one
+/// of the app's deploy keys when it has auth.api_keys
+fn deploy_access(platform: &Platform, name: &str, req_headers: &HashMap<String, String>) -> Access {
+    let by_token = platform.deploy_tokens.access(name, req_headers);
+    if by_token == Some(Access::Allowed) {
+        return Access::Allowed;
+    }
+    let by_key = {
+        let apps = platform.apps.read().unwrap();
+        apps.get(name)
+            .and_then(|app| app.auth.as_ref())
+            .filter(|auth| !auth.config.api_keys.is_empty())
+            .map(|auth| auth.deploy_access(req_headers))
+    };
+    match (by_token, by_key) {
+        (_, Some(Access::Allowed)) | (None, None) => Access::Allowed,
+        (Some(denied), _) | (None, Some(denied)) => denied,
+    }
+}
+
+/// 401 (with a Bearer challenge) or 403 for an API or deploy request
+/// without a fitting key
+fn write_key_denied(stream: &mut Conn, access: Access, extra_headers: &HashMap<String, String>) -> std::io::Result<()> {
+    let (status, challenge, msg) = match access {
+        Access::Login => (401, "WWW-Authenticate: Bearer\r\n", "{\"error\":\"API key required\"}"),
+        _ => (403, "", "{\"error\":\"API key lacks the needed scope\"}"),
+    };
+    let eh = format_extra_headers(extra_headers);
+    let resp = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\n{}\r\n",
+        status, crate::status_text(status), challenge, msg.len(), eh
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(msg.as_bytes())
+}
 
+/// Run one action for a session and render the result. Shared by
+/// POST /actions/<name> and WebSocket action messages. None if the app's
+/// V8 thread is gone.
+fn run_app_action(
+    app: &AppHandle,
+    session_id: &str,
+    action: &str,
+    payload_str: String,
+    req_headers: &HashMap<String, String>,
+) -> std::io::Result<Option<String>> {
     app.touch();
+    app.metrics.action();
     let tx = app.ensure_warm().map_err(|e| {
         std::io::Error::new(std::io::ErrorKind::Other, e)
     })?;
@@ -1321,142 +2531,216 @@ fn handle_app_action(
     // Extract auth token from session (if auth middleware configured)
     let auth_token = app.auth.as_ref()
         .and_then(|auth| auth.get_access_token(req_headers));
+    let locale = app.session_locales.get(session_id);
 
-    if action == "navigate" {
+    let snapshot = if action == "navigate" {
         let nav_path = serde_json::from_str::<serde_json::Value>(&payload_str)
             .ok()
             .and_then(|v| v.get("path")?.as_str().map(String::from))
             .unwrap_or_else(|| "/".to_string());
 
         eprintln!("[platform:{}] navigate → {} (session={})", app.name, nav_path, &session_id[..std::cmp::min(8, session_id.len())]);
-        app.session_paths.lock().unwrap().insert(session_id.clone(), nav_path.clone());
+        app.session_paths.lock().unwrap().insert(session_id.to_string(), nav_path.clone());
 
         // On navigation, fetch page-scoped data sources for the new page
         if let Some(ref ctx) = app.data_ctx {
-            fetch_page_data_with_token(ctx, &nav_path, auth_token.as_deref());
-            let data_json = ctx.data_json_for_page(&nav_path);
+            fetch_page_data_with_token(ctx, &nav_path, Some(session_id), auth_token.as_deref());
+        }
+        if let Some(data_json) = app.render_data(session_id, &nav_path) {
             let reply = Reply::new();
             if tx.send(V8Request::RenderWithData {
-                path: nav_path, data_json, session_id: session_id.clone(), reply: reply.clone(),
+                path: nav_path, data_json, session_id: session_id.to_string(), locale, reply: reply.clone(),
             }).is_err() {
-                let msg = "{\"error\":\"V8 thread unavailable\"}";
-                let resp = format!("HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n", msg.len());
-                stream.write_all(resp.as_bytes())?;
-                return stream.write_all(msg.as_bytes());
+                return Ok(None);
             }
             let dom_json = v8_result_to_json(reply.recv(), None);
-            snapshot = format!("{{\"root\":{}}}", dom_json);
+            format!("{{\"root\":{}}}", dom_json)
         } else {
             let reply = Reply::new();
-            if tx.send(V8Request::Render { path: nav_path, session_id: session_id.clone(), reply: reply.clone() }).is_err() {
-                let msg = "{\"error\":\"V8 thread unavailable\"}";
-                let resp = format!("HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n", msg.len());
-                stream.write_all(resp.as_bytes())?;
-                return stream.write_all(msg.as_bytes());
+            if tx.send(V8Request::Render { path: nav_path, session_id: session_id.to_string(), locale, reply: reply.clone() }).is_err() {
+                return Ok(None);
             }
             let dom_json = v8_result_to_json(reply.recv(), None);
-            snapshot = format!("{{\"root\":{}}}", dom_json);
+            format!("{{\"root\":{}}}", dom_json)
         }
     } else {
         let path = app.session_paths.lock().unwrap()
-            .get(&session_id).cloned().unwrap_or_else(|| "/".to_string());
+            .get(session_id).cloned().unwrap_or_else(|| "/".to_string());
         let payload_val: serde_json::Value = serde_json::from_str(&payload_str).unwrap_or_default();
 
         // Check if this action maps to an external API
         if let Some(ref ctx) = app.data_ctx {
-            if let Some(mapping) = ctx.find_action(&action) {
-                let mapping = mapping.clone();
-                eprintln!("[platform:{}] external action '{}' → {} {}", app.name, action, mapping.method, mapping.url);
-
-                // Forward to backend API
-                match forward_action(&mapping, &payload_val) {
-                    Ok(response_val) => {
-                        // If action has a target, update that data source
-                        if let Some(ref target) = mapping.target {
-                            ctx.set_value(target, response_val);
+            let mapping = ctx.find_action(action).cloned();
+            if mapping.is_some() || action == PAGINATE_ACTION {
+                if let Some(mapping) = mapping {
+                    if mapping.query.is_some() {
+                        eprintln!("[platform:{}] db action '{}'", app.name, action);
+                    } else {
+                        eprintln!("[platform:{}] external action '{}' → {} {}", app.name, action, mapping.method, crate::secrets::mask(&mapping.url));
+                    }
+
+                    // Show the optimistic result right away, over SSE
+                    let rollback = match (&mapping.optimistic, &mapping.target) {
+                        (Some(op), Some(target)) => {
+                            let prev = ctx.value_for(Some(session_id), target);
+                            match crate::optimistic::apply(op, prev.as_ref(), &payload_val) {
+                                Ok(value) => {
+                                    ctx.put_value_for(Some(session_id), target, Some(value));
+                                    let data_json = app.render_data(session_id, &path).unwrap_or_else(|| ctx.data_json_for_page(&path));
+                                    let reply = Reply::new();
+                                    if tx.send(V8Request::RenderWithData {
+                                        path: path.clone(), data_json, session_id: session_id.to_string(), locale: locale.clone(), reply: reply.clone(),
+                                    }).is_ok() {
+                                        let snapshot = format!("{{\"root\":{}}}", v8_result_to_json(reply.recv(), Some(action)));
+                                        broadcast_snapshot(&app.sse_clients, &app.sse_history, &app.sse_deltas, session_id, &snapshot);
+                                    }
+                                    Some((target.clone(), prev))
+                                }
+                                Err(e) => {
+                                    crate::logs::error(&app.name, &format!("action '{}' optimistic: {}", action, e));
+                                    None
+                                }
+                            }
+                        }
+                        _ => None,
+                    };
+
+                    // Forward to backend API
+                    match forward_action(&mapping, &payload_val) {
+                        Ok(response_val) => {
+                            // If action has a target, update that data source
+                            if let Some(ref target) = mapping.target {
+                                if mapping.query.is_none() {
+                                    ctx.set_value_for(Some(session_id), target, response_val);
+                                } else if !ctx.sources_for_page(&path).iter().any(|s| s.key == *target) {
+                                    // A statement's result isn't the target's rows: read them
+                                    // again (sources on this page are refetched below)
+                                    let source = ctx.config.data.iter().find(|s| s.key == *target).cloned();
+                                    if let Some(source) = source {
+                                        match ctx.fetch(&source, auth_token.as_deref()) {
+                                            Ok(value) => ctx.set_value_for(Some(session_id), target, value),
+                                            Err(e) => ctx.set_error_for(Some(session_id), target, &e),
+                                        }
+                                    }
+                                }
+                            }
+                            // Re-fetch affected data sources for current page
+                            fetch_page_data_with_token(ctx, &path, Some(session_id), auth_token.as_deref());
+                        }
+                        Err(e) => {
+                            crate::logs::error(&app.name, &format!("action forward error: {}", e));
+                            if let Some((target, prev)) = rollback {
+                                crate::logs::info(&app.name, &format!("rolling back optimistic '{}'", target));
+                                ctx.put_value_for(Some(session_id), &target, prev);
+                            }
                         }
-                        // Re-fetch affected data sources for current page
-                        fetch_page_data_with_token(ctx, &path, auth_token.as_deref());
                     }
-                    Err(e) => {
-                        eprintln!("[platform:{}] action forward error: {}", app.name, e);
+                } else {
+                    // Built-in: the next page of a paginated source
+                    let key = payload_val.get("key").and_then(|v| v.as_str()).unwrap_or("");
+                    if let Err(e) = fetch_next_page(ctx, session_id, key, auth_token.as_deref()) {
+                        crate::logs::error(&app.name, &format!("{} '{}': {}", PAGINATE_ACTION, key, e));
                     }
                 }
 
                 // Render with updated data
-                let data_json = ctx.data_json_for_page(&path);
+                let data_json = app.render_data(session_id, &path).unwrap_or_else(|| ctx.data_json_for_page(&path));
                 let reply = Reply::new();
                 if tx.send(V8Request::RenderWithData {
-                    path: path.clone(), data_json, session_id: session_id.clone(), reply: reply.clone(),
+                    path: path.clone(), data_json, session_id: session_id.to_string(), locale, reply: reply.clone(),
                 }).is_err() {
-                    let msg = "{\"error\":\"V8 thread unavailable\"}";
-                    let resp = format!("HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n", msg.len());
-                    stream.write_all(resp.as_bytes())?;
-                    return stream.write_all(msg.as_bytes());
+                    return Ok(None);
                 }
-                let dom_json = v8_result_to_json(reply.recv(), Some(&action));
-                snapshot = format!("{{\"root\":{}}}", dom_json);
+                let dom_json = v8_result_to_json(reply.recv(), Some(action));
+                format!("{{\"root\":{}}}", dom_json)
             } else {
                 // Not an external action — fall through to local reducer
                 let reply = Reply::new();
+                let data_json = app.render_data(session_id, &path);
                 if tx.send(V8Request::Reduce {
-                    action: action.clone(), payload: payload_str, path, session_id: session_id.clone(), reply: reply.clone(),
+                    action: action.to_string(), payload: payload_str, path, session_id: session_id.to_string(),
+                    locale, data_json, reply: reply.clone(),
                 }).is_err() {
-                    let msg = "{\"error\":\"V8 thread unavailable\"}";
-                    let resp = format!("HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n", msg.len());
-                    stream.write_all(resp.as_bytes())?;
-                    return stream.write_all(msg.as_bytes());
+                    return Ok(None);
                 }
-                let dom_json = v8_result_to_json(reply.recv(), Some(&action));
-                snapshot = format!("{{\"root\":{}}}", dom_json);
+                let dom_json = v8_result_to_json(reply.recv(), Some(action));
+                format!("{{\"root\":{}}}", dom_json)
             }
         } else {
             // No data layer — standard reducer path
             let reply = Reply::new();
+            let data_json = app.render_data(session_id, &path);
             if tx.send(V8Request::Reduce {
-                action: action.clone(), payload: payload_str, path, session_id: session_id.clone(), reply: reply.clone(),
+                action: action.to_string(), payload: payload_str, path, session_id: session_id.to_string(),
+                locale, data_json, reply: reply.clone(),
             }).is_err() {
-                let msg = "{\"error\":\"V8 thread unavailable\"}";
-                let resp = format!("HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n", msg.len());
-                stream.write_all(resp.as_bytes())?;
-                return stream.write_all(msg.as_bytes());
+                return Ok(None);
             }
-            let dom_json = v8_result_to_json(reply.recv(), Some(&action));
-            snapshot = format!("{{\"root\":{}}}", dom_json);
+            let dom_json = v8_result_to_json(reply.recv(), Some(action));
+            format!("{{\"root\":{}}}", dom_json)
         }
+    };
+    if action == "navigate" {
+        // The reply goes to this client only: later deltas start from it
+        app.sse_deltas.reset(session_id, &snapshot);
     }
+    Ok(Some(snapshot))
+}
 
+/// POST /hooks/<source>: a delivery for a webhook data source (webhook.rs)
+fn handle_app_hook(
+    stream: &mut Conn,
+    app: &AppHandle,
+    url_path: &str,
+    body: &[u8],
+    extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let key = urlencoding_decode(url_path.strip_prefix("/hooks/").unwrap_or(""));
+    let source = app.data_ctx.as_ref()
+        .and_then(|ctx| ctx.config.data.iter().find(|s| s.key == key && s.source_type == "webhook"));
+    let (status, msg) = match (app.data_ctx.as_ref(), source) {
+        (Some(ctx), Some(source)) => {
+            let header = source.signature_header.as_deref().unwrap_or(crate::webhook::DEFAULT_SIGNATURE_HEADER);
+            let verified = match source.webhook_secret() {
+                Some(secret) => crate::webhook::verify(&secret, header, req_headers, body),
+                None => Ok(()),
+            };
+            match verified {
+                Err(e) => {
+                    crate::logs::error(&app.name, &format!("webhook '{}' refused: {}", key, e));
+                    ("401 Unauthorized", serde_json::json!({"error": e}))
+                }
+                Ok(()) => {
+                    let value = serde_json::from_slice(body)
+                        .unwrap_or_else(|_| serde_json::Value::String(String::from_utf8_lossy(body).into_owned()));
+                    match ctx.deliver(source, value) {
+                        Ok(stored) => {
+                            eprintln!("[platform:{}] webhook '{}' delivery{}", app.name, key, if stored { "" } else { " (duplicate)" });
+                            ("200 OK", serde_json::json!({"ok": true}))
+                        }
+                        Err(e) => {
+                            crate::logs::error(&app.name, &format!("webhook '{}': {}", key, e));
+                            ("400 Bad Request", serde_json::json!({"error": e}))
+                        }
+                    }
+                }
+            }
+        }
+        _ => ("404 Not Found", serde_json::json!({"error": format!("No webhook '{}'", key)})),
+    };
+    let msg = msg.to_string();
     let eh = format_extra_headers(extra_headers);
     let resp = format!(
-        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
-        Content-Length: {}\r\n{}\r\n",
-        snapshot.len(), eh
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n",
+        status, msg.len(), eh
     );
     stream.write_all(resp.as_bytes())?;
-    stream.write_all(snapshot.as_bytes())?;
-
-    // Broadcast only to this session's SSE clients (not all users)
-    if action != "navigate" {
-        let mut clients = app.sse_clients.lock().unwrap();
-        if let Some(list) = clients.get_mut(&session_id) {
-            let mut alive = Vec::new();
-            for mut client in list.drain(..) {
-                if write_sse_event(&mut client, snapshot.as_bytes()).is_ok() {
-                    alive.push(client);
-                }
-            }
-            if alive.is_empty() {
-                clients.remove(&session_id);
-            } else {
-                *list = alive;
-            }
-        }
-    }
-    Ok(())
+    stream.write_all(msg.as_bytes())
 }
 
 fn handle_app_api(
-    stream: &mut TcpStream,
+    stream: &mut Conn,
     app: &AppHandle,
     method: &str,
     path: &str,
@@ -1516,7 +2800,7 @@ fn handle_app_api(
 }
 
 fn handle_app_get(
-    stream: &mut TcpStream,
+    stream: &mut Conn,
     app: Arc<AppHandle>,
     app_name: &str,
     path: &str,
@@ -1524,47 +2808,37 @@ fn handle_app_get(
     via_subdomain: bool,
     req_headers: &HashMap<String, String>,
 ) -> std::io::Result<()> {
-    // Static files
-    let has_ext = path.contains('.') && !path.ends_with('/');
-    let ext = path.rsplit('.').next().unwrap_or("");
-    if has_ext && ext != "html" {
-        let filename = path.trim_start_matches('/');
-
-        // Embedded framework assets — served from binary, never from disk
-        if let Some(result) = serve_embedded(stream, filename, extra_headers) {
-            return result;
-        }
-
-        let file_path = {
-            let hashed = std::path::Path::new(&app.asset_dir).join(filename);
-            if hashed.exists() { hashed }
-            else { std::path::Path::new(&app.static_dir).join(filename) }
-        };
+    let route_path = path.split('?').next().unwrap_or("/");
 
-        let data = match std::fs::read(&file_path) {
-            Ok(d) => d,
-            Err(_) => {
-                return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+    // auth.protect: log in first, or 403 without a needed role
+    if let Some(auth) = &app.auth {
+        match auth.access(req_headers, route_path) {
+            Access::Allowed => {}
+            Access::Login => {
+                let prefix = if via_subdomain { String::new() } else { format!("/apps/{}", app_name) };
+                let location = format!("{}/auth/login", prefix);
+                return redirects::write_redirect(stream, 302, &location, extra_headers);
             }
-        };
-
-        let ct = guess_content_type(path);
-        let is_hashed = app.manifest.reverse.contains_key(filename)
-            && app.manifest.reverse.get(filename).map(|o| o != filename).unwrap_or(false);
-        let cache = if is_hashed {
-            "public, max-age=31536000, immutable"
-        } else {
-            "public, max-age=300, must-revalidate"
-        };
+            Access::Forbidden => {
+                return write_app_error(stream, &app, via_subdomain, 403, "Forbidden", extra_headers, req_headers);
+            }
+        }
+    }
 
-        let eh = format_extra_headers(extra_headers);
-        let resp = format!(
-            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
-            Cache-Control: {}\r\n{}\r\n",
-            ct, data.len(), cache, eh
-        );
-        stream.write_all(resp.as_bytes())?;
-        return stream.write_all(&data);
+    // Static files
+    let has_ext = route_path.contains('.') && !route_path.ends_with('/');
+    let ext = route_path.rsplit('.').next().unwrap_or("");
+    if (has_ext && ext != "html") || app.routes.is_passthrough(route_path) {
+        return serve_app_static(stream, &app, route_path, via_subdomain, extra_headers, req_headers);
+    }
+    // A directory with an index file, or a client-side route
+    let dir_index = if route_path.ends_with('/') && route_path != "/" {
+        app.routes.index(&app.static_dir, route_path)
+    } else {
+        None
+    };
+    if let Some(file) = dir_index.or_else(|| app.routes.fallback(&app.static_dir, route_path)) {
+        return serve_app_static(stream, &app, &format!("/{}", file), via_subdomain, extra_headers, req_headers);
     }
 
     // SSR
@@ -1573,12 +2847,14 @@ fn handle_app_get(
         std::io::Error::new(std::io::ErrorKind::Other, e)
     })?;
 
-    let route_path = path.split('?').next().unwrap_or("/");
     let (session_id, is_new) = match crate::extract_session_cookie(req_headers) {
         Some(sid) => (sid, false),
         None => (crate::generate_session_id(), true),
     };
     app.session_paths.lock().unwrap().insert(session_id.clone(), route_path.to_string());
+    let locale = app.locales.negotiate(req_headers);
+    app.session_locales.set(&session_id, locale.clone());
+    let (csrf_token, csrf_cookie) = csrf::token(req_headers);
 
     // Extract auth token from session (if auth middleware configured)
     let auth_token = app.auth.as_ref()
@@ -1590,10 +2866,12 @@ fn handle_app_get(
     let reply = Reply::new();
     let mut pending_sources: Vec<crate::data::DataSourceConfig> = Vec::new();
     if let Some(ref ctx) = app.data_ctx {
-        pending_sources = fetch_page_data_streaming(ctx, route_path, auth_token.as_deref());
-        let data_json = ctx.data_json_for_page(route_path);
+        pending_sources = fetch_page_data_streaming(ctx, route_path, &session_id, auth_token.as_deref());
+    }
+    app.note_user(&session_id, req_headers);
+    if let Some(data_json) = app.render_data(&session_id, route_path) {
         if tx.send(V8Request::RenderWithDataAndCSS {
-            path: route_path.to_string(), data_json, session_id: session_id.clone(), reply: reply.clone(),
+            path: route_path.to_string(), data_json, session_id: session_id.clone(), locale: locale.clone(), reply: reply.clone(),
         }).is_err() {
             let msg = "<html><body><h1>503 — V8 thread unavailable</h1></body></html>";
             let resp = format!("HTTP/1.1 503 Service Unavailable\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n", msg.len());
@@ -1602,7 +2880,7 @@ fn handle_app_get(
         }
     } else {
         if tx.send(V8Request::RenderWithCSS {
-            path: route_path.to_string(), session_id: session_id.clone(), reply: reply.clone(),
+            path: route_path.to_string(), session_id: session_id.clone(), locale: locale.clone(), reply: reply.clone(),
         }).is_err() {
             let msg = "<html><body><h1>503 — V8 thread unavailable</h1></body></html>";
             let resp = format!("HTTP/1.1 503 Service Unavailable\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n", msg.len());
@@ -1617,14 +2895,15 @@ fn handle_app_get(
         let route = route_path.to_string();
         let token = auth_token.clone();
         let deferred_sid = session_id.clone();
+        let deferred_locale = locale.clone();
         thread::spawn(move || {
             if let Some(ref ctx) = deferred_app.data_ctx {
                 for source in &pending_sources {
-                    match fetch_data_source(source, token.as_deref()) {
-                        Ok(value) => ctx.set_value(&source.key, value),
+                    match ctx.fetch(source, token.as_deref()) {
+                        Ok(value) => ctx.set_value_for(Some(&deferred_sid), &source.key, value),
                         Err(e) => {
                             eprintln!("[data] deferred fetch error: {}", e);
-                            ctx.set_value(&source.key, serde_json::json!({ "__error": e }));
+                            ctx.set_error_for(Some(&deferred_sid), &source.key, &e);
                         }
                     }
                 }
@@ -1632,25 +2911,15 @@ fn handle_app_get(
                 ctx.values.write().unwrap().remove("__loading");
                 // Re-render with complete data and push via SSE
                 if let Ok(tx) = deferred_app.ensure_warm() {
-                    let data_json = ctx.data_json_for_page(&route);
+                    let data_json = deferred_app.render_data(&deferred_sid, &route).unwrap_or_else(|| ctx.data_json_for_page(&route));
                     let reply = Reply::new();
                     if tx.send(V8Request::RenderWithData {
-                        path: route, data_json, session_id: deferred_sid.clone(), reply: reply.clone(),
+                        path: route, data_json, session_id: deferred_sid.clone(), locale: deferred_locale, reply: reply.clone(),
                     }).is_ok() {
                         let dom_json = v8_result_to_json(reply.recv(), None);
                         let snapshot = format!("{{\"root\":{}}}", dom_json);
                         // Push SSE update to the session that triggered this render
-                        let mut clients = deferred_app.sse_clients.lock().unwrap();
-                        if let Some(list) = clients.get_mut(&deferred_sid) {
-                            let mut alive = Vec::new();
-                            for mut client in list.drain(..) {
-                                if write_sse_event(&mut client, snapshot.as_bytes()).is_ok() {
-                                    alive.push(client);
-                                }
-                            }
-                            if alive.is_empty() { clients.remove(&deferred_sid); }
-                            else { *list = alive; }
-                        }
+                        broadcast_snapshot(&deferred_app.sse_clients, &deferred_app.sse_history, &deferred_app.sse_deltas, &deferred_sid, &snapshot);
                         eprintln!("[data] deferred data ready, pushed SSE update (session={})", &deferred_sid[..8]);
                     }
                 }
@@ -1659,32 +2928,73 @@ fn handle_app_get(
     }
 
     // Parse {root: DomNode, css: string|null} from renderWithCSS result
-    let (dom, generated_css) = match reply.recv() {
+    // A render that fails is a 500 with the app's error page
+    let render_error = |message: &str| {
+        let dom = error_page::dom(Some(&tx), &app.static_dir, 500, message, locale.clone());
+        (dom, None, 500)
+    };
+    let (dom, generated_css, status) = match reply.recv() {
         V8Result::Ok(json) => {
             match serde_json::from_str::<serde_json::Value>(&json) {
                 Ok(wrapper) => {
                     let root_val = wrapper.get("root").cloned().unwrap_or(serde_json::Value::Null);
                     let css_val = wrapper.get("css").and_then(|v| v.as_str()).map(String::from);
                     match serde_json::from_value::<DomNode>(root_val) {
-                        Ok(d) => (d, css_val),
+                        Ok(d) => (d, css_val, 200),
                         Err(e) => {
-                            eprintln!("[platform:{}] render parse error: {}", app_name, e);
-                            (error_fallback(&format!("JSON parse error: {}", e), None), None)
+                            crate::logs::error(app_name, &format!("render parse error: {}", e));
+                            render_error(&format!("JSON parse error: {}", e))
                         }
                     }
                 }
                 Err(e) => {
-                    eprintln!("[platform:{}] render parse error: {}", app_name, e);
-                    (error_fallback(&format!("JSON parse error: {}", e), None), None)
+                    crate::logs::error(app_name, &format!("render parse error: {}", e));
+                    render_error(&format!("JSON parse error: {}", e))
                 }
             }
         }
         V8Result::Err(e) => {
             eprintln!("[platform:{}] render error: {}", app_name, e);
-            (error_fallback(&e, None), None)
+            render_error(&e)
         }
     };
 
+    let page = app_page(&app, via_subdomain, dom, generated_css, csrf_token, locale);
+
+    let eh = format_extra_headers(extra_headers);
+    let mut cookie_header = if is_new {
+        crate::session_cookie(&session_id)
+    } else {
+        String::new()
+    };
+    cookie_header.push_str(&csrf_cookie);
+    if status != 200 {
+        return error_page::write(stream, status, &page, &format!("{}{}", cookie_header, eh));
+    }
+    // no-cache: the browser may keep the page but revalidates it each time
+    let v = Validators::for_body(page.as_bytes());
+    if v.not_modified(req_headers) {
+        let headers = format!("Cache-Control: no-cache\r\n{}{}", cookie_header, eh);
+        return cache::write_not_modified(stream, &v, "text/html", &headers);
+    }
+    let resp = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+        Content-Length: {}\r\n{}Cache-Control: no-cache\r\n{}{}\r\n",
+        page.len(), v.headers(), cookie_header, eh
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(page.as_bytes())
+}
+
+/// The full HTML page around `dom`, with the app's CSS and client runtime
+fn app_page(
+    app: &AppHandle,
+    via_subdomain: bool,
+    dom: DomNode,
+    generated_css: Option<String>,
+    csrf_token: String,
+    lang: Option<String>,
+) -> String {
     // Merge CSS: generated CSS from design.json + user's style.css (if any)
     let merged_css = match (&generated_css, &app.inline_css) {
         (Some(gen), Some(user)) => Some(format!("{}{}", gen, user)),
@@ -1699,7 +3009,7 @@ fn handle_app_get(
     let prefix = if via_subdomain {
         String::new() // root-relative: /magnetic.js, /sse, etc.
     } else {
-        format!("/apps/{}", app_name) // path-prefixed: /apps/{name}/magnetic.js
+        format!("/apps/{}", app.name) // path-prefixed: /apps/{name}/magnetic.js
     };
     let js_hash = {
         let bytes = include_bytes!("../assets/magnetic.min.js");
@@ -1710,7 +3020,7 @@ fn handle_app_get(
     let magnetic_js = format!("{}/magnetic.js?v={}", prefix, js_hash);
     let wasm_url = Some(format!("{}/transport.wasm?v={}", prefix, js_hash));
 
-    let page = render_page(&PageOptions {
+    render_page(&PageOptions {
         root: dom,
         scripts: vec![magnetic_js],
         styles: vec![],
@@ -1718,24 +3028,112 @@ fn handle_app_get(
         sse_url: Some(format!("{}/sse", prefix)),
         mount_selector: Some("#app".to_string()),
         wasm_url,
-        title: Some(format!("{} | Magnetic", app_name)),
+        title: Some(format!("{} | Magnetic", app.name)),
         description: Some("Server-driven UI — Magnetic Platform".to_string()),
         inline_scripts: vec![],
-    });
+        csrf_token: Some(csrf_token),
+        lang,
+    })
+}
 
-    let eh = format_extra_headers(extra_headers);
-    let cookie_header = if is_new {
-        format!("Set-Cookie: magnetic_sid={}; Path=/; HttpOnly; SameSite=Lax\r\n", session_id)
+/// 404 for a missing file: the app's error page if the client wants HTML
+fn write_app_not_found(
+    stream: &mut Conn,
+    app: &AppHandle,
+    via_subdomain: bool,
+    extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    write_app_error(stream, app, via_subdomain, 404, "Page not found", extra_headers, req_headers)
+}
+
+/// `status` with the app's error page if the client wants HTML, else empty
+fn write_app_error(
+    stream: &mut Conn,
+    app: &AppHandle,
+    via_subdomain: bool,
+    status: u16,
+    message: &str,
+    extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    if !static_routes::accepts_html(req_headers) {
+        let resp = format!("HTTP/1.1 {} {}\r\nContent-Length: 0\r\n\r\n", status, crate::status_text(status));
+        return stream.write_all(resp.as_bytes());
+    }
+    let (csrf_token, csrf_cookie) = csrf::token(req_headers);
+    let tx = app.ensure_warm().ok();
+    let locale = app.locales.negotiate(req_headers);
+    let dom = error_page::dom(tx.as_ref(), &app.static_dir, status, message, locale.clone());
+    let page = app_page(app, via_subdomain, dom, None, csrf_token, locale);
+    error_page::write(stream, status, &page, &format!("{}{}", csrf_cookie, format_extra_headers(extra_headers)))
+}
+
+/// Serve a file from the app's public dir (hashed assets first)
+fn serve_app_static(
+    stream: &mut Conn,
+    app: &AppHandle,
+    path: &str,
+    via_subdomain: bool,
+    extra_headers: &HashMap<String, String>,
+    req_headers: &HashMap<String, String>,
+) -> std::io::Result<()> {
+    let filename = path.trim_start_matches('/');
+
+    // Embedded framework assets — served from binary, never from disk
+    if let Some(result) = serve_embedded(stream, filename, extra_headers, req_headers) {
+        return result;
+    }
+
+    let file_path = {
+        let hashed = std::path::Path::new(&app.asset_dir).join(filename);
+        if hashed.exists() { hashed }
+        else { std::path::Path::new(&app.static_dir).join(filename) }
+    };
+
+    let data = match std::fs::read(&file_path) {
+        Ok(d) => d,
+        Err(_) => {
+            // A directory's index file, or a client-side route's page
+            let route = format!("/{}", filename);
+            let file = app.routes.index(&app.static_dir, &route).or_else(|| {
+                static_routes::accepts_html(req_headers)
+                    .then(|| app.routes.fallback(&app.static_dir, &route))
+                    .flatten()
+            });
+            return match file {
+                Some(file) if file != filename => {
+                    serve_app_static(stream, app, &format!("/{}", file), via_subdomain, extra_headers, req_headers)
+                }
+                _ => write_app_not_found(stream, app, via_subdomain, extra_headers, req_headers),
+            };
+        }
+    };
+
+    let ct = guess_content_type(path);
+    let is_hashed = app.manifest.reverse.contains_key(filename)
+        && app.manifest.reverse.get(filename).map(|o| o != filename).unwrap_or(false);
+    let cache = if is_hashed {
+        "public, max-age=31536000, immutable"
     } else {
-        String::new()
+        "public, max-age=300, must-revalidate"
     };
+
+    let eh = format_extra_headers(extra_headers);
+    let v = Validators::for_asset(&app.manifest, filename, &data, &file_path);
+    if v.not_modified(req_headers) {
+        let headers = format!("Cache-Control: {}\r\n{}", cache, eh);
+        return cache::write_not_modified(stream, &v, ct, &headers);
+    }
+    // A hashed asset's precompressed file, if the client takes one
+    let (data, ce) = compress::read_variant(&file_path, req_headers).unwrap_or((data, ""));
     let resp = format!(
-        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
-        Content-Length: {}\r\n{}{}\r\n",
-        page.len(), cookie_header, eh
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}{}\
+        Cache-Control: {}\r\n{}\r\n",
+        ct, data.len(), ce, v.headers(), cache, eh
     );
     stream.write_all(resp.as_bytes())?;
-    stream.write_all(page.as_bytes())
+    stream.write_all(&data)
 }
 
 
@@ -0,0 +1,66 @@
+//! pool.rs — Bounded worker pool for HTTP connections
+//!
+//! A fixed set of threads serves accepted connections, so a burst of
+//! clients queues up instead of spawning a thread each. SSE streams don't
+//! hold a worker: their handler registers the socket and returns, and the
+//! socket's own writer thread sends it events and keepalives (outbox.rs).
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Workers when --workers isn't given
+pub const DEFAULT_WORKERS: usize = 64;
+
+/// Accepted connections waiting for a worker, per worker. Past that,
+/// `execute` blocks and new connections wait in the listen backlog.
+const QUEUE_PER_WORKER: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+pub struct WorkerPool {
+    tx: SyncSender<Job>,
+}
+
+impl WorkerPool {
+    /// Start `workers` threads (at least one), named `{name}-{i}`.
+    pub fn new(name: &str, workers: usize) -> Self {
+        let workers = workers.max(1);
+        let (tx, rx) = sync_channel::<Job>(workers * QUEUE_PER_WORKER);
+        let rx = Arc::new(Mutex::new(rx));
+        for i in 0..workers {
+            let rx = Arc::clone(&rx);
+            thread::Builder::new()
+                .name(format!("{}-{}", name, i))
+                .spawn(move || worker_loop(rx))
+                .expect("Failed to spawn worker thread");
+        }
+        WorkerPool { tx }
+    }
+
+    /// Run `job` on a worker, waiting for queue space if all are busy.
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        // Workers never exit while the pool exists, so send can't fail
+        let _ = self.tx.send(Box::new(job));
+    }
+}
+
+fn worker_loop(rx: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        // Lock only while waiting: the guard drops at the end of this statement
+        let next = rx.lock().unwrap().recv();
+        let Ok(job) = next else { return }; // pool dropped
+        // A panicking handler loses its connection, not the worker
+        if catch_unwind(AssertUnwindSafe(job)).is_err() {
+            eprintln!("[pool] connection handler panicked");
+        }
+    }
+}
+
+/// Worker count from `--workers`, or DEFAULT_WORKERS.
+pub fn workers_arg(args: &[String]) -> usize {
+    crate::find_arg(args, "--workers")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_WORKERS)
+}
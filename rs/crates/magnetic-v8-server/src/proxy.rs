@@ -0,0 +1,313 @@
+//! proxy.rs — Reverse-proxy routes to private backends
+//!
+//! `--proxy /api/billing=https://internal:9000` sends every request under
+//! `/api/billing` to that backend, without the prefix:
+//! `GET /api/billing/invoices?page=2` → `GET https://internal:9000/invoices?page=2`.
+//! An upstream with a path keeps it (`=https://internal:9000/api/billing`).
+//! The longest matching prefix wins, and a trailing `/*` on the prefix is
+//! allowed.
+//!
+//! Request headers are forwarded except hop-by-hop ones, `Host` (sent as
+//! `X-Forwarded-Host`) and `Accept-Encoding`. `Cookie` and `Authorization`
+//! carry the client's session, CSRF token and credentials, so they are
+//! dropped too unless the route opts in: `=credentials` after the URL, or
+//! `{"url": "...", "credentials": true}` in a config. The client's address
+//! is appended to `X-Forwarded-For`, and `X-Forwarded-Proto` says whether
+//! it came over TLS. The backend's status and
+//! headers come back as sent, and its body is streamed to the client as it
+//! arrives, chunked unless the backend gave a length. Backend redirects are
+//! passed on, not followed. A backend that can't be reached is a 502.
+//!
+//! Proxied requests go through the middleware (CORS, rate limits), but like
+//! SSE they skip the response phase, so response hooks don't see them. They
+//! are matched on the path as requested, before redirects and rewrites.
+//!
+//! In `--platform` mode each app sets its own under `proxy` in its
+//! magnetic.json config: `{"/api/billing": "https://internal:9000"}`.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::static_routes::under;
+use crate::MagneticContext;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Not forwarded in either direction (RFC 9110 §7.6.1)
+const HOP_BY_HOP: [&str; 8] = [
+    "connection", "keep-alive", "proxy-authenticate", "proxy-authorization",
+    "te", "trailer", "transfer-encoding", "upgrade",
+];
+
+/// Path prefix → upstream
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct ProxyRoutes(BTreeMap<String, Route>);
+
+/// A route's upstream: its URL, or the URL and whether the client's
+/// `Cookie` and `Authorization` go along
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Route {
+    Url(String),
+    Options {
+        url: String,
+        #[serde(default)]
+        credentials: bool,
+    },
+}
+
+impl Route {
+    pub fn url(&self) -> &str {
+        match self {
+            Route::Url(url) | Route::Options { url, .. } => url,
+        }
+    }
+
+    pub fn credentials(&self) -> bool {
+        matches!(self, Route::Options { credentials: true, .. })
+    }
+}
+
+/// Where a request goes: the backend URL, and whether credentials go along
+pub struct Upstream {
+    pub url: String,
+    pub credentials: bool,
+}
+
+/// Routes from `--proxy /prefix=url,/prefix=url=credentials,...`
+pub fn proxy_arg(args: &[String]) -> ProxyRoutes {
+    ProxyRoutes(
+        crate::find_arg(args, "--proxy")
+            .map(|s| {
+                s.split(',')
+                    .filter_map(|rule| rule.trim().split_once('='))
+                    .map(|(prefix, url)| {
+                        let route = match url.strip_suffix("=credentials") {
+                            Some(url) => Route::Options { url: url.to_string(), credentials: true },
+                            None => Route::Url(url.to_string()),
+                        };
+                        (prefix.to_string(), route)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    )
+}
+
+impl ProxyRoutes {
+    /// The backend for `path` (with its query string), if a route covers
+    /// it
+    pub fn upstream(&self, path: &str) -> Option<Upstream> {
+        let route_path = path.split('?').next().unwrap_or(path);
+        let (prefix, route) = self
+            .0
+            .iter()
+            .map(|(prefix, route)| (prefix.trim_end_matches("/*"), route))
+            .filter(|(prefix, _)| under(route_path, prefix))
+            .max_by_key(|(prefix, _)| prefix.trim_end_matches('/').len())?;
+        let rest = &path[prefix.trim_end_matches('/').len()..];
+        let rest = if rest.is_empty() || rest.starts_with('?') { format!("/{}", rest) } else { rest.to_string() };
+        Some(Upstream {
+            url: format!("{}{}", route.url().trim_end_matches('/'), rest),
+            credentials: route.credentials(),
+        })
+    }
+}
+
+/// Send the request in `ctx` to `upstream` and stream the backend's
+/// response back. `tls`: whether the client connected over TLS.
+pub fn forward(
+    stream: &mut impl Write,
+    upstream: &Upstream,
+    ctx: &MagneticContext,
+    body: &[u8],
+    tls: bool,
+) -> io::Result<()> {
+    let (url, method, req_headers, extra_headers) = (&upstream.url, ctx.method.as_str(), &ctx.headers, &ctx.response_headers);
+    let mut req = agent().request(method, url);
+    for (name, value) in req_headers {
+        let name = name.as_str();
+        let credential = matches!(name, "cookie" | "authorization");
+        let skipped = HOP_BY_HOP.contains(&name)
+            || matches!(name, "host" | "content-length" | "accept-encoding" | "x-forwarded-for")
+            || (credential && !upstream.credentials);
+        if !skipped {
+            req = req.set(name, value);
+        }
+    }
+    if let Some(host) = req_headers.get("host") {
+        if !req_headers.contains_key("x-forwarded-host") {
+            req = req.set("X-Forwarded-Host", host);
+        }
+    }
+    let forwarded_for = match (req_headers.get("x-forwarded-for"), ctx.peer) {
+        (Some(chain), Some(peer)) => Some(format!("{}, {}", chain, peer)),
+        (Some(chain), None) => Some(chain.clone()),
+        (None, peer) => peer.map(|p| p.to_string()),
+    };
+    if let Some(forwarded_for) = forwarded_for {
+        req = req.set("X-Forwarded-For", &forwarded_for);
+    }
+    if !req_headers.contains_key("x-forwarded-proto") {
+        req = req.set("X-Forwarded-Proto", if tls { "https" } else { "http" });
+    }
+    let result = if body.is_empty() { req.call() } else { req.send_bytes(body) };
+    let resp = match result {
+        Ok(resp) | Err(ureq::Error::Status(_, resp)) => resp,
+        Err(e) => {
+            eprintln!("[magnetic] proxy {}: {}", url, e);
+            let msg = "Bad Gateway";
+            let resp = format!(
+                "HTTP/1.1 502 Bad Gateway\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n{}\r\n{}",
+                msg.len(), crate::format_extra_headers(extra_headers), msg
+            );
+            return stream.write_all(resp.as_bytes());
+        }
+    };
+
+    let status = resp.status();
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, resp.status_text());
+    for name in resp.headers_names() {
+        if HOP_BY_HOP.contains(&name.as_str()) || extra_headers.keys().any(|k| k.eq_ignore_ascii_case(&name)) {
+            continue;
+        }
+        for value in resp.all(&name) {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    head.push_str(&crate::format_extra_headers(extra_headers));
+    let no_body = method == "HEAD" || matches!(status, 100..=199 | 204 | 304);
+    // ureq drops Content-Length when it decompresses, so the body is chunked
+    let chunked = !no_body && resp.header("content-length").is_none();
+    if chunked {
+        head.push_str("Transfer-Encoding: chunked\r\n");
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    if no_body {
+        return Ok(());
+    }
+
+    let mut reader = resp.into_reader();
+    if !chunked {
+        io::copy(&mut reader, stream)?;
+        return Ok(());
+    }
+    let mut buf = [0u8; 16 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        write!(stream, "{:x}\r\n", n)?;
+        stream.write_all(&buf[..n])?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+    }
+    stream.write_all(b"0\r\n\r\n")
+}
+
+fn agent() -> &'static ureq::Agent {
+    static AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+    AGENT.get_or_init(|| ureq::AgentBuilder::new().redirects(0).timeout_connect(CONNECT_TIMEOUT).build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Forward a GET with `headers` to a one-shot backend, and return the
+    /// lowercased request headers it saw
+    fn seen_by_backend(credentials: bool, headers: &[(&str, &str)], tls: bool) -> HashMap<String, String> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let backend = thread::spawn(move || {
+            let (tcp, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(tcp.try_clone().unwrap());
+            let mut seen = HashMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    seen.insert(name.to_ascii_lowercase(), value.trim().to_string());
+                }
+            }
+            (&tcp).write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok").unwrap();
+            seen
+        });
+
+        let headers = headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let ctx = MagneticContext::from_request("GET", "/api/x", headers, Some("10.1.2.3".parse().unwrap()));
+        let upstream = Upstream { url: format!("http://127.0.0.1:{}/x", port), credentials };
+        let mut out = Vec::new();
+        forward(&mut out, &upstream, &ctx, b"", tls).unwrap();
+        assert!(String::from_utf8_lossy(&out).starts_with("HTTP/1.1 200 OK\r\n"));
+        backend.join().unwrap()
+    }
+
+    const CLIENT: [(&str, &str); 4] = [
+        ("host", "app.example.com"),
+        ("cookie", "magnetic_sid=s1; magnetic_session=abc; magnetic_csrf=t"),
+        ("authorization", "Bearer secret"),
+        ("x-app", "1"),
+    ];
+
+    #[test]
+    fn credentials_stay_behind_by_default() {
+        let seen = seen_by_backend(false, &CLIENT, false);
+        assert!(!seen.contains_key("cookie"));
+        assert!(!seen.contains_key("authorization"));
+        assert_eq!(seen["x-app"], "1");
+        assert_eq!(seen["x-forwarded-host"], "app.example.com");
+    }
+
+    #[test]
+    fn credentials_go_along_when_the_route_opts_in() {
+        let seen = seen_by_backend(true, &CLIENT, false);
+        assert_eq!(seen["cookie"], "magnetic_sid=s1; magnetic_session=abc; magnetic_csrf=t");
+        assert_eq!(seen["authorization"], "Bearer secret");
+    }
+
+    #[test]
+    fn forwarded_for_and_proto() {
+        let seen = seen_by_backend(false, &CLIENT, true);
+        assert_eq!(seen["x-forwarded-for"], "10.1.2.3");
+        assert_eq!(seen["x-forwarded-proto"], "https");
+
+        let seen = seen_by_backend(false, &[("x-forwarded-for", "203.0.113.9")], false);
+        assert_eq!(seen["x-forwarded-for"], "203.0.113.9, 10.1.2.3");
+        assert_eq!(seen["x-forwarded-proto"], "http");
+    }
+
+    #[test]
+    fn routes_from_args_and_config() {
+        let args: Vec<String> = ["--proxy", "/api/billing=http://b:9000,/api/me/*=http://m:9001/v1=credentials"]
+            .iter().map(|s| s.to_string()).collect();
+        let routes = proxy_arg(&args);
+        let billing = routes.upstream("/api/billing/invoices?page=2").unwrap();
+        assert_eq!((billing.url.as_str(), billing.credentials), ("http://b:9000/invoices?page=2", false));
+        let me = routes.upstream("/api/me").unwrap();
+        assert_eq!((me.url.as_str(), me.credentials), ("http://m:9001/v1/", true));
+        assert!(routes.upstream("/api/other").is_none());
+
+        let routes: ProxyRoutes = serde_json::from_value(serde_json::json!({
+            "/a": "http://a",
+            "/b": {"url": "http://b", "credentials": true},
+        }))
+        .unwrap();
+        assert!(!routes.upstream("/a/x").unwrap().credentials);
+        assert!(routes.upstream("/b/x").unwrap().credentials);
+    }
+}
@@ -0,0 +1,100 @@
+//! quotas.rs — Per-app resource limits on the platform
+//!
+//! So one app can't starve the others on a host, each app gets:
+//! - `heap_mb`: heap limit per isolate (default `--v8-heap-mb`); near it,
+//!   the isolate is replaced (isolates.rs);
+//! - `render_ms`: how long one render or action may run in V8
+//!   (`--render-timeout-ms`, default DEFAULT_RENDER_MS). Past it the script
+//!   is terminated and the caller gets the app's error page;
+//! - `sse_clients`: live SSE and WebSocket clients (`--max-sse-clients`).
+//!   Past it, new ones get a 503;
+//! - `bundle_mb`: the deployed bundle's size (`--max-bundle-mb`). A larger
+//!   deploy gets a 413.
+//!
+//! 0 turns a limit off; `sse_clients` and `bundle_mb` are off by default.
+//! `--app-quota shop.render_ms=10000,shop.heap_mb=512` overrides them for
+//! one app. An app's limits are in its /status.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::isolates::IsolateLimits;
+
+pub const DEFAULT_RENDER_MS: u64 = 5000;
+
+#[derive(Clone, Copy, Serialize)]
+pub struct Quota {
+    pub heap_mb: usize,
+    pub render_ms: u64,
+    pub sse_clients: usize,
+    pub bundle_mb: usize,
+}
+
+pub struct Quotas {
+    default: Quota,
+    apps: HashMap<String, Quota>,
+}
+
+impl Quotas {
+    /// The defaults from `--v8-heap-mb`, `--render-timeout-ms`,
+    /// `--max-sse-clients` and `--max-bundle-mb`, and the overrides from
+    /// `--app-quota`
+    pub fn from_args(args: &[String]) -> Self {
+        let num = |flag: &str, default: u64| {
+            crate::find_arg(args, flag).and_then(|s| s.parse().ok()).unwrap_or(default)
+        };
+        let default = Quota {
+            heap_mb: crate::isolates::v8_heap_arg(args),
+            render_ms: num("--render-timeout-ms", DEFAULT_RENDER_MS),
+            sse_clients: num("--max-sse-clients", 0) as usize,
+            bundle_mb: num("--max-bundle-mb", 0) as usize,
+        };
+        let mut apps: HashMap<String, Quota> = HashMap::new();
+        for entry in crate::find_arg(args, "--app-quota").iter().flat_map(|s| s.split(',')) {
+            let parsed = entry.split_once('=').and_then(|(key, value)| {
+                let (app, limit) = key.trim().rsplit_once('.')?;
+                Some((app, limit, value.trim().parse::<u64>().ok()?))
+            });
+            let Some((app, limit, value)) = parsed else {
+                eprintln!("[platform] --app-quota: ignoring '{}' (want app.limit=number)", entry);
+                continue;
+            };
+            let quota = apps.entry(app.to_string()).or_insert(default);
+            match limit {
+                "heap_mb" => quota.heap_mb = value as usize,
+                "render_ms" => quota.render_ms = value,
+                "sse_clients" => quota.sse_clients = value as usize,
+                "bundle_mb" => quota.bundle_mb = value as usize,
+                _ => eprintln!("[platform] --app-quota: unknown limit '{}'", limit),
+            }
+        }
+        Quotas { default, apps }
+    }
+
+    /// The limits for `app`
+    pub fn get(&self, app: &str) -> Quota {
+        self.apps.get(app).copied().unwrap_or(self.default)
+    }
+}
+
+impl Quota {
+    /// What each of the app's isolates may use
+    pub fn isolate_limits(&self) -> IsolateLimits {
+        IsolateLimits {
+            heap_mb: self.heap_mb,
+            run_time: (self.render_ms > 0).then(|| Duration::from_millis(self.render_ms)),
+        }
+    }
+
+    /// Whether a bundle of `len` bytes is over `bundle_mb`
+    pub fn bundle_too_large(&self, len: usize) -> bool {
+        self.bundle_mb > 0 && len > self.bundle_mb * 1024 * 1024
+    }
+
+    /// Whether an app with `clients` live clients can take no more
+    pub fn clients_full(&self, clients: usize) -> bool {
+        self.sse_clients > 0 && clients >= self.sse_clients
+    }
+}
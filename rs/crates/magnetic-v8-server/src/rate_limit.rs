@@ -0,0 +1,143 @@
+//! rate_limit.rs — Token-bucket rate limiting (the `rate-limit` middleware)
+//!
+//! Each client has a bucket of `--rate-limit N` tokens (requests per
+//! minute) that refills continuously at that rate. A request takes a token;
+//! one that finds the bucket empty gets a 429 with `Retry-After`. Bursts up
+//! to N are allowed after a quiet spell.
+//!
+//! `--rate-limit-route /actions=30,/auth/send=5` gives the paths under a
+//! prefix a bucket of their own per client, at that rate, in place of the
+//! global one. The longest matching prefix wins. In `--platform` mode a
+//! prefix also matches inside each app (`/apps/<name>/actions`), with a
+//! bucket per app.
+//!
+//! A client is the first `X-Forwarded-For` address, else `X-Real-IP`, else
+//! the connection's peer address. Every response that passed through the
+//! limiter carries `X-RateLimit-Limit`, `X-RateLimit-Remaining` and
+//! `X-RateLimit-Reset` (seconds until the bucket is full again).
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::static_routes::under;
+use crate::{MagneticContext, MiddlewareFn};
+
+/// Full buckets are dropped once there are this many
+const SWEEP_AT: usize = 10_000;
+
+pub struct Limiter {
+    /// Requests per minute in the global bucket
+    per_minute: u32,
+    /// Path prefix → requests per minute in its own bucket
+    routes: BTreeMap<String, u32>,
+    /// (scope, client) → bucket; the scope is "" for the global bucket
+    buckets: Mutex<HashMap<(String, String), Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    at: Instant,
+    /// Requests per minute: the capacity, refilled over a minute
+    limit: u32,
+}
+
+impl Bucket {
+    /// Tokens at `now`, counting the refill since the last request
+    fn tokens_at(&self, now: Instant) -> f64 {
+        let refill = now.duration_since(self.at).as_secs_f64() * f64::from(self.limit) / 60.0;
+        (self.tokens + refill).min(f64::from(self.limit))
+    }
+}
+
+/// Per-route rates from `--rate-limit-route /prefix=N,...`
+pub fn routes_arg(args: &[String]) -> BTreeMap<String, u32> {
+    crate::find_arg(args, "--rate-limit-route")
+        .map(|s| {
+            s.split(',')
+                .filter_map(|rule| rule.trim().split_once('='))
+                .filter_map(|(prefix, n)| Some((prefix.to_string(), n.trim().parse().ok()?)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl Limiter {
+    pub fn new(per_minute: u32, routes: BTreeMap<String, u32>) -> Self {
+        Limiter { per_minute, routes, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn middleware(self) -> MiddlewareFn {
+        Box::new(move |ctx: &mut MagneticContext| self.check(ctx))
+    }
+
+    fn check(&self, ctx: &mut MagneticContext) {
+        let (scope, limit) = self.scope(&ctx.path);
+        let limit = limit.max(1);
+        let rate = f64::from(limit) / 60.0;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= SWEEP_AT {
+            // A bucket that would be full by now is the same as none
+            buckets.retain(|_, b| b.tokens_at(now) < f64::from(b.limit));
+        }
+        let bucket = buckets
+            .entry((scope, client(ctx)))
+            .or_insert(Bucket { tokens: f64::from(limit), at: now, limit });
+        bucket.tokens = bucket.tokens_at(now);
+        bucket.at = now;
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        let tokens = bucket.tokens;
+        drop(buckets);
+
+        let reset = ((f64::from(limit) - tokens) / rate).ceil() as u64;
+        ctx.response_headers.insert("X-RateLimit-Limit".into(), limit.to_string());
+        ctx.response_headers.insert("X-RateLimit-Remaining".into(), (tokens.floor() as u64).to_string());
+        ctx.response_headers.insert("X-RateLimit-Reset".into(), reset.to_string());
+        if !allowed {
+            let retry_after = ((1.0 - tokens) / rate).ceil().max(1.0) as u64;
+            ctx.response_headers.insert("Retry-After".into(), retry_after.to_string());
+            ctx.status = 429;
+            ctx.body = Some("{\"error\":\"Too many requests\"}".into());
+        }
+    }
+
+    /// The bucket scope and rate for `path`: the longest route prefix
+    /// covering it (within its app, in platform mode), else the global one
+    fn scope(&self, path: &str) -> (String, u32) {
+        let (app, rest) = match path.strip_prefix("/apps/").and_then(|r| r.find('/').map(|i| r.split_at(i))) {
+            Some((name, rest)) => (format!("/apps/{}", name), rest),
+            None => (String::new(), path),
+        };
+        let mut best: Option<(String, u32)> = None;
+        for (prefix, &n) in &self.routes {
+            let prefix = prefix.trim_end_matches('/');
+            let scope = if under(path, prefix) {
+                prefix.to_string()
+            } else if under(rest, prefix) {
+                format!("{}{}", app, prefix)
+            } else {
+                continue;
+            };
+            if best.as_ref().is_none_or(|(b, _)| scope.len() > b.len()) {
+                best = Some((scope, n));
+            }
+        }
+        best.unwrap_or_else(|| (String::new(), self.per_minute))
+    }
+}
+
+/// The client's address, as described above
+pub fn client(ctx: &MagneticContext) -> String {
+    ctx.headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.split(',').next())
+        .or_else(|| ctx.headers.get("x-real-ip").map(String::as_str))
+        .map(|ip| ip.trim().to_string())
+        .or_else(|| ctx.peer.map(|ip| ip.to_string()))
+        .unwrap_or_else(|| "unknown".into())
+}
@@ -0,0 +1,285 @@
+//! redirects.rs — Redirect and rewrite rules, applied before routing
+//!
+//! - Redirects answer with a `Location` (301 unless the rule says otherwise:
+//!   302, 307 or 308). `--redirect /old=/new,/promo=https://example.com/sale=302`
+//! - Rewrites serve another path without telling the client.
+//!   `--rewrite /go/*=/landing/:splat`
+//! - `--trailing-slash add|remove` redirects every page path (not files, so
+//!   not `/app.js`) to one form with a 301.
+//!
+//! A pattern's `:name` segment matches any one segment, and a final `*`
+//! matches the rest of the path. Both can be used in the target, the rest as
+//! `:splat`. Rules are tried in order, the first match wins, and the query
+//! string carries over unless the target has its own. The trailing slash is
+//! normalized first, then redirects are tried, then rewrites.
+//!
+//! In `--platform` mode each app sets the same rules in its magnetic.json
+//! config: `"redirects": [{"from": "...", "to": "...", "status": 302}]`,
+//! `"rewrites": [{"from": "...", "to": "..."}]` and `"trailing_slash"`.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Rules {
+    #[serde(default)]
+    pub redirects: Vec<Rule>,
+    #[serde(default)]
+    pub rewrites: Vec<Rule>,
+    #[serde(default)]
+    pub trailing_slash: Option<TrailingSlash>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Rule {
+    pub from: String,
+    pub to: String,
+    /// Redirects only
+    #[serde(default = "default_status")]
+    pub status: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingSlash {
+    Add,
+    Remove,
+}
+
+fn default_status() -> u16 {
+    301
+}
+
+/// What to do with a request path
+pub enum Outcome {
+    /// Send the client to this URL with this status
+    Redirect(u16, String),
+    /// Route this path instead
+    Rewrite(String),
+    /// Route the path as it is
+    Pass,
+}
+
+/// Rules from `--redirect`, `--rewrite` and `--trailing-slash`
+pub fn rules_arg(args: &[String]) -> Rules {
+    let list = |flag: &str| -> Vec<Rule> {
+        crate::find_arg(args, flag)
+            .map(|s| s.split(',').map(str::trim).filter_map(parse_rule).collect())
+            .unwrap_or_default()
+    };
+    let trailing_slash = match crate::find_arg(args, "--trailing-slash").as_deref() {
+        Some("add") => Some(TrailingSlash::Add),
+        Some("remove") => Some(TrailingSlash::Remove),
+        Some(other) => {
+            eprintln!("[magnetic] --trailing-slash {}: expected add or remove", other);
+            None
+        }
+        None => None,
+    };
+    Rules { redirects: list("--redirect"), rewrites: list("--rewrite"), trailing_slash }
+}
+
+/// `from=to` or `from=to=status`
+fn parse_rule(rule: &str) -> Option<Rule> {
+    let (from, to) = rule.split_once('=')?;
+    let (to, status) = match to.rsplit_once('=').and_then(|(to, s)| Some((to, s.parse::<u16>().ok()?))) {
+        Some((to, status)) => (to, status),
+        None => (to, default_status()),
+    };
+    Some(Rule { from: from.to_string(), to: to.to_string(), status })
+}
+
+impl Rules {
+    /// Apply the rules to `path` (with its query string, if any)
+    pub fn apply(&self, path: &str) -> Outcome {
+        let (route, query) = match path.split_once('?') {
+            Some((route, query)) => (route, Some(query)),
+            None => (path, None),
+        };
+        if let Some(normal) = self.normalize(route) {
+            return Outcome::Redirect(301, with_query(normal, query));
+        }
+        if let Some((rule, to)) = first_match(&self.redirects, route) {
+            let status = if matches!(rule.status, 301 | 302 | 307 | 308) { rule.status } else { default_status() };
+            return Outcome::Redirect(status, with_query(to, query));
+        }
+        match first_match(&self.rewrites, route) {
+            Some((_, to)) => Outcome::Rewrite(with_query(to, query)),
+            None => Outcome::Pass,
+        }
+    }
+
+    /// `route` in the configured trailing-slash form, if it isn't already.
+    /// Leading slashes (and backslashes, which browsers read as slashes)
+    /// become one, so `//evil.com/x` can't leave as a protocol-relative
+    /// `Location` to another host.
+    fn normalize(&self, route: &str) -> Option<String> {
+        let last = route.rsplit('/').next().unwrap_or("");
+        if route == "/" || last.contains('.') {
+            return None;
+        }
+        let local = format!("/{}", route.trim_start_matches(['/', '\\']));
+        let normal = match self.trailing_slash? {
+            TrailingSlash::Add if !local.ends_with('/') => format!("{}/", local),
+            TrailingSlash::Add => local,
+            TrailingSlash::Remove => match local.trim_end_matches('/') {
+                "" => "/".to_string(),
+                trimmed => trimmed.to_string(),
+            },
+        };
+        (normal != route).then_some(normal)
+    }
+}
+
+/// Send a redirect to `location`
+pub fn write_redirect(
+    stream: &mut impl Write,
+    status: u16,
+    location: &str,
+    extra_headers: &HashMap<String, String>,
+) -> io::Result<()> {
+    let resp = format!(
+        "HTTP/1.1 {} {}\r\nLocation: {}\r\nContent-Length: 0\r\n{}\r\n",
+        status, crate::status_text(status), location, crate::format_extra_headers(extra_headers)
+    );
+    stream.write_all(resp.as_bytes())
+}
+
+/// The first rule matching `route`, with its target filled in
+fn first_match<'a>(rules: &'a [Rule], route: &str) -> Option<(&'a Rule, String)> {
+    rules.iter().find_map(|rule| {
+        let params = capture(&rule.from, route)?;
+        Some((rule, fill(&rule.to, &params)))
+    })
+}
+
+/// Whether `pattern` (`:name` segments, final `*`) matches `route`
+pub fn matches(pattern: &str, route: &str) -> bool {
+    capture(pattern, route).is_some()
+}
+
+/// The `:name` and `*` (as "splat") parts of `route` that `pattern` matches
+fn capture(pattern: &str, route: &str) -> Option<HashMap<String, String>> {
+    let mut params = HashMap::new();
+    let mut segments = route.split('/');
+    let mut parts = pattern.split('/').peekable();
+    while let Some(part) = parts.next() {
+        if part == "*" && parts.peek().is_none() {
+            params.insert("splat".to_string(), segments.collect::<Vec<_>>().join("/"));
+            return Some(params);
+        }
+        let segment = segments.next()?;
+        match part.strip_prefix(':') {
+            Some(name) if !segment.is_empty() => {
+                params.insert(name.to_string(), segment.to_string());
+            }
+            _ if part == segment => {}
+            _ => return None,
+        }
+    }
+    segments.next().is_none().then_some(params)
+}
+
+/// `to` with each `:name` segment replaced by its captured value
+fn fill(to: &str, params: &HashMap<String, String>) -> String {
+    to.split('/')
+        .map(|part| {
+            part.strip_prefix(':')
+                .and_then(|name| params.get(name))
+                .map(String::as_str)
+                .unwrap_or(part)
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn with_query(to: String, query: Option<&str>) -> String {
+    match query {
+        Some(q) if !to.contains('?') => format!("{}?{}", to, q),
+        _ => to,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(trailing_slash: Option<TrailingSlash>) -> Rules {
+        let rule = |from: &str, to: &str, status| Rule { from: from.to_string(), to: to.to_string(), status };
+        Rules {
+            redirects: vec![rule("/old", "/new", 301), rule("/promo", "https://example.com/sale", 302)],
+            rewrites: vec![rule("/go/*", "/landing/:splat", 301), rule("/u/:id", "/users/:id", 301)],
+            trailing_slash,
+        }
+    }
+
+    /// The `Location` `path` is redirected to, if any
+    fn location(rules: &Rules, path: &str) -> Option<(u16, String)> {
+        match rules.apply(path) {
+            Outcome::Redirect(status, to) => Some((status, to)),
+            _ => None,
+        }
+    }
+
+    fn rewrite(rules: &Rules, path: &str) -> Option<String> {
+        match rules.apply(path) {
+            Outcome::Rewrite(to) => Some(to),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn redirects_keep_the_query() {
+        let rules = rules(None);
+        assert_eq!(location(&rules, "/old?a=1"), Some((301, "/new?a=1".to_string())));
+        assert_eq!(location(&rules, "/promo"), Some((302, "https://example.com/sale".to_string())));
+        assert_eq!(location(&rules, "/other"), None);
+    }
+
+    #[test]
+    fn rewrites_fill_params_and_splat() {
+        let rules = rules(None);
+        assert_eq!(rewrite(&rules, "/go/a/b"), Some("/landing/a/b".to_string()));
+        assert_eq!(rewrite(&rules, "/u/7?x=1"), Some("/users/7?x=1".to_string()));
+        assert_eq!(rewrite(&rules, "/u/"), None);
+    }
+
+    #[test]
+    fn trailing_slash_add_and_remove() {
+        let add = rules(Some(TrailingSlash::Add));
+        assert_eq!(location(&add, "/about?x=1"), Some((301, "/about/?x=1".to_string())));
+        assert_eq!(location(&add, "/about/"), None);
+        assert_eq!(location(&add, "/app.js"), None);
+        assert_eq!(location(&add, "/"), None);
+
+        let remove = rules(Some(TrailingSlash::Remove));
+        assert_eq!(location(&remove, "/about/"), Some((301, "/about".to_string())));
+        assert_eq!(location(&remove, "/about"), None);
+        assert_eq!(location(&remove, "/"), None);
+    }
+
+    #[test]
+    fn trailing_slash_stays_on_this_host() {
+        let add = rules(Some(TrailingSlash::Add));
+        assert_eq!(location(&add, "//evil.com/x"), Some((301, "/evil.com/x/".to_string())));
+        assert_eq!(location(&add, "/\\evil.com/x"), Some((301, "/evil.com/x/".to_string())));
+        assert_eq!(location(&add, "//evil.com/x/"), Some((301, "/evil.com/x/".to_string())));
+
+        let remove = rules(Some(TrailingSlash::Remove));
+        assert_eq!(location(&remove, "//evil.com/"), Some((301, "/evil.com".to_string())));
+        assert_eq!(location(&remove, "///evil.com/x/"), Some((301, "/evil.com/x".to_string())));
+        assert_eq!(location(&remove, "//"), Some((301, "/".to_string())));
+    }
+
+    #[test]
+    fn rules_from_args() {
+        let args: Vec<String> = ["--redirect", "/a=/b, /c=/d=307", "--trailing-slash", "remove"]
+            .iter().map(|s| s.to_string()).collect();
+        let rules = rules_arg(&args);
+        assert_eq!(rules.redirects.len(), 2);
+        assert_eq!(rules.redirects[1].status, 307);
+        assert_eq!(rules.trailing_slash, Some(TrailingSlash::Remove));
+    }
+}
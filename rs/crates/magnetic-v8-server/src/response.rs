@@ -0,0 +1,138 @@
+//! response.rs — The response phase of the middleware chain
+//!
+//! Handlers write their response to the connection as before. `respond`
+//! captures those bytes (`Conn::capture`) instead of sending them and parses
+//! them into a `Response`. It then runs the middleware's response hooks,
+//! compresses the body (compress.rs), sets `Content-Length` to the final
+//! body, and sends the result. Each hook sees the real status, headers and
+//! uncompressed body, so security headers, status logging and body rewrites
+//! live in middleware rather than in every handler.
+//!
+//! SSE streams write through their own handle and WebSocket upgrades are not
+//! captured, so the response phase doesn't see them.
+
+use std::io::{self, Write};
+
+use crate::conn::Conn;
+use crate::{compress, ws, MagneticContext, MiddlewareStack};
+
+pub struct Response {
+    pub status: u16,
+    pub reason: String,
+    /// In the order the handler wrote them, names as written
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Parse a complete HTTP/1.1 response. None if `raw` isn't one.
+    pub fn parse(raw: &[u8]) -> Option<Response> {
+        let head_len = raw.windows(4).position(|w| w == b"\r\n\r\n")?;
+        let head = std::str::from_utf8(&raw[..head_len]).ok()?;
+        let mut lines = head.split("\r\n");
+        let status_line = lines.next()?.strip_prefix("HTTP/1.1 ")?;
+        let (code, reason) = status_line.split_once(' ').unwrap_or((status_line, ""));
+        let headers = lines
+            .filter_map(|l| l.split_once(':'))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+        Some(Response {
+            status: code.parse().ok()?,
+            reason: reason.to_string(),
+            headers,
+            body: raw[head_len + 4..].to_vec(),
+        })
+    }
+
+    /// First value of a header (case-insensitive name)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    /// Replace every value of a header with `value`
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.remove_header(name);
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    /// Add a header unless the response already has one by that name
+    pub fn default_header(&mut self, name: &str, value: &str) {
+        if self.header(name).is_none() {
+            self.headers.push((name.to_string(), value.to_string()));
+        }
+    }
+
+    pub fn remove_header(&mut self, name: &str) {
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+    }
+
+    /// Send the response. `Content-Length` is set from the body, except on
+    /// statuses that have none. A HEAD response keeps the length but not
+    /// the body.
+    pub fn write_to(&mut self, stream: &mut impl Write, head: bool) -> io::Result<()> {
+        if !matches!(self.status, 100..=199 | 204 | 304) {
+            self.set_header("Content-Length", &self.body.len().to_string());
+        }
+        let mut out = format!("HTTP/1.1 {} {}\r\n", self.status, self.reason);
+        for (k, v) in &self.headers {
+            out.push_str(&format!("{}: {}\r\n", k, v));
+        }
+        out.push_str("\r\n");
+        stream.write_all(out.as_bytes())?;
+        if head {
+            return Ok(());
+        }
+        stream.write_all(&self.body)
+    }
+}
+
+/// Run `route` outside the middleware: no response hooks or compression,
+/// but a HEAD request still gets only the head
+pub fn bare<F>(stream: &mut Conn, head: bool, route: F) -> io::Result<()>
+where
+    F: FnOnce(&mut Conn) -> io::Result<()>,
+{
+    if !head {
+        return route(stream);
+    }
+    stream.capture();
+    let result = route(stream);
+    let raw = stream.take_capture().unwrap_or_default();
+    match Response::parse(&raw) {
+        Some(mut resp) => resp.write_to(stream, true)?,
+        None => stream.write_all(&raw)?,
+    }
+    result
+}
+
+/// Run `route`, then the response phase on what it wrote
+pub fn respond<F>(
+    stream: &mut Conn,
+    middleware: &MiddlewareStack,
+    ctx: &MagneticContext,
+    route: F,
+) -> io::Result<()>
+where
+    F: FnOnce(&mut Conn) -> io::Result<()>,
+{
+    if ws::is_upgrade(&ctx.headers) {
+        return route(stream);
+    }
+    stream.capture();
+    let result = route(stream);
+    let raw = stream.take_capture().unwrap_or_default();
+    match Response::parse(&raw) {
+        Some(mut resp) => {
+            crate::trace::attr("http.response.status_code", resp.status);
+            if resp.status >= 500 {
+                crate::trace::error(&resp.reason);
+            }
+            middleware.run_after(ctx, &mut resp);
+            compress::encode_response(&mut resp, &ctx.headers);
+            resp.write_to(stream, ctx.method == "HEAD")?;
+        }
+        // Nothing written here (an SSE stream), or not a response we know
+        None => stream.write_all(&raw)?,
+    }
+    result
+}
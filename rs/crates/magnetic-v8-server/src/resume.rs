@@ -0,0 +1,103 @@
+//! resume.rs — SSE event ids and Last-Event-ID replay
+//!
+//! Each snapshot broadcast to a session gets the next id in that session's
+//! log, which keeps the last HISTORY_LEN of them. SSE sends the id with the
+//! event, and a browser whose EventSource reconnects sends the last one
+//! back as `Last-Event-ID`. The server then replays the events the client
+//! missed instead of rendering a fresh snapshot. If the log doesn't reach
+//! back that far, or is gone (server restart, session dropped), the client
+//! gets a fresh snapshot as before.
+//!
+//! A log starts when an SSE client of the session connects and outlives
+//! the session's last client by one to two keepalive intervals, long
+//! enough for an EventSource to reconnect.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Events kept per session
+const HISTORY_LEN: usize = 16;
+
+pub struct History {
+    logs: Mutex<HashMap<String, EventLog>>,
+}
+
+struct EventLog {
+    last_id: u64,
+    /// (id, snapshot), oldest first
+    events: VecDeque<(u64, Arc<str>)>,
+    /// No clients at the last sweep
+    stale: bool,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History { logs: Mutex::new(HashMap::new()) }
+    }
+
+    /// Id of the session's latest event, starting its log if needed. A new
+    /// client's initial snapshot is sent with this id.
+    pub fn open(&self, session_id: &str) -> u64 {
+        let mut logs = self.logs.lock().unwrap();
+        let log = logs.entry(session_id.to_string()).or_insert_with(|| EventLog {
+            last_id: 0,
+            events: VecDeque::new(),
+            stale: false,
+        });
+        log.stale = false;
+        log.last_id
+    }
+
+    /// Record a snapshot broadcast to the session and return its id. None
+    /// if the session has no log (no SSE client has connected).
+    pub fn record(&self, session_id: &str, snapshot: &str) -> Option<u64> {
+        let mut logs = self.logs.lock().unwrap();
+        let log = logs.get_mut(session_id)?;
+        log.last_id += 1;
+        if log.events.len() == HISTORY_LEN {
+            log.events.pop_front();
+        }
+        log.events.push_back((log.last_id, Arc::from(snapshot)));
+        Some(log.last_id)
+    }
+
+    /// Events sent after the request's `Last-Event-ID`, oldest first (empty
+    /// if it missed none). None without the header, or if the log can't
+    /// fill the gap.
+    pub fn missed(
+        &self,
+        session_id: &str,
+        req_headers: &HashMap<String, String>,
+    ) -> Option<Vec<(u64, Arc<str>)>> {
+        let last_seen: u64 = req_headers.get("last-event-id")?.trim().parse().ok()?;
+        let mut logs = self.logs.lock().unwrap();
+        let log = logs.get_mut(session_id)?;
+        if last_seen > log.last_id {
+            return None;
+        }
+        let oldest = log.events.front().map_or(log.last_id + 1, |(id, _)| *id);
+        if last_seen + 1 < oldest {
+            return None;
+        }
+        log.stale = false;
+        Some(log.events.iter().filter(|(id, _)| *id > last_seen).cloned().collect())
+    }
+
+    /// Forget the session (its V8 state is gone, so old events are too)
+    pub fn remove(&self, session_id: &str) {
+        self.logs.lock().unwrap().remove(session_id);
+    }
+
+    /// Run every keepalive interval: drop logs of sessions that have had no
+    /// clients at two sweeps in a row.
+    pub fn sweep(&self, has_clients: impl Fn(&str) -> bool) {
+        self.logs.lock().unwrap().retain(|session_id, log| {
+            if has_clients(session_id) {
+                log.stale = false;
+                true
+            } else {
+                !std::mem::replace(&mut log.stale, true)
+            }
+        });
+    }
+}
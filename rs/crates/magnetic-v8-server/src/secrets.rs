@@ -0,0 +1,166 @@
+//! secrets.rs — Per-app secrets for `${secrets.NAME}`
+//!
+//! An app's secrets are kept in `<data_dir>/<name>/secrets.enc`: a JSON
+//! object of name → value, encrypted and authenticated with AES-256-GCM
+//! under a key derived from `--secrets-key` (or MAGNETIC_SECRETS_KEY).
+//! They're managed through the platform API, never deployed with the app:
+//! - `GET /api/apps/<name>/secrets`: the names, never the values;
+//! - `PUT /api/apps/<name>/secrets/<NAME>`: set one, the body is its value;
+//! - `DELETE /api/apps/<name>/secrets/<NAME>`.
+//!
+//! When an app loads, `${secrets.NAME}` in any string of its config.json is
+//! replaced with the value: data source urls and params, webhook secrets,
+//! action urls, auth client secrets. Every value filled in is remembered,
+//! and `mask` turns them into `***`. The data layer masks its log lines and
+//! the errors it keeps for `__sources` with it.
+//!
+//! Without a key, the API refuses, and `${secrets.NAME}` becomes "".
+
+use std::collections::BTreeMap;
+use std::sync::{OnceLock, RwLock};
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::Value;
+
+/// Shorter values aren't masked: they'd turn up in unrelated text.
+const MIN_MASKED_LEN: usize = 4;
+
+static KEY: OnceLock<Option<LessSafeKey>> = OnceLock::new();
+/// Values filled in so far, longest first
+static KNOWN: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Derive the secrets key from `--secrets-key`. Call once at startup.
+pub fn install(args: &[String]) {
+    let key = crate::find_arg(args, "--secrets-key")
+        .or_else(|| std::env::var("MAGNETIC_SECRETS_KEY").ok())
+        .filter(|s| !s.is_empty())
+        .map(|secret| {
+            let key = UnboundKey::new(&AES_256_GCM, &crate::cookie::derive(secret.as_bytes(), b"magnetic-secrets"))
+                .expect("32-byte key");
+            LessSafeKey::new(key)
+        });
+    if key.is_none() {
+        eprintln!("[platform] no --secrets-key: app secrets are off");
+    }
+    let _ = KEY.set(key);
+}
+
+/// Whether `name` is a usable secret name: letters, digits and `_`
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+/// The app's secrets (none if it has no secrets file)
+pub fn load(app_dir: &str) -> Result<BTreeMap<String, String>, String> {
+    let bytes = match std::fs::read(path(app_dir)) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(format!("read secrets: {}", e)),
+    };
+    if bytes.len() < NONCE_LEN {
+        return Err("secrets file is truncated".into());
+    }
+    let (nonce, cipher) = bytes.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| "bad secrets nonce")?;
+    let mut buf = cipher.to_vec();
+    let plain = key()?
+        .open_in_place(nonce, Aad::empty(), &mut buf)
+        .map_err(|_| "cannot decrypt secrets (wrong --secrets-key?)")?;
+    serde_json::from_slice(plain).map_err(|e| format!("parse secrets: {}", e))
+}
+
+/// Encrypt `secrets` into the app's secrets file
+pub fn save(app_dir: &str, secrets: &BTreeMap<String, String>) -> Result<(), String> {
+    let key = key()?;
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).map_err(|_| "system RNG failed")?;
+    let mut buf = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut buf)
+        .map_err(|_| "encrypt secrets failed")?;
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&buf);
+    std::fs::create_dir_all(app_dir).map_err(|e| format!("create app dir: {}", e))?;
+    // Written aside and renamed, so a crash can't leave half a file
+    let tmp = format!("{}.tmp", path(app_dir));
+    std::fs::write(&tmp, out).map_err(|e| format!("write secrets: {}", e))?;
+    std::fs::rename(&tmp, path(app_dir)).map_err(|e| format!("write secrets: {}", e))
+}
+
+/// `json` (an app's config.json) with its `${secrets.NAME}`s filled in
+/// from the app's secrets. Problems are logged; what can't be filled in
+/// becomes "".
+pub fn fill(app: &str, app_dir: &str, json: &str) -> String {
+    if !json.contains("${secrets.") {
+        return json.to_string();
+    }
+    let secrets = load(app_dir).unwrap_or_else(|e| {
+        eprintln!("[platform:{}] secrets: {}", app, e);
+        BTreeMap::new()
+    });
+    let Ok(mut config) = serde_json::from_str::<Value>(json) else {
+        // parse_config reports it
+        return json.to_string();
+    };
+    let mut used = Vec::new();
+    fill_value(&mut config, &secrets, &mut |name| match secrets.get(name) {
+        Some(value) => used.push(value.clone()),
+        None => eprintln!("[platform:{}] unknown secret '{}'", app, name),
+    });
+    remember(used);
+    config.to_string()
+}
+
+fn fill_value(value: &mut Value, secrets: &BTreeMap<String, String>, seen: &mut dyn FnMut(&str)) {
+    match value {
+        Value::String(s) if s.contains("${secrets.") => {
+            let mut out = String::new();
+            let mut rest = s.as_str();
+            while let Some(start) = rest.find("${secrets.") {
+                let Some(end) = rest[start..].find('}') else { break };
+                let name = &rest[start + 10..start + end];
+                seen(name);
+                out.push_str(&rest[..start]);
+                out.push_str(secrets.get(name).map_or("", String::as_str));
+                rest = &rest[start + end + 1..];
+            }
+            out.push_str(rest);
+            *s = out;
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| fill_value(v, secrets, seen)),
+        Value::Object(obj) => obj.values_mut().for_each(|v| fill_value(v, secrets, seen)),
+        _ => {}
+    }
+}
+
+fn remember(values: Vec<String>) {
+    let mut known = KNOWN.write().unwrap();
+    for value in values {
+        if value.len() >= MIN_MASKED_LEN && !known.contains(&value) {
+            known.push(value);
+        }
+    }
+    known.sort_by_key(|v| std::cmp::Reverse(v.len()));
+}
+
+/// `text` with every secret value filled in so far replaced by `***`
+pub fn mask(text: &str) -> String {
+    let known = KNOWN.read().unwrap();
+    let mut out = text.to_string();
+    for value in known.iter() {
+        if out.contains(value.as_str()) {
+            out = out.replace(value.as_str(), "***");
+        }
+    }
+    out
+}
+
+fn key() -> Result<&'static LessSafeKey, String> {
+    KEY.get()
+        .and_then(Option::as_ref)
+        .ok_or_else(|| "no secrets key (--secrets-key or MAGNETIC_SECRETS_KEY)".into())
+}
+
+fn path(app_dir: &str) -> String {
+    format!("{}/secrets.enc", app_dir)
+}
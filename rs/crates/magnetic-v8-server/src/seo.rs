@@ -0,0 +1,165 @@
+//! seo.rs — Generated robots.txt and sitemap.xml
+//!
+//! `/robots.txt` and `/sitemap.xml` are generated unless the static dir has
+//! a file of that name, which is served as before.
+//!
+//! - sitemap.xml lists the app's pages: `--sitemap-routes /,/about` if
+//!   given, else what the bundle's `routes()` export returns. Patterns
+//!   (`/blog/:slug`, `*`) are left out, since they can't be listed.
+//! - robots.txt allows everything except `--robots-disallow /admin,/api`
+//!   and points to the sitemap.
+//!
+//! URLs are absolute, under `--site-url https://example.com` or, without
+//! it, the request's `Host` (and `X-Forwarded-Proto`).
+//!
+//! In `--platform` mode each app sets the same under `seo` in its
+//! magnetic.json config: `{"site_url": "...", "routes": [...], "disallow": [...]}`.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::{self, Validators};
+use crate::conn::Conn;
+use crate::isolates::V8Pool;
+use crate::{Reply, V8Request, V8Result};
+
+pub const ROBOTS: &str = "/robots.txt";
+pub const SITEMAP: &str = "/sitemap.xml";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Seo {
+    /// Origin of the sitemap's URLs; the request's Host when None
+    #[serde(default)]
+    pub site_url: Option<String>,
+    /// Pages for the sitemap, in place of the bundle's routes()
+    #[serde(default)]
+    pub routes: Vec<String>,
+    /// Path prefixes robots.txt disallows
+    #[serde(default)]
+    pub disallow: Vec<String>,
+}
+
+/// Settings from `--site-url`, `--sitemap-routes` and `--robots-disallow`
+pub fn seo_arg(args: &[String]) -> Seo {
+    let list = |flag: &str| -> Vec<String> {
+        crate::find_arg(args, flag)
+            .map(|s| s.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect())
+            .unwrap_or_default()
+    };
+    Seo {
+        site_url: crate::find_arg(args, "--site-url"),
+        routes: list("--sitemap-routes"),
+        disallow: list("--robots-disallow"),
+    }
+}
+
+/// Whether `path` is a file this module generates, and `static_dir`
+/// doesn't have one of its own
+pub fn generates(static_dir: &str, path: &str) -> bool {
+    let route = path.split('?').next().unwrap_or(path);
+    (route == ROBOTS || route == SITEMAP) && !Path::new(static_dir).join(&route[1..]).is_file()
+}
+
+impl Seo {
+    /// Send the robots.txt or sitemap.xml for `path`. `pool` is None for
+    /// apps without a bundle; `prefix` goes before every app path.
+    pub fn write(
+        &self,
+        stream: &mut Conn,
+        path: &str,
+        pool: Option<&V8Pool>,
+        prefix: &str,
+        req_headers: &HashMap<String, String>,
+        extra_headers: &HashMap<String, String>,
+    ) -> io::Result<()> {
+        let origin = self.origin(req_headers);
+        let (body, ct) = if path.starts_with(SITEMAP) {
+            (self.sitemap(&format!("{}{}", origin, prefix), pool), "application/xml; charset=utf-8")
+        } else {
+            (self.robots(&origin, prefix), "text/plain; charset=utf-8")
+        };
+        let eh = crate::format_extra_headers(extra_headers);
+        let headers = format!("Cache-Control: public, max-age=300, must-revalidate\r\n{}", eh);
+        let v = Validators::for_body(body.as_bytes());
+        if v.not_modified(req_headers) {
+            return cache::write_not_modified(stream, &v, ct, &headers);
+        }
+        let resp = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}{}\r\n",
+            ct, body.len(), v.headers(), headers
+        );
+        stream.write_all(resp.as_bytes())?;
+        stream.write_all(body.as_bytes())
+    }
+
+    fn robots(&self, origin: &str, prefix: &str) -> String {
+        let mut out = String::from("User-agent: *\n");
+        if self.disallow.is_empty() {
+            out.push_str("Allow: /\n");
+        }
+        for path in &self.disallow {
+            out.push_str(&format!("Disallow: {}{}\n", prefix, path));
+        }
+        out.push_str(&format!("\nSitemap: {}{}{}\n", origin, prefix, SITEMAP));
+        out
+    }
+
+    fn sitemap(&self, base: &str, pool: Option<&V8Pool>) -> String {
+        let routes = if self.routes.is_empty() { bundle_routes(pool) } else { self.routes.clone() };
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+        for route in routes.iter().filter(|r| is_page(r)) {
+            out.push_str(&format!("  <url><loc>{}</loc></url>\n", escape_xml(&format!("{}{}", base, route))));
+        }
+        out.push_str("</urlset>\n");
+        out
+    }
+
+    /// `https://example.com`, without a trailing slash
+    fn origin(&self, req_headers: &HashMap<String, String>) -> String {
+        if let Some(url) = &self.site_url {
+            return url.trim_end_matches('/').to_string();
+        }
+        let proto = req_headers.get("x-forwarded-proto").map(String::as_str).unwrap_or("http");
+        let host = req_headers.get("host").map(String::as_str).unwrap_or("localhost");
+        format!("{}://{}", proto, host)
+    }
+}
+
+/// Whether a routes() entry is a page that can be listed, not a pattern
+/// like `/blog/:slug` or `*`
+pub fn is_page(route: &str) -> bool {
+    route.starts_with('/') && !route.contains([':', '*'])
+}
+
+/// The bundle's routes() list; empty without the export
+fn bundle_routes(pool: Option<&V8Pool>) -> Vec<String> {
+    let Some(pool) = pool else {
+        return Vec::new();
+    };
+    let reply = Reply::new();
+    if pool.send(V8Request::Routes { reply: reply.clone() }).is_err() {
+        return Vec::new();
+    }
+    match reply.recv() {
+        V8Result::Ok(json) => serde_json::from_str::<Option<Vec<String>>>(&json)
+            .unwrap_or_else(|e| {
+                eprintln!("[magnetic] routes() parse error: {}", e);
+                None
+            })
+            .unwrap_or_default(),
+        V8Result::Err(e) => {
+            eprintln!("[magnetic] routes() error: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
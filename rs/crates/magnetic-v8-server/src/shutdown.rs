@@ -0,0 +1,102 @@
+//! shutdown.rs — Graceful shutdown on SIGTERM/SIGINT
+//!
+//! The first signal sets `is_stopping` and wakes the accept loop, by
+//! shutting the listening sockets and connecting to them (cluster.rs). The
+//! loop then ends and the server drains. New
+//! connections are refused. Requests already being handled, and actions
+//! arriving over open WebSockets, run to completion, and their snapshots
+//! are broadcast. Then every SSE client gets a final `event: reconnect` and
+//! every WebSocket a 1012 (service restart) close, and the process exits.
+//!
+//! All of that must fit in `--shutdown-timeout` seconds (default 10). Past
+//! that deadline, or on a second signal, the process exits at once. Session
+//! state lives in the V8 isolates and does not survive the restart. A
+//! client that reconnects gets a fresh snapshot.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+/// Drain deadline when --shutdown-timeout isn't given
+pub const DEFAULT_SHUTDOWN_SECS: u64 = 10;
+
+static STOPPING: AtomicBool = AtomicBool::new(false);
+static IN_FLIGHT: Mutex<usize> = Mutex::new(0);
+static IDLE: Condvar = Condvar::new();
+
+/// Handle SIGTERM/SIGINT on a background thread. `wake` is called once to
+/// end the accept loop (`Listeners::waker`).
+pub fn install(wake: impl FnOnce() + Send + 'static, timeout: Duration) {
+    let mut signals = match Signals::new([SIGTERM, SIGINT]) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("[magnetic] signal handlers not installed: {}", e);
+            return;
+        }
+    };
+    thread::Builder::new()
+        .name("shutdown".into())
+        .spawn(move || {
+            let mut signals = signals.forever();
+            if signals.next().is_none() {
+                return;
+            }
+            eprintln!("[magnetic] shutting down (up to {}s)", timeout.as_secs());
+            STOPPING.store(true, Ordering::Release);
+            wake();
+            thread::spawn(move || {
+                thread::sleep(timeout);
+                eprintln!("[magnetic] shutdown deadline passed, exiting");
+                std::process::exit(1);
+            });
+            if signals.next().is_some() {
+                eprintln!("[magnetic] second signal, exiting");
+                std::process::exit(1);
+            }
+        })
+        .expect("Failed to spawn shutdown thread");
+}
+
+/// True once a shutdown signal has arrived
+pub fn is_stopping() -> bool {
+    STOPPING.load(Ordering::Acquire)
+}
+
+/// A request or action being handled; shutdown waits for it to drop.
+pub struct InFlight(());
+
+pub fn track() -> InFlight {
+    *IN_FLIGHT.lock().unwrap() += 1;
+    InFlight(())
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut n = IN_FLIGHT.lock().unwrap();
+        *n -= 1;
+        if *n == 0 {
+            IDLE.notify_all();
+        }
+    }
+}
+
+/// Wait for every InFlight to drop. The deadline watchdog exits the process
+/// if they don't.
+pub fn wait_idle() {
+    let mut n = IN_FLIGHT.lock().unwrap();
+    while *n > 0 {
+        n = IDLE.wait(n).unwrap();
+    }
+}
+
+/// Drain deadline from `--shutdown-timeout` (seconds), or DEFAULT_SHUTDOWN_SECS.
+pub fn timeout_arg(args: &[String]) -> Duration {
+    let secs = crate::find_arg(args, "--shutdown-timeout")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_SHUTDOWN_SECS);
+    Duration::from_secs(secs)
+}
@@ -0,0 +1,78 @@
+//! snapshot.rs — V8 startup snapshots of an evaluated bundle
+//!
+//! A snapshot is the isolate heap right after the bundle has run. An
+//! isolate booted from one skips parsing and executing the bundle, which is
+//! most of a platform app's cold start. `handle_deploy` writes one next to
+//! the app's bundle.js (snapshot.bin); `load_app` and isolate recreation
+//! (`v8_thread`) then boot from it.
+//!
+//! V8 only accepts a snapshot from its own build, so the file starts with a
+//! header line naming the V8 version and the bundle's hash. A file that
+//! doesn't match the running V8 and the bundle next to it is ignored, and
+//! the bundle is executed as before.
+
+use std::sync::Arc;
+
+/// JS source for an app's isolates, with the snapshot to boot them from
+pub struct Bundle {
+    pub source: String,
+    pub snapshot: Option<Vec<u8>>,
+    /// Run in each isolate once the bundle is loaded, snapshot or not
+    /// (`AssetManifest::script`); empty for nothing
+    pub assets: String,
+}
+
+impl Bundle {
+    /// A bundle without a snapshot: every isolate executes `source`
+    pub fn source(source: String, assets: String) -> Arc<Self> {
+        Arc::new(Bundle { source, snapshot: None, assets })
+    }
+
+    /// Read the snapshot at `path` if it was made from `source` by this V8.
+    pub fn with_snapshot_file(source: String, path: &str, assets: String) -> Arc<Self> {
+        let snapshot = std::fs::read(path).ok().and_then(|file| {
+            let body = file.strip_prefix(header(&source).as_bytes())?;
+            Some(body.to_vec())
+        });
+        Arc::new(Bundle { source, snapshot, assets })
+    }
+}
+
+/// Run `js_source` in a fresh isolate and serialize the resulting heap.
+pub fn create(js_source: &str) -> Result<Vec<u8>, String> {
+    crate::ensure_v8_initialized();
+    let mut isolate = v8::Isolate::snapshot_creator(None, None);
+    {
+        let scope = &mut v8::HandleScope::new(&mut isolate);
+        let context = v8::Context::new(scope, Default::default());
+        {
+            let scope = &mut v8::ContextScope::new(scope, context);
+            let code = v8::String::new(scope, js_source).ok_or("Bundle too large")?;
+            let script = v8::Script::compile(scope, code, None)
+                .ok_or("Failed to compile JS bundle")?;
+            script.run(scope).ok_or("JS bundle threw during execution")?;
+        }
+        scope.set_default_context(context);
+    }
+    let blob = isolate
+        .create_blob(v8::FunctionCodeHandling::Keep)
+        .ok_or("V8 could not create a snapshot")?;
+    Ok(blob.to_vec())
+}
+
+/// Snapshot `js_source` into `path`, with the header `Bundle` checks.
+pub fn write(path: &str, js_source: &str) -> Result<usize, String> {
+    let blob = create(js_source)?;
+    let mut file = header(js_source).into_bytes();
+    file.extend_from_slice(&blob);
+    std::fs::write(path, &file).map_err(|e| format!("Cannot write snapshot: {}", e))?;
+    Ok(blob.len())
+}
+
+fn header(js_source: &str) -> String {
+    format!(
+        "magnetic-snapshot v8={} bundle={}\n",
+        v8::V8::get_version(),
+        crate::content_hash(js_source.as_bytes())
+    )
+}
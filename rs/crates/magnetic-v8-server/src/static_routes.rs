@@ -0,0 +1,113 @@
+//! static_routes.rs — Directory index, fallback routes and pass-through
+//! prefixes for static files
+//!
+//! - Directory index: `/docs/` serves `docs/index.html` from the static dir
+//!   when that file exists (`--static-index`, default `index.html`; empty
+//!   turns it off).
+//! - Fallback routes: a path under a prefix that has no file of its own
+//!   serves one file, so a client-side router can take over.
+//!   `--fallback /app=app/index.html,/admin=admin/index.html`; the longest
+//!   matching prefix wins, and `/` covers every path. Requests for a missing
+//!   file fall back only if they accept HTML, so a missing script still 404s.
+//! - Pass-through: paths under `--passthrough /docs,/downloads` are always
+//!   static files, never rendered by V8.
+//!
+//! In `--platform` mode each app sets the same rules under `routes` in its
+//! magnetic.json config: `{"index": "...", "fallback": {...}, "passthrough": [...]}`.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_INDEX: &str = "index.html";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StaticRoutes {
+    /// File that answers for a directory; empty for none
+    #[serde(default = "default_index")]
+    pub index: String,
+    /// Path prefix → file in the static dir
+    #[serde(default)]
+    pub fallback: BTreeMap<String, String>,
+    /// Path prefixes served only from the static dir
+    #[serde(default)]
+    pub passthrough: Vec<String>,
+}
+
+fn default_index() -> String {
+    DEFAULT_INDEX.to_string()
+}
+
+impl Default for StaticRoutes {
+    fn default() -> Self {
+        StaticRoutes { index: default_index(), fallback: BTreeMap::new(), passthrough: Vec::new() }
+    }
+}
+
+/// Rules from `--static-index`, `--fallback` and `--passthrough`
+pub fn routes_arg(args: &[String]) -> StaticRoutes {
+    let list = |flag: &str| -> Vec<String> {
+        crate::find_arg(args, flag)
+            .map(|s| s.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect())
+            .unwrap_or_default()
+    };
+    StaticRoutes {
+        index: crate::find_arg(args, "--static-index").unwrap_or_else(default_index),
+        fallback: list("--fallback")
+            .iter()
+            .filter_map(|rule| rule.split_once('='))
+            .map(|(prefix, file)| (prefix.to_string(), file.to_string()))
+            .collect(),
+        passthrough: list("--passthrough"),
+    }
+}
+
+impl StaticRoutes {
+    /// Whether `path` is under a pass-through prefix
+    pub fn is_passthrough(&self, path: &str) -> bool {
+        self.passthrough.iter().any(|prefix| under(path, prefix))
+    }
+
+    /// The index file of `path` as a directory, relative to `dir`, if it
+    /// exists
+    pub fn index(&self, dir: &str, path: &str) -> Option<String> {
+        if self.index.is_empty() {
+            return None;
+        }
+        let rel = path.trim_matches('/');
+        let file = if rel.is_empty() { self.index.clone() } else { format!("{}/{}", rel, self.index) };
+        exists(dir, &file).then_some(file)
+    }
+
+    /// The fallback file of the longest prefix covering `path`, relative to
+    /// `dir`, if it exists
+    pub fn fallback(&self, dir: &str, path: &str) -> Option<String> {
+        let (_, file) = self
+            .fallback
+            .iter()
+            .filter(|(prefix, _)| under(path, prefix))
+            .max_by_key(|(prefix, _)| prefix.trim_end_matches('/').len())?;
+        let file = file.trim_start_matches('/');
+        exists(dir, file).then(|| file.to_string())
+    }
+}
+
+/// Whether a request for a missing file may get a fallback page instead
+pub fn accepts_html(req_headers: &HashMap<String, String>) -> bool {
+    req_headers.get("accept").is_some_and(|a| a.contains("text/html"))
+}
+
+/// Whether `path` is `prefix` or below it
+pub fn under(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+fn exists(dir: &str, rel: &str) -> bool {
+    !rel.contains("..") && Path::new(dir).join(rel).is_file()
+}
@@ -0,0 +1,173 @@
+//! stats.rs — Server statistics (`GET /api/stats`)
+//!
+//! A JSON summary for dashboards and `magnetic status`, not a metrics
+//! format: uptime, requests served, connected SSE/WebSocket clients, and
+//! per app its V8 queue depth and recent render times.
+//!
+//! ```json
+//! {"uptime_secs":3600,"requests":48210,
+//!  "clients":{"clients":12,"sessions":9},
+//!  "v8":{"isolates":2,"queue_depth":0,"render_ms":{"p50":1.8,"p95":6.4,"samples":1000},"timeouts":0}}
+//! ```
+//!
+//! In `--platform` mode `clients` is the total over every app, and `apps`
+//! holds `{"warm":..,"clients":..,"v8":..}` per app (`v8` is null for a
+//! static app). Requests are counted past the probes, per process: under
+//! `--processes` each process reports its own.
+//!
+//! - `queue_depth` is the number of requests sent to the app's isolates
+//!   that they haven't started on yet.
+//! - `render_ms` covers the last RENDER_SAMPLES render calls (render,
+//!   renderWithCSS, with or without data), measured on the V8 thread, so
+//!   time spent queued isn't included. It's null before the first render.
+//! - `timeouts` counts renders and actions terminated for running past the
+//!   time limit (quotas.rs).
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::conn::Conn;
+use crate::outbox::Client;
+
+pub const PATH: &str = "/api/stats";
+
+/// Render times kept per app
+const RENDER_SAMPLES: usize = 1000;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+/// Start the uptime clock
+pub fn start() {
+    STARTED.get_or_init(Instant::now);
+}
+
+/// Count a request
+pub fn request() {
+    REQUESTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Queue depth and render times of one app's isolates (isolates.rs)
+#[derive(Default)]
+pub struct V8Stats {
+    queued: AtomicUsize,
+    renders: Mutex<VecDeque<Duration>>,
+    /// Every render so far, and their total time in µs (metrics.rs)
+    render_count: AtomicU64,
+    render_us: AtomicU64,
+    /// Renders and actions terminated for running too long (quotas.rs)
+    timeouts: AtomicUsize,
+}
+
+impl V8Stats {
+    /// `n` requests were sent to the isolates
+    pub fn queued(&self, n: usize) {
+        self.queued.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// An isolate took a request off its queue
+    pub fn started(&self) {
+        let _ = self.queued.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
+    pub fn render_took(&self, took: Duration) {
+        let mut renders = self.renders.lock().unwrap();
+        if renders.len() >= RENDER_SAMPLES {
+            renders.pop_front();
+        }
+        renders.push_back(took);
+        self.render_count.fetch_add(1, Ordering::Relaxed);
+        self.render_us.fetch_add(took.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Renders so far and their total time
+    pub fn render_totals(&self) -> (u64, Duration) {
+        let us = self.render_us.load(Ordering::Relaxed);
+        (self.render_count.load(Ordering::Relaxed), Duration::from_micros(us))
+    }
+
+    /// Percentiles `ps` of the recent render times; None before the first
+    pub fn render_percentiles(&self, ps: &[usize]) -> Option<(Vec<Duration>, usize)> {
+        let mut renders: Vec<Duration> = self.renders.lock().unwrap().iter().copied().collect();
+        if renders.is_empty() {
+            return None;
+        }
+        renders.sort_unstable();
+        Some((ps.iter().map(|&p| percentile(&renders, p)).collect(), renders.len()))
+    }
+
+    /// A render or action ran past the time limit
+    pub fn timed_out(&self) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn timeouts(&self) -> usize {
+        self.timeouts.load(Ordering::Relaxed)
+    }
+
+    /// `{"isolates":..,"queue_depth":..,"render_ms":..,"timeouts":..}`
+    pub fn json(&self, isolates: usize) -> Value {
+        let render_ms = match self.render_percentiles(&[50, 95]) {
+            Some((p, samples)) => json!({ "p50": ms(p[0]), "p95": ms(p[1]), "samples": samples }),
+            None => Value::Null,
+        };
+        json!({
+            "isolates": isolates,
+            "queue_depth": self.queued.load(Ordering::Relaxed),
+            "render_ms": render_ms,
+            "timeouts": self.timeouts(),
+        })
+    }
+}
+
+/// Nearest-rank percentile of sorted, non-empty samples
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// Milliseconds to two decimals
+pub fn ms(d: Duration) -> f64 {
+    (d.as_secs_f64() * 100_000.0).round() / 100.0
+}
+
+/// `{"clients":..,"sessions":..}` for a client registry. Session ids are
+/// left out: they're credentials.
+pub fn clients_json(clients: &HashMap<String, Vec<Client>>) -> Value {
+    let sessions = clients.values().filter(|list| !list.is_empty()).count();
+    json!({ "clients": clients.values().map(Vec::len).sum::<usize>(), "sessions": sessions })
+}
+
+/// The fields every mode reports, to add to
+pub fn base() -> serde_json::Map<String, Value> {
+    let mut stats = serde_json::Map::new();
+    stats.insert("uptime_secs".into(), uptime_secs().into());
+    stats.insert("requests".into(), requests().into());
+    stats
+}
+
+pub fn uptime_secs() -> u64 {
+    STARTED.get().map(|at| at.elapsed().as_secs()).unwrap_or(0)
+}
+
+/// Requests counted so far
+pub fn requests() -> u64 {
+    REQUESTS.load(Ordering::Relaxed)
+}
+
+/// 200 with the stats, never cached
+pub fn write(stream: &mut Conn, stats: serde_json::Map<String, Value>, extra_headers: &HashMap<String, String>) -> std::io::Result<()> {
+    let body = Value::Object(stats).to_string();
+    let resp = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nCache-Control: no-store\r\n\
+        Content-Length: {}\r\n{}\r\n",
+        body.len(), crate::format_extra_headers(extra_headers)
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(body.as_bytes())
+}
@@ -0,0 +1,409 @@
+//! trace.rs — Request tracing, exported over OTLP
+//!
+//! `--otlp-endpoint http://collector:4318` sends spans to an OpenTelemetry
+//! collector as OTLP/HTTP JSON (`POST <endpoint>/v1/traces`), in batches
+//! from a background thread. Without it nothing is recorded.
+//!
+//! Each request gets a root span. Its children cover the middleware, the V8
+//! calls it makes (render, reduce, ...), data-layer fetches and forwarded
+//! actions, and SSE broadcasts. A request that carries a W3C `traceparent`
+//! continues that trace. Every span of a request carries its
+//! `magnetic.request_id`: the request's `X-Request-Id`, else a new one,
+//! which goes back in the response's `X-Request-Id`. Work outside a request
+//! (data polling, broadcasts from an SSE data source) starts a trace of its
+//! own.
+//!
+//! `--otlp-service` names the service in the exported resource (default
+//! `magnetic`). Spans are dropped, not queued, once MAX_PENDING are waiting
+//! for the exporter.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value as Json};
+
+/// Spans waiting for the exporter
+const MAX_PENDING: usize = 4096;
+/// Spans per export request
+const BATCH: usize = 512;
+/// Longest a finished span waits to be exported
+const FLUSH_EVERY: Duration = Duration::from_secs(2);
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// OTLP span kinds
+const KIND_INTERNAL: u8 = 1;
+const KIND_SERVER: u8 = 2;
+
+static EXPORTER: OnceLock<SyncSender<Finished>> = OnceLock::new();
+
+thread_local! {
+    /// This thread's open spans, innermost last
+    static OPEN: RefCell<Vec<Open>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Otlp {
+    /// Collector base URL; tracing is off when None
+    pub endpoint: Option<String>,
+    pub service: Option<String>,
+}
+
+/// Settings from `--otlp-endpoint` and `--otlp-service`
+pub fn otlp_arg(args: &[String]) -> Otlp {
+    Otlp {
+        endpoint: crate::find_arg(args, "--otlp-endpoint"),
+        service: crate::find_arg(args, "--otlp-service"),
+    }
+}
+
+/// Start the exporter, if an endpoint is set
+pub fn init(config: &Otlp) {
+    let Some(endpoint) = &config.endpoint else { return };
+    let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+    let service = config.service.clone().unwrap_or_else(|| "magnetic".into());
+    let (tx, rx) = mpsc::sync_channel(MAX_PENDING);
+    if EXPORTER.set(tx).is_err() {
+        return;
+    }
+    eprintln!("[magnetic] exporting traces to {}", url);
+    thread::Builder::new()
+        .name("otlp-export".into())
+        .spawn(move || export_loop(&url, &service, rx))
+        .expect("spawn otlp exporter");
+}
+
+pub fn enabled() -> bool {
+    EXPORTER.get().is_some()
+}
+
+/// An attribute value
+pub enum Value {
+    Str(String),
+    Int(i64),
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self { Value::Str(s.to_string()) }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self { Value::Str(s) }
+}
+
+impl From<u16> for Value {
+    fn from(n: u16) -> Self { Value::Int(n.into()) }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Self { Value::Int(n as i64) }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Self { Value::Int(n as i64) }
+}
+
+/// Where a span was opened, to continue its trace on another thread
+#[derive(Debug, Clone)]
+pub struct Parent {
+    trace_id: String,
+    span_id: String,
+    request_id: String,
+    at: Instant,
+}
+
+struct Open {
+    trace_id: String,
+    span_id: String,
+    parent_id: Option<String>,
+    request_id: String,
+    name: String,
+    kind: u8,
+    start: u64,
+    attrs: Vec<(&'static str, Value)>,
+    error: bool,
+    /// A parent from another thread (`enter`), not a span of its own
+    exported: bool,
+}
+
+struct Finished {
+    open: Open,
+    end: u64,
+}
+
+/// Ends its span when dropped. Spans nest: drop them in reverse order,
+/// on the thread that opened them.
+pub struct Span {
+    active: bool,
+    _thread: PhantomData<*const ()>,
+}
+
+impl Span {
+    fn push(open: Option<Open>) -> Span {
+        let active = open.is_some();
+        if let Some(open) = open {
+            OPEN.with(|s| s.borrow_mut().push(open));
+        }
+        Span { active, _thread: PhantomData }
+    }
+
+    /// The request id of this span's trace; None when tracing is off
+    pub fn request_id(&self) -> Option<String> {
+        if !self.active {
+            return None;
+        }
+        OPEN.with(|s| s.borrow().last().map(|o| o.request_id.clone()))
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        let Some(open) = OPEN.with(|s| s.borrow_mut().pop()) else { return };
+        if let (true, Some(tx)) = (open.exported, EXPORTER.get()) {
+            // A full queue drops the span rather than block the request
+            let _ = tx.try_send(Finished { open, end: now_nanos() });
+        }
+    }
+}
+
+/// The root span of an HTTP request, continuing the request's
+/// `traceparent` if it has one
+pub fn request(method: &str, path: &str, req_headers: &HashMap<String, String>) -> Span {
+    if !enabled() {
+        return Span::push(None);
+    }
+    let (trace_id, parent_id) = match req_headers.get("traceparent").and_then(|h| parse_traceparent(h)) {
+        Some((trace_id, span_id)) => (trace_id, Some(span_id)),
+        None => (random_hex(16), None),
+    };
+    let request_id = req_headers
+        .get("x-request-id")
+        .filter(|id| !id.is_empty() && id.len() <= 128)
+        .cloned()
+        .unwrap_or_else(|| random_hex(16));
+    let route = path.split('?').next().unwrap_or(path);
+    Span::push(Some(Open {
+        trace_id,
+        span_id: random_hex(8),
+        parent_id,
+        request_id,
+        name: format!("{} {}", method, route),
+        kind: KIND_SERVER,
+        start: now_nanos(),
+        attrs: vec![("http.request.method", method.into()), ("url.path", route.into())],
+        error: false,
+        exported: true,
+    }))
+}
+
+/// A span inside the current one, or the root of a new trace if this
+/// thread has none open
+pub fn span(name: &str) -> Span {
+    if !enabled() {
+        return Span::push(None);
+    }
+    let parent = current();
+    Span::push(Some(open(name, parent.as_ref())))
+}
+
+/// A span under `parent`, opened on another thread; none without one
+pub fn child_of(parent: Option<&Parent>, name: &str) -> Span {
+    match parent {
+        Some(parent) if enabled() => Span::push(Some(open(name, Some(parent)))),
+        _ => Span::push(None),
+    }
+}
+
+/// Carry `parent` over to this thread: spans opened while the guard lives
+/// are its children
+pub fn enter(parent: Option<Parent>) -> Span {
+    Span::push(parent.filter(|_| enabled()).map(|p| Open {
+        trace_id: p.trace_id,
+        span_id: p.span_id,
+        parent_id: None,
+        request_id: p.request_id,
+        name: String::new(),
+        kind: KIND_INTERNAL,
+        start: 0,
+        attrs: Vec::new(),
+        error: false,
+        exported: false,
+    }))
+}
+
+/// The innermost open span, to hand to another thread
+pub fn current() -> Option<Parent> {
+    if !enabled() {
+        return None;
+    }
+    OPEN.with(|s| {
+        s.borrow().last().map(|o| Parent {
+            trace_id: o.trace_id.clone(),
+            span_id: o.span_id.clone(),
+            request_id: o.request_id.clone(),
+            at: Instant::now(),
+        })
+    })
+}
+
+/// Set an attribute on the innermost open span
+pub fn attr(key: &'static str, value: impl Into<Value>) {
+    if !enabled() {
+        return;
+    }
+    OPEN.with(|s| {
+        if let Some(o) = s.borrow_mut().last_mut() {
+            o.attrs.push((key, value.into()));
+        }
+    });
+}
+
+/// Mark the innermost open span as failed, with `message`
+pub fn error(message: &str) {
+    if !enabled() {
+        return;
+    }
+    OPEN.with(|s| {
+        if let Some(o) = s.borrow_mut().last_mut() {
+            o.error = true;
+            o.attrs.push(("error.message", message.into()));
+        }
+    });
+}
+
+/// How long ago `parent` was taken, e.g. a V8 request's time in the queue
+pub fn since(parent: &Parent) -> Duration {
+    parent.at.elapsed()
+}
+
+fn open(name: &str, parent: Option<&Parent>) -> Open {
+    let (trace_id, parent_id, request_id) = match parent {
+        Some(p) => (p.trace_id.clone(), Some(p.span_id.clone()), p.request_id.clone()),
+        None => (random_hex(16), None, random_hex(16)),
+    };
+    Open {
+        trace_id,
+        span_id: random_hex(8),
+        parent_id,
+        request_id,
+        name: name.to_string(),
+        kind: KIND_INTERNAL,
+        start: now_nanos(),
+        attrs: Vec::new(),
+        error: false,
+        exported: true,
+    }
+}
+
+/// `00-<trace id>-<parent id>-<flags>` → (trace id, parent id)
+fn parse_traceparent(header: &str) -> Option<(String, String)> {
+    let mut parts = header.trim().split('-');
+    let (version, trace_id, span_id) = (parts.next()?, parts.next()?, parts.next()?);
+    let hex = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_hexdigit()) && s.bytes().any(|b| b != b'0');
+    (version == "00" && hex(trace_id, 32) && hex(span_id, 16))
+        .then(|| (trace_id.to_ascii_lowercase(), span_id.to_ascii_lowercase()))
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut id = crate::cookie::random_id();
+    id.truncate(bytes * 2);
+    id
+}
+
+fn now_nanos() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+// ── Exporter ────────────────────────────────────────────────────────
+
+fn export_loop(url: &str, service: &str, rx: Receiver<Finished>) {
+    let agent = ureq::AgentBuilder::new().timeout(EXPORT_TIMEOUT).build();
+    let mut batch = Vec::with_capacity(BATCH);
+    let mut failing = false;
+    loop {
+        let deadline = Instant::now() + FLUSH_EVERY;
+        let mut closed = false;
+        while batch.len() < BATCH {
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(span) => batch.push(span),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    closed = true;
+                    break;
+                }
+            }
+        }
+        if !batch.is_empty() {
+            let body = encode(service, &batch).to_string();
+            batch.clear();
+            match agent.post(url).set("Content-Type", "application/json").send_string(&body) {
+                Ok(_) if failing => {
+                    eprintln!("[magnetic] trace export recovered");
+                    failing = false;
+                }
+                Ok(_) => {}
+                // Logged once per outage, not once per batch
+                Err(e) if !failing => {
+                    eprintln!("[magnetic] trace export to {} failed: {}", url, e);
+                    failing = true;
+                }
+                Err(_) => {}
+            }
+        }
+        if closed {
+            return;
+        }
+    }
+}
+
+/// An OTLP/JSON ExportTraceServiceRequest
+fn encode(service: &str, batch: &[Finished]) -> Json {
+    let spans: Vec<Json> = batch
+        .iter()
+        .map(|f| {
+            let o = &f.open;
+            let mut attrs: Vec<Json> = o.attrs.iter().map(|(k, v)| attribute(k, v)).collect();
+            attrs.push(attribute("magnetic.request_id", &Value::Str(o.request_id.clone())));
+            let mut span = json!({
+                "traceId": o.trace_id,
+                "spanId": o.span_id,
+                "name": o.name,
+                "kind": o.kind,
+                "startTimeUnixNano": o.start.to_string(),
+                "endTimeUnixNano": f.end.to_string(),
+                "attributes": attrs,
+                // STATUS_CODE_ERROR / STATUS_CODE_UNSET
+                "status": { "code": if o.error { 2 } else { 0 } },
+            });
+            if let Some(parent) = &o.parent_id {
+                span["parentSpanId"] = json!(parent);
+            }
+            span
+        })
+        .collect();
+    json!({
+        "resourceSpans": [{
+            "resource": { "attributes": [attribute("service.name", &Value::Str(service.to_string()))] },
+            "scopeSpans": [{
+                "scope": { "name": "magnetic-v8-server", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+fn attribute(key: &str, value: &Value) -> Json {
+    let value = match value {
+        Value::Str(s) => json!({ "stringValue": s }),
+        // int64 is a string in OTLP/JSON
+        Value::Int(n) => json!({ "intValue": n.to_string() }),
+    };
+    json!({ "key": key, "value": value })
+}
@@ -0,0 +1,731 @@
+//! transform.rs — `transform` expressions for data sources
+//!
+//! A small jq-like language, applied to each fetched value (and each SSE/WS
+//! event) before it is stored, so only what the page uses reaches V8:
+//! - `.`, `.name`, `."odd-name"`, `.[0]`, `.[-1]`, `.["name"]`: paths;
+//! - `.[]`: every element of an array (or value of an object);
+//! - `.[2:10]`, `.[:5]`: array slices;
+//! - `a | b`: b applied to each result of a;
+//! - `{id, title: .name.en}`: a new object (`id` alone is `id: .id`);
+//! - `[ ... ]`: the results collected into an array;
+//! - `==`, `!=`, `<`, `<=`, `>`, `>=`, `and`, `or`, and literals (numbers,
+//!   strings, true, false, null);
+//! - `map(f)`, `select(cond)`, `limit(n)`, `length`, `first`, `last`,
+//!   `keys`, `not`.
+//!
+//! An expression yields any number of results, as in jq. One result is the
+//! value; none is null; several are collected into an array, so
+//! `.items[] | {id, title}` gives an array of small objects. An object
+//! field takes its expression's first result.
+//!
+//! Expressions are parsed when the config loads (a `Transform`), so one
+//! that doesn't parse fails the load and each value only pays for `eval`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Identity,
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Slice(Box<Expr>, Option<i64>, Option<i64>),
+    Iterate(Box<Expr>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Literal(Value),
+    Array(Option<Box<Expr>>),
+    Object(Vec<(String, Expr)>),
+    Compare(Box<Expr>, Op, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// An expression parsed once, at config load. Config fields hold it in
+/// place of its text, which is what they (de)serialize as.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Transform {
+    source: String,
+    expr: Expr,
+}
+
+impl TryFrom<String> for Transform {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, String> {
+        let expr = parse(&source).map_err(|e| format!("transform `{}`: {}", source, e))?;
+        Ok(Transform { source, expr })
+    }
+}
+
+impl From<Transform> for String {
+    fn from(transform: Transform) -> String {
+        transform.source
+    }
+}
+
+impl Transform {
+    /// Apply the expression to `input`.
+    pub fn apply(&self, input: &Value) -> Result<Value, String> {
+        let mut results = eval(&self.expr, input)?;
+        Ok(match results.len() {
+            0 => Value::Null,
+            1 => results.remove(0),
+            _ => Value::Array(results),
+        })
+    }
+}
+
+fn parse(source: &str) -> Result<Expr, String> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.pipe()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(tok) => Err(format!("unexpected {:?}", tok)),
+    }
+}
+
+// ── Tokens ──────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    Pipe,
+    Comma,
+    Colon,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Op(&'static str),
+    Ident(String),
+    Str(String),
+    Num(f64),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let single = match c {
+            '.' => Some(Token::Dot),
+            '|' => Some(Token::Pipe),
+            ',' => Some(Token::Comma),
+            ':' => Some(Token::Colon),
+            '(' => Some(Token::LParen),
+            ')' => Some(Token::RParen),
+            '[' => Some(Token::LBracket),
+            ']' => Some(Token::RBracket),
+            '{' => Some(Token::LBrace),
+            '}' => Some(Token::RBrace),
+            _ => None,
+        };
+        if let Some(tok) = single {
+            tokens.push(tok);
+            i += 1;
+            continue;
+        }
+        match (c, next) {
+            _ if c.is_whitespace() => i += 1,
+            ('=', Some('=')) | ('!', Some('=')) | ('<', Some('=')) | ('>', Some('=')) => {
+                tokens.push(Token::Op(match c {
+                    '=' => "==",
+                    '!' => "!=",
+                    '<' => "<=",
+                    _ => ">=",
+                }));
+                i += 2;
+            }
+            ('<', _) | ('>', _) => {
+                tokens.push(Token::Op(if c == '<' { "<" } else { ">" }));
+                i += 1;
+            }
+            ('"', _) => {
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("unterminated string".into()),
+                        Some('"') => break,
+                        Some('\\') => {
+                            i += 1;
+                            match chars.get(i) {
+                                Some('n') => s.push('\n'),
+                                Some('t') => s.push('\t'),
+                                Some(&other) => s.push(other),
+                                None => return Err("unterminated string".into()),
+                            }
+                        }
+                        Some(&other) => s.push(other),
+                    }
+                    i += 1;
+                }
+                tokens.push(Token::Str(s));
+                i += 1;
+            }
+            _ if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                tokens.push(Token::Num(text.parse().map_err(|_| format!("bad number {}", text))?));
+            }
+            _ if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            _ => return Err(format!("unexpected '{}' at {}", c, i)),
+        }
+    }
+    Ok(tokens)
+}
+
+// ── Parser ──────────────────────────────────────────────────────────
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn eat(&mut self, tok: &Token) -> bool {
+        if self.peek() == Some(tok) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, tok: Token) -> Result<(), String> {
+        if self.eat(&tok) {
+            Ok(())
+        } else {
+            Err(format!("expected {:?}, found {:?}", tok, self.peek()))
+        }
+    }
+
+    fn pipe(&mut self) -> Result<Expr, String> {
+        let mut left = self.or()?;
+        while self.eat(&Token::Pipe) {
+            left = Expr::Pipe(Box::new(left), Box::new(self.or()?));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.eat(&Token::Ident("or".into())) {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.compare()?;
+        while self.eat(&Token::Ident("and".into())) {
+            left = Expr::And(Box::new(left), Box::new(self.compare()?));
+        }
+        Ok(left)
+    }
+
+    fn compare(&mut self) -> Result<Expr, String> {
+        let left = self.postfix()?;
+        let op = match self.peek() {
+            Some(Token::Op(op)) => match *op {
+                "==" => Op::Eq,
+                "!=" => Op::Ne,
+                "<" => Op::Lt,
+                "<=" => Op::Le,
+                ">" => Op::Gt,
+                _ => Op::Ge,
+            },
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        Ok(Expr::Compare(Box::new(left), op, Box::new(self.postfix()?)))
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat(&Token::Dot) {
+                expr = match self.next() {
+                    Some(Token::Ident(name)) | Some(Token::Str(name)) => Expr::Field(Box::new(expr), name),
+                    Some(Token::LBracket) => self.bracket(expr)?,
+                    other => return Err(format!("expected a field name after '.', found {:?}", other)),
+                };
+            } else if self.eat(&Token::LBracket) {
+                expr = self.bracket(expr)?;
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// After `[`: `[]`, `[index]` or `[from:to]` on `base`
+    fn bracket(&mut self, base: Expr) -> Result<Expr, String> {
+        if self.eat(&Token::RBracket) {
+            return Ok(Expr::Iterate(Box::new(base)));
+        }
+        let from = self.slice_bound();
+        if self.eat(&Token::Colon) {
+            let to = self.slice_bound();
+            self.expect(Token::RBracket)?;
+            return Ok(Expr::Slice(Box::new(base), from, to));
+        }
+        let index = match from {
+            Some(n) => Expr::Literal(Value::from(n)),
+            None => self.pipe()?,
+        };
+        self.expect(Token::RBracket)?;
+        Ok(Expr::Index(Box::new(base), Box::new(index)))
+    }
+
+    /// An integer literal, if next
+    fn slice_bound(&mut self) -> Option<i64> {
+        match self.peek() {
+            Some(Token::Num(n)) if n.fract() == 0.0 => {
+                let n = *n as i64;
+                self.pos += 1;
+                Some(n)
+            }
+            _ => None,
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Dot) => match self.peek().cloned() {
+                Some(Token::Ident(name)) | Some(Token::Str(name)) => {
+                    self.pos += 1;
+                    Ok(Expr::Field(Box::new(Expr::Identity), name))
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    self.bracket(Expr::Identity)
+                }
+                _ => Ok(Expr::Identity),
+            },
+            Some(Token::Num(n)) if n.fract() == 0.0 && n.abs() < 1e15 => Ok(Expr::Literal(Value::from(n as i64))),
+            Some(Token::Num(n)) => Ok(Expr::Literal(Number::from_f64(n).map(Value::Number).unwrap_or(Value::Null))),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::LParen) => {
+                let expr = self.pipe()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::LBracket) => {
+                if self.eat(&Token::RBracket) {
+                    return Ok(Expr::Array(None));
+                }
+                let expr = self.pipe()?;
+                self.expect(Token::RBracket)?;
+                Ok(Expr::Array(Some(Box::new(expr))))
+            }
+            Some(Token::LBrace) => self.object(),
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                _ => {
+                    let mut args = Vec::new();
+                    if self.eat(&Token::LParen) {
+                        args.push(self.pipe()?);
+                        self.expect(Token::RParen)?;
+                    }
+                    check_call(&name, args.len())?;
+                    Ok(Expr::Call(name, args))
+                }
+            },
+            Some(other) => Err(format!("unexpected {:?}", other)),
+            None => Err("unexpected end of expression".into()),
+        }
+    }
+
+    /// After `{`: `key`, `key: expr`, `"key": expr`, comma-separated
+    fn object(&mut self) -> Result<Expr, String> {
+        let mut fields = Vec::new();
+        if self.eat(&Token::RBrace) {
+            return Ok(Expr::Object(fields));
+        }
+        loop {
+            let key = match self.next() {
+                Some(Token::Ident(key)) | Some(Token::Str(key)) => key,
+                other => return Err(format!("expected an object key, found {:?}", other)),
+            };
+            let value = if self.eat(&Token::Colon) {
+                // A field value stops at `,` and `}`, so no bare pipe
+                self.or()?
+            } else {
+                Expr::Field(Box::new(Expr::Identity), key.clone())
+            };
+            fields.push((key, value));
+            if self.eat(&Token::RBrace) {
+                return Ok(Expr::Object(fields));
+            }
+            self.expect(Token::Comma)?;
+        }
+    }
+}
+
+fn check_call(name: &str, args: usize) -> Result<(), String> {
+    let want = match name {
+        "map" | "select" | "limit" => 1,
+        "length" | "first" | "last" | "keys" | "not" => 0,
+        _ => return Err(format!("unknown function {}", name)),
+    };
+    if args == want {
+        Ok(())
+    } else {
+        Err(format!("{} takes {} argument(s)", name, want))
+    }
+}
+
+// ── Evaluation ──────────────────────────────────────────────────────
+
+fn eval(expr: &Expr, input: &Value) -> Result<Vec<Value>, String> {
+    Ok(match expr {
+        Expr::Identity => vec![input.clone()],
+        Expr::Literal(v) => vec![v.clone()],
+        Expr::Field(base, name) => eval(base, input)?
+            .into_iter()
+            .map(|v| field(&v, name))
+            .collect::<Result<_, _>>()?,
+        Expr::Index(base, index) => {
+            let indexes = eval(index, input)?;
+            let mut out = Vec::new();
+            for v in eval(base, input)? {
+                for i in &indexes {
+                    out.push(match i {
+                        Value::String(name) => field(&v, name)?,
+                        Value::Number(n) => element(&v, n.as_f64().unwrap_or(0.0) as i64)?,
+                        other => return Err(format!("cannot index with {}", type_name(other))),
+                    });
+                }
+            }
+            out
+        }
+        Expr::Slice(base, from, to) => eval(base, input)?
+            .into_iter()
+            .map(|v| match v {
+                Value::Array(items) => {
+                    let (from, to) = (bound(*from, items.len(), 0), bound(*to, items.len(), items.len()));
+                    Ok(Value::Array(items.get(from..to.max(from)).unwrap_or_default().to_vec()))
+                }
+                Value::Null => Ok(Value::Null),
+                other => Err(format!("cannot slice {}", type_name(&other))),
+            })
+            .collect::<Result<_, _>>()?,
+        Expr::Iterate(base) => {
+            let mut out = Vec::new();
+            for v in eval(base, input)? {
+                match v {
+                    Value::Array(items) => out.extend(items),
+                    Value::Object(map) => out.extend(map.into_iter().map(|(_, v)| v)),
+                    Value::Null => {}
+                    other => return Err(format!("cannot iterate over {}", type_name(&other))),
+                }
+            }
+            out
+        }
+        Expr::Pipe(left, right) => {
+            let mut out = Vec::new();
+            for v in eval(left, input)? {
+                out.extend(eval(right, &v)?);
+            }
+            out
+        }
+        Expr::Array(inner) => match inner {
+            Some(inner) => vec![Value::Array(eval(inner, input)?)],
+            None => vec![Value::Array(Vec::new())],
+        },
+        Expr::Object(fields) => {
+            let mut map = Map::new();
+            for (key, value) in fields {
+                let v = eval(value, input)?.into_iter().next().unwrap_or(Value::Null);
+                map.insert(key.clone(), v);
+            }
+            vec![Value::Object(map)]
+        }
+        Expr::Compare(left, op, right) => {
+            let rights = eval(right, input)?;
+            let mut out = Vec::new();
+            for l in eval(left, input)? {
+                for r in &rights {
+                    out.push(Value::Bool(compare(&l, *op, r)));
+                }
+            }
+            out
+        }
+        Expr::And(left, right) => vec![Value::Bool(truthy(eval(left, input)?) && truthy(eval(right, input)?))],
+        Expr::Or(left, right) => vec![Value::Bool(truthy(eval(left, input)?) || truthy(eval(right, input)?))],
+        Expr::Call(name, args) => call(name, args, input)?,
+    })
+}
+
+fn call(name: &str, args: &[Expr], input: &Value) -> Result<Vec<Value>, String> {
+    Ok(match name {
+        "map" => match input {
+            Value::Array(items) => {
+                let mut out = Vec::new();
+                for item in items {
+                    out.extend(eval(&args[0], item)?);
+                }
+                vec![Value::Array(out)]
+            }
+            Value::Null => vec![Value::Null],
+            other => return Err(format!("cannot map over {}", type_name(other))),
+        },
+        "select" => {
+            if truthy(eval(&args[0], input)?) {
+                vec![input.clone()]
+            } else {
+                Vec::new()
+            }
+        }
+        "limit" => {
+            let n = eval(&args[0], input)?
+                .first()
+                .and_then(Value::as_f64)
+                .ok_or("limit needs a number")?
+                .max(0.0) as usize;
+            match input {
+                Value::Array(items) => vec![Value::Array(items.iter().take(n).cloned().collect())],
+                Value::Null => vec![Value::Null],
+                other => return Err(format!("cannot limit {}", type_name(other))),
+            }
+        }
+        "length" => vec![Value::from(match input {
+            Value::Array(items) => items.len(),
+            Value::Object(map) => map.len(),
+            Value::String(s) => s.chars().count(),
+            Value::Null => 0,
+            other => return Err(format!("{} has no length", type_name(other))),
+        })],
+        "first" => vec![element(input, 0)?],
+        "last" => vec![element(input, -1)?],
+        "keys" => match input {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                vec![Value::Array(keys.into_iter().map(|k| Value::String(k.clone())).collect())]
+            }
+            other => return Err(format!("{} has no keys", type_name(other))),
+        },
+        "not" => vec![Value::Bool(!truthy(vec![input.clone()]))],
+        _ => return Err(format!("unknown function {}", name)),
+    })
+}
+
+fn field(v: &Value, name: &str) -> Result<Value, String> {
+    match v {
+        Value::Object(map) => Ok(map.get(name).cloned().unwrap_or(Value::Null)),
+        Value::Null => Ok(Value::Null),
+        other => Err(format!("cannot get .{} of {}", name, type_name(other))),
+    }
+}
+
+/// Array element `i`, counting from the end when negative
+fn element(v: &Value, i: i64) -> Result<Value, String> {
+    match v {
+        Value::Array(items) => {
+            let i = if i < 0 { items.len() as i64 + i } else { i };
+            Ok(usize::try_from(i).ok().and_then(|i| items.get(i)).cloned().unwrap_or(Value::Null))
+        }
+        Value::Null => Ok(Value::Null),
+        other => Err(format!("cannot index {} with a number", type_name(other))),
+    }
+}
+
+/// A slice bound as an index into `len` items
+fn bound(b: Option<i64>, len: usize, default: usize) -> usize {
+    match b {
+        None => default,
+        Some(b) if b < 0 => len.saturating_sub(b.unsigned_abs() as usize),
+        Some(b) => (b as usize).min(len),
+    }
+}
+
+/// Whether the first result is neither false nor null
+fn truthy(results: Vec<Value>) -> bool {
+    !matches!(results.first(), None | Some(Value::Null) | Some(Value::Bool(false)))
+}
+
+fn compare(l: &Value, op: Op, r: &Value) -> bool {
+    use std::cmp::Ordering;
+    let ord = match (l, r) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => (l == r).then_some(Ordering::Equal),
+    };
+    match op {
+        Op::Eq => ord == Some(Ordering::Equal),
+        Op::Ne => ord != Some(Ordering::Equal),
+        Op::Lt => ord == Some(Ordering::Less),
+        Op::Le => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+        Op::Gt => ord == Some(Ordering::Greater),
+        Op::Ge => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+    }
+}
+
+fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(expr: &str, input: Value) -> Result<Value, String> {
+        Transform::try_from(expr.to_string())?.apply(&input)
+    }
+
+    fn ok(expr: &str, input: Value) -> Value {
+        run(expr, input).unwrap_or_else(|e| panic!("{}: {}", expr, e))
+    }
+
+    #[test]
+    fn paths() {
+        let input = json!({"a": {"b": 1}, "odd-key": 2, "list": [1, 2, 3]});
+        assert_eq!(ok(".", json!(5)), json!(5));
+        assert_eq!(ok(".a.b", input.clone()), json!(1));
+        assert_eq!(ok(".\"odd-key\"", input.clone()), json!(2));
+        assert_eq!(ok(".[\"a\"].b", input.clone()), json!(1));
+        assert_eq!(ok(".missing.deeper", input.clone()), Value::Null);
+        assert_eq!(ok(".list[]", input), json!([1, 2, 3]));
+        assert_eq!(ok(".[]", json!({"x": 1})), json!(1));
+    }
+
+    #[test]
+    fn index_and_slice() {
+        let list = json!([10, 20, 30, 40, 50]);
+        assert_eq!(ok(".[0]", list.clone()), json!(10));
+        assert_eq!(ok(".[-1]", list.clone()), json!(50));
+        assert_eq!(ok(".[9]", list.clone()), Value::Null);
+        assert_eq!(ok(".[1:3]", list.clone()), json!([20, 30]));
+        assert_eq!(ok(".[:2]", list.clone()), json!([10, 20]));
+        assert_eq!(ok(".[-2:]", list.clone()), json!([40, 50]));
+        assert_eq!(ok(".[3:1]", list.clone()), json!([]));
+        assert_eq!(ok(".[2:100]", list), json!([30, 40, 50]));
+    }
+
+    #[test]
+    fn pipes_and_multiple_results() {
+        let input = json!({"items": [{"id": 1, "n": {"en": "a"}}, {"id": 2, "n": {"en": "b"}}]});
+        assert_eq!(ok(".items[] | .id", input.clone()), json!([1, 2]));
+        assert_eq!(ok(".items[0] | .n | .en", input.clone()), json!("a"));
+        assert_eq!(ok("[.items[] | .id]", input), json!([1, 2]));
+        assert_eq!(ok(".[]", json!([])), Value::Null);
+    }
+
+    #[test]
+    fn object_construction() {
+        let input = json!({"id": 7, "name": {"en": "Seven"}, "tags": ["x", "y"]});
+        assert_eq!(ok("{id, title: .name.en}", input.clone()), json!({"id": 7, "title": "Seven"}));
+        assert_eq!(ok("{\"first tag\": .tags[]}", input.clone()), json!({"first tag": "x"}));
+        assert_eq!(ok("{missing}", input), json!({"missing": null}));
+        assert_eq!(ok("{}", json!(1)), json!({}));
+    }
+
+    #[test]
+    fn map_select_limit() {
+        let input = json!([{"id": 1, "n": 5}, {"id": 2, "n": 15}, {"id": 3, "n": 25}]);
+        assert_eq!(ok("map(.id)", input.clone()), json!([1, 2, 3]));
+        assert_eq!(ok("map(select(.n > 10)) | map(.id)", input.clone()), json!([2, 3]));
+        assert_eq!(ok(".[] | select(.n >= 5 and .n < 15) | .id", input.clone()), json!(1));
+        assert_eq!(ok("map(select(.id == 1 or .id == 3) | .id)", input.clone()), json!([1, 3]));
+        assert_eq!(ok("limit(2) | map(.id)", input.clone()), json!([1, 2]));
+        assert_eq!(ok("map(.id) | limit(0)", input), json!([]));
+        assert_eq!(ok("map(.)", Value::Null), Value::Null);
+    }
+
+    #[test]
+    fn builtins_and_literals() {
+        assert_eq!(ok("length", json!([1, 2])), json!(2));
+        assert_eq!(ok("length", json!("héllo")), json!(5));
+        assert_eq!(ok("first", json!([1, 2])), json!(1));
+        assert_eq!(ok("last", json!([1, 2])), json!(2));
+        assert_eq!(ok("keys", json!({"b": 1, "a": 2})), json!(["a", "b"]));
+        assert_eq!(ok(".a | not", json!({"a": null})), json!(true));
+        assert_eq!(ok("[]", Value::Null), json!([]));
+        assert_eq!(ok("[.[] | select(. == true)]", json!([true, false, null])), json!([true]));
+        assert_eq!(ok(".name != \"x\"", json!({"name": "y"})), json!(true));
+        assert_eq!(ok("1.5", Value::Null), json!(1.5));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (expr, want) in [
+            (".a |", "unexpected end of expression"),
+            ("frobnicate", "unknown function frobnicate"),
+            ("map", "map takes 1 argument(s)"),
+            ("length(.)", "length takes 0 argument(s)"),
+            (".a)", "unexpected"),
+            ("{1: .a}", "expected an object key"),
+            (".[1:2", "expected RBracket"),
+        ] {
+            let err = run(expr, Value::Null).unwrap_err();
+            assert!(err.starts_with(&format!("transform `{}`: ", expr)), "{}", err);
+            assert!(err.contains(want), "{}: {}", expr, err);
+        }
+    }
+
+    #[test]
+    fn eval_errors() {
+        assert_eq!(run(".a", json!(1)).unwrap_err(), "cannot get .a of a number");
+        assert_eq!(run(".[0]", json!({})).unwrap_err(), "cannot index an object with a number");
+        assert_eq!(run(".[]", json!("s")).unwrap_err(), "cannot iterate over a string");
+        assert_eq!(run(".[1:]", json!(true)).unwrap_err(), "cannot slice a boolean");
+        assert_eq!(run("map(.)", json!({})).unwrap_err(), "cannot map over an object");
+        assert_eq!(run("limit(\"x\")", json!([])).unwrap_err(), "limit needs a number");
+        assert_eq!(run("keys", json!([])).unwrap_err(), "an array has no keys");
+    }
+
+    #[test]
+    fn config_fields_keep_their_text() {
+        let transform: Transform = serde_json::from_value(json!(".items[:2] | map(.id)")).unwrap();
+        assert_eq!(serde_json::to_value(&transform).unwrap(), json!(".items[:2] | map(.id)"));
+        assert!(serde_json::from_value::<Transform>(json!(".items[")).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! upload.rs — multipart/form-data action POSTs
+//!
+//! A form with a file input posts to `/actions/<name>` as multipart. Each
+//! file is written to the uploads dir (`--uploads`, default `uploads/`;
+//! `{data_dir}/{app}/uploads` in `--platform` mode). It is stored as
+//! `<random id>-<sanitized filename>`, and the file's metadata goes into the
+//! reduce payload in place of its bytes:
+//!
+//! ```json
+//! { "title": "Q3 report",
+//!   "attachment": { "id": "9f…-report.pdf", "filename": "report.pdf",
+//!                   "type": "application/pdf", "size": 48213 } }
+//! ```
+//!
+//! Text fields become strings. A field sent more than once (`multiple`
+//! file inputs) becomes an array. An empty file input is skipped. Uploads
+//! are not served back; the app reads them by id from the uploads dir (e.g.
+//! in a forwarded action). The body is already capped by `--max-body-mb`
+//! (limits.rs).
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use serde_json::{json, Map, Value};
+
+use crate::conn::Conn;
+
+/// Longest sanitized filename kept in a stored file's name
+const MAX_NAME_LEN: usize = 100;
+
+pub enum Error {
+    /// 400: not a well-formed multipart body
+    Malformed(String),
+    /// 500: a file couldn't be written
+    Store(String),
+}
+
+struct Part<'a> {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    data: &'a [u8],
+}
+
+/// The boundary of a multipart/form-data request, or None for any other
+pub fn boundary(req_headers: &HashMap<String, String>) -> Option<String> {
+    let ct = req_headers.get("content-type")?;
+    let (mime, params) = ct.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params.split(';').find_map(|p| {
+        let (k, v) = p.trim().split_once('=')?;
+        k.eq_ignore_ascii_case("boundary").then(|| v.trim_matches('"').to_string())
+    })
+}
+
+/// The reduce payload for a multipart body, writing its files to `dir`
+pub fn payload(body: &[u8], boundary: &str, dir: &str) -> Result<String, Error> {
+    let mut fields = Map::new();
+    for part in parse(body, boundary).map_err(|e| Error::Malformed(e.to_string()))? {
+        let value = match &part.filename {
+            Some(filename) if filename.is_empty() && part.data.is_empty() => continue,
+            Some(filename) => {
+                let id = format!("{}-{}", crate::csrf::new_token(), sanitize(filename));
+                std::fs::create_dir_all(dir)
+                    .and_then(|_| std::fs::write(format!("{}/{}", dir, id), part.data))
+                    .map_err(|e| Error::Store(format!("Cannot store upload {}: {}", filename, e)))?;
+                json!({
+                    "id": id,
+                    "filename": filename,
+                    "type": part.content_type.as_deref().unwrap_or("application/octet-stream"),
+                    "size": part.data.len(),
+                })
+            }
+            None => Value::String(String::from_utf8_lossy(part.data).into_owned()),
+        };
+        match fields.get_mut(&part.name) {
+            Some(Value::Array(list)) => list.push(value),
+            Some(first) => *first = Value::Array(vec![first.take(), value]),
+            None => { fields.insert(part.name, value); }
+        }
+    }
+    Ok(Value::Object(fields).to_string())
+}
+
+fn parse<'a>(body: &'a [u8], boundary: &str) -> Result<Vec<Part<'a>>, &'static str> {
+    let delim = format!("--{}", boundary);
+    let next_delim = format!("\r\n--{}", boundary);
+    let start = find(body, delim.as_bytes()).ok_or("Missing multipart boundary")?;
+    let mut rest = &body[start + delim.len()..];
+    let mut parts = Vec::new();
+    // Each part: CRLF, headers, blank line, data, CRLF--boundary
+    while !rest.starts_with(b"--") {
+        rest = rest.strip_prefix(b"\r\n").ok_or("Malformed multipart boundary")?;
+        let head_len = find(rest, b"\r\n\r\n").ok_or("Unterminated multipart headers")?;
+        let head = String::from_utf8_lossy(&rest[..head_len]).into_owned();
+        rest = &rest[head_len + 4..];
+        let data_len = find(rest, next_delim.as_bytes()).ok_or("Unterminated multipart part")?;
+        let data = &rest[..data_len];
+        rest = &rest[data_len + next_delim.len()..];
+
+        let mut part = Part { name: String::new(), filename: None, content_type: None, data };
+        for line in head.split("\r\n") {
+            let Some((k, v)) = line.split_once(':') else { continue };
+            if k.trim().eq_ignore_ascii_case("content-disposition") {
+                for param in v.split(';').skip(1) {
+                    let Some((pk, pv)) = param.trim().split_once('=') else { continue };
+                    let pv = pv.trim_matches('"').to_string();
+                    match pk {
+                        "name" => part.name = pv,
+                        "filename" => part.filename = Some(pv),
+                        _ => {}
+                    }
+                }
+            } else if k.trim().eq_ignore_ascii_case("content-type") {
+                part.content_type = Some(v.trim().to_string());
+            }
+        }
+        if part.name.is_empty() {
+            return Err("Multipart part without a name");
+        }
+        parts.push(part);
+    }
+    Ok(parts)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// The filename's last path segment, with anything but [A-Za-z0-9._-] as `_`
+fn sanitize(filename: &str) -> String {
+    let base = filename.rsplit(['/', '\\']).next().unwrap_or("");
+    let clean: String = base
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') { c } else { '_' })
+        .take(MAX_NAME_LEN)
+        .collect();
+    let clean = clean.trim_start_matches('.');
+    if clean.is_empty() { "file".into() } else { clean.to_string() }
+}
+
+/// 400 or 500 `{"error":...}`
+pub fn write_error(stream: &mut Conn, error: &Error) -> std::io::Result<()> {
+    let (status, msg) = match error {
+        Error::Malformed(msg) => ("400 Bad Request", msg),
+        Error::Store(msg) => {
+            eprintln!("[magnetic] upload failed: {}", msg);
+            ("500 Internal Server Error", msg)
+        }
+    };
+    let body = json!({ "error": msg }).to_string();
+    let resp = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\n\
+        Content-Length: {}\r\n\r\n",
+        status, body.len()
+    );
+    stream.write_all(resp.as_bytes())?;
+    stream.write_all(body.as_bytes())
+}
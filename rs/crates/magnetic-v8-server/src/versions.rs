@@ -0,0 +1,125 @@
+//! versions.rs — Deploy history per app
+//!
+//! Each successful deploy's payload is kept as
+//! `<app_dir>/versions/<n>.json`, numbered from 1, with
+//! `versions/index.json` listing them (when, the bundle's hash, static or
+//! not) and which one is live. Only the last `--keep-versions` (default
+//! DEFAULT_KEEP, 0 for none) are kept; the live one is never pruned.
+//! Rolling back deploys a kept payload again, as it was, and makes it the
+//! live version without adding a new one.
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_KEEP: usize = 10;
+
+#[derive(Serialize, Deserialize, Default)]
+struct Index {
+    /// The live version
+    current: Option<u64>,
+    /// Oldest first
+    versions: Vec<Version>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Version {
+    version: u64,
+    /// Unix seconds
+    deployed_at: u64,
+    #[serde(rename = "static")]
+    is_static: bool,
+    /// First 16 hex digits of the bundle's SHA-256
+    bundle: Option<String>,
+    /// Payload size in bytes
+    size: usize,
+}
+
+/// --keep-versions: how many deploys to keep per app
+pub fn keep_arg(args: &[String]) -> usize {
+    crate::find_arg(args, "--keep-versions")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_KEEP)
+}
+
+/// Keep a deploy's payload as the app's next, live version, pruning the
+/// oldest past `keep`. Returns its number (None when `keep` is 0).
+pub fn record(app_dir: &str, body: &[u8], payload: &serde_json::Value, keep: usize) -> Result<Option<u64>, String> {
+    if keep == 0 {
+        return Ok(None);
+    }
+    let dir = format!("{}/versions", app_dir);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("create {}: {}", dir, e))?;
+    let mut index = read_index(app_dir);
+    let version = index.versions.last().map_or(1, |v| v.version + 1);
+    std::fs::write(payload_path(app_dir, version), body).map_err(|e| format!("write version {}: {}", version, e))?;
+    index.versions.push(Version {
+        version,
+        deployed_at: now(),
+        is_static: payload.get("static").and_then(|v| v.as_bool()).unwrap_or(false),
+        bundle: payload
+            .get("bundle")
+            .and_then(|v| v.as_str())
+            .map(|b| crate::content_hash(b.as_bytes())[..16].to_string()),
+        size: body.len(),
+    });
+    index.current = Some(version);
+    while index.versions.len() > keep {
+        let Some(i) = index.versions.iter().position(|v| Some(v.version) != index.current) else { break };
+        let old = index.versions.remove(i);
+        let _ = std::fs::remove_file(payload_path(app_dir, old.version));
+    }
+    write_index(app_dir, &index)?;
+    Ok(Some(version))
+}
+
+/// Mark `version` as the live one
+pub fn set_current(app_dir: &str, version: u64) -> Result<(), String> {
+    let mut index = read_index(app_dir);
+    index.current = Some(version);
+    write_index(app_dir, &index)
+}
+
+/// The live version, if one is kept
+pub fn current(app_dir: &str) -> Option<u64> {
+    read_index(app_dir).current
+}
+
+/// The kept payload of `version`
+pub fn payload(app_dir: &str, version: u64) -> Result<Vec<u8>, String> {
+    std::fs::read(payload_path(app_dir, version)).map_err(|_| format!("No version {}", version))
+}
+
+/// `{"current", "versions": [...]}`, newest first
+pub fn to_json(app_dir: &str) -> serde_json::Value {
+    let mut index = read_index(app_dir);
+    index.versions.reverse();
+    serde_json::to_value(index).unwrap_or_default()
+}
+
+fn read_index(app_dir: &str) -> Index {
+    std::fs::read_to_string(index_path(app_dir))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_index(app_dir: &str, index: &Index) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(index).map_err(|e| e.to_string())?;
+    let tmp = format!("{}.tmp", index_path(app_dir));
+    std::fs::write(&tmp, json).map_err(|e| format!("write versions index: {}", e))?;
+    std::fs::rename(&tmp, index_path(app_dir)).map_err(|e| format!("write versions index: {}", e))
+}
+
+fn index_path(app_dir: &str) -> String {
+    format!("{}/versions/index.json", app_dir)
+}
+
+fn payload_path(app_dir: &str, version: u64) -> String {
+    format!("{}/versions/{}.json", app_dir, version)
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
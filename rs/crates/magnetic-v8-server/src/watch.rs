@@ -0,0 +1,176 @@
+//! watch.rs — Dev hot-reload (`--watch`)
+//!
+//! A thread polls the `--bundle` file and the `--static` dir every
+//! POLL_INTERVAL. A change to the bundle re-creates every isolate from the
+//! new source (`V8Request::Reload`), and session state starts over. A change
+//! under the static dir re-reads style.css for SSR pages. After either, the
+//! server sends every connected client a `reload` event (an SSE event, or a
+//! `{"type":"reload"}` WebSocket message), and `magnetic.js` reloads the
+//! page.
+//!
+//! `--build "<command>"` runs a bundler (through `sh -c`) once before the
+//! server reads the bundle, and, with `--watch`, again whenever a source
+//! under `--watch-src` changes (default: pages, components, server, content,
+//! state.ts(x) and design.json, those that exist). The command writes the
+//! bundle, and the bundle watch above takes it from there, so one process
+//! replaces a bundler in watch mode plus a server. A failed build is
+//! logged, and the pages keep the last good bundle.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Sources watched for --build when --watch-src isn't given
+const DEFAULT_SRC: &[&str] = &["pages", "components", "server", "content", "state.ts", "state.tsx", "design.json"];
+
+pub enum Change {
+    /// The bundle's new source
+    Bundle(String),
+    Static,
+}
+
+/// True if `--watch` was given
+pub fn watch_arg(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--watch")
+}
+
+/// A bundler command and the sources it builds from
+pub struct Build {
+    command: String,
+    src: Vec<PathBuf>,
+}
+
+/// `--build "<command>"`, with `--watch-src a,b` (else DEFAULT_SRC)
+pub fn build_arg(args: &[String]) -> Option<Build> {
+    let command = crate::find_arg(args, "--build")?;
+    let src = match crate::find_arg(args, "--watch-src") {
+        Some(list) => list.split(',').map(str::trim).filter(|s| !s.is_empty()).map(PathBuf::from).collect(),
+        None => DEFAULT_SRC.iter().map(PathBuf::from).filter(|p| p.exists()).collect::<Vec<_>>(),
+    };
+    // Absolute, to compare with the bundle's path
+    let src = src.into_iter().map(|p| p.canonicalize().unwrap_or(p)).collect();
+    Some(Build { command, src })
+}
+
+impl Build {
+    /// Run the command to completion; false (and logged) if it failed
+    pub fn run(&self) -> bool {
+        eprintln!("[magnetic] build: {}", self.command);
+        let start = Instant::now();
+        match Command::new("sh").arg("-c").arg(&self.command).status() {
+            Ok(status) if status.success() => {
+                eprintln!("[magnetic] build done in {}ms", start.elapsed().as_millis());
+                true
+            }
+            Ok(status) => {
+                eprintln!("[magnetic] build failed ({}), keeping the last bundle", status);
+                false
+            }
+            Err(e) => {
+                eprintln!("[magnetic] cannot run build command: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Newest modification time and file count over every source
+    fn stamp(&self, skip: &Path) -> (Option<SystemTime>, usize) {
+        self.src.iter().fold((None, 0), |(newest, count), path| {
+            let (n, c) = if path.is_dir() { dir_stamp(path, skip) } else { (modified(path), 1) };
+            (newest.max(n), count + c)
+        })
+    }
+
+    /// Rebuild from a background thread whenever a source changes. The
+    /// bundle's dir is skipped, so a build's own output doesn't trigger
+    /// another (just the bundle, when the sources are inside that dir).
+    pub fn spawn(self, bundle_path: &str) {
+        let bundle = Path::new(bundle_path).canonicalize().unwrap_or_else(|_| PathBuf::from(bundle_path));
+        let skip = match bundle.parent() {
+            Some(dir) if !self.src.iter().any(|s| s.starts_with(dir)) => dir.to_path_buf(),
+            _ => bundle,
+        };
+        eprintln!("[magnetic] Rebuilding on changes to {}", self.src.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", "));
+        thread::Builder::new()
+            .name("build".into())
+            .spawn(move || {
+                let mut seen = self.stamp(&skip);
+                loop {
+                    thread::sleep(POLL_INTERVAL);
+                    let now = self.stamp(&skip);
+                    if now == seen {
+                        continue;
+                    }
+                    // An editor saving several files: wait for them all
+                    thread::sleep(POLL_INTERVAL);
+                    seen = self.stamp(&skip);
+                    self.run();
+                }
+            })
+            .expect("Failed to spawn build thread");
+    }
+}
+
+/// Call `on_change` from a background thread whenever the bundle or a file
+/// under `static_dir` changes. `skip` is a dir under `static_dir` that the
+/// server writes to itself (the hashed asset copies).
+pub fn spawn<F>(bundle_path: String, static_dir: String, skip: String, on_change: F)
+where
+    F: Fn(Change) + Send + 'static,
+{
+    thread::Builder::new()
+        .name("watch".into())
+        .spawn(move || {
+            let mut bundle_seen = modified(Path::new(&bundle_path));
+            let mut static_seen = dir_stamp(Path::new(&static_dir), Path::new(&skip));
+            loop {
+                thread::sleep(POLL_INTERVAL);
+                let bundle_now = modified(Path::new(&bundle_path));
+                if bundle_now != bundle_seen {
+                    bundle_seen = bundle_now;
+                    // A bundler may still be writing: skip a half-written file
+                    match std::fs::read_to_string(&bundle_path) {
+                        Ok(source) if !source.is_empty() => on_change(Change::Bundle(source)),
+                        _ => bundle_seen = None,
+                    }
+                }
+                let static_now = dir_stamp(Path::new(&static_dir), Path::new(&skip));
+                if static_now != static_seen {
+                    static_seen = static_now;
+                    on_change(Change::Static);
+                }
+            }
+        })
+        .expect("Failed to spawn watch thread");
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Newest modification time and file count under `dir` (so a deleted file
+/// counts as a change too)
+fn dir_stamp(dir: &Path, skip: &Path) -> (Option<SystemTime>, usize) {
+    let mut newest = None;
+    let mut count = 0;
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&d) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.starts_with(skip) || entry.file_name() == "node_modules" {
+                continue;
+            }
+            if path.is_dir() {
+                stack.push(path);
+            } else {
+                count += 1;
+                newest = newest.max(modified(&path));
+            }
+        }
+    }
+    (newest, count)
+}
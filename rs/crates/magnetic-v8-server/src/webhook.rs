@@ -0,0 +1,68 @@
+//! webhook.rs — Inbound webhook data sources
+//!
+//! A `"type": "webhook"` source takes deliveries at
+//! `POST /apps/<name>/hooks/<source>`. Each delivery's body (JSON, or else a
+//! string) goes through the source's `transform` and into its value like an
+//! SSE event: appended to the last `buffer` deliveries, or replacing the
+//! value when `buffer` is 0. Then the app's sessions re-render.
+//!
+//! With a `secret`, a delivery must carry an HMAC-SHA256 of its body in
+//! `signature_header` (default `X-Hub-Signature-256`), as GitHub sends it:
+//! `sha256=<hex>` or just the hex. A `Stripe-Signature` header is read the
+//! way Stripe signs: `t=<unix>,v1=<hex>` over `<t>.<body>`, within
+//! STRIPE_TOLERANCE of now. Signatures are checked in constant time.
+
+use std::collections::HashMap;
+
+use ring::hmac;
+
+pub const DEFAULT_SIGNATURE_HEADER: &str = "x-hub-signature-256";
+/// How old a Stripe signature's timestamp may be
+const STRIPE_TOLERANCE: u64 = 300;
+
+/// Check a delivery's signature against `secret`.
+pub fn verify(secret: &str, header: &str, headers: &HashMap<String, String>, body: &[u8]) -> Result<(), String> {
+    let header = header.to_ascii_lowercase();
+    let value = headers.get(&header).ok_or_else(|| format!("missing {} header", header))?;
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    if header == "stripe-signature" {
+        let mut t = None;
+        let mut signatures = Vec::new();
+        for part in value.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", v)) => t = v.parse::<u64>().ok(),
+                Some(("v1", v)) => signatures.push(v),
+                _ => {}
+            }
+        }
+        let t = t.ok_or("signature without a timestamp")?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        if now.abs_diff(t) > STRIPE_TOLERANCE {
+            return Err("signature timestamp too old".into());
+        }
+        let mut signed = format!("{}.", t).into_bytes();
+        signed.extend_from_slice(body);
+        return signatures
+            .iter()
+            .filter_map(|s| hex(s))
+            .any(|sig| hmac::verify(&key, &signed, &sig).is_ok())
+            .then_some(())
+            .ok_or_else(|| "bad signature".into());
+    }
+    let sig = value.trim();
+    let sig = hex(sig.strip_prefix("sha256=").unwrap_or(sig)).ok_or("malformed signature")?;
+    hmac::verify(&key, body, &sig).map_err(|_| "bad signature".into())
+}
+
+fn hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,230 @@
+//! ws.rs — WebSocket transport, an alternative to SSE
+//!
+//! `GET /ws` upgrades to a WebSocket for clients where EventSource is
+//! blocked or unavailable (some mobile webviews and corporate proxies).
+//! Sessions work as for SSE: the `magnetic_sid` cookie picks the session
+//! (the 101 response sets one when missing) and the socket joins the
+//! session's client list, receiving the same snapshots. Messages are JSON
+//! text frames:
+//!
+//!   server → client  {"type":"snapshot","data":{"root":...}}
+//!   client → server  {"type":"action","action":"<name>","payload":{...}}
+//!
+//! An action is handled like POST /actions/<name>; its snapshot goes to
+//! every client of the session (navigate: only to this socket). A
+//! snapshot message starts with exactly `{"type":"snapshot","data":` so a
+//! client can slice out the same bytes a POST response carries.
+//!
+//! Each socket has a reader thread, and a writer thread for what the
+//! session sends it (outbox.rs). Writes from both (pongs; snapshots,
+//! pings) go through one mutex, so frames never interleave.
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::conn::Conn;
+use crate::SSE_KEEPALIVE_SECS;
+
+/// Largest message accepted from a client
+const MAX_MESSAGE: usize = 1 << 20;
+
+/// A client that answers none of our pings (sent every SSE_KEEPALIVE_SECS)
+/// for this long is gone
+const READ_IDLE: Duration = Duration::from_secs(SSE_KEEPALIVE_SECS * 3);
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// True for a `Connection: Upgrade` / `Upgrade: websocket` request
+pub fn is_upgrade(headers: &HashMap<String, String>) -> bool {
+    headers.get("upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+}
+
+/// Answer the upgrade request with 101 (plus `headers`, e.g. Set-Cookie),
+/// or 400 if it isn't a valid WebSocket handshake. Ok(false) after a 400.
+pub fn accept(stream: &mut Conn, req_headers: &HashMap<String, String>, headers: &str) -> io::Result<bool> {
+    let key = match req_headers.get("sec-websocket-key") {
+        Some(key) if is_upgrade(req_headers) => key,
+        _ => {
+            stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+            return Ok(false);
+        }
+    };
+    let resp = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+        Sec-WebSocket-Accept: {}\r\n{}\r\n",
+        tungstenite::handshake::derive_accept_key(key.trim().as_bytes()), headers
+    );
+    stream.write_all(resp.as_bytes())?;
+    Ok(true)
+}
+
+/// Split an upgraded connection into its write half (for the session
+/// registry) and read half (for the socket's reader thread).
+pub fn split(stream: Conn) -> io::Result<(WsSender, WsReader)> {
+    stream.set_read_timeout(Some(READ_IDLE))?;
+    let reader = WsReader { conn: stream.try_clone()?, message: Vec::new(), binary: false };
+    Ok((WsSender { conn: Arc::new(Mutex::new(stream)) }, reader))
+}
+
+/// Write half of a WebSocket; clones share the connection.
+#[derive(Clone)]
+pub struct WsSender {
+    conn: Arc<Mutex<Conn>>,
+}
+
+impl WsSender {
+    /// Send `{"type":<kind>,"data":<data>}`; `data` must be JSON.
+    pub fn send(&self, kind: &str, data: &[u8]) -> io::Result<()> {
+        let mut msg = Vec::with_capacity(data.len() + kind.len() + 20);
+        msg.extend_from_slice(b"{\"type\":\"");
+        msg.extend_from_slice(kind.as_bytes());
+        msg.extend_from_slice(b"\",\"data\":");
+        msg.extend_from_slice(data);
+        msg.push(b'}');
+        self.write_frame(OP_TEXT, &msg)
+    }
+
+    pub fn ping(&self) -> io::Result<()> {
+        self.write_frame(OP_PING, b"")
+    }
+
+    /// Send a close frame and shut the socket, which also ends the reader.
+    pub fn close(&self) {
+        let _ = self.write_frame(OP_CLOSE, b"");
+        let _ = self.conn.lock().unwrap().shutdown(Shutdown::Both);
+    }
+
+    /// Like `close`, with a status code (RFC 6455 §7.4.1), e.g. 1012 when
+    /// the server restarts.
+    pub fn close_with(&self, code: u16) {
+        let _ = self.write_frame(OP_CLOSE, &code.to_be_bytes());
+        let _ = self.conn.lock().unwrap().shutdown(Shutdown::Both);
+    }
+
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.conn.lock().unwrap().set_write_timeout(dur)
+    }
+
+    fn write_frame(&self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(payload.len() + 10);
+        frame.push(0x80 | opcode); // FIN, server frames are unmasked
+        match payload.len() {
+            n if n < 126 => frame.push(n as u8),
+            n if n <= 0xFFFF => {
+                frame.push(126);
+                frame.extend_from_slice(&(n as u16).to_be_bytes());
+            }
+            n => {
+                frame.push(127);
+                frame.extend_from_slice(&(n as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+        let mut conn = self.conn.lock().unwrap();
+        conn.write_all(&frame)?;
+        conn.flush()
+    }
+}
+
+/// Read half of a WebSocket
+pub struct WsReader {
+    conn: Conn,
+    /// Text message being reassembled from fragments
+    message: Vec<u8>,
+    /// The message being received is binary (skipped)
+    binary: bool,
+}
+
+impl WsReader {
+    /// Next action from the client as (action, payload JSON). Ok(None) once
+    /// the client closes. Pings are answered through `sender`; other
+    /// messages are ignored.
+    pub fn next_action(&mut self, sender: &WsSender) -> io::Result<Option<(String, String)>> {
+        loop {
+            let (fin, opcode, payload) = self.read_frame()?;
+            match opcode {
+                OP_PING => sender.write_frame(OP_PONG, &payload)?,
+                OP_PONG => {}
+                OP_CLOSE => {
+                    let _ = sender.write_frame(OP_CLOSE, &payload[..payload.len().min(2)]);
+                    return Ok(None);
+                }
+                OP_TEXT | OP_BINARY | OP_CONTINUATION => {
+                    if opcode != OP_CONTINUATION {
+                        self.message.clear();
+                        self.binary = opcode == OP_BINARY;
+                    }
+                    if self.binary {
+                        continue;
+                    }
+                    self.message.extend_from_slice(&payload);
+                    if self.message.len() > MAX_MESSAGE {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, "message too large"));
+                    }
+                    if !fin {
+                        continue;
+                    }
+                    let msg = std::mem::take(&mut self.message);
+                    if let Some(action) = parse_action(&msg) {
+                        return Ok(Some(action));
+                    }
+                }
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "bad opcode")),
+            }
+        }
+    }
+
+    fn read_frame(&mut self) -> io::Result<(bool, u8, Vec<u8>)> {
+        let mut head = [0u8; 2];
+        self.conn.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        if head[1] & 0x80 == 0 {
+            // RFC 6455 §5.1: client frames must be masked
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unmasked client frame"));
+        }
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut b = [0u8; 2];
+                self.conn.read_exact(&mut b)?;
+                u16::from_be_bytes(b) as u64
+            }
+            127 => {
+                let mut b = [0u8; 8];
+                self.conn.read_exact(&mut b)?;
+                u64::from_be_bytes(b)
+            }
+            n => n as u64,
+        };
+        if len > MAX_MESSAGE as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+        }
+        let mut mask = [0u8; 4];
+        self.conn.read_exact(&mut mask)?;
+        let mut payload = vec![0u8; len as usize];
+        self.conn.read_exact(&mut payload)?;
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= mask[i % 4];
+        }
+        Ok((fin, opcode, payload))
+    }
+}
+
+/// (action, payload JSON) from `{"type":"action","action":...,"payload":...}`
+fn parse_action(msg: &[u8]) -> Option<(String, String)> {
+    let val: serde_json::Value = serde_json::from_slice(msg).ok()?;
+    if val.get("type")?.as_str()? != "action" {
+        return None;
+    }
+    let action = val.get("action")?.as_str()?.to_string();
+    let payload = val.get("payload").map(|p| p.to_string()).unwrap_or_else(|| "{}".to_string());
+    Some((action, payload))
+}
@@ -0,0 +1,137 @@
+#![no_std]
+
+//! magnetic-wasm-abi — length-prefixed results for Magnetic WASM modules
+//!
+//! An export returning bytes returns one pointer to a frame:
+//!
+//!   [len: u32 little-endian][len bytes of data]
+//!
+//! so a host needs no second `*_len()` call, and a frame can't be paired
+//! with the length of a different result. The header is 4-byte aligned;
+//! JS reads it with `new DataView(memory.buffer).getUint32(ptr, true)`.
+//!
+//! Static buffers use `Framed<N>`; buffers carved out of grown memory
+//! reserve HEADER_LEN bytes in front and call `write_header`.
+
+/// Bytes before a frame's data.
+pub const HEADER_LEN: usize = 4;
+
+/// Fixed-capacity output buffer laid out as a frame: header, then data.
+#[repr(C, align(4))]
+pub struct Framed<const N: usize> {
+    header: [u8; HEADER_LEN],
+    data: [u8; N],
+}
+
+impl<const N: usize> Framed<N> {
+    /// Empty frame (length 0).
+    pub const fn new() -> Self {
+        Self { header: [0; HEADER_LEN], data: [0; N] }
+    }
+
+    /// Pointer to the frame (the header) — what an export returns.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.header.as_ptr()
+    }
+
+    /// Whole data area, for writing a result in place before `set_len`.
+    pub fn data_mut(&mut self) -> &mut [u8; N] {
+        &mut self.data
+    }
+
+    /// Pointer to the data area (for exports that predate frames).
+    pub fn data_ptr(&self) -> *const u8 {
+        self.data.as_ptr()
+    }
+
+    /// Record the result length in the header, clamped to N.
+    pub fn set_len(&mut self, len: usize) {
+        write_header(&mut self.header, len.min(N));
+    }
+
+    /// Copy `src` in as the result. False (length 0) if it doesn't fit.
+    pub fn write(&mut self, src: &[u8]) -> bool {
+        if src.len() > N {
+            self.set_len(0);
+            return false;
+        }
+        self.data[..src.len()].copy_from_slice(src);
+        self.set_len(src.len());
+        true
+    }
+
+    /// Result length from the header.
+    pub fn len(&self) -> usize {
+        u32::from_le_bytes(self.header) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The result bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.data[..self.len()]
+    }
+}
+
+impl<const N: usize> Default for Framed<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A zero-length frame, for exports with nothing to return.
+pub static EMPTY: Framed<0> = Framed::new();
+
+/// Write a frame header for `len` data bytes into the first HEADER_LEN
+/// bytes of `frame`. Returns false if `frame` is shorter than that.
+pub fn write_header(frame: &mut [u8], len: usize) -> bool {
+    let Some(header) = frame.get_mut(..HEADER_LEN) else {
+        return false;
+    };
+    header.copy_from_slice(&(len as u32).to_le_bytes());
+    true
+}
+
+/// Data of the frame at `ptr` (what a host does after calling an export).
+///
+/// # Safety
+/// `ptr` must point to a frame written by this crate that stays alive and
+/// unmodified for `'a`.
+pub unsafe fn read<'a>(ptr: *const u8) -> &'a [u8] {
+    let mut header = [0u8; HEADER_LEN];
+    core::ptr::copy_nonoverlapping(ptr, header.as_mut_ptr(), HEADER_LEN);
+    let len = u32::from_le_bytes(header) as usize;
+    core::slice::from_raw_parts(ptr.add(HEADER_LEN), len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_layout() {
+        let mut f = Framed::<8>::new();
+        assert!(f.is_empty());
+        assert!(f.write(b"abc"));
+        assert_eq!(f.data_ptr(), unsafe { f.as_ptr().add(HEADER_LEN) });
+        assert_eq!(f.as_ptr() as usize % 4, 0);
+        assert_eq!(unsafe { read(f.as_ptr()) }, b"abc");
+
+        assert!(!f.write(b"too long for it"));
+        assert_eq!(f.len(), 0);
+        f.data_mut()[..2].copy_from_slice(b"hi");
+        f.set_len(2);
+        assert_eq!(f.bytes(), b"hi");
+    }
+
+    #[test]
+    fn header_in_slice() {
+        let mut region = [0u8; 9];
+        region[HEADER_LEN..].copy_from_slice(b"hello");
+        assert!(write_header(&mut region, 5));
+        assert_eq!(unsafe { read(region.as_ptr()) }, b"hello");
+        assert!(!write_header(&mut region[..3], 0));
+    }
+}